@@ -212,7 +212,7 @@ impl Configurer {
             configs.retain(|c| {
                 self.versions
                     .get(&c.group_name)
-                    .map_or(true, |v| c.hash != *v)
+                    .is_none_or(|v| c.hash != *v)
             });
         }
 
@@ -349,7 +349,6 @@ async fn wrap_long_running_future<F: Future>(
 async fn ping(ctx: &Context, config_list: &[ConfigWithMeta]) -> bool {
     let futures = config_list
         .iter()
-        .cloned()
         .map(|item| ctx.request_to(item.addr, Ping::default()).all().resolve())
         .collect::<Vec<_>>();
 
@@ -360,7 +359,7 @@ async fn ping(ctx: &Context, config_list: &[ConfigWithMeta]) -> bool {
         .flatten()
         .filter_map(|result| match result {
             Ok(()) | Err(RequestError::Ignored) => None,
-            Err(RequestError::Failed) => Some(String::from("some group is closed")),
+            Err(err) => Some(format!("some group is unavailable: {err}")),
         })
         // TODO: include actor keys in the error message.
         .inspect(|reason| error!(%reason, "ping failed"));
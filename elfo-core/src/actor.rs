@@ -1,21 +1,38 @@
-use std::{fmt, mem, sync::Arc};
+use std::{
+    any::Any,
+    fmt, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use futures_intrusive::sync::ManualResetEvent;
-use metrics::{decrement_gauge, increment_counter, increment_gauge};
-use parking_lot::RwLock;
+use metrics::{decrement_gauge, increment_counter, increment_gauge, Key, Label};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 use tracing::{error, info, warn};
 
+use elfo_utils::likely;
+
 use crate::{
     envelope::Envelope,
     errors::{SendError, TrySendError},
     group::{RestartPolicy, TerminationPolicy},
+    handling::HandlingTimeout,
+    hedging::RequestLatencies,
+    journal::{self, EventKind},
     mailbox::{Mailbox, RecvResult},
+    message::Message,
     messages::{ActorStatusReport, Terminate},
     msg,
     request_table::RequestTable,
     scope,
+    shutdown::ShutdownToken,
     subscription::SubscriptionManager,
+    task::AttachedTasks,
+    tracing::MessageSpan,
     Addr,
 };
 
@@ -43,6 +60,7 @@ impl ActorStatus {
     pub(crate) const FAILED: ActorStatus = ActorStatus::new(ActorStatusKind::Failed);
     pub const INITIALIZING: ActorStatus = ActorStatus::new(ActorStatusKind::Initializing);
     pub const NORMAL: ActorStatus = ActorStatus::new(ActorStatusKind::Normal);
+    pub(crate) const OVERLOADED: ActorStatus = ActorStatus::new(ActorStatusKind::Overloaded);
     pub(crate) const TERMINATED: ActorStatus = ActorStatus::new(ActorStatusKind::Terminated);
     pub const TERMINATING: ActorStatus = ActorStatus::new(ActorStatusKind::Terminating);
 
@@ -101,6 +119,8 @@ pub enum ActorStatusKind {
     Terminating,
     Terminated,
     Alarming,
+    /// Set by the system if the actor doesn't keep up with incoming messages.
+    Overloaded,
     Failed,
 }
 
@@ -112,6 +132,7 @@ impl ActorStatusKind {
             ActorStatusKind::Terminating => "Terminating",
             ActorStatusKind::Terminated => "Terminated",
             ActorStatusKind::Alarming => "Alarming",
+            ActorStatusKind::Overloaded => "Overloaded",
             ActorStatusKind::Failed => "Failed",
         }
     }
@@ -124,15 +145,26 @@ pub(crate) struct Actor {
     termination_policy: TerminationPolicy,
     mailbox: Mailbox,
     request_table: RequestTable,
+    request_latencies: RequestLatencies,
+    attached_tasks: AttachedTasks,
+    message_span: MessageSpan,
+    handling: HandlingTimeout,
     control: RwLock<ControlBlock>,
+    /// Whether low-priority messages are shed, set by overload detection.
+    is_shedding: AtomicBool,
     finished: ManualResetEvent, // TODO: remove in favor of `status_subscription`?
     status_subscription: Arc<SubscriptionManager>,
+    /// The state passed to the next incarnation, see `Context::set_snapshot()`.
+    snapshot: Mutex<Option<Box<dyn Any + Send>>>,
+    shutdown: ShutdownToken,
 }
 
 struct ControlBlock {
     status: ActorStatus,
     /// If `None`, a group's policy will be used.
     restart_policy: Option<RestartPolicy>,
+    /// Set by `RollingRestart`, overrides the restart policy.
+    is_restart_requested: bool,
 }
 
 impl Actor {
@@ -147,15 +179,27 @@ impl Actor {
             termination_policy,
             mailbox: Mailbox::new(),
             request_table: RequestTable::new(addr),
+            request_latencies: RequestLatencies::default(),
+            attached_tasks: AttachedTasks::default(),
+            message_span: MessageSpan::default(),
+            handling: HandlingTimeout::default(),
             control: RwLock::new(ControlBlock {
                 status: ActorStatus::INITIALIZING,
                 restart_policy: None,
+                is_restart_requested: false,
             }),
+            is_shedding: AtomicBool::new(false),
             finished: ManualResetEvent::new(false),
             status_subscription,
+            snapshot: Mutex::new(None),
+            shutdown: ShutdownToken::default(),
         }
     }
 
+    pub(crate) fn meta(&self) -> &Arc<ActorMeta> {
+        &self.meta
+    }
+
     pub(crate) fn on_start(&self) {
         increment_gauge!("elfo_active_actors", 1.,
             "status" => ActorStatusKind::Initializing.as_str());
@@ -166,13 +210,19 @@ impl Actor {
     }
 
     pub(crate) fn try_send(&self, envelope: Envelope) -> Result<(), TrySendError<Envelope>> {
+        if self.should_shed(&envelope) {
+            return Ok(());
+        }
+
         msg!(match &envelope {
             Terminate { closing } => {
+                self.shutdown.trigger();
+
                 if *closing || self.termination_policy.close_mailbox {
                     if self.close() {
                         return Ok(());
                     } else {
-                        return Err(TrySendError::Closed(envelope));
+                        return Err(TrySendError::MailboxClosed(envelope));
                     }
                 }
             }
@@ -182,13 +232,19 @@ impl Actor {
     }
 
     pub(crate) async fn send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
+        if self.should_shed(&envelope) {
+            return Ok(());
+        }
+
         msg!(match &envelope {
             Terminate { closing } => {
+                self.shutdown.trigger();
+
                 if *closing || self.termination_policy.close_mailbox {
                     if self.close() {
                         return Ok(());
                     } else {
-                        return Err(SendError(envelope));
+                        return Err(SendError::MailboxClosed(envelope));
                     }
                 }
             }
@@ -197,6 +253,55 @@ impl Actor {
         self.mailbox.send(envelope).await
     }
 
+    pub(crate) async fn send_until(
+        &self,
+        envelope: Envelope,
+        deadline: Instant,
+    ) -> Result<(), TrySendError<Envelope>> {
+        if self.should_shed(&envelope) {
+            return Ok(());
+        }
+
+        msg!(match &envelope {
+            Terminate { closing } => {
+                self.shutdown.trigger();
+
+                if *closing || self.termination_policy.close_mailbox {
+                    if self.close() {
+                        return Ok(());
+                    } else {
+                        return Err(TrySendError::MailboxClosed(envelope));
+                    }
+                }
+            }
+        });
+
+        self.mailbox.send_until(envelope, deadline).await
+    }
+
+    /// Returns `true` if the envelope must be dropped instead of sending.
+    /// Dropped requests are rejected, because their tokens are dropped too.
+    fn should_shed(&self, envelope: &Envelope) -> bool {
+        if likely(!self.is_shedding.load(Ordering::Relaxed))
+            || !envelope.message().is_low_priority()
+        {
+            return false;
+        }
+
+        if let Some(recorder) = metrics::try_recorder() {
+            let mut labels = envelope.message().labels().to_vec();
+            labels.push(Label::new("recipient_group", self.meta.group.clone()));
+            let key = Key::from_parts("elfo_shed_messages_total", labels);
+            recorder.increment_counter(&key, 1);
+        }
+
+        true
+    }
+
+    pub(crate) fn set_shedding(&self, is_shedding: bool) {
+        self.is_shedding.store(is_shedding, Ordering::Relaxed);
+    }
+
     pub(crate) async fn recv(&self) -> RecvResult {
         self.mailbox.recv().await
     }
@@ -205,10 +310,43 @@ impl Actor {
         self.mailbox.try_recv()
     }
 
+    pub(crate) fn try_recv_many(
+        &self,
+        buf: &mut Vec<Envelope>,
+        limit: usize,
+    ) -> Option<RecvResult<usize>> {
+        self.mailbox.try_recv_many(buf, limit)
+    }
+
+    /// Returns the approximate fraction of the mailbox capacity in use.
+    pub(crate) fn mailbox_usage(&self) -> f64 {
+        self.mailbox.usage()
+    }
+
     pub(crate) fn request_table(&self) -> &RequestTable {
         &self.request_table
     }
 
+    pub(crate) fn request_latencies(&self) -> &RequestLatencies {
+        &self.request_latencies
+    }
+
+    pub(crate) fn attached_tasks(&self) -> &AttachedTasks {
+        &self.attached_tasks
+    }
+
+    pub(crate) fn message_span(&self) -> &MessageSpan {
+        &self.message_span
+    }
+
+    pub(crate) fn handling(&self) -> &HandlingTimeout {
+        &self.handling
+    }
+
+    pub(crate) fn shutdown_token(&self) -> &ShutdownToken {
+        &self.shutdown
+    }
+
     pub(crate) fn restart_policy(&self) -> Option<RestartPolicy> {
         self.control.read().restart_policy.clone()
     }
@@ -217,6 +355,25 @@ impl Actor {
         self.control.write().restart_policy = policy;
     }
 
+    /// Closes the mailbox and marks the actor to be restarted immediately once
+    /// it's terminated regardless of the restart policy.
+    pub(crate) fn request_restart(&self) -> bool {
+        self.control.write().is_restart_requested = true;
+        self.close()
+    }
+
+    pub(crate) fn is_restart_requested(&self) -> bool {
+        self.control.read().is_restart_requested
+    }
+
+    pub(crate) fn set_snapshot(&self, snapshot: Box<dyn Any + Send>) {
+        *self.snapshot.lock() = Some(snapshot);
+    }
+
+    pub(crate) fn take_snapshot(&self) -> Option<Box<dyn Any + Send>> {
+        self.snapshot.lock().take()
+    }
+
     // Note that this method should be called inside a right scope.
     pub(crate) fn set_status(&self, status: ActorStatus) {
         let mut control = self.control.write();
@@ -233,6 +390,8 @@ impl Actor {
             self.close();
             // Drop all messages to release requests immediately.
             self.mailbox.drop_all();
+            self.attached_tasks.abort_all();
+            self.message_span.close();
             self.finished.set();
         }
 
@@ -249,14 +408,21 @@ impl Actor {
             increment_counter!("elfo_actor_status_changes_total", "status" => status.kind.as_str());
         }
 
+        journal::record(EventKind::StatusChanged { status });
+
         // TODO: use `sdnotify` to provide a detailed status to systemd.
         //       or use another actor to listen all statuses for this.
     }
 
     pub(crate) fn close(&self) -> bool {
+        self.shutdown.trigger();
         self.mailbox.close(scope::trace_id())
     }
 
+    pub(crate) fn status(&self) -> ActorStatus {
+        self.control.read().status.clone()
+    }
+
     pub(crate) fn is_initializing(&self) -> bool {
         matches!(
             self.control.read().status.kind,
@@ -296,13 +462,17 @@ fn log_status(status: &ActorStatus) {
     if let Some(details) = status.details.as_deref() {
         match status.kind {
             ActorStatusKind::Failed => error!(status = ?status.kind, %details, "status changed"),
-            ActorStatusKind::Alarming => warn!(status = ?status.kind, %details, "status changed"),
+            ActorStatusKind::Alarming | ActorStatusKind::Overloaded => {
+                warn!(status = ?status.kind, %details, "status changed")
+            }
             _ => info!(status = ?status.kind, %details, "status changed"),
         }
     } else {
         match status.kind {
             ActorStatusKind::Failed => error!(status = ?status.kind, "status changed"),
-            ActorStatusKind::Alarming => warn!(status = ?status.kind, "status changed"),
+            ActorStatusKind::Alarming | ActorStatusKind::Overloaded => {
+                warn!(status = ?status.kind, "status changed")
+            }
             _ => info!(status = ?status.kind, "status changed"),
         }
     }
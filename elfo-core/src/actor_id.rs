@@ -0,0 +1,128 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    addr::{Addr, GroupNo, NodeNo, GROUP_NO_SHIFT},
+    address_book::AddressBook,
+};
+
+/// A stable identifier of an actor, which can be embedded in messages.
+///
+/// Unlike [`Addr`], it's serializable and can be sent to other nodes, which
+/// makes callback-style patterns possible: an actor passes its own id
+/// (see [`Context::actor_id()`]) and the receiver resolves it back
+/// to a send handle (see [`Context::resolve_actor_id()`]) later.
+///
+/// The id consists of the node's number, the group's number, the hash of the
+/// actor's key and the incarnation, which is changed on every restart.
+/// Thus, ids of finished actors are never resolved to their successors.
+///
+/// [`Context::actor_id()`]: crate::Context::actor_id
+/// [`Context::resolve_actor_id()`]: crate::Context::resolve_actor_id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ActorId {
+    /// `None` if the node's number is unknown.
+    node_no: Option<NodeNo>,
+    group_no: GroupNo,
+    key_hash: u64,
+    incarnation: u64,
+}
+
+impl ActorId {
+    pub(crate) fn new(addr: Addr, node_no: Option<NodeNo>, key: &str) -> Self {
+        debug_assert!(addr.is_local());
+
+        Self {
+            node_no,
+            group_no: addr.group_no().expect("invalid addr"),
+            key_hash: key_hash(key),
+            incarnation: addr.into_bits() & ((1 << GROUP_NO_SHIFT) - 1),
+        }
+    }
+
+    /// Returns the number of the node, which the actor belongs to.
+    #[inline]
+    pub fn node_no(&self) -> Option<NodeNo> {
+        self.node_no
+    }
+
+    /// Returns the hash of the actor's key.
+    #[inline]
+    pub fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    /// Resolves the id to the address, which can be used on the current node.
+    ///
+    /// Local ids are checked against alive actors, including the key's hash.
+    /// Remote ids require a route to the remote group.
+    pub(crate) fn resolve(&self, book: &AddressBook) -> Option<Addr> {
+        let local = self.local_addr()?;
+
+        match self.node_no {
+            Some(node_no) if Some(node_no) != book.node_no().get() => {
+                #[cfg(feature = "network")]
+                {
+                    let remote = local.into_remote(node_no);
+                    book.get(remote).map(|_| remote)
+                }
+
+                #[cfg(not(feature = "network"))]
+                {
+                    let _ = node_no;
+                    None
+                }
+            }
+            _ => book
+                .get(local)?
+                .as_actor()
+                .filter(|actor| key_hash(&actor.meta().key) == self.key_hash)
+                .map(|_| local),
+        }
+    }
+
+    fn local_addr(&self) -> Option<Addr> {
+        Addr::from_bits(u64::from(self.group_no.into_bits()) << GROUP_NO_SHIFT | self.incarnation)
+    }
+}
+
+impl fmt::Display for ActorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(node_no) = self.node_no {
+            write!(f, "{}/", node_no)?;
+        }
+
+        write!(
+            f,
+            "{}/{}#{:x}",
+            self.group_no, self.incarnation, self.key_hash
+        )
+    }
+}
+
+// `fxhash` is deterministic, so all nodes calculate the same hash.
+fn key_hash(key: &str) -> u64 {
+    fxhash::hash64(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::addr::NodeLaunchId;
+
+    #[test]
+    fn preserves_addr() {
+        let launch_id = NodeLaunchId::from_bits(0x1234_5678_9abc_def0);
+        let group_no = GroupNo::new(3, launch_id).unwrap();
+        let addr = Addr::new_local(42, group_no, launch_id);
+
+        let id = ActorId::new(addr, NodeNo::from_bits(7), "key");
+        assert_eq!(id.node_no(), NodeNo::from_bits(7));
+        assert_eq!(id.key_hash(), key_hash("key"));
+        assert_ne!(id.key_hash(), key_hash("another"));
+
+        assert_eq!(id.local_addr(), Some(addr));
+    }
+}
@@ -10,7 +10,6 @@ use serde::{Deserialize, Serialize};
 
 /// Represents the node's number.
 /// Cannot be `0`, it's reserved to represent the local node.
-#[stability::unstable]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[derive(Display, Serialize, Deserialize)]
 pub struct NodeNo(NonZeroU16);
@@ -137,8 +136,13 @@ impl GroupNo {
 /// `Addr` cannot be sent inside messages. It prevents from different
 /// possible errors like responding without having a valid connection.
 /// The only way to get an address of remote actor is `envelope.sender()`.
-/// If sending `Addr` inside a message is unavoidable, use `Local<Addr>`,
-/// however it won't be possible to send such message to a remote actor.
+/// If an actor should be referenced inside a message, use [`ActorId`], which
+/// is resolved by [`Context::resolve_actor_id()`] on any node. Otherwise,
+/// use `Local<Addr>`, however it won't be possible to send such message to
+/// a remote actor.
+///
+/// [`ActorId`]: crate::ActorId
+/// [`Context::resolve_actor_id()`]: crate::Context::resolve_actor_id
 // ~
 // Structure (64b platform):
 //  64           48         40           30      21                0
@@ -175,7 +179,7 @@ impl GroupNo {
 pub struct Addr(u64); // TODO: make it `NonZeroU64` instead of `Addr::NULL`?
 
 const NODE_NO_SHIFT: u32 = 48;
-const GROUP_NO_SHIFT: u32 = 40;
+pub(crate) const GROUP_NO_SHIFT: u32 = 40;
 
 // See `Addr` docs for details.
 assert_not_impl_all!(Addr: Serialize, Deserialize<'static>);
@@ -259,7 +263,6 @@ impl Addr {
         GroupNo::from_bits((self.0 >> GROUP_NO_SHIFT) as u8)
     }
 
-    #[cfg(feature = "network")]
     pub(crate) fn node_no_group_no(self) -> u32 {
         (self.0 >> GROUP_NO_SHIFT) as u32
     }
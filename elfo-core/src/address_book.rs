@@ -1,17 +1,32 @@
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+use fxhash::FxHashMap;
 use sharded_slab::{self as slab, Slab};
 
 use crate::{
     addr::{Addr, GroupNo, NodeLaunchId, NodeNo, SlabConfig},
+    context::Overflows,
+    journal::Journal,
+    node::LocalNodeNo,
     object::{Object, ObjectArc, ObjectRef},
+    time::Clock,
 };
 
 // Reexported in `_priv`.
 #[derive(Clone)]
 pub struct AddressBook {
     launch_id: NodeLaunchId,
+    node_no: LocalNodeNo,
+    clock: Arc<ArcSwap<Clock>>,
+    journal: Journal,
+    /// Messages sent by `Context::unbounded_send*()` and not delivered yet.
+    overflows: Arc<Overflows>,
     local: Arc<Slab<Object, SlabConfig>>,
+    /// `node_no_group_no` -> group name, both for local and remote groups.
+    group_names: Arc<ArcSwap<FxHashMap<u32, Arc<str>>>>,
+    /// Names of local groups -> their addresses.
+    local_groups: Arc<ArcSwap<FxHashMap<Arc<str>, Addr>>>,
     #[cfg(feature = "network")]
     remote: Arc<RemoteToHandleMap>, // TODO: use `arc_swap::cache::Cache` in TLS?
 }
@@ -21,16 +36,86 @@ assert_impl_all!(AddressBook: Sync);
 impl AddressBook {
     pub(crate) fn new(launch_id: NodeLaunchId) -> Self {
         let local = Arc::new(Slab::new_with_config::<SlabConfig>());
+        let group_names = Default::default();
+        let local_groups = Default::default();
 
         #[cfg(feature = "network")]
         return Self {
             launch_id,
+            node_no: Default::default(),
+            clock: Default::default(),
+            journal: Default::default(),
+            overflows: Default::default(),
             local,
+            group_names,
+            local_groups,
             remote: Default::default(),
         };
 
         #[cfg(not(feature = "network"))]
-        Self { launch_id, local }
+        Self {
+            launch_id,
+            node_no: Default::default(),
+            clock: Default::default(),
+            journal: Default::default(),
+            overflows: Default::default(),
+            local,
+            group_names,
+            local_groups,
+        }
+    }
+
+    pub(crate) fn node_no(&self) -> &LocalNodeNo {
+        &self.node_no
+    }
+
+    pub(crate) fn clock(&self) -> Clock {
+        (**self.clock.load()).clone()
+    }
+
+    pub(crate) fn set_clock(&self, clock: Clock) {
+        self.clock.store(Arc::new(clock));
+    }
+
+    pub(crate) fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub(crate) fn overflows(&self) -> &Arc<Overflows> {
+        &self.overflows
+    }
+
+    /// Remembers the name of the local group, which the address belongs to.
+    pub(crate) fn register_group_name(&self, addr: Addr, name: &str) {
+        self.insert_group_name(addr.node_no_group_no(), name);
+
+        let name = Arc::<str>::from(name);
+        self.local_groups.rcu(|groups| {
+            let mut groups = (**groups).clone();
+            groups.insert(name.clone(), addr);
+            groups
+        });
+    }
+
+    /// Returns the address of the local group by its name.
+    pub fn local_group(&self, name: &str) -> Option<Addr> {
+        self.local_groups.load().get(name).copied()
+    }
+
+    fn insert_group_name(&self, node_no_group_no: u32, name: &str) {
+        let name = Arc::<str>::from(name);
+
+        self.group_names.rcu(|names| {
+            let mut names = (**names).clone();
+            names.insert(node_no_group_no, name.clone());
+            names
+        });
+    }
+
+    /// Returns the name of the group by `Addr::node_no_group_no()`.
+    /// Works for both local and remote groups.
+    pub(crate) fn group_name_by_bits(&self, node_no_group_no: u32) -> Option<Arc<str>> {
+        self.group_names.load().get(&node_no_group_no).cloned()
     }
 
     #[cfg(feature = "network")]
@@ -38,8 +123,12 @@ impl AddressBook {
         &self,
         local_group: GroupNo,
         remote_group: (NodeNo, GroupNo),
+        remote_group_name: &str,
         handle_addr: Addr,
     ) {
+        let node_no_group_no =
+            u32::from(remote_group.0.into_bits()) << 8 | u32::from(remote_group.1.into_bits());
+        self.insert_group_name(node_no_group_no, remote_group_name);
         self.remote.insert(local_group, remote_group, handle_addr);
     }
 
@@ -82,7 +171,14 @@ impl AddressBook {
     }
 
     pub(crate) fn remove(&self, addr: Addr) {
-        self.local.remove(addr.into_bits() as usize);
+        let key = addr.into_bits() as usize;
+
+        // Entries can be still held by `SendHandle`s, notify them.
+        if let Some(object) = self.local.get(key) {
+            object.mark_removed();
+        }
+
+        self.local.remove(key);
     }
 }
 
@@ -103,9 +199,6 @@ impl<'b> VacantEntry<'b> {
 }
 
 cfg_network!({
-    use arc_swap::ArcSwap;
-    use fxhash::FxHashMap;
-
     #[derive(Default)]
     pub(super) struct RemoteToHandleMap {
         // (local_group_no, remote_node_no_group_no) -> handle_addr
@@ -0,0 +1,44 @@
+use std::future::Future;
+
+use crate::{context::Context, errors::RequestError, handle::SystemHandle, message::Request};
+
+/// Sends requests using routes of the group and waits for responses.
+/// Implemented by [`Context`] and [`SystemHandle`], so clients generated by
+/// [`client!`] work both inside and outside actors.
+///
+/// [`client!`]: crate::client
+pub trait Requester {
+    /// Sends a request and waits for the response.
+    /// See [`Context::request()`] for details.
+    fn request<R: Request>(
+        &self,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + Send + '_;
+}
+
+impl<C: Send + Sync + 'static, K: Send + Sync> Requester for Context<C, K> {
+    fn request<R: Request>(
+        &self,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + Send + '_ {
+        Context::request(self, request).resolve()
+    }
+}
+
+impl Requester for SystemHandle {
+    fn request<R: Request>(
+        &self,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + Send + '_ {
+        SystemHandle::request(self, request)
+    }
+}
+
+impl<T: Requester + Sync + ?Sized> Requester for &T {
+    fn request<R: Request>(
+        &self,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + Send + '_ {
+        (**self).request(request)
+    }
+}
@@ -6,11 +6,15 @@ use std::{
 };
 
 use derive_more::From;
-use serde::{de, de::value::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
-use serde_value::{Value, ValueDeserializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_value::Value;
 
 use crate::local::Local;
 
+pub mod types;
+
+mod path;
+
 pub trait Config: for<'de> Deserialize<'de> + Send + Sync + fmt::Debug + 'static {}
 impl<C> Config for C where C: for<'de> Deserialize<'de> + Send + Sync + fmt::Debug + 'static {}
 
@@ -61,8 +65,8 @@ impl AnyConfig {
 
         let system_decoded = if let Value::Map(map) = &mut raw {
             if let Some(system_raw) = map.remove(&Value::String("system".into())) {
-                let de = ValueDeserializer::<DeError>::new(system_raw);
-                let config = SystemConfig::deserialize(de).map_err(|err| err.to_string())?;
+                let config = path::deserialize::<SystemConfig>(system_raw)
+                    .map_err(|err| err.at("system").to_string())?;
                 Arc::new(config)
             } else {
                 Default::default()
@@ -75,8 +79,7 @@ impl AnyConfig {
         let user_decoded = if TypeId::of::<C>() == TypeId::of::<()>() {
             Arc::new(Arc::new(())) as Arc<_>
         } else {
-            let de = ValueDeserializer::<DeError>::new(raw);
-            let config = C::deserialize(de).map_err(|err| err.to_string())?;
+            let config = path::deserialize::<C>(raw).map_err(|err| err.to_string())?;
             Arc::new(Arc::new(config)) as Arc<_>
         };
 
@@ -162,6 +165,13 @@ pub(crate) struct SystemConfig {
     pub(crate) logging: crate::logging::LoggingConfig,
     pub(crate) dumping: crate::dumping::DumpingConfig,
     pub(crate) telemetry: crate::telemetry::TelemetryConfig,
+    pub(crate) overload: crate::overload::OverloadConfig,
+    pub(crate) hedging: crate::hedging::HedgingConfig,
+    pub(crate) tracing: crate::tracing::TracingConfig,
+    pub(crate) requests: crate::request_table::RequestsConfig,
+    pub(crate) handling: crate::handling::HandlingConfig,
+    pub(crate) flags: crate::flags::FlagsConfig,
+    pub(crate) readiness: crate::supervisor::readiness::ReadinessConfig,
 }
 
 // === Secret ===
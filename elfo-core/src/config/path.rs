@@ -0,0 +1,255 @@
+//! Deserialization of configs reporting paths to invalid values in errors,
+//! e.g. "invalid value at `discovery.predefined[1]`: ...".
+
+use std::{collections::btree_map, fmt, vec};
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any,
+};
+use serde_value::{Value, ValueDeserializer};
+
+pub(crate) fn deserialize<T: de::DeserializeOwned>(value: Value) -> Result<T, Error> {
+    T::deserialize(PathDeserializer(value))
+}
+
+// === Error ===
+
+#[derive(Debug)]
+pub(crate) struct Error {
+    /// Segments in reversed order, because they're added while unwinding.
+    path: Vec<Segment>,
+    message: String,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Error {
+    pub(crate) fn at(mut self, key: impl Into<String>) -> Self {
+        self.path.push(Segment::Key(key.into()));
+        self
+    }
+
+    fn at_index(mut self, index: usize) -> Self {
+        self.path.push(Segment::Index(index));
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            return f.write_str(&self.message);
+        }
+
+        f.write_str("invalid value at `")?;
+        for (i, segment) in self.path.iter().rev().enumerate() {
+            match segment {
+                Segment::Key(key) if i == 0 => f.write_str(key)?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        write!(f, "`: {}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            path: Vec::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+// === PathDeserializer ===
+
+struct PathDeserializer(Value);
+
+impl<'de> Deserializer<'de> for PathDeserializer {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit
+        seq bytes byte_buf map unit_struct tuple_struct struct
+        tuple ignored_any identifier
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Seq(seq) => {
+                let len = seq.len();
+                let mut access = SeqDeserializer(seq.into_iter().enumerate());
+                let value = visitor.visit_seq(&mut access)?;
+
+                if access.0.len() == 0 {
+                    Ok(value)
+                } else {
+                    Err(de::Error::invalid_length(
+                        len,
+                        &"fewer elements in sequence",
+                    ))
+                }
+            }
+            Value::Map(map) => visitor.visit_map(MapDeserializer {
+                iter: map.into_iter(),
+                value: None,
+            }),
+            Value::Option(Some(value)) => visitor.visit_some(PathDeserializer(*value)),
+            Value::Option(None) => visitor.visit_none(),
+            Value::Newtype(value) => visitor.visit_newtype_struct(PathDeserializer(*value)),
+            value => ValueDeserializer::new(value).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Option(Some(value)) => visitor.visit_some(PathDeserializer(*value)),
+            Value::Option(None) => visitor.visit_none(),
+            Value::Unit => visitor.visit_unit(),
+            value => visitor.visit_some(PathDeserializer(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Newtype(value) => visitor.visit_newtype_struct(PathDeserializer(*value)),
+            value => visitor.visit_newtype_struct(PathDeserializer(value)),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        ValueDeserializer::new(self.0).deserialize_enum(name, variants, visitor)
+    }
+}
+
+struct SeqDeserializer(std::iter::Enumerate<vec::IntoIter<Value>>);
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some((index, value)) = self.0.next() else {
+            return Ok(None);
+        };
+
+        seed.deserialize(PathDeserializer(value))
+            .map(Some)
+            .map_err(|err| err.at_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: btree_map::IntoIter<Value, Value>,
+    value: Option<(String, Value)>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+
+        let name = match &key {
+            Value::String(name) => name.clone(),
+            key => format!("{key:?}"),
+        };
+
+        self.value = Some((name, value));
+        seed.deserialize(ValueDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (name, value) = self.value.take().expect("value is missing");
+        seed.deserialize(PathDeserializer(value))
+            .map_err(|err| err.at(name))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        #[allow(dead_code)]
+        nodes: Vec<Node>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Node {
+        #[allow(dead_code)]
+        port: u16,
+    }
+
+    fn node(port: Value) -> Value {
+        let mut node = BTreeMap::new();
+        node.insert(Value::String("port".into()), port);
+        Value::Map(node)
+    }
+
+    #[test]
+    fn it_reports_paths() {
+        let mut config = BTreeMap::new();
+        config.insert(
+            Value::String("nodes".into()),
+            Value::Seq(vec![
+                node(Value::U64(8080)),
+                node(Value::String("http".into())),
+            ]),
+        );
+
+        let err = deserialize::<Config>(Value::Map(config)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"invalid value at `nodes[1].port`: invalid type: string "http", expected u16"#
+        );
+
+        let err = deserialize::<Config>(Value::Map(BTreeMap::new())).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `nodes`");
+
+        let err = deserialize::<Config>(Value::Map(BTreeMap::new()))
+            .unwrap_err()
+            .at("system");
+        assert_eq!(
+            err.to_string(),
+            "invalid value at `system`: missing field `nodes`"
+        );
+    }
+}
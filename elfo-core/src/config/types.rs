@@ -0,0 +1,399 @@
+//! Types with humane formats for use in configs.
+//!
+//! * [`Duration`]: `"100ms"`, `"5s"`, `"1h 30m"`.
+//! * [`ByteSize`]: `"64KiB"`, `"1.5GiB"`, `"100MB"` or a number of bytes.
+//! * [`SocketAddr`]: `"0.0.0.0:8843"`, `"[::1]:8843"`.
+//!
+//! All of them are transparent wrappers dereferencing to the underlying type.
+//! Errors contain the invalid value and an example of the expected format.
+//!
+//! # Example
+//! ```
+//! # use elfo_core as elfo;
+//! use elfo::config::types::{ByteSize, Duration, SocketAddr};
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     listen: SocketAddr,
+//!     timeout: Duration,
+//!     buffer_size: ByteSize,
+//! }
+//! ```
+
+use std::{fmt, net, ops::Deref, str::FromStr, time};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// === Duration ===
+
+/// A duration in the humantime format, e.g. `"100ms"`, `"5s"` or `"1h 30m"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Duration(pub time::Duration);
+
+impl Deref for Duration {
+    type Target = time::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<time::Duration> for Duration {
+    fn from(duration: time::Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<Duration> for time::Duration {
+    fn from(duration: Duration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for Duration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        humantime::parse_duration(s)
+            .map(Self)
+            .map_err(|err| format!("invalid duration {s:?}: {err}, expected e.g. \"5s\""))
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&humantime::format_duration(self.0), f)
+    }
+}
+
+impl Serialize for Duration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Duration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new("a duration, e.g. \"5s\""))
+    }
+}
+
+// === ByteSize ===
+
+/// A size in bytes, e.g. `"64KiB"`, `"1.5GiB"`, `"100MB"` or `1024`.
+///
+/// Both decimal (`KB`, `MB`, `GB`, `TB`) and binary (`KiB`, `MiB`, `GiB`,
+/// `TiB`) units are supported, case-insensitively. Plain numbers are bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ByteSize(pub u64);
+
+const UNITS: &[(&str, u64)] = &[
+    ("b", 1),
+    ("kb", 1000),
+    ("kib", 1 << 10),
+    ("mb", 1000 * 1000),
+    ("mib", 1 << 20),
+    ("gb", 1000 * 1000 * 1000),
+    ("gib", 1 << 30),
+    ("tb", 1000 * 1000 * 1000 * 1000),
+    ("tib", 1 << 40),
+];
+
+impl ByteSize {
+    pub const fn b(size: u64) -> Self {
+        Self(size)
+    }
+
+    pub const fn kib(size: u64) -> Self {
+        Self(size << 10)
+    }
+
+    pub const fn mib(size: u64) -> Self {
+        Self(size << 20)
+    }
+
+    pub const fn gib(size: u64) -> Self {
+        Self(size << 30)
+    }
+
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl Deref for ByteSize {
+    type Target = u64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(size: u64) -> Self {
+        Self(size)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason: &str| format!("invalid size {s:?}: {reason}, expected e.g. \"64KiB\"");
+
+        let trimmed = s.trim();
+        let split_at = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split_at);
+        let unit = unit.trim_start();
+
+        let multiplier = if unit.is_empty() {
+            1
+        } else {
+            UNITS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+                .map(|(_, multiplier)| *multiplier)
+                .ok_or_else(|| error(&format!("unknown unit {unit:?}")))?
+        };
+
+        if let Ok(number) = number.parse::<u64>() {
+            return number
+                .checked_mul(multiplier)
+                .map(Self)
+                .ok_or_else(|| error("too large"));
+        }
+
+        let number = number.parse::<f64>().map_err(|_| error("not a number"))?;
+        let size = (number * multiplier as f64).round();
+
+        if size > u64::MAX as f64 {
+            return Err(error("too large"));
+        }
+
+        Ok(Self(size as u64))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Use the largest binary unit keeping the value integer (all are powers of
+        // two).
+        let (name, multiplier) = [
+            ("TiB", 1 << 40),
+            ("GiB", 1 << 30),
+            ("MiB", 1 << 20),
+            ("KiB", 1 << 10),
+        ]
+        .into_iter()
+        .find(|(_, multiplier)| self.0 != 0 && self.0 & (multiplier - 1) == 0)
+        .unwrap_or(("B", 1));
+
+        write!(f, "{}{}", self.0 / multiplier, name)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FromStrVisitor::new("a size, e.g. \"64KiB\""))
+    }
+}
+
+// === SocketAddr ===
+
+/// A socket address, e.g. `"0.0.0.0:8843"` or `"[::1]:8843"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SocketAddr(pub net::SocketAddr);
+
+impl Deref for SocketAddr {
+    type Target = net::SocketAddr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<net::SocketAddr> for SocketAddr {
+    fn from(addr: net::SocketAddr) -> Self {
+        Self(addr)
+    }
+}
+
+impl From<SocketAddr> for net::SocketAddr {
+    fn from(addr: SocketAddr) -> Self {
+        addr.0
+    }
+}
+
+impl FromStr for SocketAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .map(Self)
+            .map_err(|_| format!("invalid socket address {s:?}, expected e.g. \"0.0.0.0:8843\""))
+    }
+}
+
+impl fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for SocketAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SocketAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new(
+            "a socket address, e.g. \"0.0.0.0:8843\"",
+        ))
+    }
+}
+
+// === FromStrVisitor ===
+
+/// Parses strings by `FromStr`, unsigned integers by `From<u64>` if allowed.
+struct FromStrVisitor<T> {
+    expecting: &'static str,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> FromStrVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        Self {
+            expecting,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+trait FromNumber: Sized {
+    fn from_number(number: u64) -> Option<Self>;
+}
+
+impl FromNumber for Duration {
+    fn from_number(_: u64) -> Option<Self> {
+        None
+    }
+}
+
+impl FromNumber for ByteSize {
+    fn from_number(number: u64) -> Option<Self> {
+        Some(Self(number))
+    }
+}
+
+impl FromNumber for SocketAddr {
+    fn from_number(_: u64) -> Option<Self> {
+        None
+    }
+}
+
+impl<'de, T> de::Visitor<'de> for FromStrVisitor<T>
+where
+    T: FromStr<Err = String> + FromNumber,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        T::from_number(v).ok_or_else(|| E::invalid_type(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(E::invalid_type(de::Unexpected::Signed(v), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_value::{Value, ValueDeserializer};
+
+    use super::*;
+
+    fn decode<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, String> {
+        T::deserialize(ValueDeserializer::<de::value::Error>::new(value)).map_err(|e| e.to_string())
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    #[test]
+    fn duration() {
+        let d = |s| decode::<Duration>(string(s)).map(|d| d.0);
+        assert_eq!(d("100ms"), Ok(time::Duration::from_millis(100)));
+        assert_eq!(d("5s"), Ok(time::Duration::from_secs(5)));
+        assert_eq!(d("1h 30m"), Ok(time::Duration::from_secs(5400)));
+        assert!(d("5x").unwrap_err().starts_with("invalid duration \"5x\""));
+        assert!(decode::<Duration>(Value::U64(5)).is_err());
+
+        assert_eq!(
+            Duration::from(time::Duration::from_millis(1500)).to_string(),
+            "1s 500ms"
+        );
+    }
+
+    #[test]
+    fn byte_size() {
+        let s = |s| decode::<ByteSize>(string(s)).map(|s| s.0);
+        assert_eq!(s("64KiB"), Ok(64 * 1024));
+        assert_eq!(s("64 kib"), Ok(64 * 1024));
+        assert_eq!(s("1.5GiB"), Ok(3 << 29));
+        assert_eq!(s("100MB"), Ok(100_000_000));
+        assert_eq!(s("42"), Ok(42));
+        assert_eq!(s("42B"), Ok(42));
+        assert_eq!(decode::<ByteSize>(Value::U64(42)), Ok(ByteSize(42)));
+        assert_eq!(decode::<ByteSize>(Value::I64(42)), Ok(ByteSize(42)));
+
+        assert!(s("64KB/s").unwrap_err().contains("unknown unit \"KB/s\""));
+        assert!(s("KiB").unwrap_err().contains("not a number"));
+        assert!(s("100000000TiB").unwrap_err().contains("too large"));
+        assert!(decode::<ByteSize>(Value::I64(-1)).is_err());
+
+        assert_eq!(ByteSize::mib(64).to_string(), "64MiB");
+        assert_eq!(ByteSize(3 << 29).to_string(), "1536MiB");
+        assert_eq!(ByteSize(1000).to_string(), "1000B");
+        assert_eq!(ByteSize(0).to_string(), "0B");
+    }
+
+    #[test]
+    fn socket_addr() {
+        let a = |s| decode::<SocketAddr>(string(s)).map(|a| a.0);
+        assert_eq!(a("0.0.0.0:8843"), Ok("0.0.0.0:8843".parse().unwrap()));
+        assert_eq!(a("[::1]:8843"), Ok("[::1]:8843".parse().unwrap()));
+        assert_eq!(
+            a("localhost"),
+            Err(r#"invalid socket address "localhost", expected e.g. "0.0.0.0:8843""#.into())
+        );
+    }
+}
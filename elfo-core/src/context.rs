@@ -1,13 +1,24 @@
-use std::{future::poll_fn, marker::PhantomData, pin::Pin, sync::Arc, task::Poll};
+use std::{
+    any::TypeId,
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
 
 use futures::{pin_mut, Stream};
+use metrics::Key;
 use once_cell::sync::Lazy;
-use tracing::{info, trace};
+use tokio::{sync::Semaphore, time::Instant};
+use tracing::{error_span, info, trace, warn};
 
 use elfo_utils::unlikely;
 
 use crate::{
     actor::{Actor, ActorStatus},
+    actor_id::ActorId,
     addr::Addr,
     address_book::AddressBook,
     config::AnyConfig,
@@ -15,21 +26,34 @@ use crate::{
     dumping::{Direction, Dump, Dumper, INTERNAL_CLASS},
     envelope::{AnyMessageBorrowed, AnyMessageOwned, Envelope, EnvelopeOwned, MessageKind},
     errors::{RequestError, SendError, TryRecvError, TrySendError},
+    flags::Flag,
     group::RestartPolicy,
     mailbox::RecvResult,
     message::{Message, Request},
     messages, msg,
-    object::ObjectArc,
+    object::{Object, ObjectArc},
+    overload::OverloadDetector,
     request_table::ResponseToken,
     routers::Singleton,
     scope,
+    shutdown::ShutdownToken,
     source::{SourceHandle, Sources, UnattachedSource},
+    task::TaskOutput,
+    tracing::{Baggage, TraceId},
 };
 
-use self::{budget::Budget, stats::Stats};
+use self::{budget::Budget, concurrency::ConcurrencyLimit, idle::IdleTimer, stats::Stats};
+
+pub use self::send_handle::SendHandle;
 
 mod budget;
+mod concurrency;
+mod idle;
+mod send_handle;
 mod stats;
+mod unbounded;
+
+pub(crate) use self::unbounded::Overflows;
 
 static DUMPER: Lazy<Dumper> = Lazy::new(|| Dumper::new(INTERNAL_CLASS));
 
@@ -45,7 +69,20 @@ pub struct Context<C = (), K = Singleton> {
     sources: Sources,
     stage: Stage,
     stats: Stats,
+    overload: OverloadDetector,
     budget: Budget,
+    idle: IdleTimer,
+    concurrency: ConcurrencyLimit,
+}
+
+/// The result of [`Context::recv_or()`].
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum RecvOr<T> {
+    /// An envelope is received from the mailbox or sources.
+    Envelope(Envelope),
+    /// The provided future is completed.
+    Future(T),
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -77,17 +114,101 @@ impl<C, K> Context<C, K> {
         &self.config
     }
 
+    /// Returns the current value of the feature flag, which is set in the
+    /// `system.flags` section of the config. See [`flags`] for details.
+    ///
+    /// [`flags`]: crate::flags
+    #[inline]
+    pub fn flag<F: Flag>(&self) -> bool {
+        scope::with(|scope| scope.flags().get::<F>())
+    }
+
     /// Returns the actor's key.
     #[inline]
     pub fn key(&self) -> &K {
         &self.key
     }
 
+    /// Returns a token triggered once the actor should stop, see
+    /// [`ShutdownToken`] for details.
+    ///
+    /// For contexts without an actor (group's and pruned ones), the returned
+    /// token is never triggered, so obtain it before calling
+    /// [`Context::pruned()`].
+    pub fn shutdown_token(&self) -> ShutdownToken {
+        let actor = self.actor.as_ref().and_then(|o| o.as_actor());
+        actor
+            .map(|actor| actor.shutdown_token().clone())
+            .unwrap_or_default()
+    }
+
     /// Attaches the provided source to the context.
     pub fn attach<S1: SourceHandle>(&mut self, source: UnattachedSource<S1>) -> S1 {
         source.attach_to(&mut self.sources)
     }
 
+    /// Spawns a background task tied to the actor's lifetime.
+    ///
+    /// Unlike raw `tokio::spawn`, the task is aborted once the actor is
+    /// finished (including restarts), and its panic fails the actor, so the
+    /// restart policy is applied. If the task outputs a message, it's sent
+    /// back to the actor.
+    ///
+    /// Does nothing if called on a pruned context or a finished actor.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # #[elfo::message] struct Fetched(u32);
+    /// # async fn fetch() -> u32 { 42 }
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// use elfo::msg;
+    ///
+    /// ctx.attach_task(async { Fetched(fetch().await) });
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         Fetched(value) => { /* ... */ }
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    pub fn attach_task<F>(&self, future: F)
+    where
+        F: Future + Send + 'static,
+        F::Output: TaskOutput,
+    {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        actor.attached_tasks().spawn(self.pruned(), future);
+    }
+
+    /// Runs the provided future with the specified trace id, restoring the
+    /// current one afterwards. Messages sent and requests made inside the
+    /// future carry this trace id.
+    ///
+    /// Useful for bridge actors consuming external events (HTTP, Kafka and so
+    /// on) to seed the trace id from upstream headers rather than use a
+    /// disconnected one.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # #[elfo::message] struct Event;
+    /// # async fn exec(ctx: elfo::Context, header: &str) {
+    /// use elfo::tracing::TraceId;
+    ///
+    /// if let Ok(trace_id) = header.parse::<TraceId>() {
+    ///     ctx.with_trace_id(trace_id, async {
+    ///         let _ = ctx.send(Event).await;
+    ///     })
+    ///     .await;
+    /// }
+    /// # }
+    /// ```
+    pub async fn with_trace_id<F: Future>(&self, trace_id: TraceId, f: F) -> F::Output {
+        scope::with_trace_id(trace_id, f).await
+    }
+
     /// Updates the actor's status.
     ///
     /// # Example
@@ -121,6 +242,41 @@ impl<C, K> Context<C, K> {
         ward!(self.actor.as_ref().and_then(|o| o.as_actor())).set_restart_policy(policy.into());
     }
 
+    /// Leaves the state to the next incarnation of the actor, which takes it
+    /// by [`Context::take_snapshot()`] after restarting, e.g. because of
+    /// [`messages::RollingRestart`] or a failure. Replaces the previous one.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// let mut sessions: Vec<u64> = ctx.take_snapshot().unwrap_or_default();
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     // ...
+    /// }
+    ///
+    /// ctx.set_snapshot(sessions);
+    /// # }
+    /// ```
+    pub fn set_snapshot<S: Send + 'static>(&self, snapshot: S) {
+        ward!(self.actor.as_ref().and_then(|o| o.as_actor())).set_snapshot(Box::new(snapshot));
+    }
+
+    /// Takes the state left by the previous incarnation of the actor by
+    /// [`Context::set_snapshot()`]. Returns `None` if there is no snapshot or
+    /// it has another type.
+    pub fn take_snapshot<S: 'static>(&self) -> Option<S> {
+        let actor = self.actor.as_ref().and_then(|o| o.as_actor())?;
+        actor.take_snapshot()?.downcast().ok().map(|s| *s)
+    }
+
+    /// Resets the idle timer set by `ActorGroup::idle_timeout()`. Call it on
+    /// [`messages::IdleTimeout`] to prevent the actor from being closed.
+    pub fn keep_alive(&mut self) {
+        self.idle.reset();
+    }
+
     /// Closes the mailbox, that leads to returning `None` from `recv()` and
     /// `try_recv()` after handling all available messages in the mailbox.
     ///
@@ -153,12 +309,74 @@ impl<C, K> Context<C, K> {
         self.do_send(message, kind).await
     }
 
+    /// Sends a message using the routing system, but waits for capacity of
+    /// mailboxes no longer than the provided timeout.
+    ///
+    /// Returns
+    /// * `Ok(())` if the message has been added to any mailbox.
+    /// * `Err(MailboxFull(_))` if mailboxes are still full after the timeout.
+    /// * `Err(_)` with other reasons the same as [`Context::send()`] does.
+    ///
+    /// # Example
+    /// ```ignore
+    /// if let Err(err) = ctx.send_timeout(SomethingHappened, timeout).await {
+    ///     if err.is_full() {
+    ///         // Shed load.
+    ///     }
+    /// }
+    /// ```
+    pub async fn send_timeout<M: Message>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<(), TrySendError<M>> {
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+        let deadline = Instant::now() + timeout;
+        self.do_send_until(message, kind, Some(deadline)).await
+    }
+
+    /// Sends a message using the routing system, but recipients drop it
+    /// instead of receiving if it's older than the provided TTL. Overrides
+    /// the TTL specified by `#[message(ttl = "..")]`.
+    ///
+    /// Expired messages are counted by the `elfo_expired_messages_total`
+    /// metric. Dropped requests are rejected, because their tokens are
+    /// dropped too. The TTL is kept only inside the node, on other nodes
+    /// only the message's TTL is applied.
+    ///
+    /// Returns `Err` if the message hasn't reached any mailboxes.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // A stale tick is worse than a missed one.
+    /// let _ = ctx.send_with_ttl(Tick, Duration::from_millis(500)).await;
+    /// ```
+    pub async fn send_with_ttl<M: Message>(
+        &self,
+        message: M,
+        ttl: Duration,
+    ) -> Result<(), SendError<M>> {
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+        let mut envelope = self.prepare_envelope(message, kind);
+        envelope.set_ttl(ttl);
+        let addrs = self.demux.filter(&envelope);
+
+        self.send_envelope_until(envelope, &addrs, None)
+            .await
+            .map_err(|err| err.map(e2m).into_send_error())
+    }
+
     /// Tries to send a message using the routing system.
     ///
     /// Returns
     /// * `Ok(())` if the message has been added to any mailbox.
-    /// * `Err(Full(_))` if some mailboxes are full.
-    /// * `Err(Closed(_))` otherwise.
+    /// * `Err(MailboxFull(_))` if some mailboxes are full.
+    /// * `Err(NoRoute(_))` if there are no recipients.
+    /// * `Err(_)` with the last occurred reason otherwise.
     ///
     /// # Example
     /// ```ignore
@@ -181,15 +399,16 @@ impl<C, K> Context<C, K> {
         self.stats.on_sent_message(&message);
 
         trace!("> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
+        let envelope = Envelope::new(message, kind);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
         }
 
-        let envelope = Envelope::new(message, kind).upcast();
+        let envelope = envelope.upcast();
         let addrs = self.demux.filter(&envelope);
 
         if addrs.is_empty() {
-            return Err(TrySendError::Closed(e2m(envelope)));
+            return Err(TrySendError::NoRoute(e2m(envelope)));
         }
 
         if addrs.len() == 1 {
@@ -197,11 +416,12 @@ impl<C, K> Context<C, K> {
                 Some(object) => object
                     .try_send(Addr::NULL, envelope)
                     .map_err(|err| err.map(e2m)),
-                None => Err(TrySendError::Closed(e2m(envelope))),
+                None => Err(TrySendError::NoRoute(e2m(envelope))),
             };
         }
 
         let mut unused = None;
+        let mut reason = TrySendError::NoRoute(());
         let mut has_full = false;
         let mut success = false;
 
@@ -211,7 +431,9 @@ impl<C, K> Context<C, K> {
                     Ok(()) => success = true,
                     Err(err) => {
                         has_full |= err.is_full();
-                        forget_and_replace(&mut unused, Some(err.into_inner()));
+                        let (new_reason, envelope) = err.split();
+                        reason = new_reason;
+                        forget_and_replace(&mut unused, Some(envelope));
                     }
                 },
                 None => forget_and_replace(&mut unused, Some(envelope)),
@@ -222,9 +444,68 @@ impl<C, K> Context<C, K> {
             forget_and_replace(&mut unused, None);
             Ok(())
         } else if has_full {
-            Err(TrySendError::Full(e2m(unused.unwrap())))
+            Err(TrySendError::MailboxFull(e2m(unused.unwrap())))
         } else {
-            Err(TrySendError::Closed(e2m(unused.unwrap())))
+            Err(reason.map(|()| e2m(unused.unwrap())))
+        }
+    }
+
+    /// Sends a message using the routing system without waiting for capacity
+    /// of mailboxes. Messages to full mailboxes are put into per-recipient
+    /// overflow queues, which are delivered in order by separate tasks.
+    ///
+    /// It's an escape hatch for rare control paths that must never block
+    /// (e.g. inside `Drop`), prefer [`Context::send()`] or
+    /// [`Context::try_send()`] otherwise. Usage is accounted by the
+    /// `elfo_unbounded_overflows_total` and `elfo_unbounded_pending_messages`
+    /// metrics, a warning is logged if too many messages are pending.
+    ///
+    /// Returns `Err` if the message cannot reach any mailboxes. If called
+    /// outside the tokio runtime, messages to full mailboxes are lost and
+    /// counted by the `elfo_unbounded_lost_messages_total` metric.
+    pub fn unbounded_send<M: Message>(&self, message: M) -> Result<(), SendError<M>> {
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+
+        self.stats.on_sent_message(&message);
+
+        trace!("> {:?}", message);
+        let envelope = Envelope::new(message, kind);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
+        }
+
+        let envelope = envelope.upcast();
+        let addrs = self.demux.filter(&envelope);
+
+        if addrs.is_empty() {
+            return Err(SendError::NoRoute(e2m(envelope)));
+        }
+
+        let mut unused = None;
+        let mut reason = SendError::NoRoute(());
+        let mut success = false;
+
+        for (addr, envelope) in addrs_with_envelope(envelope, &addrs) {
+            match self.book.get_owned(addr) {
+                Some(object) => match self.do_unbounded_send(object, Addr::NULL, envelope) {
+                    Ok(()) => success = true,
+                    Err(err) => {
+                        let (new_reason, envelope) = err.split();
+                        reason = new_reason;
+                        forget_and_replace(&mut unused, Some(envelope));
+                    }
+                },
+                None => forget_and_replace(&mut unused, Some(envelope)),
+            };
+        }
+
+        if success {
+            forget_and_replace(&mut unused, None);
+            Ok(())
+        } else {
+            Err(reason.map(|()| e2m(unused.unwrap())))
         }
     }
 
@@ -267,43 +548,78 @@ impl<C, K> Context<C, K> {
     }
 
     async fn do_send<M: Message>(&self, message: M, kind: MessageKind) -> Result<(), SendError<M>> {
+        self.do_send_until(message, kind, None)
+            .await
+            .map_err(TrySendError::into_send_error)
+    }
+
+    async fn do_send_until<M: Message>(
+        &self,
+        message: M,
+        kind: MessageKind,
+        deadline: Option<Instant>,
+    ) -> Result<(), TrySendError<M>> {
+        let envelope = self.prepare_envelope(message, kind);
+        let addrs = self.demux.filter(&envelope);
+
+        self.send_envelope_until(envelope, &addrs, deadline)
+            .await
+            .map_err(|err| err.map(e2m))
+    }
+
+    fn prepare_envelope<M: Message>(&self, message: M, kind: MessageKind) -> Envelope {
         self.stats.on_sent_message(&message);
 
         trace!("> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
+        let envelope = Envelope::new(message, kind);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
         }
 
-        let envelope = Envelope::new(message, kind).upcast();
-        let addrs = self.demux.filter(&envelope);
+        envelope.upcast()
+    }
 
+    async fn send_envelope_until(
+        &self,
+        envelope: Envelope,
+        addrs: &[Addr],
+        deadline: Option<Instant>,
+    ) -> Result<(), TrySendError<Envelope>> {
         if addrs.is_empty() {
-            return Err(SendError(e2m(envelope)));
+            return Err(TrySendError::NoRoute(envelope));
         }
 
         if addrs.len() == 1 {
             let recipient = addrs[0];
             return match self.book.get_owned(recipient) {
-                Some(object) => object
-                    .send(self, Addr::NULL, envelope)
-                    .await
-                    .map_err(|err| SendError(e2m(err.0))),
-                None => Err(SendError(e2m(envelope))),
+                Some(object) => {
+                    object
+                        .send_until(self, Addr::NULL, envelope, deadline)
+                        .await
+                }
+                None => Err(TrySendError::NoRoute(envelope)),
             };
         }
 
         let mut unused = None;
+        let mut reason = TrySendError::NoRoute(());
+        let mut has_full = false;
         let mut success = false;
 
         // TODO: send concurrently.
-        for (addr, envelope) in addrs_with_envelope(envelope, &addrs) {
+        for (addr, envelope) in addrs_with_envelope(envelope, addrs) {
             match self.book.get_owned(addr) {
                 Some(object) => {
                     let returned_envelope = object
-                        .send(self, Addr::NULL, envelope)
+                        .send_until(self, Addr::NULL, envelope, deadline)
                         .await
                         .err()
-                        .map(|err| err.0);
+                        .map(|err| {
+                            has_full |= err.is_full();
+                            let (new_reason, envelope) = err.split();
+                            reason = new_reason;
+                            envelope
+                        });
                     forget_and_replace(&mut unused, returned_envelope);
                     if unused.is_none() {
                         success = true;
@@ -316,11 +632,83 @@ impl<C, K> Context<C, K> {
         if success {
             forget_and_replace(&mut unused, None);
             Ok(())
+        } else if has_full {
+            Err(TrySendError::MailboxFull(unused.unwrap()))
         } else {
-            Err(SendError(e2m(unused.unwrap())))
+            Err(reason.map(|()| unused.unwrap()))
         }
     }
 
+    /// Sends an idempotent request to the first recipient and, if there is no
+    /// response after the delay, its duplicate to the second one.
+    /// Other recipients are ignored. See the `hedging` module for details.
+    async fn do_request_hedged<R: Request>(
+        &self,
+        request: R,
+        kind: MessageKind,
+        actor: &Actor,
+        delay: Duration,
+    ) -> Result<Envelope, RequestError> {
+        let request_id = match &kind {
+            MessageKind::RequestAny(token) => token.request_id(),
+            _ => unreachable!("only `any` requests are hedged"),
+        };
+
+        let envelope = self.prepare_envelope(request, kind);
+        let addrs = self.demux.filter(&envelope);
+        let table = actor.request_table();
+
+        if addrs.len() < 2 {
+            if let Err(err) = self.send_envelope_until(envelope, &addrs, None).await {
+                table.cancel_request(request_id);
+                return Err(RequestError::NotSent(err.into_send_error().map(drop)));
+            }
+
+            return table
+                .wait(request_id)
+                .await
+                .pop()
+                .expect("missing response");
+        }
+
+        // The request isn't completed by a failed response of the first
+        // recipient, because the duplicate is already accounted.
+        let hedged = envelope.duplicate();
+        let is_sent = self
+            .send_envelope_until(envelope, &addrs[..1], None)
+            .await
+            .is_ok();
+
+        if is_sent {
+            if let Ok(mut responses) = tokio::time::timeout(delay, table.wait(request_id)).await {
+                let (_, token) = hedged.unpack_request();
+                token.forget();
+                return responses.pop().expect("missing response");
+            }
+        }
+
+        if let Some(recorder) = metrics::try_recorder() {
+            let key = metrics::Key::from_static_parts(
+                "elfo_hedged_requests_total",
+                hedged.message().labels(),
+            );
+            recorder.increment_counter(&key, 1);
+        }
+
+        let hedged_res = self.send_envelope_until(hedged, &addrs[1..2], None).await;
+
+        if let (false, Err(err)) = (is_sent, hedged_res) {
+            table.cancel_request(request_id);
+            return Err(RequestError::NotSent(err.into_send_error().map(drop)));
+        }
+
+        table
+            .wait(request_id)
+            .await
+            .pop()
+            .expect("missing response")
+    }
+
     /// Sends a message to the specified recipient.
     ///
     /// Returns `Err` if the message hasn't reached any mailboxes.
@@ -349,25 +737,114 @@ impl<C, K> Context<C, K> {
         self.do_send_to(recipient, message, kind).await
     }
 
-    async fn do_send_to<M: Message>(
+    /// Sends a message to the specified recipient, but the recipient drops it
+    /// instead of receiving if it's older than the provided TTL.
+    /// See [`Context::send_with_ttl()`] for details.
+    pub async fn send_to_with_ttl<M: Message>(
         &self,
         recipient: Addr,
         message: M,
-        kind: MessageKind,
+        ttl: Duration,
     ) -> Result<(), SendError<M>> {
         self.stats.on_sent_message(&message);
 
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+
         trace!(to = %recipient, "> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
+        let mut envelope = Envelope::new(message, kind);
+        envelope.set_ttl(ttl);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
         }
 
         let entry = self.book.get_owned(recipient);
-        let object = ward!(entry, return Err(SendError(message)));
+        let object = ward!(
+            entry,
+            return Err(SendError::NoRoute(envelope.into_message()))
+        );
+        let fut = object.send_until(self, recipient, envelope.upcast(), None);
+        let result = fut.await;
+        result.map_err(|err| err.map(e2m).into_send_error())
+    }
+
+    async fn do_send_to<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+        kind: MessageKind,
+    ) -> Result<(), SendError<M>> {
+        self.do_send_to_until(recipient, message, kind, None)
+            .await
+            .map_err(TrySendError::into_send_error)
+    }
+
+    async fn do_send_to_until<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+        kind: MessageKind,
+        deadline: Option<Instant>,
+    ) -> Result<(), TrySendError<M>> {
+        let envelope = self.envelope_to(recipient, message, kind);
+        let entry = self.book.get_owned(recipient);
+        self.send_to_object_until(entry.as_deref(), recipient, envelope, deadline)
+            .await
+    }
+
+    /// Accounts, traces and dumps a message sent to the specified recipient.
+    fn envelope_to<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+        kind: MessageKind,
+    ) -> Envelope<M> {
+        self.stats.on_sent_message(&message);
+
+        trace!(to = %recipient, "> {:?}", message);
         let envelope = Envelope::new(message, kind);
-        let fut = object.send(self, recipient, envelope.upcast());
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
+        }
+
+        envelope
+    }
+
+    /// Sends an envelope to the resolved recipient, `None` means no route.
+    async fn send_to_object_until<M: Message>(
+        &self,
+        object: Option<&Object>,
+        recipient: Addr,
+        envelope: Envelope<M>,
+        deadline: Option<Instant>,
+    ) -> Result<(), TrySendError<M>> {
+        let object = ward!(
+            object,
+            return Err(TrySendError::NoRoute(envelope.into_message()))
+        );
+        let fut = object.send_until(self, recipient, envelope.upcast(), deadline);
         let result = fut.await;
-        result.map_err(|err| SendError(e2m(err.0)))
+        result.map_err(|err| err.map(e2m))
+    }
+
+    /// Sends a message to the specified recipient, but waits for capacity of
+    /// its mailbox no longer than the provided timeout.
+    ///
+    /// Returns `Err(MailboxFull(_))` if the mailbox is still full after the
+    /// timeout, see [`Context::send_timeout()`] for details.
+    pub async fn send_to_timeout<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+        timeout: Duration,
+    ) -> Result<(), TrySendError<M>> {
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+        let deadline = Instant::now() + timeout;
+        self.do_send_to_until(recipient, message, kind, Some(deadline))
+            .await
     }
 
     /// Tries to send a message to the specified recipient.
@@ -392,36 +869,174 @@ impl<C, K> Context<C, K> {
         recipient: Addr,
         message: M,
     ) -> Result<(), TrySendError<M>> {
-        self.stats.on_sent_message(&message);
+        let kind = MessageKind::Regular {
+            sender: self.actor_addr,
+        };
+
+        let envelope = self.envelope_to(recipient, message, kind);
+        let entry = self.book.get(recipient);
+        try_send_to_object(entry.as_deref(), recipient, envelope)
+    }
 
+    /// Sends a message to the specified recipient without waiting for capacity
+    /// of its mailbox.
+    ///
+    /// See [`Context::unbounded_send()`] for details.
+    pub fn unbounded_send_to<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+    ) -> Result<(), SendError<M>> {
         let kind = MessageKind::Regular {
             sender: self.actor_addr,
         };
 
+        self.stats.on_sent_message(&message);
+
         trace!(to = %recipient, "> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
+        let envelope = Envelope::new(message, kind);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
         }
 
-        let entry = self.book.get(recipient);
-        let object = ward!(entry, return Err(TrySendError::Closed(message)));
-        let envelope = Envelope::new(message, kind);
+        let entry = self.book.get_owned(recipient);
+        let object = ward!(
+            entry,
+            return Err(SendError::NoRoute(envelope.into_message()))
+        );
+        let envelope = envelope.upcast();
 
-        object
-            .try_send(recipient, envelope.upcast())
+        self.do_unbounded_send(object, recipient, envelope)
             .map_err(|err| err.map(e2m))
     }
 
+    fn do_unbounded_send(
+        &self,
+        object: ObjectArc,
+        recipient: Addr,
+        envelope: Envelope,
+    ) -> Result<(), SendError<Envelope>> {
+        // Keep the order of messages if previous ones are still queued.
+        let envelope = match self.book.overflows().enqueue(&object, recipient, envelope) {
+            Ok(()) => return Ok(()),
+            Err(envelope) => envelope,
+        };
+
+        match object.try_send(recipient, envelope) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::MailboxFull(envelope)) => {
+                unbounded::deliver_later(self.pruned(), object, recipient, envelope);
+                Ok(())
+            }
+            Err(err) => Err(err.into_send_error()),
+        }
+    }
+
+    /// Returns a handle to send messages to the specified recipient
+    /// repeatedly without resolving it on every call.
+    ///
+    /// See [`SendHandle`] for details.
+    pub fn send_handle(&self, recipient: Addr) -> SendHandle {
+        SendHandle::new(&self.book, recipient)
+    }
+
+    /// Returns the actor's [`ActorId`], which, unlike [`Addr`], can be
+    /// embedded in messages and sent to other nodes.
+    pub fn actor_id(&self) -> ActorId {
+        let actor = self.actor.as_ref().and_then(|o| o.as_actor());
+        let key = actor.map_or("", |actor| &actor.meta().key);
+        ActorId::new(self.actor_addr, self.book.node_no().get(), key)
+    }
+
+    /// Resolves the [`ActorId`] to a handle to send messages to the actor.
+    ///
+    /// Returns `None` if the actor has finished or restarted since the id was
+    /// obtained, or if there is no route to its node.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // The callback is passed as `ctx.actor_id()` by a subscriber.
+    /// if let Some(mut handle) = ctx.resolve_actor_id(callback) {
+    ///     handle.send(&ctx, SomethingHappened).await?;
+    /// }
+    /// ```
+    pub fn resolve_actor_id(&self, id: ActorId) -> Option<SendHandle> {
+        id.resolve(&self.book).map(|addr| self.send_handle(addr))
+    }
+
+    /// Forwards the received envelope using the routing system as is,
+    /// keeping its sender and trace id.
+    ///
+    /// If the envelope contains a request, the response goes directly to the
+    /// original requester, even if it's located on another node. Thus, it's
+    /// the way to build proxies without handling responses manually.
+    ///
+    /// # Example
+    /// ```ignore
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     let _ = ctx.forward(envelope).await;
+    /// }
+    /// ```
+    pub async fn forward(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
+        trace!("> forwarded {}", envelope.message().name());
+        let addrs = self.demux.filter(&envelope);
+
+        self.send_envelope_until(envelope, &addrs, None)
+            .await
+            .map_err(TrySendError::into_send_error)
+    }
+
+    /// Forwards the received envelope to the specified recipient as is.
+    /// The recipient can be either a local actor or a remote one.
+    ///
+    /// See [`Context::forward()`] for details.
+    pub async fn forward_to(
+        &self,
+        recipient: Addr,
+        envelope: Envelope,
+    ) -> Result<(), SendError<Envelope>> {
+        trace!(to = %recipient, "> forwarded {}", envelope.message().name());
+        let entry = self.book.get_owned(recipient);
+        let object = ward!(entry, return Err(SendError::NoRoute(envelope)));
+        object.send(self, recipient, envelope).await
+    }
+
     /// Responds to the requester with the provided response.
     ///
     /// The token can be used only once.
     ///
-    /// ```ignore
-    /// msg!(match envelope {
-    ///     (SomeRequest, token) => {
-    ///         ctx.respond(token, SomeResponse);
-    ///     }
-    /// })
+    /// The token provided by `msg!` for the `(SomeRequest, token)` pattern is
+    /// typed by the request, so the response type is checked at compile time.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{message, msg};
+    /// #[message(ret = u64)]
+    /// struct GetCount;
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         (GetCount, token) => ctx.respond(token, 42),
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// A response of another type is rejected:
+    /// ```compile_fail,E0308
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{message, msg};
+    /// # #[message(ret = u64)]
+    /// # struct GetCount;
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         (GetCount, token) => ctx.respond(token, "42"),
+    ///     });
+    /// }
+    /// # }
     /// ```
     pub fn respond<R: Request>(&self, token: ResponseToken<R>, message: R::Response) {
         if token.is_forgotten() {
@@ -439,11 +1054,12 @@ impl<C, K> Context<C, K> {
         };
 
         trace!(to = %recipient, "> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
+        let envelope = Envelope::new(message, kind);
+        if let Some(permit) = DUMPER.acquire_m(envelope.message()) {
+            permit.record(Dump::envelope(&envelope, Direction::Out));
         }
 
-        let envelope = Envelope::new(message, kind).upcast();
+        let envelope = envelope.upcast();
         let object = ward!(self.book.get(recipient));
         object.respond(token, Ok(envelope));
     }
@@ -485,6 +1101,16 @@ impl<C, K> Context<C, K> {
     where
         C: 'static,
     {
+        // The previous call has been cancelled while waiting for a permit.
+        if let Some(envelope) = self.concurrency.take_held() {
+            let envelope = self.concurrency.admit(envelope).await;
+            self.start_handling(&envelope);
+            return Some(envelope);
+        }
+
+        self.finish_message_span();
+        self.finish_handling();
+
         'outer: loop {
             // TODO: reset if the mailbox is empty.
             self.budget.acquire().await;
@@ -492,6 +1118,7 @@ impl<C, K> Context<C, K> {
             self.pre_recv();
 
             let envelope = 'received: {
+                let idle_fut = self.idle.wait();
                 let mailbox_fut = self.actor.as_ref()?.as_actor()?.recv();
                 pin_mut!(mailbox_fut);
 
@@ -511,15 +1138,74 @@ impl<C, K> Context<C, K> {
                         let envelope = ward!(option, continue 'outer);
                         break 'received envelope;
                     },
+                    _ = idle_fut, if self.idle.is_armed() => {
+                        scope::set_trace_id(TraceId::generate());
+                        scope::set_baggage(Baggage::default());
+                        scope::set_message_id(None);
+                        let kind = MessageKind::Regular { sender: Addr::NULL };
+                        break 'received Envelope::new(messages::IdleTimeout, kind).upcast();
+                    },
                 }
             };
 
             if let Some(envelope) = self.post_recv(envelope) {
+                self.start_message_span(&envelope);
+                // Waiting for a permit doesn't count toward the handling timeout.
+                let envelope = self.concurrency.admit(envelope).await;
+                self.start_handling(&envelope);
                 return Some(envelope);
             }
         }
     }
 
+    /// Receives the next envelope like [`Context::recv()`] or waits for the
+    /// provided future, whichever is ready first. Branches are polled in
+    /// random order, so neither of them starves the other one.
+    ///
+    /// Unlike manual `select!` against `recv()`, system messages are handled
+    /// as usual and the mailbox's closing is reported by returning `None`,
+    /// so `Terminate` isn't missed.
+    ///
+    /// The future is dropped if an envelope is received first. To keep
+    /// long-running futures between calls, pass `&mut fut` of a pinned one.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe if the future is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn connect() -> Result<(), ()> { Ok(()) }
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{msg, RecvOr};
+    /// # #[elfo::message] struct SomethingHappened;
+    /// let mut connecting = Box::pin(connect());
+    ///
+    /// while let Some(input) = ctx.recv_or(&mut connecting).await {
+    ///     match input {
+    ///         RecvOr::Envelope(envelope) => msg!(match envelope {
+    ///             SomethingHappened => { /* ... */ },
+    ///         }),
+    ///         RecvOr::Future(_result) => {
+    ///             // Connected or failed, reconnect.
+    ///             connecting = Box::pin(connect());
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn recv_or<F: Future>(&mut self, future: F) -> Option<RecvOr<F::Output>>
+    where
+        C: 'static,
+    {
+        tokio::select! {
+            envelope = self.recv() => envelope.map(RecvOr::Envelope),
+            output = future => Some(RecvOr::Future(output)),
+        }
+    }
+
     /// Receives the next envelope from the mailbox or sources without waiting.
     /// If the envelope isn't available, `Err(TryRecvError::Empty)` is returned.
     /// If the mailbox is closed, `Err(TryRecvError::Closed)` is returned.
@@ -577,12 +1263,29 @@ impl<C, K> Context<C, K> {
     where
         C: 'static,
     {
+        if self.concurrency.has_held() {
+            let envelope = self
+                .concurrency
+                .try_take_held()
+                .ok_or(TryRecvError::Empty)?;
+            self.start_handling(&envelope);
+            return Ok(envelope);
+        }
+
+        self.finish_message_span();
+        self.finish_handling();
+
         #[allow(clippy::never_loop)] // false positive
         loop {
             self.budget.acquire().await;
 
             self.pre_recv();
 
+            // Messages are considered unavailable until a permit is acquired.
+            if !self.concurrency.try_acquire() {
+                return Err(TryRecvError::Empty);
+            }
+
             let envelope = 'received: {
                 let actor = ward!(
                     self.actor.as_ref().and_then(|o| o.as_actor()),
@@ -619,13 +1322,312 @@ impl<C, K> Context<C, K> {
             };
 
             if let Some(envelope) = self.post_recv(envelope) {
+                self.start_message_span(&envelope);
+                self.start_handling(&envelope);
                 return Ok(envelope);
             }
         }
     }
 
+    /// Receives envelopes in a batch: waits for the first one like
+    /// [`Context::recv()`] and then moves up to `limit - 1` more envelopes,
+    /// which are already in the mailbox, without waiting. Envelopes are
+    /// appended to `buf`. Returns the number of received envelopes or `0` if
+    /// the mailbox is closed.
+    ///
+    /// Useful for hot actors consuming a lot of messages, because the cost of
+    /// receiving is amortized over the batch. Message spans and handling
+    /// timeouts aren't applied to batches.
+    ///
+    /// # Batches
+    ///
+    /// * The batch is bounded by the actor's budget, so the actor still returns
+    ///   the execution back to the runtime periodically.
+    /// * The whole batch is handled under one permit of the group's concurrency
+    ///   limit, see `ActorGroup::concurrency_limit()`.
+    /// * Envelopes of the batch can belong to different traces, so the scope
+    ///   isn't attributed to any of them: if the batch contains several
+    ///   envelopes, a new trace is started. Use [`Context::set_scope()`] to
+    ///   handle envelopes one by one in their own traces.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is zero or if the method is called again after `0` is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # fn write_all(_batch: impl Iterator<Item = elfo::Envelope>) {}
+    /// let mut batch = Vec::new();
+    ///
+    /// while ctx.recv_many(&mut batch, 1024).await > 0 {
+    ///     write_all(batch.drain(..));
+    /// }
+    /// # }
+    /// ```
+    pub async fn recv_many(&mut self, buf: &mut Vec<Envelope>, limit: usize) -> usize
+    where
+        C: 'static,
+    {
+        assert!(limit > 0, "limit must be positive");
+
+        let envelope = ward!(self.recv().await, return 0);
+        self.finish_message_span();
+        self.finish_handling();
+
+        let len = buf.len();
+        buf.push(envelope);
+        self.drain_mailbox(buf, limit - 1);
+        buf.len() - len
+    }
+
+    /// Receives up to `max` envelopes, which are already available, without
+    /// waiting. Like [`Context::try_recv()`], returns
+    /// `Err(TryRecvError::Empty)` if there are no envelopes and
+    /// `Err(TryRecvError::Closed)` if the mailbox is closed.
+    ///
+    /// Useful for aggregating actors (metric rollups, DB writers and so on),
+    /// which issue one bulk operation per batch. Message spans and handling
+    /// timeouts aren't applied to batches. Batches are limited and traced as
+    /// described in [`Context::recv_many()`].
+    ///
+    /// # Panics
+    ///
+    /// If `max` is zero or if the method is called again after
+    /// `Err(TryRecvError::Closed)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # fn insert_all(_batch: Vec<elfo::Envelope>) {}
+    /// loop {
+    ///     match ctx.try_recv_batch(1000).await {
+    ///         Ok(batch) => insert_all(batch),
+    ///         Err(err) if err.is_empty() => {
+    ///             // Wait for the next batch.
+    ///             match ctx.recv().await {
+    ///                 Some(envelope) => insert_all(vec![envelope]),
+    ///                 None => break,
+    ///             }
+    ///         }
+    ///         Err(_) => break,
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn try_recv_batch(&mut self, max: usize) -> Result<Vec<Envelope>, TryRecvError>
+    where
+        C: 'static,
+    {
+        assert!(max > 0, "max must be positive");
+
+        let envelope = self.try_recv().await?;
+        self.finish_message_span();
+        self.finish_handling();
+
+        let mut batch = vec![envelope];
+        self.drain_mailbox(&mut batch, max - 1);
+        Ok(batch)
+    }
+
+    /// Sets the current scope (the trace id, the baggage and the message id)
+    /// to the envelope's one, so messages sent after that are attributed to
+    /// the envelope. Useful to handle envelopes of batches received by
+    /// [`Context::recv_many()`] and [`Context::try_recv_batch()`] one by one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{message, msg};
+    /// # #[message]
+    /// # struct SomethingHappened;
+    /// let mut batch = Vec::new();
+    ///
+    /// while ctx.recv_many(&mut batch, 1024).await > 0 {
+    ///     for envelope in batch.drain(..) {
+    ///         ctx.set_scope(&envelope);
+    ///
+    ///         msg!(match envelope {
+    ///             SomethingHappened => { /* ... */ },
+    ///         });
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn set_scope(&self, envelope: &Envelope) {
+        scope::set_trace_id(envelope.trace_id());
+        scope::set_baggage(envelope.baggage().clone());
+        scope::set_message_id(Some(envelope.message_id()));
+    }
+
+    /// Moves up to `limit` envelopes, which are already in the mailbox,
+    /// to `buf`. Closing of the mailbox is handled by the next `recv()`.
+    ///
+    /// The permit of the concurrency limit, acquired for the first envelope
+    /// of the batch, is held until the next `recv()`, so the batch is
+    /// admitted as a whole.
+    fn drain_mailbox(&mut self, buf: &mut Vec<Envelope>, limit: usize)
+    where
+        C: 'static,
+    {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        let mut batch = Vec::new();
+
+        // Every envelope consumes the budget in `post_recv()`.
+        let limit = limit.min(self.budget.remaining());
+
+        if limit == 0
+            || !matches!(
+                actor.try_recv_many(&mut batch, limit),
+                Some(RecvResult::Data(_))
+            )
+        {
+            return;
+        }
+
+        let len = buf.len();
+        for envelope in batch {
+            if let Some(envelope) = self.post_recv(envelope) {
+                buf.push(envelope);
+            }
+        }
+
+        // `post_recv()` leaves the scope of the last envelope, but the batch
+        // isn't attributed to any of them.
+        if buf.len() > len {
+            scope::set_trace_id(TraceId::generate());
+            scope::set_baggage(Baggage::default());
+            scope::set_message_id(None);
+        }
+    }
+
+    /// Handles pending control messages without waiting for new ones.
+    /// Intended for source actors producing messages instead of consuming
+    /// them (file tailers, feed readers and so on), that don't want to
+    /// `select!` against the mailbox manually.
+    ///
+    /// Configs are updated (available by [`Context::config()`]), pings are
+    /// responded and `ValidateConfig` is discarded as usual. Other messages
+    /// are dropped with a warning.
+    ///
+    /// Returns `false` once the actor should be finished: `Terminate` has
+    /// been received or the mailbox is closed.
+    ///
+    /// # Budget
+    ///
+    /// Like [`Context::try_recv()`], the method returns the execution back to
+    /// the runtime once the actor's budget has been exhausted, so it can be
+    /// called on every iteration of a hot loop.
+    ///
+    /// # Panics
+    ///
+    /// If the method is called again after the mailbox is closed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # #[elfo::message] struct Line(String);
+    /// # async fn read_line() -> String { String::new() }
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// while ctx.poll_control().await {
+    ///     let line = read_line().await;
+    ///     let _ = ctx.send(Line(line)).await;
+    /// }
+    /// # }
+    /// ```
+    pub async fn poll_control(&mut self) -> bool
+    where
+        C: 'static,
+    {
+        loop {
+            let envelope = match self.try_recv().await {
+                Ok(envelope) => envelope,
+                Err(err) => return err.is_empty(),
+            };
+
+            msg!(match envelope {
+                messages::Terminate => return false,
+                messages::ConfigUpdated => {}
+                (messages::ValidateConfig { .. }, token) => drop(token),
+                envelope => {
+                    let message = envelope.message();
+                    warn!(
+                        message = message.name(),
+                        "message is dropped by the source actor"
+                    );
+                }
+            });
+        }
+    }
+
+    /// Returns the execution back to the runtime, so other actors on the same
+    /// runtime can make progress. Also resets the actor's budget.
+    ///
+    /// Useful inside handlers doing a lot of work without `.await` points,
+    /// e.g. handling a giant batch. See also [`Context::for_each_chunked()`]
+    /// and `system.overload.max_busy_time` to detect such handlers.
+    pub async fn yield_now(&mut self) {
+        self.budget = Budget::default();
+        tokio::task::yield_now().await;
+    }
+
+    /// Calls `f` for every item, yielding to the runtime (see
+    /// [`Context::yield_now()`]) after every `chunk_size` items, so handling a
+    /// giant batch doesn't starve other actors.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # #[elfo::message] struct Rows(Vec<u64>);
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::msg;
+    /// let mut sum = 0;
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         Rows(rows) => ctx.for_each_chunked(rows, 1000, |row| sum += row).await,
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    pub async fn for_each_chunked<T>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+        chunk_size: usize,
+        mut f: impl FnMut(T),
+    ) {
+        assert!(chunk_size > 0, "chunk size must be positive");
+
+        let mut items = items.into_iter().peekable();
+        loop {
+            for item in items.by_ref().take(chunk_size) {
+                f(item);
+            }
+
+            if items.peek().is_none() {
+                break;
+            }
+
+            self.yield_now().await;
+        }
+    }
+
     fn pre_recv(&mut self) {
         self.stats.on_recv();
+        self.concurrency.release();
 
         if unlikely(self.stage == Stage::Closed) {
             panic!("calling `recv()` or `try_recv()` after `None` is returned, an infinite loop?");
@@ -638,6 +1640,21 @@ impl<C, K> Context<C, K> {
             }
             self.stage = Stage::Working;
         }
+
+        if unlikely(self.idle.is_expired()) {
+            self.on_idle_expired();
+        }
+    }
+
+    #[cold]
+    fn on_idle_expired(&mut self) {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+
+        // Available messages are still handled, but the actor isn't restarted.
+        if actor.close() {
+            info!("idle actor is closed");
+            actor.set_restart_policy(Some(RestartPolicy::never()));
+        }
     }
 
     fn post_recv(&mut self, envelope: Envelope) -> Option<Envelope>
@@ -647,6 +1664,13 @@ impl<C, K> Context<C, K> {
         self.budget.decrement();
 
         scope::set_trace_id(envelope.trace_id());
+        scope::set_baggage(envelope.baggage().clone());
+        scope::set_message_id(Some(envelope.message_id()));
+
+        if unlikely(envelope.is_expired()) {
+            on_expired(&envelope);
+            return None;
+        }
 
         let envelope = msg!(match envelope {
             (messages::UpdateConfig { config }, token) => {
@@ -666,8 +1690,7 @@ impl<C, K> Context<C, K> {
         let message = envelope.message();
         trace!("< {:?}", message);
         if let Some(permit) = DUMPER.acquire_m(message) {
-            let kind = envelope.message_kind();
-            permit.record(Dump::message(message, kind, Direction::In));
+            permit.record(Dump::envelope(&envelope, Direction::In));
         }
 
         // We should change the status after dumping the original message
@@ -676,15 +1699,62 @@ impl<C, K> Context<C, K> {
             self.set_status(ActorStatus::TERMINATING);
         }
 
-        self.stats.on_received_envelope(&envelope);
+        self.stats.on_received_envelope(&envelope, &self.book);
+
+        if let Some(actor) = self.actor.as_ref().and_then(|o| o.as_actor()) {
+            let waiting_time = envelope.queued_for();
+            let config = scope::with(|scope| scope.overload());
+            self.overload.on_received(actor, &config, waiting_time);
+        }
 
-        msg!(match envelope {
+        let envelope = msg!(match envelope {
             (messages::Ping, token) => {
                 self.respond(token, ());
-                None
+                return None;
             }
-            envelope => Some(envelope),
-        })
+            envelope => envelope,
+        });
+
+        if envelope.is::<messages::IdleTimeout>() {
+            self.idle.expire();
+        } else {
+            self.idle.reset();
+        }
+
+        Some(envelope)
+    }
+
+    fn start_message_span(&self, envelope: &Envelope) {
+        if !scope::with(|scope| scope.tracing().message_spans) {
+            return;
+        }
+
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        let message = envelope.message();
+        actor.message_span().start(error_span!(
+            "message",
+            message = message.name(),
+            protocol = message.protocol(),
+            trace_id = %envelope.trace_id(),
+            message_id = u64::from(envelope.message_id()),
+            parent_id = envelope.parent_id().map(u64::from),
+        ));
+    }
+
+    fn finish_message_span(&self) {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        actor.message_span().finish();
+    }
+
+    fn start_handling(&self, envelope: &Envelope) {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        let config = scope::with(|scope| scope.handling());
+        actor.handling().start(envelope.message(), &config);
+    }
+
+    fn finish_handling(&self) {
+        let actor = ward!(self.actor.as_ref().and_then(|o| o.as_actor()));
+        actor.handling().finish();
     }
 
     /// This is a part of private API for now.
@@ -725,7 +1795,10 @@ impl<C, K> Context<C, K> {
             sources: Sources::new(),
             stage: self.stage,
             stats: Stats::empty(),
+            overload: OverloadDetector::new(),
             budget: self.budget.clone(),
+            idle: IdleTimer::default(),
+            concurrency: ConcurrencyLimit::default(),
         }
     }
 
@@ -747,7 +1820,10 @@ impl<C, K> Context<C, K> {
             sources: self.sources,
             stage: self.stage,
             stats: self.stats,
+            overload: self.overload,
             budget: self.budget,
+            idle: self.idle,
+            concurrency: self.concurrency,
         }
     }
 
@@ -764,6 +1840,16 @@ impl<C, K> Context<C, K> {
         self
     }
 
+    pub(crate) fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle = IdleTimer::new(timeout);
+        self
+    }
+
+    pub(crate) fn with_concurrency_limit(mut self, semaphore: Option<Arc<Semaphore>>) -> Self {
+        self.concurrency = ConcurrencyLimit::new(semaphore);
+        self
+    }
+
     pub(crate) fn with_key<K1>(self, key: K1) -> Context<C, K1> {
         Context {
             book: self.book,
@@ -776,16 +1862,36 @@ impl<C, K> Context<C, K> {
             sources: self.sources,
             stage: self.stage,
             stats: self.stats,
+            overload: self.overload,
             budget: self.budget,
+            idle: self.idle,
+            concurrency: self.concurrency,
         }
     }
 }
 
+/// Tries to send an envelope to the resolved recipient, `None` means no
+/// route.
+fn try_send_to_object<M: Message>(
+    object: Option<&Object>,
+    recipient: Addr,
+    envelope: Envelope<M>,
+) -> Result<(), TrySendError<M>> {
+    let object = ward!(
+        object,
+        return Err(TrySendError::NoRoute(envelope.into_message()))
+    );
+
+    object
+        .try_send(recipient, envelope.upcast())
+        .map_err(|err| err.map(e2m))
+}
+
 fn e2m<M: Message>(envelope: Envelope) -> M {
-    envelope
-        .unpack_regular()
-        .downcast()
-        .expect("invalid message")
+    // Unsent requests are returned too, requesters cancel them on their own.
+    let (message, token) = envelope.unpack_request();
+    token.forget();
+    message.downcast().expect("invalid message")
 }
 
 #[cold]
@@ -797,6 +1903,17 @@ fn on_input_closed(stage: &mut Stage, actor: &Actor) {
     trace!("input closed");
 }
 
+#[cold]
+fn on_expired(envelope: &Envelope) {
+    let message = envelope.message();
+    trace!(age = ?envelope.queued_for(), "< expired {:?}", message);
+
+    if let Some(recorder) = metrics::try_recorder() {
+        let key = Key::from_static_parts("elfo_expired_messages_total", message.labels());
+        recorder.increment_counter(&key, 1);
+    }
+}
+
 fn addrs_with_envelope(
     envelope: Envelope,
     addrs: &[Addr],
@@ -838,7 +1955,10 @@ impl Context {
             sources: Sources::new(),
             stage: Stage::PreRecv,
             stats: Stats::empty(),
+            overload: OverloadDetector::new(),
             budget: Budget::default(),
+            idle: IdleTimer::default(),
+            concurrency: ConcurrencyLimit::default(),
         }
     }
 }
@@ -857,7 +1977,10 @@ impl<C, K: Clone> Clone for Context<C, K> {
             sources: Sources::new(),
             stage: self.stage,
             stats: Stats::empty(),
+            overload: OverloadDetector::new(),
             budget: self.budget.clone(),
+            idle: IdleTimer::default(),
+            concurrency: ConcurrencyLimit::default(),
         }
     }
 }
@@ -906,6 +2029,9 @@ impl<'c, C, K, R, M> RequestBuilder<'c, C, K, R, M> {
 // TODO: add `pub async fn id() { ... }`
 impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, Any> {
     /// Waits for the response.
+    ///
+    /// Idempotent requests routed to multiple recipients are hedged,
+    /// see `system.hedging`.
     pub async fn resolve(self) -> Result<R::Response, RequestError> {
         // TODO: cache `OwnedEntry`?
         let this = self.context.actor_addr;
@@ -916,22 +2042,60 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, Any> {
                 .request_table()
                 .new_request(self.context.book.clone(), scope::trace_id(), false);
         let request_id = token.request_id();
+        let _guard = actor.request_table().cancel_on_drop(request_id);
         let kind = MessageKind::RequestAny(token);
+        let labels = self.request.labels();
+        let start = Instant::now();
 
-        let res = if let Some(recipient) = self.to {
-            self.context.do_send_to(recipient, self.request, kind).await
-        } else {
-            self.context.do_send(self.request, kind).await
+        let hedging = (R::IS_IDEMPOTENT && self.to.is_none())
+            .then(|| scope::with(|scope| scope.hedging()))
+            .filter(|config| !config.disabled);
+
+        let Some(config) = hedging else {
+            let res = if let Some(recipient) = self.to {
+                self.context.do_send_to(recipient, self.request, kind).await
+            } else {
+                self.context.do_send(self.request, kind).await
+            };
+
+            if let Err(err) = res {
+                actor.request_table().cancel_request(request_id);
+                return Err(RequestError::NotSent(err.map(drop)));
+            }
+
+            let mut responses = actor.request_table().wait(request_id).await;
+            debug_assert_eq!(responses.len(), 1);
+            let response = responses.pop().expect("missing response");
+            stats::on_response(labels, start.elapsed(), &response, &self.context.book);
+            return prepare_response::<R>(response);
         };
 
-        if res.is_err() {
-            actor.request_table().cancel_request(request_id);
-            return Err(RequestError::Failed);
+        let type_id = TypeId::of::<R>();
+
+        let response = match actor.request_latencies().delay(type_id, &config) {
+            Some(delay) => {
+                self.context
+                    .do_request_hedged(self.request, kind, actor, delay)
+                    .await
+            }
+            // Not enough latencies yet, so send as usual.
+            None => {
+                if let Err(err) = self.context.do_send(self.request, kind).await {
+                    actor.request_table().cancel_request(request_id);
+                    return Err(RequestError::NotSent(err.map(drop)));
+                }
+
+                let mut responses = actor.request_table().wait(request_id).await;
+                responses.pop().expect("missing response")
+            }
+        };
+
+        if response.is_ok() {
+            actor.request_latencies().push(type_id, start.elapsed());
         }
 
-        let mut responses = actor.request_table().wait(request_id).await;
-        debug_assert_eq!(responses.len(), 1);
-        prepare_response::<R>(responses.pop().expect("missing response"))
+        stats::on_response(labels, start.elapsed(), &response, &self.context.book);
+        prepare_response::<R>(response)
     }
 }
 
@@ -947,7 +2111,10 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, All> {
                 .request_table()
                 .new_request(self.context.book.clone(), scope::trace_id(), true);
         let request_id = token.request_id();
+        let _guard = actor.request_table().cancel_on_drop(request_id);
         let kind = MessageKind::RequestAll(token);
+        let labels = self.request.labels();
+        let start = Instant::now();
 
         let res = if let Some(recipient) = self.to {
             self.context.do_send_to(recipient, self.request, kind).await
@@ -955,9 +2122,9 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, All> {
             self.context.do_send(self.request, kind).await
         };
 
-        if res.is_err() {
+        if let Err(err) = res {
             actor.request_table().cancel_request(request_id);
-            return vec![Err(RequestError::Failed)];
+            return vec![Err(RequestError::NotSent(err.map(drop)))];
         }
 
         actor
@@ -965,7 +2132,10 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, All> {
             .wait(request_id)
             .await
             .into_iter()
-            .map(prepare_response::<R>)
+            .map(|response| {
+                stats::on_response(labels, start.elapsed(), &response, &self.context.book);
+                prepare_response::<R>(response)
+            })
             .collect()
     }
 }
@@ -979,8 +2149,7 @@ fn prepare_response<R: Request>(
     // TODO: increase a counter.
     trace!("< {:?}", message);
     if let Some(permit) = DUMPER.acquire_m(message) {
-        let kind = envelope.message_kind();
-        permit.record(Dump::message(message, kind, Direction::In));
+        permit.record(Dump::envelope(&envelope, Direction::In));
     }
 
     Ok(envelope.unpack_regular().downcast2::<R::Wrapper>().into())
@@ -17,6 +17,11 @@ impl Budget {
         }
     }
 
+    /// How many messages can be received before yielding.
+    pub(crate) fn remaining(&self) -> usize {
+        usize::from(self.0)
+    }
+
     pub(crate) fn decrement(&mut self) {
         // We use a saturating operation here because `try_recv()`
         // can be called many times without calling `Budget::acquire()`.
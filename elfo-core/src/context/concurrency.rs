@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::envelope::Envelope;
+
+/// Bounds the number of actors of a group handling messages simultaneously,
+/// see `ActorGroup::concurrency_limit()`.
+///
+/// The actor holds a permit from returning a message by `recv()` or
+/// `try_recv()` until the next call of them.
+#[derive(Default)]
+pub(crate) struct ConcurrencyLimit {
+    /// `None` if disabled.
+    semaphore: Option<Arc<Semaphore>>,
+    permit: Option<OwnedSemaphorePermit>,
+    /// The message received while waiting for a permit. Kept here to make
+    /// `recv()` cancel safe. Never locked, it's only to keep `Context: Sync`.
+    held: Mutex<Option<Envelope>>,
+}
+
+impl ConcurrencyLimit {
+    pub(crate) fn new(semaphore: Option<Arc<Semaphore>>) -> Self {
+        Self {
+            semaphore,
+            permit: None,
+            held: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.permit = None;
+    }
+
+    pub(crate) fn has_held(&mut self) -> bool {
+        self.held.get_mut().is_some()
+    }
+
+    pub(crate) fn take_held(&mut self) -> Option<Envelope> {
+        self.held.get_mut().take()
+    }
+
+    /// Returns the held message if a permit is available.
+    pub(crate) fn try_take_held(&mut self) -> Option<Envelope> {
+        if self.has_held() && self.try_acquire() {
+            self.take_held()
+        } else {
+            None
+        }
+    }
+
+    /// Waits for a permit to return the message.
+    pub(crate) async fn admit(&mut self, envelope: Envelope) -> Envelope {
+        let semaphore = ward!(&self.semaphore, return envelope).clone();
+
+        *self.held.get_mut() = Some(envelope);
+        let permit = semaphore.acquire_owned().await.expect("never closed");
+        self.permit = Some(permit);
+        self.take_held().expect("just held")
+    }
+
+    /// Returns `false` if there is no available permit.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        let semaphore = ward!(&self.semaphore, return true);
+
+        if self.permit.is_none() {
+            let permit = ward!(semaphore.clone().try_acquire_owned().ok(), return false);
+            self.permit = Some(permit);
+        }
+
+        true
+    }
+}
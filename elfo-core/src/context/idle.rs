@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Tracks the time since the last received message, see
+/// `ActorGroup::idle_timeout()`.
+pub(crate) struct IdleTimer {
+    /// `None` if disabled.
+    timeout: Option<Duration>,
+    deadline: Instant,
+    /// Set once `IdleTimeout` is delivered until the actor is active again.
+    is_expiring: bool,
+}
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl IdleTimer {
+    pub(crate) fn new(timeout: Option<Duration>) -> Self {
+        let mut timer = Self {
+            timeout,
+            deadline: Instant::now(),
+            is_expiring: false,
+        };
+        timer.reset();
+        timer
+    }
+
+    /// Returns `true` if the timer should be polled.
+    pub(crate) fn is_armed(&self) -> bool {
+        self.timeout.is_some() && !self.is_expiring
+    }
+
+    pub(crate) async fn wait(&self) {
+        tokio::time::sleep_until(self.deadline).await
+    }
+
+    /// Called when `IdleTimeout` is delivered.
+    pub(crate) fn expire(&mut self) {
+        self.is_expiring = true;
+    }
+
+    /// Returns `true` if `IdleTimeout` has been delivered and the actor has
+    /// been inactive since that.
+    pub(crate) fn is_expired(&self) -> bool {
+        self.is_expiring
+    }
+
+    pub(crate) fn reset(&mut self) {
+        if let Some(timeout) = self.timeout {
+            self.deadline = Instant::now() + timeout;
+            self.is_expiring = false;
+        }
+    }
+}
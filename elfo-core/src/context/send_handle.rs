@@ -0,0 +1,103 @@
+use super::{try_send_to_object, Context};
+use crate::{
+    addr::Addr,
+    address_book::AddressBook,
+    envelope::MessageKind,
+    errors::{SendError, TrySendError},
+    message::Message,
+    object::{Object, ObjectArc},
+};
+
+/// A handle to send messages to the same recipient repeatedly.
+///
+/// [`Context::send_to()`] resolves the recipient on every call. This handle
+/// caches the resolved entry instead and revalidates it only if the entry
+/// has been removed since the last resolution. It's useful for hot
+/// producer-consumer pairs.
+///
+/// The behaviour is the same as for [`Context::send_to()`] and
+/// [`Context::try_send_to()`] otherwise.
+///
+/// # Example
+/// ```ignore
+/// let mut handle = ctx.send_handle(addr);
+///
+/// for value in values {
+///     handle.send(&ctx, SomethingHappened { value }).await?;
+/// }
+/// ```
+pub struct SendHandle {
+    recipient: Addr,
+    cached: Option<ObjectArc>,
+}
+
+assert_impl_all!(SendHandle: Send, Sync);
+
+impl SendHandle {
+    pub(super) fn new(book: &AddressBook, recipient: Addr) -> Self {
+        let mut handle = Self {
+            recipient,
+            cached: None,
+        };
+        handle.resolve(book);
+        handle
+    }
+
+    /// Returns the recipient's address.
+    #[inline]
+    pub fn addr(&self) -> Addr {
+        self.recipient
+    }
+
+    /// Sends a message to the recipient.
+    ///
+    /// Returns `Err` if the message hasn't reached any mailboxes.
+    pub async fn send<C, K, M: Message>(
+        &mut self,
+        ctx: &Context<C, K>,
+        message: M,
+    ) -> Result<(), SendError<M>> {
+        let recipient = self.recipient;
+        let kind = MessageKind::Regular {
+            sender: ctx.actor_addr,
+        };
+
+        let envelope = ctx.envelope_to(recipient, message, kind);
+        let object = self.resolve(&ctx.book);
+        ctx.send_to_object_until(object, recipient, envelope, None)
+            .await
+            .map_err(TrySendError::into_send_error)
+    }
+
+    /// Tries to send a message to the recipient.
+    ///
+    /// Returns `Err` if the message hasn't reached mailboxes or they are full.
+    pub fn try_send<C, K, M: Message>(
+        &mut self,
+        ctx: &Context<C, K>,
+        message: M,
+    ) -> Result<(), TrySendError<M>> {
+        let recipient = self.recipient;
+        let kind = MessageKind::Regular {
+            sender: ctx.actor_addr,
+        };
+
+        let envelope = ctx.envelope_to(recipient, message, kind);
+        let object = self.resolve(&ctx.book);
+        try_send_to_object(object, recipient, envelope)
+    }
+
+    fn resolve(&mut self, book: &AddressBook) -> Option<&Object> {
+        // Removed objects are marked, so only the recipient's own removal
+        // invalidates the cached entry, not removals of unrelated actors.
+        if self
+            .cached
+            .as_ref()
+            .is_none_or(|object| object.is_removed())
+        {
+            self.cached = book.get_owned(self.recipient);
+        }
+
+        self.cached.as_deref()
+    }
+}
@@ -1,11 +1,15 @@
+use crate::{
+    address_book::AddressBook, envelope::Envelope, errors::RequestError, message::Message, time,
+};
 use derive_more::Constructor;
+use fxhash::FxHashMap;
 use metrics::{self, Key, Label};
-use quanta::Instant;
-
-use crate::{envelope::Envelope, message::Message};
 
 pub(super) struct Stats {
     in_handling: Option<InHandling>,
+    /// `node_no_group_no` of the sender ->
+    /// `elfo_message_delivery_time_seconds`.
+    delivery_keys: FxHashMap<u32, Key>,
 }
 
 #[derive(Constructor)]
@@ -19,12 +23,16 @@ static EMPTY_MAILBOX_LABELS: &[Label] = &[Label::from_static_parts("message", "<
 
 impl Stats {
     pub(super) fn empty() -> Self {
-        Self { in_handling: None }
+        Self {
+            in_handling: None,
+            delivery_keys: FxHashMap::default(),
+        }
     }
 
     pub(super) fn startup() -> Self {
         Self {
-            in_handling: Some(InHandling::new(STARTUP_LABELS, Instant::now())),
+            in_handling: Some(InHandling::new(STARTUP_LABELS, time::instant())),
+            delivery_keys: FxHashMap::default(),
         }
     }
 
@@ -33,23 +41,47 @@ impl Stats {
         self.emit_handling_time();
     }
 
-    pub(super) fn on_received_envelope(&mut self, envelope: &Envelope) {
+    pub(super) fn on_received_envelope(&mut self, envelope: &Envelope, book: &AddressBook) {
         debug_assert!(self.in_handling.is_none());
 
         let recorder = ward!(metrics::try_recorder());
         let key = Key::from_static_name("elfo_message_waiting_time_seconds");
-        let now = Instant::now();
+        let now = time::instant();
         // Now envelope cannot be forwarded, so use the created time as a start time.
+        // For messages from other nodes, it's corrected by the network actor.
         let value = (now - envelope.created_time()).as_secs_f64();
         recorder.record_histogram(&key, value);
 
+        // The same value, but per source group. The destination group is added
+        // by the telemetry scope. Messages from unknown groups are skipped.
+        let sender = envelope.sender();
+        if !sender.is_null() {
+            if let Some(key) = self.delivery_key(sender.node_no_group_no(), book) {
+                recorder.record_histogram(key, value);
+            }
+        }
+
         self.in_handling = Some(InHandling::new(envelope.message().labels(), now));
     }
 
+    fn delivery_key(&mut self, node_no_group_no: u32, book: &AddressBook) -> Option<&Key> {
+        use std::collections::hash_map::Entry;
+
+        match self.delivery_keys.entry(node_no_group_no) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let name = book.group_name_by_bits(node_no_group_no)?;
+                let labels = vec![Label::new("source", name.to_string())];
+                let key = Key::from_parts("elfo_message_delivery_time_seconds", labels);
+                Some(entry.insert(key))
+            }
+        }
+    }
+
     pub(super) fn on_empty_mailbox(&mut self) {
         debug_assert!(self.in_handling.is_none());
 
-        self.in_handling = Some(InHandling::new(EMPTY_MAILBOX_LABELS, Instant::now()));
+        self.in_handling = Some(InHandling::new(EMPTY_MAILBOX_LABELS, time::instant()));
     }
 
     pub(super) fn on_sent_message(&self, message: &impl Message) {
@@ -62,11 +94,31 @@ impl Stats {
         let in_handling = ward!(self.in_handling.take());
         let recorder = ward!(metrics::try_recorder());
         let key = Key::from_static_parts("elfo_message_handling_time_seconds", in_handling.labels);
-        let value = (Instant::now() - in_handling.start_time).as_secs_f64();
+        let value = (time::instant() - in_handling.start_time).as_secs_f64();
         recorder.record_histogram(&key, value);
     }
 }
 
+/// Records `elfo_request_duration_seconds` of the successful response per
+/// the request type and the responding group.
+/// Responses from unknown groups are skipped.
+pub(super) fn on_response(
+    labels: &'static [Label],
+    elapsed: std::time::Duration,
+    response: &Result<Envelope, RequestError>,
+    book: &AddressBook,
+) {
+    let Ok(envelope) = response else { return };
+    let recorder = ward!(metrics::try_recorder());
+    let sender = envelope.sender();
+    let name = ward!(book.group_name_by_bits(sender.node_no_group_no()));
+
+    let mut key_labels = labels.to_vec();
+    key_labels.push(Label::new("destination", name.to_string()));
+    let key = Key::from_parts("elfo_request_duration_seconds", key_labels);
+    recorder.record_histogram(&key, elapsed.as_secs_f64());
+}
+
 impl Drop for Stats {
     fn drop(&mut self) {
         self.emit_handling_time();
@@ -0,0 +1,140 @@
+//! Delivery of messages sent by `Context::unbounded_send*()` to full
+//! mailboxes. Such messages are put into per-recipient overflow queues, each
+//! drained in order by a single task, and counted per topology, which makes
+//! hidden unboundedness observable.
+
+use std::{
+    collections::{hash_map::Entry, VecDeque},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use fxhash::FxHashMap;
+use metrics::{decrement_gauge, increment_counter, increment_gauge};
+use parking_lot::Mutex;
+use tokio::runtime::Handle;
+use tracing::{trace, warn};
+
+use super::Context;
+use crate::{addr::Addr, envelope::Envelope, object::ObjectArc, scope};
+
+/// Every time the number of pending messages reaches a multiple of this value,
+/// a warning is emitted.
+const WARN_THRESHOLD: usize = 1000;
+
+/// Overflow queues of one topology, kept in the address book.
+#[derive(Default)]
+pub(crate) struct Overflows {
+    /// Messages in queues, not delivered yet.
+    pending: AtomicUsize,
+    /// `(object, recipient)` -> queued messages.
+    /// A queue exists while it's drained by its task.
+    queues: Mutex<FxHashMap<(Addr, Addr), VecDeque<Envelope>>>,
+}
+
+impl Overflows {
+    /// Puts the envelope at the end of the recipient's queue if it exists,
+    /// so messages don't overtake previously queued ones.
+    pub(super) fn enqueue(
+        &self,
+        object: &ObjectArc,
+        recipient: Addr,
+        envelope: Envelope,
+    ) -> Result<(), Envelope> {
+        // Fast path: there are no queues at all.
+        if self.pending.load(Ordering::Relaxed) == 0 {
+            return Err(envelope);
+        }
+
+        let mut queues = self.queues.lock();
+        match queues.get_mut(&(object.addr(), recipient)) {
+            Some(queue) => {
+                queue.push_back(envelope);
+                self.on_queued();
+                Ok(())
+            }
+            None => Err(envelope),
+        }
+    }
+
+    // Must be called under the lock of queues.
+    fn on_queued(&self) {
+        let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        increment_counter!("elfo_unbounded_overflows_total");
+        increment_gauge!("elfo_unbounded_pending_messages", 1.);
+
+        if pending.is_multiple_of(WARN_THRESHOLD) {
+            warn!(
+                pending,
+                "too many messages sent by `unbounded_send` are pending, consider limiting them"
+            );
+        }
+    }
+
+    fn on_delivered(&self) {
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+        decrement_gauge!("elfo_unbounded_pending_messages", 1.);
+    }
+}
+
+pub(super) fn deliver_later(ctx: Context, object: ObjectArc, recipient: Addr, envelope: Envelope) {
+    // There is no way to wait for capacity outside the runtime (e.g. in `Drop`
+    // of some global), so the message is lost, but it's still accounted.
+    let Ok(runtime) = Handle::try_current() else {
+        increment_counter!("elfo_unbounded_lost_messages_total");
+        warn!("message sent by `unbounded_send` is lost, because there is no tokio runtime");
+        return;
+    };
+
+    let overflows = ctx.book().overflows().clone();
+    let key = (object.addr(), recipient);
+
+    {
+        let mut queues = overflows.queues.lock();
+        let is_new = match queues.entry(key) {
+            // The queue has been created concurrently, its task delivers the message.
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().push_back(envelope);
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(VecDeque::from([envelope]));
+                true
+            }
+        };
+        overflows.on_queued();
+
+        if !is_new {
+            return;
+        }
+    }
+
+    let fut = async move {
+        loop {
+            let envelope = {
+                let mut queues = overflows.queues.lock();
+                let queue = queues
+                    .get_mut(&key)
+                    .expect("queue is removed by its task only");
+
+                match queue.pop_front() {
+                    Some(envelope) => envelope,
+                    None => {
+                        queues.remove(&key);
+                        break;
+                    }
+                }
+            };
+
+            if let Err(err) = object.send(&ctx, recipient, envelope).await {
+                trace!(error = %err, "unbounded message is lost");
+            }
+
+            overflows.on_delivered();
+        }
+    };
+
+    match scope::try_expose() {
+        Some(scope) => runtime.spawn(scope.within(fut)),
+        None => runtime.spawn(fut),
+    };
+}
@@ -12,18 +12,20 @@ type Addrs = SmallVec<[Addr; OPTIMAL_COUNT]>;
 pub struct Demux {
     #[allow(clippy::type_complexity)]
     filter: Option<Arc<dyn Fn(&Envelope, &mut Addrs) + Send + Sync>>,
+    /// Applied after the filter only if the envelope is routed somewhere.
+    #[allow(clippy::type_complexity)]
+    tap: Option<Arc<dyn Fn(&Envelope, &mut Addrs) + Send + Sync>>,
 }
 
 impl Demux {
     pub(crate) fn append(&mut self, f: impl Fn(&Envelope, &mut Addrs) + Send + Sync + 'static) {
-        self.filter = Some(if let Some(prev) = self.filter.take() {
-            Arc::new(move |envelope, addrs| {
-                prev(envelope, addrs);
-                f(envelope, addrs);
-            })
-        } else {
-            Arc::new(f)
-        })
+        compose(&mut self.filter, f);
+    }
+
+    /// Like `append()`, but `f` isn't called for envelopes without recipients,
+    /// so taps don't affect whether a message is routed or not.
+    pub(crate) fn append_tap(&mut self, f: impl Fn(&Envelope, &mut Addrs) + Send + Sync + 'static) {
+        compose(&mut self.tap, f);
     }
 
     // TODO: return an iterator?
@@ -32,6 +34,24 @@ impl Demux {
         if let Some(filter) = &self.filter {
             (filter)(envelope, &mut addrs);
         }
+        if let Some(tap) = self.tap.as_ref().filter(|_| !addrs.is_empty()) {
+            (tap)(envelope, &mut addrs);
+        }
         addrs
     }
 }
+
+#[allow(clippy::type_complexity)]
+fn compose(
+    slot: &mut Option<Arc<dyn Fn(&Envelope, &mut Addrs) + Send + Sync>>,
+    f: impl Fn(&Envelope, &mut Addrs) + Send + Sync + 'static,
+) {
+    *slot = Some(if let Some(prev) = slot.take() {
+        Arc::new(move |envelope, addrs| {
+            prev(envelope, addrs);
+            f(envelope, addrs);
+        })
+    } else {
+        Arc::new(f)
+    })
+}
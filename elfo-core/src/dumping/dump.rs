@@ -6,7 +6,14 @@ use smallbox::{smallbox, SmallBox};
 
 use super::{extract_name::extract_name, sequence_no::SequenceNo};
 use crate::{
-    actor::ActorMeta, envelope, message, scope, thread::ThreadId, tracing::TraceId, Message,
+    actor::ActorMeta,
+    envelope::Envelope,
+    message,
+    message::AnyMessage,
+    scope,
+    thread::ThreadId,
+    tracing::{MessageId, TraceId},
+    Message,
 };
 
 // === Dump ===
@@ -18,6 +25,8 @@ pub struct Dump {
     pub sequence_no: SequenceNo,
     pub timestamp: Timestamp,
     pub trace_id: TraceId,
+    pub message_id: Option<MessageId>,
+    pub parent_id: Option<MessageId>,
     pub thread_id: ThreadId,
     pub direction: Direction,
     pub message_name: MessageName,
@@ -28,7 +37,8 @@ pub struct Dump {
 
 #[doc(hidden)]
 #[stability::unstable]
-pub type ErasedMessage = SmallBox<dyn ErasedSerialize + Send, [usize; 24]>;
+// Two words less than it could be to keep `Dump` 320 bytes.
+pub type ErasedMessage = SmallBox<dyn ErasedSerialize + Send, [usize; 22]>;
 
 assert_impl_all!(Dump: Send);
 assert_eq_size!(Dump, [u8; 320]);
@@ -38,6 +48,8 @@ impl Dump {
     pub fn builder() -> DumpBuilder {
         DumpBuilder {
             timestamp: None,
+            message_id: None,
+            parent_id: None,
             direction: Direction::Out,
             message_name: None,
             message_protocol: "",
@@ -45,16 +57,16 @@ impl Dump {
         }
     }
 
-    pub(crate) fn message(
-        message: &impl Message,
-        kind: &envelope::MessageKind,
-        direction: Direction,
-    ) -> Self {
+    pub(crate) fn envelope<M: Message>(envelope: &Envelope<M>, direction: Direction) -> Self {
+        let message = envelope.message();
+
         Self::builder()
             .direction(direction)
             .message_name(message.name())
             .message_protocol(message.protocol())
-            .message_kind(MessageKind::from_message_kind(kind))
+            .message_kind(MessageKind::from_message_kind(envelope.message_kind()))
+            .message_id(Some(envelope.message_id()))
+            .parent_id(envelope.parent_id())
             .do_finish(message._erase())
     }
 }
@@ -64,6 +76,8 @@ impl Dump {
 #[stability::unstable]
 pub struct DumpBuilder {
     timestamp: Option<Timestamp>,
+    message_id: Option<MessageId>,
+    parent_id: Option<MessageId>,
     direction: Direction,
     message_name: Option<MessageName>,
     message_protocol: &'static str,
@@ -77,6 +91,18 @@ impl DumpBuilder {
         self
     }
 
+    #[stability::unstable]
+    pub fn message_id(&mut self, message_id: Option<MessageId>) -> &mut Self {
+        self.message_id = message_id;
+        self
+    }
+
+    #[stability::unstable]
+    pub fn parent_id(&mut self, parent_id: Option<MessageId>) -> &mut Self {
+        self.parent_id = parent_id;
+        self
+    }
+
     #[stability::unstable]
     pub fn direction(&mut self, direction: Direction) -> &mut Self {
         self.direction = direction;
@@ -114,6 +140,15 @@ impl DumpBuilder {
         self.do_finish(smallbox!(message))
     }
 
+    /// Like `finish()`, but for a type-erased message, e.g. received from
+    /// the network. The name and protocol are taken from the message.
+    #[stability::unstable]
+    pub fn finish_any(&mut self, message: &AnyMessage) -> Dump {
+        self.message_name = Some(message.name().into());
+        self.message_protocol = message.protocol();
+        self.do_finish(message._erase())
+    }
+
     fn do_finish(&mut self, message: ErasedMessage) -> Dump {
         let (meta, trace_id, sequence_no) = scope::with(|scope| {
             (
@@ -128,6 +163,8 @@ impl DumpBuilder {
             sequence_no,
             timestamp: self.timestamp.unwrap_or_else(Timestamp::now),
             trace_id,
+            message_id: self.message_id,
+            parent_id: self.parent_id,
             thread_id: crate::thread::id(),
             direction: self.direction,
             message_name: self.message_name.take().unwrap_or_default(),
@@ -152,7 +189,7 @@ impl Timestamp {
     #[inline]
     #[stability::unstable]
     pub fn now() -> Self {
-        SystemTime::now().into()
+        crate::time::now().into()
     }
 
     #[cfg(test)]
@@ -196,7 +233,7 @@ impl<'a> PartialEq<&'a str> for MessageName {
     fn eq(&self, s: &&'a str) -> bool {
         if let Some(variant) = self.1 {
             s.split_once("::")
-                .map_or(false, |(n, v)| n == self.0 && v == variant)
+                .is_some_and(|(n, v)| n == self.0 && v == variant)
         } else {
             self.0 == *s
         }
@@ -39,6 +39,19 @@ mod raw;
 mod recorder;
 mod sequence_no;
 
+/// Dumps a field as `<redacted>`.
+///
+/// Used for fields marked as `#[message(secret)]`, which are also printed
+/// as `<redacted>` by `Debug`. Other serializations, e.g. network ones,
+/// are left intact.
+pub fn redact<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    if crate::scope::serde_mode() == crate::scope::SerdeMode::Dumping {
+        serializer.serialize_str("<redacted>")
+    } else {
+        value.serialize(serializer)
+    }
+}
+
 /// Dumps a field as `<hidden>`.
 pub fn hide<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
     if crate::scope::serde_mode() == crate::scope::SerdeMode::Dumping {
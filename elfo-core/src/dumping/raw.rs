@@ -14,7 +14,7 @@ use serde_json::value::RawValue;
 pub struct Raw<T>(pub T);
 
 impl<T: AsRef<str>> Serialize for Raw<T> {
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
@@ -1,9 +1,13 @@
+use std::{num::NonZeroU32, time::Duration};
+
 use quanta::Instant;
 
 use crate::{
     message::{AnyMessage, Message},
+    node,
     request_table::{RequestId, ResponseToken},
-    tracing::TraceId,
+    time,
+    tracing::{Baggage, MessageId, TraceId},
     Addr,
 };
 
@@ -12,6 +16,12 @@ use crate::{
 pub struct Envelope<M = AnyMessage> {
     created_time: Instant, // Now used also as a sent time.
     trace_id: TraceId,
+    baggage: Baggage,
+    message_id: MessageId,
+    /// The message being handled when this one was sent.
+    parent_id: Option<MessageId>,
+    /// Overrides the message's TTL, in milliseconds.
+    ttl: Option<NonZeroU32>,
     kind: MessageKind,
     message: M,
 }
@@ -33,7 +43,16 @@ impl<M> Envelope<M> {
     #[doc(hidden)]
     #[inline]
     pub fn new(message: M, kind: MessageKind) -> Self {
-        Self::with_trace_id(message, kind, crate::scope::trace_id())
+        crate::scope::with(|scope| Self {
+            created_time: scope.clock().instant(),
+            trace_id: scope.trace_id(),
+            baggage: scope.baggage(),
+            message_id: MessageId::generate_on(scope.node_no()),
+            parent_id: scope.message_id(),
+            ttl: None,
+            kind,
+            message,
+        })
     }
 
     // This is private API. Do not use it.
@@ -41,8 +60,12 @@ impl<M> Envelope<M> {
     #[inline]
     pub fn with_trace_id(message: M, kind: MessageKind, trace_id: TraceId) -> Self {
         Self {
-            created_time: Instant::now(),
+            created_time: time::instant(),
             trace_id,
+            baggage: Baggage::default(),
+            message_id: MessageId::generate_on(node::node_no()),
+            parent_id: None,
+            ttl: None,
             kind,
             message,
         }
@@ -53,11 +76,54 @@ impl<M> Envelope<M> {
         self.trace_id
     }
 
+    /// Returns the baggage captured when the envelope was sent.
+    #[inline]
+    pub fn baggage(&self) -> &Baggage {
+        &self.baggage
+    }
+
+    /// Part of private API. Do not use it.
+    #[doc(hidden)]
+    #[stability::unstable]
+    pub fn set_baggage(&mut self, baggage: Baggage) {
+        self.baggage = baggage;
+    }
+
+    /// Returns the unique id of the envelope.
+    #[inline]
+    pub fn message_id(&self) -> MessageId {
+        self.message_id
+    }
+
+    /// Returns the id of the message that was being handled when this one
+    /// was sent, i.e. the cause of this message.
+    ///
+    /// `None` for messages sent outside handling of other messages,
+    /// e.g. produced by sources or on start of actors.
+    #[inline]
+    pub fn parent_id(&self) -> Option<MessageId> {
+        self.parent_id
+    }
+
+    /// Replaces ids of the envelope, e.g. received from other nodes.
+    ///
+    /// Part of private API. Do not use it.
+    #[doc(hidden)]
+    #[stability::unstable]
+    pub fn set_message_ids(&mut self, message_id: MessageId, parent_id: Option<MessageId>) {
+        self.message_id = message_id;
+        self.parent_id = parent_id;
+    }
+
     #[inline]
     pub fn message(&self) -> &M {
         &self.message
     }
 
+    pub(crate) fn into_message(self) -> M {
+        self.message
+    }
+
     /// Part of private API. Do not use it.
     #[doc(hidden)]
     pub fn message_kind(&self) -> &MessageKind {
@@ -68,6 +134,47 @@ impl<M> Envelope<M> {
         self.created_time
     }
 
+    /// Returns the time when the envelope was sent.
+    ///
+    /// For envelopes received from other nodes, the time spent on the way is
+    /// taken into account if the clock offset between nodes is estimated.
+    ///
+    /// The result is always relative to the wall-clock [`std::time::Instant`],
+    /// so it's meaningless if the clock is mocked or simulated, e.g. in tests.
+    /// Use [`Envelope::queued_for()`] in such cases, it respects the clock.
+    pub fn created_at(&self) -> std::time::Instant {
+        let now = std::time::Instant::now();
+        now.checked_sub(self.queued_for()).unwrap_or(now)
+    }
+
+    /// Returns the time elapsed since the envelope was sent.
+    ///
+    /// Right after receiving, it's the queueing delay of the envelope, useful
+    /// to implement custom staleness logic. Later, it also includes the time
+    /// spent on handling. See [`Envelope::created_at()`] for remote envelopes.
+    #[inline]
+    pub fn queued_for(&self) -> Duration {
+        time::instant() - self.created_time
+    }
+
+    /// Makes the envelope look like it was sent `age` ago.
+    /// Used to take into account a time that has been spent on other nodes.
+    ///
+    /// Part of private API. Do not use it.
+    #[doc(hidden)]
+    #[stability::unstable]
+    pub fn set_age(&mut self, age: Duration) {
+        let now = time::instant();
+        self.created_time = now.checked_sub(age).unwrap_or(now);
+    }
+
+    /// Sets the TTL overriding the message's one, see [`Message::ttl()`].
+    /// It's stored in milliseconds, rounded up.
+    pub(crate) fn set_ttl(&mut self, ttl: Duration) {
+        let millis = ttl.as_nanos().div_ceil(1_000_000);
+        self.ttl = Some(NonZeroU32::new(millis.clamp(1, u32::MAX as u128) as u32).unwrap());
+    }
+
     #[inline]
     pub fn sender(&self) -> Addr {
         match &self.kind {
@@ -86,6 +193,10 @@ impl<M: Message> Envelope<M> {
         Envelope {
             created_time: self.created_time,
             trace_id: self.trace_id,
+            baggage: self.baggage,
+            message_id: self.message_id,
+            parent_id: self.parent_id,
+            ttl: self.ttl,
             kind: self.kind,
             message: self.message.upcast(),
         }
@@ -98,12 +209,33 @@ impl Envelope {
         self.message.is::<M>()
     }
 
+    /// Returns the TTL of the envelope: provided at send time or specified by
+    /// `#[message(ttl = "..")]`. The TTL is counted since the envelope was
+    /// sent.
+    #[inline]
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+            .map(|millis| Duration::from_millis(millis.get().into()))
+            .or_else(|| self.message.ttl())
+    }
+
+    /// Returns `true` if the TTL of the envelope has elapsed.
+    /// Such envelopes are dropped instead of being received.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.ttl().is_some_and(|ttl| self.queued_for() > ttl)
+    }
+
     #[doc(hidden)]
     #[stability::unstable]
     pub fn duplicate(&self) -> Self {
         Self {
             created_time: self.created_time,
             trace_id: self.trace_id,
+            baggage: self.baggage.clone(),
+            message_id: self.message_id,
+            parent_id: self.parent_id,
+            ttl: self.ttl,
             kind: match &self.kind {
                 MessageKind::Regular { sender } => MessageKind::Regular { sender: *sender },
                 MessageKind::RequestAny(token) => MessageKind::RequestAny(token.duplicate()),
@@ -200,3 +332,26 @@ impl AnyMessageBorrowed for AnyMessage {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::message;
+
+    #[message]
+    struct Sample;
+
+    #[test]
+    fn queued_for() {
+        let kind = MessageKind::Regular { sender: Addr::NULL };
+        let mut envelope = Envelope::with_trace_id(Sample, kind, TraceId::try_from(1).unwrap());
+
+        envelope.set_age(Duration::from_secs(5));
+        assert!(envelope.queued_for() >= Duration::from_secs(5));
+
+        let elapsed = envelope.created_at().elapsed();
+        assert!(elapsed >= Duration::from_millis(4900), "{elapsed:?}");
+        assert!(elapsed <= Duration::from_millis(5100), "{elapsed:?}");
+    }
+}
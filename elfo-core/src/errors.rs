@@ -5,6 +5,8 @@ use std::{
 
 use derive_more::{Display, Error};
 
+use crate::addr::NodeNo;
+
 #[derive(Error)]
 #[non_exhaustive]
 pub struct StartError {
@@ -83,17 +85,139 @@ pub struct StartGroupError {
 }
 
 #[derive(Debug, Display, Error)]
-#[display(fmt = "mailbox closed")]
-pub struct SendError<T>(#[error(not(source))] pub T);
+#[non_exhaustive]
+pub enum SendError<T> {
+    /// The mailbox has been closed.
+    #[display(fmt = "mailbox closed")]
+    MailboxClosed(#[error(not(source))] T),
+    /// There is no recipient: the address is unknown or the message has been
+    /// discarded by routers.
+    #[display(fmt = "no route")]
+    NoRoute(#[error(not(source))] T),
+    /// The connection to the remote node has been lost.
+    #[display(fmt = "remote node {node_no} is down")]
+    RemoteDown {
+        #[error(not(source))]
+        message: T,
+        node_no: NodeNo,
+    },
+    /// The remote group is considered failing and requests to it are rejected
+    /// locally for a while, see the network's circuit breaker.
+    #[display(fmt = "remote node {node_no} is unavailable")]
+    RemoteUnavailable {
+        #[error(not(source))]
+        message: T,
+        node_no: NodeNo,
+    },
+    /// The message cannot be serialized to be sent to another node.
+    /// Messages to connected nodes are serialized lazily, so it's returned only
+    /// while messages are spooled, see `system.network.spool`.
+    #[display(fmt = "serialization failed")]
+    Serialization(#[error(not(source))] T),
+}
+
+impl<T> SendError<T> {
+    /// Converts the error into its inner value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::MailboxClosed(inner) => inner,
+            Self::NoRoute(inner) => inner,
+            Self::RemoteDown { message, .. } => message,
+            Self::RemoteUnavailable { message, .. } => message,
+            Self::Serialization(inner) => inner,
+        }
+    }
+
+    /// Transforms the inner message, preserving the reason.
+    #[inline]
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> SendError<U> {
+        match self {
+            Self::MailboxClosed(inner) => SendError::MailboxClosed(f(inner)),
+            Self::NoRoute(inner) => SendError::NoRoute(f(inner)),
+            Self::RemoteDown { message, node_no } => SendError::RemoteDown {
+                message: f(message),
+                node_no,
+            },
+            Self::RemoteUnavailable { message, node_no } => SendError::RemoteUnavailable {
+                message: f(message),
+                node_no,
+            },
+            Self::Serialization(inner) => SendError::Serialization(f(inner)),
+        }
+    }
+
+    /// Separates the reason from the inner value.
+    pub(crate) fn split(self) -> (SendError<()>, T) {
+        let mut inner = None;
+        let reason = self.map(|value| inner = Some(value));
+        (reason, inner.expect("map() must call the closure"))
+    }
+
+    /// Returns whether the error is the `MailboxClosed` variant.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::MailboxClosed(_))
+    }
+
+    /// Returns whether the error is the `NoRoute` variant.
+    #[inline]
+    pub fn is_no_route(&self) -> bool {
+        matches!(self, Self::NoRoute(_))
+    }
+
+    /// Returns whether the error is the `RemoteDown` variant.
+    #[inline]
+    pub fn is_remote_down(&self) -> bool {
+        matches!(self, Self::RemoteDown { .. })
+    }
+
+    /// Returns whether the error is the `RemoteUnavailable` variant.
+    #[inline]
+    pub fn is_remote_unavailable(&self) -> bool {
+        matches!(self, Self::RemoteUnavailable { .. })
+    }
+
+    /// Returns whether the error is the `Serialization` variant.
+    #[inline]
+    pub fn is_serialization(&self) -> bool {
+        matches!(self, Self::Serialization(_))
+    }
+}
 
 #[derive(Debug, Display, Error)]
+#[non_exhaustive]
 pub enum TrySendError<T> {
     /// The mailbox is full.
     #[display(fmt = "mailbox full")]
-    Full(#[error(not(source))] T),
+    MailboxFull(#[error(not(source))] T),
     /// The mailbox has been closed.
     #[display(fmt = "mailbox closed")]
-    Closed(#[error(not(source))] T),
+    MailboxClosed(#[error(not(source))] T),
+    /// There is no recipient: the address is unknown or the message has been
+    /// discarded by routers.
+    #[display(fmt = "no route")]
+    NoRoute(#[error(not(source))] T),
+    /// The connection to the remote node has been lost.
+    #[display(fmt = "remote node {node_no} is down")]
+    RemoteDown {
+        #[error(not(source))]
+        message: T,
+        node_no: NodeNo,
+    },
+    /// The remote group is considered failing and requests to it are rejected
+    /// locally for a while, see the network's circuit breaker.
+    #[display(fmt = "remote node {node_no} is unavailable")]
+    RemoteUnavailable {
+        #[error(not(source))]
+        message: T,
+        node_no: NodeNo,
+    },
+    /// The message cannot be serialized to be sent to another node.
+    /// Messages to connected nodes are serialized lazily, so it's returned only
+    /// while messages are spooled, see `system.network.spool`.
+    #[display(fmt = "serialization failed")]
+    Serialization(#[error(not(source))] T),
 }
 
 impl<T> TrySendError<T> {
@@ -101,44 +225,147 @@ impl<T> TrySendError<T> {
     #[inline]
     pub fn into_inner(self) -> T {
         match self {
-            Self::Closed(inner) => inner,
-            Self::Full(inner) => inner,
+            Self::MailboxFull(inner) => inner,
+            Self::MailboxClosed(inner) => inner,
+            Self::NoRoute(inner) => inner,
+            Self::RemoteDown { message, .. } => message,
+            Self::RemoteUnavailable { message, .. } => message,
+            Self::Serialization(inner) => inner,
         }
     }
 
-    /// Transforms the inner message.
+    /// Transforms the inner message, preserving the reason.
     #[inline]
     pub fn map<U>(self, f: impl FnOnce(T) -> U) -> TrySendError<U> {
         match self {
-            Self::Full(inner) => TrySendError::Full(f(inner)),
-            Self::Closed(inner) => TrySendError::Closed(f(inner)),
+            Self::MailboxFull(inner) => TrySendError::MailboxFull(f(inner)),
+            Self::MailboxClosed(inner) => TrySendError::MailboxClosed(f(inner)),
+            Self::NoRoute(inner) => TrySendError::NoRoute(f(inner)),
+            Self::RemoteDown { message, node_no } => TrySendError::RemoteDown {
+                message: f(message),
+                node_no,
+            },
+            Self::RemoteUnavailable { message, node_no } => TrySendError::RemoteUnavailable {
+                message: f(message),
+                node_no,
+            },
+            Self::Serialization(inner) => TrySendError::Serialization(f(inner)),
+        }
+    }
+
+    /// Separates the reason from the inner value.
+    pub(crate) fn split(self) -> (TrySendError<()>, T) {
+        let mut inner = None;
+        let reason = self.map(|value| inner = Some(value));
+        (reason, inner.expect("map() must call the closure"))
+    }
+
+    /// Converts the error returned by waiting without a deadline.
+    pub(crate) fn into_send_error(self) -> SendError<T> {
+        match self {
+            Self::MailboxFull(_) => unreachable!("mailbox is full without a deadline"),
+            Self::MailboxClosed(inner) => SendError::MailboxClosed(inner),
+            Self::NoRoute(inner) => SendError::NoRoute(inner),
+            Self::RemoteDown { message, node_no } => SendError::RemoteDown { message, node_no },
+            Self::RemoteUnavailable { message, node_no } => {
+                SendError::RemoteUnavailable { message, node_no }
+            }
+            Self::Serialization(inner) => SendError::Serialization(inner),
         }
     }
 
-    /// Returns whether the error is the `Full` variant.
+    /// Returns whether the error is the `MailboxFull` variant.
     #[inline]
     pub fn is_full(&self) -> bool {
-        matches!(self, Self::Full(_))
+        matches!(self, Self::MailboxFull(_))
     }
 
-    /// Returns whether the error is the `Closed` variant.
+    /// Returns whether the error is the `MailboxClosed` variant.
     #[inline]
     pub fn is_closed(&self) -> bool {
-        matches!(self, Self::Closed(_))
+        matches!(self, Self::MailboxClosed(_))
+    }
+
+    /// Returns whether the error is the `NoRoute` variant.
+    #[inline]
+    pub fn is_no_route(&self) -> bool {
+        matches!(self, Self::NoRoute(_))
+    }
+
+    /// Returns whether the error is the `RemoteDown` variant.
+    #[inline]
+    pub fn is_remote_down(&self) -> bool {
+        matches!(self, Self::RemoteDown { .. })
+    }
+
+    /// Returns whether the error is the `RemoteUnavailable` variant.
+    #[inline]
+    pub fn is_remote_unavailable(&self) -> bool {
+        matches!(self, Self::RemoteUnavailable { .. })
+    }
+
+    /// Returns whether the error is the `Serialization` variant.
+    #[inline]
+    pub fn is_serialization(&self) -> bool {
+        matches!(self, Self::Serialization(_))
+    }
+}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    #[inline]
+    fn from(err: SendError<T>) -> Self {
+        match err {
+            SendError::MailboxClosed(inner) => Self::MailboxClosed(inner),
+            SendError::NoRoute(inner) => Self::NoRoute(inner),
+            SendError::RemoteDown { message, node_no } => Self::RemoteDown { message, node_no },
+            SendError::RemoteUnavailable { message, node_no } => {
+                Self::RemoteUnavailable { message, node_no }
+            }
+            SendError::Serialization(inner) => Self::Serialization(inner),
+        }
     }
 }
 
 #[derive(Debug, Display, Error)]
+#[non_exhaustive]
 pub enum RequestError {
-    /// Receiver hasn't got the request.
+    /// The request hasn't been sent, the reason is provided.
+    ///
+    /// Requests wait for free space in mailboxes, so it's never
+    /// `MailboxFull`, unlike errors of [`Context::try_send()`].
+    ///
+    /// [`Context::try_send()`]: crate::Context::try_send
+    #[display(fmt = "request not sent: {_0}")]
+    NotSent(#[error(not(source))] SendError<()>),
+    /// Receiver hasn't got the request, e.g. it has been lost
+    /// after sending because the receiver or the connection was closed.
     #[display(fmt = "request failed")]
     Failed,
     /// Receiver has got the request, but ignored it.
+    ///
+    /// Returned as soon as the receiver drops the response token without
+    /// responding, even if the receiver is on another node. Counted by the
+    /// `elfo_ignored_requests_total` metric on the receiver's side.
     #[display(fmt = "request ignored")]
     Ignored,
 }
 
 impl RequestError {
+    /// Returns whether the error is the `NotSent` variant.
+    #[inline]
+    pub fn is_not_sent(&self) -> bool {
+        matches!(self, Self::NotSent(_))
+    }
+
+    /// Returns the reason if the error is the `NotSent` variant.
+    #[inline]
+    pub fn send_error(&self) -> Option<&SendError<()>> {
+        match self {
+            Self::NotSent(err) => Some(err),
+            _ => None,
+        }
+    }
+
     /// Returns whether the error is the `Failed` variant.
     #[inline]
     pub fn is_failed(&self) -> bool {
@@ -175,3 +402,116 @@ impl TryRecvError {
         matches!(self, Self::Closed)
     }
 }
+
+/// A message isn't allowed in the current state of a state machine,
+/// see [`StateMachine::apply()`].
+///
+/// [`StateMachine::apply()`]: crate::state_machine::StateMachine::apply
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TransitionError<S> {
+    /// The state the message has been received in.
+    pub state: S,
+    pub protocol: &'static str,
+    pub message: &'static str,
+}
+
+impl<S: Debug> Display for TransitionError<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "message {}/{} isn't allowed in state {:?}",
+            self.protocol, self.message, self.state
+        )
+    }
+}
+
+impl<S: Debug> std::error::Error for TransitionError<S> {}
+
+/// A classified failure of an actor, returned from its exec function as
+/// `Result<(), ExecError>`. The kind determines whether the actor is
+/// restarted, see [`FailureKind`].
+///
+/// Other errors and panics are considered transient.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # async fn connect(_url: &str) -> Result<(), std::io::Error> { Ok(()) }
+/// use elfo::errors::ExecError;
+///
+/// async fn exec(ctx: elfo::Context) -> Result<(), ExecError> {
+///     let url = "";
+///     if url.is_empty() {
+///         return Err(ExecError::misconfiguration("empty url"));
+///     }
+///
+///     connect(url).await.map_err(ExecError::transient)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ExecError {
+    kind: FailureKind,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl ExecError {
+    /// The actor cannot recover, see [`FailureKind::Fatal`].
+    pub fn fatal(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::new(FailureKind::Fatal, source)
+    }
+
+    /// The failure is temporary, see [`FailureKind::Transient`].
+    pub fn transient(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::new(FailureKind::Transient, source)
+    }
+
+    /// The config is invalid, see [`FailureKind::Misconfiguration`].
+    pub fn misconfiguration(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::new(FailureKind::Misconfiguration, source)
+    }
+
+    fn new(kind: FailureKind, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    /// Returns the kind of the failure.
+    #[inline]
+    pub fn kind(&self) -> FailureKind {
+        self.kind
+    }
+}
+
+impl Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)
+    }
+}
+
+impl std::error::Error for ExecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// The kind of [`ExecError`] determining whether the actor is restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum FailureKind {
+    /// The actor cannot recover, so it isn't restarted regardless of the
+    /// restart policy.
+    #[display(fmt = "fatal")]
+    Fatal,
+    /// A temporary failure, e.g. a lost connection. The actor is restarted
+    /// with backoff according to the restart policy.
+    #[display(fmt = "transient")]
+    Transient,
+    /// The config is invalid, so restarting with it is useless. The actor
+    /// isn't restarted until the group's config is updated.
+    #[display(fmt = "misconfiguration")]
+    Misconfiguration,
+}
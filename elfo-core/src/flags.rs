@@ -0,0 +1,95 @@
+//! Feature flags delivered through configs.
+//!
+//! A flag is declared as a type implementing [`Flag`] with its name and
+//! default value. Then, it's read by [`Context::flag()`] and set per group in
+//! the `system.flags` section of the config, e.g. `[common]` or the group's
+//! one. Flags are updated live once the config is reloaded.
+//!
+//! The current values of flags set in the config are exposed by the
+//! `elfo_feature_flags` gauge (`1` if enabled) per group, other flags are at
+//! their defaults.
+//!
+//! # Example
+//! ```
+//! # use elfo_core as elfo;
+//! use elfo::flags::Flag;
+//!
+//! struct OrderV2Enabled;
+//!
+//! impl Flag for OrderV2Enabled {
+//!     const NAME: &'static str = "order_v2_enabled";
+//!     const DEFAULT: bool = false;
+//! }
+//!
+//! # async fn exec(ctx: elfo::Context) {
+//! if ctx.flag::<OrderV2Enabled>() {
+//!     // ...
+//! }
+//! # }
+//! ```
+//!
+//! The config:
+//! ```toml
+//! [orders]
+//! system.flags.order_v2_enabled = true
+//! ```
+//!
+//! [`Context::flag()`]: crate::Context::flag
+
+use fxhash::FxHashMap;
+use serde::Deserialize;
+
+/// A feature flag, see [the module-level documentation](self) for details.
+pub trait Flag: 'static {
+    /// The name of the flag in the `system.flags` section.
+    const NAME: &'static str;
+    /// The value used if the flag isn't set in the config.
+    const DEFAULT: bool;
+}
+
+// === FlagsConfig ===
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct FlagsConfig(FxHashMap<String, bool>);
+
+impl FlagsConfig {
+    pub(crate) fn get<F: Flag>(&self) -> bool {
+        self.0.get(F::NAME).copied().unwrap_or(F::DEFAULT)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Enabled;
+
+    impl Flag for Enabled {
+        const DEFAULT: bool = true;
+        const NAME: &'static str = "enabled";
+    }
+
+    struct Disabled;
+
+    impl Flag for Disabled {
+        const DEFAULT: bool = false;
+        const NAME: &'static str = "disabled";
+    }
+
+    #[test]
+    fn defaults() {
+        let config = FlagsConfig::default();
+        assert!(config.get::<Enabled>());
+        assert!(!config.get::<Disabled>());
+
+        let config: FlagsConfig =
+            serde_json::from_str(r#"{"enabled": false, "disabled": true}"#).unwrap();
+        assert!(!config.get::<Enabled>());
+        assert!(config.get::<Disabled>());
+    }
+}
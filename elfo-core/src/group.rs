@@ -1,4 +1,10 @@
-use std::{fmt::Debug, future::Future, marker::PhantomData, sync::Arc};
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
 
 use futures::future::BoxFuture;
 
@@ -8,6 +14,7 @@ use crate::{
     envelope::Envelope,
     exec::{Exec, ExecResult},
     object::{GroupHandle, GroupVisitor, Object},
+    restart_budget::RestartTracker,
     routers::Router,
     runtime::RuntimeManager,
     supervisor::Supervisor,
@@ -17,7 +24,17 @@ use crate::{
 pub struct ActorGroup<R, C> {
     restart_policy: RestartPolicy,
     termination_policy: TerminationPolicy,
+    start_policy: StartPolicy,
+    max_actors: Option<usize>,
+    target_actors: Option<usize>,
+    admission_policy: AdmissionPolicy,
+    idle_timeout: Option<Duration>,
+    concurrency_limit: Option<usize>,
+    max_deferred_routes: usize,
+    forward_dead_letters: bool,
+    guard: Option<Guard>,
     router: R,
+    handled_protocols: Option<Vec<String>>,
     _config: PhantomData<C>,
 }
 
@@ -27,7 +44,17 @@ impl ActorGroup<(), ()> {
         Self {
             restart_policy: RestartPolicy::default(),
             termination_policy: TerminationPolicy::default(),
+            start_policy: StartPolicy::default(),
+            max_actors: None,
+            target_actors: None,
+            admission_policy: AdmissionPolicy::default(),
+            idle_timeout: None,
+            concurrency_limit: None,
+            max_deferred_routes: 64,
+            forward_dead_letters: false,
+            guard: None,
             router: (),
+            handled_protocols: None,
             _config: PhantomData,
         }
     }
@@ -38,7 +65,17 @@ impl<R, C> ActorGroup<R, C> {
         ActorGroup {
             restart_policy: self.restart_policy,
             termination_policy: self.termination_policy,
+            start_policy: self.start_policy,
+            max_actors: self.max_actors,
+            target_actors: self.target_actors,
+            admission_policy: self.admission_policy,
+            idle_timeout: self.idle_timeout,
+            concurrency_limit: self.concurrency_limit,
+            max_deferred_routes: self.max_deferred_routes,
+            forward_dead_letters: self.forward_dead_letters,
+            guard: self.guard,
             router: self.router,
+            handled_protocols: self.handled_protocols,
             _config: PhantomData,
         }
     }
@@ -57,11 +94,137 @@ impl<R, C> ActorGroup<R, C> {
         self
     }
 
+    /// The behaviour on node boot.
+    /// `StartPolicy::Eager` is used by default.
+    pub fn start_policy(mut self, policy: StartPolicy) -> Self {
+        self.start_policy = policy;
+        self
+    }
+
+    /// Limits the number of actors in the group. Restarts aren't limited.
+    /// Unlimited by default.
+    pub fn max_actors(mut self, limit: usize) -> Self {
+        self.max_actors = Some(limit);
+        self
+    }
+
+    /// Evicts the least recently active actors once the number of actors
+    /// exceeds the target. Unlike [`ActorGroup::max_actors()`], new actors are
+    /// always spawned, so it suits cache-like groups. Disabled by default.
+    ///
+    /// Evicted actors are closed and aren't restarted. They're reported to
+    /// status subscribers as `Terminating` with the "evicted" details.
+    pub fn target_actors(mut self, target: usize) -> Self {
+        self.target_actors = Some(target);
+        self
+    }
+
+    /// The behaviour when a new actor would exceed
+    /// [`ActorGroup::max_actors()`]. `AdmissionPolicy::reject` is used by
+    /// default.
+    pub fn admission_policy(mut self, policy: AdmissionPolicy) -> Self {
+        self.admission_policy = policy;
+        self
+    }
+
+    /// Closes actors that haven't received messages for the provided duration.
+    /// Pings aren't counted. Before closing, the actor receives
+    /// [`IdleTimeout`] and can call [`Context::keep_alive()`] to veto it.
+    /// Closed actors aren't restarted. Disabled by default.
+    ///
+    /// [`IdleTimeout`]: crate::messages::IdleTimeout
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the number of actors handling messages simultaneously, e.g. if
+    /// all of them use the same database with a hard connection limit.
+    /// An actor is considered to handle a message from returning it by
+    /// [`Context::recv()`] or [`Context::try_recv()`] until the next call of
+    /// them. Disabled by default.
+    ///
+    /// Pings are responded regardless of the limit.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Limits the number of concurrently resolved `Outcome::Deferred`.
+    /// 64 is used by default.
+    pub fn max_deferred_routes(mut self, limit: usize) -> Self {
+        self.max_deferred_routes = limit;
+        self
+    }
+
+    /// Sends messages discarded by `Outcome::DiscardWith` as
+    /// `messages::DeadLetter`, which should be routed by the topology.
+    /// Disabled by default.
+    pub fn forward_dead_letters(mut self, enabled: bool) -> Self {
+        self.forward_dead_letters = enabled;
+        self
+    }
+
+    /// Installs a predicate checked on the sender's side before routing, so
+    /// rejected messages neither reach the router nor occupy mailboxes.
+    /// It's a cheaper complement to routing for broadcast-heavy topologies,
+    /// e.g. to skip message types irrelevant to the group.
+    ///
+    /// Rejected messages are discarded as by `Outcome::DiscardWith("guard")`,
+    /// i.e. counted by `elfo_discarded_messages_total` and sent as dead letters
+    /// if [`ActorGroup::forward_dead_letters()`] is enabled. System messages
+    /// (configs, pings, termination and so on) aren't checked.
+    ///
+    /// The predicate is called for every message sent to the group, so it
+    /// should be fast and must not block.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use elfo::{message, msg, ActorGroup};
+    /// # #[message] struct Trade;
+    /// # #[message] struct Quote;
+    /// ActorGroup::new().guard(|envelope| {
+    ///     msg!(match envelope {
+    ///         Trade | Quote => true,
+    ///         _ => false,
+    ///     })
+    /// })
+    /// # ;
+    /// ```
+    pub fn guard(mut self, guard: impl Fn(&Envelope) -> bool + Send + Sync + 'static) -> Self {
+        self.guard = Some(Guard(Arc::new(guard)));
+        self
+    }
+
+    /// Declares protocols of messages handled by actors of this group.
+    ///
+    /// It's used to verify pipelines declared by [`Local::pipeline()`] at
+    /// startup. Can be called multiple times to extend the list.
+    ///
+    /// [`Local::pipeline()`]: crate::topology::Local::pipeline
+    pub fn handles<P: Into<String>>(mut self, protocols: impl IntoIterator<Item = P>) -> Self {
+        self.handled_protocols
+            .get_or_insert_with(Vec::new)
+            .extend(protocols.into_iter().map(Into::into));
+        self
+    }
+
     pub fn router<R1: Router<C>>(self, router: R1) -> ActorGroup<R1, C> {
         ActorGroup {
             restart_policy: self.restart_policy,
             termination_policy: self.termination_policy,
+            start_policy: self.start_policy,
+            max_actors: self.max_actors,
+            target_actors: self.target_actors,
+            admission_policy: self.admission_policy,
+            idle_timeout: self.idle_timeout,
+            concurrency_limit: self.concurrency_limit,
+            max_deferred_routes: self.max_deferred_routes,
+            forward_dead_letters: self.forward_dead_letters,
+            guard: self.guard,
             router,
+            handled_protocols: self.handled_protocols,
             _config: self._config,
         }
     }
@@ -74,7 +237,12 @@ impl<R, C> ActorGroup<R, C> {
         ER: ExecResult,
         C: Config,
     {
-        let run = move |ctx: Context, name: String, rt_manager: RuntimeManager| {
+        let handled_protocols = self.handled_protocols;
+        let run = move |ctx: Context,
+                        name: String,
+                        rt_manager: RuntimeManager,
+                        restarts: Arc<RestartTracker>,
+                        is_gated: bool| {
             let addr = ctx.group();
             let sv = Arc::new(Supervisor::new(
                 ctx,
@@ -83,13 +251,27 @@ impl<R, C> ActorGroup<R, C> {
                 self.router,
                 self.restart_policy,
                 self.termination_policy,
+                self.start_policy,
+                self.max_actors,
+                self.target_actors,
+                self.admission_policy,
+                self.idle_timeout,
+                self.concurrency_limit,
+                self.max_deferred_routes,
+                self.forward_dead_letters,
+                self.guard,
                 rt_manager,
+                restarts,
+                is_gated,
             ));
 
             Object::new(addr, Box::new(Handle(sv)) as Box<dyn GroupHandle>)
         };
 
-        Blueprint { run: Box::new(run) }
+        Blueprint {
+            run: Box::new(run),
+            handled_protocols,
+        }
     }
 }
 
@@ -111,8 +293,27 @@ where
     }
 }
 
+/// A predicate installed by [`ActorGroup::guard()`].
+pub(crate) struct Guard(Arc<dyn Fn(&Envelope) -> bool + Send + Sync>);
+
+impl Guard {
+    pub(crate) fn check(&self, envelope: &Envelope) -> bool {
+        (self.0)(envelope)
+    }
+}
+
+impl Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Guard")
+    }
+}
+
 pub struct Blueprint {
-    pub(crate) run: Box<dyn FnOnce(Context, String, RuntimeManager) -> Object>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) run:
+        Box<dyn FnOnce(Context, String, RuntimeManager, Arc<RestartTracker>, bool) -> Object>,
+    /// `None` if the group doesn't declare handled protocols.
+    pub(crate) handled_protocols: Option<Vec<String>>,
 }
 
 /// The behaviour on the `Terminate` message.
@@ -156,7 +357,81 @@ impl TerminationPolicy {
     // TODO: add `stop_spawning`?
 }
 
+/// The behaviour on node boot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StartPolicy {
+    /// Actors are spawned once the group receives its config, according to
+    /// the outcome of the router for `UpdateConfig`.
+    ///
+    /// This behaviour is used by default.
+    #[default]
+    Eager,
+    /// Actors are spawned only when messages are routed to them.
+    /// Useful for rarely used groups.
+    OnFirstMessage,
+    /// Actors aren't spawned until the group receives the [`StartGroup`]
+    /// message, then behaves like `Eager`. Messages routed to the group before
+    /// that are discarded. Useful for staged startup.
+    ///
+    /// [`StartGroup`]: crate::messages::StartGroup
+    Manual,
+}
+
+/// The behaviour when a new actor would exceed [`ActorGroup::max_actors()`].
+#[derive(Debug, Clone)]
+pub struct AdmissionPolicy {
+    pub(crate) mode: AdmissionMode,
+}
+
+impl Default for AdmissionPolicy {
+    fn default() -> Self {
+        Self::reject()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum AdmissionMode {
+    Reject,
+    Queue { capacity: usize },
+    EvictLeastRecentlyActive,
+}
+
+impl AdmissionPolicy {
+    /// The actor isn't spawned, the message is discarded and the sender gets
+    /// an error.
+    ///
+    /// This behaviour is used by default.
+    pub fn reject() -> Self {
+        Self {
+            mode: AdmissionMode::Reject,
+        }
+    }
+
+    /// Unicast messages are queued until some actor is terminated, then they
+    /// are routed again. If the queue is full, messages are rejected.
+    /// Multicast messages are delivered only to existing actors.
+    pub fn queue(capacity: usize) -> Self {
+        Self {
+            mode: AdmissionMode::Queue { capacity },
+        }
+    }
+
+    /// The mailbox of the actor which hasn't received messages for the longest
+    /// time is closed, and the new actor is spawned immediately. The evicted
+    /// actor is handled like by [`ActorGroup::target_actors()`].
+    pub fn evict_least_recently_active() -> Self {
+        Self {
+            mode: AdmissionMode::EvictLeastRecentlyActive,
+        }
+    }
+}
+
 /// The behaviour on actor termination.
+///
+/// Failures classified by [`ExecError`] can override it, see [`FailureKind`].
+///
+/// [`ExecError`]: crate::errors::ExecError
+/// [`FailureKind`]: crate::errors::FailureKind
 #[derive(Debug, Clone)]
 pub struct RestartPolicy {
     pub(crate) mode: RestartMode,
@@ -0,0 +1,204 @@
+use std::{future::Future, sync::Arc};
+
+use once_cell::sync::OnceCell;
+use tokio::runtime::{EnterGuard, Handle};
+use tracing::level_filters::LevelFilter;
+
+use crate::{
+    actor::{Actor, ActorMeta},
+    address_book::AddressBook,
+    config::SystemConfig,
+    context::Context,
+    errors::{RequestError, SendError, TrySendError},
+    message::{Message, Request},
+    object::Object,
+    scope::{Scope, ScopeGroupShared},
+    subscription::SubscriptionManager,
+    tracing::TraceId,
+    Addr,
+};
+
+/// Sends messages and requests into the actor system from non-actor code,
+/// e.g. HTTP handlers or FFI callbacks. Created by [`Local::handle()`].
+///
+/// The handle has its own address inside the group, which is used as the
+/// sender of messages and the requester. Messages are routed by routes of the
+/// group, defined by [`Local::route_to()`].
+///
+/// Every call uses a new trace id unless [`SystemHandle::with_trace_id()`]
+/// is used. Cloning is cheap, all clones share the same address, which is
+/// released once all of them are dropped.
+///
+/// Async methods must be called inside the tokio runtime the system is
+/// started on. Sync methods can be called from any thread once the system
+/// is started, they enter the runtime themselves.
+///
+/// [`Local::handle()`]: crate::topology::Local::handle
+/// [`Local::route_to()`]: crate::topology::Local::route_to
+#[derive(Clone)]
+pub struct SystemHandle {
+    inner: Arc<Inner>,
+    trace_id: Option<TraceId>,
+}
+
+struct Inner {
+    ctx: Context,
+    meta: Arc<ActorMeta>,
+    scope_shared: Arc<ScopeGroupShared>,
+    /// Set once the system is started, see `Topology::set_system_rt()`.
+    rt: Arc<OnceCell<Handle>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.ctx.book().remove(self.ctx.addr());
+    }
+}
+
+impl SystemHandle {
+    pub(crate) fn new(
+        book: AddressBook,
+        ctx: Context,
+        group: String,
+        rt: Arc<OnceCell<Handle>>,
+    ) -> Self {
+        let group_addr = ctx.group();
+        let group_no = group_addr.group_no().expect("invalid group addr");
+        let entry = book.vacant_entry(group_no);
+        let addr = entry.addr();
+
+        let meta = Arc::new(ActorMeta {
+            group,
+            key: "handle".into(),
+        });
+
+        // Just like the init actor.
+        let scope_shared = ScopeGroupShared::new(
+            group_addr,
+            book.node_no().clone(),
+            book.clock(),
+            book.journal().clone(),
+        );
+        let mut config = SystemConfig::default();
+        config.logging.max_level = LevelFilter::INFO;
+        scope_shared.configure(&config);
+        let scope_shared = Arc::new(scope_shared);
+
+        let actor = Actor::new(
+            meta.clone(),
+            addr,
+            Default::default(),
+            Arc::new(SubscriptionManager::new(ctx.clone())),
+        );
+        // The handle doesn't receive regular messages.
+        let scope = Scope::new(
+            TraceId::generate(),
+            addr,
+            meta.clone(),
+            scope_shared.clone(),
+        );
+        scope.sync_within(|| actor.close());
+        entry.insert(Object::new(addr, actor));
+
+        Self {
+            inner: Arc::new(Inner {
+                // It must be called after `entry.insert()`.
+                ctx: ctx.with_addr(addr),
+                meta,
+                scope_shared,
+                rt,
+            }),
+            trace_id: None,
+        }
+    }
+
+    /// Returns the address of the handle.
+    pub fn addr(&self) -> Addr {
+        self.inner.ctx.addr()
+    }
+
+    /// Returns a handle using the provided trace id for all calls, e.g. to
+    /// continue a trace started by an external request.
+    pub fn with_trace_id(&self, trace_id: TraceId) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            trace_id: Some(trace_id),
+        }
+    }
+
+    /// Sends a message using routes of the group.
+    /// See [`Context::send()`] for details.
+    pub async fn send<M: Message>(&self, message: M) -> Result<(), SendError<M>> {
+        let ctx = &self.inner.ctx;
+        self.scope().within(ctx.send(message)).await
+    }
+
+    /// Sends a message to the specified recipient.
+    /// See [`Context::send_to()`] for details.
+    pub async fn send_to<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+    ) -> Result<(), SendError<M>> {
+        let ctx = &self.inner.ctx;
+        self.scope().within(ctx.send_to(recipient, message)).await
+    }
+
+    /// Tries to send a message without waiting, so it can be used in sync code.
+    /// See [`Context::try_send()`] for details.
+    pub fn try_send<M: Message>(&self, message: M) -> Result<(), TrySendError<M>> {
+        let ctx = &self.inner.ctx;
+        let _rt = self.enter_runtime();
+        self.scope().sync_within(|| ctx.try_send(message))
+    }
+
+    /// Tries to send a message to the specified recipient without waiting.
+    /// See [`Context::try_send_to()`] for details.
+    pub fn try_send_to<M: Message>(
+        &self,
+        recipient: Addr,
+        message: M,
+    ) -> Result<(), TrySendError<M>> {
+        let ctx = &self.inner.ctx;
+        let _rt = self.enter_runtime();
+        self.scope()
+            .sync_within(|| ctx.try_send_to(recipient, message))
+    }
+
+    /// Sends a request using routes of the group and waits for the response.
+    /// See [`Context::request()`] for details.
+    pub fn request<R: Request>(
+        &self,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + '_ {
+        let ctx = &self.inner.ctx;
+        self.scope().within(ctx.request(request).resolve())
+    }
+
+    /// Sends a request to the specified recipient and waits for the response.
+    /// See [`Context::request_to()`] for details.
+    pub fn request_to<R: Request>(
+        &self,
+        recipient: Addr,
+        request: R,
+    ) -> impl Future<Output = Result<R::Response, RequestError>> + '_ {
+        let ctx = &self.inner.ctx;
+        self.scope()
+            .within(ctx.request_to(recipient, request).resolve())
+    }
+
+    // Sending can spawn tasks, e.g. new actors of keyed groups.
+    fn enter_runtime(&self) -> Option<EnterGuard<'_>> {
+        self.inner.rt.get().map(Handle::enter)
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::new(
+            self.trace_id
+                .unwrap_or_else(|| TraceId::generate_on(self.inner.ctx.book().node_no().get())),
+            self.addr(),
+            self.inner.meta.clone(),
+            self.inner.scope_shared.clone(),
+        )
+    }
+}
@@ -0,0 +1,210 @@
+//! Timeouts of message handling, configured by `system.handling`.
+//!
+//! A message is handled from receiving it by `recv()` or `try_recv()` until
+//! the next call of them. If handling takes longer than the timeout, e.g. the
+//! handler waits for a hung external call, the actor is considered stuck: an
+//! error with the offending message is logged and counted by the
+//! `elfo_handling_timeouts_total` metric. Then the policy is applied:
+//! * `Alert` (default): nothing else, the handler continues.
+//! * `Restart`: the handler is cancelled and the actor is restarted regardless
+//!   of the restart policy. Such restarts are accounted by the restart budget.
+//!
+//! Like message spans, the deadline is tracked by the actor and checked on
+//! every poll of the actor's future by [`WithHandlingTimeout`].
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use metrics::{Key, Label};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use serde::Deserialize;
+use tokio::time::{Instant, Sleep};
+use tracing::error;
+
+use crate::{config::types, message::Message};
+
+// === HandlingConfig ===
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct HandlingConfig {
+    /// The timeout for all messages, unlimited by default.
+    #[serde(with = "humantime_serde")]
+    pub(crate) timeout: Option<Duration>,
+    /// Overrides the timeout for messages with specified names.
+    pub(crate) timeouts: HashMap<String, types::Duration>,
+    pub(crate) on_timeout: OnTimeout,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub(crate) enum OnTimeout {
+    #[default]
+    Alert,
+    Restart,
+}
+
+impl HandlingConfig {
+    fn timeout(&self, name: &str) -> Option<Duration> {
+        if self.timeouts.is_empty() {
+            return self.timeout;
+        }
+
+        self.timeouts.get(name).map(|t| t.0).or(self.timeout)
+    }
+}
+
+// === HandlingTimeout ===
+
+/// The deadline of the message being handled by an actor.
+#[derive(Default)]
+pub(crate) struct HandlingTimeout(Mutex<Option<InHandling>>);
+
+struct InHandling {
+    name: &'static str,
+    labels: &'static [Label],
+    timeout: Duration,
+    deadline: Instant,
+    on_timeout: OnTimeout,
+}
+
+impl HandlingTimeout {
+    /// Starts tracking the received message if the timeout is configured.
+    pub(crate) fn start(&self, message: &impl Message, config: &HandlingConfig) {
+        let timeout = ward!(config.timeout(message.name()));
+
+        *self.0.lock() = Some(InHandling {
+            name: message.name(),
+            labels: message.labels(),
+            timeout,
+            deadline: Instant::now() + timeout,
+            on_timeout: config.on_timeout,
+        });
+    }
+
+    /// Stops tracking the current message, if any.
+    pub(crate) fn finish(&self) {
+        self.0.lock().take();
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.0.lock().as_ref().map(|h| h.deadline)
+    }
+
+    /// Reports the expired handling. Returns an error if the handler must be
+    /// cancelled, otherwise stops tracking to report it only once.
+    fn on_expired(&self) -> Option<TimedOut> {
+        let in_handling = self.0.lock().take()?;
+
+        if let Some(recorder) = metrics::try_recorder() {
+            let key = Key::from_static_parts("elfo_handling_timeouts_total", in_handling.labels);
+            recorder.increment_counter(&key, 1);
+        }
+
+        error!(
+            message = in_handling.name,
+            timeout = ?in_handling.timeout,
+            policy = ?in_handling.on_timeout,
+            "message handling has timed out, the actor is stuck"
+        );
+
+        (in_handling.on_timeout == OnTimeout::Restart).then_some(TimedOut {
+            name: in_handling.name,
+            timeout: in_handling.timeout,
+        })
+    }
+}
+
+/// The handler has been cancelled by the timeout.
+pub(crate) struct TimedOut {
+    name: &'static str,
+    timeout: Duration,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "handling of `{}` has timed out after {:?}",
+            self.name, self.timeout
+        )
+    }
+}
+
+// === WithHandlingTimeout ===
+
+/// Waits for the deadline of the current message on every poll of the inner
+/// future and cancels it if required by the policy.
+#[pin_project]
+pub(crate) struct WithHandlingTimeout<'a, F> {
+    #[pin]
+    inner: F,
+    handling: &'a HandlingTimeout,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<'a, F> WithHandlingTimeout<'a, F> {
+    pub(crate) fn new(inner: F, handling: &'a HandlingTimeout) -> Self {
+        Self {
+            inner,
+            handling,
+            sleep: None,
+        }
+    }
+}
+
+impl<F: Future> Future for WithHandlingTimeout<'_, F> {
+    type Output = Result<F::Output, TimedOut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        // Poll the inner future first, because it starts and finishes handling.
+        if let Poll::Ready(output) = this.inner.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        let deadline = ward!(this.handling.deadline(), return Poll::Pending);
+        let sleep = this
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+
+        if sleep.deadline() != deadline {
+            sleep.as_mut().reset(deadline);
+        }
+
+        ready!(sleep.as_mut().poll(cx));
+
+        match this.handling.on_expired() {
+            Some(timed_out) => Poll::Ready(Err(timed_out)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_per_message() {
+        let mut config = HandlingConfig::default();
+        assert_eq!(config.timeout("A"), None);
+
+        config
+            .timeouts
+            .insert("A".into(), Duration::from_secs(5).into());
+        assert_eq!(config.timeout("A"), Some(Duration::from_secs(5)));
+        assert_eq!(config.timeout("B"), None);
+
+        config.timeout = Some(Duration::from_secs(1));
+        assert_eq!(config.timeout("A"), Some(Duration::from_secs(5)));
+        assert_eq!(config.timeout("B"), Some(Duration::from_secs(1)));
+    }
+}
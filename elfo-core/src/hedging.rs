@@ -0,0 +1,140 @@
+//! Hedging of idempotent requests to reduce tail latency.
+//!
+//! A request marked as `#[message(ret = R, idempotent)]` and routed by the
+//! topology to multiple recipients (e.g. the same remote group on different
+//! nodes) is sent only to the first one. Routers inside groups aren't involved.
+//! If there is no response after the delay, which is the configured percentile
+//! of latencies of recent requests of the same type, a duplicate is sent to the
+//! second recipient. The first successful response is taken, the other one is
+//! discarded once received.
+//!
+//! Until enough latencies are collected, requests are sent as usual.
+//! Hedged requests are counted by the `elfo_hedged_requests_total` metric.
+
+use std::{any::TypeId, time::Duration};
+
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+/// The number of recent latencies kept per request type.
+const CAPACITY: usize = 100;
+
+// === HedgingConfig ===
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct HedgingConfig {
+    pub(crate) disabled: bool,
+    /// The percentile of latencies used as the delay.
+    pub(crate) percentile: f64,
+    /// The minimum number of latencies to start hedging.
+    pub(crate) min_samples: usize,
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            disabled: false,
+            percentile: 0.95,
+            min_samples: 20,
+        }
+    }
+}
+
+// === RequestLatencies ===
+
+/// Latencies of recent successful requests of each type sent by the actor.
+#[derive(Default)]
+pub(crate) struct RequestLatencies {
+    map: Mutex<FxHashMap<TypeId, Samples>>,
+}
+
+impl RequestLatencies {
+    pub(crate) fn push(&self, type_id: TypeId, latency: Duration) {
+        self.map.lock().entry(type_id).or_default().push(latency);
+    }
+
+    /// Returns the delay before hedging or `None` if there are not enough
+    /// samples yet.
+    pub(crate) fn delay(&self, type_id: TypeId, config: &HedgingConfig) -> Option<Duration> {
+        let map = self.map.lock();
+        let samples = map.get(&type_id)?;
+
+        if samples.list.len() < config.min_samples.max(1) {
+            return None;
+        }
+
+        Some(samples.percentile(config.percentile))
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    list: Vec<Duration>,
+    /// The position to overwrite once the list is full.
+    next: usize,
+}
+
+impl Samples {
+    fn push(&mut self, latency: Duration) {
+        if self.list.len() < CAPACITY {
+            self.list.push(latency);
+        } else {
+            self.list[self.next] = latency;
+            self.next = (self.next + 1) % CAPACITY;
+        }
+    }
+
+    fn percentile(&self, percentile: f64) -> Duration {
+        debug_assert!(!self.list.is_empty());
+
+        let mut sorted = self.list.clone();
+        sorted.sort_unstable();
+
+        let rank = (percentile.clamp(0., 1.) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.saturating_sub(1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay() {
+        let config = HedgingConfig {
+            min_samples: 10,
+            ..Default::default()
+        };
+        let latencies = RequestLatencies::default();
+        let type_id = TypeId::of::<u32>();
+        let ms = Duration::from_millis;
+
+        for i in 1..10 {
+            latencies.push(type_id, ms(i));
+        }
+        assert_eq!(latencies.delay(type_id, &config), None);
+
+        latencies.push(type_id, ms(10));
+        assert_eq!(latencies.delay(type_id, &config), Some(ms(10)));
+        assert_eq!(latencies.delay(TypeId::of::<u64>(), &config), None);
+
+        for i in 11..=100 {
+            latencies.push(type_id, ms(i));
+        }
+        assert_eq!(latencies.delay(type_id, &config), Some(ms(95)));
+
+        // Old latencies are replaced.
+        for _ in 0..50 {
+            latencies.push(type_id, ms(1000));
+        }
+        assert_eq!(latencies.delay(type_id, &config), Some(ms(1000)));
+
+        let config = HedgingConfig {
+            percentile: 0.5,
+            ..config
+        };
+        assert_eq!(latencies.delay(type_id, &config), Some(ms(100)));
+    }
+}
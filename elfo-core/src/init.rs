@@ -2,7 +2,11 @@ use std::{future::Future, sync::Arc, time::Duration};
 
 use futures::future::join_all;
 use tokio::{
-    pin, select,
+    pin,
+    runtime::Handle,
+    select,
+    sync::oneshot,
+    task::JoinHandle,
     time::{sleep, timeout, Instant},
 };
 use tracing::{error, info, level_filters::LevelFilter, warn};
@@ -18,12 +22,12 @@ use crate::{
     demux::Demux,
     errors::{RequestError, StartError, StartGroupError},
     message,
-    messages::{StartEntrypoint, Terminate, UpdateConfig},
+    messages::{Ping, ReleaseBarrier, StartEntrypoint, Terminate, UpdateConfig},
     object::Object,
-    scope::{Scope, ScopeGroupShared},
+    scope::{self, Scope, ScopeGroupShared},
     signal::{Signal, SignalKind},
     subscription::SubscriptionManager,
-    topology::{Topology, SYSTEM_INIT_GROUP_NO},
+    topology::{Barrier, Topology, SYSTEM_INIT_GROUP_NO},
     tracing::TraceId,
 };
 
@@ -49,7 +53,7 @@ async fn start_entrypoints(ctx: &Context, topology: &Topology, is_check_only: bo
                 Ok(Ok(())) => Ok(()),
                 Ok(Err(e)) => Err(StartError::single(group.name.clone(), e.reason)),
                 Err(RequestError::Ignored) => Ok(()),
-                Err(RequestError::Failed) => Err(StartError::single(
+                Err(_) => Err(StartError::single(
                     group.name.clone(),
                     "config cannot be delivered to the entrypoint".into(),
                 )),
@@ -73,7 +77,7 @@ async fn start_entrypoints(ctx: &Context, topology: &Topology, is_check_only: bo
                     Err(StartError::multiple(group_errors))
                 }
                 Err(RequestError::Ignored) => Ok(()),
-                Err(RequestError::Failed) => Err(StartError::single(
+                Err(_) => Err(StartError::single(
                     group.name,
                     "starting message cannot be delivered to the entrypoint".into(),
                 )),
@@ -94,6 +98,64 @@ async fn start_entrypoints(ctx: &Context, topology: &Topology, is_check_only: bo
     }
 }
 
+/// Waits until groups are ready and releases messages held by groups
+/// depending on them, see `Local::wait_for_ready()`.
+fn release_barriers(ctx: &Context, topology: &Topology) {
+    let barriers = topology.barriers();
+    let mut groups = Vec::new();
+    for barrier in &barriers {
+        if !groups.contains(&barrier.group) {
+            groups.push(barrier.group);
+        }
+    }
+
+    for group in groups {
+        let ctx = ctx.pruned();
+        let barriers = barriers
+            .iter()
+            .filter(|b| b.group == group)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let fut = async move {
+            join_all(barriers.iter().map(|b| wait_for_ready(&ctx, b))).await;
+
+            if ctx.send_to(group, ReleaseBarrier).await.is_err() {
+                warn!(%group, "cannot release held messages");
+            }
+        };
+
+        tokio::spawn(scope::expose().within(fut));
+    }
+}
+
+async fn wait_for_ready(ctx: &Context, barrier: &Barrier) {
+    // The group is ready once at least one of its actors handles messages.
+    let is_ready = async {
+        loop {
+            let responses = ctx
+                .request_to(barrier.dependency, Ping)
+                .all()
+                .resolve()
+                .await;
+            if responses.iter().any(Result::is_ok) {
+                break;
+            }
+
+            // There are no actors yet, or all of them have failed.
+            sleep(RETRY_READINESS_CHECK_AFTER).await;
+        }
+    };
+
+    if timeout(barrier.timeout, is_ready).await.is_err() {
+        warn!(
+            group = %barrier.dependency_name,
+            timeout = ?barrier.timeout,
+            "the group isn't ready in time, releasing held messages anyway"
+        );
+    }
+}
+
 /// Starts a node with the provided topology.
 ///
 /// # Panics
@@ -118,6 +180,127 @@ pub async fn try_start(topology: Topology) -> Result<()> {
     res
 }
 
+/// Starts a node with the provided topology on an existing runtime, e.g. to
+/// embed it into a test binary or a service not owned by elfo.
+///
+/// Unlike [`start()`], it doesn't wait for the system to terminate and doesn't
+/// handle signals. Instead, the system is terminated once the returned guard
+/// is dropped or [`SystemGuard::shutdown()`] is called.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # fn topology() -> elfo::Topology { elfo::Topology::empty() }
+/// let rt = tokio::runtime::Runtime::new().unwrap();
+/// let mut guard = elfo::init::start_with_runtime(rt.handle(), topology()).unwrap();
+/// rt.block_on(guard.started()).unwrap();
+///
+/// // ... the system is running ...
+///
+/// drop(guard); // blocks until the system terminates
+/// ```
+pub fn start_with_runtime(rt: &Handle, topology: Topology) -> Result<SystemGuard> {
+    check_messages_uniqueness()?;
+
+    let (started_tx, started_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let task = rt.spawn(do_start(topology, false, |ctx, topology| async move {
+        let _ = started_tx.send(());
+        // Both sending and dropping the sender mean stopping.
+        let _ = stop_rx.await;
+        do_termination(ctx.pruned(), topology).await;
+    }));
+
+    Ok(SystemGuard {
+        rt: rt.clone(),
+        started_rx: Some(started_rx),
+        stop_tx: Some(stop_tx),
+        task: Some(task),
+        deadline: SHUTDOWN_DEADLINE,
+    })
+}
+
+/// Terminates the system started by [`start_with_runtime()`] once dropped.
+///
+/// Dropping the guard outside the runtime blocks the current thread until the
+/// system terminates or the deadline expires. Inside the runtime, it cannot
+/// block, so the system is terminated in the background; use
+/// [`SystemGuard::shutdown()`] to wait for it in async code.
+///
+/// Blocking requires the runtime to be driven by other threads,
+/// i.e. to be a multi-threaded one.
+#[must_use = "the system is terminated once the guard is dropped"]
+pub struct SystemGuard {
+    rt: Handle,
+    started_rx: Option<oneshot::Receiver<()>>,
+    stop_tx: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<Result<()>>>,
+    deadline: Duration,
+}
+
+impl SystemGuard {
+    /// Sets how long to wait for the system to terminate.
+    /// After the deadline, the guard stops waiting and aborts the termination.
+    ///
+    /// 90s by default.
+    pub fn with_shutdown_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Waits until entrypoints are started, i.e. the system is ready to use.
+    /// Returns an error if the system has failed to start.
+    pub async fn started(&mut self) -> Result<()> {
+        let started_rx = ward!(self.started_rx.take(), return Ok(()));
+        if started_rx.await.is_ok() {
+            return Ok(());
+        }
+
+        let task = ward!(self.task.take(), return Ok(()));
+        task.await.expect("the system has panicked")
+    }
+
+    /// Returns `true` if the system is already terminated or failed to start.
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(|task| task.is_finished())
+    }
+
+    /// Terminates the system and waits until it's done or the deadline expires.
+    /// Returns an error if the system has failed to start.
+    pub async fn shutdown(mut self) -> Result<()> {
+        match self.stop() {
+            Some(waiting) => waiting.await.unwrap_or(Ok(())),
+            None => Ok(()),
+        }
+    }
+
+    fn stop(&mut self) -> Option<JoinHandle<Result<()>>> {
+        drop(self.stop_tx.take());
+        let mut task = self.task.take()?;
+        let deadline = self.deadline;
+
+        Some(self.rt.spawn(async move {
+            match timeout(deadline, &mut task).await {
+                Ok(result) => result.unwrap_or(Ok(())),
+                Err(_) => {
+                    task.abort();
+                    Ok(())
+                }
+            }
+        }))
+    }
+}
+
+impl Drop for SystemGuard {
+    fn drop(&mut self) {
+        let waiting = ward!(self.stop());
+
+        if Handle::try_current().is_err() {
+            let _ = self.rt.block_on(waiting);
+        }
+    }
+}
+
 /// Starts node in "check only" mode. Entrypoints are started, then the system
 /// is immediately gracefully terminated.
 pub async fn check_only(topology: Topology) -> Result<()> {
@@ -154,6 +337,29 @@ pub async fn do_start<F: Future>(
     is_check_only: bool,
     and_then: impl FnOnce(Context, Topology) -> F,
 ) -> Result<F::Output> {
+    let (ctx, scope) = start_init_actor(&topology);
+    topology.set_system_rt();
+
+    let init = async move {
+        let mut errors = topology.check_pipelines();
+        errors.extend(topology.check_taps());
+        if !errors.is_empty() {
+            return Err(StartError::multiple(errors));
+        }
+
+        start_entrypoints(&ctx, &topology, is_check_only).await?;
+
+        if !is_check_only {
+            release_barriers(&ctx, &topology);
+        }
+
+        Ok(and_then(ctx, topology).await)
+    };
+    scope.within(init).await
+}
+
+/// Registers the pseudo-actor used by `do_start()` to send messages.
+fn start_init_actor(topology: &Topology) -> (Context, Scope) {
     let group_no = GroupNo::new(SYSTEM_INIT_GROUP_NO, topology.launch_id()).unwrap();
     let entry = topology.book.vacant_entry(group_no);
     let addr = entry.addr();
@@ -172,23 +378,25 @@ pub async fn do_start<F: Future>(
         Arc::new(SubscriptionManager::new(ctx.clone())),
     );
 
-    let scope_shared = ScopeGroupShared::new(addr);
+    let scope_shared = ScopeGroupShared::new(
+        addr,
+        topology.book.node_no().clone(),
+        topology.book.clock(),
+        topology.book.journal().clone(),
+    );
     let mut config = SystemConfig::default();
     config.logging.max_level = LevelFilter::INFO;
     scope_shared.configure(&config);
 
-    let scope = Scope::new(TraceId::generate(), addr, meta, Arc::new(scope_shared));
+    let trace_id = TraceId::generate_on(topology.node_no());
+    let scope = Scope::new(trace_id, addr, meta, Arc::new(scope_shared));
     scope.clone().sync_within(|| actor.on_start()); // need to emit initial metrics
     entry.insert(Object::new(addr, actor));
 
     // It must be called after `entry.insert()`.
     let ctx = ctx.with_addr(addr);
 
-    let init = async move {
-        start_entrypoints(&ctx, &topology, is_check_only).await?;
-        Ok(and_then(ctx, topology).await)
-    };
-    scope.within(init).await
+    (ctx, scope)
 }
 
 #[message]
@@ -198,8 +406,10 @@ struct TerminateSystem;
 struct CheckMemoryUsageTick;
 
 // TODO: make these values configurable.
+const RETRY_READINESS_CHECK_AFTER: Duration = Duration::from_millis(100);
 const SEND_CLOSING_TERMINATE_AFTER: Duration = Duration::from_secs(30);
 const STOP_GROUP_TERMINATION_AFTER: Duration = Duration::from_secs(45);
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(90);
 
 async fn termination(mut ctx: Context, topology: Topology) {
     ctx.attach(Signal::new(SignalKind::UnixTerminate, TerminateSystem));
@@ -0,0 +1,220 @@
+//! The system journal keeps recent system events in memory.
+//!
+//! Events (actor lifecycle, restarts, config updates and network connections)
+//! are recorded automatically into a ring buffer of the topology, the oldest
+//! events are evicted once the capacity is reached. Unlike logs, the journal
+//! is always available in the process, so recent history can be inspected
+//! even if logs are rotated or filtered out. Use [`recent()`] inside actors,
+//! [`Topology::journal()`] outside or the `GetRecentEvents` request of the
+//! telemeter to query it.
+//!
+//! Every topology has its own journal, so several nodes running in one
+//! process (e.g. in integration tests) don't mix their events.
+//!
+//! [`Topology::journal()`]: crate::topology::Topology::journal
+
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{ActorMeta, ActorStatus},
+    addr::NodeNo,
+    scope,
+    tracing::TraceId,
+};
+
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// A system event recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Event {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The trace id of the scope the event is recorded in.
+    pub trace_id: TraceId,
+    /// The actor (or group) the event relates to.
+    pub meta: Arc<ActorMeta>,
+    /// What happened.
+    pub kind: EventKind,
+}
+
+/// The kind of a system event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum EventKind {
+    /// The actor's status is changed.
+    StatusChanged {
+        /// The new status.
+        status: ActorStatus,
+    },
+    /// The actor will be restarted after the delay.
+    Restarting {
+        /// The delay before the restart.
+        after: Duration,
+    },
+    /// The group's config is updated.
+    ConfigUpdated,
+    /// A connection to a remote group is established.
+    Connected {
+        /// The remote node.
+        node_no: NodeNo,
+        /// The remote group's name.
+        group: String,
+    },
+    /// A connection to a remote group is closed.
+    Disconnected {
+        /// The remote node.
+        node_no: NodeNo,
+        /// The remote group's name.
+        group: String,
+    },
+}
+
+/// The journal of a topology, cheap to clone.
+#[derive(Clone)]
+pub struct Journal(Arc<Mutex<Inner>>);
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(Inner::new(DEFAULT_CAPACITY))))
+    }
+}
+
+impl Journal {
+    /// Sets the maximum number of events kept in the journal.
+    /// The oldest events are evicted immediately if there are more of them.
+    ///
+    /// `1000` by default, `0` disables the journal.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.0.lock().set_capacity(capacity);
+    }
+
+    /// Returns up to `limit` most recent events, the oldest first.
+    pub fn recent(&self, limit: usize) -> Vec<Event> {
+        self.0.lock().recent(limit)
+    }
+
+    fn push(&self, event: Event) {
+        self.0.lock().push(event);
+    }
+}
+
+/// Sets the maximum number of events kept in the journal of the current
+/// topology, see [`Journal::set_capacity()`].
+///
+/// Does nothing if called outside the actor system.
+pub fn set_capacity(capacity: usize) {
+    scope::try_with(|scope| scope.journal().set_capacity(capacity));
+}
+
+/// Returns up to `limit` most recent events of the current topology, the
+/// oldest first.
+///
+/// Returns nothing if called outside the actor system.
+pub fn recent(limit: usize) -> Vec<Event> {
+    scope::try_with(|scope| scope.journal().recent(limit)).unwrap_or_default()
+}
+
+/// Records the event into the journal of the current topology, the actor's
+/// meta and the trace id are taken from the current scope.
+///
+/// Does nothing if called outside the actor system.
+#[stability::unstable]
+pub fn record(kind: EventKind) {
+    let timestamp_ms = crate::time::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+
+    scope::try_with(|scope| {
+        scope.journal().push(Event {
+            timestamp_ms,
+            trace_id: scope.trace_id(),
+            meta: scope.meta().clone(),
+            kind,
+        })
+    });
+}
+
+struct Inner {
+    events: VecDeque<Event>,
+    capacity: usize,
+}
+
+impl Inner {
+    fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.truncate();
+        self.events.shrink_to(capacity);
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.events.push_back(event);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.events.len().saturating_sub(self.capacity);
+        self.events.drain(..excess);
+    }
+
+    fn recent(&self, limit: usize) -> Vec<Event> {
+        let skip = self.events.len().saturating_sub(limit);
+        self.events.iter().skip(skip).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(no: u64) -> Event {
+        Event {
+            timestamp_ms: no,
+            trace_id: TraceId::try_from(1).unwrap(),
+            meta: Arc::new(ActorMeta {
+                group: "group".into(),
+                key: String::new(),
+            }),
+            kind: EventKind::ConfigUpdated,
+        }
+    }
+
+    fn timestamps(events: Vec<Event>) -> Vec<u64> {
+        events.into_iter().map(|e| e.timestamp_ms).collect()
+    }
+
+    #[test]
+    fn it_evicts_oldest_events() {
+        let journal = Journal::default();
+        journal.set_capacity(3);
+        (1..=5).for_each(|no| journal.push(event(no)));
+
+        assert_eq!(timestamps(journal.recent(10)), vec![3, 4, 5]);
+        assert_eq!(timestamps(journal.recent(2)), vec![4, 5]);
+
+        journal.set_capacity(1);
+        assert_eq!(timestamps(journal.recent(10)), vec![5]);
+
+        journal.set_capacity(0);
+        journal.push(event(6));
+        assert!(journal.recent(10).is_empty());
+    }
+}
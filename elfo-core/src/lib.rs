@@ -1,4 +1,5 @@
 #![warn(rust_2018_idioms, unreachable_pub)] // TODO: add `missing_docs`.
+#![allow(clippy::result_large_err)] // `Envelope` and `AnyMessage` are large by design.
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 #[macro_use]
@@ -12,15 +13,24 @@ extern crate self as elfo_core;
 // TODO: revise this list
 pub use crate::{
     actor::{ActorMeta, ActorStatus, ActorStatusKind},
-    addr::Addr,
+    actor_id::ActorId,
+    addr::{Addr, NodeNo},
+    client::Requester,
     config::Config,
-    context::{Context, RequestBuilder},
+    context::{Context, RecvOr, RequestBuilder, SendHandle},
     envelope::Envelope,
-    group::{ActorGroup, Blueprint, RestartPolicy, TerminationPolicy},
+    group::{
+        ActorGroup, AdmissionPolicy, Blueprint, RestartPolicy, StartPolicy, TerminationPolicy,
+    },
+    handle::SystemHandle,
+    init::{start_with_runtime, SystemGuard},
     local::{Local, MoveOwnership},
     message::{Message, Request},
     request_table::ResponseToken,
+    restart_budget::RestartBudget,
+    shutdown::ShutdownToken,
     source::{SourceHandle, UnattachedSource},
+    task::TaskOutput,
     topology::Topology,
 };
 pub use elfo_macros::{message_core as message, msg_core as msg};
@@ -31,13 +41,17 @@ mod macros;
 pub mod config;
 pub mod dumping;
 pub mod errors;
+pub mod flags;
 pub mod init;
+pub mod journal;
 pub mod logging;
 pub mod messages;
 pub mod node;
+pub mod registry;
 pub mod routers;
 pub mod scope;
 pub mod signal;
+pub mod state_machine;
 pub mod stream;
 #[cfg(feature = "unstable-stuck-detection")]
 pub mod stuck_detection;
@@ -46,29 +60,38 @@ pub mod topology;
 pub mod tracing;
 
 mod actor;
+mod actor_id;
 mod addr;
 mod address_book;
+mod client;
 mod context;
 mod demux;
 mod envelope;
 mod exec;
 mod group;
+mod handle;
+mod handling;
+mod hedging;
 mod local;
 mod mailbox;
 #[cfg(target_os = "linux")]
 mod memory_tracker;
 mod message;
 mod object;
+mod overload;
 mod permissions;
 #[cfg(all(feature = "network", feature = "unstable"))]
 pub mod remote;
 #[cfg(all(feature = "network", not(feature = "unstable")))]
 mod remote;
 mod request_table;
+mod restart_budget;
 mod runtime;
+mod shutdown;
 mod source;
 mod subscription;
 mod supervisor;
+mod task;
 mod telemetry;
 mod thread;
 
@@ -91,7 +114,7 @@ pub mod _priv {
         message::*,
         object::{GroupVisitor, Object, ObjectArc},
         permissions::{AtomicPermissions, Permissions},
-        request_table::RequestId,
+        request_table::{RemoteCancellation, RequestId},
     };
     pub use erased_serde;
     pub use linkme;
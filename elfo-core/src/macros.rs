@@ -57,6 +57,103 @@ macro_rules! set_protocol {
     };
 }
 
+/// Allows tapping messages of the protocol of the current module, i.e.
+/// copying them to auditing groups by [`Local::tap_to()`]. Protocols aren't
+/// tappable by default, because their messages can contain sensitive data.
+///
+/// Should be called once per protocol, in the module where the protocol is
+/// set by [`set_protocol!`] (if it's used).
+///
+/// [`Local::tap_to()`]: crate::topology::Local::tap_to
+#[macro_export]
+macro_rules! allow_tapping {
+    () => {
+        const _: () = {
+            use $crate::_priv::{linkme, TAPPABLE_PROTOCOLS};
+
+            #[linkme::distributed_slice(TAPPABLE_PROTOCOLS)]
+            #[linkme(crate = linkme)]
+            static PROTOCOL: &'static str = $crate::get_protocol!();
+        };
+    };
+}
+
+/// Generates a typed client of a protocol with an async method per request,
+/// which sends the request and waits for the response. Clients are created by
+/// `new()` from any [`Requester`], e.g. `&Context` or [`SystemHandle`], so
+/// consumers of the protocol don't deal with request builders and tokens.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// use elfo::{errors::RequestError, message};
+///
+/// #[message(ret = u64)]
+/// pub struct Charge {
+///     pub amount: u64,
+/// }
+///
+/// #[message(ret = ())]
+/// pub struct Refund {
+///     pub id: u64,
+/// }
+///
+/// elfo::client! {
+///     /// The client of the billing service.
+///     pub struct BillingClient {
+///         pub async fn charge(Charge);
+///         pub async fn refund(Refund);
+///     }
+/// }
+///
+/// async fn exec(ctx: elfo::Context) -> Result<(), RequestError> {
+///     let billing = BillingClient::new(&ctx);
+///     let id = billing.charge(Charge { amount: 100 }).await?;
+///     billing.refund(Refund { id }).await
+/// }
+/// ```
+///
+/// [`Requester`]: crate::Requester
+/// [`SystemHandle`]: crate::SystemHandle
+#[macro_export]
+macro_rules! client {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$method_attr:meta])*
+                $method_vis:vis async fn $method:ident($request:ty);
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone)]
+        $vis struct $name<Q> {
+            requester: Q,
+        }
+
+        impl<Q: $crate::Requester> $name<Q> {
+            /// Creates a client sending requests by the provided requester.
+            $vis fn new(requester: Q) -> Self {
+                Self { requester }
+            }
+
+            $(
+                $(#[$method_attr])*
+                $method_vis async fn $method(
+                    &self,
+                    request: $request,
+                ) -> ::std::result::Result<
+                    <$request as $crate::Request>::Response,
+                    $crate::errors::RequestError,
+                > {
+                    $crate::Requester::request(&self.requester, request).await
+                }
+            )*
+        }
+    };
+}
+
 // See https://github.com/GoldsteinE/gh-blog/blob/master/const_deref_specialization/src/lib.md
 #[doc(hidden)]
 #[macro_export]
@@ -1,8 +1,19 @@
-use futures_intrusive::{
-    buffer::GrowingHeapBuf,
-    channel::{self, GenericChannel},
+use std::{
+    future::poll_fn,
+    pin::pin,
+    sync::{
+        atomic::{self, AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+        mpsc,
+    },
+    task::{self, Poll},
+};
+
+use futures::task::AtomicWaker;
+use parking_lot::Mutex;
+use tokio::{
+    sync::Notify,
+    time::{self, Instant},
 };
-use parking_lot::{Mutex, RawMutex};
 
 use crate::{
     envelope::Envelope,
@@ -11,78 +22,280 @@ use crate::{
 };
 
 // TODO: make mailboxes bounded by time instead of size.
-const LIMIT: usize = 100_000;
+const LIMIT: isize = 100_000;
 
+/// A bounded MPSC queue of envelopes.
+///
+/// The queue itself is lock-free for producers, the consumer's side is
+/// guarded by an uncontended lock, because the mailbox is drained only by
+/// the owning actor.
+///
+/// Wakeups are coalesced: the consumer is woken only if it's parked, so a
+/// burst of sends results in a single wakeup, and then the consumer drains
+/// available envelopes, possibly in batches (see [`Mailbox::try_recv_many()`]).
 pub(crate) struct Mailbox {
-    queue: GenericChannel<RawMutex, Envelope, GrowingHeapBuf<Envelope>>,
+    tx: mpsc::Sender<Envelope>,
+    rx: Mutex<mpsc::Receiver<Envelope>>,
+    /// The number of reserved slots, i.e. envelopes in the queue and being
+    /// pushed right now. Can temporarily exceed `LIMIT` on failed attempts.
+    len: AtomicIsize,
+    closed: AtomicBool,
+    /// Set by the consumer before waiting for envelopes.
+    parked: AtomicBool,
+    rx_waker: AtomicWaker,
+    /// Producers waiting for capacity.
+    space: Notify,
+    waiting_senders: AtomicUsize,
     closed_trace_id: Mutex<Option<TraceId>>,
 }
 
+enum Rejected {
+    Full,
+    Closed,
+}
+
 impl Mailbox {
     pub(crate) fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+
         Self {
-            queue: GenericChannel::with_capacity(LIMIT),
+            tx,
+            rx: Mutex::new(rx),
+            len: AtomicIsize::new(0),
+            closed: AtomicBool::new(false),
+            parked: AtomicBool::new(false),
+            rx_waker: AtomicWaker::new(),
+            space: Notify::new(),
+            waiting_senders: AtomicUsize::new(0),
             closed_trace_id: Mutex::new(None),
         }
     }
 
     pub(crate) async fn send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
-        let fut = self.queue.send(envelope);
-        fut.await.map_err(|err| SendError(err.0))
+        match self.reserve().await {
+            Ok(()) => {
+                self.push(envelope);
+                Ok(())
+            }
+            Err(Rejected::Closed) => Err(SendError::MailboxClosed(envelope)),
+            Err(Rejected::Full) => unreachable!("waits for capacity"),
+        }
+    }
+
+    /// Waits for capacity until the deadline.
+    /// Returns `MailboxFull` with the envelope if the deadline is reached.
+    pub(crate) async fn send_until(
+        &self,
+        envelope: Envelope,
+        deadline: Instant,
+    ) -> Result<(), TrySendError<Envelope>> {
+        // `reserve()` is cancel safe, a slot is reserved only on completion.
+        match time::timeout_at(deadline, self.reserve()).await {
+            Ok(Ok(())) => {
+                self.push(envelope);
+                Ok(())
+            }
+            Ok(Err(Rejected::Closed)) => Err(TrySendError::MailboxClosed(envelope)),
+            Ok(Err(Rejected::Full)) | Err(_) => Err(TrySendError::MailboxFull(envelope)),
+        }
     }
 
     pub(crate) fn try_send(&self, envelope: Envelope) -> Result<(), TrySendError<Envelope>> {
-        self.queue.try_send(envelope).map_err(|err| match err {
-            channel::TrySendError::Full(envelope) => TrySendError::Full(envelope),
-            channel::TrySendError::Closed(envelope) => TrySendError::Closed(envelope),
-        })
+        match self.try_reserve() {
+            Ok(()) => {
+                self.push(envelope);
+                Ok(())
+            }
+            Err(Rejected::Full) => Err(TrySendError::MailboxFull(envelope)),
+            Err(Rejected::Closed) => Err(TrySendError::MailboxClosed(envelope)),
+        }
     }
 
-    pub(crate) async fn recv(&self) -> RecvResult {
-        let fut = self.queue.receive();
-        match fut.await {
-            Some(envelope) => RecvResult::Data(envelope),
-            None => self.on_close(),
+    fn try_reserve(&self) -> Result<(), Rejected> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(Rejected::Closed);
+        }
+
+        let prev = self.len.fetch_add(1, Ordering::SeqCst);
+        if prev < LIMIT && !self.closed.load(Ordering::SeqCst) {
+            return Ok(());
         }
+
+        self.len.fetch_sub(1, Ordering::SeqCst);
+
+        if self.closed.load(Ordering::SeqCst) {
+            // The consumer can wait for this slot to decide that it's closed.
+            self.wake_consumer();
+            Err(Rejected::Closed)
+        } else {
+            Err(Rejected::Full)
+        }
+    }
+
+    async fn reserve(&self) -> Result<(), Rejected> {
+        loop {
+            match self.try_reserve() {
+                Err(Rejected::Full) => {}
+                result => return result,
+            }
+
+            let mut notified = pin!(self.space.notified());
+            notified.as_mut().enable();
+
+            let _waiting = WaitingSender::new(&self.waiting_senders);
+            match self.try_reserve() {
+                Err(Rejected::Full) => notified.await,
+                result => return result,
+            }
+        }
+    }
+
+    fn push(&self, envelope: Envelope) {
+        // The receiver lives as long as the sender.
+        let _ = self.tx.send(envelope);
+        self.wake_consumer();
+    }
+
+    fn wake_consumer(&self) {
+        // Pairs with the fence in `poll_recv()`.
+        atomic::fence(Ordering::SeqCst);
+
+        if self.parked.load(Ordering::Relaxed) && self.parked.swap(false, Ordering::AcqRel) {
+            self.rx_waker.wake();
+        }
+    }
+
+    pub(crate) async fn recv(&self) -> RecvResult {
+        poll_fn(|cx| self.poll_recv(cx)).await
     }
 
     pub(crate) fn try_recv(&self) -> Option<RecvResult> {
-        match self.queue.try_receive() {
-            Ok(envelope) => Some(RecvResult::Data(envelope)),
-            Err(channel::TryReceiveError::Empty) => None,
-            Err(channel::TryReceiveError::Closed) => Some(self.on_close()),
+        match self.rx.lock().try_recv() {
+            Ok(envelope) => {
+                self.on_popped(1);
+                Some(RecvResult::Data(envelope))
+            }
+            Err(_) if self.is_drained() => Some(self.on_close()),
+            Err(_) => None,
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut task::Context<'_>) -> Poll<RecvResult> {
+        if let Some(result) = self.try_recv() {
+            return Poll::Ready(result);
+        }
+
+        self.rx_waker.register(cx.waker());
+        self.parked.store(true, Ordering::SeqCst);
+        // Pairs with the fence in `wake_consumer()`.
+        atomic::fence(Ordering::SeqCst);
+
+        // Envelopes can be pushed before parking, check again.
+        match self.try_recv() {
+            Some(result) => {
+                self.parked.store(false, Ordering::Relaxed);
+                Poll::Ready(result)
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    /// Moves up to `limit` available envelopes into `buf` at once.
+    /// Returns `None` if the mailbox is empty, but not closed.
+    pub(crate) fn try_recv_many(
+        &self,
+        buf: &mut Vec<Envelope>,
+        limit: usize,
+    ) -> Option<RecvResult<usize>> {
+        let len = buf.len();
+        buf.extend(self.rx.lock().try_iter().take(limit));
+
+        let count = buf.len() - len;
+        if count > 0 {
+            self.on_popped(count);
+            Some(RecvResult::Data(count))
+        } else if self.is_drained() {
+            Some(self.on_close())
+        } else {
+            None
         }
     }
 
     #[cold]
     pub(crate) fn close(&self, trace_id: TraceId) -> bool {
         // NOTE: It is important that we take the lock here before actually closing the
-        // channel. If we take a lock after closing the channel, data race is
-        // possible when we try to `recv()` after the channel is closed, but
+        // mailbox. If we take a lock after closing the mailbox, data race is
+        // possible when we try to `recv()` after the mailbox is closed, but
         // before the `closed_trace_id` is assigned.
         let mut closed_trace_id = self.closed_trace_id.lock();
-        if self.queue.close().is_newly_closed() {
-            *closed_trace_id = Some(trace_id);
-            true
-        } else {
-            false
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return false;
         }
+
+        *closed_trace_id = Some(trace_id);
+        drop(closed_trace_id);
+
+        // Senders waiting for capacity should fail.
+        self.space.notify_waiters();
+        self.wake_consumer();
+        true
     }
 
     #[cold]
     pub(crate) fn drop_all(&self) {
-        while self.queue.try_receive().is_ok() {}
+        let count = self.rx.lock().try_iter().count();
+        self.on_popped(count);
+    }
+
+    /// Returns the approximate fraction of the capacity in use.
+    pub(crate) fn usage(&self) -> f64 {
+        self.len.load(Ordering::Relaxed).clamp(0, LIMIT) as f64 / LIMIT as f64
+    }
+
+    /// Returns `true` if the mailbox is closed and all envelopes are received.
+    fn is_drained(&self) -> bool {
+        // Pending pushes are accounted by `len`, so they aren't lost.
+        self.closed.load(Ordering::SeqCst) && self.len.load(Ordering::SeqCst) == 0
+    }
+
+    fn on_popped(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        self.len.fetch_sub(count as isize, Ordering::SeqCst);
+
+        let waiting = self.waiting_senders.load(Ordering::SeqCst);
+        for _ in 0..count.min(waiting) {
+            self.space.notify_one();
+        }
     }
 
     #[cold]
-    fn on_close(&self) -> RecvResult {
+    fn on_close<T>(&self) -> RecvResult<T> {
         let trace_id = self.closed_trace_id.lock().expect("called before close()");
         RecvResult::Closed(trace_id)
     }
 }
 
+/// Accounts a producer waiting for capacity, even if it's cancelled.
+struct WaitingSender<'a>(&'a AtomicUsize);
+
+impl<'a> WaitingSender<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for WaitingSender<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
-pub(crate) enum RecvResult {
-    Data(Envelope),
+pub(crate) enum RecvResult<T = Envelope> {
+    Data(T),
     Closed(TraceId),
 }
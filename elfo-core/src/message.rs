@@ -1,4 +1,4 @@
-use std::{any::Any, fmt, ops::Deref};
+use std::{any::Any, fmt, ops::Deref, time::Duration};
 
 use fxhash::{FxHashMap, FxHashSet};
 use linkme::distributed_slice;
@@ -26,6 +26,12 @@ pub trait Message: fmt::Debug + Clone + Any + Send + Serialize + for<'de> Deseri
         self._vtable().protocol
     }
 
+    /// A message's version, set by `#[message(version = N)]`, `1` by default.
+    #[inline(always)]
+    fn version(&self) -> u8 {
+        self._vtable().version
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     fn labels(&self) -> &'static [Label] {
@@ -38,6 +44,24 @@ pub trait Message: fmt::Debug + Clone + Any + Send + Serialize + for<'de> Deseri
         self._vtable().dumping_allowed
     }
 
+    /// Whether the message is marked as `#[message(priority = "low")]`.
+    /// Such messages are shed by overloaded actors if it's enabled by
+    /// `system.overload.shedding`.
+    #[inline(always)]
+    fn is_low_priority(&self) -> bool {
+        self._vtable().low_priority
+    }
+
+    /// Returns the TTL specified by `#[message(ttl = "..")]`.
+    /// Expired messages are dropped instead of being received.
+    /// It can be overridden at send time, e.g. by [`Context::send_with_ttl()`].
+    ///
+    /// [`Context::send_with_ttl()`]: crate::Context::send_with_ttl
+    #[inline(always)]
+    fn ttl(&self) -> Option<Duration> {
+        self._vtable().ttl
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     fn upcast(self) -> AnyMessage {
@@ -68,6 +92,11 @@ pub trait Message: fmt::Debug + Clone + Any + Send + Serialize + for<'de> Deseri
 pub trait Request: Message {
     type Response: fmt::Debug + Clone + Send + Serialize;
 
+    /// Whether the request is marked as `#[message(ret = R, idempotent)]`.
+    /// Such requests are hedged if routed to multiple recipients, see
+    /// `system.hedging`.
+    const IS_IDEMPOTENT: bool = false;
+
     #[doc(hidden)]
     type Wrapper: Message + Into<Self::Response> + From<Self::Response>;
 }
@@ -77,30 +106,42 @@ pub trait Request: Message {
 // Reexported in `elfo::_priv`.
 pub struct AnyMessage {
     vtable: &'static MessageVTable,
-    data: SmallBox<dyn Any + Send, [usize; 24]>,
+    // Four words less than it could be to keep `Envelope` 256 bytes.
+    data: SmallBox<dyn Any + Send, [usize; 20]>,
 }
 
 impl AnyMessage {
     #[inline]
     pub fn is<M: Message>(&self) -> bool {
-        self.data.is::<M>()
+        self.data.is::<M>() || (self as &dyn Any).is::<M>()
     }
 
     #[inline]
     pub fn downcast_ref<M: Message>(&self) -> Option<&M> {
-        self.data.downcast_ref::<M>().map(|message| {
-            message._touch();
-            message
-        })
+        // `AnyMessage` itself, e.g. a response to an erased request.
+        if let Some(this) = (self as &dyn Any).downcast_ref::<M>() {
+            return Some(this);
+        }
+
+        self.data
+            .downcast_ref::<M>()
+            .inspect(|message| message._touch())
     }
 
     #[inline]
     pub fn downcast<M: Message>(self) -> Result<M, AnyMessage> {
-        if !self.is::<M>() {
-            return Err(self);
+        // `AnyMessage` itself, e.g. a response to an erased request.
+        let mut this = Some(self);
+        if let Some(this) = (&mut this as &mut dyn Any).downcast_mut::<Option<M>>() {
+            return Ok(this.take().expect("just set"));
         }
+        let this = this.expect("just set");
 
-        let message = self
+        if !this.data.is::<M>() {
+            return Err(this);
+        }
+
+        let message = this
             .data
             .downcast::<M>()
             .expect("cannot downcast")
@@ -132,6 +173,14 @@ impl Message for AnyMessage {
     }
 }
 
+/// Sends messages of types unknown at compile time (e.g. deserialized from
+/// external clients) as requests. Responses are provided as is, so the
+/// responder's type must be known to the requester to interpret them.
+impl Request for AnyMessage {
+    type Response = AnyMessage;
+    type Wrapper = AnyMessage;
+}
+
 impl Clone for AnyMessage {
     #[inline]
     fn clone(&self) -> Self {
@@ -232,9 +281,10 @@ cfg_network!({
             buffer: &[u8],
             protocol: &str,
             name: &str,
+            version: u8,
         ) -> Result<Option<Self>, rmps::decode::Error> {
             lookup_vtable(protocol, name)
-                .map(|vtable| (vtable.read_msgpack)(buffer))
+                .map(|vtable| (vtable.read_msgpack)(buffer, version))
                 .transpose()
         }
 
@@ -249,13 +299,34 @@ cfg_network!({
         }
     }
 
+    /// Decodes a message of the provided version, probably older one.
+    /// Implemented by the `#[message]` macro.
+    // Reexported in `elfo::_priv`.
+    pub trait Versioned: Sized {
+        const VERSION: u8;
+
+        fn read_msgpack_versioned(buffer: &[u8], version: u8) -> Result<Self, rmps::decode::Error>;
+    }
+
     // For monomorphization in the `#[message]` macro.
     // Reexported in `elfo::_priv`.
     #[inline]
-    pub fn read_msgpack<M: Message>(buffer: &[u8]) -> Result<M, rmps::decode::Error> {
+    pub fn read_msgpack<M: for<'de> Deserialize<'de>>(
+        buffer: &[u8],
+    ) -> Result<M, rmps::decode::Error> {
         rmps::decode::from_slice(buffer)
     }
 
+    // Used in the `#[message]` macro.
+    // Reexported in `elfo::_priv`.
+    #[cold]
+    pub fn unsupported_version<M: Versioned>(version: u8) -> rmps::decode::Error {
+        rmps::decode::Error::Uncategorized(format!(
+            "unsupported version {version}, the current one is {}",
+            M::VERSION
+        ))
+    }
+
     // For monomorphization in the `#[message]` macro.
     // Reexported in `elfo::_priv`.
     #[inline]
@@ -289,6 +360,18 @@ cfg_network!({
     }
 });
 
+// === Redacted ===
+
+// Used in the `#[message]` macro to print secret fields.
+// Reexported in `elfo::_priv`.
+pub struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
 // === ProtocolExtractor ===
 // Reexported in `elfo::_priv`.
 // See https://github.com/GoldsteinE/gh-blog/blob/master/const_deref_specialization/src/lib.md
@@ -331,7 +414,19 @@ pub struct MessageVTable {
     /// Usually, it's a crate name where the message is defined.
     pub protocol: &'static str,
     pub labels: &'static [Label],
+    /// A hash of the message's shape, see [`MessageInfo::schema_hash`].
+    ///
+    /// [`MessageInfo::schema_hash`]: crate::registry::MessageInfo::schema_hash
+    pub schema_hash: u64,
+    /// A message's version, see [`Message::version()`].
+    pub version: u8,
+    /// Whether the message is a request, see [`MessageInfo::is_request`].
+    ///
+    /// [`MessageInfo::is_request`]: crate::registry::MessageInfo::is_request
+    pub is_request: bool,
     pub dumping_allowed: bool, // TODO: introduce `DumpingMode`.
+    pub low_priority: bool,
+    pub ttl: Option<Duration>,
     pub clone: fn(&AnyMessage) -> AnyMessage,
     pub debug: fn(&AnyMessage, &mut fmt::Formatter<'_>) -> fmt::Result,
     pub erase: fn(&AnyMessage) -> dumping::ErasedMessage,
@@ -340,14 +435,25 @@ pub struct MessageVTable {
     #[cfg(feature = "network")]
     pub write_msgpack: fn(&AnyMessage, &mut Vec<u8>, usize) -> Result<(), rmps::encode::Error>,
     #[cfg(feature = "network")]
-    pub read_msgpack: fn(&[u8]) -> Result<AnyMessage, rmps::decode::Error>,
+    pub read_msgpack: fn(&[u8], u8) -> Result<AnyMessage, rmps::decode::Error>,
 }
 
 // Reexported in `elfo::_priv`.
 #[distributed_slice]
 pub static MESSAGE_LIST: [&'static MessageVTable] = [..];
 
-static MESSAGES: Lazy<FxHashMap<(&'static str, &'static str), &'static MessageVTable>> =
+// Reexported in `elfo::_priv`.
+/// Protocols allowed to be tapped, see [`allow_tapping!`].
+///
+/// [`allow_tapping!`]: crate::allow_tapping
+#[distributed_slice]
+pub static TAPPABLE_PROTOCOLS: [&'static str] = [..];
+
+pub(crate) fn is_tappable(protocol: &str) -> bool {
+    TAPPABLE_PROTOCOLS.contains(&protocol)
+}
+
+pub(crate) static MESSAGES: Lazy<FxHashMap<(&'static str, &'static str), &'static MessageVTable>> =
     Lazy::new(|| {
         MESSAGE_LIST
             .iter()
@@ -355,13 +461,13 @@ static MESSAGES: Lazy<FxHashMap<(&'static str, &'static str), &'static MessageVT
             .collect()
     });
 
-fn lookup_vtable(protocol: &str, name: &str) -> Option<&'static MessageVTable> {
+pub(crate) fn lookup_vtable(protocol: &str, name: &str) -> Option<&'static MessageVTable> {
     // Extend lifetimes to static in order to get `(&'static str, &'static str)`.
     // SAFETY: this pair doesn't overlive the function.
     let (protocol, name) = unsafe {
         (
-            std::mem::transmute::<_, &'static str>(protocol),
-            std::mem::transmute::<_, &'static str>(name),
+            std::mem::transmute::<&str, &'static str>(protocol),
+            std::mem::transmute::<&str, &'static str>(name),
         )
     };
 
@@ -416,4 +522,52 @@ mod tests {
 
         assert_eq!(msg, deserialized_msg);
     }
+
+    #[test]
+    fn secret_fields() {
+        use crate::scope::{self, SerdeMode};
+
+        #[message]
+        struct Login {
+            user: String,
+            #[message(secret)]
+            token: String,
+        }
+
+        #[message]
+        struct Card(u32, #[message(secret)] String);
+
+        #[message]
+        enum Payment {
+            Cash,
+            Card {
+                #[message(secret)]
+                number: String,
+            },
+        }
+
+        let login = Login {
+            user: "alice".into(),
+            token: "xxx".into(),
+        };
+        assert_eq!(
+            format!("{login:?}"),
+            r#"Login { user: "alice", token: <redacted> }"#
+        );
+        assert_eq!(
+            format!("{:?}", Card(1, "1234".into())),
+            "Card(1, <redacted>)"
+        );
+        assert_eq!(format!("{:?}", Payment::Cash), "Cash");
+        let card = Payment::Card {
+            number: "1234".into(),
+        };
+        assert_eq!(format!("{card:?}"), "Card { number: <redacted> }");
+
+        // Secrets are hidden in dumps, but left intact otherwise.
+        let dumped = scope::with_serde_mode(SerdeMode::Dumping, || serde_json::to_string(&login));
+        assert_eq!(dumped.unwrap(), r#"{"user":"alice","token":"<redacted>"}"#);
+        let serialized = serde_json::to_string(&login).unwrap();
+        assert_eq!(serialized, r#"{"user":"alice","token":"xxx"}"#);
+    }
 }
@@ -1,4 +1,4 @@
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, sync::Arc, time::Duration};
 
 use derive_more::Constructor;
 
@@ -6,6 +6,7 @@ use crate::{
     actor::{ActorMeta, ActorStatus},
     config::AnyConfig,
     message,
+    message::AnyMessage,
 };
 
 /// A helper type for using in generic code (e.g. as an associated type) to
@@ -52,6 +53,58 @@ impl<R: Display> From<R> for ConfigRejected {
     }
 }
 
+/// Starts a group with `StartPolicy::Manual`. Handled by the supervisor,
+/// does nothing for already started groups.
+///
+/// Can be sent as a request to wait until the group is started.
+#[message(ret = ())]
+#[derive(Default)]
+#[non_exhaustive]
+pub struct StartGroup;
+
+/// Restarts actors of the group in waves of at most `max_parallel` actors,
+/// waiting for `delay` between waves, e.g. to re-establish connections without
+/// a thundering herd. Handled by the supervisor of the group named `group`,
+/// other groups ignore it. Responds once all actors are restarted.
+///
+/// Mailboxes of restarted actors are closed, like by `Context::close()`, and
+/// actors are restarted immediately regardless of the restart policy. The
+/// state can be passed to the next incarnation by `Context::set_snapshot()`.
+#[message(ret = ())]
+#[derive(Constructor)]
+#[non_exhaustive]
+pub struct RollingRestart {
+    pub group: String,
+    pub max_parallel: usize,
+    pub delay: Duration,
+}
+
+/// Requests a snapshot of the actor's internal state, e.g. key counters, for
+/// introspection of live actors. It's exposed by the telemeter on the
+/// `/actors/{group}/{key}/state` path.
+///
+/// Handled by the supervisor, which routes it to the actor whose key equals
+/// `key` in the textual form (`_` for singletons). Other keys are discarded,
+/// so the requester gets `RequestError::NotSent` with `SendError::NoRoute`.
+///
+/// Handling is optional, actors that don't describe their state simply
+/// ignore it, so the requester gets `RequestError::Ignored`. Otherwise,
+/// ```ignore
+/// (messages::DescribeActor, token) => ctx.respond(token, self.describe()),
+/// ```
+/// where `describe(&self) -> serde_json::Value` is provided by the actor.
+#[message(ret = serde_json::Value)]
+#[derive(Constructor)]
+#[non_exhaustive]
+pub struct DescribeActor {
+    pub key: String,
+}
+
+/// Releases messages held by a group until its dependencies are ready,
+/// see `Local::wait_for_ready()`. Sent by the init actor.
+#[message]
+pub(crate) struct ReleaseBarrier;
+
 #[message(ret = Result<(), StartEntrypointRejected>)]
 #[derive(Constructor)]
 #[non_exhaustive]
@@ -94,6 +147,37 @@ impl Terminate {
     }
 }
 
+/// Sent to an actor that hasn't received messages for
+/// `ActorGroup::idle_timeout()`. Unless `Context::keep_alive()` is called, the
+/// mailbox is closed on the next `recv()`, so the actor can flush its state.
+#[message]
+#[derive(Default)]
+#[non_exhaustive]
+pub struct IdleTimeout;
+
+/// A message discarded by `Outcome::DiscardWith`. Sent by the supervisor of
+/// a group with `ActorGroup::forward_dead_letters()` set, so it should be
+/// routed to some group collecting dead letters by the topology.
+#[message]
+#[derive(Constructor)]
+#[non_exhaustive]
+pub struct DeadLetter {
+    pub reason: String,
+    pub message: AnyMessage,
+}
+
+/// The node-wide restart budget is exceeded, see
+/// `Topology::set_restart_budget()`. Sent once per storm by the supervisor of
+/// the group, whose restart has exceeded the budget, so it should be routed by
+/// the topology.
+#[message]
+#[non_exhaustive]
+pub struct RestartStorm {
+    pub group: String,
+    /// Restarts across all groups during the last minute.
+    pub restarts: usize,
+}
+
 // === Status ===
 
 // TODO: should it be a request?
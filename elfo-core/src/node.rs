@@ -1,4 +1,9 @@
-use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{
+    atomic::{AtomicU16, Ordering},
+    Arc,
+};
+
+use crate::scope;
 
 #[cfg(feature = "unstable")]
 pub use crate::addr::NodeNo;
@@ -20,7 +25,15 @@ impl From<u16> for NodeNo {
 static NODE_NO: AtomicU16 = AtomicU16::new(0);
 
 /// Returns the current `node_no`.
+///
+/// Inside actors, it's `node_no` of the topology they belong to, which makes it
+/// possible to run several nodes in one process. Otherwise, the process-wide
+/// one is returned.
 pub fn node_no() -> Option<crate::addr::NodeNo> {
+    scope::try_with(|scope| scope.node_no()).unwrap_or_else(process_node_no)
+}
+
+fn process_node_no() -> Option<crate::addr::NodeNo> {
     crate::addr::NodeNo::from_bits(NODE_NO.load(Ordering::Relaxed))
 }
 
@@ -31,3 +44,18 @@ pub fn node_no() -> Option<crate::addr::NodeNo> {
 pub(crate) fn set_node_no(node_no: u16) {
     NODE_NO.store(node_no, Ordering::Relaxed)
 }
+
+/// `node_no` of a specific topology, shared by all its actors.
+/// If unset, the process-wide one is used.
+#[derive(Clone, Default)]
+pub(crate) struct LocalNodeNo(Arc<AtomicU16>);
+
+impl LocalNodeNo {
+    pub(crate) fn get(&self) -> Option<crate::addr::NodeNo> {
+        crate::addr::NodeNo::from_bits(self.0.load(Ordering::Relaxed)).or_else(process_node_no)
+    }
+
+    pub(crate) fn set(&self, node_no: crate::addr::NodeNo) {
+        self.0.store(node_no.into_bits(), Ordering::Relaxed)
+    }
+}
@@ -1,6 +1,9 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use derive_more::From;
 use futures::future::{join_all, BoxFuture};
 use smallvec::SmallVec;
+use tokio::time::Instant;
 
 #[cfg(feature = "network")]
 use crate::remote::{self, RemoteHandle};
@@ -18,6 +21,9 @@ use crate::{
 pub struct Object {
     addr: Addr,
     kind: ObjectKind,
+    /// Set when the object is removed from the address book, used to
+    /// revalidate entries cached by `SendHandle`.
+    removed: AtomicBool,
 }
 
 assert_impl_all!(Object: Sync);
@@ -30,6 +36,7 @@ pub(crate) type ObjectRef<'a> = sharded_slab::Entry<'a, Object, SlabConfig>;
 pub type ObjectArc = sharded_slab::OwnedEntry<Object, SlabConfig>;
 
 #[derive(From)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum ObjectKind {
     Actor(Actor),
     Group(Box<dyn GroupHandle>),
@@ -42,9 +49,18 @@ impl Object {
         Self {
             addr,
             kind: kind.into(),
+            removed: AtomicBool::new(false),
         }
     }
 
+    pub(crate) fn mark_removed(&self) {
+        self.removed.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_removed(&self) -> bool {
+        self.removed.load(Ordering::Acquire)
+    }
+
     #[stability::unstable]
     #[inline]
     pub fn addr(&self) -> Addr {
@@ -59,12 +75,43 @@ impl Object {
         recipient: Addr,
         envelope: Envelope,
     ) -> Result<(), SendError<Envelope>> {
+        self.send_until(ctx, recipient, envelope, None)
+            .await
+            .map_err(TrySendError::into_send_error)
+    }
+
+    /// Like `send()`, but stops waiting for capacity at the deadline.
+    /// Returns `MailboxFull` in this case.
+    pub(crate) async fn send_until<C, K>(
+        &self,
+        ctx: &Context<C, K>,
+        recipient: Addr,
+        envelope: Envelope,
+        deadline: Option<Instant>,
+    ) -> Result<(), TrySendError<Envelope>> {
         match &self.kind {
-            ObjectKind::Actor(handle) => handle.send(envelope).await,
+            ObjectKind::Actor(handle) => send_to_actor(handle, envelope, deadline).await,
             ObjectKind::Group(handle) => {
-                let mut visitor = SendGroupVisitor::new(ctx.book());
-                handle.handle(envelope, &mut visitor);
-                visitor.finish().await
+                let mut envelope = envelope;
+                loop {
+                    let mut visitor = SendGroupVisitor::new(ctx.book(), deadline);
+                    handle.handle(envelope, &mut visitor);
+
+                    let Some((e, room)) = visitor.take_room() else {
+                        break visitor.finish().await;
+                    };
+
+                    // The group cannot accept the envelope now, try again later.
+                    envelope = e;
+
+                    if let Some(deadline) = deadline {
+                        if tokio::time::timeout_at(deadline, room).await.is_err() {
+                            break Err(TrySendError::MailboxFull(envelope));
+                        }
+                    } else {
+                        room.await;
+                    }
+                }
             }
             #[cfg(feature = "network")]
             ObjectKind::Remote(handle) => {
@@ -72,10 +119,17 @@ impl Object {
                 loop {
                     match handle.send(recipient, envelope) {
                         remote::SendResult::Ok => break Ok(()),
-                        remote::SendResult::Err(err) => break Err(err),
+                        remote::SendResult::Err(err) => break Err(err.into()),
                         remote::SendResult::Wait(notified, e) => {
                             envelope = e;
-                            notified.await;
+
+                            if let Some(deadline) = deadline {
+                                if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                                    break Err(TrySendError::MailboxFull(envelope));
+                                }
+                            } else {
+                                notified.await;
+                            }
                         }
                     }
                 }
@@ -153,46 +207,76 @@ pub(crate) trait GroupHandle: Send + Sync + 'static {
 pub trait GroupVisitor {
     fn done(&mut self);
     fn empty(&mut self, envelope: Envelope);
+    /// The group cannot accept the envelope now, e.g. too many are held.
+    /// `room` is resolved once the envelope can be handled by the group again.
+    fn full(&mut self, envelope: Envelope, room: BoxFuture<'static, ()>);
     fn visit(&mut self, object: &ObjectArc, envelope: &Envelope);
     fn visit_last(&mut self, object: &ObjectArc, envelope: Envelope);
 }
 
 // === SendGroupVisitor ===
 
-struct SendGroupVisitor<'a> {
+async fn send_to_actor(
+    actor: &Actor,
+    envelope: Envelope,
+    deadline: Option<Instant>,
+) -> Result<(), TrySendError<Envelope>> {
+    match deadline {
+        Some(deadline) => actor.send_until(envelope, deadline).await,
+        None => actor.send(envelope).await.map_err(Into::into),
+    }
+}
+
+pub(crate) struct SendGroupVisitor<'a> {
     book: &'a AddressBook,
+    deadline: Option<Instant>,
     full: SmallVec<[(Addr, Envelope); 1]>,
     extra: Option<Envelope>,
+    room: Option<BoxFuture<'static, ()>>,
     has_ok: bool,
+    has_full: bool,
+    is_empty: bool,
 }
 
 impl<'a> SendGroupVisitor<'a> {
-    fn new(book: &'a AddressBook) -> Self {
+    pub(crate) fn new(book: &'a AddressBook, deadline: Option<Instant>) -> Self {
         Self {
             book,
+            deadline,
             full: Default::default(),
             extra: None,
+            room: None,
             has_ok: false,
+            has_full: false,
+            is_empty: false,
         }
     }
 
+    /// Returns the envelope the group cannot accept now and a future resolved
+    /// once it's worth handling the envelope by the group again.
+    pub(crate) fn take_room(&mut self) -> Option<(Envelope, BoxFuture<'static, ()>)> {
+        let room = self.room.take()?;
+        let envelope = self.extra.take().expect("missing envelope");
+        Some((envelope, room))
+    }
+
     // We must send while visiting to ensure that a message starting a new actor
     // is actually the first message that the actor receives.
     fn try_send(&mut self, object: &ObjectArc, envelope: Envelope) {
         let actor = object.as_actor().expect("group stores only actors");
         match actor.try_send(envelope) {
             Ok(()) => self.has_ok = true,
-            Err(TrySendError::Full(envelope)) => {
+            Err(TrySendError::MailboxFull(envelope)) => {
                 self.full.push((object.addr(), envelope));
             }
-            Err(TrySendError::Closed(envelope)) => {
-                self.extra = Some(envelope);
+            Err(err) => {
+                self.extra = Some(err.into_inner());
             }
         }
     }
 
     #[inline]
-    async fn finish(mut self) -> Result<(), SendError<Envelope>> {
+    pub(crate) async fn finish(mut self) -> Result<(), TrySendError<Envelope>> {
         // Wait until messages reach all full actors.
         #[allow(clippy::comparison_chain)]
         if self.full.len() == 1 {
@@ -200,9 +284,11 @@ impl<'a> SendGroupVisitor<'a> {
 
             if let Some(object) = self.book.get_owned(addr) {
                 let actor = object.as_actor().expect("group stores only actors");
-                match actor.send(envelope).await {
+                match send_to_actor(actor, envelope, self.deadline).await {
                     Ok(()) => self.has_ok = true,
-                    Err(SendError(envelope)) => {
+                    Err(err) => {
+                        self.has_full |= err.is_full();
+                        let envelope = err.into_inner();
                         if !self.has_ok {
                             self.extra = Some(envelope);
                         }
@@ -216,16 +302,14 @@ impl<'a> SendGroupVisitor<'a> {
 
             for (addr, envelope) in self.full.drain(..) {
                 let object = self.book.get_owned(addr);
+                let deadline = self.deadline;
                 futures.push(async move {
                     match object {
                         Some(object) => {
-                            object
-                                .as_actor()
-                                .expect("group stores only actors")
-                                .send(envelope)
-                                .await
+                            let actor = object.as_actor().expect("group stores only actors");
+                            send_to_actor(actor, envelope, deadline).await
                         }
-                        None => Err(SendError(envelope)),
+                        None => Err(TrySendError::MailboxClosed(envelope)),
                     }
                 });
             }
@@ -233,7 +317,9 @@ impl<'a> SendGroupVisitor<'a> {
             for result in join_all(futures).await {
                 match result {
                     Ok(()) => self.has_ok = true,
-                    Err(SendError(envelope)) => {
+                    Err(err) => {
+                        self.has_full |= err.is_full();
+                        let envelope = err.into_inner();
                         if !self.has_ok {
                             self.extra = Some(envelope);
                         }
@@ -245,10 +331,17 @@ impl<'a> SendGroupVisitor<'a> {
         debug_assert!(self.full.is_empty());
 
         if self.has_ok {
-            Ok(())
-        } else {
-            Err(SendError(self.extra.take().expect("missing envelope")))
+            return Ok(());
         }
+
+        let envelope = self.extra.take().expect("missing envelope");
+        Err(if self.has_full {
+            TrySendError::MailboxFull(envelope)
+        } else if self.is_empty {
+            TrySendError::NoRoute(envelope)
+        } else {
+            TrySendError::MailboxClosed(envelope)
+        })
     }
 }
 
@@ -265,6 +358,16 @@ impl GroupVisitor for SendGroupVisitor<'_> {
         debug_assert!(self.extra.is_none());
         debug_assert!(!self.has_ok);
         self.extra = Some(envelope);
+        self.is_empty = true;
+    }
+
+    fn full(&mut self, envelope: Envelope, room: BoxFuture<'static, ()>) {
+        debug_assert!(self.full.is_empty());
+        debug_assert!(self.extra.is_none());
+        debug_assert!(!self.has_ok);
+        self.extra = Some(envelope);
+        self.room = Some(room);
+        self.has_full = true;
     }
 
     fn visit(&mut self, object: &ObjectArc, envelope: &Envelope) {
@@ -284,6 +387,7 @@ struct TrySendGroupVisitor {
     extra: Option<Envelope>,
     has_ok: bool,
     has_full: bool,
+    is_empty: bool,
 }
 
 impl TrySendGroupVisitor {
@@ -308,9 +412,11 @@ impl TrySendGroupVisitor {
         } else {
             let envelope = self.extra.take().expect("missing envelope");
             Err(if self.has_full {
-                TrySendError::Full(envelope)
+                TrySendError::MailboxFull(envelope)
+            } else if self.is_empty {
+                TrySendError::NoRoute(envelope)
             } else {
-                TrySendError::Closed(envelope)
+                TrySendError::MailboxClosed(envelope)
             })
         }
     }
@@ -327,6 +433,14 @@ impl GroupVisitor for TrySendGroupVisitor {
         debug_assert!(self.extra.is_none());
         debug_assert!(!self.has_ok);
         self.extra = Some(envelope);
+        self.is_empty = true;
+    }
+
+    fn full(&mut self, envelope: Envelope, _room: BoxFuture<'static, ()>) {
+        debug_assert!(self.extra.is_none());
+        debug_assert!(!self.has_ok);
+        self.extra = Some(envelope);
+        self.has_full = true;
     }
 
     fn visit(&mut self, object: &ObjectArc, envelope: &Envelope) {
@@ -0,0 +1,244 @@
+//! Detection of actors that don't keep up with incoming messages.
+//!
+//! An actor is considered overloaded if its mailbox stays filled above the
+//! threshold for some time or if a received message has waited too long.
+//! Overloaded actors get the `Overloaded` status, which is reported to status
+//! subscribers and counted by the `elfo_active_actors` metric. The previous
+//! status is restored once the actor recovers.
+//!
+//! If shedding is enabled, messages marked as `#[message(priority = "low")]`
+//! aren't sent to overloaded actors, so they don't block other messages.
+//! Such messages are counted by the `elfo_shed_messages_total` metric.
+//!
+//! Also, actors busy for too long without yielding to the runtime, e.g.
+//! handling a giant batch, are reported by warnings, because they starve other
+//! actors on the same runtime. See `Context::yield_now()`.
+//!
+//! The time from spawning an actor to its first poll is measured by the
+//! `elfo_actor_startup_delay_seconds` metric, and actors waiting too long
+//! are reported by warnings, because it means the runtime is saturated.
+
+use std::time::Duration;
+
+use quanta::Instant;
+use serde::Deserialize;
+
+use crate::{
+    actor::{Actor, ActorStatus, ActorStatusKind},
+    time,
+};
+
+// === OverloadConfig ===
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct OverloadConfig {
+    pub(crate) disabled: bool,
+    /// A fraction of the mailbox capacity.
+    pub(crate) mailbox_usage: f64,
+    /// How long the mailbox usage must stay above the threshold.
+    #[serde(with = "humantime_serde")]
+    pub(crate) mailbox_usage_for: Duration,
+    /// How long a message can wait in the mailbox.
+    #[serde(with = "humantime_serde")]
+    pub(crate) max_waiting_time: Option<Duration>,
+    /// Whether low-priority messages are dropped while overloaded.
+    pub(crate) shedding: bool,
+    /// How long an actor can be busy without yielding to the runtime.
+    #[serde(with = "humantime_serde")]
+    pub(crate) max_busy_time: Option<Duration>,
+    /// How long a spawned actor can wait to be polled for the first time.
+    #[serde(with = "humantime_serde")]
+    pub(crate) max_startup_delay: Option<Duration>,
+}
+
+impl Default for OverloadConfig {
+    fn default() -> Self {
+        Self {
+            disabled: false,
+            mailbox_usage: 0.8,
+            mailbox_usage_for: Duration::from_secs(10),
+            max_waiting_time: None,
+            shedding: false,
+            max_busy_time: None,
+            max_startup_delay: None,
+        }
+    }
+}
+
+// === OverloadDetector ===
+
+pub(crate) struct OverloadDetector {
+    /// When the mailbox usage has exceeded the threshold.
+    above_since: Option<Instant>,
+    is_overloaded: bool,
+    /// The status before overloading, restored after recovering.
+    prev_status: Option<ActorStatus>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Overloaded(String),
+    Recovered,
+}
+
+impl OverloadDetector {
+    pub(crate) fn new() -> Self {
+        Self {
+            above_since: None,
+            is_overloaded: false,
+            prev_status: None,
+        }
+    }
+
+    /// Called on every received message. Since the mailbox cannot be peeked,
+    /// the age of the head message is checked when it's received.
+    pub(crate) fn on_received(
+        &mut self,
+        actor: &Actor,
+        config: &OverloadConfig,
+        waiting_time: Duration,
+    ) {
+        if config.disabled {
+            return;
+        }
+
+        let usage = actor.mailbox_usage();
+
+        match ward!(self.check(config, usage, waiting_time, time::instant())) {
+            Change::Overloaded(details) => {
+                actor.set_shedding(config.shedding);
+                let status = actor.status();
+
+                // Don't hide more important statuses.
+                if matches!(
+                    status.kind(),
+                    ActorStatusKind::Normal | ActorStatusKind::Alarming
+                ) {
+                    self.prev_status = Some(status);
+                    actor.set_status(ActorStatus::OVERLOADED.with_details(details));
+                }
+            }
+            Change::Recovered => {
+                actor.set_shedding(false);
+                let prev_status = ward!(self.prev_status.take());
+
+                // The status can be changed by the actor itself meanwhile.
+                if actor.status().kind() == ActorStatusKind::Overloaded {
+                    actor.set_status(prev_status);
+                }
+            }
+        }
+    }
+
+    fn check(
+        &mut self,
+        config: &OverloadConfig,
+        usage: f64,
+        waiting_time: Duration,
+        now: Instant,
+    ) -> Option<Change> {
+        let reason = if usage >= config.mailbox_usage {
+            let since = *self.above_since.get_or_insert(now);
+            let elapsed = now - since;
+
+            (elapsed >= config.mailbox_usage_for).then(|| {
+                format!(
+                    "the mailbox is {:.0}% full for {:.1?}",
+                    usage * 100.,
+                    elapsed
+                )
+            })
+        } else {
+            self.above_since = None;
+            None
+        };
+
+        let reason = reason.or_else(|| {
+            config
+                .max_waiting_time
+                .filter(|max| waiting_time > *max)
+                .map(|_| format!("a message has waited for {waiting_time:.1?}"))
+        });
+
+        match (reason, self.is_overloaded) {
+            (Some(details), false) => {
+                self.is_overloaded = true;
+                Some(Change::Overloaded(details))
+            }
+            (None, true) if self.above_since.is_none() => {
+                self.is_overloaded = false;
+                Some(Change::Recovered)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quanta::{Clock, Mock};
+
+    use super::*;
+
+    fn with_time_mock(f: impl FnOnce(&Mock)) {
+        let (clock, mock) = Clock::mock();
+        quanta::with_clock(&clock, || f(&mock));
+    }
+
+    #[test]
+    fn mailbox_usage() {
+        with_time_mock(|mock| {
+            let config = OverloadConfig::default();
+            let mut detector = OverloadDetector::new();
+            let mut check = |usage| detector.check(&config, usage, Duration::ZERO, Instant::now());
+
+            assert_eq!(check(0.9), None);
+            mock.increment(Duration::from_secs(5));
+            assert_eq!(check(0.9), None);
+
+            // Dropping below the threshold resets the timer.
+            assert_eq!(check(0.5), None);
+            assert_eq!(check(0.9), None);
+            mock.increment(Duration::from_secs(9));
+            assert_eq!(check(0.8), None);
+            mock.increment(Duration::from_secs(1));
+            assert_eq!(
+                check(0.95),
+                Some(Change::Overloaded(
+                    "the mailbox is 95% full for 10.0s".into()
+                ))
+            );
+
+            // Reported only once.
+            mock.increment(Duration::from_secs(1));
+            assert_eq!(check(0.9), None);
+
+            assert_eq!(check(0.7), Some(Change::Recovered));
+            assert_eq!(check(0.7), None);
+        });
+    }
+
+    #[test]
+    fn waiting_time() {
+        with_time_mock(|_| {
+            let config = OverloadConfig {
+                max_waiting_time: Some(Duration::from_secs(1)),
+                ..Default::default()
+            };
+            let mut detector = OverloadDetector::new();
+            let mut check = |secs| {
+                let waiting_time = Duration::from_secs_f64(secs);
+                detector.check(&config, 0., waiting_time, Instant::now())
+            };
+
+            assert_eq!(check(0.5), None);
+            assert_eq!(
+                check(1.5),
+                Some(Change::Overloaded("a message has waited for 1.5s".into()))
+            );
+            assert_eq!(check(2.), None);
+            assert_eq!(check(0.1), Some(Change::Recovered));
+        });
+    }
+}
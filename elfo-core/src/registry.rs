@@ -0,0 +1,122 @@
+//! The registry of all messages known to the binary.
+//!
+//! Messages are registered by the `#[message]` derive, no manual actions are
+//! required. The registry is useful for tooling like dump decoders and
+//! compatibility checkers between nodes.
+
+use serde::Serialize;
+
+use crate::message::{self, MessageVTable};
+
+/// Information about a message registered by the `#[message]` derive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct MessageInfo {
+    /// A protocol's name. Usually, it's a crate name where the message is
+    /// defined.
+    pub protocol: &'static str,
+    /// A message's name.
+    pub name: &'static str,
+    /// A hash of the message's shape: names and types of fields and variants
+    /// along with `serde` attributes. It's stable between compilations, so
+    /// messages with different hashes are likely to be incompatible.
+    ///
+    /// Note that types are hashed by their names, so changes inside nested
+    /// types don't affect the hash.
+    pub schema_hash: u64,
+    /// A message's version, see [`Message::version()`].
+    ///
+    /// [`Message::version()`]: crate::Message::version
+    pub version: u8,
+    /// Whether the message is a request, i.e. defined with
+    /// `#[message(ret = ..)]`.
+    pub is_request: bool,
+}
+
+impl From<&'static MessageVTable> for MessageInfo {
+    fn from(vtable: &'static MessageVTable) -> Self {
+        Self {
+            protocol: vtable.protocol,
+            name: vtable.name,
+            schema_hash: vtable.schema_hash,
+            version: vtable.version,
+            is_request: vtable.is_request,
+        }
+    }
+}
+
+/// Returns all registered messages sorted by protocol and name.
+pub fn messages() -> Vec<MessageInfo> {
+    let mut list = message::MESSAGES
+        .values()
+        .copied()
+        .map(MessageInfo::from)
+        .collect::<Vec<_>>();
+
+    list.sort_unstable_by_key(|info| (info.protocol, info.name));
+    list
+}
+
+/// Returns a registered message by its protocol and name.
+pub fn lookup(protocol: &str, name: &str) -> Option<MessageInfo> {
+    message::lookup_vtable(protocol, name).map(MessageInfo::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message;
+
+    #[message(protocol = "registry-test")]
+    struct Basic {
+        a: u32,
+        b: String,
+    }
+
+    /// The same shape with different docs.
+    #[message(protocol = "registry-test")]
+    struct Documented {
+        /// Some docs.
+        a: u32,
+        b: String,
+    }
+
+    #[message(protocol = "registry-test")]
+    struct Retyped {
+        a: u64,
+        b: String,
+    }
+
+    #[message(protocol = "registry-test")]
+    struct Renamed {
+        #[serde(rename = "c")]
+        a: u32,
+        b: String,
+    }
+
+    #[message(protocol = "registry-test", ret = ())]
+    struct Requested;
+
+    #[test]
+    fn it_works() {
+        let list = messages();
+        assert!(list
+            .windows(2)
+            .all(|w| (w[0].protocol, w[0].name) < (w[1].protocol, w[1].name)));
+
+        let hash = |name| lookup("registry-test", name).unwrap().schema_hash;
+
+        for name in ["Basic", "Documented", "Retyped", "Renamed"] {
+            let info = lookup("registry-test", name).unwrap();
+            assert_eq!(info.name, name);
+            assert!(list.contains(&info));
+        }
+
+        assert_eq!(hash("Basic"), hash("Documented"));
+        assert_ne!(hash("Basic"), hash("Retyped"));
+        assert_ne!(hash("Basic"), hash("Renamed"));
+        assert!(lookup("registry-test", "Requested").unwrap().is_request);
+        assert!(!lookup("registry-test", "Basic").unwrap().is_request);
+        assert!(lookup("registry-test", "Unknown").is_none());
+    }
+}
@@ -1,13 +1,24 @@
-use std::{fmt, marker::PhantomData, sync::Arc};
+use std::{
+    fmt,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+};
 
 use futures_intrusive::sync::ManualResetEvent;
+use metrics::{counter, decrement_gauge, increment_gauge};
+use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
-use slotmap::{new_key_type, Key, SlotMap};
+use serde::Deserialize;
+use slotmap::{new_key_type, Key, KeyData, SlotMap};
 use smallvec::SmallVec;
+use tracing::warn;
 
 use crate::{
     address_book::AddressBook, envelope::Envelope, errors::RequestError, message::AnyMessage,
-    tracing::TraceId, Addr,
+    scope, tracing::TraceId, Addr,
 };
 
 // === RequestId ===
@@ -36,16 +47,97 @@ impl RequestId {
     }
 }
 
+// === RequestsConfig ===
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct RequestsConfig {
+    /// The number of pending requests of a single actor to warn about,
+    /// which usually means that responses are lost somewhere.
+    pub(crate) pending_warn_threshold: usize,
+}
+
+impl Default for RequestsConfig {
+    fn default() -> Self {
+        Self {
+            pending_warn_threshold: 1000,
+        }
+    }
+}
+
 // === RequestTable ===
 
+/// Pending requests of the actor, exposed by the `elfo_pending_requests`
+/// gauge, which is per actor if `system.telemetry.per_actor_key` is enabled.
+///
+/// Requests are distributed between shards in a round-robin manner to reduce
+/// contention between responders when there are many pending requests.
+/// The shard's number is encoded in the lower bits of the slot index of
+/// [`RequestId`]. Shards are allocated on the first request, because most
+/// actors never make requests.
 pub(crate) struct RequestTable {
     owner: Addr,
-    notifier: ManualResetEvent,
-    requests: Mutex<SlotMap<RequestId, RequestData>>,
+    shards: OnceCell<Box<[Shard]>>,
+    next_shard: AtomicUsize,
+    pending: AtomicUsize,
 }
 
 assert_impl_all!(RequestTable: Sync);
 
+const SHARD_BITS: u32 = 3;
+const SHARD_COUNT: usize = 1 << SHARD_BITS;
+
+new_key_type! {
+    struct SlotKey;
+}
+
+struct Shard {
+    notifier: ManualResetEvent,
+    inner: Mutex<ShardInner>,
+}
+
+impl Default for Shard {
+    fn default() -> Self {
+        Self {
+            notifier: ManualResetEvent::new(false),
+            inner: Mutex::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShardInner {
+    requests: SlotMap<SlotKey, RequestData>,
+    /// The number of completed, but not yet taken requests.
+    /// The notifier is set while it's positive.
+    completed: usize,
+}
+
+impl Shard {
+    fn on_uncompleted(&self, inner: &mut ShardInner) {
+        inner.completed -= 1;
+        if inner.completed == 0 {
+            self.notifier.reset();
+        }
+    }
+}
+
+fn compose_request_id(shard_no: usize, key: SlotKey) -> RequestId {
+    let bits = key.data().as_ffi();
+    let (version, idx) = (bits >> 32, bits as u32);
+    debug_assert!(idx < u32::MAX >> SHARD_BITS, "too many pending requests");
+    let idx = idx << SHARD_BITS | shard_no as u32;
+    RequestId::from_ffi(version << 32 | u64::from(idx))
+}
+
+fn decompose_request_id(request_id: RequestId) -> (usize, SlotKey) {
+    let bits = request_id.to_ffi();
+    let (version, idx) = (bits >> 32, bits as u32);
+    let shard_no = idx as usize & (SHARD_COUNT - 1);
+    let key = KeyData::from_ffi(version << 32 | u64::from(idx >> SHARD_BITS));
+    (shard_no, key.into())
+}
+
 type Responses = SmallVec<[Result<Envelope, RequestError>; 1]>;
 
 #[derive(Default)]
@@ -97,49 +189,113 @@ impl RequestTable {
     pub(crate) fn new(owner: Addr) -> Self {
         Self {
             owner,
-            notifier: ManualResetEvent::new(false),
-            requests: Mutex::new(SlotMap::default()),
+            shards: OnceCell::new(),
+            next_shard: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
         }
     }
 
+    fn shards(&self) -> &[Shard] {
+        self.shards
+            .get_or_init(|| (0..SHARD_COUNT).map(|_| Shard::default()).collect())
+    }
+
+    fn find(&self, request_id: RequestId) -> Option<(&Shard, SlotKey)> {
+        let (shard_no, key) = decompose_request_id(request_id);
+        Some((&self.shards.get()?[shard_no], key))
+    }
+
     pub(crate) fn new_request(
         &self,
         book: AddressBook,
         trace_id: TraceId,
         collect_all: bool,
     ) -> ResponseToken {
-        let mut requests = self.requests.lock();
-        let request_id = requests.insert(RequestData {
-            remainder: 1,
-            responses: Responses::new(),
-            collect_all,
-        });
+        let shard_no = self.next_shard.fetch_add(1, Ordering::Relaxed) % SHARD_COUNT;
+        let key = self.shards()[shard_no]
+            .inner
+            .lock()
+            .requests
+            .insert(RequestData {
+                remainder: 1,
+                responses: Responses::new(),
+                collect_all,
+            });
+
+        increment_gauge!("elfo_pending_requests", 1.);
+
+        // Warn only once the threshold is crossed to avoid flooding logs.
+        let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        let threshold = scope::try_with(|scope| scope.requests().pending_warn_threshold);
+        if threshold.is_some_and(|threshold| pending == threshold + 1) {
+            warn!(pending, "too many pending requests, responses can be lost");
+        }
+
+        let request_id = compose_request_id(shard_no, key);
         ResponseToken::new(self.owner, request_id, trace_id, book)
     }
 
+    fn on_removed(&self) {
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+        decrement_gauge!("elfo_pending_requests", 1.);
+    }
+
     pub(crate) fn cancel_request(&self, request_id: RequestId) {
-        let mut requests = self.requests.lock();
-        requests.remove(request_id);
+        let (shard, key) = ward!(self.find(request_id));
+        let mut inner = shard.inner.lock();
+        let data = ward!(inner.requests.remove(key));
+        self.on_removed();
+
+        // The canceled request can be the only completed one.
+        if data.remainder == 0 {
+            shard.on_uncompleted(&mut inner);
+        }
+    }
+
+    /// Cancels the request on drop unless it's already completed, e.g. if
+    /// the requester is terminated while waiting for responses.
+    pub(crate) fn cancel_on_drop(&self, request_id: RequestId) -> CancelOnDrop<'_> {
+        CancelOnDrop {
+            table: self,
+            request_id,
+        }
+    }
+
+    fn is_pending(&self, request_id: RequestId) -> bool {
+        self.find(request_id)
+            .is_some_and(|(shard, key)| shard.inner.lock().requests.contains_key(key))
+    }
+
+    /// Expects one more response to the request.
+    fn duplicate(&self, request_id: RequestId) -> Option<()> {
+        let (shard, key) = self.find(request_id)?;
+        let mut inner = shard.inner.lock();
+        let request = inner.requests.get_mut(key)?;
+        let was_completed = request.remainder == 0;
+        request.remainder += 1;
+
+        if was_completed {
+            shard.on_uncompleted(&mut inner);
+        }
+
+        Some(())
     }
 
     pub(crate) async fn wait(&self, request_id: RequestId) -> Responses {
+        let (shard, key) = self.find(request_id).expect("unknown request");
         let mut n = 0;
 
         loop {
-            self.notifier.wait().await;
+            shard.notifier.wait().await;
 
             {
-                let mut requests = self.requests.lock();
-                let request = requests.get(request_id).expect("unknown request");
+                let mut inner = shard.inner.lock();
+                let request = inner.requests.get(key).expect("unknown request");
 
                 if request.remainder == 0 {
-                    let data = requests.remove(request_id).expect("under lock");
-
-                    // TODO: use another approach.
-                    if requests.values().all(|data| data.remainder != 0) {
-                        self.notifier.reset();
-                    }
-
+                    let data = inner.requests.remove(key).expect("under lock");
+                    shard.on_uncompleted(&mut inner);
+                    self.on_removed();
                     break data.responses;
                 }
             }
@@ -161,18 +317,32 @@ impl RequestTable {
     ) {
         // Do nothing for forgotten tokens.
         let data = ward!(token.data.take());
-        let mut requests = self.requests.lock();
+        let (shard, key) = ward!(self.find(data.request_id));
+        let mut inner = shard.inner.lock();
 
         // `None` here means the request was with `collect_all = false` and
         // the response has been recieved already.
-        let request = ward!(requests.get_mut(data.request_id));
+        let request = ward!(inner.requests.get_mut(key));
 
         if request.push(response) {
-            self.notifier.set();
+            inner.completed += 1;
+            shard.notifier.set();
         }
     }
 }
 
+pub(crate) struct CancelOnDrop<'a> {
+    table: &'a RequestTable,
+    request_id: RequestId,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        // Completed requests are already removed, the id isn't reused.
+        self.table.cancel_request(self.request_id);
+    }
+}
+
 // === ResponseToken ===
 
 #[must_use]
@@ -188,6 +358,8 @@ struct ResponseTokenData {
     request_id: RequestId,
     trace_id: TraceId,
     book: AddressBook,
+    /// Set by the network if the remote requester has stopped waiting.
+    canceled: AtomicBool,
 }
 
 impl ResponseToken {
@@ -203,6 +375,7 @@ impl ResponseToken {
                 request_id,
                 trace_id,
                 book,
+                canceled: AtomicBool::new(false),
             })),
             received: false,
             marker: PhantomData,
@@ -267,14 +440,25 @@ impl ResponseToken {
         self.data = None;
     }
 
+    /// Returns a handle to cancel the request on behalf of the remote
+    /// requester. The handle doesn't prolong the token's life.
+    ///
+    /// Part of private API. Do not use it.
+    #[doc(hidden)]
+    #[stability::unstable]
+    pub fn remote_cancellation(&self) -> Option<RemoteCancellation> {
+        let data = self.data.as_ref()?;
+        debug_assert!(!data.sender.is_local());
+        Some(RemoteCancellation(Arc::downgrade(data)))
+    }
+
     fn do_duplicate(&self) -> Option<Arc<ResponseTokenData>> {
         let data = self.data.as_ref()?;
 
         if data.sender.is_local() {
             let object = data.book.get(data.sender)?;
             let actor = object.as_actor()?;
-            let mut requests = actor.request_table().requests.lock();
-            requests.get_mut(data.request_id)?.remainder += 1;
+            actor.request_table().duplicate(data.request_id)?;
         }
 
         Some(data.clone())
@@ -303,6 +487,46 @@ impl<R> ResponseToken<R> {
     pub fn is_forgotten(&self) -> bool {
         self.data.is_none()
     }
+
+    /// Returns `true` if the requester doesn't wait for the response anymore,
+    /// e.g. it's terminated, restarted or has stopped waiting. Useful to skip
+    /// expensive handling of canceled requests.
+    ///
+    /// For requests from other nodes, the cancellation is propagated by the
+    /// network with some delay (up to `system.network.ping_interval`), and
+    /// only if both nodes support it.
+    pub fn is_canceled(&self) -> bool {
+        let data = ward!(&self.data, return true);
+
+        if !data.sender.is_local() {
+            return data.canceled.load(Ordering::Relaxed);
+        }
+
+        let object = ward!(data.book.get(data.sender), return true);
+        let actor = ward!(object.as_actor(), return true);
+        !actor.request_table().is_pending(data.request_id)
+    }
+}
+
+/// Cancels a request from another node, see
+/// [`ResponseToken::remote_cancellation()`].
+///
+/// Part of private API. Do not use it.
+#[doc(hidden)]
+pub struct RemoteCancellation(Weak<ResponseTokenData>);
+
+impl RemoteCancellation {
+    /// Marks the request as canceled, see [`ResponseToken::is_canceled()`].
+    pub fn cancel(&self) {
+        if let Some(data) = self.0.upgrade() {
+            data.canceled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns `true` if the request isn't responded yet.
+    pub fn is_pending(&self) -> bool {
+        self.0.strong_count() > 0
+    }
 }
 
 impl<T> Drop for ResponseToken<T> {
@@ -318,6 +542,7 @@ impl<T> Drop for ResponseToken<T> {
             marker: PhantomData,
         };
         let err = if self.received {
+            counter!("elfo_ignored_requests_total", 1);
             RequestError::Ignored
         } else {
             RequestError::Failed
@@ -0,0 +1,156 @@
+use std::{collections::VecDeque, mem, time::Duration};
+
+use parking_lot::Mutex;
+use quanta::Instant;
+
+/// The window, which the budget is defined for.
+const WINDOW: Duration = Duration::from_secs(60);
+
+// === RestartBudget ===
+
+/// The node-wide limit of actor restarts across all groups, see
+/// [`Topology::set_restart_budget()`].
+///
+/// Once the limit is exceeded, the restart storm begins: the
+/// [`RestartStorm`] message is sent and, if enabled, groups restarting actors
+/// during the storm are switched to [`StartPolicy::Manual`], so their actors
+/// aren't restarted until [`StartGroup`] is received.
+///
+/// [`Topology::set_restart_budget()`]: crate::Topology::set_restart_budget
+/// [`RestartStorm`]: crate::messages::RestartStorm
+/// [`StartPolicy::Manual`]: crate::StartPolicy::Manual
+/// [`StartGroup`]: crate::messages::StartGroup
+#[derive(Debug, Clone)]
+pub struct RestartBudget {
+    max_per_minute: usize,
+    manual_start_on_storm: bool,
+}
+
+impl RestartBudget {
+    /// Allows at most `max` restarts during the last minute.
+    pub fn per_minute(max: usize) -> Self {
+        Self {
+            max_per_minute: max,
+            manual_start_on_storm: false,
+        }
+    }
+
+    /// Switches groups restarting actors during the storm to manual start.
+    pub fn manual_start_on_storm(mut self) -> Self {
+        self.manual_start_on_storm = true;
+        self
+    }
+}
+
+// === RestartTracker ===
+
+/// Tracks restarts of all groups, shared between supervisors.
+#[derive(Default)]
+pub(crate) struct RestartTracker {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    budget: Option<RestartBudget>,
+    restarts: VecDeque<Instant>,
+    is_storm: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Verdict {
+    Allowed,
+    Storm {
+        /// Set only for the restart beginning the storm.
+        is_new: bool,
+        /// Restarts during the last minute.
+        restarts: usize,
+        manual_start: bool,
+    },
+}
+
+impl RestartTracker {
+    pub(crate) fn set_budget(&self, budget: RestartBudget) {
+        self.state.lock().budget = Some(budget);
+    }
+
+    pub(crate) fn on_restart(&self) -> Verdict {
+        let mut state = self.state.lock();
+        let budget = ward!(state.budget.clone(), return Verdict::Allowed);
+
+        let now = Instant::now();
+        while let Some(&time) = state.restarts.front() {
+            if now.duration_since(time) < WINDOW {
+                break;
+            }
+            state.restarts.pop_front();
+        }
+
+        state.restarts.push_back(now);
+
+        // The storm ends once restarts fit the budget again.
+        if state.restarts.len() <= budget.max_per_minute {
+            state.is_storm = false;
+            return Verdict::Allowed;
+        }
+
+        Verdict::Storm {
+            is_new: !mem::replace(&mut state.is_storm, true),
+            restarts: state.restarts.len(),
+            manual_start: budget.manual_start_on_storm,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quanta::{Clock, Mock};
+
+    use super::*;
+
+    fn with_time_mock(f: impl FnOnce(&Mock)) {
+        let (clock, mock) = Clock::mock();
+        quanta::with_clock(&clock, || f(&mock));
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let tracker = RestartTracker::default();
+
+        for _ in 0..100 {
+            assert_eq!(tracker.on_restart(), Verdict::Allowed);
+        }
+    }
+
+    #[test]
+    fn storm() {
+        with_time_mock(|mock| {
+            let tracker = RestartTracker::default();
+            tracker.set_budget(RestartBudget::per_minute(2).manual_start_on_storm());
+
+            assert_eq!(tracker.on_restart(), Verdict::Allowed);
+            mock.increment(Duration::from_secs(20));
+            assert_eq!(tracker.on_restart(), Verdict::Allowed);
+            mock.increment(Duration::from_secs(20));
+
+            // The storm begins.
+            let storm = |is_new, restarts| Verdict::Storm {
+                is_new,
+                restarts,
+                manual_start: true,
+            };
+            assert_eq!(tracker.on_restart(), storm(true, 3));
+            assert_eq!(tracker.on_restart(), storm(false, 4));
+
+            // The first restarts are out of the window, but it's not enough.
+            mock.increment(Duration::from_secs(30));
+            assert_eq!(tracker.on_restart(), storm(false, 4));
+
+            // The storm ends.
+            mock.increment(Duration::from_secs(60));
+            assert_eq!(tracker.on_restart(), Verdict::Allowed);
+            assert_eq!(tracker.on_restart(), Verdict::Allowed);
+            assert_eq!(tracker.on_restart(), storm(true, 3));
+        });
+    }
+}
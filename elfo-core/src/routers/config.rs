@@ -0,0 +1,89 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use arc_swap::ArcSwapOption;
+use futures::future::BoxFuture;
+
+use super::{Outcome, Router};
+use crate::envelope::Envelope;
+
+/// A router rebuilt from the config every time it's updated, e.g. if routing
+/// depends on the number of partitions.
+///
+/// The new router replaces the old one atomically, so every message is routed
+/// either by the old or by the new one. `UpdateConfig` is routed by the new
+/// one, so it can start actors for new keys. Actors for keys that aren't
+/// produced by the new router anymore aren't stopped and still receive
+/// messages routed by the old one.
+///
+/// The built router is updated by the same config before being used, so
+/// routers with state (e.g. [`MapRouter::with_state`]) can be nested.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # #[elfo::message] struct Item { id: u32 }
+/// # #[derive(serde::Deserialize)] struct Config { partitions: u32 }
+/// use elfo::{msg, routers::{ConfigRouter, MapRouter, Outcome}};
+///
+/// let router = ConfigRouter::new(|config: &Config| {
+///     let partitions = config.partitions;
+///     MapRouter::new(move |envelope| {
+///         msg!(match envelope {
+///             Item { id } => Outcome::Unicast(id % partitions),
+///             _ => Outcome::Default,
+///         })
+///     })
+/// });
+/// # let _: &dyn elfo::routers::Router<Config, Key = u32> = &router;
+/// ```
+///
+/// [`MapRouter::with_state`]: super::MapRouter::with_state
+pub struct ConfigRouter<C, R, F> {
+    build: F,
+    /// `None` until the first config is received.
+    router: ArcSwapOption<R>,
+    _config: PhantomData<C>,
+}
+
+impl<C, R, F> ConfigRouter<C, R, F>
+where
+    F: Fn(&C) -> R,
+{
+    pub fn new(build: F) -> Self {
+        Self {
+            build,
+            router: ArcSwapOption::empty(),
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<C, R, F> Router<C> for ConfigRouter<C, R, F>
+where
+    C: Send + Sync + 'static,
+    R: Router<C>,
+    F: Fn(&C) -> R + Send + Sync + 'static,
+{
+    type Key = R::Key;
+
+    fn update(&self, config: &C) {
+        let router = (self.build)(config);
+        router.update(config);
+        self.router.store(Some(Arc::new(router)));
+    }
+
+    #[inline]
+    fn route(&self, envelope: &Envelope) -> Outcome<Self::Key> {
+        match &*self.router.load() {
+            Some(router) => router.route(envelope),
+            None => Outcome::Default,
+        }
+    }
+
+    fn route_deferred(&self, envelope: &Envelope) -> BoxFuture<'static, Outcome<Self::Key>> {
+        match &*self.router.load() {
+            Some(router) => router.route_deferred(envelope),
+            None => Box::pin(async { Outcome::Discard }),
+        }
+    }
+}
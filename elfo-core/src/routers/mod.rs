@@ -3,17 +3,34 @@ use std::{
     hash::Hash,
 };
 
+use futures::future::BoxFuture;
+use fxhash::FxHashSet;
+
 use crate::{envelope::Envelope, msg};
 
-pub use self::map::MapRouter;
+pub use self::{
+    config::ConfigRouter,
+    map::MapRouter,
+    sticky::{Affinity, StickyRouter},
+};
 
+mod config;
 mod map;
+mod sticky;
 
 pub trait Router<C>: Send + Sync + 'static {
     type Key: Clone + Hash + Eq + Display + Send + Sync; // TODO: why is `Sync` required?
 
     fn update(&self, _config: &C) {}
     fn route(&self, envelope: &Envelope) -> Outcome<Self::Key>;
+
+    /// Called if `route()` returns `Outcome::Deferred`. The future cannot
+    /// borrow the envelope, so required fields should be copied.
+    /// The resolved `Outcome::Default` and `Outcome::Deferred` are considered
+    /// as `Outcome::Discard`.
+    fn route_deferred(&self, _envelope: &Envelope) -> BoxFuture<'static, Outcome<Self::Key>> {
+        Box::pin(async { Outcome::Discard })
+    }
 }
 
 /// Specifies which actors will get a message.
@@ -31,6 +48,7 @@ pub enum Outcome<T> {
     /// Routes a message to all actors with specified keys.
     /// If there is no active or restarting actors for these keys,
     /// they will be started.
+    /// Use `Outcome::multicast()` to skip duplicated keys.
     Multicast(Vec<T>),
     /// Routes a message to all actors with specified keys.
     /// If there is no active or restarting actors for these keys,
@@ -41,6 +59,21 @@ pub enum Outcome<T> {
     /// Discards a message.
     /// If a message is discarded by everyone, the sending side gets an error.
     Discard,
+    /// Like `Discard`, but explains why the message is discarded.
+    ///
+    /// Counted by `elfo_discarded_messages_total` with the `reason` label, so
+    /// the reason should be a short static string. Forwarded as
+    /// `messages::DeadLetter` if `ActorGroup::forward_dead_letters()` is set.
+    DiscardWith(&'static str),
+    /// Routes a message by the future returned from `Router::route_deferred()`,
+    /// e.g. after consulting with another actor. The sending side doesn't wait
+    /// for it. Messages of the same sender are routed in order, even if
+    /// following ones are routed synchronously.
+    ///
+    /// The number of concurrently resolved futures is limited by
+    /// `ActorGroup::max_deferred_routes()`. No actors are started by this
+    /// outcome for `UpdateConfig`.
+    Deferred,
     /// Route message using default behaviour.
     /// This behaviour depends on the message type:
     /// - `ValidateConfig` is routed as `Discard`
@@ -65,6 +98,8 @@ impl<T> Outcome<T> {
             }
             Outcome::Broadcast => Outcome::Broadcast,
             Outcome::Discard => Outcome::Discard,
+            Outcome::DiscardWith(reason) => Outcome::DiscardWith(reason),
+            Outcome::Deferred => Outcome::Deferred,
             Outcome::Default => Outcome::Default,
         }
     }
@@ -79,6 +114,43 @@ impl<T> Outcome<T> {
     }
 }
 
+impl<T: Hash + Eq> Outcome<T> {
+    /// Creates `Multicast` to the computed set of keys, skipping duplicates.
+    /// Returns `Discard` if there are no keys.
+    pub fn multicast(keys: impl IntoIterator<Item = T>) -> Self {
+        let list = dedup(keys);
+        if list.is_empty() {
+            Outcome::Discard
+        } else {
+            Outcome::Multicast(list)
+        }
+    }
+
+    /// Creates `GentleMulticast` to the computed set of keys, skipping
+    /// duplicates. Returns `Discard` if there are no keys.
+    pub fn gentle_multicast(keys: impl IntoIterator<Item = T>) -> Self {
+        let list = dedup(keys);
+        if list.is_empty() {
+            Outcome::Discard
+        } else {
+            Outcome::GentleMulticast(list)
+        }
+    }
+}
+
+/// Removes duplicates, keeping the order of first occurrences.
+fn dedup<T: Hash + Eq>(keys: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut list = keys.into_iter().collect::<Vec<_>>();
+
+    let mut seen = FxHashSet::default();
+    let is_first = list.iter().map(|key| seen.insert(key)).collect::<Vec<_>>();
+    drop(seen);
+
+    let mut is_first = is_first.into_iter();
+    list.retain(|_| is_first.next().unwrap_or_default());
+    list
+}
+
 impl<C> Router<C> for () {
     type Key = Singleton;
 
@@ -0,0 +1,159 @@
+use std::{fmt::Display, hash::Hash, marker::PhantomData, time::Duration};
+
+use dashmap::DashMap;
+use fxhash::FxBuildHasher;
+use parking_lot::Mutex;
+use tokio::time::Instant;
+
+use super::{Outcome, Router};
+use crate::envelope::Envelope;
+
+/// Pins sessions to actors, see [`StickyRouter`].
+#[derive(Debug)]
+pub enum Affinity<S, K> {
+    /// Pins the session to the actor with the key and routes the message to
+    /// it, e.g. for the first message of the session. The key is usually
+    /// computed only once, because it can be expensive.
+    Pin(S, K),
+    /// Routes the message to the actor the session is pinned to.
+    /// The message is discarded if the session is unknown or expired.
+    Follow(S),
+    /// Like `Follow`, but unpins the session after routing.
+    Unpin(S),
+    /// Routes the message regardless of sessions.
+    Route(Outcome<K>),
+}
+
+/// A router pinning all messages of a session (e.g. carrying the same
+/// correlation id) to the same actor.
+///
+/// Sessions expire if no messages are routed within the provided `ttl`.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use elfo_core as elfo;
+/// # #[elfo::message] struct Login { session: u64, user: String }
+/// # #[elfo::message] struct Action { session: u64 }
+/// # #[elfo::message] struct Logout { session: u64 }
+/// use elfo::{msg, routers::{Affinity, Outcome, StickyRouter}};
+///
+/// let router = StickyRouter::new(Duration::from_secs(600), |envelope| {
+///     msg!(match envelope {
+///         Login { session, user } => Affinity::Pin(*session, user.clone()),
+///         Action { session } => Affinity::Follow(*session),
+///         Logout { session } => Affinity::Unpin(*session),
+///         _ => Affinity::Route(Outcome::Default),
+///     })
+/// });
+/// # let _: StickyRouter<(), u64, String, _> = router;
+/// ```
+pub struct StickyRouter<C, S, K, F> {
+    ttl: Duration,
+    extract: F,
+    sessions: DashMap<S, Pinned<K>, FxBuildHasher>,
+    /// When expired sessions have been removed last time.
+    swept_at: Mutex<Instant>,
+    _config: PhantomData<C>,
+}
+
+struct Pinned<K> {
+    key: K,
+    used_at: Instant,
+}
+
+impl<C, S, K, F> StickyRouter<C, S, K, F>
+where
+    S: Hash + Eq,
+    F: Fn(&Envelope) -> Affinity<S, K>,
+{
+    pub fn new(ttl: Duration, extract: F) -> Self {
+        Self {
+            ttl,
+            extract,
+            sessions: DashMap::default(),
+            swept_at: Mutex::new(Instant::now()),
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<C, S, K, F> StickyRouter<C, S, K, F>
+where
+    S: Hash + Eq,
+    K: Clone,
+{
+    /// Returns the number of pinned sessions, including expired ones that
+    /// haven't been removed yet.
+    pub fn sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    fn pin(&self, session: S, key: K, now: Instant) {
+        self.sessions.insert(session, Pinned { key, used_at: now });
+    }
+
+    fn follow(&self, session: &S, now: Instant) -> Option<K> {
+        let mut pinned = self.sessions.get_mut(session)?;
+        if now.duration_since(pinned.used_at) >= self.ttl {
+            drop(pinned);
+            self.sessions.remove_if(session, |_, pinned| {
+                now.duration_since(pinned.used_at) >= self.ttl
+            });
+            return None;
+        }
+
+        pinned.used_at = now;
+        Some(pinned.key.clone())
+    }
+
+    fn unpin(&self, session: &S, now: Instant) -> Option<K> {
+        let (_, pinned) = self.sessions.remove(session)?;
+        (now.duration_since(pinned.used_at) < self.ttl).then_some(pinned.key)
+    }
+
+    /// Removes expired sessions at most once per `ttl`.
+    fn sweep(&self, now: Instant) {
+        {
+            let mut swept_at = ward!(self.swept_at.try_lock());
+            if now.duration_since(*swept_at) < self.ttl {
+                return;
+            }
+            *swept_at = now;
+        }
+
+        self.sessions
+            .retain(|_, pinned| now.duration_since(pinned.used_at) < self.ttl);
+    }
+}
+
+impl<C, S, K, F> Router<C> for StickyRouter<C, S, K, F>
+where
+    C: Send + Sync + 'static,
+    S: Hash + Eq + Send + Sync + 'static,
+    K: Clone + Hash + Eq + Display + Send + Sync + 'static,
+    F: Fn(&Envelope) -> Affinity<S, K> + Send + Sync + 'static,
+{
+    type Key = K;
+
+    fn route(&self, envelope: &Envelope) -> Outcome<Self::Key> {
+        let now = Instant::now();
+
+        let outcome = match (self.extract)(envelope) {
+            Affinity::Pin(session, key) => {
+                self.pin(session, key.clone(), now);
+                Outcome::Unicast(key)
+            }
+            Affinity::Follow(session) => self
+                .follow(&session, now)
+                .map_or(Outcome::Discard, Outcome::Unicast),
+            Affinity::Unpin(session) => self
+                .unpin(&session, now)
+                .map_or(Outcome::Discard, Outcome::Unicast),
+            Affinity::Route(outcome) => outcome,
+        };
+
+        self.sweep(now);
+        outcome
+    }
+}
@@ -1,23 +1,33 @@
 #![allow(clippy::declare_interior_mutable_const)] // see tokio#4872
 
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     future::Future,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
         Arc,
     },
 };
 
+use arc_swap::{ArcSwap, Guard};
+
 use crate::{
     actor::ActorMeta,
     config::SystemConfig,
     dumping::DumpingControl,
+    flags::FlagsConfig,
+    handling::HandlingConfig,
+    hedging::HedgingConfig,
+    journal::Journal,
     logging::_priv::LoggingControl,
+    node::LocalNodeNo,
+    overload::OverloadConfig,
     permissions::{AtomicPermissions, Permissions},
-    telemetry::TelemetryConfig,
-    tracing::TraceId,
-    Addr,
+    request_table::RequestsConfig,
+    telemetry::{ActorKeys, TelemetryConfig, OTHER_KEY},
+    time::Clock,
+    tracing::{Baggage, MessageId, TraceId, TracingConfig},
+    Addr, NodeNo,
 };
 
 tokio::task_local! {
@@ -27,6 +37,8 @@ tokio::task_local! {
 #[derive(Clone)]
 pub struct Scope {
     trace_id: Cell<TraceId>,
+    baggage: RefCell<Baggage>,
+    message_id: Cell<Option<MessageId>>,
     actor: Arc<ScopeActorShared>,
     group: Arc<ScopeGroupShared>,
 }
@@ -38,11 +50,22 @@ impl Scope {
     /// Private API for now.
     #[doc(hidden)]
     pub fn test(actor: Addr, meta: Arc<ActorMeta>) -> Self {
+        Self::test_with_clock(actor, meta, Clock::default())
+    }
+
+    /// Private API for now.
+    #[doc(hidden)]
+    pub fn test_with_clock(actor: Addr, meta: Arc<ActorMeta>, clock: Clock) -> Self {
         Self::new(
             TraceId::generate(),
             actor,
             meta,
-            Arc::new(ScopeGroupShared::new(Addr::NULL)),
+            Arc::new(ScopeGroupShared::new(
+                Addr::NULL,
+                LocalNodeNo::default(),
+                clock,
+                Journal::default(),
+            )),
         )
     }
 
@@ -54,16 +77,30 @@ impl Scope {
     ) -> Self {
         Self {
             trace_id: Cell::new(trace_id),
+            baggage: RefCell::default(),
+            message_id: Cell::new(None),
             actor: Arc::new(ScopeActorShared::new(addr, meta)),
             group,
         }
     }
 
     pub(crate) fn with_telemetry(mut self, config: &TelemetryConfig) -> Self {
-        self.actor = Arc::new(self.actor.with_telemetry(config));
+        self.actor = Arc::new(
+            self.actor
+                .with_telemetry(config, &self.group.telemetry_keys),
+        );
         self
     }
 
+    /// Releases the actor's key admitted to metrics, if any.
+    /// Called once the actor terminates.
+    pub(crate) fn release_telemetry(&self) {
+        if self.actor.is_key_admitted {
+            let key = &self.actor.telemetry_meta.key;
+            self.group.telemetry_keys.release(key);
+        }
+    }
+
     #[inline]
     pub fn actor(&self) -> Addr {
         self.actor.addr
@@ -88,6 +125,23 @@ impl Scope {
         &self.actor.telemetry_meta
     }
 
+    /// Returns `node_no` of the topology the actor belongs to.
+    pub(crate) fn node_no(&self) -> Option<NodeNo> {
+        self.group.node_no.get()
+    }
+
+    /// Returns the clock of the topology the actor belongs to.
+    #[inline]
+    pub(crate) fn clock(&self) -> &Clock {
+        &self.group.clock
+    }
+
+    /// Returns the journal of the topology the actor belongs to.
+    #[inline]
+    pub(crate) fn journal(&self) -> &Journal {
+        &self.group.journal
+    }
+
     /// Returns the current trace id.
     #[inline]
     pub fn trace_id(&self) -> TraceId {
@@ -100,6 +154,31 @@ impl Scope {
         self.trace_id.set(trace_id);
     }
 
+    /// Returns the current baggage.
+    #[inline]
+    pub fn baggage(&self) -> Baggage {
+        self.baggage.borrow().clone()
+    }
+
+    /// Replaces the current baggage with the provided one.
+    #[inline]
+    pub fn set_baggage(&self, baggage: Baggage) {
+        *self.baggage.borrow_mut() = baggage;
+    }
+
+    /// Returns the id of the message being handled, if any.
+    /// It becomes the parent of messages sent while handling it.
+    #[inline]
+    pub fn message_id(&self) -> Option<MessageId> {
+        self.message_id.get()
+    }
+
+    /// Replaces the id of the message being handled.
+    #[inline]
+    pub(crate) fn set_message_id(&self, message_id: Option<MessageId>) {
+        self.message_id.set(message_id);
+    }
+
     /// Returns the current permissions (for logging, telemetry and so on).
     #[inline]
     pub fn permissions(&self) -> Permissions {
@@ -122,6 +201,30 @@ impl Scope {
         &self.group.dumping
     }
 
+    pub(crate) fn overload(&self) -> Guard<Arc<OverloadConfig>> {
+        self.group.overload.load()
+    }
+
+    pub(crate) fn hedging(&self) -> Guard<Arc<HedgingConfig>> {
+        self.group.hedging.load()
+    }
+
+    pub(crate) fn tracing(&self) -> Guard<Arc<TracingConfig>> {
+        self.group.tracing.load()
+    }
+
+    pub(crate) fn requests(&self) -> Guard<Arc<RequestsConfig>> {
+        self.group.requests.load()
+    }
+
+    pub(crate) fn handling(&self) -> Guard<Arc<HandlingConfig>> {
+        self.group.handling.load()
+    }
+
+    pub(crate) fn flags(&self) -> Guard<Arc<FlagsConfig>> {
+        self.group.flags.load()
+    }
+
     #[doc(hidden)]
     #[stability::unstable]
     pub fn increment_allocated_bytes(&self, by: usize) {
@@ -136,6 +239,28 @@ impl Scope {
             .fetch_add(by, Ordering::Relaxed);
     }
 
+    /// Returns a slot to attribute resident memory to the current group,
+    /// see [`adjust_resident_bytes()`].
+    #[doc(hidden)]
+    #[stability::unstable]
+    pub fn resident_slot(&self) -> u8 {
+        self.group
+            .addr
+            .group_no()
+            .map_or(0, |group_no| group_no.into_bits())
+    }
+
+    /// Returns bytes allocated by the current group and not freed yet,
+    /// if tracked by the allocator.
+    pub(crate) fn resident_bytes(&self) -> Option<isize> {
+        if !IS_RESIDENT_TRACKED.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let slot = usize::from(self.resident_slot());
+        Some(RESIDENT_BYTES[slot].load(Ordering::Relaxed))
+    }
+
     pub(crate) fn take_allocated_bytes(&self) -> usize {
         self.actor.allocated_bytes.swap(0, Ordering::Relaxed)
     }
@@ -159,6 +284,8 @@ struct ScopeActorShared {
     addr: Addr,
     meta: Arc<ActorMeta>,
     telemetry_meta: Arc<ActorMeta>,
+    /// Whether the key of `telemetry_meta` is admitted by `ActorKeys`.
+    is_key_admitted: bool,
     allocated_bytes: AtomicUsize,
     deallocated_bytes: AtomicUsize,
 }
@@ -169,18 +296,31 @@ impl ScopeActorShared {
             addr,
             meta: meta.clone(),
             telemetry_meta: meta,
+            is_key_admitted: false,
             allocated_bytes: AtomicUsize::new(0),
             deallocated_bytes: AtomicUsize::new(0),
         }
     }
 
-    fn with_telemetry(&self, config: &TelemetryConfig) -> Self {
+    fn with_telemetry(&self, config: &TelemetryConfig, keys: &ActorKeys) -> Self {
+        let mut key = config.per_actor_key.key(&self.meta.key);
+        let mut is_key_admitted = false;
+
+        if let Some(max) = config.max_actor_keys {
+            if config.per_actor_key.is_enabled() {
+                let admitted = key.as_deref().unwrap_or(&self.meta.key);
+                is_key_admitted = keys.admit(admitted, max);
+                if !is_key_admitted {
+                    key = Some(OTHER_KEY.into());
+                }
+            }
+        }
+
         Self {
             addr: self.addr,
             meta: self.meta.clone(),
-            telemetry_meta: config
-                .per_actor_key
-                .key(&self.meta.key)
+            is_key_admitted,
+            telemetry_meta: key
                 .map(|key| {
                     Arc::new(ActorMeta {
                         group: self.meta.group.clone(),
@@ -198,20 +338,41 @@ assert_impl_all!(ScopeGroupShared: Send, Sync);
 
 pub(crate) struct ScopeGroupShared {
     addr: Addr,
+    node_no: LocalNodeNo,
+    clock: Clock,
+    journal: Journal,
     permissions: AtomicPermissions,
     logging: LoggingControl,
     dumping: DumpingControl,
+    overload: ArcSwap<OverloadConfig>,
+    hedging: ArcSwap<HedgingConfig>,
+    tracing: ArcSwap<TracingConfig>,
+    requests: ArcSwap<RequestsConfig>,
+    handling: ArcSwap<HandlingConfig>,
+    flags: ArcSwap<FlagsConfig>,
+    /// Actor keys admitted to metrics, see `system.telemetry.max_actor_keys`.
+    telemetry_keys: ActorKeys,
 }
 
 assert_impl_all!(ScopeGroupShared: Send, Sync);
 
 impl ScopeGroupShared {
-    pub(crate) fn new(addr: Addr) -> Self {
+    pub(crate) fn new(addr: Addr, node_no: LocalNodeNo, clock: Clock, journal: Journal) -> Self {
         Self {
             addr,
+            node_no,
+            clock,
+            journal,
             permissions: Default::default(), // everything is disabled
             logging: Default::default(),
             dumping: Default::default(),
+            overload: Default::default(),
+            hedging: Default::default(),
+            tracing: Default::default(),
+            requests: Default::default(),
+            handling: Default::default(),
+            flags: Default::default(),
+            telemetry_keys: Default::default(),
         }
     }
 
@@ -222,6 +383,24 @@ impl ScopeGroupShared {
         // Update the dumping subsystem.
         self.dumping.configure(&config.dumping);
 
+        // Update the overload detection.
+        self.overload.store(Arc::new(config.overload.clone()));
+
+        // Update the hedging of requests.
+        self.hedging.store(Arc::new(config.hedging.clone()));
+
+        // Update the tracing of handled messages.
+        self.tracing.store(Arc::new(config.tracing.clone()));
+
+        // Update the tracking of pending requests.
+        self.requests.store(Arc::new(config.requests.clone()));
+
+        // Update timeouts of message handling.
+        self.handling.store(Arc::new(config.handling.clone()));
+
+        // Update feature flags.
+        self.flags.store(Arc::new(config.flags.clone()));
+
         // Update permissions.
         let mut perm = self.permissions.load();
         perm.set_logging_enabled(config.logging.max_level.into());
@@ -296,6 +475,106 @@ pub fn try_set_trace_id(trace_id: TraceId) -> bool {
     try_with(|scope| scope.set_trace_id(trace_id)).is_some()
 }
 
+/// Returns the current baggage.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn baggage() -> Baggage {
+    with(Scope::baggage)
+}
+
+/// Returns the current baggage if inside the actor system.
+#[inline]
+pub fn try_baggage() -> Option<Baggage> {
+    try_with(Scope::baggage)
+}
+
+/// Replaces the current baggage with the provided one.
+/// The baggage is captured by sent messages and restored around handling
+/// of the message by the recipient, like the trace id.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn set_baggage(baggage: Baggage) {
+    with(|scope| scope.set_baggage(baggage));
+}
+
+/// Replaces the current baggage with the provided one
+/// if inside the actor system.
+///
+/// Returns `true` if the baggage has been replaced.
+#[inline]
+pub fn try_set_baggage(baggage: Baggage) -> bool {
+    try_with(|scope| scope.set_baggage(baggage)).is_some()
+}
+
+/// Returns the id of the message being handled, if any.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn message_id() -> Option<MessageId> {
+    with(Scope::message_id)
+}
+
+#[inline]
+pub(crate) fn set_message_id(message_id: Option<MessageId>) {
+    with(|scope| scope.set_message_id(message_id));
+}
+
+/// Returns the clock of the current topology, see [`Clock`].
+///
+/// Returns the system clock if called outside the actor system.
+#[inline]
+pub fn clock() -> Clock {
+    try_with(|scope| scope.clock().clone()).unwrap_or_default()
+}
+
+/// Runs the provided future with the specified trace id, restoring the current
+/// one afterwards, even if the future panics or is dropped.
+///
+/// Useful for bridges consuming external events (HTTP, Kafka and so on) to
+/// seed the trace id from upstream headers.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+pub async fn with_trace_id<F: Future>(trace_id: TraceId, f: F) -> F::Output {
+    struct Guard(TraceId);
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            try_set_trace_id(self.0);
+        }
+    }
+
+    let prev = with(|scope| scope.trace_id.replace(trace_id));
+    let _guard = Guard(prev);
+    f.await
+}
+
+// Indexed by `Scope::resident_slot()`, `0` is used outside groups.
+// Unlike other telemetry, it's process-wide, because the allocator is: groups
+// with the same number in different topologies of one process share the slot.
+static RESIDENT_BYTES: [AtomicIsize; 256] = [const { AtomicIsize::new(0) }; 256];
+static IS_RESIDENT_TRACKED: AtomicBool = AtomicBool::new(false);
+
+/// Adjusts resident memory of the group occupying the provided slot.
+///
+/// Unlike `increment_(de)allocated_bytes()`, memory is attributed to the
+/// group that has allocated it, even if it's freed by another group, e.g.
+/// after sending a message. Thus, allocators must remember the slot along
+/// with each allocation.
+#[doc(hidden)]
+#[stability::unstable]
+pub fn adjust_resident_bytes(slot: u8, delta: isize) {
+    RESIDENT_BYTES[usize::from(slot)].fetch_add(delta, Ordering::Relaxed);
+
+    if !IS_RESIDENT_TRACKED.load(Ordering::Relaxed) {
+        IS_RESIDENT_TRACKED.store(true, Ordering::Relaxed);
+    }
+}
+
 /// Returns the current object's meta.
 ///
 /// # Panics
@@ -312,7 +591,7 @@ pub fn try_meta() -> Option<Arc<ActorMeta>> {
 }
 
 thread_local! {
-    static SERDE_MODE: Cell<SerdeMode> = Cell::new(SerdeMode::Normal);
+    static SERDE_MODE: Cell<SerdeMode> = const { Cell::new(SerdeMode::Normal) };
 }
 
 /// A mode of (de)serialization.
@@ -0,0 +1,72 @@
+use std::{
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tokio::sync::Notify;
+
+/// A handle to find out that the actor should stop, obtained by
+/// [`Context::shutdown_token()`].
+///
+/// The token is triggered once [`Terminate`] is sent to the actor, its mailbox
+/// is closed (e.g. by the idle timeout or a rolling restart) or the actor is
+/// finished. Thus, long-running loops and attached tasks can react to
+/// termination without polling the mailbox.
+///
+/// Tokens are cheap to clone, all clones share the same state.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # async fn process_next_chunk() {}
+/// # async fn exec(mut ctx: elfo::Context) {
+/// let token = ctx.shutdown_token();
+///
+/// while !token.is_triggered() {
+///     process_next_chunk().await;
+/// }
+/// # }
+/// ```
+///
+/// [`Context::shutdown_token()`]: crate::Context::shutdown_token
+/// [`Terminate`]: crate::messages::Terminate
+#[derive(Clone, Default)]
+pub struct ShutdownToken(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    is_triggered: AtomicBool,
+    notify: Notify,
+}
+
+impl ShutdownToken {
+    /// Returns `true` if the actor should stop.
+    #[inline]
+    pub fn is_triggered(&self) -> bool {
+        self.0.is_triggered.load(Ordering::Acquire)
+    }
+
+    /// Waits until the actor should stop.
+    /// Completes immediately if the token is already triggered.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn triggered(&self) {
+        let mut notified = pin!(self.0.notify.notified());
+        notified.as_mut().enable();
+
+        if !self.is_triggered() {
+            notified.await;
+        }
+    }
+
+    pub(crate) fn trigger(&self) {
+        if !self.0.is_triggered.swap(true, Ordering::AcqRel) {
+            self.0.notify.notify_waiters();
+        }
+    }
+}
@@ -8,7 +8,6 @@ use std::{
 
 use pin_project::pin_project;
 use sealed::sealed;
-use tokio::signal;
 #[cfg(unix)]
 use tokio::signal::unix;
 #[cfg(windows)]
@@ -85,10 +84,10 @@ enum SignalInner {
 
 /// A kind of signal to listen to.
 ///
-/// * `Unix*` variants are available only on UNIX systems and produce nothing
-/// on other systems.
-/// * `Windows*` variants are available only on Windows and produce nothing
-/// on other systems.
+/// * `Unix*` variants are available only on UNIX systems and produce nothing on
+///   other systems.
+/// * `Windows*` variants are available only on Windows and produce nothing on
+///   other systems.
 ///
 /// It helps to avoid writing `#[cfg(_)]` everywhere around signals.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -145,7 +144,7 @@ impl<M: Message> Signal<M> {
 impl SignalInner {
     #[cfg(unix)]
     fn new(kind: SignalKind) -> io::Result<SignalInner> {
-        use signal::unix::SignalKind as U;
+        use unix::SignalKind as U;
 
         let kind = match kind {
             SignalKind::UnixRaw(signum) => U::from_raw(signum),
@@ -0,0 +1,232 @@
+//! Helpers for actors that are essentially typed state machines.
+//!
+//! [`StateMachine`] declares states, messages allowed in each state and
+//! transitions between them. Then, [`StateMachine::apply()`] is called for
+//! every received envelope before handling it, which replaces pyramids of
+//! `match (state, message)` with a declarative table. Effects, like sending
+//! messages, are still performed by the actor itself.
+//!
+//! Messages not mentioned in the table (e.g. `ConfigUpdated`) aren't governed
+//! by the machine and pass through. Governed messages received in a state that
+//! doesn't allow them are violations: [`TransitionError`] is returned and the
+//! `elfo_fsm_violations_total` metric is incremented.
+//!
+//! # Example
+//! ```
+//! # use elfo_core as elfo;
+//! use elfo::{msg, state_machine::StateMachine};
+//!
+//! # #[elfo::message] struct Submit;
+//! # #[elfo::message] struct Fill { rest: u32 }
+//! # #[elfo::message] struct Cancel;
+//! #[derive(Debug, Clone, PartialEq)]
+//! enum Order {
+//!     New,
+//!     Submitted,
+//!     Filled,
+//!     Canceled,
+//! }
+//!
+//! # async fn exec(mut ctx: elfo::Context) {
+//! let mut fsm = StateMachine::new(Order::New)
+//!     .transition::<Submit>(Order::New, Order::Submitted)
+//!     .on::<Fill>(Order::Submitted, |fill| {
+//!         if fill.rest == 0 { Order::Filled } else { Order::Submitted }
+//!     })
+//!     .transition::<Cancel>(Order::Submitted, Order::Canceled);
+//!
+//! while let Some(envelope) = ctx.recv().await {
+//!     if let Err(err) = fsm.apply(&envelope) {
+//!         tracing::warn!(error = %err, "invalid order transition");
+//!         continue;
+//!     }
+//!
+//!     msg!(match envelope {
+//!         Submit => { /* send to an exchange */ }
+//!         _ => {}
+//!     });
+//! }
+//! # }
+//! ```
+
+use std::fmt;
+
+use metrics::{Key, Label};
+
+use crate::{
+    envelope::Envelope,
+    errors::TransitionError,
+    message::{AnyMessage, Message},
+};
+
+/// A table of states and transitions between them, see [the module-level
+/// documentation](self) for details.
+pub struct StateMachine<S> {
+    state: S,
+    rules: Vec<Rule<S>>,
+}
+
+struct Rule<S> {
+    from: S,
+    is: fn(&AnyMessage) -> bool,
+    next: Box<dyn Fn(&AnyMessage) -> S + Send + Sync>,
+}
+
+/// A transition made by [`StateMachine::apply()`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Transition<S> {
+    pub from: S,
+    pub to: S,
+}
+
+impl<S> StateMachine<S>
+where
+    S: fmt::Debug + Clone + PartialEq,
+{
+    /// Creates a new state machine in the provided state without transitions.
+    pub fn new(initial: S) -> Self {
+        Self {
+            state: initial,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Allows `M` in the `from` state, which leads to the `to` state.
+    pub fn transition<M: Message>(self, from: S, to: S) -> Self
+    where
+        S: Send + Sync + 'static,
+    {
+        self.on::<M>(from, move |_| to.clone())
+    }
+
+    /// Allows `M` in the `from` state, the next state is calculated by `next`.
+    /// Useful if the next state depends on the message.
+    pub fn on<M: Message>(
+        mut self,
+        from: S,
+        next: impl Fn(&M) -> S + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push(Rule {
+            from,
+            is: AnyMessage::is::<M>,
+            next: Box::new(move |message| {
+                next(message.downcast_ref::<M>().expect("checked by `is`"))
+            }),
+        });
+        self
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Makes a transition by the message of the envelope.
+    ///
+    /// Returns `Ok(None)` if the message isn't governed by the machine, i.e.
+    /// isn't mentioned in any transition. Returns `Err` without changing the
+    /// state if the message isn't allowed in the current state.
+    pub fn apply(
+        &mut self,
+        envelope: &Envelope,
+    ) -> Result<Option<Transition<S>>, TransitionError<S>> {
+        let message = envelope.message();
+        let mut is_governed = false;
+
+        for rule in &self.rules {
+            if !(rule.is)(message) {
+                continue;
+            }
+
+            is_governed = true;
+
+            if rule.from == self.state {
+                let to = (rule.next)(message);
+                let from = std::mem::replace(&mut self.state, to.clone());
+                return Ok(Some(Transition { from, to }));
+            }
+        }
+
+        if !is_governed {
+            return Ok(None);
+        }
+
+        if let Some(recorder) = metrics::try_recorder() {
+            let mut labels = message.labels().to_vec();
+            labels.push(Label::new("state", format!("{:?}", self.state)));
+            let key = Key::from_parts("elfo_fsm_violations_total", labels);
+            recorder.increment_counter(&key, 1);
+        }
+
+        Err(TransitionError {
+            state: self.state.clone(),
+            protocol: message.protocol(),
+            message: message.name(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{envelope::MessageKind, message, tracing::TraceId, Addr};
+
+    #[message]
+    struct Start;
+
+    #[message]
+    struct Step(u32);
+
+    #[message]
+    struct Unrelated;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum State {
+        Idle,
+        Running,
+        Done,
+    }
+
+    fn envelope<M: Message>(message: M) -> Envelope {
+        let kind = MessageKind::Regular { sender: Addr::NULL };
+        Envelope::with_trace_id(message, kind, TraceId::try_from(1).unwrap()).upcast()
+    }
+
+    #[test]
+    fn it_works() {
+        let mut fsm = StateMachine::new(State::Idle)
+            .transition::<Start>(State::Idle, State::Running)
+            .on::<Step>(State::Running, |step| {
+                if step.0 == 0 {
+                    State::Done
+                } else {
+                    State::Running
+                }
+            });
+
+        // Not governed messages pass through.
+        assert_eq!(fsm.apply(&envelope(Unrelated)).unwrap(), None);
+
+        // Not allowed in the current state.
+        let err = fsm.apply(&envelope(Step(1))).unwrap_err();
+        assert_eq!(err.state, State::Idle);
+        assert_eq!(err.message, "Step");
+        assert_eq!(fsm.state(), &State::Idle);
+
+        let transition = fsm.apply(&envelope(Start)).unwrap().unwrap();
+        assert_eq!(
+            (transition.from, transition.to),
+            (State::Idle, State::Running)
+        );
+
+        let transition = fsm.apply(&envelope(Step(1))).unwrap().unwrap();
+        assert_eq!(transition.to, State::Running);
+        let transition = fsm.apply(&envelope(Step(0))).unwrap().unwrap();
+        assert_eq!(transition.to, State::Done);
+
+        assert!(fsm.apply(&envelope(Start)).is_err());
+        assert_eq!(fsm.state(), &State::Done);
+    }
+}
@@ -1,52 +1,100 @@
 use std::{
-    any::Any, future::Future, mem, ops::Deref, panic::AssertUnwindSafe, sync::Arc, time::Duration,
+    any::Any,
+    collections::VecDeque,
+    error::Error,
+    future::Future,
+    mem,
+    ops::Deref,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use futures::{future::BoxFuture, FutureExt};
 use fxhash::FxBuildHasher;
-use metrics::{decrement_gauge, increment_gauge};
-use parking_lot::RwLock;
-use tracing::{debug, error, error_span, info, warn, Instrument, Span};
+use metrics::{decrement_gauge, gauge, histogram, increment_counter, increment_gauge, Key, Label};
+use parking_lot::{Mutex, RwLock};
+use quanta::Instant;
+use tokio::sync::{watch, Semaphore};
+use tracing::{debug, error, error_span, info, trace, warn, Instrument, Span};
 
 use elfo_utils::CachePadded;
 
-use self::{backoff::Backoff, error_chain::ErrorChain, measure_poll::MeasurePoll};
+use self::{
+    backoff::Backoff,
+    deferred::{Deferred, Pending},
+    error_chain::ErrorChain,
+    limit::{Limit, Queued},
+    measure_poll::MeasurePoll,
+};
 use crate::{
     actor::{Actor, ActorMeta, ActorStatus},
     config::{AnyConfig, Config, SystemConfig},
     context::Context,
-    envelope::Envelope,
+    envelope::{Envelope, MessageKind},
+    errors::{ExecError, FailureKind},
     exec::{Exec, ExecResult},
-    group::{RestartMode, RestartPolicy, TerminationPolicy},
-    message::Request,
+    group::{AdmissionPolicy, Guard, RestartMode, RestartPolicy, StartPolicy, TerminationPolicy},
+    handling::WithHandlingTimeout,
+    journal::{self, EventKind},
+    message::{Message, Request},
     messages, msg,
-    object::{GroupVisitor, Object, ObjectArc},
+    object::{GroupVisitor, Object, ObjectArc, SendGroupVisitor},
+    restart_budget::{RestartTracker, Verdict},
     routers::{Outcome, Router},
     runtime::RuntimeManager,
     scope::{self, Scope, ScopeGroupShared},
     subscription::SubscriptionManager,
-    tracing::TraceId,
+    tracing::{Baggage, InMessageSpan, TraceId},
     Addr, ResponseToken,
 };
 
 mod backoff;
+mod deferred;
 mod error_chain;
+mod limit;
 mod measure_poll;
+pub(crate) mod readiness;
 
 pub(crate) struct Supervisor<R: Router<C>, C, X> {
     meta: Arc<ActorMeta>,
     restart_policy: RestartPolicy,
     termination_policy: TerminationPolicy,
+    start_policy: StartPolicy,
+    idle_timeout: Option<Duration>,
+    /// Set if `ActorGroup::concurrency_limit()` is used.
+    concurrency: Option<Arc<Semaphore>>,
     span: Span,
     context: Context,
     objects: DashMap<R::Key, ObjectArc, FxBuildHasher>,
+    /// Set if `ActorGroup::max_actors()` or `target_actors()` is used.
+    limit: Option<Limit<R::Key>>,
+    deferred: Deferred<R::Key>,
+    forward_dead_letters: bool,
+    /// Set if `ActorGroup::guard()` is used.
+    guard: Option<Guard>,
     router: R,
     exec: X,
     control: CachePadded<RwLock<ControlBlock<C>>>,
     scope_shared: Arc<ScopeGroupShared>,
     status_subscription: Arc<SubscriptionManager>,
     rt_manager: RuntimeManager,
+    /// Shared between all groups of the node.
+    restarts: Arc<RestartTracker>,
+    /// Actors failed by `FailureKind::Misconfiguration`, spawned again once
+    /// the config is updated. Until then, messages routed to them are
+    /// discarded instead of spawning them again.
+    misconfigured: DashSet<R::Key, FxBuildHasher>,
+    /// Set until `ReleaseBarrier` is received and all held messages are sent.
+    is_gated: AtomicBool,
+    held: Mutex<VecDeque<Envelope>>,
+    /// Notified once a held message is released, senders waiting for room
+    /// in `held` try again.
+    held_room: watch::Sender<()>,
 }
 
 struct ControlBlock<C> {
@@ -54,6 +102,12 @@ struct ControlBlock<C> {
     user_config: Option<Arc<C>>,
     is_started: bool,
     stop_spawning: bool,
+    /// Set for `StartPolicy::Manual` until `StartGroup` is received.
+    is_waiting_for_start: bool,
+    /// The last config received while waiting for `StartGroup`.
+    pending_config: Option<AnyConfig>,
+    /// The last applied config, used if the group is switched to manual start.
+    last_config: Option<AnyConfig>,
 }
 
 /// Returns `None` if cannot be spawned.
@@ -65,7 +119,7 @@ macro_rules! get_or_spawn {
             None => $this
                 .objects
                 .entry(key.clone())
-                .or_try_insert_with(|| $this.spawn(key, Default::default()).ok_or(()))
+                .or_try_insert_with(|| $this.spawn_new(key).ok_or(()))
                 .map(|o| o.downgrade()) // FIXME: take an exclusive lock here.
                 .ok(),
         }
@@ -79,6 +133,7 @@ where
     <X::Output as Future>::Output: ExecResult,
     C: Config,
 {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         ctx: Context,
         group: String,
@@ -86,13 +141,27 @@ where
         router: R,
         restart_policy: RestartPolicy,
         termination_policy: TerminationPolicy,
+        start_policy: StartPolicy,
+        max_actors: Option<usize>,
+        target_actors: Option<usize>,
+        admission_policy: AdmissionPolicy,
+        idle_timeout: Option<Duration>,
+        concurrency_limit: Option<usize>,
+        max_deferred_routes: usize,
+        forward_dead_letters: bool,
+        guard: Option<Guard>,
         rt_manager: RuntimeManager,
+        restarts: Arc<RestartTracker>,
+        is_gated: bool,
     ) -> Self {
         let control = ControlBlock {
             system_config: Default::default(),
             user_config: None,
             is_started: false,
             stop_spawning: false,
+            is_waiting_for_start: start_policy == StartPolicy::Manual,
+            pending_config: None,
+            last_config: None,
         };
 
         let status_subscription = SubscriptionManager::new(ctx.clone());
@@ -105,14 +174,32 @@ where
             }),
             restart_policy,
             termination_policy,
+            start_policy,
+            idle_timeout,
+            concurrency: concurrency_limit.map(|limit| Arc::new(Semaphore::new(limit))),
             objects: DashMap::default(),
+            limit: (max_actors.is_some() || target_actors.is_some())
+                .then(|| Limit::new(max_actors, target_actors, admission_policy)),
+            deferred: Deferred::new(max_deferred_routes),
+            forward_dead_letters,
+            guard,
             router,
             exec,
             control: CachePadded(RwLock::new(control)),
-            scope_shared: Arc::new(ScopeGroupShared::new(ctx.group())),
+            scope_shared: Arc::new(ScopeGroupShared::new(
+                ctx.group(),
+                ctx.book().node_no().clone(),
+                ctx.book().clock(),
+                ctx.book().journal().clone(),
+            )),
             status_subscription: Arc::new(status_subscription),
             context: ctx,
             rt_manager,
+            restarts,
+            misconfigured: DashSet::default(),
+            is_gated: AtomicBool::new(is_gated),
+            held: Mutex::new(VecDeque::new()),
+            held_room: watch::channel(()).0,
         }
     }
 
@@ -128,7 +215,44 @@ where
         .sync_within(|| self.span.in_scope(f));
     }
 
-    pub(crate) fn handle(self: &Arc<Self>, mut envelope: Envelope, visitor: &mut dyn GroupVisitor) {
+    pub(crate) fn handle(self: &Arc<Self>, envelope: Envelope, visitor: &mut dyn GroupVisitor) {
+        if self.is_gated.load(Ordering::Acquire) && is_holdable(&envelope) {
+            let mut held = self.held.lock();
+
+            // Recheck under the lock, held messages can be just released.
+            if self.is_gated.load(Ordering::Acquire) {
+                let max_held = self.control.read().system_config.readiness.max_held;
+
+                if held.len() < max_held {
+                    held.push_back(envelope);
+                    return visitor.done();
+                }
+
+                // Subscribe under the lock to not miss releases.
+                let mut room = self.held_room.subscribe();
+                drop(held);
+
+                // It's usual backpressure for `send()`, which waits and tries again.
+                self.in_scope(|| {
+                    increment_counter!("elfo_rejected_held_messages_total");
+                    debug!(
+                        message = "message isn't held, too many messages are held",
+                        max_held,
+                        name = envelope.message().name(),
+                    );
+                });
+
+                let room = async move {
+                    let _ = room.changed().await;
+                };
+                return visitor.full(envelope, room.boxed());
+            }
+        }
+
+        self.route(envelope, visitor)
+    }
+
+    fn route(self: &Arc<Self>, mut envelope: Envelope, visitor: &mut dyn GroupVisitor) {
         let outcome = msg!(match &envelope {
             messages::ValidateConfig { config } => match config.decode::<C>() {
                 Ok(config) => {
@@ -168,12 +292,28 @@ where
                     }
 
                     control.is_started = true;
+
+                    // Actors are spawned by this config once `StartGroup` is received.
+                    if control.is_waiting_for_start {
+                        control.pending_config = Some(config);
+                        drop(control);
+                        let token = extract_response_token::<messages::UpdateConfig>(envelope);
+                        self.context.respond(token, Ok(()));
+                        return visitor.done();
+                    }
+
                     drop(control);
 
+                    if !only_spawn {
+                        self.respawn_misconfigured();
+                    }
+
                     let outcome = self.router.route(&envelope);
 
                     if only_spawn {
-                        self.spawn_by_outcome(outcome);
+                        if self.start_policy != StartPolicy::OnFirstMessage {
+                            self.spawn_by_outcome(outcome);
+                        }
                         let token = extract_response_token::<messages::UpdateConfig>(envelope);
                         self.context.respond(token, Ok(()));
                         return visitor.done();
@@ -193,6 +333,40 @@ where
                     return visitor.done();
                 }
             },
+            messages::StartGroup => {
+                self.start_manually();
+                msg!(match envelope {
+                    (messages::StartGroup, token) => self.context.respond(token, ()),
+                    _ => {}
+                });
+                return visitor.done();
+            }
+            messages::RollingRestart {
+                group,
+                max_parallel,
+                delay,
+            } => {
+                // Other groups ignore it, so the requester gets `Ignored`.
+                if *group == self.meta.group {
+                    let (max_parallel, delay) = (*max_parallel, *delay);
+                    let token = extract_response_token::<messages::RollingRestart>(envelope);
+                    self.rolling_restart(token, max_parallel, delay);
+                }
+                return visitor.done();
+            }
+            messages::ReleaseBarrier => {
+                self.release_held();
+                return visitor.done();
+            }
+            messages::DescribeActor { key } => {
+                let key = self
+                    .objects
+                    .iter()
+                    .find(|entry| entry.key().to_string() == *key)
+                    .map(|entry| entry.key().clone());
+
+                key.map_or(Outcome::Discard, Outcome::GentleUnicast)
+            }
             messages::SubscribeToActorStatuses { forcing } => {
                 let sender = envelope.sender();
                 self.in_scope(|| self.subscribe_to_statuses(sender, *forcing));
@@ -212,14 +386,51 @@ where
                 self.router.route(&envelope).or(Outcome::Broadcast)
             }
             _ => {
-                self.router.route(&envelope).or(Outcome::Discard)
+                if self
+                    .guard
+                    .as_ref()
+                    .is_none_or(|guard| guard.check(&envelope))
+                {
+                    self.router.route(&envelope).or(Outcome::Discard)
+                } else {
+                    Outcome::DiscardWith("guard")
+                }
             }
         });
 
+        // Keep the order of messages if the sender has deferred ones.
+        let (envelope, outcome) = ward!(
+            self.deferred.try_push(envelope, outcome),
+            return visitor.done()
+        );
+
+        self.visit(envelope, outcome, visitor)
+    }
+
+    fn visit(
+        self: &Arc<Self>,
+        envelope: Envelope,
+        outcome: Outcome<R::Key>,
+        visitor: &mut dyn GroupVisitor,
+    ) {
+        if let Some(limit) = &self.limit {
+            match &outcome {
+                Outcome::Unicast(key) | Outcome::GentleUnicast(key) => limit.touch(key),
+                Outcome::Multicast(list) | Outcome::GentleMulticast(list) => {
+                    list.iter().for_each(|key| limit.touch(key))
+                }
+                _ => {}
+            }
+        }
+
         match outcome {
+            Outcome::Unicast(key) if self.is_misconfigured(&key) => {
+                self.on_discarded(&envelope, "misconfigured");
+                visitor.empty(envelope)
+            }
             Outcome::Unicast(key) => match get_or_spawn!(self, key) {
                 Some(object) => visitor.visit_last(&object, envelope),
-                None => visitor.empty(envelope),
+                None => self.enqueue(envelope, visitor),
             },
             Outcome::GentleUnicast(key) => match self.objects.get(&key) {
                 Some(object) => visitor.visit_last(&object, envelope),
@@ -227,7 +438,7 @@ where
             },
             Outcome::Multicast(list) => {
                 for key in list.iter() {
-                    if !self.objects.contains_key(key) {
+                    if !self.objects.contains_key(key) && !self.is_misconfigured(key) {
                         get_or_spawn!(self, key.clone());
                     }
                 }
@@ -240,10 +451,102 @@ where
             }
             Outcome::Broadcast => self.visit_multiple(envelope, visitor, self.objects.iter()),
             Outcome::Discard => visitor.empty(envelope),
+            Outcome::DiscardWith(reason) => {
+                self.on_discarded(&envelope, reason);
+                visitor.empty(envelope)
+            }
+            Outcome::Deferred => {
+                let future = self.router.route_deferred(&envelope);
+                let sender = envelope.sender();
+
+                if self.deferred.push(Pending::Deferred(envelope, future)) {
+                    self.drain_deferred(sender);
+                }
+                visitor.done()
+            }
             Outcome::Default => unreachable!("must be altered earlier"),
         }
     }
 
+    fn drain_deferred(self: &Arc<Self>, sender: Addr) {
+        let this = self.clone();
+        let scope = Scope::new(
+            scope::trace_id(),
+            Addr::NULL,
+            self.meta.clone(),
+            self.scope_shared.clone(),
+        );
+
+        tokio::spawn(scope.within(async move {
+            while let Some(pending) = this.deferred.pop(sender) {
+                let (envelope, outcome) = match pending {
+                    Pending::Ready(envelope, outcome) => (envelope, outcome),
+                    Pending::Deferred(envelope, future) => {
+                        let _permit = this.deferred.acquire().await;
+                        scope::set_trace_id(envelope.trace_id());
+                        scope::set_baggage(envelope.baggage().clone());
+
+                        match future.await {
+                            Outcome::Default | Outcome::Deferred => (envelope, Outcome::Discard),
+                            outcome => (envelope, outcome),
+                        }
+                    }
+                };
+
+                scope::set_trace_id(envelope.trace_id());
+                let mut visitor = SendGroupVisitor::new(this.context.book(), None);
+                this.visit(envelope, outcome, &mut visitor);
+
+                if let Err(err) = visitor.finish().await {
+                    trace!(error = %err, "deferred message is lost");
+                }
+            }
+        }));
+    }
+
+    fn on_discarded(&self, envelope: &Envelope, reason: &'static str) {
+        if let Some(recorder) = metrics::try_recorder() {
+            let mut labels = envelope.message().labels().to_vec();
+            labels.push(Label::new("recipient_group", self.meta.group.clone()));
+            labels.push(Label::new("reason", reason));
+            let key = Key::from_parts("elfo_discarded_messages_total", labels);
+            recorder.increment_counter(&key, 1);
+        }
+
+        if !self.forward_dead_letters {
+            return;
+        }
+
+        let dead_letter = messages::DeadLetter {
+            reason: reason.into(),
+            message: envelope.message().clone(),
+        };
+
+        self.in_scope(|| {
+            if let Err(err) = self.context.try_send(dead_letter) {
+                debug!(%reason, error = %err, "dead letter is lost");
+            }
+        });
+    }
+
+    /// Called if the actor cannot be spawned.
+    fn enqueue(self: &Arc<Self>, envelope: Envelope, visitor: &mut dyn GroupVisitor) {
+        let limit = ward!(&self.limit, return visitor.empty(envelope));
+
+        {
+            let control = self.control.read();
+            if control.stop_spawning || control.is_waiting_for_start {
+                return visitor.empty(envelope);
+            }
+        }
+
+        match limit.enqueue(envelope) {
+            Queued::Done => visitor.done(),
+            Queued::Rejected(envelope) => visitor.empty(envelope),
+            Queued::Vacant(envelope) => self.route(envelope, visitor),
+        }
+    }
+
     fn visit_multiple(
         &self,
         envelope: Envelope,
@@ -266,12 +569,61 @@ where
         }
     }
 
-    fn spawn(self: &Arc<Self>, key: R::Key, mut backoff: Backoff) -> Option<ObjectArc> {
+    /// Spawns a new actor if it's allowed by `ActorGroup::max_actors()`.
+    /// Evicts actors exceeding `ActorGroup::target_actors()`.
+    fn spawn_new(self: &Arc<Self>, key: R::Key) -> Option<ObjectArc> {
+        let limit = ward!(
+            &self.limit,
+            return self.spawn(key, Default::default(), None)
+        );
+        let _guard = limit.lock();
+
+        if limit.is_full() {
+            if let Some((evicted, addr)) = limit.evict() {
+                self.evict(evicted, addr);
+            } else {
+                increment_counter!("elfo_rejected_spawns_total");
+                self.in_scope(
+                    || warn!(%key, reason = "limit", "actor isn't spawned, the limit is reached"),
+                );
+                return None;
+            }
+        }
+
+        let object = self.spawn(key.clone(), Default::default(), None)?;
+        limit.insert(key, object.addr());
+
+        for (evicted, addr) in limit.evict_over_target() {
+            self.evict(evicted, addr);
+        }
+
+        Some(object)
+    }
+
+    fn evict(&self, key: R::Key, addr: Addr) {
+        let object = ward!(self.context.book().get_owned(addr));
+        let actor = object.as_actor().expect("a supervisor stores only actors");
+
+        self.in_scope(|| {
+            if actor.close() {
+                actor.set_status(ActorStatus::TERMINATING.with_details("evicted"));
+            }
+            info!(%key, %addr, "actor evicted");
+        });
+    }
+
+    fn spawn(
+        self: &Arc<Self>,
+        key: R::Key,
+        mut backoff: Backoff,
+        snapshot: Option<Box<dyn Any + Send>>,
+    ) -> Option<ObjectArc> {
         let control = self.control.read();
-        if control.stop_spawning {
+        if control.stop_spawning || control.is_waiting_for_start || self.is_misconfigured(&key) {
             return None;
         }
 
+        let spawned_at = Instant::now();
         let group_no = self.context.group().group_no().expect("invalid group addr");
         let entry = self.context.book().vacant_entry(group_no);
         let addr = entry.addr();
@@ -295,7 +647,9 @@ where
             .context
             .clone()
             .with_key(key.clone())
-            .with_config(user_config);
+            .with_config(user_config)
+            .with_idle_timeout(self.idle_timeout)
+            .with_concurrency_limit(self.concurrency.clone());
 
         drop(control);
 
@@ -303,45 +657,101 @@ where
 
         // TODO: move to `harness.rs`.
         let fut = async move {
+            // Includes time in the runtime's queue, which grows if it's saturated.
+            let startup_delay = Instant::now() - spawned_at;
+            histogram!(
+                "elfo_actor_startup_delay_seconds",
+                startup_delay.as_secs_f64()
+            );
+
+            let overload = scope::with(|scope| scope.overload());
+            let max_startup_delay = overload.max_startup_delay.filter(|_| !overload.disabled);
+            if max_startup_delay.is_some_and(|max| startup_delay > max) {
+                warn!(
+                    ?startup_delay,
+                    reason = "runtime_saturated",
+                    "actor startup is delayed"
+                );
+            }
+            drop(overload);
+
             let thread = std::thread::current();
 
             info!(%addr, thread = %thread.name().unwrap_or("?"), "started");
 
-            sv.objects
-                .get(&key)
-                .expect("where is the current actor?")
-                .as_actor()
-                .expect("a supervisor stores only actors")
-                .on_start();
+            let object = sv
+                .context
+                .book()
+                .get_owned(addr)
+                .expect("where is the current actor?");
+            let actor = object.as_actor().expect("a supervisor stores only actors");
+            actor.on_start();
 
             // It must be called after `entry.insert()`.
             let ctx = ctx.with_addr(addr);
-            let fut = AssertUnwindSafe(async { sv.exec.exec(ctx).await.unify() }).catch_unwind();
-            let new_status = match fut.await {
-                Ok(Ok(())) => ActorStatus::TERMINATED,
-                Ok(Err(err)) => ActorStatus::FAILED.with_details(ErrorChain(&*err)),
-                Err(panic) => ActorStatus::FAILED.with_details(panic_to_string(panic)),
+            let fut = InMessageSpan::new(sv.exec.exec(ctx), actor.message_span());
+            let fut = WithHandlingTimeout::new(fut, actor.handling());
+            let fut = AssertUnwindSafe(async { fut.await.map(|r| r.unify()) }).catch_unwind();
+
+            // Panics of attached tasks are failures of the actor.
+            let mut is_timed_out = false;
+            let mut failure_kind = None;
+            let new_status = tokio::select! {
+                result = fut => match result {
+                    Ok(Ok(Ok(()))) => ActorStatus::TERMINATED,
+                    Ok(Ok(Err(err))) => {
+                        failure_kind = classify_failure(&*err);
+                        ActorStatus::FAILED.with_details(ErrorChain(&*err))
+                    }
+                    Ok(Err(timed_out)) => {
+                        is_timed_out = true;
+                        ActorStatus::FAILED.with_details(timed_out)
+                    }
+                    Err(panic) => ActorStatus::FAILED.with_details(panic_to_string(panic)),
+                },
+                reason = actor.attached_tasks().failed() => {
+                    ActorStatus::FAILED.with_details(format!("attached task {reason}"))
+                }
             };
+            drop(object);
 
-            let should_restart = {
+            let (should_restart, is_requested, snapshot) = {
                 let object = sv.objects.get(&key).expect("where is the current actor?");
                 let actor = object.as_actor().expect("a supervisor stores only actors");
 
                 let rp_override = actor.restart_policy();
                 let restart_policy = rp_override.as_ref().unwrap_or(&sv.restart_policy);
-                let should_restart = match restart_policy.mode {
-                    RestartMode::Always => true,
-                    RestartMode::OnFailures => new_status.is_failed(),
-                    RestartMode::Never => false,
+                let is_requested = actor.is_restart_requested();
+                let should_restart = match failure_kind {
+                    Some(FailureKind::Fatal | FailureKind::Misconfiguration) => false,
+                    _ => {
+                        is_requested
+                            || is_timed_out
+                            || match restart_policy.mode {
+                                RestartMode::Always => true,
+                                RestartMode::OnFailures => new_status.is_failed(),
+                                RestartMode::Never => false,
+                            }
+                    }
                 };
 
                 actor.set_status(new_status);
-                should_restart
+                (should_restart, is_requested, actor.take_snapshot())
             };
 
-            let need_to_restart = should_restart && !sv.control.read().stop_spawning;
+            // Requested restarts aren't accounted by the restart budget.
+            let need_to_restart = should_restart
+                && !sv.is_evicted(&key)
+                && !sv.control.read().stop_spawning
+                && (is_requested || sv.check_restart_budget());
             if need_to_restart {
-                let after = backoff.next();
+                let after = if is_requested {
+                    Duration::ZERO
+                } else {
+                    backoff.next()
+                };
+
+                journal::record(EventKind::Restarting { after });
 
                 if after == Duration::ZERO {
                     debug!("actor will be restarted immediately");
@@ -353,21 +763,43 @@ where
                     decrement_gauge!("elfo_restarting_actors", 1.);
                 }
 
-                // Restarted actors should have a new trace id.
+                // Restarted actors should have a new trace id and no baggage.
                 scope::set_trace_id(TraceId::generate());
+                scope::set_baggage(Baggage::default());
+                scope::set_message_id(None);
 
                 backoff.start();
-                if let Some(object) = sv.spawn(key.clone(), backoff) {
+                let object = if sv.is_evicted(&key) {
+                    None
+                } else {
+                    sv.spawn(key.clone(), backoff, snapshot)
+                };
+
+                if let Some(object) = object {
+                    if let Some(limit) = &sv.limit {
+                        limit.update(&key, object.addr());
+                    }
                     sv.objects.insert(key.clone(), object)
                 } else {
                     sv.objects.remove(&key).map(|(_, v)| v)
                 }
+            } else if failure_kind == Some(FailureKind::Misconfiguration) {
+                debug!("actor will be restarted once the config is updated");
+                sv.misconfigured.insert(key.clone());
+                sv.objects.remove(&key).map(|(_, v)| v)
             } else {
                 debug!("actor won't be restarted");
                 sv.objects.remove(&key).map(|(_, v)| v)
             }
             .expect("where is the current actor?");
 
+            if let Some(limit) = &sv.limit {
+                sv.release_queued(limit.remove(&key, addr));
+            }
+
+            // The restarted actor is already admitted, so its key is kept.
+            scope::with(|scope| scope.release_telemetry());
+
             // TODO: should we unregister the address right after failure?
             sv.context.book().remove(addr);
         };
@@ -385,6 +817,9 @@ where
             self.termination_policy.clone(),
             self.status_subscription.clone(),
         );
+        if let Some(snapshot) = snapshot {
+            actor.set_snapshot(snapshot);
+        }
         entry.insert(Object::new(addr, actor));
 
         let scope = Scope::new(scope::trace_id(), addr, meta, self.scope_shared.clone())
@@ -400,6 +835,231 @@ where
         Some(object)
     }
 
+    fn respawn_misconfigured(self: &Arc<Self>) {
+        let keys = self
+            .misconfigured
+            .iter()
+            .map(|key| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            self.misconfigured.remove(&key);
+            get_or_spawn!(self, key);
+        }
+    }
+
+    /// Returns `false` if the group is switched to manual start by the
+    /// restart storm, so the actor mustn't be restarted.
+    fn check_restart_budget(&self) -> bool {
+        let Verdict::Storm {
+            is_new,
+            restarts,
+            manual_start,
+        } = self.restarts.on_restart()
+        else {
+            return true;
+        };
+
+        if is_new {
+            increment_counter!("elfo_restart_storms_total");
+            error!(
+                restarts,
+                "restart storm, the node-wide restart budget is exceeded"
+            );
+
+            let storm = messages::RestartStorm {
+                group: self.meta.group.clone(),
+                restarts,
+            };
+
+            if let Err(err) = self.context.try_send(storm) {
+                debug!(error = %err, "restart storm event is lost");
+            }
+        }
+
+        if !manual_start {
+            return true;
+        }
+
+        let mut control = self.control.write();
+        if !mem::replace(&mut control.is_waiting_for_start, true) {
+            // Actors are spawned by this config once `StartGroup` is received.
+            control.pending_config = control.last_config.clone();
+            drop(control);
+            warn!("the group is switched to manual start by the restart storm");
+        }
+
+        false
+    }
+
+    fn release_held(self: &Arc<Self>) {
+        let this = self.clone();
+
+        let scope = Scope::new(
+            scope::trace_id(),
+            Addr::NULL,
+            self.meta.clone(),
+            self.scope_shared.clone(),
+        );
+
+        // Held messages are sent one by one to preserve their order. New ones are
+        // still held until the queue is drained.
+        tokio::spawn(scope.within(async move {
+            let mut count = 0;
+
+            loop {
+                let envelope = {
+                    let mut held = this.held.lock();
+                    let envelope = held.pop_front();
+                    if envelope.is_none() {
+                        this.is_gated.store(false, Ordering::Release);
+                    }
+                    envelope
+                };
+
+                // Fails only if nobody waits for room.
+                let _ = this.held_room.send(());
+
+                let Some(envelope) = envelope else {
+                    break;
+                };
+
+                scope::set_trace_id(envelope.trace_id());
+                let mut visitor = SendGroupVisitor::new(this.context.book(), None);
+                this.route(envelope, &mut visitor);
+
+                if let Err(err) = visitor.finish().await {
+                    let error = err.to_string();
+                    let name = err.into_inner().message().name();
+
+                    this.in_scope(|| {
+                        increment_counter!("elfo_lost_held_messages_total");
+                        warn!(message = "held message is lost", error, name);
+                    });
+                }
+
+                count += 1;
+            }
+
+            this.in_scope(|| info!(count, "released held messages"));
+        }));
+    }
+
+    /// Misconfigured actors aren't spawned until the config is updated.
+    fn is_misconfigured(&self, key: &R::Key) -> bool {
+        self.misconfigured.contains(key)
+    }
+
+    /// Evicted actors are never restarted.
+    fn is_evicted(&self, key: &R::Key) -> bool {
+        self.limit
+            .as_ref()
+            .is_some_and(|limit| !limit.is_active(key))
+    }
+
+    fn rolling_restart(
+        self: &Arc<Self>,
+        token: ResponseToken<messages::RollingRestart>,
+        max_parallel: usize,
+        delay: Duration,
+    ) {
+        let addrs = self.objects.iter().map(|o| o.addr()).collect::<Vec<_>>();
+        let this = self.clone();
+
+        let scope = Scope::new(
+            scope::trace_id(),
+            Addr::NULL,
+            self.meta.clone(),
+            self.scope_shared.clone(),
+        );
+
+        tokio::spawn(scope.within(async move {
+            info!(
+                actors = addrs.len(),
+                max_parallel,
+                ?delay,
+                "rolling restart started"
+            );
+
+            for (no, wave) in addrs.chunks(max_parallel.max(1)).enumerate() {
+                if no > 0 {
+                    tokio::time::sleep(delay).await;
+                }
+
+                // Already terminated actors are skipped.
+                let objects = wave
+                    .iter()
+                    .filter_map(|addr| this.context.book().get_owned(*addr))
+                    .collect::<Vec<_>>();
+
+                for object in &objects {
+                    let actor = object.as_actor().expect("a supervisor stores only actors");
+                    if actor.request_restart() {
+                        actor.set_status(ActorStatus::TERMINATING.with_details("rolling restart"));
+                    }
+                }
+
+                for object in &objects {
+                    let actor = object.as_actor().expect("a supervisor stores only actors");
+                    actor.finished().await;
+                }
+            }
+
+            info!("rolling restart finished");
+            this.context.respond(token, ());
+        }));
+    }
+
+    fn release_queued(self: &Arc<Self>, queued: VecDeque<Envelope>) {
+        if queued.is_empty() {
+            return;
+        }
+
+        let this = self.clone();
+
+        let scope = Scope::new(
+            scope::trace_id(),
+            Addr::NULL,
+            self.meta.clone(),
+            self.scope_shared.clone(),
+        );
+
+        // Messages are routed again in the same order, they're queued again if
+        // the limit is still reached.
+        tokio::spawn(scope.within(async move {
+            for envelope in queued {
+                let mut visitor = SendGroupVisitor::new(this.context.book(), None);
+                this.route(envelope, &mut visitor);
+
+                if let Err(err) = visitor.finish().await {
+                    this.in_scope(|| trace!(error = %err, "queued message is lost"));
+                }
+            }
+        }));
+    }
+
+    fn start_manually(self: &Arc<Self>) {
+        let config = {
+            let mut control = self.control.write();
+            if !mem::replace(&mut control.is_waiting_for_start, false) {
+                return;
+            }
+            control.pending_config.take()
+        };
+
+        self.in_scope(|| info!("starting the group"));
+
+        // Without a config the group is started on the first `UpdateConfig`.
+        if let Some(config) = config {
+            let envelope = Envelope::new(
+                messages::UpdateConfig::new(config),
+                MessageKind::Regular { sender: Addr::NULL },
+            )
+            .upcast();
+            self.spawn_by_outcome(self.router.route(&envelope));
+        }
+    }
+
     fn spawn_by_outcome(self: &Arc<Self>, outcome: Outcome<R::Key>) {
         match outcome {
             Outcome::Unicast(key) => {
@@ -414,6 +1074,8 @@ where
             | Outcome::GentleMulticast(_)
             | Outcome::Broadcast
             | Outcome::Discard
+            | Outcome::DiscardWith(_)
+            | Outcome::Deferred
             | Outcome::Default => {}
         }
     }
@@ -423,17 +1085,24 @@ where
         self.scope_shared.configure(system);
 
         // Update user's config.
+        control.last_config = Some(config.clone());
         control.system_config = config.get_system().clone();
         control.user_config = Some(config.get_user::<C>().clone());
         self.router
             .update(control.user_config.as_ref().expect("just saved"));
 
         self.in_scope(|| {
+            for (flag, value) in system.flags.iter() {
+                gauge!("elfo_feature_flags", f64::from(u8::from(value)), "flag" => flag.to_owned());
+            }
+
             debug!(
                 message = "config updated",
                 system = ?control.system_config,
                 custom = ?control.user_config.as_ref().unwrap(),
-            )
+            );
+
+            journal::record(EventKind::ConfigUpdated);
         });
     }
 
@@ -482,6 +1151,20 @@ where
     }
 }
 
+/// Messages controlling the group itself are never held.
+fn is_holdable(envelope: &Envelope) -> bool {
+    msg!(match envelope {
+        messages::ValidateConfig
+        | messages::UpdateConfig
+        | messages::StartGroup
+        | messages::RollingRestart
+        | messages::ReleaseBarrier
+        | messages::SubscribeToActorStatuses
+        | messages::Terminate => false,
+        _ => true,
+    })
+}
+
 fn extract_response_token<R: Request>(envelope: Envelope) -> ResponseToken<R> {
     msg!(match envelope {
         (R, token) => token,
@@ -489,7 +1172,14 @@ fn extract_response_token<R: Request>(envelope: Envelope) -> ResponseToken<R> {
     })
 }
 
-fn panic_to_string(payload: Box<dyn Any>) -> String {
+/// Finds `ExecError` in the chain, e.g. wrapped by `anyhow::Context`.
+fn classify_failure(err: &(dyn Error + 'static)) -> Option<FailureKind> {
+    std::iter::successors(Some(err), |&err| err.source())
+        .find_map(|err| err.downcast_ref::<ExecError>())
+        .map(ExecError::kind)
+}
+
+pub(crate) fn panic_to_string(payload: Box<dyn Any>) -> String {
     if let Some(message) = payload.downcast_ref::<&str>() {
         format!("panic: {message}")
     } else if let Some(message) = payload.downcast_ref::<String>() {
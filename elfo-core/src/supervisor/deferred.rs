@@ -0,0 +1,106 @@
+//! Messages routed by `Outcome::Deferred`, see `Router::route_deferred()`.
+//!
+//! Messages are queued per sender and routed one by one, so the order is kept
+//! for each sender. Once a sender has queued messages, its next messages are
+//! queued too, even if they're routed synchronously.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use futures::future::BoxFuture;
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::{envelope::Envelope, routers::Outcome, Addr};
+
+pub(super) struct Deferred<K> {
+    /// Limits the number of concurrently resolved outcomes.
+    semaphore: Semaphore,
+    /// The number of senders with queued messages, to avoid locking.
+    senders: AtomicUsize,
+    queues: Mutex<FxHashMap<Addr, VecDeque<Pending<K>>>>,
+}
+
+pub(super) enum Pending<K> {
+    Ready(Envelope, Outcome<K>),
+    Deferred(Envelope, BoxFuture<'static, Outcome<K>>),
+}
+
+impl<K> Pending<K> {
+    fn sender(&self) -> Addr {
+        match self {
+            Self::Ready(envelope, _) | Self::Deferred(envelope, _) => envelope.sender(),
+        }
+    }
+}
+
+impl<K> Deferred<K> {
+    pub(super) fn new(max_concurrent: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(max_concurrent),
+            senders: AtomicUsize::new(0),
+            queues: Mutex::default(),
+        }
+    }
+
+    /// Queues the message if its sender has queued messages.
+    /// Otherwise, or if the outcome is deferred, returns the message back.
+    pub(super) fn try_push(
+        &self,
+        envelope: Envelope,
+        outcome: Outcome<K>,
+    ) -> Option<(Envelope, Outcome<K>)> {
+        if self.senders.load(Ordering::Acquire) == 0 || matches!(outcome, Outcome::Deferred) {
+            return Some((envelope, outcome));
+        }
+
+        let mut queues = self.queues.lock();
+        match queues.get_mut(&envelope.sender()) {
+            Some(queue) => {
+                queue.push_back(Pending::Ready(envelope, outcome));
+                None
+            }
+            None => Some((envelope, outcome)),
+        }
+    }
+
+    /// Queues the message. Returns `true` if the sender has no queued messages
+    /// before, so they should be drained.
+    pub(super) fn push(&self, pending: Pending<K>) -> bool {
+        let mut queues = self.queues.lock();
+        let mut is_new = false;
+
+        queues
+            .entry(pending.sender())
+            .or_insert_with(|| {
+                is_new = true;
+                self.senders.fetch_add(1, Ordering::Release);
+                VecDeque::new()
+            })
+            .push_back(pending);
+
+        is_new
+    }
+
+    /// Returns `None` once the sender has no queued messages. Until that, the
+    /// sender is considered to have queued messages even if the last one is
+    /// popped, because it's still being routed.
+    pub(super) fn pop(&self, sender: Addr) -> Option<Pending<K>> {
+        let mut queues = self.queues.lock();
+        let queue = queues.get_mut(&sender)?;
+
+        let pending = queue.pop_front();
+        if pending.is_none() {
+            queues.remove(&sender);
+            self.senders.fetch_sub(1, Ordering::Release);
+        }
+        pending
+    }
+
+    pub(super) async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.semaphore.acquire().await.expect("never closed")
+    }
+}
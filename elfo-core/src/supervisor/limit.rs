@@ -0,0 +1,173 @@
+//! Limits the number of actors in a group, see `ActorGroup::max_actors()`.
+
+use std::{
+    collections::VecDeque,
+    hash::Hash,
+    mem,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use dashmap::DashMap;
+use fxhash::FxBuildHasher;
+use metrics::{counter, increment_counter};
+use parking_lot::{Mutex, MutexGuard};
+
+use crate::{
+    envelope::Envelope,
+    group::{AdmissionMode, AdmissionPolicy},
+    Addr,
+};
+
+pub(super) struct Limit<K> {
+    max: Option<usize>,
+    target: Option<usize>,
+    mode: AdmissionMode,
+    /// Serializes admission of new actors. Contains queued messages.
+    queued: Mutex<VecDeque<Envelope>>,
+    /// The logical clock to find the least recently active actor.
+    clock: AtomicU64,
+    /// Actors counted against the limit. Evicted actors are removed at once,
+    /// even if they're still finishing.
+    active: DashMap<K, Activity, FxBuildHasher>,
+}
+
+struct Activity {
+    addr: Addr,
+    tick: u64,
+}
+
+pub(super) enum Queued {
+    Done,
+    /// The queue is full or disabled, the message isn't consumed.
+    Rejected(Envelope),
+    /// The limit isn't reached anymore, the message isn't consumed.
+    Vacant(Envelope),
+}
+
+impl<K: Clone + Hash + Eq> Limit<K> {
+    pub(super) fn new(max: Option<usize>, target: Option<usize>, policy: AdmissionPolicy) -> Self {
+        Self {
+            max,
+            target,
+            mode: policy.mode,
+            queued: Mutex::new(VecDeque::new()),
+            clock: AtomicU64::new(0),
+            active: DashMap::default(),
+        }
+    }
+
+    /// Must be held while new actors are admitted.
+    pub(super) fn lock(&self) -> MutexGuard<'_, VecDeque<Envelope>> {
+        self.queued.lock()
+    }
+
+    pub(super) fn is_full(&self) -> bool {
+        self.max.is_some_and(|max| self.active.len() >= max)
+    }
+
+    pub(super) fn is_active(&self, key: &K) -> bool {
+        self.active.contains_key(key)
+    }
+
+    /// Marks the actor as recently active.
+    pub(super) fn touch(&self, key: &K) {
+        if let Some(mut activity) = self.active.get_mut(key) {
+            activity.tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn insert(&self, key: K, addr: Addr) {
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        self.active.insert(key, Activity { addr, tick });
+    }
+
+    /// Called when the actor is restarted with a new address.
+    pub(super) fn update(&self, key: &K, addr: Addr) {
+        if let Some(mut activity) = self.active.get_mut(key) {
+            activity.addr = addr;
+        }
+    }
+
+    /// Stops counting the least recently active actor and returns it.
+    /// Returns `None` if eviction is disabled.
+    pub(super) fn evict(&self) -> Option<(K, Addr)> {
+        if !matches!(self.mode, AdmissionMode::EvictLeastRecentlyActive) {
+            return None;
+        }
+
+        let key = self
+            .active
+            .iter()
+            .min_by_key(|item| item.value().tick)
+            .map(|item| item.key().clone())?;
+
+        let (key, activity) = self.active.remove(&key)?;
+        increment_counter!("elfo_evicted_actors_total");
+        Some((key, activity.addr))
+    }
+
+    /// Stops counting the least recently active actors exceeding the target
+    /// and returns them.
+    pub(super) fn evict_over_target(&self) -> Vec<(K, Addr)> {
+        let target = ward!(self.target, return Vec::new());
+        let excess = self.active.len().saturating_sub(target);
+        if excess == 0 {
+            return Vec::new();
+        }
+
+        let mut ticks = self
+            .active
+            .iter()
+            .map(|item| (item.value().tick, item.key().clone()))
+            .collect::<Vec<_>>();
+
+        if excess < ticks.len() {
+            ticks.select_nth_unstable_by_key(excess, |(tick, _)| *tick);
+            ticks.truncate(excess);
+        }
+
+        let evicted = ticks
+            .into_iter()
+            .filter_map(|(_, key)| self.active.remove(&key))
+            .map(|(key, activity)| (key, activity.addr))
+            .collect::<Vec<_>>();
+
+        counter!("elfo_evicted_actors_total", evicted.len() as u64);
+        evicted
+    }
+
+    pub(super) fn enqueue(&self, envelope: Envelope) -> Queued {
+        let AdmissionMode::Queue { capacity } = self.mode else {
+            return Queued::Rejected(envelope);
+        };
+
+        let mut queued = self.lock();
+
+        // Recheck under the lock, some actor can be just removed.
+        if !self.is_full() {
+            return Queued::Vacant(envelope);
+        }
+
+        if queued.len() >= capacity {
+            return Queued::Rejected(envelope);
+        }
+
+        queued.push_back(envelope);
+        Queued::Done
+    }
+
+    /// Stops counting the terminated actor.
+    /// Returns queued messages, which should be routed again.
+    pub(super) fn remove(&self, key: &K, addr: Addr) -> VecDeque<Envelope> {
+        let mut queued = self.lock();
+        if self
+            .active
+            .remove_if(key, |_, activity| activity.addr == addr)
+            .is_some()
+        {
+            mem::take(&mut *queued)
+        } else {
+            VecDeque::new()
+        }
+    }
+}
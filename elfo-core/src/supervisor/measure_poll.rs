@@ -4,9 +4,10 @@ use std::{
     task::{Context, Poll},
 };
 
-use metrics::Key;
+use metrics::{GaugeValue, Key};
 use pin_project::pin_project;
 use quanta::Instant;
+use tracing::warn;
 
 #[cfg(feature = "unstable-stuck-detection")]
 use crate::stuck_detection::StuckDetector;
@@ -14,6 +15,7 @@ use crate::stuck_detection::StuckDetector;
 static BUSY_TIME_SECONDS: Key = Key::from_static_name("elfo_busy_time_seconds");
 static ALLOCATED_BYTES: Key = Key::from_static_name("elfo_allocated_bytes_total");
 static DEALLOCATED_BYTES: Key = Key::from_static_name("elfo_deallocated_bytes_total");
+static RESIDENT_BYTES: Key = Key::from_static_name("elfo_resident_bytes");
 
 #[pin_project]
 pub(crate) struct MeasurePoll<F> {
@@ -47,16 +49,40 @@ impl<F: Future> Future for MeasurePoll<F> {
         #[cfg(feature = "unstable-stuck-detection")]
         this.stuck_detector.enter();
 
-        let result = if let Some(recorder) = metrics::try_recorder() {
+        let max_busy_time = crate::scope::with(|scope| scope.overload().max_busy_time);
+        let recorder = metrics::try_recorder();
+
+        let result = if recorder.is_some() || max_busy_time.is_some() {
             let start_time = Instant::now();
             let res = this.inner.poll(cx);
             let elapsed = Instant::now().duration_since(start_time);
-            recorder.record_histogram(&BUSY_TIME_SECONDS, elapsed.as_secs_f64());
-            crate::scope::with(|scope| {
-                recorder.increment_counter(&ALLOCATED_BYTES, scope.take_allocated_bytes() as u64);
-                recorder
-                    .increment_counter(&DEALLOCATED_BYTES, scope.take_deallocated_bytes() as u64);
-            });
+
+            if let Some(max_busy_time) = max_busy_time.filter(|max| elapsed > *max) {
+                warn!(
+                    ?elapsed,
+                    ?max_busy_time,
+                    "the actor has been busy for too long without yielding, \
+                     it can starve other actors; consider `ctx.yield_now()`"
+                );
+            }
+
+            if let Some(recorder) = recorder {
+                recorder.record_histogram(&BUSY_TIME_SECONDS, elapsed.as_secs_f64());
+                crate::scope::with(|scope| {
+                    recorder
+                        .increment_counter(&ALLOCATED_BYTES, scope.take_allocated_bytes() as u64);
+                    recorder.increment_counter(
+                        &DEALLOCATED_BYTES,
+                        scope.take_deallocated_bytes() as u64,
+                    );
+
+                    if let Some(bytes) = scope.resident_bytes() {
+                        let value = GaugeValue::Absolute(bytes as f64);
+                        recorder.update_gauge(&RESIDENT_BYTES, value);
+                    }
+                });
+            }
+
             res
         } else {
             this.inner.poll(cx)
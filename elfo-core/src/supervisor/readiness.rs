@@ -0,0 +1,26 @@
+//! Messages are held until dependencies of the group are ready,
+//! see [`Local::wait_for_ready()`].
+//!
+//! The number of held messages is limited by `system.readiness.max_held`.
+//! When the limit is reached, `send()` waits for room, i.e. for the release.
+//! Only `try_send()` and `send_timeout()` (after the timeout) reject messages
+//! with `MailboxFull`. Rejected messages are counted by the
+//! `elfo_rejected_held_messages_total` metric, and held messages that cannot
+//! be delivered after the release by `elfo_lost_held_messages_total`.
+//!
+//! [`Local::wait_for_ready()`]: crate::topology::Local::wait_for_ready
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct ReadinessConfig {
+    /// The maximum number of held messages.
+    pub(crate) max_held: usize,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self { max_held: 100_000 }
+    }
+}
@@ -0,0 +1,119 @@
+//! Tasks attached to actors by `Context::attach_task()`.
+
+use std::{future::Future, panic::AssertUnwindSafe};
+
+use futures::{future::BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use sealed::sealed;
+use tokio::{sync::Notify, task::AbortHandle};
+use tracing::trace;
+
+use crate::{context::Context, message::Message, scope, supervisor};
+
+/// The output of a task attached by [`Context::attach_task()`].
+///
+/// `()` is ignored, messages are sent back to the actor.
+#[sealed]
+pub trait TaskOutput: Send + 'static {
+    #[doc(hidden)]
+    fn deliver(self, ctx: Context) -> BoxFuture<'static, ()>;
+}
+
+#[sealed]
+impl TaskOutput for () {
+    fn deliver(self, _ctx: Context) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
+}
+
+#[sealed]
+impl<M: Message> TaskOutput for M {
+    fn deliver(self, ctx: Context) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            if let Err(err) = ctx.send_to(ctx.addr(), self).await {
+                trace!(error = %err, "the output of the task is lost");
+            }
+        })
+    }
+}
+
+/// Tasks of one actor, aborted once the actor is finished.
+#[derive(Default)]
+pub(crate) struct AttachedTasks {
+    inner: Mutex<Inner>,
+    failed: Notify,
+}
+
+#[derive(Default)]
+struct Inner {
+    handles: Vec<AbortHandle>,
+    failure: Option<String>,
+    is_closed: bool,
+}
+
+impl AttachedTasks {
+    /// Spawns the task. `ctx` must be a pruned context of the owning actor.
+    pub(crate) fn spawn<F>(&self, ctx: Context, future: F)
+    where
+        F: Future + Send + 'static,
+        F::Output: TaskOutput,
+    {
+        let mut inner = self.inner.lock();
+        if inner.is_closed {
+            return;
+        }
+
+        let fut = async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(output) => output.deliver(ctx).await,
+                Err(panic) => {
+                    let reason = supervisor::panic_to_string(panic);
+                    if let Some(object) = ctx.book().get(ctx.addr()) {
+                        let actor = object
+                            .as_actor()
+                            .expect("tasks are attached only to actors");
+                        actor.attached_tasks().fail(reason);
+                    }
+                }
+            }
+        };
+
+        let handle = tokio::spawn(scope::expose().within(fut));
+
+        // Forget about completed tasks to avoid unbounded growth.
+        inner.handles.retain(|handle| !handle.is_finished());
+        inner.handles.push(handle.abort_handle());
+    }
+
+    /// Resolves once any task panics, returning the reason.
+    pub(crate) async fn failed(&self) -> String {
+        loop {
+            if let Some(reason) = self.inner.lock().failure.take() {
+                return reason;
+            }
+
+            self.failed.notified().await;
+        }
+    }
+
+    /// Aborts all tasks and prevents spawning new ones.
+    pub(crate) fn abort_all(&self) {
+        let mut inner = self.inner.lock();
+        inner.is_closed = true;
+
+        for handle in inner.handles.drain(..) {
+            handle.abort();
+        }
+    }
+
+    fn fail(&self, reason: String) {
+        let mut inner = self.inner.lock();
+        if inner.failure.is_none() {
+            inner.failure = Some(reason);
+        }
+        drop(inner);
+
+        // `notify_one()` stores a permit if the actor isn't waiting yet.
+        self.failed.notify_one();
+    }
+}
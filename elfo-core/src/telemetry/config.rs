@@ -11,6 +11,10 @@ use serde::{
 pub(crate) struct TelemetryConfig {
     pub(crate) per_actor_group: bool,
     pub(crate) per_actor_key: PerActorKey,
+    /// Limits the number of distinct keys if `per_actor_key` is enabled.
+    /// Metrics of other actors are aggregated under the `other` key.
+    /// Keys are released once their actors terminate.
+    pub(crate) max_actor_keys: Option<usize>,
 }
 
 pub(crate) enum PerActorKey {
@@ -76,6 +80,7 @@ impl Default for TelemetryConfig {
         Self {
             per_actor_group: true,
             per_actor_key: PerActorKey::Bool(false),
+            max_actor_keys: None,
         }
     }
 }
@@ -0,0 +1,89 @@
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
+/// The key used for metrics of actors exceeding `max_actor_keys`.
+pub(crate) const OTHER_KEY: &str = "other";
+
+/// Limits the number of distinct actor keys in metrics of a group.
+///
+/// Keys are admitted in order of appearance and released once all actors
+/// using them terminate, so new actors replace terminated ones. Restarted
+/// actors keep their keys, because the new incarnation is admitted before
+/// the old one is released.
+#[derive(Default)]
+pub(crate) struct ActorKeys {
+    /// The number of actors using each admitted key.
+    admitted: Mutex<FxHashMap<String, usize>>,
+}
+
+impl ActorKeys {
+    /// Returns `false` if the limit is reached, so `OTHER_KEY` should be used.
+    /// Otherwise, the key must be released by `release()` once the actor
+    /// terminates.
+    pub(crate) fn admit(&self, key: &str, max: usize) -> bool {
+        let mut admitted = self.admitted.lock();
+
+        if let Some(count) = admitted.get_mut(key) {
+            *count += 1;
+            return true;
+        }
+
+        if admitted.len() < max {
+            admitted.insert(key.into(), 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Releases the key returned by `admit()`.
+    pub(crate) fn release(&self, key: &str) {
+        let mut admitted = self.admitted.lock();
+        let count = ward!(admitted.get_mut(key));
+
+        *count -= 1;
+        if *count == 0 {
+            admitted.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_limits_keys() {
+        let keys = ActorKeys::default();
+
+        assert!(keys.admit("a", 2));
+        assert!(keys.admit("b", 2));
+        assert!(!keys.admit("c", 2));
+        assert!(keys.admit("a", 2));
+
+        // The limit is increased by the config.
+        assert!(keys.admit("c", 3));
+        assert!(!keys.admit("d", 3));
+
+        // Admitted keys are kept if the limit is decreased.
+        assert!(keys.admit("b", 1));
+        assert!(!keys.admit("d", 1));
+    }
+
+    #[test]
+    fn it_releases_keys() {
+        let keys = ActorKeys::default();
+
+        assert!(keys.admit("a", 1));
+        assert!(keys.admit("a", 1));
+        assert!(!keys.admit("b", 1));
+
+        // The key is used by two actors.
+        keys.release("a");
+        assert!(!keys.admit("b", 1));
+
+        keys.release("a");
+        assert!(keys.admit("b", 1));
+        assert!(!keys.admit("a", 1));
+    }
+}
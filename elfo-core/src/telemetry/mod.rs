@@ -1,3 +1,7 @@
-pub(crate) use config::TelemetryConfig;
+pub(crate) use self::{
+    config::TelemetryConfig,
+    keys::{ActorKeys, OTHER_KEY},
+};
 
 mod config;
+mod keys;
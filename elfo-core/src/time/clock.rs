@@ -0,0 +1,319 @@
+use std::{
+    fmt,
+    future::Future,
+    ops::{Add, Sub},
+    pin::Pin,
+    sync::Arc,
+    task::{self, Poll, Waker},
+    time::{Duration, SystemTime},
+};
+
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use tokio::time::Sleep;
+
+/// A source of time used by actors of a topology, see
+/// [`Topology::set_clock()`].
+///
+/// It's used by timers ([`Delay`] and [`Interval`]), TTLs and waiting times
+/// of messages, handling telemetry and timestamps of traces, dumps and the
+/// journal. Timeouts of requests and other internal deadlines are still
+/// driven by the tokio timer, use [`tokio::time::pause()`] to control them.
+///
+/// The system clock is used by default. A simulated clock is moved only by
+/// [`Clock::advance()`] and [`Clock::advance_to()`], which makes tests
+/// deterministic and allows replaying dumped traffic at original timestamps.
+///
+/// The clock of the current actor is returned by [`scope::clock()`].
+///
+/// [`Topology::set_clock()`]: crate::Topology::set_clock
+/// [`Delay`]: crate::time::Delay
+/// [`Interval`]: crate::time::Interval
+/// [`scope::clock()`]: crate::scope::clock
+#[derive(Clone, Default)]
+pub struct Clock(Option<Arc<Simulated>>);
+
+pub(crate) struct Simulated {
+    // Counts nanoseconds since Unix epoch.
+    clock: quanta::Clock,
+    mock: Arc<quanta::Mock>,
+    // Deadlines and wakers of pending timers.
+    timers: Mutex<Vec<(quanta::Instant, Waker)>>,
+}
+
+impl Clock {
+    /// Returns the system clock.
+    pub fn system() -> Self {
+        Self(None)
+    }
+
+    /// Returns a simulated clock starting at `start`.
+    ///
+    /// # Panics
+    ///
+    /// If `start` is earlier than Unix epoch.
+    #[track_caller]
+    pub fn simulated(start: SystemTime) -> Self {
+        let since_epoch = start
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("the start must be after Unix epoch");
+
+        let (clock, mock) = quanta::Clock::mock();
+        mock.increment(since_epoch);
+
+        Self(Some(Arc::new(Simulated {
+            clock,
+            mock,
+            timers: Mutex::default(),
+        })))
+    }
+
+    /// Returns `true` if the clock is simulated.
+    #[inline]
+    pub fn is_simulated(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Returns the current wall-clock time.
+    #[inline]
+    pub fn now(&self) -> SystemTime {
+        match &self.0 {
+            Some(simulated) => {
+                SystemTime::UNIX_EPOCH + Duration::from_nanos(simulated.mock.value())
+            }
+            None => SystemTime::now(),
+        }
+    }
+
+    /// Returns the current monotonic time.
+    #[inline]
+    pub(crate) fn instant(&self) -> quanta::Instant {
+        match &self.0 {
+            Some(simulated) => simulated.clock.now(),
+            None => quanta::Instant::now(),
+        }
+    }
+
+    /// Moves the simulated clock forward by `duration` and fires timers
+    /// whose deadlines have been reached.
+    ///
+    /// # Panics
+    ///
+    /// If the clock isn't simulated.
+    #[track_caller]
+    pub fn advance(&self, duration: Duration) {
+        let simulated = self
+            .0
+            .as_ref()
+            .expect("only simulated clocks can be advanced");
+        simulated.mock.increment(duration);
+
+        let now = simulated.clock.now();
+        let mut fired = Vec::new();
+
+        simulated.timers.lock().retain(|(deadline, waker)| {
+            let is_fired = *deadline <= now;
+            if is_fired {
+                fired.push(waker.clone());
+            }
+            !is_fired
+        });
+
+        // Wake outside the lock, wakers can poll timers immediately.
+        fired.into_iter().for_each(Waker::wake);
+    }
+
+    /// Moves the simulated clock forward to `time`, e.g. a timestamp of a
+    /// replayed message. Does nothing if `time` is in the past.
+    ///
+    /// # Panics
+    ///
+    /// If the clock isn't simulated.
+    #[track_caller]
+    pub fn advance_to(&self, time: SystemTime) {
+        let duration = time.duration_since(self.now()).unwrap_or_default();
+        self.advance(duration);
+    }
+
+    /// Creates a timer firing after `delay`.
+    pub(crate) fn timer_after(&self, delay: Duration) -> Timer {
+        match &self.0 {
+            Some(simulated) => Timer::Simulated {
+                deadline: simulated.clock.now() + delay,
+                simulated: simulated.clone(),
+            },
+            None => Timer::System(tokio::time::sleep(delay)),
+        }
+    }
+
+    /// Creates a timer firing at `when` of the tokio timer.
+    pub(crate) fn timer_at(&self, when: tokio::time::Instant) -> Timer {
+        match &self.0 {
+            Some(_) => {
+                self.timer_after(when.saturating_duration_since(tokio::time::Instant::now()))
+            }
+            None => Timer::System(tokio::time::sleep_until(when)),
+        }
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_simulated() {
+            f.debug_tuple("Simulated").field(&self.now()).finish()
+        } else {
+            f.write_str("System")
+        }
+    }
+}
+
+/// A deadline of `Timer`, measured by the timer's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Deadline {
+    System(tokio::time::Instant),
+    Simulated(quanta::Instant),
+}
+
+impl Add<Duration> for Deadline {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        match self {
+            Self::System(instant) => Self::System(instant + duration),
+            Self::Simulated(instant) => Self::Simulated(instant + duration),
+        }
+    }
+}
+
+impl Sub<Duration> for Deadline {
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self {
+        match self {
+            Self::System(instant) => Self::System(instant - duration),
+            Self::Simulated(instant) => Self::Simulated(instant - duration),
+        }
+    }
+}
+
+/// A sleep driven by the tokio timer or a simulated clock.
+#[pin_project(project = TimerProj)]
+pub(crate) enum Timer {
+    System(#[pin] Sleep),
+    Simulated {
+        simulated: Arc<Simulated>,
+        deadline: quanta::Instant,
+    },
+}
+
+impl Timer {
+    /// Returns the current time of the timer's clock.
+    pub(crate) fn now(&self) -> Deadline {
+        match self {
+            Self::System(_) => Deadline::System(tokio::time::Instant::now()),
+            Self::Simulated { simulated, .. } => Deadline::Simulated(simulated.clock.now()),
+        }
+    }
+
+    /// Converts the instant of the tokio timer to the timer's clock.
+    pub(crate) fn at(&self, when: tokio::time::Instant) -> Deadline {
+        match self.now() {
+            Deadline::System(_) => Deadline::System(when),
+            now => now + when.saturating_duration_since(tokio::time::Instant::now()),
+        }
+    }
+
+    pub(crate) fn deadline(&self) -> Deadline {
+        match self {
+            Self::System(sleep) => Deadline::System(sleep.deadline()),
+            Self::Simulated { deadline, .. } => Deadline::Simulated(*deadline),
+        }
+    }
+
+    pub(crate) fn reset(self: Pin<&mut Self>, new_deadline: Deadline) {
+        match (self.project(), new_deadline) {
+            (TimerProj::System(sleep), Deadline::System(instant)) => sleep.reset(instant),
+            (TimerProj::Simulated { deadline, .. }, Deadline::Simulated(instant)) => {
+                *deadline = instant;
+            }
+            _ => unreachable!("the deadline of another clock"),
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<()> {
+        let (simulated, deadline) = match self.project() {
+            TimerProj::System(sleep) => return sleep.poll(cx),
+            TimerProj::Simulated {
+                simulated,
+                deadline,
+            } => (simulated, *deadline),
+        };
+
+        // Check under the lock to avoid races with `Clock::advance()`.
+        let mut timers = simulated.timers.lock();
+
+        if simulated.clock.now() >= deadline {
+            return Poll::Ready(());
+        }
+
+        // All timers of an actor share the same waker, so keep the earliest
+        // deadline. Spurious wakeups only lead to polling timers again.
+        let waker = cx.waker();
+        match timers.iter_mut().find(|(_, w)| w.will_wake(waker)) {
+            Some((d, _)) => *d = (*d).min(deadline),
+            None => timers.push((deadline, waker.clone())),
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Context;
+
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    #[test]
+    fn simulated() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = Clock::simulated(start);
+        assert!(clock.is_simulated());
+        assert_eq!(clock.now(), start);
+
+        let instant = clock.instant();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), start + Duration::from_secs(5));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(5));
+
+        // Doesn't go backwards.
+        clock.advance_to(start);
+        assert_eq!(clock.now(), start + Duration::from_secs(5));
+        clock.advance_to(start + Duration::from_secs(7));
+        assert_eq!(clock.now(), start + Duration::from_secs(7));
+    }
+
+    #[test]
+    fn timer() {
+        let clock = Clock::simulated(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        let mut timer = Box::pin(clock.timer_after(Duration::from_secs(1)));
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let deadline = timer.now() + Duration::from_secs(10);
+        timer.as_mut().reset(deadline);
+        assert_eq!(timer.deadline(), deadline);
+        assert!(timer.as_mut().poll(&mut cx).is_pending());
+
+        clock.advance(Duration::from_secs(9));
+        assert!(timer.as_mut().poll(&mut cx).is_pending());
+        clock.advance(Duration::from_secs(1));
+        assert!(timer.as_mut().poll(&mut cx).is_ready());
+        assert!(clock.0.as_ref().unwrap().timers.lock().is_empty());
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 
 use pin_project::pin_project;
 use sealed::sealed;
-use tokio::time::{Duration, Instant, Sleep};
+use tokio::time::{Duration, Instant};
 
 use crate::{
     addr::Addr,
@@ -15,6 +15,7 @@ use crate::{
     message::Message,
     scope,
     source::{SourceArc, SourceStream, UnattachedSource},
+    time::Timer,
     tracing::TraceId,
 };
 
@@ -71,7 +72,7 @@ struct DelaySource<M> {
     message: Option<M>,
     trace_id: Option<TraceId>,
     #[pin]
-    sleep: Sleep,
+    timer: Timer,
 }
 
 impl<M: Message> Delay<M> {
@@ -79,7 +80,7 @@ impl<M: Message> Delay<M> {
     ///
     /// Creates an unattached instance of [`Delay`].
     pub fn new(delay: Duration, message: M) -> UnattachedSource<Self> {
-        Self::until(Instant::now() + delay, message)
+        Self::with_timer(scope::clock().timer_after(delay), message)
     }
 
     /// Schedules the timer to emit the provided message at `when`.
@@ -92,10 +93,14 @@ impl<M: Message> Delay<M> {
     /// which will be replaced in the future to support other runtimes.
     #[stability::unstable]
     pub fn until(when: Instant, message: M) -> UnattachedSource<Self> {
+        Self::with_timer(scope::clock().timer_at(when), message)
+    }
+
+    fn with_timer(timer: Timer, message: M) -> UnattachedSource<Self> {
         let source = DelaySource {
             message: Some(message),
             trace_id: Some(scope::trace_id()),
-            sleep: tokio::time::sleep_until(when),
+            timer,
         };
 
         let source = SourceArc::new(source, true);
@@ -118,7 +123,7 @@ impl<M: Message> SourceStream for DelaySource<M> {
         }
 
         // Wait for a tick from implementation.
-        if !this.sleep.as_mut().poll(cx).is_ready() {
+        if !this.timer.as_mut().poll(cx).is_ready() {
             return Poll::Pending;
         }
 
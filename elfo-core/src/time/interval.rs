@@ -7,13 +7,14 @@ use std::{
 
 use pin_project::pin_project;
 use sealed::sealed;
-use tokio::time::{Duration, Instant, Sleep};
+use tokio::time::{Duration, Instant};
 
 use crate::{
     envelope::{Envelope, MessageKind},
     message::Message,
+    scope,
     source::{SourceArc, SourceStream, UnattachedSource},
-    time::far_future,
+    time::{far_future, Deadline, Timer},
     tracing::TraceId,
     Addr,
 };
@@ -77,7 +78,7 @@ struct IntervalSource<M> {
     period: Duration,
     is_delayed: bool,
     #[pin]
-    sleep: Sleep,
+    timer: Timer,
 }
 
 impl<M: Message> Interval<M> {
@@ -87,7 +88,7 @@ impl<M: Message> Interval<M> {
             message,
             period: NEVER,
             is_delayed: false,
-            sleep: tokio::time::sleep_until(far_future()),
+            timer: scope::clock().timer_at(far_future()),
         };
 
         let source = SourceArc::new(source, false);
@@ -137,8 +138,8 @@ impl<M: Message> Interval<M> {
 
         // Reschedule if inside the period.
         if !*source.is_delayed {
-            let new_deadline = source.sleep.deadline() - *source.period + period;
-            source.sleep.reset(new_deadline);
+            let new_deadline = source.timer.deadline() - *source.period + period;
+            source.timer.reset(new_deadline);
             *source.period = period;
             guard.wake();
         } else {
@@ -157,7 +158,7 @@ impl<M: Message> Interval<M> {
     #[track_caller]
     pub fn start(&self, period: Duration) {
         assert_ne!(period, NEVER, "period must be non-zero");
-        self.schedule(None, period);
+        self.schedule(false, period, |timer| timer.now() + period);
     }
 
     /// Schedules the timer to start emitting ticks every `period`.
@@ -170,7 +171,8 @@ impl<M: Message> Interval<M> {
     /// If `period` is zero.
     #[track_caller]
     pub fn start_after(&self, delay: Duration, period: Duration) {
-        self.start_at(Instant::now() + delay, period);
+        assert_ne!(period, NEVER, "period must be non-zero");
+        self.schedule(true, period, |timer| timer.now() + delay);
     }
 
     /// Schedules the timer to start emitting ticks every `period`.
@@ -190,7 +192,7 @@ impl<M: Message> Interval<M> {
     #[track_caller]
     pub fn start_at(&self, when: Instant, period: Duration) {
         assert_ne!(period, NEVER, "period must be non-zero");
-        self.schedule(Some(when), period);
+        self.schedule(true, period, |timer| timer.at(when));
     }
 
     /// Stops any ticks. To resume ticks use one of `start_*` methods.
@@ -200,18 +202,18 @@ impl<M: Message> Interval<M> {
     ///
     /// [`SourceHandle::terminate()`]: crate::SourceHandle::terminate()
     pub fn stop(&self) {
-        self.schedule(Some(far_future()), NEVER);
+        self.schedule(true, NEVER, |timer| timer.at(far_future()));
     }
 
-    fn schedule(&self, when: Option<Instant>, period: Duration) {
+    fn schedule(&self, is_delayed: bool, period: Duration, when: impl FnOnce(&Timer) -> Deadline) {
         let mut guard = ward!(self.source.lock());
         let source = guard.stream().project();
 
-        *source.is_delayed = when.is_some();
+        *source.is_delayed = is_delayed;
         *source.period = period;
 
-        let new_deadline = when.unwrap_or_else(|| Instant::now() + period);
-        source.sleep.reset(new_deadline);
+        let new_deadline = when(&source.timer);
+        source.timer.reset(new_deadline);
         guard.wake();
     }
 }
@@ -231,7 +233,7 @@ impl<M: Message> SourceStream for IntervalSource<M> {
         }
 
         // Wait for a tick from implementation.
-        if !this.sleep.as_mut().poll(cx).is_ready() {
+        if !this.timer.as_mut().poll(cx).is_ready() {
             return Poll::Pending;
         }
 
@@ -241,8 +243,8 @@ impl<M: Message> SourceStream for IntervalSource<M> {
         // Reset the underlying timer.
         // It would be nice to use `reset_without_reregister` here, but it's private.
         // TODO: consider moving to `tokio::time::Interval`, which uses it internally.
-        let new_deadline = this.sleep.deadline() + *this.period;
-        this.sleep.reset(new_deadline);
+        let new_deadline = this.timer.deadline() + *this.period;
+        this.timer.reset(new_deadline);
 
         // Emit the message.
         let message = this.message.clone();
@@ -2,10 +2,12 @@ use std::time::{Duration, SystemTime};
 
 use tokio::time::Instant;
 
-pub use self::{delay::Delay, interval::Interval};
+pub use self::{clock::Clock, delay::Delay, interval::Interval};
 
+pub(crate) use self::clock::{Deadline, Timer};
 pub(crate) use r#impl::*;
 
+mod clock;
 mod delay;
 mod interval;
 
@@ -18,6 +20,12 @@ fn far_future() -> Instant {
     Instant::now() + Duration::from_secs(86400 * 365 * 30)
 }
 
+/// Returns the current monotonic time of the current topology's clock.
+#[inline]
+pub(crate) fn instant() -> quanta::Instant {
+    crate::scope::try_with(|scope| scope.clock().instant()).unwrap_or_else(quanta::Instant::now)
+}
+
 #[cfg(test)]
 pub(crate) mod r#impl {
     use std::{cell::Cell, time::Duration};
@@ -41,8 +49,9 @@ pub(crate) mod r#impl {
 pub(crate) mod r#impl {
     use super::*;
 
+    /// Returns the current wall-clock time of the current topology's clock.
     #[inline]
     pub(crate) fn now() -> SystemTime {
-        SystemTime::now()
+        crate::scope::try_with(|scope| scope.clock().now()).unwrap_or_else(SystemTime::now)
     }
 }
@@ -1,5 +1,6 @@
-use std::{cell::RefCell, sync::Arc};
+use std::{cell::RefCell, sync::Arc, time::Duration};
 
+use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use sealed::sealed;
 use tokio::runtime::Handle;
@@ -7,16 +8,30 @@ use tokio::runtime::Handle;
 #[cfg(feature = "unstable-stuck-detection")]
 use crate::stuck_detection::StuckDetector;
 use crate::{
-    addr::{Addr, GroupNo, NodeLaunchId},
+    addr::{Addr, GroupNo, NodeLaunchId, NodeNo},
     address_book::{AddressBook, VacantEntry},
     context::Context,
     demux::Demux,
-    envelope::Envelope,
-    group::Blueprint,
+    envelope::{Envelope, MessageKind},
+    errors::StartGroupError,
+    group::{ActorGroup, Blueprint},
+    handle::SystemHandle,
+    journal::Journal,
+    message::{self, Message},
     object::Object,
+    restart_budget::{RestartBudget, RestartTracker},
     runtime::RuntimeManager,
+    time::Clock,
 };
 
+pub use self::{
+    subsystem::{PrefixedTopology, Subsystem},
+    visualize::{GraphConnection, GraphGroup, TopologyGraph},
+};
+
+mod subsystem;
+mod visualize;
+
 pub(crate) const SYSTEM_INIT_GROUP_NO: u8 = 1;
 
 /// The topology defines local and remote groups, and routes between them.
@@ -32,8 +47,16 @@ struct Inner {
     locals: Vec<LocalActorGroup>,
     #[cfg(feature = "network")]
     remotes: Vec<RemoteActorGroup>,
+    #[cfg(feature = "network")]
+    zones: Zones,
     connections: Vec<Connection>,
+    pipelines: Vec<Pipeline>,
+    taps: Vec<Tap>,
+    barriers: Vec<Barrier>,
     rt_manager: RuntimeManager,
+    restarts: Arc<RestartTracker>,
+    /// The runtime the system is started on, used by `SystemHandle`.
+    system_rt: Arc<OnceCell<Handle>>,
 }
 
 impl Default for Inner {
@@ -43,8 +66,15 @@ impl Default for Inner {
             locals: Vec::new(),
             #[cfg(feature = "network")]
             remotes: Vec::new(),
+            #[cfg(feature = "network")]
+            zones: Zones::default(),
             connections: Vec::new(),
+            pipelines: Vec::new(),
+            taps: Vec::new(),
+            barriers: Vec::new(),
             rt_manager: RuntimeManager::default(),
+            restarts: Default::default(),
+            system_rt: Default::default(),
         }
     }
 }
@@ -56,6 +86,16 @@ pub struct LocalActorGroup {
     pub addr: Addr,
     pub name: String,
     pub is_entrypoint: bool,
+    /// Set when a blueprint is mounted.
+    handled_protocols: Option<Vec<String>>,
+}
+
+impl LocalActorGroup {
+    /// Returns protocols declared by [`ActorGroup::handles()`], if any.
+    #[stability::unstable]
+    pub fn handled_protocols(&self) -> Option<&[String]> {
+        self.handled_protocols.as_deref()
+    }
 }
 
 /// Represents a connection between two groups.
@@ -108,6 +148,41 @@ impl Topology {
         self.launch_id
     }
 
+    /// Returns `node_no` of this node, see [`Topology::set_node_no()`].
+    #[stability::unstable]
+    pub fn node_no(&self) -> Option<NodeNo> {
+        self.book.node_no().get()
+    }
+
+    /// Sets `node_no` of this node. Unlike the process-wide one, it's used only
+    /// by actors of this topology, so several nodes can run in one process,
+    /// e.g. in integration tests.
+    ///
+    /// If isn't called, the process-wide `node_no` is used.
+    #[stability::unstable]
+    pub fn set_node_no(&self, node_no: NodeNo) {
+        self.book.node_no().set(node_no);
+    }
+
+    /// Returns the clock used by actors of this topology.
+    pub fn clock(&self) -> Clock {
+        self.book.clock()
+    }
+
+    /// Sets the clock used by actors of this topology, e.g. a simulated one
+    /// for deterministic tests or replaying dumps. See [`Clock`] for details.
+    ///
+    /// Must be called before the topology is started, the system clock is
+    /// used by default.
+    pub fn set_clock(&self, clock: Clock) {
+        self.book.set_clock(clock);
+    }
+
+    /// Returns the journal of this topology, see [`journal`](crate::journal).
+    pub fn journal(&self) -> Journal {
+        self.book.journal().clone()
+    }
+
     #[stability::unstable]
     pub fn add_dedicated_rt<F: Fn(&crate::ActorMeta) -> bool + Send + Sync + 'static>(
         &self,
@@ -117,6 +192,18 @@ impl Topology {
         self.inner.write().rt_manager.add(filter, handle);
     }
 
+    /// Remembers the current runtime as the one the system is started on.
+    pub(crate) fn set_system_rt(&self) {
+        let _ = self.inner.read().system_rt.set(Handle::current());
+    }
+
+    /// Limits restarts of actors across all groups of the node to avoid
+    /// crash loops burning CPU, e.g. because of a broken dependency.
+    /// See [`RestartBudget`] for details. Unlimited by default.
+    pub fn set_restart_budget(&self, budget: RestartBudget) {
+        self.inner.read().restarts.set_budget(budget);
+    }
+
     #[cfg(feature = "unstable-stuck-detection")]
     pub fn stuck_detector(&self) -> StuckDetector {
         self.inner.read().rt_manager.stuck_detector()
@@ -142,10 +229,12 @@ impl Topology {
         let group_no = GroupNo::new(inner.last_group_no, self.launch_id).expect("invalid group no");
 
         let entry = self.book.vacant_entry(group_no);
+        self.book.register_group_name(entry.addr(), &name);
         inner.locals.push(LocalActorGroup {
             addr: entry.addr(),
             name: name.clone(),
             is_entrypoint: false,
+            handled_protocols: None,
         });
 
         Local {
@@ -156,6 +245,17 @@ impl Topology {
         }
     }
 
+    /// Mounts a subsystem: declares its groups named `<prefix>.<name>` and
+    /// routes between them. See [`Subsystem`] for details.
+    ///
+    /// # Panics
+    /// * If the prefix is empty or contains empty parts, e.g. `a..b`.
+    /// * If names of groups are already taken.
+    #[track_caller]
+    pub fn mount(&self, prefix: impl Into<String>, subsystem: Subsystem<'_>) {
+        subsystem::mount(self, prefix.into(), subsystem);
+    }
+
     /// Returns an iterator over all local groups.
     pub fn locals(&self) -> impl Iterator<Item = LocalActorGroup> + '_ {
         let inner = self.inner.read();
@@ -167,6 +267,115 @@ impl Topology {
         let inner = self.inner.read();
         inner.connections.clone().into_iter()
     }
+
+    /// Checks that receiving local groups of all pipelines handle declared
+    /// protocols. Returns an error per violation.
+    ///
+    /// Pipelines to remote groups are checked by
+    /// [`Topology::check_remote_pipelines()`] once the remote node is
+    /// connected.
+    pub(crate) fn check_pipelines(&self) -> Vec<StartGroupError> {
+        let inner = self.inner.read();
+        let mut errors = Vec::new();
+
+        for pipeline in &inner.pipelines {
+            let addr = match &pipeline.to {
+                ConnectionTo::Local(addr) => addr,
+                #[cfg(feature = "network")]
+                ConnectionTo::Remote(_) => continue,
+            };
+
+            let group = inner
+                .locals
+                .iter()
+                .find(|group| group.addr == *addr)
+                .expect("invalid pipeline");
+
+            pipeline.check(&group.name, group.handled_protocols(), &mut errors);
+        }
+
+        errors
+    }
+
+    /// Checks that all tapped protocols allow it. Returns an error per
+    /// violation.
+    pub(crate) fn check_taps(&self) -> Vec<StartGroupError> {
+        let inner = self.inner.read();
+
+        inner
+            .taps
+            .iter()
+            .flat_map(|tap| {
+                tap.protocols
+                    .iter()
+                    .filter(|protocol| !message::is_tappable(protocol))
+                    .map(|protocol| StartGroupError {
+                        group: tap.from.clone(),
+                        reason: format!(
+                            "the `{protocol}` protocol is tapped, but it doesn't allow it, \
+                             see `elfo::allow_tapping!`"
+                        ),
+                    })
+            })
+            .collect()
+    }
+
+    pub(crate) fn barriers(&self) -> Vec<Barrier> {
+        self.inner.read().barriers.clone()
+    }
+}
+
+/// A flow of messages between two local groups, see [`Local::pipeline()`].
+struct Pipeline {
+    name: String,
+    to: ConnectionTo,
+    protocols: Vec<String>,
+}
+
+impl Pipeline {
+    fn check(&self, group: &str, handled: Option<&[String]>, errors: &mut Vec<StartGroupError>) {
+        let Some(handled) = handled else {
+            errors.push(StartGroupError {
+                group: group.into(),
+                reason: format!(
+                    "the group is a recipient of the pipeline `{}`, \
+                     but doesn't declare handled protocols",
+                    self.name
+                ),
+            });
+            return;
+        };
+
+        for protocol in &self.protocols {
+            if !handled.contains(protocol) {
+                errors.push(StartGroupError {
+                    group: group.into(),
+                    reason: format!(
+                        "the pipeline `{}` carries the `{}` protocol, \
+                         but the group doesn't handle it",
+                        self.name, protocol
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// Routed messages of `protocols` are copied from `from` to another group,
+/// see [`Local::tap_to()`].
+struct Tap {
+    from: String,
+    protocols: Vec<String>,
+}
+
+/// Messages to `group` are held until `dependency` is ready,
+/// see [`Local::wait_for_ready()`].
+#[derive(Clone)]
+pub(crate) struct Barrier {
+    pub(crate) group: Addr,
+    pub(crate) dependency: Addr,
+    pub(crate) dependency_name: String,
+    pub(crate) timeout: Duration,
 }
 
 /// Represents a local group's settings.
@@ -243,17 +452,201 @@ impl<'t> Local<'t> {
         self.demux
             .borrow_mut()
             .append(move |_, addrs| addrs.push(addr));
+
+        let mut inner = self.topology.inner.write();
+        inner.connections.push(Connection {
+            from: self.entry.addr(),
+            to: ConnectionTo::Local(addr),
+        });
+    }
+
+    /// Declares a named pipeline: messages of the provided protocols flow
+    /// from this group to `dest`. Routes must be defined separately.
+    ///
+    /// At startup, it's checked that `dest` declares these protocols as
+    /// handled by [`ActorGroup::handles()`], otherwise the system fails to
+    /// start instead of silently dropping messages later.
+    ///
+    /// If `dest` is a remote group, it's checked once a node with this group
+    /// is connected, and the connection is rejected in case of violations.
+    /// Nodes of versions not sending handled protocols aren't checked.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::Topology;
+    ///
+    /// let topology = Topology::empty();
+    /// let producers = topology.local("producers");
+    /// let consumers = topology.local("consumers");
+    ///
+    /// producers.route_all_to(&consumers);
+    /// producers.pipeline("events", &consumers, ["my-protocol"]);
+    /// ```
+    ///
+    /// [`ActorGroup::handles()`]: crate::ActorGroup::handles
+    pub fn pipeline<P: Into<String>>(
+        &self,
+        name: impl Into<String>,
+        dest: &impl PipelineDestination,
+        protocols: impl IntoIterator<Item = P>,
+    ) {
+        let mut inner = self.topology.inner.write();
+        inner.pipelines.push(Pipeline {
+            name: name.into(),
+            to: dest.pipeline_endpoint(),
+            protocols: protocols.into_iter().map(Into::into).collect(),
+        });
+    }
+
+    /// Copies messages of the provided protocols routed from this group to
+    /// `dest`, e.g. an auditing or debugging group. Unlike routes, it doesn't
+    /// affect whether a message is routed: copies are sent only along with
+    /// messages routed to other groups.
+    ///
+    /// Only regular messages are copied, requests and messages sent directly
+    /// by [`Context::send_to()`] aren't. The tapped protocols must allow it by
+    /// [`allow_tapping!`], otherwise the system fails to start.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::Topology;
+    ///
+    /// let topology = Topology::empty();
+    /// let gateways = topology.local("gateways");
+    /// let orders = topology.local("orders");
+    /// let audit = topology.local("audit");
+    ///
+    /// gateways.route_all_to(&orders);
+    /// gateways.tap_to(&audit, ["orders-protocol"]);
+    /// ```
+    ///
+    /// [`Context::send_to()`]: crate::Context::send_to
+    /// [`allow_tapping!`]: crate::allow_tapping
+    pub fn tap_to<P: Into<String>>(
+        &self,
+        dest: &Local<'_>,
+        protocols: impl IntoIterator<Item = P>,
+    ) {
+        let protocols = protocols.into_iter().map(Into::into).collect::<Vec<_>>();
+        let addr = dest.entry.addr();
+
+        let tapped = protocols.clone();
+        self.demux.borrow_mut().append_tap(move |envelope, addrs| {
+            let is_regular = matches!(envelope.message_kind(), MessageKind::Regular { .. });
+            let protocol = envelope.message().protocol();
+
+            if is_regular && tapped.iter().any(|p| p == protocol) && !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        });
+
+        let mut inner = self.topology.inner.write();
+        inner.taps.push(Tap {
+            from: self.name.clone(),
+            protocols,
+        });
+        inner.connections.push(Connection {
+            from: self.entry.addr(),
+            to: ConnectionTo::Local(addr),
+        });
+    }
+
+    /// Declares that this group must not receive messages until `dependency`
+    /// is ready, i.e. its actors have started handling messages.
+    ///
+    /// Until then, messages sent to this group are held by its supervisor and
+    /// delivered in the same order afterwards. If `dependency` isn't ready
+    /// after `timeout`, the messages are released anyway with a warning.
+    ///
+    /// At most `system.readiness.max_held` messages are held. Once the limit
+    /// is reached, `send()` waits for held messages to be released,
+    /// `send_timeout()` fails with `MailboxFull` if it takes too long, and
+    /// `try_send()` fails with `MailboxFull` immediately.
+    ///
+    /// Configs are delivered as usual, so actors of this group are started
+    /// in parallel with `dependency`'s ones. Must be called before
+    /// [`Local::mount()`].
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use std::time::Duration;
+    /// use elfo::Topology;
+    ///
+    /// let topology = Topology::empty();
+    /// let storage = topology.local("storage");
+    /// let api = topology.local("api");
+    ///
+    /// api.route_all_to(&storage);
+    /// api.wait_for_ready(&storage, Duration::from_secs(10));
+    /// ```
+    pub fn wait_for_ready(&self, dependency: &Local<'_>, timeout: Duration) {
+        let mut inner = self.topology.inner.write();
+        inner.barriers.push(Barrier {
+            group: self.entry.addr(),
+            dependency: dependency.entry.addr(),
+            dependency_name: dependency.name.clone(),
+            timeout,
+        });
     }
 
     /// Mounts a blueprint to this group.
     pub fn mount(self, blueprint: Blueprint) {
         let addr = self.entry.addr();
+        let is_gated = {
+            let mut inner = self.topology.inner.write();
+            inner
+                .locals
+                .iter_mut()
+                .find(|group| group.addr == addr)
+                .expect("just created")
+                .handled_protocols = blueprint.handled_protocols;
+            inner.barriers.iter().any(|barrier| barrier.group == addr)
+        };
+
         let book = self.topology.book.clone();
         let ctx = Context::new(book, self.demux.into_inner()).with_group(addr);
-        let rt_manager = self.topology.inner.read().rt_manager.clone();
-        let object = (blueprint.run)(ctx, self.name, rt_manager);
+        let (rt_manager, restarts) = {
+            let inner = self.topology.inner.read();
+            (inner.rt_manager.clone(), inner.restarts.clone())
+        };
+        let object = (blueprint.run)(ctx, self.name, rt_manager, restarts, is_gated);
         self.entry.insert(object);
     }
+
+    /// Turns this group into a handle to send messages and requests from
+    /// non-actor code, e.g. HTTP handlers. Routes must be defined before.
+    ///
+    /// The group is mounted with actors discarding all incoming messages,
+    /// so it isn't possible to mount another blueprint to it.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::Topology;
+    ///
+    /// let topology = Topology::empty();
+    /// let api = topology.local("api");
+    /// let storage = topology.local("storage");
+    ///
+    /// api.route_all_to(&storage);
+    /// let handle = api.handle();
+    /// ```
+    pub fn handle(self) -> SystemHandle {
+        let book = self.topology.book.clone();
+        let ctx = Context::new(book.clone(), self.demux.borrow().clone());
+        let ctx = ctx.with_group(self.entry.addr());
+        let name = self.name.clone();
+        let rt = self.topology.inner.read().system_rt.clone();
+
+        self.mount(
+            ActorGroup::new().exec(|mut ctx| async move { while ctx.recv().await.is_some() {} }),
+        );
+
+        SystemHandle::new(book, ctx, name, rt)
+    }
 }
 
 #[sealed]
@@ -265,6 +658,20 @@ pub trait Destination<F> {
     fn connection_endpoint(&self) -> ConnectionTo;
 }
 
+/// A recipient of pipelines, see [`Local::pipeline()`].
+#[sealed]
+pub trait PipelineDestination {
+    #[doc(hidden)]
+    fn pipeline_endpoint(&self) -> ConnectionTo;
+}
+
+#[sealed]
+impl PipelineDestination for Local<'_> {
+    fn pipeline_endpoint(&self) -> ConnectionTo {
+        ConnectionTo::Local(self.entry.addr())
+    }
+}
+
 #[sealed]
 impl<F> Destination<F> for Local<'_>
 where
@@ -288,12 +695,15 @@ cfg_network!({
     use arc_swap::ArcSwap;
     use fxhash::FxHashMap;
 
-    use crate::{addr::NodeNo, remote::RemoteHandle};
+    use crate::remote::RemoteHandle;
 
     /// Contains nodes available for routing between one specific local group
     /// and set of remote ones with the same group name.
     type Nodes = Arc<ArcSwap<FxHashMap<NodeNo, Addr>>>;
 
+    /// Declared zones of nodes, including this one.
+    type Zones = Arc<ArcSwap<FxHashMap<NodeNo, Arc<str>>>>;
+
     // TODO: remove `Clone` here, possible footgun in the future.
     /// Represents remote group(s).
     #[stability::unstable]
@@ -326,7 +736,7 @@ cfg_network!({
             entry.insert(object);
 
             self.book
-                .register_remote(local_group, remote_group, handle_addr);
+                .register_remote(local_group, remote_group, remote_group_name, handle_addr);
 
             // Update the demux to make `send()` work,
             // but only if there is a route between these groups.
@@ -355,6 +765,27 @@ cfg_network!({
             }
         }
 
+        /// Sets the zone (e.g. an availability zone) of the node,
+        /// used to prefer nodes in the same zone while routing.
+        #[stability::unstable]
+        pub fn set_node_zone(&self, node_no: NodeNo, zone: Option<&str>) {
+            let zones = self.inner.read().zones.clone();
+            zones.rcu(|zones| {
+                let mut zones = (**zones).clone();
+                match zone {
+                    Some(zone) => zones.insert(node_no, zone.into()),
+                    None => zones.remove(&node_no),
+                };
+                zones
+            });
+        }
+
+        /// Returns the zone of the node set by [`Topology::set_node_zone()`].
+        #[stability::unstable]
+        pub fn node_zone(&self, node_no: NodeNo) -> Option<Arc<str>> {
+            self.inner.read().zones.load().get(&node_no).cloned()
+        }
+
         /// Declares a new remote group.
         ///
         /// # Panics
@@ -386,6 +817,27 @@ cfg_network!({
             let inner = self.inner.read();
             inner.remotes.clone().into_iter()
         }
+
+        /// Checks that the remote group `group` handles protocols of all
+        /// pipelines directed to it. `handled` are protocols declared by the
+        /// remote node for this group. Returns an error per violation.
+        #[stability::unstable]
+        pub fn check_remote_pipelines(
+            &self,
+            group: &str,
+            handled: Option<&[String]>,
+        ) -> Vec<StartGroupError> {
+            let inner = self.inner.read();
+            let mut errors = Vec::new();
+
+            for pipeline in &inner.pipelines {
+                if matches!(&pipeline.to, ConnectionTo::Remote(name) if name == group) {
+                    pipeline.check(group, handled, &mut errors);
+                }
+            }
+
+            errors
+        }
     }
 
     /// Represents a remote group's settings.
@@ -400,10 +852,9 @@ cfg_network!({
         F: Fn(&Envelope, &NodeDiscovery) -> Outcome + Send + Sync + 'static,
     {
         fn extend_demux(&self, local_group_no: GroupNo, demux: &mut Demux, filter: F) {
-            let nodes = self
-                .topology
-                .inner
-                .write()
+            let mut inner = self.topology.inner.write();
+            let zones = inner.zones.clone();
+            let nodes = inner
                 .remotes
                 .iter_mut()
                 .find(|group| group.name == self.name)
@@ -412,32 +863,37 @@ cfg_network!({
                 .entry(local_group_no)
                 .or_default()
                 .clone();
+            drop(inner);
 
-            demux.append(move |envelope, addrs| {
-                let discovery = NodeDiscovery(());
+            let discovery = NodeDiscovery { zones };
 
-                match filter(envelope, &discovery) {
-                    Outcome::Unicast(node_no) => {
-                        if let Some(addr) = nodes.load().get(&node_no) {
+            demux.append(move |envelope, addrs| match filter(envelope, &discovery) {
+                Outcome::Unicast(node_no) => {
+                    if let Some(addr) = nodes.load().get(&node_no) {
+                        addrs.push(*addr);
+                    }
+                }
+                Outcome::Multicast(node_nos) => {
+                    let nodes = nodes.load();
+                    for node_no in node_nos {
+                        if let Some(addr) = nodes.get(&node_no) {
                             addrs.push(*addr);
                         }
                     }
-                    Outcome::Multicast(node_nos) => {
-                        let nodes = nodes.load();
-                        for node_no in node_nos {
-                            if let Some(addr) = nodes.get(&node_no) {
-                                addrs.push(*addr);
-                            }
-                        }
+                }
+                Outcome::Broadcast => {
+                    let nodes = nodes.load();
+                    for addr in nodes.values() {
+                        addrs.push(*addr);
                     }
-                    Outcome::Broadcast => {
-                        let nodes = nodes.load();
-                        for addr in nodes.values() {
-                            addrs.push(*addr);
-                        }
+                }
+                Outcome::PreferSameZone(node_nos) => {
+                    let nodes = nodes.load();
+                    if let Some(addr) = prefer_same_zone(&nodes, &node_nos, &discovery) {
+                        addrs.push(addr);
                     }
-                    Outcome::Discard => {}
                 }
+                Outcome::Discard => {}
             });
         }
 
@@ -446,6 +902,13 @@ cfg_network!({
         }
     }
 
+    #[sealed]
+    impl PipelineDestination for Remote<'_> {
+        fn pipeline_endpoint(&self) -> ConnectionTo {
+            ConnectionTo::Remote(self.name.clone())
+        }
+    }
+
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Outcome {
@@ -455,12 +918,60 @@ cfg_network!({
         Multicast(Vec<NodeNo>),
         /// Routes a message to all active nodes.
         Broadcast,
+        /// Routes a message to one of the specified active nodes (or any
+        /// active node if the list is empty), preferring ones in the same zone
+        /// as this node. Otherwise, the first active one is used.
+        PreferSameZone(Vec<NodeNo>),
         /// Discards a message.
         Discard,
     }
 
-    // Nothing for now, reserved for future use.
-    pub struct NodeDiscovery(());
+    /// Provides information about remote nodes for routing.
+    pub struct NodeDiscovery {
+        zones: Zones,
+    }
+
+    impl NodeDiscovery {
+        /// Returns the declared zone of the node.
+        pub fn zone(&self, node_no: NodeNo) -> Option<Arc<str>> {
+            self.zones.load().get(&node_no).cloned()
+        }
+
+        /// Returns `true` if both this node and the specified one are declared
+        /// to be in the same zone.
+        pub fn is_same_zone(&self, node_no: NodeNo) -> bool {
+            let zones = self.zones.load();
+            let this_zone = crate::node::node_no().and_then(|this| zones.get(&this));
+            matches!((this_zone, zones.get(&node_no)), (Some(a), Some(b)) if a == b)
+        }
+    }
+
+    fn prefer_same_zone(
+        nodes: &FxHashMap<NodeNo, Addr>,
+        listed: &[NodeNo],
+        discovery: &NodeDiscovery,
+    ) -> Option<Addr> {
+        let mut fallback = None;
+        let mut check = |node_no: NodeNo, addr: Addr| {
+            if discovery.is_same_zone(node_no) {
+                return Some(addr);
+            }
+            fallback.get_or_insert(addr);
+            None
+        };
+
+        let found = if listed.is_empty() {
+            nodes
+                .iter()
+                .find_map(|(node_no, addr)| check(*node_no, *addr))
+        } else {
+            listed
+                .iter()
+                .find_map(|node_no| check(*node_no, *nodes.get(node_no)?))
+        };
+
+        found.or(fallback)
+    }
 
     #[stability::unstable]
     pub struct RegisterRemoteGroupGuard<'a> {
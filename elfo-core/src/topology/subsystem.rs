@@ -0,0 +1,99 @@
+use super::{Local, Topology};
+
+/// A set of related groups with internal wiring, mounted under a name prefix
+/// by [`Topology::mount()`]. Allows library crates to export whole subsystems
+/// instead of separate blueprints.
+///
+/// Groups are named `<prefix>.<name>`, so their configs are placed in the
+/// `<prefix>` section of the config. Routes to and from groups outside the
+/// subsystem are defined by passing them to the function building the
+/// subsystem.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # fn api_blueprint() -> elfo::Blueprint { elfo::ActorGroup::new().exec(|_| async {}) }
+/// # fn ledger_blueprint() -> elfo::Blueprint { elfo::ActorGroup::new().exec(|_| async {}) }
+/// use elfo::{
+///     topology::{Local, Subsystem},
+///     Topology,
+/// };
+///
+/// // In the `billing` crate.
+/// pub fn subsystem<'a>(gateways: &'a Local<'_>) -> Subsystem<'a> {
+///     Subsystem::new(move |billing| {
+///         let api = billing.local("api");
+///         let ledger = billing.local("ledger");
+///
+///         gateways.route_all_to(&api);
+///         api.route_all_to(&ledger);
+///
+///         api.mount(api_blueprint());
+///         ledger.mount(ledger_blueprint());
+///     })
+/// }
+///
+/// // In the service.
+/// let topology = Topology::empty();
+/// let gateways = topology.local("gateways");
+///
+/// // Configs are `[billing.api]` and `[billing.ledger]`.
+/// topology.mount("billing", subsystem(&gateways));
+/// ```
+#[must_use]
+pub struct Subsystem<'a> {
+    build: Box<dyn FnOnce(&PrefixedTopology<'_>) + 'a>,
+}
+
+impl<'a> Subsystem<'a> {
+    /// Creates a new subsystem, which is built by `f` on mounting.
+    pub fn new(f: impl FnOnce(&PrefixedTopology<'_>) + 'a) -> Self {
+        Self { build: Box::new(f) }
+    }
+}
+
+/// The topology restricted to the prefix of a [`Subsystem`].
+pub struct PrefixedTopology<'t> {
+    topology: &'t Topology,
+    prefix: String,
+}
+
+impl<'t> PrefixedTopology<'t> {
+    /// Returns the prefix of the subsystem, including prefixes of
+    /// subsystems it's mounted to.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the whole topology, e.g. to pass it to batteries.
+    pub fn topology(&self) -> &'t Topology {
+        self.topology
+    }
+
+    /// Declares a new local group named `<prefix>.<name>`.
+    ///
+    /// # Panics
+    /// See [`Topology::local()`].
+    #[track_caller]
+    pub fn local(&self, name: impl AsRef<str>) -> Local<'t> {
+        self.topology
+            .local(format!("{}.{}", self.prefix, name.as_ref()))
+    }
+
+    /// Mounts a nested subsystem under `<prefix>.<name>`.
+    #[track_caller]
+    pub fn mount(&self, name: impl AsRef<str>, subsystem: Subsystem<'_>) {
+        let prefix = format!("{}.{}", self.prefix, name.as_ref());
+        mount(self.topology, prefix, subsystem);
+    }
+}
+
+#[track_caller]
+pub(super) fn mount(topology: &Topology, prefix: String, subsystem: Subsystem<'_>) {
+    assert!(
+        !prefix.is_empty() && prefix.split('.').all(|part| !part.is_empty()),
+        "invalid subsystem prefix `{prefix}`"
+    );
+
+    (subsystem.build)(&PrefixedTopology { topology, prefix });
+}
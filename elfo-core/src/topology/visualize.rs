@@ -0,0 +1,251 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use super::{ConnectionTo, Topology};
+#[cfg(feature = "network")]
+use crate::addr::NodeNo;
+
+/// A snapshot of the topology: groups and connections between them.
+///
+/// Connections are taken from routes declared by [`Local::route_to()`] and
+/// [`Local::route_all_to()`]. Remote connections contain nodes, which are
+/// currently discovered and available for routing.
+///
+/// Can be rendered by [`TopologyGraph::to_dot()`] or
+/// [`TopologyGraph::to_json()`].
+///
+/// [`Local::route_to()`]: crate::topology::Local::route_to
+/// [`Local::route_all_to()`]: crate::topology::Local::route_all_to
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct TopologyGraph {
+    pub groups: Vec<GraphGroup>,
+    pub connections: Vec<GraphConnection>,
+}
+
+/// A group in [`TopologyGraph`].
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct GraphGroup {
+    pub name: String,
+    pub is_remote: bool,
+    pub is_entrypoint: bool,
+}
+
+/// A connection in [`TopologyGraph`].
+/// It always starts from a local group.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct GraphConnection {
+    pub from: String,
+    pub to: String,
+    pub is_remote: bool,
+    /// Nodes available for routing, sorted. Always empty for local connections.
+    #[cfg(feature = "network")]
+    pub nodes: Vec<NodeNo>,
+}
+
+impl Topology {
+    /// Returns a snapshot of groups and connections between them.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::Topology;
+    ///
+    /// let topology = Topology::empty();
+    /// let foo = topology.local("foo");
+    /// let bar = topology.local("bar");
+    /// foo.route_all_to(&bar);
+    ///
+    /// let dot = topology.visualize().to_dot();
+    /// assert!(dot.contains(r#""foo" -> "bar";"#));
+    /// ```
+    pub fn visualize(&self) -> TopologyGraph {
+        let inner = self.inner.read();
+
+        let groups = inner.locals.iter().map(|group| GraphGroup {
+            name: group.name.clone(),
+            is_remote: false,
+            is_entrypoint: group.is_entrypoint,
+        });
+
+        #[cfg(feature = "network")]
+        let groups = groups.chain(inner.remotes.iter().map(|group| GraphGroup {
+            name: group.name.clone(),
+            is_remote: true,
+            is_entrypoint: false,
+        }));
+
+        let groups = groups.collect::<Vec<_>>();
+
+        let local_name = |addr| {
+            inner
+                .locals
+                .iter()
+                .find(|group| group.addr == addr)
+                .map(|group| group.name.clone())
+        };
+
+        let connections = inner
+            .connections
+            .iter()
+            .filter_map(|conn| {
+                let from = local_name(conn.from)?;
+
+                Some(match &conn.to {
+                    ConnectionTo::Local(addr) => GraphConnection {
+                        from,
+                        to: local_name(*addr)?,
+                        is_remote: false,
+                        #[cfg(feature = "network")]
+                        nodes: Vec::new(),
+                    },
+                    #[cfg(feature = "network")]
+                    ConnectionTo::Remote(name) => {
+                        let local_group_no = conn.from.group_no().expect("invalid addr");
+                        let mut nodes = inner
+                            .remotes
+                            .iter()
+                            .find(|group| &group.name == name)
+                            .and_then(|group| group.nodes.get(&local_group_no))
+                            .map(|nodes| nodes.load().keys().copied().collect::<Vec<_>>())
+                            .unwrap_or_default();
+                        nodes.sort_by_key(|node_no| node_no.into_bits());
+
+                        GraphConnection {
+                            from,
+                            to: name.clone(),
+                            is_remote: true,
+                            nodes,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        TopologyGraph {
+            groups,
+            connections,
+        }
+    }
+}
+
+impl TopologyGraph {
+    /// Renders the graph in the DOT format (graphviz).
+    ///
+    /// Entrypoints are bold, remote groups are dashed and prefixed with
+    /// `remote:` to avoid collisions with local ones.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph topology {\n");
+
+        for group in &self.groups {
+            let id = dot_id(&group.name, group.is_remote);
+            let mut attrs = vec![format!("label={}", quote(&group.name)), "shape=box".into()];
+            if group.is_entrypoint {
+                attrs.push("style=bold".into());
+            }
+            if group.is_remote {
+                attrs.push("style=dashed".into());
+            }
+            let _ = writeln!(out, "    {id} [{}];", attrs.join(", "));
+        }
+
+        for conn in &self.connections {
+            let from = dot_id(&conn.from, false);
+            let to = dot_id(&conn.to, conn.is_remote);
+
+            #[cfg(feature = "network")]
+            if conn.is_remote {
+                let nodes = conn
+                    .nodes
+                    .iter()
+                    .map(|node_no| node_no.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let label = quote(&format!("nodes: [{nodes}]"));
+                let _ = writeln!(out, "    {from} -> {to} [label={label}, style=dashed];");
+                continue;
+            }
+
+            let _ = writeln!(out, "    {from} -> {to};");
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("cannot serialize the topology graph")
+    }
+}
+
+fn dot_id(name: &str, is_remote: bool) -> String {
+    if is_remote {
+        quote(&format!("remote:{name}"))
+    } else {
+        quote(name)
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local() {
+        let topology = Topology::empty();
+        let configurers = topology.local("system.configurers").entrypoint();
+        let foo = topology.local("foo");
+        let bar = topology.local("bar");
+        configurers.route_all_to(&foo);
+        foo.route_to(&bar, |_| true);
+
+        let graph = topology.visualize();
+
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph topology {
+    "system.configurers" [label="system.configurers", shape=box, style=bold];
+    "foo" [label="foo", shape=box];
+    "bar" [label="bar", shape=box];
+    "system.configurers" -> "foo";
+    "foo" -> "bar";
+}
+"#
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["groups"].as_array().unwrap().len(), 3);
+        assert_eq!(json["connections"][1]["from"], "foo");
+        assert_eq!(json["connections"][1]["to"], "bar");
+        assert_eq!(json["connections"][1]["is_remote"], false);
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn remote() {
+        use crate::topology::Outcome;
+
+        let topology = Topology::empty();
+        let foo = topology.local("foo");
+        let bar = topology.remote("bar");
+        foo.route_to(&bar, |_, _| Outcome::Broadcast);
+
+        assert_eq!(
+            topology.visualize().to_dot(),
+            r#"digraph topology {
+    "foo" [label="foo", shape=box];
+    "remote:bar" [label="bar", shape=box, style=dashed];
+    "foo" -> "remote:bar" [label="nodes: []", style=dashed];
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::{fmt, sync::Arc};
+
+/// User-defined key-value pairs (e.g. tenant or locale) propagated along with
+/// the trace id.
+///
+/// The baggage of the current scope is captured by every sent message and
+/// restored around handling of the message by the recipient, including
+/// recipients on other nodes. See [`scope::baggage()`] and
+/// [`scope::set_baggage()`].
+///
+/// It's immutable and cheap to clone, modifying methods return a new baggage.
+/// Empty baggage doesn't allocate.
+///
+/// Keep it small, it's copied into every sent envelope. Messages with more
+/// than 255 entries, keys longer than 255 bytes or values longer than 65535
+/// bytes cannot be sent to other nodes.
+///
+/// [`scope::baggage()`]: crate::scope::baggage
+/// [`scope::set_baggage()`]: crate::scope::set_baggage
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Baggage(Option<Arc<Vec<(String, String)>>>);
+
+assert_eq_size!(Baggage, usize);
+
+impl Baggage {
+    /// Returns `true` if there are no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns the number of entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |entries| entries.len())
+    }
+
+    /// Returns the value of the entry with the provided key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Returns entries in the order of insertion.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .flat_map(|entries| entries.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns a new baggage with the entry added or replaced.
+    pub fn with(&self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let (key, value) = (key.into(), value.into());
+        let mut replaced = false;
+
+        let mut entries = self
+            .iter()
+            .map(|(k, v)| {
+                if k == key {
+                    replaced = true;
+                    (key.clone(), value.clone())
+                } else {
+                    (k.to_string(), v.to_string())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if !replaced {
+            entries.push((key, value));
+        }
+
+        Self(Some(Arc::new(entries)))
+    }
+
+    /// Returns a new baggage without the entry with the provided key.
+    pub fn without(&self, key: &str) -> Self {
+        self.iter()
+            .filter(|(k, _)| *k != key)
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Baggage {
+    /// Collects entries, the last one wins if keys are repeated.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |baggage, (k, v)| baggage.with(k, v))
+    }
+}
+
+impl fmt::Debug for Baggage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let empty = Baggage::default();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get("tenant"), None);
+
+        let baggage = empty.with("tenant", "acme").with("locale", "en");
+        assert_eq!(baggage.len(), 2);
+        assert_eq!(baggage.get("tenant"), Some("acme"));
+        assert_eq!(baggage.get("locale"), Some("en"));
+        assert!(empty.is_empty());
+
+        let replaced = baggage.with("tenant", "globex");
+        let entries = replaced.iter().collect::<Vec<_>>();
+        assert_eq!(entries, [("tenant", "globex"), ("locale", "en")]);
+        assert_eq!(baggage.get("tenant"), Some("acme"));
+
+        let removed = replaced.without("tenant").without("unknown");
+        assert_eq!(removed.iter().collect::<Vec<_>>(), [("locale", "en")]);
+        assert!(removed.without("locale").is_empty());
+
+        let collected = [("a", "1"), ("b", "2"), ("a", "3")]
+            .into_iter()
+            .collect::<Baggage>();
+        assert_eq!(collected, Baggage::default().with("a", "3").with("b", "2"));
+        assert_eq!(format!("{collected:?}"), r#"{"a": "3", "b": "2"}"#);
+    }
+}
@@ -1,7 +1,7 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use super::trace_id::{TraceId, TraceIdLayout};
-use crate::{node, time};
+use crate::{node, time, NodeNo};
 
 // === ChunkRegistry ===
 
@@ -36,6 +36,15 @@ impl Generator {
     /// * 12 bits (chunk_no & 0xfff)
     /// * 10 bits counter
     pub(crate) fn generate(&mut self, chunk_registry: &ChunkRegistry) -> TraceId {
+        self.generate_on(chunk_registry, node::node_no())
+    }
+
+    /// The same as `generate()`, but for the provided `node_no`.
+    pub(crate) fn generate_on(
+        &mut self,
+        chunk_registry: &ChunkRegistry,
+        node_no: Option<NodeNo>,
+    ) -> TraceId {
         // Check whether the chunk is exhausted.
         if self.counter == 0x3ff {
             self.chunk_no = next_chunk(chunk_registry);
@@ -47,7 +56,7 @@ impl Generator {
 
         TraceId::from_layout(TraceIdLayout {
             timestamp: time::now().into(),
-            node_no: node::node_no(),
+            node_no,
             bottom: bottom.into(),
         })
     }
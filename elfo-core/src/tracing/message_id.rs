@@ -0,0 +1,92 @@
+use std::{
+    cell::Cell,
+    convert::TryFrom,
+    num::{NonZeroU64, TryFromIntError},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use derive_more::{Display, From, Into};
+use serde::{Deserialize, Serialize};
+
+use crate::addr::NodeNo;
+
+/// The unique identifier of a sent message.
+///
+/// Every envelope gets a new one when it's sent. Along with the id of the
+/// message being handled at that moment (the parent), it allows to build
+/// causality graphs of messages. See [`Envelope::message_id()`].
+///
+/// It's a 64-bit number with the following layout:
+/// * 16 bits node_no (zero if unknown)
+/// * 48 bits counter (non-zero), unique within the node's launch
+///
+/// [`Envelope::message_id()`]: crate::Envelope::message_id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Into, From, Display)]
+#[display(fmt = "{_0}")]
+pub struct MessageId(NonZeroU64);
+
+const COUNTER_MASK: u64 = (1 << 48) - 1;
+const CHUNK_SIZE: u64 = 1024;
+
+// Starts from `1` to avoid zero counters.
+static CHUNK_REGISTRY: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // The next counter and the end of the current chunk.
+    static CHUNK: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+impl MessageId {
+    /// Generates a new message id for the provided node.
+    pub(crate) fn generate_on(node_no: Option<NodeNo>) -> Self {
+        let counter = CHUNK.with(|chunk| {
+            let (mut next, mut end) = chunk.get();
+
+            if next == end {
+                next = CHUNK_REGISTRY.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+                end = next + CHUNK_SIZE;
+            }
+
+            chunk.set((next + 1, end));
+            next
+        });
+
+        // Skip zero counters after wrapping around.
+        let counter = (counter & COUNTER_MASK).max(1);
+        let node_no = u64::from(node_no.map_or(0, |n| n.into_bits()));
+        Self::try_from(node_no << 48 | counter).unwrap()
+    }
+
+    /// Returns the node that has sent the message, if known.
+    pub fn node_no(self) -> Option<NodeNo> {
+        NodeNo::from_bits((self.0.get() >> 48) as u16)
+    }
+}
+
+impl TryFrom<u64> for MessageId {
+    type Error = TryFromIntError;
+
+    #[inline]
+    fn try_from(raw: u64) -> Result<Self, Self::Error> {
+        NonZeroU64::try_from(raw).map(MessageId)
+    }
+}
+
+impl From<MessageId> for u64 {
+    #[inline]
+    fn from(message_id: MessageId) -> Self {
+        message_id.0.get()
+    }
+}
+
+#[test]
+fn uniqueness() {
+    let node_no = NodeNo::from_bits(42);
+    let ids = (0..3 * CHUNK_SIZE)
+        .map(|_| MessageId::generate_on(node_no))
+        .collect::<std::collections::HashSet<_>>();
+
+    assert_eq!(ids.len() as u64, 3 * CHUNK_SIZE);
+    assert!(ids.iter().all(|id| id.node_no() == node_no));
+}
@@ -0,0 +1,101 @@
+//! Spans covering handling of each message, enabled by
+//! `system.tracing.message_spans`.
+//!
+//! A span is opened by `recv()` and `try_recv()` once a message is received,
+//! and closed by the next call of them. It's a child of the actor's span,
+//! which contains the group and the key.
+//!
+//! The span cannot be entered by a guard, because user code runs across
+//! `await` points. Instead, it's entered on every poll of the actor's future
+//! by [`InMessageSpan`], and switched inside `recv()` in the middle of a poll.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use tracing::Span;
+
+/// The span of the message being handled by an actor.
+#[derive(Default)]
+pub(crate) struct MessageSpan(Mutex<Option<Span>>);
+
+impl MessageSpan {
+    /// Enters the provided span and makes it current.
+    pub(crate) fn start(&self, span: Span) {
+        enter(&span);
+        let prev = self.0.lock().replace(span);
+        debug_assert!(prev.is_none());
+    }
+
+    /// Exits and closes the current span, if any.
+    pub(crate) fn finish(&self) {
+        if let Some(span) = self.0.lock().take() {
+            exit(&span);
+        }
+    }
+
+    /// Closes the current span without exiting, used once the actor is
+    /// finished and the span isn't entered anymore.
+    pub(crate) fn close(&self) {
+        self.0.lock().take();
+    }
+
+    fn enter_current(&self) {
+        if let Some(span) = &*self.0.lock() {
+            enter(span);
+        }
+    }
+
+    fn exit_current(&self) {
+        if let Some(span) = &*self.0.lock() {
+            exit(span);
+        }
+    }
+}
+
+fn enter(span: &Span) {
+    span.with_subscriber(|(id, dispatch)| dispatch.enter(id));
+}
+
+fn exit(span: &Span) {
+    span.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+}
+
+/// Enters the current message span on every poll of the inner future.
+#[pin_project]
+pub(crate) struct InMessageSpan<'a, F> {
+    #[pin]
+    inner: F,
+    span: &'a MessageSpan,
+}
+
+impl<'a, F> InMessageSpan<'a, F> {
+    pub(crate) fn new(inner: F, span: &'a MessageSpan) -> Self {
+        Self { inner, span }
+    }
+}
+
+impl<F: Future> Future for InMessageSpan<'_, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        // Exit the span even if the actor panics.
+        struct ExitOnDrop<'a>(&'a MessageSpan);
+
+        impl Drop for ExitOnDrop<'_> {
+            fn drop(&mut self) {
+                self.0.exit_current();
+            }
+        }
+
+        this.span.enter_current();
+        let _guard = ExitOnDrop(this.span);
+        this.inner.poll(cx)
+    }
+}
@@ -1,17 +1,32 @@
-//! Includes `TraceId` and useful utilities around it.
+//! Includes `TraceId`, `MessageId`, `Baggage` and useful utilities around it.
 //! For more details see [The Actoromicon](https://actoromicon.rs/ch05-04-tracing.html).
 
 use std::cell::RefCell;
 
+use serde::Deserialize;
+
 use self::generator::{ChunkRegistry, Generator};
+use crate::NodeNo;
+
+pub use self::{
+    baggage::Baggage,
+    message_id::MessageId,
+    trace_id::{ParseTraceIdError, TraceId},
+    validator::TraceIdValidator,
+};
 
-pub use self::{trace_id::TraceId, validator::TraceIdValidator};
+pub(crate) use self::message_span::{InMessageSpan, MessageSpan};
 
 impl TraceId {
     /// Generates a new trace id according to [the schema](https://actoromicon.rs/ch05-04-tracing.html#traceid).
     pub fn generate() -> Self {
         GENERATOR.with(|cell| cell.borrow_mut().generate(&CHUNK_REGISTRY))
     }
+
+    /// Generates a new trace id for the provided node, e.g. outside actors.
+    pub(crate) fn generate_on(node_no: Option<NodeNo>) -> Self {
+        GENERATOR.with(|cell| cell.borrow_mut().generate_on(&CHUNK_REGISTRY, node_no))
+    }
 }
 
 static CHUNK_REGISTRY: ChunkRegistry = ChunkRegistry::new(0);
@@ -19,6 +34,17 @@ thread_local! {
     static GENERATOR: RefCell<Generator> = RefCell::new(Generator::default());
 }
 
+/// See `system.tracing`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct TracingConfig {
+    /// Opens a span per handled message, see [`MessageSpan`].
+    pub(crate) message_spans: bool,
+}
+
+mod baggage;
 mod generator;
+mod message_id;
+mod message_span;
 mod trace_id;
 mod validator;
@@ -1,15 +1,24 @@
 use std::{
     convert::TryFrom,
     num::{NonZeroU64, TryFromIntError},
+    str::FromStr,
     time::SystemTime,
 };
 
-use derive_more::{Deref, Display, From, Into};
+use derive_more::{Deref, Display, Error, From, Into};
 use serde::{Deserialize, Serialize};
 
 use crate::addr::NodeNo;
 
 /// The struct that represents the trace id.
+///
+/// It's a 63-bit number with the following layout:
+/// * 25 bits timestamp in secs (truncated, wraps every ~388 days)
+/// * 16 bits node_no (zero if unknown)
+/// * 22 bits counter (non-zero)
+///
+/// The canonical textual form is the decimal representation of this number,
+/// it's used by `Display` and `FromStr`.
 // TODO(v0.2): remove `derive(Deserialize)`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[derive(Serialize, Deserialize, Into, From, Display)]
@@ -17,6 +26,43 @@ use crate::addr::NodeNo;
 pub struct TraceId(NonZeroU64);
 
 impl TraceId {
+    /// Builds a trace id from the provided components, e.g. to mint trace ids
+    /// outside the elfo system (HTTP gateways and so on) compatible with
+    /// generated ones. To remain unique across the cluster, such systems must
+    /// use `node_no` not used by any node.
+    ///
+    /// Returns `None` if `counter` is zero or doesn't fit into 22 bits.
+    pub fn from_parts(
+        timestamp: SystemTime,
+        node_no: Option<NodeNo>,
+        counter: u32,
+    ) -> Option<Self> {
+        if !(1..=0x3f_ffff).contains(&counter) {
+            return None;
+        }
+
+        Some(Self::from_layout(TraceIdLayout {
+            timestamp: timestamp.into(),
+            node_no,
+            bottom: counter.into(),
+        }))
+    }
+
+    /// Returns the timestamp in secs truncated to 25 bits.
+    pub fn truncated_timestamp(self) -> u32 {
+        *self.to_layout().timestamp
+    }
+
+    /// Returns the node that generated this trace id, if known.
+    pub fn node_no(self) -> Option<NodeNo> {
+        self.to_layout().node_no
+    }
+
+    /// Returns the counter part, unique per node within a second.
+    pub fn counter(self) -> u32 {
+        *self.to_layout().bottom
+    }
+
     pub(crate) fn from_layout(layout: TraceIdLayout) -> Self {
         let raw = (u64::from(*layout.timestamp)) << 38
             | u64::from(layout.node_no.map_or(0, |n| n.into_bits())) << 22
@@ -52,6 +98,32 @@ impl From<TraceId> for u64 {
     }
 }
 
+impl FromStr for TraceId {
+    type Err = ParseTraceIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.parse::<u64>().map_err(|_| ParseTraceIdError::Invalid)?;
+
+        if raw & (1 << 63) != 0 {
+            return Err(ParseTraceIdError::Invalid);
+        }
+
+        TraceId::try_from(raw).map_err(|_| ParseTraceIdError::Zero)
+    }
+}
+
+/// An error returned by `TraceId::from_str()`.
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+#[non_exhaustive]
+pub enum ParseTraceIdError {
+    /// Not a 63-bit decimal number.
+    #[display(fmt = "invalid trace id")]
+    Invalid,
+    /// Trace ids cannot be zero.
+    #[display(fmt = "trace id cannot be zero")]
+    Zero,
+}
+
 // === TraceIdLayout ===
 
 #[derive(Clone, Copy)]
@@ -131,3 +203,33 @@ fn layout_roundtrip() {
     check(5197794958151101819);
     check(8446744073709551614);
 }
+
+#[test]
+fn from_parts() {
+    let timestamp = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    let node_no = NodeNo::from_bits(42);
+
+    let trace_id = TraceId::from_parts(timestamp, node_no, 5).unwrap();
+    assert_eq!(trace_id.truncated_timestamp(), 1_700_000_000 & 0x1ff_ffff);
+    assert_eq!(trace_id.node_no(), node_no);
+    assert_eq!(trace_id.counter(), 5);
+
+    assert!(TraceId::from_parts(timestamp, node_no, 0).is_none());
+    assert!(TraceId::from_parts(timestamp, node_no, 0x40_0000).is_none());
+    assert!(TraceId::from_parts(timestamp, None, 0x3f_ffff).is_some());
+}
+
+#[test]
+fn parse() {
+    let trace_id = TraceId::try_from(75997165362483795).unwrap();
+    assert_eq!(trace_id.to_string().parse::<TraceId>(), Ok(trace_id));
+
+    assert_eq!("0".parse::<TraceId>(), Err(ParseTraceIdError::Zero));
+    assert_eq!("".parse::<TraceId>(), Err(ParseTraceIdError::Invalid));
+    assert_eq!("-1".parse::<TraceId>(), Err(ParseTraceIdError::Invalid));
+    assert_eq!("0x1".parse::<TraceId>(), Err(ParseTraceIdError::Invalid));
+    assert_eq!(
+        (1u64 << 63).to_string().parse::<TraceId>(),
+        Err(ParseTraceIdError::Invalid)
+    );
+}
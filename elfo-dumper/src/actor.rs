@@ -1,6 +1,7 @@
 use std::{iter, panic, sync::Arc, time::Duration};
 
-use eyre::{Result, WrapErr};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use eyre::{eyre, Result, WrapErr};
 use fxhash::FxHashSet;
 use parking_lot::Mutex;
 use tokio::task;
@@ -20,12 +21,14 @@ use elfo_core::{
 use elfo_utils::ward;
 
 use crate::{
-    config::Config,
+    config::{Config, Encryption},
     dump_storage::{Drain, DumpRegistry, DumpStorage},
+    encryption::Encryptor,
     file_registry::{FileHandle, FileRegistry},
     reporter::{Report, Reporter},
     rule_set::RuleSet,
     serializer::Serializer,
+    KeyProvider,
 };
 
 #[message]
@@ -41,6 +44,7 @@ struct Dumper {
     ctx: Context<Config, String>,
     dump_registry: Arc<DumpRegistry>,
     file_registry: Arc<FileRegistry>,
+    key_provider: Option<KeyProvider>,
     interval: Interval<DumpingTick>,
 
     // Used only by the manager actor.
@@ -57,6 +61,7 @@ impl Dumper {
         mut ctx: Context<Config, String>,
         dump_storage: Arc<Mutex<DumpStorage>>,
         file_registry: Arc<FileRegistry>,
+        key_provider: Option<KeyProvider>,
     ) -> Self {
         // TODO: avoid leaking here.
         let class = Box::leak(ctx.key().clone().into_boxed_str());
@@ -74,6 +79,7 @@ impl Dumper {
         Self {
             dump_registry,
             file_registry,
+            key_provider,
             interval: ctx.attach(Interval::new(DumpingTick)),
             manager,
             ctx,
@@ -93,6 +99,7 @@ impl Dumper {
         let mut need_to_terminate = false;
 
         rule_set.configure(&self.ctx.config().rules);
+        serializer.set_encryptor(self.make_encryptor()?);
 
         self.ctx
             .attach(Signal::new(SignalKind::UnixHangup, ReopenDumpFile));
@@ -114,6 +121,7 @@ impl Dumper {
 
                     rule_set.configure(&config.rules);
                     reporter.configure(config.log_cooldown);
+                    serializer.set_encryptor(self.make_encryptor()?);
 
                     if let Some(m) = &self.manager {
                         m.dump_storage.lock().configure(config.registry_capacity);
@@ -190,6 +198,14 @@ impl Dumper {
         Ok(())
     }
 
+    // Errors aren't ignored in order to never write dumps in plaintext.
+    fn make_encryptor(&self) -> Result<Option<Encryptor>> {
+        let encryption = ward!(&self.ctx.config().encryption, return Ok(None));
+        let key = resolve_key(encryption, self.key_provider.as_ref())
+            .wrap_err("cannot resolve the encryption key")?;
+        Encryptor::new(&key).map(Some)
+    }
+
     fn spawn_dumpers_if_needed(&mut self) {
         let m = ward!(self.manager.as_mut());
 
@@ -242,11 +258,27 @@ fn write_dumps(
     Ok(())
 }
 
+fn resolve_key(encryption: &Encryption, key_provider: Option<&KeyProvider>) -> Result<Vec<u8>> {
+    match (&encryption.key, &encryption.key_id) {
+        (Some(key), None) => STANDARD.decode(key).wrap_err("invalid base64"),
+        (None, Some(key_id)) => {
+            let provider = key_provider.ok_or_else(|| {
+                eyre!("`key_id` is specified, but the dumper is created without a key provider")
+            })?;
+            provider(key_id).map_err(|err| eyre!(err))
+        }
+        _ => Err(eyre!("exactly one of `key` and `key_id` must be specified")),
+    }
+}
+
 fn collect_classes(map: &FxHashSet<&'static str>) -> Vec<String> {
     map.iter().map(|s| s.to_string()).collect()
 }
 
-pub(crate) fn new(dump_storage: Arc<Mutex<DumpStorage>>) -> Blueprint {
+pub(crate) fn new(
+    dump_storage: Arc<Mutex<DumpStorage>>,
+    key_provider: Option<KeyProvider>,
+) -> Blueprint {
     let storage_1 = dump_storage.clone();
     let file_registry = Arc::new(FileRegistry::default());
 
@@ -262,5 +294,13 @@ pub(crate) fn new(dump_storage: Arc<Mutex<DumpStorage>>) -> Blueprint {
                 _ => Outcome::Default,
             })
         }))
-        .exec(move |ctx| Dumper::new(ctx, storage_1.clone(), file_registry.clone()).main())
+        .exec(move |ctx| {
+            Dumper::new(
+                ctx,
+                storage_1.clone(),
+                file_registry.clone(),
+                key_provider.clone(),
+            )
+            .main()
+        })
 }
@@ -67,6 +67,37 @@ pub struct Config {
     /// ```
     #[serde(default)]
     pub rules: Vec<Rule>,
+    /// If specified, messages are encrypted using AES-256-GCM before writing.
+    /// Other fields are written in plaintext.
+    ///
+    /// Use `elfo_dumper::Decryptor` to read such dumps.
+    #[serde(default)]
+    pub encryption: Option<Encryption>,
+}
+
+/// Defines how to get a key to encrypt messages.
+/// Exactly one of `key` and `key_id` must be specified.
+///
+/// It's exported only for documentation purposes and cannot be created or
+/// received outside the dumper.
+///
+/// # Examples
+/// ```toml
+/// [system.dumpers]
+/// encryption.key = "base64-encoded 256-bit key"
+/// ```
+/// or, if a key provider is passed to `elfo_dumper::new_with_key_provider()`
+/// ```toml
+/// [system.dumpers]
+/// encryption.key_id = "dumps/prod"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Encryption {
+    /// A base64-encoded 256-bit key.
+    pub key: Option<String>,
+    /// An identifier of a key passed to the key provider, e.g. to fetch it from
+    /// KMS.
+    pub key_id: Option<String>,
 }
 
 /// Defines a rule to override some properties.
@@ -0,0 +1,179 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use derive_more::Display;
+
+const NONCE_SIZE: usize = 12;
+
+/// A field containing an encrypted message instead of `m`.
+pub(crate) const ENCRYPTED_FIELD: &str = "me";
+
+// === Encryptor ===
+
+/// Encrypts messages using AES-256-GCM.
+///
+/// Nonces are random 96-bit values chosen per record, because encryptors are
+/// recreated on every start and reconfiguration with the same key, so any
+/// per-encryptor state (e.g. a counter) would be repeated under the key.
+pub(crate) struct Encryptor {
+    cipher: Aes256Gcm,
+}
+
+impl Encryptor {
+    pub(crate) fn new(key: &[u8]) -> eyre::Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| eyre::eyre!("the encryption key must be 256 bits long"))?;
+        Ok(Self { cipher })
+    }
+
+    /// Returns `base64(nonce || ciphertext)`.
+    pub(crate) fn encrypt(&mut self, plaintext: &[u8]) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        debug_assert_eq!(nonce.len(), NONCE_SIZE);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .expect("buffer is unbounded");
+
+        let mut out = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        STANDARD.encode(out)
+    }
+}
+
+// === Decryptor ===
+
+/// Decrypts dumps written with enabled encryption.
+///
+/// Only messages are encrypted, other fields are written in plaintext to
+/// allow filtering without a key.
+///
+/// # Example
+/// ```ignore
+/// let decryptor = Decryptor::new(&key)?;
+///
+/// for line in reader.lines() {
+///     let dump = decryptor.decrypt_line(&line?)?;
+///     // `dump` is the same as it would be without encryption.
+/// }
+/// ```
+pub struct Decryptor {
+    cipher: Aes256Gcm,
+}
+
+/// An error returned by [`Decryptor`].
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum DecryptError {
+    /// The key is not a 256-bit key.
+    #[display(fmt = "the key must be 256 bits long")]
+    InvalidKey,
+    /// The line contains a malformed encrypted message.
+    #[display(fmt = "the encrypted message is malformed")]
+    Malformed,
+    /// Authentication failed, the key is wrong or the message is corrupted.
+    #[display(fmt = "the key is wrong or the message is corrupted")]
+    Unauthenticated,
+}
+
+impl std::error::Error for DecryptError {}
+
+impl Decryptor {
+    /// Creates a decryptor using the provided 256-bit key.
+    pub fn new(key: &[u8]) -> Result<Self, DecryptError> {
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| DecryptError::InvalidKey)?;
+        Ok(Self { cipher })
+    }
+
+    /// Decrypts a line of a dump file.
+    /// Lines without encrypted messages are returned as is.
+    pub fn decrypt_line(&self, line: &str) -> Result<String, DecryptError> {
+        // Other fields cannot contain unescaped quotes, so it's enough.
+        let pattern = format!(",\"{ENCRYPTED_FIELD}\":\"");
+        let Some(start) = line.find(&pattern) else {
+            return Ok(line.to_string());
+        };
+
+        let value_start = start + pattern.len();
+        let value_len = line[value_start..]
+            .find('"')
+            .ok_or(DecryptError::Malformed)?;
+        let value = &line[value_start..value_start + value_len];
+
+        let data = STANDARD
+            .decode(value)
+            .map_err(|_| DecryptError::Malformed)?;
+        if data.len() < NONCE_SIZE {
+            return Err(DecryptError::Malformed);
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| DecryptError::Unauthenticated)?;
+        let plaintext = std::str::from_utf8(&plaintext).map_err(|_| DecryptError::Malformed)?;
+
+        let rest = &line[value_start + value_len + 1..];
+        Ok(format!("{},\"m\":{plaintext}{rest}", &line[..start]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let key = [42; 32];
+        let mut encryptor = Encryptor::new(&key).unwrap();
+        let decryptor = Decryptor::new(&key).unwrap();
+
+        let first = encryptor.encrypt(br#"{"body":"X"}"#);
+        let second = encryptor.encrypt(br#"{"body":"X"}"#);
+        assert_ne!(first, second);
+
+        let line = format!(r#"{{"ts":2,"me":"{first}","c":5}}"#);
+        assert_eq!(
+            decryptor.decrypt_line(&line).unwrap(),
+            r#"{"ts":2,"m":{"body":"X"},"c":5}"#
+        );
+
+        // Plaintext lines are returned as is.
+        let line = r#"{"ts":2,"m":{"body":"X"}}"#;
+        assert_eq!(decryptor.decrypt_line(line).unwrap(), line);
+
+        // Wrong keys are detected.
+        let line = format!(r#"{{"ts":2,"me":"{first}"}}"#);
+        let decryptor = Decryptor::new(&[0; 32]).unwrap();
+        assert!(matches!(
+            decryptor.decrypt_line(&line),
+            Err(DecryptError::Unauthenticated)
+        ));
+
+        assert!(matches!(
+            Decryptor::new(&[0; 16]),
+            Err(DecryptError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn unique_nonces_under_the_same_key() {
+        let key = [42; 32];
+        let mut nonces = std::collections::HashSet::new();
+
+        // Encryptors are recreated on every reconfiguration with the same key.
+        for _ in 0..100 {
+            let mut encryptor = Encryptor::new(&key).unwrap();
+
+            for _ in 0..100 {
+                let data = STANDARD.decode(encryptor.encrypt(b"{}")).unwrap();
+                assert!(nonces.insert(data[..NONCE_SIZE].to_vec()));
+            }
+        }
+    }
+}
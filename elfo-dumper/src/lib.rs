@@ -5,6 +5,12 @@
 //! For more details about dumping see [The Actoromicon](https://actoromicon.rs/ch05-03-dumping.html).
 //!
 //! Configuration can be found in [`config::Config`].
+//!
+//! Messages can be encrypted at rest, see [`config::Encryption`] and
+//! [`Decryptor`].
+//!
+//! Dumps written by several nodes can be merged into a single timeline per
+//! trace, see [`Merger`].
 #![warn(rust_2018_idioms, unreachable_pub, missing_docs)]
 
 use std::sync::Arc;
@@ -19,9 +25,16 @@ use elfo_core::{
 
 use self::dump_storage::DumpStorage;
 
+pub use self::{
+    encryption::{DecryptError, Decryptor},
+    merge::{MergeError, MergedDump, Merger, Timeline},
+};
+
 mod actor;
 mod dump_storage;
+mod encryption;
 mod file_registry;
+mod merge;
 mod recorder;
 mod reporter;
 mod rule_set;
@@ -32,10 +45,27 @@ mod config;
 #[cfg(docsrs)]
 pub mod config;
 
+/// Fetches a key to encrypt messages by its identifier.
+pub(crate) type KeyProvider = Arc<dyn Fn(&str) -> Result<Vec<u8>, String> + Send + Sync>;
+
 /// Installs a global dump recorder and returns a group to handle dumps.
 pub fn new() -> Blueprint {
+    install(None)
+}
+
+/// The same as [`new()`], but keys to encrypt messages are fetched by the
+/// provided callback using `encryption.key_id` from the config, e.g. from KMS.
+///
+/// The callback is called on every start and reconfiguration of dumpers.
+pub fn new_with_key_provider(
+    provider: impl Fn(&str) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+) -> Blueprint {
+    install(Some(Arc::new(provider)))
+}
+
+fn install(key_provider: Option<KeyProvider>) -> Blueprint {
     let storage = Arc::new(Mutex::new(DumpStorage::new()));
-    let blueprint = actor::new(storage.clone());
+    let blueprint = actor::new(storage.clone(), key_provider);
 
     let is_ok = dumping::set_make_recorder(Box::new(move |class| {
         storage.lock().registry(class) as Arc<dyn Recorder>
@@ -0,0 +1,418 @@
+use std::{
+    collections::{hash_map::Entry, VecDeque},
+    io::{self, BufRead},
+};
+
+use derive_more::Display;
+use fxhash::FxHashMap;
+use serde::Deserialize;
+
+use elfo_core::{
+    dumping::Direction,
+    tracing::{MessageId, TraceId},
+    NodeNo,
+};
+
+// === Merger ===
+
+/// Merges dumps written by several nodes into a single timeline per trace.
+///
+/// Dumps are stitched by trace ids and ordered by timestamps corrected by
+/// offsets of nodes' clocks. Offsets are either provided explicitly, e.g.
+/// estimated by `elfo-network` and returned by its `GetNetworkStatus`, or
+/// inferred from messages dumped both by senders and receivers. Also, an
+/// incoming message is never placed before the outgoing one.
+///
+/// Encrypted dumps can be merged without decryption, see [`Decryptor`].
+///
+/// # Example
+/// ```ignore
+/// let mut merger = Merger::new();
+///
+/// for path in paths {
+///     merger.add_reader(BufReader::new(File::open(path)?))?;
+/// }
+///
+/// merger.infer_clock_offsets();
+///
+/// for timeline in merger.into_timelines() {
+///     for dump in &timeline.dumps {
+///         println!("{}", dump.line);
+///     }
+/// }
+/// ```
+///
+/// [`Decryptor`]: crate::Decryptor
+#[derive(Default)]
+pub struct Merger {
+    dumps: Vec<MergedDump>,
+    /// Offsets of nodes' clocks in nanoseconds, positive if ahead.
+    offsets: FxHashMap<Option<NodeNo>, i64>,
+}
+
+/// A dump line with the timestamp corrected by the clock offset of its node.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MergedDump {
+    /// The corrected timestamp in nanoseconds since Unix epoch.
+    pub timestamp: u64,
+    /// The timestamp written by the node in nanoseconds since Unix epoch.
+    pub original_timestamp: u64,
+    /// The node written the dump, `None` if unknown.
+    pub node_no: Option<NodeNo>,
+    /// The trace id of the dumped message.
+    pub trace_id: TraceId,
+    /// The message id of the dumped message, if dumped.
+    pub message_id: Option<MessageId>,
+    /// Whether the message was sent or received.
+    pub direction: Direction,
+    /// The original line.
+    pub line: String,
+}
+
+/// Dumps of the same trace ordered by corrected timestamps.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Timeline {
+    /// The trace id of all dumps.
+    pub trace_id: TraceId,
+    /// Dumps ordered by corrected timestamps.
+    pub dumps: Vec<MergedDump>,
+}
+
+/// An error returned by [`Merger`].
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum MergeError {
+    /// The line isn't a valid dump.
+    #[display(fmt = "the line isn't a valid dump")]
+    Malformed,
+    /// Reading failed.
+    #[display(fmt = "cannot read dumps: {_0}")]
+    Io(io::Error),
+}
+
+impl std::error::Error for MergeError {}
+
+#[derive(Deserialize)]
+struct RawDump {
+    ts: u64,
+    n: Option<u16>,
+    t: TraceId,
+    mi: Option<MessageId>,
+    d: RawDirection,
+}
+
+#[derive(Deserialize)]
+enum RawDirection {
+    In,
+    Out,
+}
+
+impl Merger {
+    /// Creates an empty merger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the offset of the node's clock relative to the reference clock in
+    /// seconds, positive if the node's clock is ahead.
+    ///
+    /// It's compatible with `clock_offset` estimated by `elfo-network` on the
+    /// reference node. Explicit offsets aren't changed by
+    /// [`Merger::infer_clock_offsets()`].
+    pub fn set_clock_offset(&mut self, node_no: NodeNo, offset: f64) {
+        self.offsets
+            .insert(Some(node_no), (offset * 1e9).round() as i64);
+    }
+
+    /// Adds a line of a dump file. Empty lines are skipped.
+    pub fn add_line(&mut self, line: &str) -> Result<(), MergeError> {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        let raw = serde_json::from_str::<RawDump>(line).map_err(|_| MergeError::Malformed)?;
+
+        self.dumps.push(MergedDump {
+            timestamp: raw.ts,
+            original_timestamp: raw.ts,
+            node_no: raw.n.and_then(NodeNo::from_bits),
+            trace_id: raw.t,
+            message_id: raw.mi,
+            direction: match raw.d {
+                RawDirection::In => Direction::In,
+                RawDirection::Out => Direction::Out,
+            },
+            line: line.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Adds all lines of a dump file.
+    pub fn add_reader(&mut self, reader: impl BufRead) -> Result<(), MergeError> {
+        for line in reader.lines() {
+            self.add_line(&line.map_err(MergeError::Io)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Infers offsets of nodes' clocks, which aren't set explicitly, using
+    /// messages dumped both by the sender and the receiver.
+    ///
+    /// If two nodes exchange messages in both directions, the offset is
+    /// estimated as a half of the difference between minimal delays in each
+    /// direction. Otherwise, it's corrected only if the receiver dumps
+    /// messages earlier than they are sent.
+    ///
+    /// Offsets are relative to nodes with explicit offsets or, if there are
+    /// no such nodes, to the node with the lowest number.
+    pub fn infer_clock_offsets(&mut self) {
+        // The earliest outgoing and incoming dumps per message and node.
+        let mut sent = FxHashMap::<MessageId, (Option<NodeNo>, u64)>::default();
+        let mut received = FxHashMap::<(MessageId, Option<NodeNo>), u64>::default();
+
+        for dump in &self.dumps {
+            let Some(message_id) = dump.message_id else {
+                continue;
+            };
+
+            let ts = dump.original_timestamp;
+
+            match dump.direction {
+                Direction::Out => match sent.entry(message_id) {
+                    Entry::Occupied(mut entry) if entry.get().1 > ts => {
+                        entry.insert((dump.node_no, ts));
+                    }
+                    Entry::Occupied(_) => {}
+                    Entry::Vacant(entry) => {
+                        entry.insert((dump.node_no, ts));
+                    }
+                },
+                Direction::In => {
+                    let earliest = received.entry((message_id, dump.node_no)).or_insert(ts);
+                    *earliest = (*earliest).min(ts);
+                }
+            }
+        }
+
+        // Minimal observed delays between nodes, including clock offsets.
+        let mut delays = FxHashMap::<(Option<NodeNo>, Option<NodeNo>), i64>::default();
+
+        for ((message_id, receiver), received_at) in received {
+            let Some(&(sender, sent_at)) = sent.get(&message_id) else {
+                continue;
+            };
+
+            if sender != receiver {
+                let delay = received_at as i64 - sent_at as i64;
+                let min = delays.entry((sender, receiver)).or_insert(delay);
+                *min = (*min).min(delay);
+            }
+        }
+
+        // Estimated `offset(to) - offset(from)` for adjacent nodes.
+        let mut edges = FxHashMap::<Option<NodeNo>, Vec<(Option<NodeNo>, i64)>>::default();
+
+        for (&(from, to), &delay) in &delays {
+            let diff = match delays.get(&(to, from)) {
+                Some(&back) => (delay - back) / 2,
+                None => delay.min(0),
+            };
+
+            edges.entry(from).or_default().push((to, diff));
+            edges.entry(to).or_default().push((from, -diff));
+        }
+
+        let mut nodes = self.dumps.iter().map(|d| d.node_no).collect::<Vec<_>>();
+        nodes.sort_unstable_by_key(|n| n.map(NodeNo::into_bits));
+        nodes.dedup();
+
+        // Explicit offsets go first to be references for their neighbours.
+        let (explicit, rest): (Vec<_>, Vec<_>) = nodes
+            .into_iter()
+            .partition(|n| self.offsets.contains_key(n));
+        let mut queue = VecDeque::from(explicit);
+
+        for node_no in rest {
+            queue.push_back(node_no);
+
+            while let Some(node_no) = queue.pop_front() {
+                let offset = *self.offsets.entry(node_no).or_insert(0);
+
+                for &(neighbour, diff) in edges.get(&node_no).into_iter().flatten() {
+                    if let Entry::Vacant(entry) = self.offsets.entry(neighbour) {
+                        entry.insert(offset + diff);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns timelines of all traces ordered by their first dumps.
+    pub fn into_timelines(self) -> Vec<Timeline> {
+        let mut dumps = self.dumps;
+
+        for dump in &mut dumps {
+            let offset = self.offsets.get(&dump.node_no).copied().unwrap_or(0);
+            dump.timestamp = (dump.original_timestamp as i64 - offset).max(0) as u64;
+        }
+
+        // Incoming messages cannot be received before they're sent.
+        let mut sent = FxHashMap::<MessageId, u64>::default();
+
+        for dump in &dumps {
+            if let (Some(message_id), Direction::Out) = (dump.message_id, dump.direction) {
+                let earliest = sent.entry(message_id).or_insert(dump.timestamp);
+                *earliest = (*earliest).min(dump.timestamp);
+            }
+        }
+
+        for dump in &mut dumps {
+            if let (Some(message_id), Direction::In) = (dump.message_id, dump.direction) {
+                if let Some(&sent_at) = sent.get(&message_id) {
+                    dump.timestamp = dump.timestamp.max(sent_at);
+                }
+            }
+        }
+
+        let mut by_trace = FxHashMap::<TraceId, Vec<MergedDump>>::default();
+        for dump in dumps {
+            by_trace.entry(dump.trace_id).or_default().push(dump);
+        }
+
+        let mut timelines = by_trace
+            .into_iter()
+            .map(|(trace_id, mut dumps)| {
+                // Outgoing dumps go first on ties. The sort is stable, so
+                // dumps of the same node keep the order of lines otherwise.
+                dumps.sort_by_key(|d| (d.timestamp, d.direction == Direction::In));
+                Timeline { trace_id, dumps }
+            })
+            .collect::<Vec<_>>();
+
+        timelines.sort_by_key(|t| (t.dumps[0].timestamp, u64::from(t.trace_id)));
+        timelines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(ts: u64, node_no: u16, trace_id: u64, message_id: u64, d: &str) -> String {
+        format!(
+            r#"{{"ts":{ts},"g":"g","n":{node_no},"s":1,"t":{trace_id},"mi":{message_id},"th":0,"d":"{d}","cl":"","mn":"M","mp":"p","mk":"Regular","m":null}}"#
+        )
+    }
+
+    fn order(timeline: &Timeline) -> Vec<(u64, Option<u16>)> {
+        timeline
+            .dumps
+            .iter()
+            .map(|d| (d.timestamp, d.node_no.map(NodeNo::into_bits)))
+            .collect()
+    }
+
+    fn node(no: u16) -> NodeNo {
+        NodeNo::from_bits(no).unwrap()
+    }
+
+    #[test]
+    fn it_merges_by_trace_id() {
+        let mut merger = Merger::new();
+        let lines = [
+            line(100, 1, 7, 1, "Out"),
+            line(50, 2, 8, 5, "Out"),
+            line(110, 2, 7, 1, "In"),
+            line(120, 2, 7, 2, "Out"),
+            line(130, 1, 7, 2, "In"),
+            String::new(),
+        ];
+        merger.add_reader(lines.join("\n").as_bytes()).unwrap();
+
+        let timelines = merger.into_timelines();
+        assert_eq!(timelines.len(), 2);
+        assert_eq!(u64::from(timelines[0].trace_id), 8);
+        assert_eq!(u64::from(timelines[1].trace_id), 7);
+        assert_eq!(
+            order(&timelines[1]),
+            [
+                (100, Some(1)),
+                (110, Some(2)),
+                (120, Some(2)),
+                (130, Some(1))
+            ]
+        );
+
+        assert!(matches!(
+            Merger::new().add_line(r#"{"ts":1}"#),
+            Err(MergeError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn it_corrects_timestamps() {
+        // The clock of the second node is ahead by 1000ns, the latency is 10ns.
+        let lines = [
+            line(100, 1, 7, 1, "Out"),
+            line(1110, 2, 7, 1, "In"),
+            line(1120, 2, 7, 2, "Out"),
+            line(130, 1, 7, 2, "In"),
+        ];
+
+        let expected = [
+            (100, Some(1)),
+            (110, Some(2)),
+            (120, Some(2)),
+            (130, Some(1)),
+        ];
+
+        // Explicit offsets.
+        let mut merger = Merger::new();
+        lines.iter().for_each(|l| merger.add_line(l).unwrap());
+        merger.set_clock_offset(node(2), 1e-6);
+        assert_eq!(order(&merger.into_timelines()[0]), expected);
+
+        // Inferred offsets.
+        let mut merger = Merger::new();
+        lines.iter().for_each(|l| merger.add_line(l).unwrap());
+        merger.infer_clock_offsets();
+        assert_eq!(order(&merger.into_timelines()[0]), expected);
+
+        // Inferred relative to explicit ones.
+        let mut merger = Merger::new();
+        lines.iter().for_each(|l| merger.add_line(l).unwrap());
+        merger.set_clock_offset(node(2), 0.);
+        merger.infer_clock_offsets();
+        let shifted = expected.map(|(ts, n)| (ts + 1000, n));
+        assert_eq!(order(&merger.into_timelines()[0]), shifted);
+    }
+
+    #[test]
+    fn it_keeps_causality() {
+        // The clock of the second node is behind, only one direction is dumped.
+        let lines = [line(100, 1, 7, 1, "Out"), line(40, 2, 7, 1, "In")];
+
+        // Without inference, the incoming message is moved after the outgoing one.
+        let mut merger = Merger::new();
+        lines.iter().for_each(|l| merger.add_line(l).unwrap());
+        assert_eq!(
+            order(&merger.into_timelines()[0]),
+            [(100, Some(1)), (100, Some(2))]
+        );
+
+        let mut merger = Merger::new();
+        lines.iter().for_each(|l| merger.add_line(l).unwrap());
+        merger.add_line(&line(50, 2, 7, 3, "Out")).unwrap();
+        merger.infer_clock_offsets();
+        assert_eq!(
+            order(&merger.into_timelines()[0]),
+            [(100, Some(1)), (100, Some(2)), (110, Some(2))]
+        );
+    }
+}
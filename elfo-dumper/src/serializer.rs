@@ -9,7 +9,12 @@ use elfo_core::{
 };
 use elfo_utils::unlikely;
 
-use crate::{config::OnOverflow, reporter::Report, rule_set::DumpParams};
+use crate::{
+    config::OnOverflow,
+    encryption::{Encryptor, ENCRYPTED_FIELD},
+    reporter::Report,
+    rule_set::DumpParams,
+};
 
 // === Serializer ===
 
@@ -24,6 +29,8 @@ pub(crate) struct Serializer {
     output: Vec<u8>,
     need_to_clear: bool,
     report: Report,
+    /// If set, messages are written encrypted.
+    encryptor: Option<Encryptor>,
 }
 
 impl Serializer {
@@ -46,9 +53,14 @@ impl Serializer {
             output: Vec::with_capacity(initial_chunk_capacity),
             need_to_clear: false,
             report: Report::default(),
+            encryptor: None,
         }
     }
 
+    pub(crate) fn set_encryptor(&mut self, encryptor: Option<Encryptor>) {
+        self.encryptor = encryptor;
+    }
+
     pub(crate) fn append(&mut self, dump: &Dump, params: &DumpParams) -> Option<&[u8]> {
         self.clear_if_needed();
 
@@ -77,12 +89,17 @@ impl Serializer {
     /// * `Ok(false)` — skipped.
     /// * `Err(err)` — failed.
     fn do_append(&mut self, dump: &Dump, params: &DumpParams) -> Result<bool, serde_json::Error> {
+        if self.encryptor.is_some() {
+            return self.do_append_encrypted(dump, params);
+        }
+
         let mut compact_dump = CompactDump {
             dump,
             class: self.class,
             node_no: self.node_no,
             message_name: dump.message_name.to_str(&mut self.name_buffer),
             message: None,
+            encrypted_message: None,
         };
 
         let prev_len = self.output.len();
@@ -145,10 +162,54 @@ impl Serializer {
                 }
                 true
             })
-            .map_err(|err| {
-                self.output.truncate(prev_len);
-                err
+            .inspect_err(|_| self.output.truncate(prev_len))
+    }
+
+    /// The same as `do_append()`, but the message is encrypted.
+    /// The plaintext is exactly what would be written as the `m` field.
+    fn do_append_encrypted(
+        &mut self,
+        dump: &Dump,
+        params: &DumpParams,
+    ) -> Result<bool, serde_json::Error> {
+        self.message_buffer.clear();
+
+        let mut wr = LimitedWrite::new(&mut self.message_buffer, params.max_size);
+        let limit_reached = match serde_json::to_writer(&mut wr, &*dump.message) {
+            Ok(()) => false,
+            Err(err) if !wr.limit_reached => return Err(err),
+            Err(_) if params.on_overflow == OnOverflow::Skip => {
+                self.report.add_overflow(dump, false, params);
+                return Ok(false);
+            }
+            Err(_) => {
+                self.message_buffer.extend_from_slice(b" TRUNCATED");
+                let message = String::from_utf8_lossy(&self.message_buffer).into_owned();
+                self.message_buffer.clear();
+                serde_json::to_writer(&mut self.message_buffer, &message)?;
+                true
+            }
+        };
+
+        let encryptor = self.encryptor.as_mut().expect("checked before");
+        let compact_dump = CompactDump {
+            dump,
+            class: self.class,
+            node_no: self.node_no,
+            message_name: dump.message_name.to_str(&mut self.name_buffer),
+            message: None,
+            encrypted_message: Some(encryptor.encrypt(&self.message_buffer)),
+        };
+
+        let prev_len = self.output.len();
+        serde_json::to_writer(&mut self.output, &compact_dump)
+            .map(|_| {
+                if limit_reached {
+                    self.report.add_overflow(dump, true, params);
+                }
+                true
             })
+            .inspect_err(|_| self.output.truncate(prev_len))
     }
 
     pub(crate) fn take(&mut self) -> (Option<&[u8]>, Report) {
@@ -182,6 +243,7 @@ struct CompactDump<'a> {
     node_no: Option<NodeNo>,
     message_name: &'a str,
     message: Option<Cow<'a, str>>,
+    encrypted_message: Option<String>,
 }
 
 impl<'a> serde::Serialize for CompactDump<'a> {
@@ -189,6 +251,8 @@ impl<'a> serde::Serialize for CompactDump<'a> {
         let field_count = 11
             + !self.dump.meta.key.is_empty() as usize // "k"
             + self.node_no.is_some() as usize // "n"
+            + self.dump.message_id.is_some() as usize // "mi"
+            + self.dump.parent_id.is_some() as usize // "pmi"
             + !matches!(self.dump.message_kind, MessageKind::Regular) as usize; // "c"
 
         let mut s = serializer.serialize_struct("Dump", field_count)?;
@@ -207,6 +271,15 @@ impl<'a> serde::Serialize for CompactDump<'a> {
 
         s.serialize_field("s", &self.dump.sequence_no)?;
         s.serialize_field("t", &self.dump.trace_id)?;
+
+        if let Some(message_id) = self.dump.message_id {
+            s.serialize_field("mi", &message_id)?;
+        }
+
+        if let Some(parent_id) = self.dump.parent_id {
+            s.serialize_field("pmi", &parent_id)?;
+        }
+
         s.serialize_field("th", &self.dump.thread_id)?;
         s.serialize_field("d", &self.dump.direction)?;
         s.serialize_field("cl", &self.class)?;
@@ -221,7 +294,9 @@ impl<'a> serde::Serialize for CompactDump<'a> {
 
         s.serialize_field("mk", message_kind)?;
 
-        if let Some(message) = &self.message {
+        if let Some(encrypted) = &self.encrypted_message {
+            s.serialize_field(ENCRYPTED_FIELD, encrypted)?;
+        } else if let Some(message) = &self.message {
             s.serialize_field("m", message)?;
         } else {
             s.serialize_field("m", &*self.dump.message)?;
@@ -275,7 +350,12 @@ mod tests {
     use fxhash::FxHashMap;
     use tracing::{level_filters::LevelFilter, Level};
 
-    use elfo_core::{dumping::Timestamp, scope::Scope, tracing::TraceId, ActorMeta, Addr};
+    use elfo_core::{
+        dumping::Timestamp,
+        scope::Scope,
+        tracing::{MessageId, TraceId},
+        ActorMeta, Addr,
+    };
 
     use super::*;
     use crate::reporter::OverflowDumpInfo;
@@ -452,6 +532,58 @@ mod tests {
         assert_eq!(report.failed.len(), 0);
     }
 
+    #[test]
+    fn encrypted() {
+        elfo_core::_priv::node::set_node_no(65535);
+
+        let key = [42; 32];
+        let decryptor = crate::Decryptor::new(&key).unwrap();
+        let mut serializer = Serializer::with_chunk_size(0, "some");
+        serializer.set_encryptor(Some(Encryptor::new(&key).unwrap()));
+
+        let decrypt = |chunk: Option<&[u8]>| {
+            let chunk = std::str::from_utf8(chunk.unwrap()).unwrap();
+            assert!(!chunk.contains("XXXX"));
+            decryptor.decrypt_line(chunk.trim_end()).unwrap()
+        };
+
+        let chunk = serializer.append(&dump(42, 4, true), &DumpParams::default());
+        assert_eq!(decrypt(chunk), line(42, 4));
+
+        let params = DumpParams {
+            max_size: 10,
+            on_overflow: OnOverflow::Truncate,
+            ..DumpParams::default()
+        };
+        let chunk = serializer.append(&dump(42, 4, true), &params);
+        let expected = r#"{"ts":2,"g":"group","k":"key","n":65535,"s":42,"t":1,"th":0,"d":"Out","cl":"some","mn":"Some","mp":"some","mk":"Regular","m":"{\"body\":\" TRUNCATED"}"#;
+        assert_eq!(decrypt(chunk), expected);
+
+        let params = DumpParams {
+            max_size: 10,
+            ..DumpParams::default()
+        };
+        assert!(serializer.append(&dump(42, 4, true), &params).is_none());
+    }
+
+    #[test]
+    fn message_ids() {
+        elfo_core::_priv::node::set_node_no(65535);
+
+        let mut serializer = Serializer::new("some");
+        let mut sample = dump(42, 4, true);
+        sample.message_id = Some(MessageId::try_from(10).unwrap());
+        sample.parent_id = Some(MessageId::try_from(5).unwrap());
+
+        serializer.append(&sample, &DumpParams::default());
+        let (chunk, _) = serializer.take();
+        let expected = line(42, 4).replace(r#""t":1,"#, r#""t":1,"mi":10,"pmi":5,"#);
+        assert_eq!(
+            std::str::from_utf8(chunk.unwrap()).unwrap(),
+            expected + "\n"
+        );
+    }
+
     #[test]
     fn take() {
         elfo_core::_priv::node::set_node_no(65535);
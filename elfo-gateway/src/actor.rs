@@ -0,0 +1,296 @@
+use std::{sync::Arc, time::Duration};
+
+use hyper::{
+    body::HttpBody,
+    header::{HeaderValue, CONTENT_TYPE},
+    Body, Method, Request, Response, StatusCode,
+};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use elfo_core::{
+    _priv::AnyMessage,
+    errors::RequestError,
+    message,
+    messages::ConfigUpdated,
+    msg,
+    registry::{self, MessageInfo},
+    scope,
+    tracing::TraceId,
+    ActorGroup, Blueprint, Context, Message, MoveOwnership,
+};
+
+use crate::config::Config;
+
+const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Settings shared with the server, updated on config updates.
+#[derive(Default)]
+struct Settings {
+    messages: Vec<MessageInfo>,
+    timeout: Duration,
+    max_body_size: usize,
+}
+
+#[message]
+struct ServerFailed(MoveOwnership<hyper::Error>);
+
+pub(crate) fn new() -> Blueprint {
+    ActorGroup::new().config::<Config>().exec(exec)
+}
+
+async fn exec(mut ctx: Context<Config>) {
+    let settings = Arc::new(RwLock::new(Settings::default()));
+    configure(&ctx, &settings);
+
+    let mut address = ctx.config().address;
+    let mut server = start_server(&ctx, settings.clone());
+
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            ConfigUpdated => {
+                configure(&ctx, &settings);
+
+                if ctx.config().address != address {
+                    info!("address changed, rerun the server");
+                    server.abort();
+                    address = ctx.config().address;
+                    server = start_server(&ctx, settings.clone());
+                }
+            }
+            ServerFailed(error) => {
+                error!(error = %&error.take().unwrap(), "server failed");
+                panic!("server failed");
+            }
+        });
+    }
+
+    server.abort();
+}
+
+fn configure(ctx: &Context<Config>, settings: &RwLock<Settings>) {
+    let config = ctx.config();
+    let messages = config
+        .messages
+        .iter()
+        .filter_map(|m| {
+            let info = registry::lookup(&m.protocol, &m.name);
+            if info.is_none() {
+                warn!(protocol = %m.protocol, name = %m.name, "unknown message, not exposed");
+            }
+            info
+        })
+        .collect();
+
+    *settings.write() = Settings {
+        messages,
+        timeout: config.timeout,
+        max_body_size: config.max_body_size,
+    };
+}
+
+async fn handle(ctx: &Context, settings: &RwLock<Settings>, req: Request<Body>) -> Response<Body> {
+    let path = req.uri().path().to_owned();
+
+    // Introspection of exposed messages.
+    if path == "/messages" {
+        if req.method() != Method::GET {
+            return respond(StatusCode::METHOD_NOT_ALLOWED, "expected GET".into());
+        }
+
+        let output = serde_json::to_string(&settings.read().messages)
+            .expect("cannot serialize exposed messages");
+        return respond_json(StatusCode::OK, output);
+    }
+
+    let Some((protocol, name)) = path
+        .strip_prefix("/messages/")
+        .and_then(|path| path.split_once('/'))
+    else {
+        return respond(StatusCode::NOT_FOUND, "unknown path".into());
+    };
+
+    if req.method() != Method::POST {
+        return respond(StatusCode::METHOD_NOT_ALLOWED, "expected POST".into());
+    }
+
+    let (is_request, timeout, max_body_size) = {
+        let settings = settings.read();
+        let info = settings
+            .messages
+            .iter()
+            .find(|info| info.protocol == protocol && info.name == name);
+
+        let Some(info) = info else {
+            return respond(StatusCode::NOT_FOUND, "the message isn't exposed".into());
+        };
+
+        (info.is_request, settings.timeout, settings.max_body_size)
+    };
+
+    let body = match read_body(req.into_body(), max_body_size).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    // An empty body is allowed for unit messages.
+    let body = match body.is_empty() {
+        true => Value::Null,
+        false => match serde_json::from_slice(&body) {
+            Ok(body) => body,
+            Err(err) => return respond(StatusCode::BAD_REQUEST, err.to_string()),
+        },
+    };
+
+    // `AnyMessage` is deserialized from the `(protocol, name, body)` tuple.
+    // Names are borrowed while deserializing, so `Value` cannot be used here.
+    let tuple = json!([protocol, name, body]).to_string();
+    let message = match serde_json::from_str::<AnyMessage>(&tuple) {
+        Ok(message) => message,
+        Err(err) => return respond(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    if !is_request {
+        return match tokio::time::timeout(timeout, ctx.send(message)).await {
+            Ok(Ok(())) => respond(StatusCode::ACCEPTED, String::new()),
+            Ok(Err(err)) => respond(StatusCode::BAD_GATEWAY, err.to_string()),
+            Err(_) => respond(StatusCode::GATEWAY_TIMEOUT, "sending timed out".into()),
+        };
+    }
+
+    let response = tokio::time::timeout(timeout, ctx.request(message).resolve()).await;
+
+    match response {
+        Ok(Ok(response)) => match serde_json::to_string(&*response._erase()) {
+            Ok(output) => respond_json(StatusCode::OK, output),
+            Err(err) => respond(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+        },
+        Ok(Err(RequestError::Ignored)) => {
+            respond(StatusCode::NO_CONTENT, "the request is ignored".into())
+        }
+        Ok(Err(err)) if err.send_error().is_some_and(|err| err.is_no_route()) => {
+            respond(StatusCode::BAD_GATEWAY, "no recipients".into())
+        }
+        Ok(Err(err)) => respond(StatusCode::BAD_GATEWAY, err.to_string()),
+        Err(_) => {
+            debug!(?timeout, "request timed out");
+            respond(StatusCode::GATEWAY_TIMEOUT, "request timed out".into())
+        }
+    }
+}
+
+/// Reads the body, but no more than `limit` bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, Response<Body>> {
+    let too_large = || {
+        let message = format!("the body is larger than {limit} bytes");
+        respond(StatusCode::PAYLOAD_TOO_LARGE, message)
+    };
+
+    // Reject early if `content-length` is known.
+    if body.size_hint().lower() > limit as u64 {
+        return Err(too_large());
+    }
+
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| respond(StatusCode::BAD_REQUEST, err.to_string()))?;
+
+        if buffer.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+
+        buffer.extend_from_slice(&chunk);
+    }
+
+    Ok(buffer)
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+fn respond_json(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = respond(status, body);
+    let content_type = HeaderValue::from_static("application/json");
+    response.headers_mut().insert(CONTENT_TYPE, content_type);
+    response
+}
+
+/// Uses the client's trace id if provided, otherwise generates a new one.
+fn extract_trace_id(req: &Request<Body>) -> Result<TraceId, String> {
+    let Some(value) = req.headers().get(TRACE_ID_HEADER) else {
+        return Ok(TraceId::generate());
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("invalid {TRACE_ID_HEADER} header"))
+}
+
+fn start_server(ctx: &Context<Config>, settings: Arc<RwLock<Settings>>) -> JoinHandle<()> {
+    use hyper::{
+        server::{conn::AddrStream, Server},
+        service::{make_service_fn, service_fn},
+        Error as HyperError,
+    };
+
+    let address = ctx.config().address;
+    let ctx = Arc::new(ctx.pruned());
+    let ctx1 = ctx.clone();
+
+    let scope = scope::expose();
+    let scope1 = scope.clone();
+
+    let serving = async move {
+        let server = Server::try_bind(&address)?;
+        let make_svc = make_service_fn(move |_socket: &AddrStream| {
+            let ctx = ctx.clone();
+            let settings = settings.clone();
+            let scope = scope.clone();
+
+            async move {
+                Ok::<_, HyperError>(service_fn(move |req: Request<Body>| {
+                    let ctx = ctx.clone();
+                    let settings = settings.clone();
+                    let scope = scope.clone();
+
+                    let trace_id = extract_trace_id(&req);
+
+                    async move {
+                        let trace_id = match trace_id {
+                            Ok(trace_id) => trace_id,
+                            Err(err) => {
+                                return Ok::<_, HyperError>(respond(StatusCode::BAD_REQUEST, err))
+                            }
+                        };
+
+                        scope.set_trace_id(trace_id);
+                        let mut response = scope.within(handle(&ctx, &settings, req)).await;
+
+                        let trace_id = HeaderValue::from(u64::from(trace_id));
+                        response.headers_mut().insert(TRACE_ID_HEADER, trace_id);
+                        Ok::<_, HyperError>(response)
+                    }
+                }))
+            }
+        });
+        server.serve(make_svc).await
+    };
+
+    tokio::spawn(async move {
+        if let Err(err) = serving.await {
+            let f = async {
+                let _ = ctx1.send_to(ctx1.addr(), ServerFailed(err.into())).await;
+            };
+
+            scope1.set_trace_id(TraceId::generate());
+            scope1.within(f).await;
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    /// The address to listen for clients.
+    pub(crate) address: SocketAddr,
+    /// Messages exposed to clients, others are rejected.
+    #[serde(default)]
+    pub(crate) messages: Vec<MessageConfig>,
+    /// The maximum time to wait for a response.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub(crate) timeout: Duration,
+    /// The maximum size of a request body in bytes, larger ones are rejected.
+    #[serde(default = "default_max_body_size")]
+    pub(crate) max_body_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct MessageConfig {
+    /// The message protocol.
+    pub(crate) protocol: String,
+    /// The message name.
+    pub(crate) name: String,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_max_body_size() -> usize {
+    1024 * 1024
+}
@@ -0,0 +1,53 @@
+//! Exposes selected messages to external clients over HTTP/JSON, so tools
+//! can poke the system without linking Rust.
+//!
+//! `POST /messages/{protocol}/{name}` deserializes the body as the message and
+//! sends it by routes of the gateway's group, e.g.
+//! `gateways.route_all_to(&orders)`. Requests are waited for responses, which
+//! are serialized as JSON. The body can be omitted for unit messages.
+//!
+//! | Status | Meaning                                                  |
+//! |--------|----------------------------------------------------------|
+//! | 200    | The response is in the body.                             |
+//! | 202    | The regular message is sent.                             |
+//! | 204    | The request is ignored by the recipient.                 |
+//! | 400    | The body or the `x-trace-id` header is invalid.          |
+//! | 404    | The message isn't exposed.                               |
+//! | 413    | The body is larger than `max_body_size`.                 |
+//! | 502    | There are no recipients or they have failed.             |
+//! | 504    | No response within `timeout`.                            |
+//!
+//! The trace id is taken from the `x-trace-id` header if provided, otherwise
+//! it's generated. Anyway, it's returned in the `x-trace-id` header. Clients
+//! minting trace ids should use [`TraceId::from_parts()`].
+//!
+//! `GET /messages` lists exposed messages, see [`elfo_core::registry`].
+//!
+//! ```toml
+//! [system.gateways]
+//! address = "0.0.0.0:9043"
+//! # The maximum time to wait for a response, 10s by default.
+//! timeout = "5s"
+//! # The maximum size of a request body in bytes, 1MiB by default.
+//! max_body_size = 65536
+//!
+//! # Messages exposed to clients, others are rejected.
+//! [[system.gateways.messages]]
+//! protocol = "orders"
+//! name = "PlaceOrder"
+//! ```
+//!
+//! Only HTTP/JSON is supported for now.
+//!
+//! [`TraceId::from_parts()`]: elfo_core::tracing::TraceId::from_parts
+#![warn(rust_2018_idioms, unreachable_pub)]
+
+use elfo_core::Blueprint;
+
+mod actor;
+mod config;
+
+/// Creates a blueprint of the gateway.
+pub fn new() -> Blueprint {
+    actor::new()
+}
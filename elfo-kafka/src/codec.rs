@@ -0,0 +1,71 @@
+//! Conversion between Kafka records and messages.
+
+use elfo_core::_priv::AnyMessage;
+
+/// A Kafka record without metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    /// The record's key, used for partitioning.
+    pub key: Option<Vec<u8>>,
+    /// The record's payload.
+    pub payload: Vec<u8>,
+}
+
+/// Converts consumed records to messages and messages to produced records.
+pub trait Codec: Send + Sync + 'static {
+    /// Converts a record consumed from the topic to a message,
+    /// `Ok(None)` skips the record.
+    fn decode(&self, topic: &str, record: Record) -> Result<Option<AnyMessage>, String>;
+
+    /// Converts a message to a record, `Ok(None)` skips the message.
+    fn encode(&self, message: &AnyMessage) -> Result<Option<Record>, String>;
+}
+
+/// Messages as `[protocol, name, body]` JSON arrays in payloads, e.g.
+/// `["orders", "OrderPlaced", { "id": 42 }]`. Keys are omitted.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn decode(&self, _topic: &str, record: Record) -> Result<Option<AnyMessage>, String> {
+        serde_json::from_slice(&record.payload)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    fn encode(&self, message: &AnyMessage) -> Result<Option<Record>, String> {
+        let payload = serde_json::to_vec(message).map_err(|err| err.to_string())?;
+        Ok(Some(Record { key: None, payload }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{message, Message};
+
+    use super::*;
+
+    #[message(protocol = "kafka-test")]
+    #[derive(PartialEq)]
+    struct OrderPlaced {
+        id: u32,
+    }
+
+    #[test]
+    fn json_codec() {
+        let message = OrderPlaced { id: 42 };
+
+        let record = JsonCodec
+            .encode(&message.clone().upcast())
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.payload, br#"["kafka-test","OrderPlaced",{"id":42}]"#);
+        assert_eq!(record.key, None);
+
+        let decoded = JsonCodec.decode("orders", record).unwrap().unwrap();
+        assert_eq!(decoded.downcast::<OrderPlaced>().unwrap(), message);
+
+        let record = Record::default();
+        assert!(JsonCodec.decode("orders", record).is_err());
+    }
+}
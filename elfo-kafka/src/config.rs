@@ -0,0 +1,105 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use rdkafka::ClientConfig;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct ConsumerConfig {
+    /// Bootstrap brokers, e.g. `kafka-1:9092,kafka-2:9092`.
+    pub(crate) brokers: String,
+    /// The consumer group.
+    pub(crate) group_id: String,
+    /// Topics to consume from.
+    pub(crate) topics: Vec<String>,
+    /// The delay before redelivering a message that isn't acknowledged.
+    #[serde(with = "humantime_serde", default = "default_retry_delay")]
+    pub(crate) retry_delay: Duration,
+    /// Additional properties of `librdkafka`.
+    #[serde(default)]
+    pub(crate) properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct ProducerConfig {
+    /// Bootstrap brokers, e.g. `kafka-1:9092,kafka-2:9092`.
+    pub(crate) brokers: String,
+    /// The topic to publish to.
+    pub(crate) topic: String,
+    /// The maximum time to wait for a delivery.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub(crate) timeout: Duration,
+    /// Additional properties of `librdkafka`.
+    #[serde(default)]
+    pub(crate) properties: BTreeMap<String, String>,
+}
+
+impl ConsumerConfig {
+    pub(crate) fn client_config(&self) -> ClientConfig {
+        let mut config = client_config(&self.brokers, &self.properties);
+        config.set("group.id", &self.group_id);
+        // Offsets are stored only after acknowledgements, see `consumer.rs`.
+        config.set("enable.auto.offset.store", "false");
+        config
+    }
+
+    pub(crate) fn is_resubscription_needed(&self, prev: &Self) -> bool {
+        self.brokers != prev.brokers
+            || self.group_id != prev.group_id
+            || self.topics != prev.topics
+            || self.properties != prev.properties
+    }
+}
+
+impl ProducerConfig {
+    pub(crate) fn client_config(&self) -> ClientConfig {
+        client_config(&self.brokers, &self.properties)
+    }
+
+    pub(crate) fn is_recreation_needed(&self, prev: &Self) -> bool {
+        self.brokers != prev.brokers || self.properties != prev.properties
+    }
+}
+
+fn client_config(brokers: &str, properties: &BTreeMap<String, String>) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", brokers);
+
+    for (key, value) in properties {
+        config.set(key, value);
+    }
+
+    config
+}
+
+fn default_retry_delay() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consumer_properties() {
+        let config: ConsumerConfig = toml::from_str(
+            r#"
+            brokers = "kafka:9092"
+            group_id = "pipeline"
+            topics = ["orders"]
+            properties = { "enable.auto.offset.store" = "true", "session.timeout.ms" = "6000" }
+            "#,
+        )
+        .unwrap();
+
+        let client = config.client_config();
+        assert_eq!(client.get("bootstrap.servers"), Some("kafka:9092"));
+        assert_eq!(client.get("group.id"), Some("pipeline"));
+        assert_eq!(client.get("session.timeout.ms"), Some("6000"));
+        // Cannot be overridden, otherwise messages can be lost.
+        assert_eq!(client.get("enable.auto.offset.store"), Some("false"));
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use metrics::increment_counter;
+use rdkafka::{
+    consumer::{Consumer as _, StreamConsumer},
+    Message as _,
+};
+use tracing::{debug, error, info, warn};
+
+use elfo_core::{
+    _priv::AnyMessage,
+    messages::{ConfigUpdated, Terminate},
+    msg, registry, Context, Envelope, Message, RecvOr,
+};
+
+use crate::{
+    codec::{Codec, Record},
+    config::ConsumerConfig,
+};
+
+struct Consumer {
+    ctx: Context<ConsumerConfig>,
+    codec: Arc<dyn Codec>,
+    subscribed: Option<ConsumerConfig>,
+    is_outdated: bool,
+}
+
+pub(crate) async fn exec(ctx: Context<ConsumerConfig>, codec: Arc<dyn Codec>) {
+    Consumer {
+        ctx,
+        codec,
+        subscribed: None,
+        is_outdated: false,
+    }
+    .main()
+    .await
+}
+
+impl Consumer {
+    async fn main(mut self) {
+        let mut consumer = self.subscribe();
+
+        loop {
+            if self.is_outdated {
+                info!("config changed, resubscribe");
+                self.is_outdated = false;
+                consumer = self.subscribe();
+            }
+
+            // `StreamConsumer::recv()` is cancel safe.
+            let Some(input) = self.ctx.recv_or(consumer.recv()).await else {
+                break;
+            };
+
+            let (topic, partition, offset, record) = match input {
+                RecvOr::Envelope(envelope) => {
+                    if !self.on_envelope(envelope) {
+                        break;
+                    }
+                    continue;
+                }
+                RecvOr::Future(Ok(message)) => (
+                    message.topic().to_owned(),
+                    message.partition(),
+                    message.offset(),
+                    Record {
+                        key: message.key().map(<[u8]>::to_vec),
+                        payload: message.payload().unwrap_or_default().to_vec(),
+                    },
+                ),
+                RecvOr::Future(Err(err)) => {
+                    warn!(error = %err, "cannot consume a record");
+                    continue;
+                }
+            };
+
+            if !self.handle(&topic, record).await {
+                // The offset isn't stored, so the record is redelivered.
+                break;
+            }
+
+            // Committed by `librdkafka` periodically.
+            if let Err(err) = consumer.store_offset(&topic, partition, offset) {
+                warn!(%topic, partition, offset, error = %err, "cannot store the offset");
+            }
+        }
+    }
+
+    fn subscribe(&mut self) -> StreamConsumer {
+        let config = self.ctx.config();
+
+        let consumer: StreamConsumer = match config.client_config().create() {
+            Ok(consumer) => consumer,
+            Err(err) => {
+                error!(error = %err, "cannot create a consumer");
+                panic!("cannot create a consumer");
+            }
+        };
+
+        let topics = config.topics.iter().map(String::as_str).collect::<Vec<_>>();
+        if let Err(err) = consumer.subscribe(&topics) {
+            error!(error = %err, "cannot subscribe");
+            panic!("cannot subscribe");
+        }
+
+        info!(?topics, "subscribed");
+        self.subscribed = Some(config.clone());
+        consumer
+    }
+
+    /// Returns `false` once the consumer should be terminated.
+    fn on_envelope(&mut self, envelope: Envelope) -> bool {
+        msg!(match envelope {
+            ConfigUpdated => {
+                // Applied after handling the current record.
+                let subscribed = self.subscribed.as_ref().expect("subscribed on start");
+                self.is_outdated = self.ctx.config().is_resubscription_needed(subscribed);
+                true
+            }
+            Terminate => false,
+            _ => true,
+        })
+    }
+
+    /// Delivers the record until it's acknowledged.
+    /// Returns `false` if the consumer is terminated before that.
+    async fn handle(&mut self, topic: &str, record: Record) -> bool {
+        let message = match self.codec.decode(topic, record) {
+            Ok(Some(message)) => message,
+            Ok(None) => return true,
+            Err(err) => {
+                // Poison records are skipped, otherwise the partition is stuck.
+                warn!(%topic, error = %err, "cannot decode the record, skipped");
+                increment_counter!("elfo_kafka_skipped_records_total");
+                return true;
+            }
+        };
+
+        loop {
+            match self.deliver(message.clone()).await {
+                Ok(()) => return true,
+                Err(err) => debug!(%topic, error = %err, "the message isn't acknowledged"),
+            }
+
+            increment_counter!("elfo_kafka_redeliveries_total");
+
+            let delay = self.ctx.config().retry_delay;
+            let mut sleep = Box::pin(tokio::time::sleep(delay));
+
+            loop {
+                match self.ctx.recv_or(&mut sleep).await {
+                    Some(RecvOr::Future(())) => break,
+                    Some(RecvOr::Envelope(envelope)) => {
+                        if !self.on_envelope(envelope) {
+                            return false;
+                        }
+                    }
+                    None => return false,
+                }
+            }
+        }
+    }
+
+    /// Requests are acknowledged by responses, regular messages by sending.
+    ///
+    /// Note that regular messages aren't tracked after sending, so they can be
+    /// lost if the node crashes before handling them, see the crate's docs.
+    async fn deliver(&self, message: AnyMessage) -> Result<(), String> {
+        let is_request = registry::lookup(message.protocol(), message.name())
+            .is_some_and(|info| info.is_request);
+
+        if is_request {
+            let response = self.ctx.request(message).resolve().await;
+            response.map(drop).map_err(|err| err.to_string())
+        } else {
+            let result = self.ctx.send(message).await;
+            result.map_err(|err| err.to_string())
+        }
+    }
+}
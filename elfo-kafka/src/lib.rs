@@ -0,0 +1,71 @@
+//! Blueprints to consume messages from Kafka topics and publish messages to
+//! them, so pipelines can sit between Kafka stages.
+//!
+//! The consumer converts records to messages by the provided [`Codec`] and
+//! sends them by routes of its group. Records are handled one by one, and the
+//! offset is stored (and then committed periodically) only once the message is
+//! acknowledged:
+//! * a request is acknowledged by a response, i.e. after it's handled,
+//! * a regular message is acknowledged by sending it, i.e. once it's enqueued
+//!   into the recipient's mailbox.
+//!
+//! Unacknowledged messages are redelivered after `retry_delay`. Thus, delivery
+//! of requests is at-least-once and their handlers should be idempotent.
+//! However, regular messages are at-most-once after enqueueing: if the node
+//! crashes before such a message is handled, the record is lost. Use requests
+//! (e.g. `#[message(ret = ())]`) if handling must be acknowledged. Records that
+//! cannot be decoded are skipped.
+//!
+//! ```toml
+//! [kafka_consumers]
+//! brokers = "kafka-1:9092,kafka-2:9092"
+//! group_id = "pipeline"
+//! topics = ["orders"]
+//! # The delay before redelivering, 1s by default.
+//! retry_delay = "500ms"
+//! # Additional properties of `librdkafka`.
+//! properties = { "session.timeout.ms" = "6000" }
+//! ```
+//!
+//! The producer publishes all messages sent to it to the topic, waiting for
+//! each delivery. Undelivered messages are discarded and counted by
+//! `elfo_kafka_discarded_messages_total`.
+//!
+//! ```toml
+//! [kafka_producers]
+//! brokers = "kafka-1:9092,kafka-2:9092"
+//! topic = "fills"
+//! # The maximum time to wait for a delivery, 5s by default.
+//! timeout = "10s"
+//! ```
+//!
+//! Both are recreated on config updates if connection properties are changed.
+#![warn(rust_2018_idioms, unreachable_pub, missing_docs)]
+
+use std::sync::Arc;
+
+use elfo_core::{ActorGroup, Blueprint};
+
+pub mod codec;
+
+mod config;
+mod consumer;
+mod producer;
+
+pub use codec::{Codec, JsonCodec, Record};
+
+/// Creates a blueprint consuming records from topics as messages.
+pub fn consumer(codec: impl Codec) -> Blueprint {
+    let codec = Arc::new(codec) as Arc<dyn Codec>;
+    ActorGroup::new()
+        .config::<config::ConsumerConfig>()
+        .exec(move |ctx| consumer::exec(ctx, codec.clone()))
+}
+
+/// Creates a blueprint publishing messages to the topic.
+pub fn producer(codec: impl Codec) -> Blueprint {
+    let codec = Arc::new(codec) as Arc<dyn Codec>;
+    ActorGroup::new()
+        .config::<config::ProducerConfig>()
+        .exec(move |ctx| producer::exec(ctx, codec.clone()))
+}
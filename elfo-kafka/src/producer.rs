@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use metrics::increment_counter;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tracing::{debug, error, info, warn};
+
+use elfo_core::{
+    messages::{ConfigUpdated, Ping},
+    msg, Context, Message,
+};
+
+use crate::{codec::Codec, config::ProducerConfig};
+
+pub(crate) async fn exec(mut ctx: Context<ProducerConfig>, codec: Arc<dyn Codec>) {
+    let system_protocol = Ping::default().protocol();
+    let mut created = ctx.config().clone();
+    let mut producer = create(&created);
+
+    while let Some(envelope) = ctx.recv().await {
+        let envelope = msg!(match envelope {
+            ConfigUpdated => {
+                if ctx.config().is_recreation_needed(&created) {
+                    info!("config changed, recreate the producer");
+                    created = ctx.config().clone();
+                    producer = create(&created);
+                }
+                continue;
+            }
+            envelope => envelope,
+        });
+
+        // System messages (e.g. `Terminate`) aren't published.
+        if envelope.message().protocol() == system_protocol {
+            continue;
+        }
+
+        let name = envelope.message().name();
+        let record = match codec.encode(envelope.message()) {
+            Ok(Some(record)) => record,
+            Ok(None) => continue,
+            Err(err) => {
+                warn!(%name, error = %err, "cannot encode the message, discarded");
+                increment_counter!("elfo_kafka_discarded_messages_total", "reason" => "encoding");
+                continue;
+            }
+        };
+        drop(envelope);
+
+        let config = ctx.config();
+        let mut future_record = FutureRecord::to(&config.topic).payload(&record.payload);
+        if let Some(key) = &record.key {
+            future_record = future_record.key(key);
+        }
+
+        // Delivered one by one to keep the order.
+        if let Err((err, _)) = producer.send(future_record, config.timeout).await {
+            debug!(%name, error = %err, "cannot publish the message, discarded");
+            increment_counter!("elfo_kafka_discarded_messages_total", "reason" => "delivery");
+        }
+    }
+}
+
+fn create(config: &ProducerConfig) -> FutureProducer {
+    match config.client_config().create() {
+        Ok(producer) => producer,
+        Err(err) => {
+            error!(error = %err, "cannot create a producer");
+            panic!("cannot create a producer");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use syn::{
@@ -5,7 +7,7 @@ use syn::{
     parse::{Error as ParseError, Parse, ParseStream},
     parse_macro_input,
     spanned::Spanned,
-    Data, DeriveInput, Ident, LitStr, Path, Token, Type,
+    Attribute, Data, DeriveInput, Fields, Ident, LitInt, LitStr, Path, Token, Type,
 };
 
 use crate::errors::emit_error;
@@ -15,9 +17,14 @@ struct MessageArgs {
     name: Option<LitStr>,
     protocol: Option<LitStr>,
     ret: Option<Type>,
+    idempotent: Option<Ident>,
     part: bool,
     transparent: bool,
     dumping_allowed: Option<bool>,
+    low_priority: Option<bool>,
+    ttl: Option<(LitStr, Duration)>,
+    version: Option<LitInt>,
+    migrates_from: Option<Type>,
     crate_: Option<Path>,
     not: Vec<String>,
 }
@@ -26,11 +33,16 @@ impl Parse for MessageArgs {
     fn parse(input: ParseStream<'_>) -> Result<Self, ParseError> {
         let mut args = MessageArgs {
             ret: None,
+            idempotent: None,
             name: None,
             protocol: None,
             part: false,
             transparent: false,
             dumping_allowed: None,
+            low_priority: None,
+            ttl: None,
+            version: None,
+            migrates_from: None,
             crate_: None,
             not: Vec::new(),
         };
@@ -39,11 +51,18 @@ impl Parse for MessageArgs {
         // `#[message(name = "N")]`
         // `#[message(protocol = "P")]`
         // `#[message(ret = A)]`
+        // `#[message(ret = A, idempotent)]`
         // `#[message(part)]`
         // `#[message(part, transparent)]`
         // `#[message(elfo = some)]`
         // `#[message(not(Debug))]`
         // `#[message(dumping = "disabled")]`
+        // `#[message(priority = "low")]`
+        // `#[message(ttl = "500ms")]`
+        // `#[message(version = 2)]`
+        // `#[message(version = 2, migrates_from = A)]`
+        //
+        // Also, fields can be marked as `#[message(secret)]`.
         while !input.is_empty() {
             let ident: Ident = input.parse()?;
 
@@ -60,6 +79,7 @@ impl Parse for MessageArgs {
                     let _: Token![=] = input.parse()?;
                     args.ret = Some(input.parse()?);
                 }
+                "idempotent" => args.idempotent = Some(ident),
                 "part" => args.part = true,
                 "transparent" => args.transparent = true,
                 "dumping" => {
@@ -73,6 +93,45 @@ impl Parse for MessageArgs {
                         return Err(input.error("only `dumping = \"disabled\"` is supported"));
                     }
                 }
+                "priority" => {
+                    let _: Token![=] = input.parse()?;
+                    let s: LitStr = input.parse()?;
+
+                    if s.value() == "low" {
+                        args.low_priority = Some(true);
+                    } else {
+                        return Err(input.error("only `priority = \"low\"` is supported"));
+                    }
+                }
+                "ttl" => {
+                    let _: Token![=] = input.parse()?;
+                    let s: LitStr = input.parse()?;
+
+                    match humantime::parse_duration(&s.value()) {
+                        Ok(ttl) if !ttl.is_zero() => args.ttl = Some((s, ttl)),
+                        Ok(_) => return Err(ParseError::new(s.span(), "`ttl` must be positive")),
+                        Err(err) => {
+                            return Err(ParseError::new(s.span(), format!("invalid `ttl`: {err}")))
+                        }
+                    }
+                }
+                "version" => {
+                    let _: Token![=] = input.parse()?;
+                    let version: LitInt = input.parse()?;
+
+                    if !matches!(version.base10_parse::<u8>(), Ok(1..)) {
+                        return Err(ParseError::new(
+                            version.span(),
+                            "`version` must be in the range 1..=255",
+                        ));
+                    }
+
+                    args.version = Some(version);
+                }
+                "migrates_from" => {
+                    let _: Token![=] = input.parse()?;
+                    args.migrates_from = Some(input.parse()?);
+                }
                 // TODO: call it `crate` like in linkme?
                 "elfo" => {
                     let _: Token![=] = input.parse()?;
@@ -112,6 +171,26 @@ impl MessageArgs {
             incompatible(&self.name, "name");
             incompatible(&self.protocol, "protocol");
             incompatible(&self.dumping_allowed, "dumping_allowed");
+            incompatible(&self.low_priority, "priority");
+            incompatible(&self.ttl.as_ref().map(|(s, _)| s), "ttl");
+        }
+
+        if let Some(idempotent) = &self.idempotent {
+            if self.ret.is_none() {
+                emit_error!(
+                    idempotent.span(),
+                    "`idempotent` is applicable only for requests"
+                );
+            }
+        }
+
+        if let Some(migrates_from) = &self.migrates_from {
+            if self.version.is_none() {
+                emit_error!(
+                    migrates_from.span(),
+                    "`migrates_from` requires `version` to be specified"
+                );
+            }
         }
     }
 }
@@ -124,7 +203,11 @@ fn gen_derive_attr(blacklist: &[String], name: &str, path: TokenStream) -> Token
         .into_token_stream()
 }
 
-fn gen_impl_debug(input: &DeriveInput) -> TokenStream {
+fn gen_impl_debug(
+    input: &DeriveInput,
+    secrets: &[Vec<bool>],
+    internal: &TokenStream,
+) -> TokenStream {
     let name = &input.ident;
     let field = match &input.data {
         Data::Struct(data) if data.fields.len() == 1 => Some(data.fields.iter().next().unwrap()),
@@ -139,7 +222,9 @@ fn gen_impl_debug(input: &DeriveInput) -> TokenStream {
         return TokenStream::new();
     };
 
-    let propagate_fmt = if let Some(ident) = field.ident.as_ref() {
+    let propagate_fmt = if secrets.iter().flatten().any(|is_secret| *is_secret) {
+        quote! { ::std::fmt::Debug::fmt(&#internal::Redacted, f) }
+    } else if let Some(ident) = field.ident.as_ref() {
         quote! { self.#ident.fmt(f) }
     } else {
         quote! { self.0.fmt(f) }
@@ -155,6 +240,173 @@ fn gen_impl_debug(input: &DeriveInput) -> TokenStream {
     }
 }
 
+/// Removes `#[message(secret)]` from fields and hides such fields in dumps.
+/// Returns flags for fields of every variant (one for structs).
+fn extract_secrets(input: &mut DeriveInput, crate_: &Path) -> Vec<Vec<bool>> {
+    let redact = format!("{}::dumping::redact", crate_.to_token_stream());
+
+    let process = |fields: &mut Fields| {
+        fields
+            .iter_mut()
+            .map(|field| {
+                let len = field.attrs.len();
+                let mut is_valid = true;
+
+                field.attrs.retain(|attr| {
+                    if !attr.path().is_ident("message") {
+                        return true;
+                    }
+
+                    match attr.parse_args::<Ident>() {
+                        Ok(ident) if ident == "secret" => {}
+                        _ => is_valid = false,
+                    }
+                    false
+                });
+
+                if !is_valid {
+                    emit_error!(
+                        field.span(),
+                        "only `#[message(secret)]` is supported on fields"
+                    );
+                }
+
+                let is_secret = field.attrs.len() != len;
+                if is_secret {
+                    field
+                        .attrs
+                        .push(syn::parse_quote! { #[serde(serialize_with = #redact)] });
+                }
+                is_secret
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match &mut input.data {
+        Data::Struct(data) => vec![process(&mut data.fields)],
+        Data::Enum(data) => data
+            .variants
+            .iter_mut()
+            .map(|variant| process(&mut variant.fields))
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    }
+}
+
+/// Generates `Debug` printing secret fields as `<redacted>`.
+fn gen_impl_debug_redacted(
+    input: &DeriveInput,
+    secrets: &[Vec<bool>],
+    internal: &TokenStream,
+) -> TokenStream {
+    let name = &input.ident;
+
+    let gen_arm = |path: TokenStream, ident: &Ident, fields: &Fields, secrets: &[bool]| {
+        let ident_str = ident.to_string();
+        let bindings = (0..fields.len())
+            .map(|i| Ident::new(&format!("_{i}"), ident.span()))
+            .collect::<Vec<_>>();
+        let values = bindings.iter().zip(secrets).map(|(binding, is_secret)| {
+            if *is_secret {
+                quote! { &#internal::Redacted }
+            } else {
+                quote! { #binding }
+            }
+        });
+
+        match fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| f.ident.as_ref().unwrap());
+                let names_str = names.clone().map(|ident| ident.to_string());
+                quote! {
+                    #path { #(#names: #bindings),* } => f
+                        .debug_struct(#ident_str)
+                        #(.field(#names_str, #values))*
+                        .finish()
+                }
+            }
+            Fields::Unnamed(_) => quote! {
+                #path(#(#bindings),*) => f
+                    .debug_tuple(#ident_str)
+                    #(.field(#values))*
+                    .finish()
+            },
+            Fields::Unit => quote! { #path => f.write_str(#ident_str) },
+        }
+    };
+
+    let arms = match &input.data {
+        Data::Struct(data) => vec![gen_arm(quote! { Self }, name, &data.fields, &secrets[0])],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .zip(secrets)
+            .map(|(variant, secrets)| {
+                let ident = &variant.ident;
+                gen_arm(quote! { Self::#ident }, ident, &variant.fields, secrets)
+            })
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    quote! {
+        impl ::std::fmt::Debug for #name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    }
+}
+
+/// Calculates a hash of the message's shape: names and types of fields and
+/// variants along with `serde` attributes, which affect the wire format.
+/// Doc comments and other attributes don't affect the hash.
+///
+/// FNV-1a is used to keep hashes stable between compilations and platforms.
+fn gen_schema_hash(input: &DeriveInput) -> u64 {
+    fn push_serde_attrs(shape: &mut String, attrs: &[Attribute]) {
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            shape.push_str(&attr.meta.to_token_stream().to_string());
+        }
+    }
+
+    fn push_fields(shape: &mut String, fields: &Fields) {
+        shape.push('{');
+        for field in fields {
+            push_serde_attrs(shape, &field.attrs);
+            if let Some(ident) = &field.ident {
+                shape.push_str(&ident.to_string());
+                shape.push(':');
+            }
+            shape.push_str(&field.ty.to_token_stream().to_string());
+            shape.push(',');
+        }
+        shape.push('}');
+    }
+
+    let mut shape = String::new();
+    push_serde_attrs(&mut shape, &input.attrs);
+
+    match &input.data {
+        Data::Struct(data) => push_fields(&mut shape, &data.fields),
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                push_serde_attrs(&mut shape, &variant.attrs);
+                shape.push_str(&variant.ident.to_string());
+                push_fields(&mut shape, &variant.fields);
+                shape.push(';');
+            }
+        }
+        Data::Union(_) => {}
+    }
+
+    shape.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 pub fn message_impl(
     args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
@@ -166,7 +418,11 @@ pub fn message_impl(
     let crate_ = args.crate_.unwrap_or(default_path_to_elfo);
 
     // TODO: what about parsing into something cheaper?
-    let input = parse_macro_input!(input as DeriveInput);
+    let mut input = parse_macro_input!(input as DeriveInput);
+    // Secrets don't affect the wire format, so the hash is calculated before.
+    let schema_hash = gen_schema_hash(&input);
+    let secrets = extract_secrets(&mut input, &crate_);
+    let has_secrets = secrets.iter().flatten().any(|is_secret| *is_secret);
     let name = &input.ident;
     let serde_crate = format!("{}::_priv::serde", crate_.to_token_stream());
     let internal = quote![#crate_::_priv];
@@ -177,8 +433,8 @@ pub fn message_impl(
         .map(LitStr::value)
         .unwrap_or_else(|| input.ident.to_string());
 
-    let derive_debug =
-        (!args.transparent).then(|| gen_derive_attr(&args.not, "Debug", quote![Debug]));
+    let derive_debug = (!args.transparent && !has_secrets)
+        .then(|| gen_derive_attr(&args.not, "Debug", quote![Debug]));
     let derive_clone = gen_derive_attr(&args.not, "Clone", quote![Clone]);
     let derive_serialize =
         gen_derive_attr(&args.not, "Serialize", quote![#internal::serde::Serialize]);
@@ -195,6 +451,53 @@ pub fn message_impl(
 
     // TODO: pass to `_elfo_Wrapper`.
     let dumping_allowed = args.dumping_allowed.unwrap_or(true);
+    let low_priority = args.low_priority.unwrap_or(false);
+    let is_request = args.ret.is_some();
+    let ttl = match &args.ttl {
+        Some((_, ttl)) => {
+            let nanos = ttl.as_nanos() as u64;
+            quote! { ::std::option::Option::Some(::std::time::Duration::from_nanos(#nanos)) }
+        }
+        None => quote! { ::std::option::Option::None },
+    };
+
+    let version = args
+        .version
+        .as_ref()
+        .map_or(1, |version| version.base10_parse::<u8>().unwrap());
+
+    // Parts implement `Versioned` only if they explicitly declare a version
+    // in order to be used in `migrates_from`.
+    let impl_versioned = (cfg!(feature = "network") && (!args.part || args.version.is_some()))
+        .then(|| {
+            let migrate = args.migrates_from.as_ref().map(|old| {
+                quote! {
+                    if version < #version {
+                        return <#old as #internal::Versioned>::read_msgpack_versioned(buffer, version)
+                            .map(<#name as ::std::convert::From<#old>>::from);
+                    }
+                }
+            });
+
+            quote! {
+                impl #internal::Versioned for #name {
+                    const VERSION: u8 = #version;
+
+                    fn read_msgpack_versioned(
+                        buffer: &[u8],
+                        version: u8,
+                    ) -> ::std::result::Result<Self, #internal::rmps::decode::Error> {
+                        if version == #version {
+                            return #internal::read_msgpack::<#name>(buffer);
+                        }
+
+                        #migrate
+
+                        Err(#internal::unsupported_version::<#name>(version))
+                    }
+                }
+            }
+        });
 
     let network_fns = cfg!(feature = "network").then(|| {
         quote! {
@@ -206,10 +509,11 @@ pub fn message_impl(
                 #internal::write_msgpack(buffer, limit, cast_ref(message))
             }
 
-            fn read_msgpack(buffer: &[u8]) ->
+            fn read_msgpack(buffer: &[u8], version: u8) ->
                 ::std::result::Result<#internal::AnyMessage, #internal::rmps::decode::Error>
             {
-                #internal::read_msgpack::<#name>(buffer).map(#crate_::Message::upcast)
+                <#name as #internal::Versioned>::read_msgpack_versioned(buffer, version)
+                    .map(#crate_::Message::upcast)
             }
         }
     });
@@ -267,7 +571,12 @@ pub fn message_impl(
                     #internal::metrics::Label::from_static_parts("message", #name_str),
                     #internal::metrics::Label::from_static_parts("protocol", #protocol),
                 ],
+                schema_hash: #schema_hash,
+                version: #version,
+                is_request: #is_request,
                 dumping_allowed: #dumping_allowed,
+                low_priority: #low_priority,
+                ttl: #ttl,
                 clone,
                 debug,
                 erase,
@@ -283,6 +592,7 @@ pub fn message_impl(
     });
 
     let impl_request = args.ret.as_ref().map(|ret| {
+        let is_idempotent = args.idempotent.is_some();
         let wrapper_name_str = format!("{name_str}::Response");
         let protocol = args.protocol.as_ref().map(|p| quote! { protocol = #p, });
 
@@ -290,6 +600,7 @@ pub fn message_impl(
             impl #crate_::Request for #name {
                 type Response = #ret;
                 type Wrapper = _elfo_Wrapper;
+                const IS_IDEMPOTENT: bool = #is_idempotent;
             }
 
             #[message(not(Debug), #protocol name = #wrapper_name_str, elfo = #crate_)]
@@ -298,7 +609,7 @@ pub fn message_impl(
             impl ::std::fmt::Debug for _elfo_Wrapper {
                 #[inline]
                 fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                    self.0.fmt(f)
+                    ::std::fmt::Debug::fmt(&self.0, f)
                 }
             }
 
@@ -318,8 +629,15 @@ pub fn message_impl(
         }
     });
 
-    let impl_debug =
-        (args.transparent && args.not.iter().all(|x| x != "Debug")).then(|| gen_impl_debug(&input));
+    let impl_debug = args.not.iter().all(|x| x != "Debug").then(|| {
+        if args.transparent {
+            gen_impl_debug(&input, &secrets, &internal)
+        } else if has_secrets {
+            gen_impl_debug_redacted(&input, &secrets, &internal)
+        } else {
+            TokenStream::new()
+        }
+    });
 
     let expanded = quote! {
         #derive_debug
@@ -339,6 +657,7 @@ pub fn message_impl(
             use #internal::{MESSAGE_LIST, smallbox::smallbox, linkme};
 
             #impl_message
+            #impl_versioned
             #impl_request
             #impl_debug
         };
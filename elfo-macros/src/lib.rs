@@ -3,6 +3,23 @@ use syn::parse_quote;
 
 use elfo_macros_impl::{message_impl, msg_impl};
 
+/// Matches an envelope against messages.
+///
+/// Supported arms:
+/// * `SomeMessage => ..` or with a pattern, e.g. `SomeMessage { a, .. } => ..`
+///   — regular messages.
+/// * `(SomeRequest, token) => ctx.respond(token, response)` — requests. The
+///   token is typed by the request, so the response must be of the type
+///   specified by `#[message(ret = ..)]`, it's checked at compile time.
+/// * `envelope => ..` or `_ => ..` — other messages.
+///
+/// ```ignore
+/// msg!(match envelope {
+///     SomethingHappened { id } => { /* ... */ }
+///     (GetCount, token) => ctx.respond(token, count),
+///     _ => {}
+/// });
+/// ```
 #[proc_macro]
 pub fn msg(input: TokenStream) -> TokenStream {
     msg_impl(input, parse_quote!(::elfo))
@@ -0,0 +1,148 @@
+//! Exposes the internode codec in order to measure its performance outside
+//! the crate. The format is the same as used by workers, see the `codec`
+//! module for details.
+
+use futures::future;
+use tokio::{
+    io::AsyncReadExt,
+    net::{TcpListener, TcpStream},
+};
+
+use elfo_core::{
+    scope,
+    tracing::{Baggage, TraceId},
+    Message,
+};
+
+use crate::{
+    codec::{
+        decode::{decode, DecodeState, DecodeStats},
+        encode::{encode, EncodeError, EncodeStats},
+        format::{NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload},
+    },
+    frame::write::FramedWrite,
+    socket::WriteHalf,
+    worker::PIPELINE_DEPTH,
+};
+
+/// Encodes the message as a regular one and appends it to `dst`.
+///
+/// Returns the size of the encoded frame, `0` if the message has been skipped.
+///
+/// # Panics
+///
+/// If a fatal encoding error occurs.
+pub fn encode_regular<M: Message>(message: M, dst: &mut Vec<u8>) -> usize {
+    let envelope = regular_envelope(message);
+    let start_pos = dst.len();
+    match encode(&envelope, dst, &mut EncodeStats::default(), None) {
+        Ok(()) => dst.len() - start_pos,
+        Err(EncodeError::Skipped) => 0,
+        Err(EncodeError::Fatal(err)) => panic!("cannot encode: {err}"),
+    }
+}
+
+/// Decodes all messages from `src`, dropping them.
+///
+/// Returns the number of successfully decoded messages.
+///
+/// # Panics
+///
+/// If a fatal decoding error occurs or `src` ends with an incomplete frame.
+pub fn decode_all(mut src: &[u8]) -> usize {
+    let mut stats = DecodeStats::default();
+
+    while !src.is_empty() {
+        let bytes_consumed = match decode(src, &mut stats).expect("cannot decode") {
+            DecodeState::Done { bytes_consumed, .. } => bytes_consumed,
+            DecodeState::Skipped { bytes_consumed, .. } => bytes_consumed,
+            DecodeState::Chunk { bytes_consumed } => bytes_consumed,
+            DecodeState::NeedMoreData { .. } => panic!("incomplete frame"),
+        };
+
+        src = &src[bytes_consumed..];
+    }
+
+    stats.total_messages_decoded as usize
+}
+
+/// Sends `count` copies of the message, one per LZ4 frame, through a loopback
+/// TCP connection by the same frame writer as used by workers.
+/// Up to `pool_size` frame buffers are reused, `0` disables reusing.
+///
+/// Returns the number of bytes received by the other side.
+///
+/// # Panics
+///
+/// If the message cannot be encoded or an IO error occurs.
+pub async fn write_frames<M: Message>(message: M, count: usize, pool_size: usize) -> usize {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("cannot bind");
+    let addr = listener.local_addr().expect("cannot get a local address");
+    let (client, server) = future::join(TcpStream::connect(addr), listener.accept()).await;
+    let (_client_read, client_write) = client.expect("cannot connect").into_split();
+    let (mut server, _) = server.expect("cannot accept");
+
+    let write = WriteHalf::from_tcp(FramedWrite::lz4(None, false), client_write);
+    let (mut encoding, frame_writer) = write.pipeline(PIPELINE_DEPTH, pool_size);
+    let envelope = regular_envelope(message);
+
+    let encoder = async move {
+        for _ in 0..count {
+            encoding.feed(&envelope).expect("cannot encode");
+            encoding.flush().await.expect("cannot flush");
+        }
+    };
+
+    let reader = async move {
+        let mut buffer = vec![0; 64 * 1024];
+        let mut total = 0;
+        loop {
+            match server.read(&mut buffer).await.expect("cannot read") {
+                0 => break total,
+                bytes_read => total += bytes_read,
+            }
+        }
+    };
+
+    let (_, written, received) = future::join3(encoder, frame_writer.exec(), reader).await;
+    written.expect("cannot write frames");
+    received
+}
+
+fn regular_envelope<M: Message>(message: M) -> NetworkEnvelope {
+    NetworkEnvelope {
+        sender: NetworkAddr::NULL,
+        recipient: NetworkAddr::NULL,
+        trace_id: scope::try_trace_id().unwrap_or_else(TraceId::generate),
+        message_id: None,
+        parent_id: None,
+        sent_time: None,
+        baggage: Baggage::default(),
+        versions: true,
+        payload: NetworkEnvelopePayload::Regular {
+            message: message.upcast(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::message;
+
+    use super::*;
+
+    #[message]
+    struct Sample(Vec<u8>);
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = Vec::new();
+
+        for size in [0, 10, 1000] {
+            let frame_size = encode_regular(Sample(vec![42; size]), &mut buffer);
+            assert!(frame_size > size);
+        }
+
+        assert_eq!(decode_all(&buffer), 3);
+    }
+}
@@ -0,0 +1,421 @@
+//! Streaming of large blobs (files, snapshots) between actors as a sequence of
+//! chunks, usually located on different nodes.
+//!
+//! The sender waits for every chunk to be acknowledged before sending the next
+//! one, so the transfer is never faster than the receiver. Every chunk is
+//! protected by a checksum and retransmitted if it's corrupted. Acks contain
+//! the offset expected by the receiver, so the sender continues from it after
+//! reconnection or even restart.
+//!
+//! ```ignore
+//! // The sender's side.
+//! let file = tokio::fs::File::open(path).await?;
+//! BlobTransfer::new(transfer_id).send(&ctx, file).await?;
+//!
+//! // The receiver's side.
+//! let mut receivers = HashMap::new();
+//! while let Some(envelope) = ctx.recv().await {
+//!     msg!(match envelope {
+//!         (chunk @ BlobChunk, token) => {
+//!             let receiver = receivers
+//!                 .entry(chunk.id)
+//!                 .or_insert_with(|| BlobReceiver::new(Vec::new()));
+//!             let ack = receiver.handle(&chunk).await?;
+//!             ctx.respond(token, ack);
+//!         }
+//!     });
+//! }
+//! ```
+
+use std::{fmt, io::SeekFrom, time::Duration};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tracing::warn;
+use twox_hash::XxHash64;
+
+use elfo_core::{errors::RequestError, message, Addr, Context, Request};
+
+/// A chunk of the blob, sent by [`BlobTransfer::send()`] and handled by
+/// [`BlobReceiver::handle()`].
+#[message(ret = BlobAck)]
+#[non_exhaustive]
+pub struct BlobChunk {
+    /// The identifier of the transfer.
+    pub id: u64,
+    /// The offset of the chunk in the blob.
+    pub offset: u64,
+    /// Is it the last chunk of the blob?
+    pub is_last: bool,
+    /// The xxHash64 of `data`.
+    pub checksum: u64,
+    /// The content of the chunk.
+    pub data: BlobData,
+}
+
+/// A response to [`BlobChunk`].
+#[message(part)]
+#[non_exhaustive]
+pub struct BlobAck {
+    /// The offset of the next chunk expected by the receiver.
+    pub next_offset: u64,
+    /// Is the whole blob received?
+    pub is_complete: bool,
+}
+
+/// Raw bytes serialized as a binary string and printed only by its length.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BlobData(pub Vec<u8>);
+
+impl fmt::Debug for BlobData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0.len())
+    }
+}
+
+impl Serialize for BlobData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlobData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = BlobData;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(BlobData(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(BlobData(v))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(BlobData(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(Visitor)
+    }
+}
+
+fn checksum(data: &[u8]) -> u64 {
+    XxHash64::oneshot(0, data)
+}
+
+// === BlobTransfer ===
+
+/// Sends a blob to a receiver. See the module-level documentation.
+#[derive(Debug, Clone)]
+pub struct BlobTransfer {
+    id: u64,
+    recipient: Option<Addr>,
+    chunk_size: usize,
+    retry_interval: Duration,
+    max_retries: u32,
+}
+
+/// An error returned by [`BlobTransfer::send()`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BlobError {
+    /// Cannot read the source.
+    Io(std::io::Error),
+    /// The receiver hasn't made progress after `max_retries` attempts.
+    Stuck(RequestError),
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read the blob: {err}"),
+            Self::Stuck(err) => write!(f, "the receiver doesn't make progress: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BlobError {}
+
+impl From<std::io::Error> for BlobError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl BlobTransfer {
+    /// Creates a new transfer with the provided identifier, which must be
+    /// unique for the receiver. The same identifier should be used to resume
+    /// the transfer after restart.
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            recipient: None,
+            chunk_size: 1024 * 1024,
+            retry_interval: Duration::from_secs(1),
+            max_retries: 10,
+        }
+    }
+
+    /// Sends chunks to the specified recipient instead of routing them.
+    pub fn recipient(mut self, recipient: Addr) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Sets the size of chunks, 1MiB by default.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Sets the interval between retries of failed chunks, 1s by default.
+    pub fn retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Sets how many times a chunk is retried without progress before giving
+    /// up, 10 by default.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sends the blob, returns its size.
+    ///
+    /// The source is read from the offset expected by the receiver, which is
+    /// zero for new transfers.
+    pub async fn send<C, K, S>(&self, ctx: &Context<C, K>, source: S) -> Result<u64, BlobError>
+    where
+        C: 'static,
+        S: AsyncRead + AsyncSeek + Unpin,
+    {
+        self.send_wrapped(ctx, source, |chunk| chunk).await
+    }
+
+    /// Like `send()`, but chunks are wrapped into another request.
+    pub(crate) async fn send_wrapped<C, K, S, R>(
+        &self,
+        ctx: &Context<C, K>,
+        mut source: S,
+        wrap: impl Fn(BlobChunk) -> R,
+    ) -> Result<u64, BlobError>
+    where
+        C: 'static,
+        S: AsyncRead + AsyncSeek + Unpin,
+        R: Request<Response = BlobAck>,
+    {
+        let mut buffer = vec![0; self.chunk_size];
+        let mut position = source.seek(SeekFrom::Start(0)).await?;
+        let mut offset = 0;
+        let mut retries = 0;
+
+        loop {
+            if position != offset {
+                position = source.seek(SeekFrom::Start(offset)).await?;
+            }
+
+            let len = read_full(&mut source, &mut buffer).await?;
+            position += len as u64;
+
+            let data = &buffer[..len];
+            let chunk = wrap(BlobChunk {
+                id: self.id,
+                offset,
+                is_last: len < buffer.len(),
+                checksum: checksum(data),
+                data: BlobData(data.to_vec()),
+            });
+
+            let result = match self.recipient {
+                Some(recipient) => ctx.request_to(recipient, chunk).resolve().await,
+                None => ctx.request(chunk).resolve().await,
+            };
+
+            let error = match result {
+                Ok(ack) if ack.is_complete => return Ok(ack.next_offset),
+                Ok(ack) if ack.next_offset != offset => {
+                    offset = ack.next_offset;
+                    retries = 0;
+                    continue;
+                }
+                // The chunk is rejected, e.g. because of a corruption.
+                Ok(_) => RequestError::Failed,
+                Err(err) => err,
+            };
+
+            retries += 1;
+            if retries > self.max_retries {
+                return Err(BlobError::Stuck(error));
+            }
+
+            warn!(
+                message = "cannot send a blob chunk, retrying",
+                id = self.id,
+                offset,
+                error = %error,
+            );
+            tokio::time::sleep(self.retry_interval).await;
+        }
+    }
+}
+
+/// Reads until the buffer is full or EOF is reached.
+async fn read_full<S: AsyncRead + Unpin>(
+    source: &mut S,
+    buffer: &mut [u8],
+) -> std::io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < buffer.len() {
+        let len = source.read(&mut buffer[filled..]).await?;
+        if len == 0 {
+            break;
+        }
+        filled += len;
+    }
+
+    Ok(filled)
+}
+
+// === BlobReceiver ===
+
+/// Writes received chunks of one transfer. See the module-level documentation.
+pub struct BlobReceiver<W> {
+    writer: W,
+    offset: u64,
+    is_complete: bool,
+}
+
+impl<W: AsyncWrite + Unpin> BlobReceiver<W> {
+    /// Creates a receiver of a new transfer.
+    pub fn new(writer: W) -> Self {
+        Self::resume(writer, 0)
+    }
+
+    /// Creates a receiver continuing the transfer from the provided offset,
+    /// e.g. the size of a partially written file after restart.
+    pub fn resume(writer: W, offset: u64) -> Self {
+        Self {
+            writer,
+            offset,
+            is_complete: false,
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Is the whole blob received?
+    pub fn is_complete(&self) -> bool {
+        self.is_complete
+    }
+
+    /// Writes the chunk if it's expected and valid, returns the ack to respond
+    /// with. Unexpected chunks (duplicated or corrupted ones) are ignored.
+    pub async fn handle(&mut self, chunk: &BlobChunk) -> std::io::Result<BlobAck> {
+        if !self.is_complete && chunk.offset == self.offset {
+            if checksum(&chunk.data.0) == chunk.checksum {
+                self.writer.write_all(&chunk.data.0).await?;
+                self.offset += chunk.data.0.len() as u64;
+
+                if chunk.is_last {
+                    self.writer.flush().await?;
+                    self.is_complete = true;
+                }
+            } else {
+                warn!(
+                    message = "blob chunk is corrupted",
+                    id = chunk.id,
+                    offset = chunk.offset,
+                );
+            }
+        }
+
+        Ok(BlobAck {
+            next_offset: self.offset,
+            is_complete: self.is_complete,
+        })
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::_priv::rmps;
+
+    use super::*;
+
+    fn make_chunk(offset: u64, data: &[u8], is_last: bool) -> BlobChunk {
+        BlobChunk {
+            id: 1,
+            offset,
+            is_last,
+            checksum: checksum(data),
+            data: BlobData(data.to_vec()),
+        }
+    }
+
+    #[tokio::test]
+    async fn receiver() {
+        let mut receiver = BlobReceiver::new(Vec::new());
+
+        let ack = receiver
+            .handle(&make_chunk(0, b"hello", false))
+            .await
+            .unwrap();
+        assert_eq!((ack.next_offset, ack.is_complete), (5, false));
+
+        // Duplicated.
+        let ack = receiver
+            .handle(&make_chunk(0, b"hello", false))
+            .await
+            .unwrap();
+        assert_eq!((ack.next_offset, ack.is_complete), (5, false));
+
+        // Corrupted.
+        let mut chunk = make_chunk(5, b" world", true);
+        chunk.data.0[0] = b'_';
+        let ack = receiver.handle(&chunk).await.unwrap();
+        assert_eq!((ack.next_offset, ack.is_complete), (5, false));
+
+        let ack = receiver
+            .handle(&make_chunk(5, b" world", true))
+            .await
+            .unwrap();
+        assert_eq!((ack.next_offset, ack.is_complete), (11, true));
+        assert_eq!(receiver.into_inner(), b"hello world");
+    }
+
+    #[test]
+    fn data_is_compact() {
+        let data = BlobData(vec![0xFF; 100]);
+        let bytes = rmps::to_vec(&data).unwrap();
+        // bin8 marker + length + data.
+        assert_eq!(bytes.len(), 102);
+        assert_eq!(rmps::from_slice::<BlobData>(&bytes).unwrap(), data);
+        assert_eq!(format!("{data:?}"), "<100 bytes>");
+    }
+}
@@ -0,0 +1,292 @@
+//! Protects callers from piling up requests to a failing remote group.
+//!
+//! A breaker is created per connection, i.e. per pair of a local and a remote
+//! group. It counts outcomes of requests: responses with `Failed` and requests
+//! without a response for too long are failures, all other responses are
+//! successes. Once the failure rate exceeds the threshold, the breaker opens
+//! and requests are rejected locally with `RemoteUnavailable` for a cooldown.
+//! Then, a single probe request is let through: the breaker closes if it
+//! succeeds or opens again otherwise. If no outcome of the probe is reported
+//! for `request_timeout` (e.g. it hasn't been sent at all), another probe is
+//! let through.
+
+use std::time::Duration;
+
+use metrics::increment_counter;
+use parking_lot::Mutex;
+use quanta::Instant;
+use tracing::{info, warn};
+
+use crate::config::CircuitBreakerConfig;
+
+pub(crate) struct CircuitBreaker {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    config: CircuitBreakerConfig,
+    state: State,
+}
+
+#[derive(Debug, PartialEq)]
+enum State {
+    Closed {
+        window_start: Instant,
+        successes: u32,
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        /// The probe is considered lost if there is no outcome until then.
+        probe_deadline: Instant,
+    },
+}
+
+impl State {
+    fn closed(now: Instant) -> Self {
+        Self::Closed {
+            window_start: now,
+            successes: 0,
+            failures: 0,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                config,
+                state: State::closed(Instant::now()),
+            }),
+        }
+    }
+
+    pub(crate) fn configure(&self, config: CircuitBreakerConfig) {
+        let mut inner = self.inner.lock();
+
+        if !config.enabled && inner.config.enabled {
+            inner.close(Instant::now());
+        }
+
+        inner.config = config;
+    }
+
+    pub(crate) fn request_timeout(&self) -> Option<Duration> {
+        let inner = self.inner.lock();
+        inner.config.enabled.then_some(inner.config.request_timeout)
+    }
+
+    /// Returns whether a request can be sent right now.
+    pub(crate) fn allow(&self) -> bool {
+        let mut inner = self.inner.lock();
+
+        if !inner.config.enabled {
+            return true;
+        }
+
+        let allowed = inner.allow(Instant::now());
+        if !allowed {
+            increment_counter!("elfo_network_rejected_requests_total");
+        }
+        allowed
+    }
+
+    pub(crate) fn on_success(&self) {
+        self.on_outcome(false);
+    }
+
+    pub(crate) fn on_failure(&self) {
+        self.on_outcome(true);
+    }
+
+    fn on_outcome(&self, is_failure: bool) {
+        let mut inner = self.inner.lock();
+
+        if inner.config.enabled {
+            inner.on_outcome(is_failure, Instant::now());
+        }
+    }
+}
+
+impl Inner {
+    fn allow(&mut self, now: Instant) -> bool {
+        let probe_deadline = now + self.config.request_timeout;
+
+        match &mut self.state {
+            State::Closed { .. } => true,
+            State::Open { until } if now < *until => false,
+            State::Open { .. } => {
+                info!("circuit breaker is half-open, probing");
+                self.state = State::HalfOpen { probe_deadline };
+                true
+            }
+            State::HalfOpen { probe_deadline: d } if now < *d => false,
+            State::HalfOpen { probe_deadline: d } => {
+                warn!("the probe is lost, probing again");
+                *d = probe_deadline;
+                true
+            }
+        }
+    }
+
+    fn on_outcome(&mut self, is_failure: bool, now: Instant) {
+        let config = &self.config;
+
+        let should_open = match &mut self.state {
+            State::Closed {
+                window_start,
+                successes,
+                failures,
+            } => {
+                if now - *window_start >= config.window {
+                    *window_start = now;
+                    *successes = 0;
+                    *failures = 0;
+                }
+
+                if is_failure {
+                    *failures += 1;
+                } else {
+                    *successes += 1;
+                }
+
+                let total = *successes + *failures;
+                total >= config.min_requests
+                    && f64::from(*failures) >= config.failure_rate * f64::from(total)
+            }
+            // Late outcomes of requests sent before opening.
+            State::Open { .. } => false,
+            State::HalfOpen { .. } => is_failure,
+        };
+
+        if should_open {
+            warn!(cooldown = ?config.cooldown, "circuit breaker is open");
+            self.state = State::Open {
+                until: now + config.cooldown,
+            };
+            increment_counter!("elfo_network_circuit_breaker_opened_total");
+        } else if matches!(self.state, State::HalfOpen { .. }) {
+            info!("circuit breaker is closed");
+            self.close(now);
+        }
+    }
+
+    fn close(&mut self, now: Instant) {
+        self.state = State::closed(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quanta::{Clock, Mock};
+
+    use super::*;
+
+    fn with_time_mock(f: impl FnOnce(&Mock)) {
+        let (clock, mock) = Clock::mock();
+        quanta::with_clock(&clock, || f(&mock));
+    }
+
+    #[test]
+    fn it_works() {
+        with_time_mock(|mock| {
+            let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+                enabled: true,
+                min_requests: 4,
+                ..Default::default()
+            });
+
+            // Not enough requests to decide.
+            breaker.on_failure();
+            breaker.on_failure();
+            breaker.on_failure();
+            assert!(breaker.allow());
+
+            // The failure rate is 75%.
+            breaker.on_success();
+            assert!(!breaker.allow());
+
+            mock.increment(Duration::from_secs(29));
+            assert!(!breaker.allow());
+
+            // Only one probe is allowed.
+            mock.increment(Duration::from_secs(1));
+            assert!(breaker.allow());
+            assert!(!breaker.allow());
+
+            // The probe fails.
+            breaker.on_failure();
+            assert!(!breaker.allow());
+
+            // The probe succeeds.
+            mock.increment(Duration::from_secs(30));
+            assert!(breaker.allow());
+            breaker.on_success();
+            assert!(breaker.allow());
+            assert!(breaker.allow());
+        });
+    }
+
+    #[test]
+    fn lost_probe() {
+        with_time_mock(|mock| {
+            let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+                enabled: true,
+                min_requests: 1,
+                ..Default::default()
+            });
+
+            breaker.on_failure();
+            mock.increment(Duration::from_secs(30));
+
+            // The probe is let through, but never sent (e.g. because of flow
+            // control), so its outcome isn't reported.
+            assert!(breaker.allow());
+            mock.increment(Duration::from_secs(4));
+            assert!(!breaker.allow());
+
+            // The probe is considered lost after `request_timeout`.
+            mock.increment(Duration::from_secs(1));
+            assert!(breaker.allow());
+            assert!(!breaker.allow());
+
+            breaker.on_success();
+            assert!(breaker.allow());
+        });
+    }
+
+    #[test]
+    fn window() {
+        with_time_mock(|mock| {
+            let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+                enabled: true,
+                min_requests: 2,
+                ..Default::default()
+            });
+
+            breaker.on_failure();
+            mock.increment(Duration::from_secs(10));
+
+            // The previous window is forgotten.
+            breaker.on_success();
+            assert!(breaker.allow());
+            breaker.on_failure();
+            assert!(!breaker.allow());
+        });
+    }
+
+    #[test]
+    fn disabled() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 1,
+            ..Default::default()
+        });
+
+        breaker.on_failure();
+        assert!(breaker.allow());
+        assert_eq!(breaker.request_timeout(), None);
+    }
+}
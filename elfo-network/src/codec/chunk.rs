@@ -0,0 +1,333 @@
+//! Large envelopes are split into chunks to keep frames small, so they don't
+//! require huge framing (and compression) buffers on both sides.
+//! However, the whole encoded envelope is still kept in one buffer by both
+//! the sender and the receiver, so its size is limited on both sides.
+//! Oversized envelopes are skipped, the connection is kept.
+//! See the `format` module for the layout of chunks.
+
+use std::{collections::VecDeque, mem};
+
+use eyre::{bail, ensure, Result};
+use metrics::counter;
+use tracing::error;
+
+use crate::codec::{
+    decode::{self, DecodeState, DecodeStats, EnvelopeDetails},
+    format::KIND_CHUNK,
+};
+
+/// Envelopes larger than this are sent in chunks of this size.
+pub(crate) const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunked envelopes larger than this are skipped unless configured.
+pub(crate) const DEFAULT_MAX_ENVELOPE_SIZE: usize = 256 * 1024 * 1024;
+
+// size (4) + flags and kind (1) + stream id (8)
+const CHUNK_HEADER_SIZE: usize = 13;
+
+struct OutgoingStream {
+    id: u64,
+    data: Vec<u8>,
+    position: usize,
+}
+
+/// Splits large encoded envelopes into chunks.
+pub(crate) struct Chunker {
+    queue: VecDeque<OutgoingStream>,
+    next_stream_id: u64,
+    max_envelope_size: usize,
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            next_stream_id: 0,
+            max_envelope_size: DEFAULT_MAX_ENVELOPE_SIZE,
+        }
+    }
+}
+
+impl Chunker {
+    /// Sets the maximum size of chunked envelopes, larger ones are rejected.
+    pub(crate) fn set_max_envelope_size(&mut self, max_envelope_size: usize) {
+        self.max_envelope_size = max_envelope_size;
+    }
+
+    /// Moves the encoded envelope starting at `start` out of `buffer` if it's
+    /// too large to be sent at once. The buffer is reallocated with the
+    /// provided `capacity` to avoid keeping memory of the large envelope.
+    ///
+    /// Returns `true` if the envelope is taken. If it exceeds the limit, it's
+    /// removed from `buffer` and an error is returned.
+    pub(crate) fn take_large(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        start: usize,
+        capacity: usize,
+    ) -> Result<bool> {
+        let size = buffer.len() - start;
+        if size <= MAX_CHUNK_SIZE {
+            return Ok(false);
+        }
+
+        if size > self.max_envelope_size {
+            buffer.truncate(start);
+            buffer.shrink_to(capacity.max(start));
+            bail!(
+                "envelope of {size} bytes exceeds the limit of {} bytes",
+                self.max_envelope_size
+            );
+        }
+
+        let data = mem::replace(buffer, Vec::with_capacity(capacity.max(start)));
+        buffer.extend_from_slice(&data[..start]);
+
+        let id = self.next_stream_id;
+        self.next_stream_id = self.next_stream_id.wrapping_add(1);
+
+        self.queue.push_back(OutgoingStream {
+            id,
+            data,
+            position: start,
+        });
+
+        Ok(true)
+    }
+
+    /// Returns `true` if there are no more chunks to write.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Appends the next chunk (if any) to `dst`.
+    pub(crate) fn write_next(&mut self, dst: &mut Vec<u8>) {
+        let Some(stream) = self.queue.front_mut() else {
+            return;
+        };
+
+        let end = (stream.position + MAX_CHUNK_SIZE).min(stream.data.len());
+        let data = &stream.data[stream.position..end];
+
+        dst.extend_from_slice(&((CHUNK_HEADER_SIZE + data.len()) as u32).to_le_bytes());
+        dst.push(KIND_CHUNK);
+        dst.extend_from_slice(&stream.id.to_le_bytes());
+        dst.extend_from_slice(data);
+
+        stream.position = end;
+        if stream.position == stream.data.len() {
+            self.queue.pop_front();
+        }
+    }
+}
+
+struct IncomingStream {
+    id: u64,
+    size: usize,
+    received: usize,
+    body: StreamBody,
+}
+
+enum StreamBody {
+    Collecting(Vec<u8>),
+    /// The envelope exceeds the limit, so its chunks are discarded.
+    Discarding(Option<EnvelopeDetails>),
+}
+
+/// Collects chunks of large envelopes.
+///
+/// Chunks of one envelope are written sequentially by `Chunker`, so only one
+/// envelope is reassembled at a time.
+pub(crate) struct Reassembler {
+    current: Option<IncomingStream>,
+    max_envelope_size: usize,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self {
+            current: None,
+            max_envelope_size: DEFAULT_MAX_ENVELOPE_SIZE,
+        }
+    }
+}
+
+impl Reassembler {
+    /// Sets the maximum size of chunked envelopes, chunks of larger ones are
+    /// discarded and the envelope is skipped.
+    pub(crate) fn set_max_envelope_size(&mut self, max_envelope_size: usize) {
+        self.max_envelope_size = max_envelope_size;
+    }
+
+    /// Like `decode::decode()`, but also handles chunks. Returns
+    /// `DecodeState::Chunk` while the envelope isn't complete.
+    pub(crate) fn decode(&mut self, input: &[u8], stats: &mut DecodeStats) -> Result<DecodeState> {
+        let bytes_consumed = match decode::decode(input, stats)? {
+            DecodeState::Chunk { bytes_consumed } => bytes_consumed,
+            state => return Ok(state),
+        };
+
+        ensure!(bytes_consumed >= CHUNK_HEADER_SIZE, "invalid chunk header");
+        let id = u64::from_le_bytes(input[5..CHUNK_HEADER_SIZE].try_into().unwrap());
+        let data = &input[CHUNK_HEADER_SIZE..bytes_consumed];
+
+        let stream = match &mut self.current {
+            Some(stream) if stream.id == id => stream,
+            Some(stream) => bail!("chunks of streams {} and {id} are interleaved", stream.id),
+            None => {
+                // The first chunk starts with the size of the whole envelope.
+                ensure!(data.len() >= 4, "invalid first chunk");
+                let size = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+
+                let body = if size <= self.max_envelope_size {
+                    // The size is declared by the peer, so the buffer grows as
+                    // chunks arrive instead of being allocated at once.
+                    StreamBody::Collecting(Vec::new())
+                } else {
+                    StreamBody::Discarding(discard(data, size, self.max_envelope_size))
+                };
+
+                self.current.insert(IncomingStream {
+                    id,
+                    size,
+                    received: 0,
+                    body,
+                })
+            }
+        };
+
+        ensure!(
+            stream.received + data.len() <= stream.size,
+            "chunks exceed the envelope size"
+        );
+        stream.received += data.len();
+
+        if let StreamBody::Collecting(buffer) = &mut stream.body {
+            buffer.extend_from_slice(data);
+        }
+
+        if stream.received < stream.size {
+            return Ok(DecodeState::Chunk { bytes_consumed });
+        }
+
+        let data = match self.current.take().unwrap().body {
+            StreamBody::Collecting(data) => data,
+            StreamBody::Discarding(details) => {
+                stats.total_messages_decoding_skipped += 1;
+                return Ok(DecodeState::Skipped {
+                    bytes_consumed,
+                    details,
+                });
+            }
+        };
+
+        Ok(match decode::decode(&data, stats)? {
+            DecodeState::Done { decoded, .. } => DecodeState::Done {
+                bytes_consumed,
+                decoded,
+            },
+            DecodeState::Skipped { details, .. } => DecodeState::Skipped {
+                bytes_consumed,
+                details,
+            },
+            DecodeState::NeedMoreData { .. } | DecodeState::Chunk { .. } => {
+                bail!("invalid chunked envelope")
+            }
+        })
+    }
+}
+
+/// Logs the oversized envelope, which is skipped once all its chunks arrive.
+fn discard(first_chunk: &[u8], size: usize, limit: usize) -> Option<EnvelopeDetails> {
+    counter!("elfo_network_oversized_envelopes_total", 1);
+
+    let details = decode::decode_details(first_chunk);
+    if let Some(details) = &details {
+        error!(
+            message = "received envelope exceeds the limit, skipping",
+            size,
+            limit,
+            kind = ?details.kind,
+            sender = %details.sender,
+            recipient = %details.recipient,
+            request_id = ?details.request_id,
+            trace_id = %details.trace_id,
+        );
+    } else {
+        error!(
+            message = "received envelope exceeds the limit, skipping",
+            size, limit,
+        );
+    }
+
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_chunk(id: u64, data: &[u8]) -> Vec<u8> {
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&((CHUNK_HEADER_SIZE + data.len()) as u32).to_le_bytes());
+        chunk.push(KIND_CHUNK);
+        chunk.extend_from_slice(&id.to_le_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    #[test]
+    fn oversized() {
+        let mut reassembler = Reassembler::default();
+        reassembler.set_max_envelope_size(1024);
+        let mut stats = DecodeStats::default();
+
+        // The first chunk starts with the declared size of the envelope.
+        let mut data = 2000u32.to_le_bytes().to_vec();
+        data.resize(1500, 0);
+        let state = reassembler
+            .decode(&make_chunk(0, &data), &mut stats)
+            .unwrap();
+        assert!(matches!(
+            state,
+            DecodeState::Chunk {
+                bytes_consumed: 1513
+            }
+        ));
+
+        // Chunks are discarded until the end of the envelope.
+        let state = reassembler
+            .decode(&make_chunk(0, &[0; 500]), &mut stats)
+            .unwrap();
+        assert!(matches!(
+            state,
+            DecodeState::Skipped {
+                bytes_consumed: 513,
+                details: None
+            }
+        ));
+        assert_eq!(stats.total_messages_decoding_skipped, 1);
+
+        // Next envelopes are accepted.
+        let chunk = make_chunk(1, &1024u32.to_le_bytes());
+        let state = reassembler.decode(&chunk, &mut stats).unwrap();
+        assert!(matches!(state, DecodeState::Chunk { bytes_consumed: 17 }));
+    }
+
+    #[test]
+    fn oversized_outgoing() {
+        let mut chunker = Chunker::default();
+        chunker.set_max_envelope_size(MAX_CHUNK_SIZE * 2);
+
+        let mut buffer = vec![1; 10];
+        buffer.resize(10 + MAX_CHUNK_SIZE * 2 + 1, 0);
+        let err = chunker.take_large(&mut buffer, 10, 64).unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit"));
+        assert_eq!(buffer, vec![1; 10]);
+        assert!(chunker.is_empty());
+
+        buffer.resize(10 + MAX_CHUNK_SIZE * 2, 0);
+        assert!(chunker.take_large(&mut buffer, 10, 64).unwrap());
+        assert!(!chunker.is_empty());
+    }
+}
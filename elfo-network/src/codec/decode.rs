@@ -2,17 +2,19 @@ use std::{convert::TryFrom, io::Cursor};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use eyre::{ensure, eyre, Error, WrapErr};
+use metrics::counter;
 use tracing::error;
 
 use elfo_core::{
     _priv::{AnyMessage, RequestId},
     errors::RequestError,
-    tracing::TraceId,
+    tracing::{Baggage, MessageId, TraceId},
 };
 use elfo_utils::likely;
 
 use crate::codec::format::{
-    NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, FLAG_IS_LAST_RESPONSE, KIND_MASK,
+    NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, FLAG_HAS_BAGGAGE, FLAG_HAS_MESSAGE_IDS,
+    FLAG_HAS_SENT_TIME, FLAG_HAS_VERSION, FLAG_IS_LAST_RESPONSE, KIND_CHUNK, KIND_MASK,
     KIND_REGULAR, KIND_REQUEST_ALL, KIND_REQUEST_ANY, KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED,
     KIND_RESPONSE_OK,
 };
@@ -32,8 +34,21 @@ pub(crate) struct EnvelopeDetails {
     pub(crate) recipient: NetworkAddr,
     pub(crate) request_id: Option<RequestId>,
     pub(crate) trace_id: TraceId,
+    /// Set if the message is unknown to this node, e.g. added in a newer
+    /// version of the remote node.
+    pub(crate) unknown: Option<Box<UnknownMessage>>,
 }
 
+#[derive(Debug)]
+pub(crate) struct UnknownMessage {
+    pub(crate) protocol: String,
+    pub(crate) name: String,
+    pub(crate) version: u8,
+    /// The message encoded as msgpack.
+    pub(crate) payload: Vec<u8>,
+}
+
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum DecodeState {
     /// Buffer needs to contain at least `total_length_estimate` bytes in total
     /// in order for the decoder to make progress.
@@ -50,6 +65,9 @@ pub(crate) enum DecodeState {
         bytes_consumed: usize,
         decoded: NetworkEnvelope,
     },
+    /// Decoder consumed a chunk of a large envelope, which occupied
+    /// `bytes_consumed` bytes in the buffer. See `chunk::Reassembler`.
+    Chunk { bytes_consumed: usize },
 }
 
 pub(crate) fn decode(input: &[u8], stats: &mut DecodeStats) -> eyre::Result<DecodeState> {
@@ -68,6 +86,13 @@ pub(crate) fn decode(input: &[u8], stats: &mut DecodeStats) -> eyre::Result<Deco
         });
     }
 
+    // Chunks are handled by `chunk::Reassembler`.
+    if size > 4 && input[4] & KIND_MASK == KIND_CHUNK {
+        return Ok(DecodeState::Chunk {
+            bytes_consumed: size,
+        });
+    }
+
     let decode_result = do_decode(&mut src);
     if likely(decode_result.is_ok()) {
         stats.total_messages_decoded += 1;
@@ -80,7 +105,24 @@ pub(crate) fn decode(input: &[u8], stats: &mut DecodeStats) -> eyre::Result<Deco
     stats.total_messages_decoding_skipped += 1;
 
     // TODO: cooldown/metrics.
-    let DecodeError { message, details } = decode_result.unwrap_err();
+    let DecodeError {
+        mut message,
+        mut details,
+    } = decode_result.unwrap_err();
+
+    if let Some(unknown) = message.unknown.take() {
+        counter!(
+            "elfo_network_unknown_messages_total",
+            1,
+            "protocol" => unknown.protocol.clone(),
+            "message" => unknown.name.clone(),
+        );
+
+        if let Some(details) = &mut details {
+            details.unknown = Some(unknown);
+        }
+    }
+
     if let Some(details) = &details {
         error!(
             message = "cannot decode message, skipping",
@@ -124,6 +166,7 @@ where
                 protocol: None,
                 name: None,
                 error: value.into(),
+                unknown: None,
             },
             details: None,
         }
@@ -135,6 +178,7 @@ struct MessageDecodeError {
     protocol: Option<String>,
     name: Option<String>,
     error: eyre::Report,
+    unknown: Option<Box<UnknownMessage>>,
 }
 
 impl<T> From<T> for MessageDecodeError
@@ -146,6 +190,7 @@ where
             protocol: None,
             name: None,
             error: value.into(),
+            unknown: None,
         }
     }
 }
@@ -159,7 +204,7 @@ fn get_request_id(frame: &mut Cursor<&[u8]>) -> eyre::Result<RequestId> {
     Ok(RequestId::from_ffi(frame.read_u64::<LittleEndian>()?))
 }
 
-fn get_message(frame: &mut Cursor<&[u8]>) -> Result<AnyMessage, MessageDecodeError> {
+fn get_message(frame: &mut Cursor<&[u8]>, flags: u8) -> Result<AnyMessage, MessageDecodeError> {
     let protocol = get_str(frame).wrap_err("invalid message protocol")?;
     let name = get_str(frame)
         .wrap_err("invalid message name")
@@ -167,30 +212,57 @@ fn get_message(frame: &mut Cursor<&[u8]>) -> Result<AnyMessage, MessageDecodeErr
             protocol: Some(protocol.to_string()),
             name: None,
             error,
+            unknown: None,
         })?;
 
+    let version = if flags & FLAG_HAS_VERSION != 0 {
+        frame
+            .read_u8()
+            .wrap_err("invalid message version")
+            .map_err(|error| MessageDecodeError {
+                protocol: Some(protocol.to_string()),
+                name: Some(name.to_string()),
+                error,
+                unknown: None,
+            })?
+    } else {
+        1
+    };
+
     // TODO: replace with `Cursor::remaining_slice` once it becomes stable.
     let position = frame.position() as usize;
     let remaining_slice = &frame.get_ref()[position..];
 
-    let result = AnyMessage::read_msgpack(remaining_slice, protocol, name).map_err(|error| {
-        MessageDecodeError {
-            protocol: Some(protocol.to_string()),
-            name: Some(name.to_string()),
-            error: error.into(),
-        }
-    })?;
+    let result =
+        AnyMessage::read_msgpack(remaining_slice, protocol, name, version).map_err(|error| {
+            MessageDecodeError {
+                protocol: Some(protocol.to_string()),
+                name: Some(name.to_string()),
+                error: error.into(),
+                unknown: None,
+            }
+        })?;
     frame.set_position(frame.get_ref().len() as u64);
 
     result.ok_or_else(|| MessageDecodeError {
         protocol: Some(protocol.to_string()),
         name: Some(name.to_string()),
         error: eyre!("unknown message"),
+        unknown: Some(Box::new(UnknownMessage {
+            protocol: protocol.to_string(),
+            name: name.to_string(),
+            version,
+            payload: remaining_slice.to_vec(),
+        })),
     })
 }
 
 fn get_str<'a>(frame: &mut Cursor<&'a [u8]>) -> eyre::Result<&'a str> {
     let len = frame.read_u8()? as usize;
+    get_str_of_len(frame, len)
+}
+
+fn get_str_of_len<'a>(frame: &mut Cursor<&'a [u8]>, len: usize) -> eyre::Result<&'a str> {
     let string_end = frame.position() as usize + len;
 
     // TODO: It's not enough, still can fail if `len` is wrong.
@@ -202,13 +274,96 @@ fn get_str<'a>(frame: &mut Cursor<&'a [u8]>) -> eyre::Result<&'a str> {
     Ok(decoded_string)
 }
 
-fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError> {
+/// Decodes details of the envelope from its beginning, which is enough to
+/// skip it, e.g. if only the first chunk of a large envelope is available.
+pub(crate) fn decode_details(input: &[u8]) -> Option<EnvelopeDetails> {
+    let mut frame = Cursor::new(input);
+    frame.set_position(4); // size
+
+    let header = get_header(&mut frame).ok()?;
+    let request_id = match header.kind {
+        KIND_REGULAR => None,
+        KIND_REQUEST_ANY
+        | KIND_REQUEST_ALL
+        | KIND_RESPONSE_OK
+        | KIND_RESPONSE_FAILED
+        | KIND_RESPONSE_IGNORED => Some(get_request_id(&mut frame).ok()?),
+        _ => return None,
+    };
+
+    Some(EnvelopeDetails {
+        kind: header.kind,
+        sender: header.sender,
+        recipient: header.recipient,
+        request_id,
+        trace_id: header.trace_id,
+        unknown: None,
+    })
+}
+
+/// Fields preceding the kind-specific part of the envelope.
+struct Header {
+    flags: u8,
+    kind: u8,
+    sender: NetworkAddr,
+    recipient: NetworkAddr,
+    trace_id: TraceId,
+    sent_time: Option<u64>,
+    message_id: Option<MessageId>,
+    parent_id: Option<MessageId>,
+    baggage: Baggage,
+}
+
+fn get_header(frame: &mut Cursor<&[u8]>) -> eyre::Result<Header> {
     let flags = frame.read_u8()?;
     let kind = flags & KIND_MASK;
 
     let sender = get_addr(frame)?;
     let recipient = get_addr(frame)?;
     let trace_id = TraceId::try_from(frame.read_u64::<LittleEndian>()?)?;
+    let sent_time = if flags & FLAG_HAS_SENT_TIME != 0 {
+        Some(frame.read_u64::<LittleEndian>()?)
+    } else {
+        None
+    };
+    let (message_id, parent_id) = if flags & FLAG_HAS_MESSAGE_IDS != 0 {
+        let message_id = MessageId::try_from(frame.read_u64::<LittleEndian>()?)?;
+        let parent_id = MessageId::try_from(frame.read_u64::<LittleEndian>()?).ok();
+        (Some(message_id), parent_id)
+    } else {
+        (None, None)
+    };
+    let baggage = if flags & FLAG_HAS_BAGGAGE != 0 {
+        get_baggage(frame)?
+    } else {
+        Baggage::default()
+    };
+
+    Ok(Header {
+        flags,
+        kind,
+        sender,
+        recipient,
+        trace_id,
+        sent_time,
+        message_id,
+        parent_id,
+        baggage,
+    })
+}
+
+fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError> {
+    let Header {
+        flags,
+        kind,
+        sender,
+        recipient,
+        trace_id,
+        sent_time,
+        message_id,
+        parent_id,
+        baggage,
+    } = get_header(frame)?;
 
     let map_decode_error = |result: Result<AnyMessage, MessageDecodeError>,
                             request_id: Option<RequestId>|
@@ -221,6 +376,7 @@ fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError>
                 recipient,
                 request_id,
                 trace_id,
+                unknown: None,
             }),
         })
     };
@@ -228,27 +384,30 @@ fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError>
     use NetworkEnvelopePayload::*;
     let payload = match kind {
         KIND_REGULAR => Regular {
-            message: map_decode_error(get_message(frame), None)?,
+            message: map_decode_error(get_message(frame, flags), None)?,
         },
         KIND_REQUEST_ANY => {
             let request_id = get_request_id(frame)?;
             RequestAny {
                 request_id,
-                message: map_decode_error(get_message(frame), Some(request_id))?,
+                message: map_decode_error(get_message(frame, flags), Some(request_id))?,
             }
         }
         KIND_REQUEST_ALL => {
             let request_id = get_request_id(frame)?;
             RequestAll {
                 request_id,
-                message: map_decode_error(get_message(frame), Some(request_id))?,
+                message: map_decode_error(get_message(frame, flags), Some(request_id))?,
             }
         }
         KIND_RESPONSE_OK => {
             let request_id = get_request_id(frame)?;
             Response {
                 request_id,
-                message: Ok(map_decode_error(get_message(frame), Some(request_id))?),
+                message: Ok(map_decode_error(
+                    get_message(frame, flags),
+                    Some(request_id),
+                )?),
                 is_last: flags & FLAG_IS_LAST_RESPONSE != 0,
             }
         }
@@ -269,6 +428,27 @@ fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError>
         sender,
         recipient,
         trace_id,
+        message_id,
+        parent_id,
+        sent_time,
+        baggage,
+        // The envelope is addressed to this node, which supports versions.
+        versions: true,
         payload,
     })
 }
+
+fn get_baggage(frame: &mut Cursor<&[u8]>) -> eyre::Result<Baggage> {
+    let count = frame.read_u8()?;
+
+    (0..count)
+        .map(|_| {
+            let key = get_str(frame)?;
+            let len = frame.read_u16::<LittleEndian>()? as usize;
+            let value = get_str_of_len(frame, len)?;
+            Ok((key, value))
+        })
+        .collect::<eyre::Result<Vec<_>>>()
+        .map(Baggage::from_iter)
+        .wrap_err("invalid baggage")
+}
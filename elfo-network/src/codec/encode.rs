@@ -1,12 +1,14 @@
 use byteorder::{LittleEndian, WriteBytesExt};
 use derive_more::{Display, From};
+use eyre::ensure;
 use tracing::error;
 
-use elfo_core::{errors::RequestError, scope, Message};
+use elfo_core::{errors::RequestError, scope, tracing::Baggage, Message};
 use elfo_utils::likely;
 
 use crate::codec::format::{
-    NetworkEnvelope, NetworkEnvelopePayload, FLAG_IS_LAST_RESPONSE, KIND_REGULAR, KIND_REQUEST_ALL,
+    NetworkEnvelope, NetworkEnvelopePayload, FLAG_HAS_BAGGAGE, FLAG_HAS_MESSAGE_IDS,
+    FLAG_HAS_SENT_TIME, FLAG_HAS_VERSION, FLAG_IS_LAST_RESPONSE, KIND_REGULAR, KIND_REQUEST_ALL,
     KIND_REQUEST_ANY, KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED, KIND_RESPONSE_OK,
 };
 
@@ -92,8 +94,10 @@ fn do_encode(
             *is_last,
             match &message {
                 Ok(_) => KIND_RESPONSE_OK,
-                Err(RequestError::Failed) => KIND_RESPONSE_FAILED,
                 Err(RequestError::Ignored) => KIND_RESPONSE_IGNORED,
+                // Other errors are produced locally by the requester,
+                // so they shouldn't be sent, but they are failures anyway.
+                Err(_) => KIND_RESPONSE_FAILED,
             },
             Some(*request_id),
             message.as_ref().ok(),
@@ -105,6 +109,23 @@ fn do_encode(
     if is_last_response {
         flags |= FLAG_IS_LAST_RESPONSE;
     }
+    let version = message.map_or(1, |message| message.version());
+    if version != 1 {
+        ensure!(
+            envelope.versions,
+            "the peer doesn't support message versions, only the first one"
+        );
+        flags |= FLAG_HAS_VERSION;
+    }
+    if envelope.sent_time.is_some() {
+        flags |= FLAG_HAS_SENT_TIME;
+    }
+    if !envelope.baggage.is_empty() {
+        flags |= FLAG_HAS_BAGGAGE;
+    }
+    if envelope.message_id.is_some() {
+        flags |= FLAG_HAS_MESSAGE_IDS;
+    }
     dst.write_u8(flags | kind)?;
 
     // sender
@@ -116,6 +137,22 @@ fn do_encode(
     // trace_id
     dst.write_u64::<LittleEndian>(u64::from(envelope.trace_id))?;
 
+    // sent_time
+    if let Some(sent_time) = envelope.sent_time {
+        dst.write_u64::<LittleEndian>(sent_time)?;
+    }
+
+    // message_id and parent_id
+    if let Some(message_id) = envelope.message_id {
+        dst.write_u64::<LittleEndian>(message_id.into())?;
+        dst.write_u64::<LittleEndian>(envelope.parent_id.map_or(0, u64::from))?;
+    }
+
+    // baggage
+    if !envelope.baggage.is_empty() {
+        put_baggage(&envelope.baggage, dst)?;
+    }
+
     // request_id
     if let Some(request_id) = request_id {
         dst.write_u64::<LittleEndian>(request_id.to_ffi())?;
@@ -136,6 +173,11 @@ fn do_encode(
         // name
         put_str(message.name())?;
 
+        // version
+        if version != 1 {
+            dst.write_u8(version)?;
+        }
+
         // message
         let max_limit = u32::MAX as usize - (dst.len() - start_pos);
         let limit = limit.map_or(max_limit, |limit| limit.min(max_limit));
@@ -147,3 +189,20 @@ fn do_encode(
 
     Ok(())
 }
+
+fn put_baggage(baggage: &Baggage, dst: &mut Vec<u8>) -> eyre::Result<()> {
+    ensure!(baggage.len() <= 255, "too many baggage entries");
+    dst.write_u8(baggage.len() as u8)?;
+
+    for (key, value) in baggage.iter() {
+        ensure!(key.len() <= 255, "too long baggage key");
+        ensure!(value.len() <= 65535, "too long baggage value");
+
+        dst.write_u8(key.len() as u8)?;
+        dst.extend_from_slice(key.as_bytes());
+        dst.write_u16::<LittleEndian>(value.len() as u16)?;
+        dst.extend_from_slice(value.as_bytes());
+    }
+
+    Ok(())
+}
@@ -6,27 +6,79 @@
 //! | size of whole frame   | 32 |                     |
 //! +-----------------------+----+                     |
 //! | flags                 |  4 |                     | flags:
-//! +-----------------------+----+                     | - <reserved>       = 1
-//! | kind                  |  4 |                     | - <reserved>       = 2
-//! +-----------------------+----+       always        | - <reserved>       = 4
+//! +-----------------------+----+                     | - has version      = 1
+//! | kind                  |  4 |                     | - has sent time    = 2
+//! +-----------------------+----+       always        | - has baggage      = 4
 //! | sender                | 64 |                     | - is last response = 8
 //! +-----------------------+----+                     |
-//! | recipient             | 64 |                     |
-//! +-----------------------+----+                     |
-//! | trace id              | 64 |                     | kinds:
-//! +-----------------------+----+---------------------+ - Regular           = 0
-//! | request id            | 64 | if kind != Regular  | - RequestAny        = 1
+//! | recipient             | 64 |                     | kinds:
+//! +-----------------------+----+                     | - Regular           = 0
+//! | trace id              | 64 |                     | - RequestAny        = 1
 //! +-----------------------+----+---------------------+ - RequestAll        = 2
-//! | protocol's length (P) |  8 |                     | - Response::Ok      = 3
-//! +-----------------------+----+                     | - Response::Failed  = 4
-//! | protocol              | 8P |                     | - Response::Ignored = 5
+//! | sent time             | 64 | if has sent time    | - Response::Ok      = 3
+//! +-----------------------+----+---------------------+ - Response::Failed  = 4
+//! | message id            | 64 |                     | - Response::Ignored = 5
+//! +-----------------------+----+ if has message ids  |
+//! | parent message id     | 64 |                     |
+//! +-----------------------+----+---------------------+
+//! | baggage               | *  | if has baggage      |
+//! +-----------------------+----+---------------------+
+//! | request id            | 64 | if kind != Regular  |
+//! +-----------------------+----+---------------------+
+//! | protocol's length (P) |  8 |                     |
+//! +-----------------------+----+                     |
+//! | protocol              | 8P |                     |
 //! +-----------------------+----+ if kind !=          |
 //! | msg name's length (N) |  8 | - Response::Failed  |
 //! +-----------------------+----+ - Response::Ignored |
 //! | msg name              | 8N |                     |
-//! +-----------------------+----+                     |
-//! | msg payload           |rest|                     |
 //! +-----------------------+----+---------------------+
+//! | msg version           |  8 | if has version      |
+//! +-----------------------+----+---------------------+
+//! | msg payload           |rest| if kind !=          |
+//! |                       |    | - Response::Failed  |
+//! |                       |    | - Response::Ignored |
+//! +-----------------------+----+---------------------+
+//!
+//! Envelopes larger than `chunk::MAX_CHUNK_SIZE` are sent in chunks if the
+//! peer has announced support of it. Each chunk is a separate frame:
+//!           name           bits
+//! +-----------------------+----+
+//! | size of whole frame   | 32 |
+//! +-----------------------+----+
+//! | flags (always zero)   |  4 |
+//! +-----------------------+----+
+//! | kind (always Chunk=6) |  4 |
+//! +-----------------------+----+
+//! | stream id             | 64 |
+//! +-----------------------+----+
+//! | data                  |rest|
+//! +-----------------------+----+
+//!
+//! Data of all chunks with the same stream id form the original envelope,
+//! including its size, which is used to detect the last chunk.
+//!
+//! Chunking only keeps frames small: the whole envelope is still encoded into
+//! and reassembled in one contiguous buffer. Thus, envelopes larger than the
+//! configured `max_envelope_size` aren't sent, and the receiver discards
+//! chunks of envelopes with a larger declared size and skips them.
+//!
+//! The version is sent only if the peer has announced support of it, and it's
+//! omitted for messages of the first version anyway. Thus, nodes without
+//! versioning support can still communicate using such messages, but other
+//! versions are skipped, because the peer cannot decode them.
+//!
+//! The sent time is the unix time in nanoseconds according to the sender's
+//! clock. It's sent only if the peer has announced support of it.
+//!
+//! There are no free flags, so the highest bit of the kind is used as the
+//! "has message ids" flag. Message ids are sent only if the peer has announced
+//! support of them. The parent message id is zero if there is no parent.
+//!
+//! The baggage is sent only if it's non-empty and the peer has announced
+//! support of it. It's encoded as the number of entries (8 bits) followed by
+//! entries, each one is the key's length (8 bits), the key, the value's length
+//! (16 bits) and the value.
 //!
 //! All fields are encoded using LE ordering.
 
@@ -35,29 +87,45 @@
 use derive_more::Display;
 
 use elfo_core::{
+    _priv::{AnyMessage, NodeNo, RequestId},
     errors::RequestError,
-    tracing::TraceId,
+    tracing::{Baggage, MessageId, TraceId},
     Addr, Message,
-    _priv::{AnyMessage, NodeNo, RequestId},
 };
 use elfo_utils::likely;
 
 // Flags are shifted by 4 bits to the left because of the kind.
+pub(crate) const FLAG_HAS_VERSION: u8 = 1 << 4;
+pub(crate) const FLAG_HAS_SENT_TIME: u8 = 1 << 5;
+pub(crate) const FLAG_HAS_BAGGAGE: u8 = 1 << 6;
+// Kinds fit into 3 bits, so the highest bit of the kind is used as a flag.
+pub(crate) const FLAG_HAS_MESSAGE_IDS: u8 = 1 << 3;
 pub(crate) const FLAG_IS_LAST_RESPONSE: u8 = 1 << 7;
 
-pub(crate) const KIND_MASK: u8 = 0xF;
+pub(crate) const KIND_MASK: u8 = 0x7;
 pub(crate) const KIND_REGULAR: u8 = 0;
 pub(crate) const KIND_REQUEST_ANY: u8 = 1;
 pub(crate) const KIND_REQUEST_ALL: u8 = 2;
 pub(crate) const KIND_RESPONSE_OK: u8 = 3;
 pub(crate) const KIND_RESPONSE_FAILED: u8 = 4;
 pub(crate) const KIND_RESPONSE_IGNORED: u8 = 5;
+pub(crate) const KIND_CHUNK: u8 = 6;
 
 #[derive(Debug)]
 pub(crate) struct NetworkEnvelope {
     pub(crate) sender: NetworkAddr,
     pub(crate) recipient: NetworkAddr,
     pub(crate) trace_id: TraceId,
+    /// `None` if the peer doesn't support message ids.
+    pub(crate) message_id: Option<MessageId>,
+    pub(crate) parent_id: Option<MessageId>,
+    /// The unix time in nanoseconds when the message was sent.
+    pub(crate) sent_time: Option<u64>,
+    /// Empty if the peer doesn't support baggage.
+    pub(crate) baggage: Baggage,
+    /// `false` if the peer doesn't support message versions, then only
+    /// messages of the first version can be sent.
+    pub(crate) versions: bool,
     pub(crate) payload: NetworkEnvelopePayload,
 }
 
@@ -155,14 +223,14 @@ impl NetworkEnvelopePayload {
                 message: Ok(message),
                 ..
             } => (message.protocol(), message.name()),
-            Self::Response {
-                message: Err(RequestError::Failed),
-                ..
-            } => ("", "RequestError::Failed"),
             Self::Response {
                 message: Err(RequestError::Ignored),
                 ..
             } => ("", "RequestError::Ignored"),
+            // Other errors are sent as `Failed`, see the encoder.
+            Self::Response {
+                message: Err(_), ..
+            } => ("", "RequestError::Failed"),
         }
     }
 }
@@ -1,16 +1,25 @@
+pub(crate) mod chunk;
 pub(crate) mod decode;
 pub(crate) mod encode;
 pub(crate) mod format;
 
 #[cfg(test)]
 mod tests {
-    use elfo_core::{message, tracing::TraceId, Message, _priv::AnyMessage};
+    use elfo_core::{
+        _priv::{rmps, AnyMessage},
+        message,
+        tracing::{Baggage, MessageId, TraceId},
+        Message,
+    };
     use std::convert::TryFrom;
 
     use super::{
         decode::{decode, DecodeState},
         encode::{encode, EncodeError},
-        format::{NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload},
+        format::{
+            NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, FLAG_HAS_BAGGAGE,
+            FLAG_HAS_MESSAGE_IDS, FLAG_HAS_SENT_TIME, FLAG_HAS_VERSION,
+        },
     };
 
     #[message]
@@ -26,6 +35,11 @@ mod tests {
             sender: NetworkAddr::NULL,
             recipient: NetworkAddr::NULL,
             trace_id: TraceId::try_from(trace_index).unwrap(),
+            message_id: None,
+            parent_id: None,
+            sent_time: None,
+            baggage: Baggage::default(),
+            versions: true,
             payload: NetworkEnvelopePayload::Regular { message },
         }
     }
@@ -76,6 +90,7 @@ mod tests {
                 DecodeState::NeedMoreData { .. } => {
                     panic!("decoder requested more data that there was available")
                 }
+                DecodeState::Chunk { .. } => panic!("unexpected chunk"),
                 DecodeState::Done {
                     bytes_consumed,
                     decoded,
@@ -147,5 +162,240 @@ mod tests {
         }
     }
 
+    #[message(part, version = 1)]
+    struct PointV1 {
+        x: u32,
+    }
+
+    #[message(part, version = 2, migrates_from = PointV1)]
+    struct PointV2 {
+        x: u32,
+        y: u32,
+    }
+
+    impl From<PointV1> for PointV2 {
+        fn from(old: PointV1) -> Self {
+            Self { x: old.x, y: 0 }
+        }
+    }
+
+    #[message(version = 3, migrates_from = PointV2)]
+    #[derive(PartialEq)]
+    struct Point {
+        x: u32,
+        y: u32,
+        z: u32,
+    }
+
+    impl From<PointV2> for Point {
+        fn from(old: PointV2) -> Self {
+            Self {
+                x: old.x,
+                y: old.y,
+                z: 0,
+            }
+        }
+    }
+
+    // Emulates a regular message sent by a peer with another version.
+    fn make_frame(version: u8, payload: &impl serde::Serialize) -> Vec<u8> {
+        make_named_frame("Point", version, payload)
+    }
+
+    fn make_named_frame(name: &str, version: u8, payload: &impl serde::Serialize) -> Vec<u8> {
+        let mut frame = vec![0; 4];
+        frame.push(FLAG_HAS_VERSION);
+        frame.extend_from_slice(&NetworkAddr::NULL.into_bits().to_le_bytes());
+        frame.extend_from_slice(&NetworkAddr::NULL.into_bits().to_le_bytes());
+        frame.extend_from_slice(&1u64.to_le_bytes());
+        for s in ["elfo-network", name] {
+            frame.push(s.len() as u8);
+            frame.extend_from_slice(s.as_bytes());
+        }
+        frame.push(version);
+        frame.extend_from_slice(&rmps::to_vec_named(payload).unwrap());
+
+        let size = frame.len() as u32;
+        frame[..4].copy_from_slice(&size.to_le_bytes());
+        frame
+    }
+
+    fn decode_point(bytes: &[u8]) -> Option<Point> {
+        match decode(bytes, &mut Default::default()).unwrap() {
+            DecodeState::Done { decoded, .. } => match decoded.payload {
+                NetworkEnvelopePayload::Regular { message } => message.downcast().ok(),
+                _ => panic!("expected a regular message"),
+            },
+            DecodeState::Skipped { .. } => None,
+            DecodeState::NeedMoreData { .. } => panic!("unexpected end of data"),
+            DecodeState::Chunk { .. } => panic!("unexpected chunk"),
+        }
+    }
+
+    #[test]
+    fn versioned() {
+        let point = Point { x: 1, y: 2, z: 3 };
+
+        // The current version.
+        let mut bytes = Vec::new();
+        let envelope = make_envelope(point.clone().upcast(), 1);
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_ne!(bytes[4] & FLAG_HAS_VERSION, 0);
+        assert_eq!(decode_point(&bytes), Some(point.clone()));
+        assert_eq!(decode_point(&make_frame(3, &point)), Some(point));
+
+        // Older versions are migrated.
+        let v2 = PointV2 { x: 1, y: 2 };
+        let expected = Point { x: 1, y: 2, z: 0 };
+        assert_eq!(decode_point(&make_frame(2, &v2)), Some(expected));
+
+        let v1 = PointV1 { x: 1 };
+        let expected = Point { x: 1, y: 0, z: 0 };
+        assert_eq!(decode_point(&make_frame(1, &v1)), Some(expected));
+
+        // Newer versions are skipped.
+        assert_eq!(decode_point(&make_frame(4, &v1)), None);
+
+        // Messages of the first version are encoded without the version.
+        let mut bytes = Vec::new();
+        let envelope = make_envelope(SmallMessage(1).upcast(), 1);
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_eq!(bytes[4] & FLAG_HAS_VERSION, 0);
+    }
+
+    #[test]
+    fn versions_unsupported_by_peer() {
+        // Messages of the first version are sent as is.
+        let mut envelope = make_envelope(SmallMessage(1).upcast(), 1);
+        envelope.versions = false;
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_eq!(bytes[4] & FLAG_HAS_VERSION, 0);
+
+        // Other versions cannot be decoded by the peer, so they're skipped.
+        let mut envelope = make_envelope(Point { x: 1, y: 2, z: 3 }.upcast(), 1);
+        envelope.versions = false;
+        let mut bytes = Vec::new();
+        let mut stats = Default::default();
+        let result = encode(&envelope, &mut bytes, &mut stats, None);
+        assert!(matches!(result, Err(EncodeError::Skipped)));
+        assert!(bytes.is_empty());
+        assert_eq!(stats.total_messages_encoding_skipped, 1);
+    }
+
+    #[test]
+    fn unknown() {
+        // A message added in a newer version of the remote node.
+        let point = Point { x: 1, y: 2, z: 3 };
+        let bytes = make_named_frame("NewPoint", 1, &point);
+
+        let state = decode(&bytes, &mut Default::default()).unwrap();
+        let DecodeState::Skipped {
+            bytes_consumed,
+            details: Some(details),
+        } = state
+        else {
+            panic!("expected the message to be skipped");
+        };
+        assert_eq!(bytes_consumed, bytes.len());
+
+        let unknown = details.unknown.expect("unknown message is missing");
+        assert_eq!(unknown.protocol, "elfo-network");
+        assert_eq!(unknown.name, "NewPoint");
+        assert_eq!(unknown.version, 1);
+        assert_eq!(unknown.payload, rmps::to_vec_named(&point).unwrap());
+
+        // Known messages that cannot be decoded aren't reported as unknown.
+        let state = decode(&make_frame(4, &point), &mut Default::default()).unwrap();
+        let DecodeState::Skipped {
+            details: Some(details),
+            ..
+        } = state
+        else {
+            panic!("expected the message to be skipped");
+        };
+        assert!(details.unknown.is_none());
+    }
+
+    #[test]
+    fn sent_time() {
+        let mut envelope = make_envelope(SmallMessage(5).upcast(), 1);
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_eq!(bytes[4] & FLAG_HAS_SENT_TIME, 0);
+        let size_without = bytes.len();
+
+        envelope.sent_time = Some(1_700_000_000_000_000_000);
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_ne!(bytes[4] & FLAG_HAS_SENT_TIME, 0);
+        assert_eq!(bytes.len(), size_without + 8);
+
+        match decode(&bytes, &mut Default::default()).unwrap() {
+            DecodeState::Done { decoded, .. } => {
+                assert_eq!(decoded.sent_time, envelope.sent_time);
+                assert_regular_eq::<SmallMessage>(&decoded, &envelope);
+            }
+            _ => panic!("expected the message to be decoded"),
+        }
+    }
+
+    #[test]
+    fn baggage() {
+        let mut envelope = make_envelope(SmallMessage(5).upcast(), 1);
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_eq!(bytes[4] & FLAG_HAS_BAGGAGE, 0);
+
+        envelope.baggage = Baggage::default()
+            .with("tenant", "acme")
+            .with("locale", "x".repeat(1000));
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_ne!(bytes[4] & FLAG_HAS_BAGGAGE, 0);
+
+        match decode(&bytes, &mut Default::default()).unwrap() {
+            DecodeState::Done { decoded, .. } => {
+                assert_eq!(decoded.baggage, envelope.baggage);
+                assert_regular_eq::<SmallMessage>(&decoded, &envelope);
+            }
+            _ => panic!("expected the message to be decoded"),
+        }
+
+        // Too long values cannot be encoded.
+        envelope.baggage = Baggage::default().with("locale", "x".repeat(70000));
+        let mut bytes = Vec::new();
+        let result = encode(&envelope, &mut bytes, &mut Default::default(), None);
+        assert!(matches!(result, Err(EncodeError::Skipped)));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn message_ids() {
+        let mut envelope = make_envelope(SmallMessage(5).upcast(), 1);
+        let mut bytes = Vec::new();
+        encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+        assert_eq!(bytes[4] & FLAG_HAS_MESSAGE_IDS, 0);
+        let size_without = bytes.len();
+
+        for parent_id in [None, Some(MessageId::try_from(7).unwrap())] {
+            envelope.message_id = Some(MessageId::try_from(42).unwrap());
+            envelope.parent_id = parent_id;
+            let mut bytes = Vec::new();
+            encode(&envelope, &mut bytes, &mut Default::default(), None).unwrap();
+            assert_ne!(bytes[4] & FLAG_HAS_MESSAGE_IDS, 0);
+            assert_eq!(bytes.len(), size_without + 16);
+
+            match decode(&bytes, &mut Default::default()).unwrap() {
+                DecodeState::Done { decoded, .. } => {
+                    assert_eq!(decoded.message_id, envelope.message_id);
+                    assert_eq!(decoded.parent_id, parent_id);
+                    assert_regular_eq::<SmallMessage>(&decoded, &envelope);
+                }
+                _ => panic!("expected the message to be decoded"),
+            }
+        }
+    }
+
     // TODO: test errors (including mismatch node_no).
 }
@@ -1,4 +1,8 @@
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
 
 use derive_more::Display;
 use serde::{
@@ -6,15 +10,89 @@ use serde::{
     Deserialize, Serialize,
 };
 
+use elfo_core::{_priv::NodeNo, config::types::ByteSize};
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
     pub(crate) listen: Vec<Transport>,
+    /// Transports other nodes should use to reach this node instead of
+    /// `listen`, e.g. an external address if the node is behind NAT.
+    /// Either one transport or a list of them.
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    pub(crate) advertise_addr: Vec<Transport>,
+    /// The zone (e.g. an availability zone) of this node.
+    #[serde(default)]
+    pub(crate) zone: Option<String>,
     #[serde(with = "humantime_serde", default = "default_ping_interval")]
     pub(crate) ping_interval: Duration,
+    /// How long to wait for a pong when checking that the peer is reachable.
+    #[serde(with = "humantime_serde", default = "default_reachability_timeout")]
+    pub(crate) reachability_timeout: Duration,
+    /// How many written frame buffers are kept per connection to be reused
+    /// for next frames, `0` disables reusing.
+    #[serde(default = "default_frame_buffer_pool_size")]
+    pub(crate) frame_buffer_pool_size: usize,
+    /// The maximum size of an envelope sent or received in chunks, larger ones
+    /// are skipped and counted by `elfo_network_oversized_envelopes_total`.
+    /// The connection is kept. Applied to new connections.
+    #[serde(default = "default_max_envelope_size")]
+    pub(crate) max_envelope_size: ByteSize,
+    /// Whether messages unknown to this node are sent as dead letters (see
+    /// `elfo_network::messages::UnknownMessage`) instead of only counting them.
+    /// Applied to new connections.
+    #[serde(default)]
+    pub(crate) forward_unknown_messages: bool,
+    /// Whether envelopes received from other nodes are annotated with the
+    /// receive time corrected by the estimated clock offset, see
+    /// `elfo_network::RECEIVED_TIME_KEY`. Applied to new connections.
+    #[serde(default)]
+    pub(crate) annotate_received_time: bool,
     #[serde(default)]
     pub(crate) discovery: DiscoveryConfig, // TODO: optional?
     #[serde(default)]
     pub(crate) compression: CompressionConfig,
+    #[serde(default)]
+    pub(crate) circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub(crate) relay: RelayConfig,
+    /// If specified, messages to disconnected nodes are persisted on disk
+    /// and sent once the connection is restored.
+    #[serde(default)]
+    pub(crate) spool: Option<SpoolConfig>,
+    /// Dumps of messages exchanged with other nodes.
+    #[serde(default)]
+    pub(crate) taps: Vec<TapConfig>,
+    /// Which nodes this node can connect to or accept connections from.
+    #[serde(default)]
+    pub(crate) peers: PeersConfig,
+}
+
+impl Config {
+    /// Transports other nodes should use to reach this node.
+    pub(crate) fn advertised(&self) -> Vec<Transport> {
+        if self.advertise_addr.is_empty() {
+            self.listen.clone()
+        } else {
+            self.advertise_addr.clone()
+        }
+    }
+}
+
+fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Vec<Transport>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Transport),
+        Many(Vec<Transport>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(transport) => vec![transport],
+        OneOrMany::Many(transports) => transports,
+    })
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -30,25 +108,258 @@ pub(crate) enum CompressionAlgorithm {
     None,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct CircuitBreakerConfig {
+    pub(crate) enabled: bool,
+    /// A fraction of failed requests to open the breaker.
+    pub(crate) failure_rate: f64,
+    /// The minimum number of requests in the window to open the breaker.
+    pub(crate) min_requests: u32,
+    /// How long outcomes of requests are counted.
+    #[serde(with = "humantime_serde")]
+    pub(crate) window: Duration,
+    /// Requests without a response for longer are considered failed.
+    #[serde(with = "humantime_serde")]
+    pub(crate) request_timeout: Duration,
+    /// How long requests are rejected before probing.
+    #[serde(with = "humantime_serde")]
+    pub(crate) cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failure_rate: 0.5,
+            min_requests: 20,
+            window: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(5),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct RelayConfig {
+    /// Whether this node forwards connections between peers,
+    /// that cannot reach each other directly.
+    pub(crate) enabled: bool,
+    /// The maximum number of relays between two nodes.
+    pub(crate) max_hops: u8,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_hops: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SpoolConfig {
+    /// A directory for spool files, one per pair of local and remote groups.
+    /// Files are synced to disk after every batch of written messages.
+    pub(crate) path: PathBuf,
+    /// Names of remote groups to spool messages for, all if empty.
+    #[serde(default)]
+    pub(crate) groups: Vec<String>,
+    /// The maximum size of a spool file, new messages are rejected once it's
+    /// reached.
+    #[serde(default = "default_spool_max_size")]
+    pub(crate) max_size: ByteSize,
+    /// How long messages are kept after disconnection.
+    #[serde(with = "humantime_serde", default = "default_spool_ttl")]
+    pub(crate) ttl: Duration,
+}
+
+impl SpoolConfig {
+    pub(crate) fn is_enabled_for(&self, group_name: &str) -> bool {
+        self.groups.is_empty() || self.groups.iter().any(|name| name == group_name)
+    }
+}
+
+fn default_spool_max_size() -> ByteSize {
+    ByteSize::mib(64)
+}
+
+fn default_spool_ttl() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Dumps messages passing the network boundary in the `network` class.
+/// Dumps are made in the scope of the connection's worker, so they contain
+/// the peer in the actor's key.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TapConfig {
+    /// The direction of messages, both if not specified.
+    #[serde(default)]
+    pub(crate) direction: Option<TapDirection>,
+    /// The peer node, all if not specified.
+    #[serde(default)]
+    pub(crate) node_no: Option<NodeNo>,
+    /// Names of messages, all if empty.
+    #[serde(default)]
+    pub(crate) messages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub(crate) enum TapDirection {
+    /// Messages received from the peer.
+    Ingress,
+    /// Messages sent to the peer.
+    Egress,
+}
+
+/// Checked when connecting to a peer and accepting a connection from it.
+/// Established connections to peers denied after reconfiguration are closed.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct PeersConfig {
+    /// If not empty, only peers matching any of the rules are allowed.
+    pub(crate) allow: Vec<PeerRule>,
+    /// Peers matching any of the rules are rejected, even if allowed.
+    pub(crate) deny: Vec<PeerRule>,
+}
+
+/// Matches a peer if all specified conditions are met.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PeerRule {
+    #[serde(default)]
+    pub(crate) node_no: Option<NodeNo>,
+    /// The peer's IP address, e.g. `10.0.0.0/8` or `10.1.2.3`.
+    #[serde(default)]
+    pub(crate) address: Option<Cidr>,
+    /// The name of any group on the peer.
+    #[serde(default)]
+    pub(crate) group: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub(crate) fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len));
+                let mask = mask.unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_len));
+                let mask = mask.unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(deserializer: D) -> Result<Cidr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+
+        parse_cidr(&s)
+            .map_err(|err| de::Error::custom(format!(r#"invalid address: "{}", {}"#, s, err)))
+    }
+}
+
+pub(crate) fn parse_cidr(s: &str) -> Result<Cidr, &'static str> {
+    let (addr, prefix_len) = s.split_once('/').unwrap_or((s, ""));
+    let addr: IpAddr = addr.parse().map_err(|_| "invalid IP address")?;
+    let max_len = if addr.is_ipv4() { 32 } else { 128 };
+
+    let prefix_len = if prefix_len.is_empty() {
+        max_len
+    } else {
+        prefix_len.parse().map_err(|_| "invalid prefix length")?
+    };
+
+    if prefix_len > max_len {
+        return Err("too long prefix");
+    }
+
+    Ok(Cidr { addr, prefix_len })
+}
+
 fn default_ping_interval() -> Duration {
     Duration::from_secs(5)
 }
 
-#[derive(Debug, Deserialize, Default)]
+fn default_reachability_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_frame_buffer_pool_size() -> usize {
+    8
+}
+
+fn default_max_envelope_size() -> ByteSize {
+    ByteSize::b(crate::codec::chunk::DEFAULT_MAX_ENVELOPE_SIZE as u64)
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
 pub(crate) struct DiscoveryConfig {
     pub(crate) predefined: Vec<Transport>,
     #[serde(with = "humantime_serde", default = "default_attempt_interval")]
     pub(crate) attempt_interval: Duration,
+    /// How often routes are requested from relays.
+    #[serde(with = "humantime_serde", default = "default_gossip_interval")]
+    pub(crate) gossip_interval: Duration,
+    /// How long a single connection attempt, including the handshake, can take.
+    #[serde(with = "humantime_serde", default = "default_attempt_timeout")]
+    pub(crate) attempt_timeout: Duration,
+    /// The maximum number of connection attempts in progress.
+    #[serde(default = "default_max_parallel_attempts")]
+    pub(crate) max_parallel_attempts: usize,
+    /// If specified, nodes without connections for longer are forgotten.
+    #[serde(with = "humantime_serde", default)]
+    pub(crate) node_ttl: Option<Duration>,
 }
 
 fn default_attempt_interval() -> Duration {
     Duration::from_secs(60)
 }
 
+fn default_attempt_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_max_parallel_attempts() -> usize {
+    16
+}
+
+fn default_gossip_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Display, Serialize)]
 pub(crate) enum Transport {
     #[display(fmt = "tcp://{}", _0)]
     Tcp(SocketAddr),
+    /// The in-process transport, see the `mem` module.
+    #[display(fmt = "mem://{}", _0)]
+    Mem(String),
+}
+
+impl Transport {
+    /// Returns the IP address, if the transport has one.
+    pub(crate) fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Self::Tcp(addr) => Some(addr.ip()),
+            Self::Mem(_) => None,
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Transport {
@@ -64,7 +375,7 @@ impl<'de> Deserialize<'de> for Transport {
     }
 }
 
-fn parse_transport(s: &str) -> Result<Transport, &'static str> {
+pub(crate) fn parse_transport(s: &str) -> Result<Transport, &'static str> {
     if !s.contains("://") {
         return Err(r#"protocol must be specified (e.g. "tcp://")"#);
     }
@@ -73,7 +384,80 @@ fn parse_transport(s: &str) -> Result<Transport, &'static str> {
         addr.parse()
             .map(Transport::Tcp)
             .map_err(|_| "invalid TCP address")
+    } else if let Some(name) = s.strip_prefix("mem://") {
+        if name.is_empty() {
+            Err("empty name")
+        } else {
+            Ok(Transport::Mem(name.into()))
+        }
     } else {
         Err("unknown protocol")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn advertised() {
+        let config = |advertise_addr| {
+            let mut config = json!({ "listen": ["tcp://0.0.0.0:4242", "mem://a"] });
+            if let Some(advertise_addr) = advertise_addr {
+                config["advertise_addr"] = advertise_addr;
+            }
+            Config::deserialize(config).unwrap().advertised()
+        };
+        let tcp = |addr: &str| Transport::Tcp(addr.parse().unwrap());
+
+        assert_eq!(
+            config(None),
+            [tcp("0.0.0.0:4242"), Transport::Mem("a".into())]
+        );
+        assert_eq!(config(Some(json!("tcp://1.2.3.4:42"))), [tcp("1.2.3.4:42")]);
+        assert_eq!(
+            config(Some(json!(["tcp://1.2.3.4:42", "mem://b"]))),
+            [tcp("1.2.3.4:42"), Transport::Mem("b".into())]
+        );
+    }
+
+    #[test]
+    fn cidr() {
+        let cidr = parse_cidr("10.1.0.0/16").unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("10.2.0.1".parse().unwrap()));
+        assert!(!cidr.contains("::1".parse().unwrap()));
+
+        let cidr = parse_cidr("10.1.2.3").unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("10.1.2.4".parse().unwrap()));
+
+        let cidr = parse_cidr("0.0.0.0/0").unwrap();
+        assert!(cidr.contains("192.168.0.1".parse().unwrap()));
+
+        let cidr = parse_cidr("fd00::/8").unwrap();
+        assert!(cidr.contains("fd12::1".parse().unwrap()));
+        assert!(!cidr.contains("fe80::1".parse().unwrap()));
+
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+        assert!(parse_cidr("10.0.0/8").is_err());
+    }
+
+    #[test]
+    fn transport() {
+        let transport = parse_transport("tcp://127.0.0.1:4242").unwrap();
+        assert_eq!(transport.to_string(), "tcp://127.0.0.1:4242");
+        assert!(transport.ip().is_some());
+
+        let transport = parse_transport("mem://server").unwrap();
+        assert_eq!(transport, Transport::Mem("server".into()));
+        assert_eq!(transport.to_string(), "mem://server");
+        assert!(transport.ip().is_none());
+
+        assert!(parse_transport("mem://").is_err());
+        assert!(parse_transport("udp://127.0.0.1:4242").is_err());
+        assert!(parse_transport("127.0.0.1:4242").is_err());
+    }
+}
@@ -1,30 +1,50 @@
-use std::sync::Arc;
+use std::{
+    future::Future,
+    mem,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
+use arc_swap::ArcSwap;
 use eyre::{bail, eyre, Result, WrapErr};
 use futures::StreamExt;
+use metrics::{counter, decrement_gauge, increment_gauge};
+use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 
 use elfo_core::{
-    message, msg, scope, Envelope, Message, MoveOwnership, RestartPolicy,
-    _priv::{GroupNo, MessageKind},
+    _priv::{GroupNo, MessageKind, NodeNo},
+    message,
     messages::ConfigUpdated,
+    msg, scope,
     stream::Stream,
-    Topology,
+    time::{Delay, Interval},
+    tracing::Baggage,
+    Envelope, Message, MoveOwnership, RestartPolicy, Topology,
 };
 
 use crate::{
     codec::format::{NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload},
-    config::{CompressionAlgorithm, Transport},
-    node_map::{NodeInfo, NodeMap},
-    protocol::{internode, GroupInfo, HandleConnection},
-    socket::{self, ReadError, Socket},
+    config::{self, CompressionAlgorithm, DiscoveryConfig, PeersConfig, Transport},
+    lifecycle::{Failure, Phase},
+    node_map::{NodeInfo, NodeMap, Route},
+    protocol::{internode, CloseConnection, GroupInfo, HandleConnection},
+    socket::{self, Incoming, ReadError, RelayRequest, RelayTarget, Socket, ZoneTraffic},
+    status::{GetNetworkStatus, GetNodeMap, StatusRegistry},
     NetworkContext,
 };
 
+use self::policy::Candidate;
+
+mod policy;
+
 /// Initial window size of every flow.
 /// TODO: should be different for groups and actors.
 const INITIAL_WINDOW_SIZE: i32 = 100_000;
 
+/// The minimum period of checking nodes for eviction.
+const MIN_EVICT_PERIOD: Duration = Duration::from_secs(1);
+
 #[message]
 struct ConnectionEstablished {
     role: ConnectionRole,
@@ -62,9 +82,40 @@ struct ConnectionRejected {
     peer: Transport,
 }
 
+#[message]
+struct RelayRequested {
+    request: MoveOwnership<RelayRequest>,
+}
+
+#[message]
+struct RelayClosed {
+    target: NodeNo,
+    error: Option<String>,
+}
+
+/// Asks the relay for new routes again.
+#[message]
+struct RefreshRoutes {
+    peer: Transport,
+}
+
+#[message]
+struct EvictTick;
+
 pub(super) struct Discovery {
     ctx: NetworkContext,
+    topology: Topology,
     node_map: Arc<NodeMap>,
+    status: Arc<StatusRegistry>,
+    /// Limits the number of connection attempts in progress.
+    /// Replaced if the limit is changed, attempts in progress keep permits
+    /// of the previous semaphore until they're completed.
+    attempts: Arc<ArcSwap<Semaphore>>,
+    max_attempts: usize,
+    /// Shared with connection attempts to check peers before dialing.
+    peers: Arc<ArcSwap<PeersConfig>>,
+    /// Shared with connection attempts to use actual intervals and timeouts.
+    discovery: Arc<ArcSwap<DiscoveryConfig>>,
 }
 
 // TODO: detect duplicate nodes.
@@ -74,10 +125,24 @@ pub(super) struct Discovery {
 // TODO: repeat discovery by timer.
 
 impl Discovery {
-    pub(super) fn new(ctx: NetworkContext, topology: Topology) -> Self {
+    pub(super) fn new(
+        ctx: NetworkContext,
+        topology: Topology,
+        status: Arc<StatusRegistry>,
+    ) -> Self {
+        let max_attempts = ctx.config().discovery.max_parallel_attempts.max(1);
+        let peers = Arc::new(ArcSwap::from_pointee(ctx.config().peers.clone()));
+        let discovery = Arc::new(ArcSwap::from_pointee(ctx.config().discovery.clone()));
+
         Self {
             ctx,
             node_map: Arc::new(NodeMap::new(&topology)),
+            topology,
+            status,
+            attempts: Arc::new(ArcSwap::from_pointee(Semaphore::new(max_attempts))),
+            max_attempts,
+            peers,
+            discovery,
         }
     }
 
@@ -85,12 +150,24 @@ impl Discovery {
         // The default restart policy of this group is `never`, so override it.
         self.ctx.set_restart_policy(RestartPolicy::on_failures());
 
+        self.update_zone();
         self.listen().await?;
         self.discover();
 
+        let evict_interval = self.ctx.attach(Interval::new(EvictTick));
+        self.configure_eviction(&evict_interval);
+
         while let Some(envelope) = self.ctx.recv().await {
             msg!(match envelope {
                 ConfigUpdated => {
+                    // Peers will know about the new zone only after reconnecting.
+                    self.update_zone();
+                    self.configure_eviction(&evict_interval);
+                    self.peers.store(Arc::new(self.ctx.config().peers.clone()));
+                    self.close_denied_connections();
+                    self.discovery
+                        .store(Arc::new(self.ctx.config().discovery.clone()));
+                    self.update_max_attempts();
                     // TODO: update listeners.
                     // TODO: stop discovering for removed transports.
                     // TODO: self.discover();
@@ -98,14 +175,62 @@ impl Discovery {
                 msg @ ConnectionEstablished => self.on_connection_established(msg),
                 msg @ ConnectionAccepted => self.on_connection_accepted(msg),
                 msg @ ConnectionRejected => self.on_connection_rejected(msg),
+                msg @ RelayRequested => self.on_relay_requested(msg),
+                msg @ RelayClosed => self.on_relay_closed(msg),
+                msg @ RefreshRoutes => {
+                    let control = self.make_control_message(None);
+                    self.open_connection(&msg.peer, None, ConnectionRole::Control(control));
+                }
+                (GetNetworkStatus, token) => {
+                    self.ctx.respond(token, self.status.snapshot());
+                }
+                (GetNodeMap, token) => {
+                    self.ctx
+                        .respond(token, self.node_map.snapshot(&self.status));
+                }
+                EvictTick => self.evict_stale_nodes(),
             });
         }
 
         Ok(())
     }
 
+    fn update_zone(&self) {
+        let zone = self.ctx.config().zone.as_deref();
+        self.topology
+            .set_node_zone(self.node_map.this.node_no, zone);
+    }
+
+    fn configure_eviction(&self, interval: &Interval<EvictTick>) {
+        match self.ctx.config().discovery.node_ttl {
+            // Disconnection is detected by ticks, so check more often than TTL.
+            Some(ttl) => interval.start((ttl / 2).max(MIN_EVICT_PERIOD)),
+            None => interval.stop(),
+        }
+    }
+
+    fn evict_stale_nodes(&self) {
+        let ttl = ward!(self.ctx.config().discovery.node_ttl);
+        let evicted = self.node_map.evict(&self.status, ttl, Instant::now());
+
+        for (node_no, disconnected_for) in evicted {
+            info!(
+                message = "stale node evicted",
+                node_no = %node_no,
+                disconnected_for = ?disconnected_for,
+            );
+            counter!("elfo_network_evicted_nodes_total", 1);
+        }
+    }
+
     fn get_capabilities(&self) -> socket::Capabilities {
-        let mut capabilities = socket::Capabilities::empty();
+        let mut capabilities = socket::Capabilities::SENT_TIME
+            | socket::Capabilities::CHUNKING
+            | socket::Capabilities::REACHABILITY_CHECK
+            | socket::Capabilities::BAGGAGE
+            | socket::Capabilities::MESSAGE_IDS
+            | socket::Capabilities::VERSIONS
+            | socket::Capabilities::CANCELLATION;
         if self.ctx.config().compression.algorithm == CompressionAlgorithm::Lz4 {
             capabilities |= socket::Capabilities::LZ4;
         }
@@ -117,9 +242,14 @@ impl Discovery {
             let stream = socket::listen(&transport, &self.node_map.this, self.get_capabilities())
                 .await
                 .wrap_err_with(|| eyre!("cannot listen {}", transport))?
-                .map(|socket| ConnectionEstablished {
-                    role: ConnectionRole::Unknown,
-                    socket: socket.into(),
+                .map(|incoming| match incoming {
+                    Incoming::Socket(socket) => Ok(ConnectionEstablished {
+                        role: ConnectionRole::Unknown,
+                        socket: socket.into(),
+                    }),
+                    Incoming::Relay(request) => Err(RelayRequested {
+                        request: request.into(),
+                    }),
                 });
 
             info!(
@@ -134,30 +264,166 @@ impl Discovery {
     }
 
     fn discover(&mut self) {
-        let msg = internode::SwitchToControl {
+        let msg = self.make_control_message(None);
+
+        for transport in self.ctx.config().discovery.predefined.clone() {
+            self.open_connection(&transport, None, ConnectionRole::Control(msg.clone()));
+        }
+    }
+
+    /// Describes this node for the peer, if it's known.
+    fn make_control_message(&self, peer: Option<NodeNo>) -> internode::SwitchToControl {
+        let config = self.ctx.config();
+
+        // Relays advertise all known routes except ones to the peer itself.
+        // Every route is one hop longer for the peer.
+        let routes = config.relay.enabled.then(|| {
+            self.node_map
+                .routes
+                .lock()
+                .iter()
+                .filter(|(node_no, route)| {
+                    Some(**node_no) != peer && route.hops < config.relay.max_hops
+                })
+                .map(|(node_no, route)| internode::Route {
+                    node_no: *node_no,
+                    hops: route.hops + 1,
+                })
+                .collect()
+        });
+
+        internode::SwitchToControl {
             groups: self.node_map.this.groups.clone(),
+            listen: config.advertised().iter().map(|t| t.to_string()).collect(),
+            routes,
+            zone: config.zone.clone(),
+            handled_protocols: Some(
+                self.topology
+                    .locals()
+                    .map(|group| internode::HandledProtocols {
+                        group_no: group.addr.group_no().expect("invalid group no"),
+                        protocols: group.handled_protocols().map(<[_]>::to_vec),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Checks pipelines to remote groups of the connected node.
+    fn check_remote_pipelines(&self, remote: &internode::SwitchToControl) -> Result<(), String> {
+        // Nodes of older versions don't send handled protocols.
+        let Some(handled) = &remote.handled_protocols else {
+            return Ok(());
         };
 
-        for transport in self.ctx.config().discovery.predefined.clone() {
-            self.open_connection(&transport, ConnectionRole::Control(msg.clone()));
+        let errors = remote
+            .groups
+            .iter()
+            .flat_map(|group| {
+                let protocols = handled
+                    .iter()
+                    .find(|h| h.group_no == group.group_no)
+                    .and_then(|h| h.protocols.as_deref());
+
+                self.topology.check_remote_pipelines(&group.name, protocols)
+            })
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Closes established connections to peers denied by the new config.
+    fn close_denied_connections(&self) {
+        let peers = self.peers.load();
+
+        for (local, remote, peer) in self.status.peers() {
+            let groups = self
+                .node_map
+                .nodes
+                .lock()
+                .get(&peer.node_no)
+                .map(|n| n.groups.clone());
+
+            let mut candidate = Candidate::handshaken(&peer);
+            if let Some(groups) = &groups {
+                candidate = candidate.with_groups(groups);
+            }
+
+            if policy::check(&peers, &candidate).is_ok() {
+                continue;
+            }
+
+            info!(
+                message = "closing connection, the peer is denied",
+                peer = %peer,
+                local = %local.group_name,
+                remote = %remote.group_name,
+            );
+
+            if let Err(err) = self
+                .ctx
+                .try_send_to(self.ctx.group(), CloseConnection { local, remote })
+            {
+                warn!(message = "cannot close connection", error = %err);
+            }
+        }
+    }
+
+    fn update_max_attempts(&mut self) {
+        let max_attempts = self.ctx.config().discovery.max_parallel_attempts.max(1);
+
+        if max_attempts != self.max_attempts {
+            info!(message = "limit of parallel attempts changed", max_attempts);
+            self.attempts.store(Arc::new(Semaphore::new(max_attempts)));
+            self.max_attempts = max_attempts;
         }
     }
 
     fn open_connection(
         &mut self,
         peer: &Transport,
+        relay: Option<RelayTarget>,
         role: ConnectionRole,
     ) -> Stream<ConnectionEstablished> {
-        let interval = self.ctx.config().discovery.attempt_interval;
+        let attempts = self.attempts.clone();
+        let peers = self.peers.clone();
+        let discovery = self.discovery.clone();
         let peer = peer.clone();
         let this_node = self.node_map.this.clone();
         let capabilities = self.get_capabilities();
 
+        // Every peer is dialed independently, so unreachable ones don't delay
+        // others, but only limited number of attempts is made simultaneously.
         self.ctx.attach(Stream::once(async move {
             loop {
-                debug!(message = "connecting to peer", peer = %peer, role = ?role);
+                // The config can be changed between attempts.
+                let config = discovery.load_full();
 
-                match socket::connect(&peer, &this_node, capabilities).await {
+                // Denied peers are still retried, because the config can be changed.
+                let result = match policy::check(&peers.load(), &Candidate::dialed(&peer, relay)) {
+                    Ok(()) => {
+                        let attempt = async {
+                            debug!(message = "connecting to peer", peer = %peer, role = ?role);
+                            socket::connect(&peer, relay, &this_node, capabilities).await
+                        };
+                        limit_attempt(&attempts, config.attempt_timeout, attempt).await
+                    }
+                    Err(err) => Err(err),
+                };
+
+                let result = result.and_then(|socket| {
+                    if let Some(socket) = &socket {
+                        policy::check(&peers.load(), &Candidate::handshaken(&socket.peer))?;
+                    }
+                    Ok(socket)
+                });
+
+                match result {
                     Ok(socket) => match socket {
                         Some(socket) => {
                             break ConnectionEstablished {
@@ -178,6 +444,7 @@ impl Discovery {
                 }
 
                 // TODO: should we change trace_id?
+                let interval = config.attempt_interval;
                 debug!(message = "retrying after some time", peer = %peer, delay = ?interval);
                 tokio::time::sleep(interval).await;
             }
@@ -187,17 +454,22 @@ impl Discovery {
     fn on_connection_established(&mut self, msg: ConnectionEstablished) {
         let socket = msg.socket.take().unwrap();
 
+        if let Err(err) = policy::check(&self.peers.load(), &Candidate::handshaken(&socket.peer)) {
+            warn!(message = "new connection rejected", peer = %socket.peer, error = %err);
+            return;
+        }
+
         info!(
             message = "new connection established",
             peer = %socket.peer,
             role = msg.role.as_str(),
         );
 
-        let node_map = self.node_map.clone();
+        let my_control = self.make_control_message(Some(socket.peer.node_no));
         self.ctx.attach(Stream::once(async move {
             let peer = socket.peer.transport.clone();
 
-            let result = accept_connection(socket, msg.role, &node_map.this).await;
+            let result = accept_connection(socket, msg.role, my_control).await;
             match result {
                 Ok(accepted) => Ok(accepted),
                 Err(err) => {
@@ -224,14 +496,29 @@ impl Discovery {
             message = "new connection accepted",
             peer = %peer,
             role = msg.role.as_str(),
+            handshake_time = ?socket.timer.total(),
         );
 
         match msg.role {
             ConnectionRole::Unknown => unreachable!(),
             ConnectionRole::Control(remote) => {
-                {
+                let candidate = Candidate::handshaken(peer).with_groups(&remote.groups);
+                if let Err(err) = policy::check(&self.peers.load(), &candidate) {
+                    warn!(message = "new connection rejected", peer = %peer, error = %err);
+                    return;
+                }
+
+                if let Err(err) = self.check_remote_pipelines(&remote) {
+                    Failure::Pipeline.count();
+                    error!(message = "new connection rejected, invalid pipelines", peer = %peer, error = %err);
+                    return;
+                }
+
+                self.touch(peer.node_no);
+
+                let is_known = {
                     let mut nodes = self.node_map.nodes.lock();
-                    nodes.insert(
+                    let prev = nodes.insert(
                         peer.node_no,
                         NodeInfo {
                             node_no: peer.node_no,
@@ -241,11 +528,23 @@ impl Discovery {
                     );
 
                     // TODO: check launch_id.
+                    prev.is_some_and(|prev| prev.launch_id == peer.launch_id)
+                };
+
+                self.topology
+                    .set_node_zone(peer.node_no, remote.zone.as_deref());
+
+                if peer.relay.is_none() {
+                    self.on_direct_peer(&socket.peer, &remote, msg.is_initiator);
                 }
 
+                let mut timer = socket.timer;
+                timer.phase(Phase::Established);
+
                 // Only initiator (client) can start new connections,
                 // because he knows the transport address.
-                if !msg.is_initiator {
+                // Repeated control connections are used only to refresh routes.
+                if !msg.is_initiator || is_known {
                     return;
                 }
 
@@ -261,6 +560,7 @@ impl Discovery {
                         // TODO: save stream to cancel later.
                         self.open_connection(
                             &socket.peer.transport,
+                            socket.peer.relay,
                             ConnectionRole::Data(internode::SwitchToData {
                                 my_group_no: local_group_no,
                                 your_group_no: remote_group_no,
@@ -272,6 +572,24 @@ impl Discovery {
                 // TODO: start ping-pong process on the socket.
             }
             ConnectionRole::Data(remote) => {
+                // The config can be changed after the control connection is accepted.
+                let checked = self
+                    .node_map
+                    .nodes
+                    .lock()
+                    .get(&peer.node_no)
+                    .map_or(Ok(()), |n| {
+                        let candidate = Candidate::handshaken(peer).with_groups(&n.groups);
+                        policy::check(&self.peers.load(), &candidate)
+                    });
+
+                if let Err(err) = checked {
+                    warn!(message = "new connection rejected", peer = %peer, error = %err);
+                    return;
+                }
+
+                self.touch(peer.node_no);
+
                 let local_group_name = self
                     .node_map
                     .this
@@ -290,10 +608,21 @@ impl Discovery {
 
                 let (local_group_name, remote_group_name) =
                     ward!(local_group_name.zip(remote_group_name), {
+                        Failure::UnknownGroup.count();
                         error!("control and data connections contradict each other");
                         return;
                     });
 
+                let peer_node_no = peer.node_no;
+                let this_zone = self.topology.node_zone(self.node_map.this.node_no);
+                let peer_zone = self.topology.node_zone(peer_node_no);
+
+                let mut socket = socket;
+                let mut timer = socket.timer;
+                socket
+                    .set_zone_traffic(ZoneTraffic::new(this_zone.as_deref(), peer_zone.as_deref()));
+                socket.set_max_envelope_size(self.ctx.config().max_envelope_size.as_u64() as usize);
+
                 let res = self.ctx.try_send_to(
                     self.ctx.group(),
                     HandleConnection {
@@ -303,7 +632,7 @@ impl Discovery {
                             group_name: local_group_name,
                         },
                         remote: GroupInfo {
-                            node_no: peer.node_no,
+                            node_no: peer_node_no,
                             group_no: remote.my_group_no,
                             group_name: remote_group_name,
                         },
@@ -312,32 +641,208 @@ impl Discovery {
                     },
                 );
 
-                if let Err(err) = res {
-                    error!(message = "cannot start connection handler", error = %err);
-                    // TODO: something else?
+                match res {
+                    Ok(()) => timer.phase(Phase::Established),
+                    Err(err) => {
+                        Failure::NoWorker.count();
+                        error!(message = "cannot start connection handler", error = %err);
+                        // TODO: something else?
+                    }
                 }
             }
         }
     }
 
+    fn touch(&self, node_no: NodeNo) {
+        let now = SystemTime::now();
+        self.node_map.last_seen.lock().insert(node_no, now);
+    }
+
     fn on_connection_rejected(&mut self, _msg: ConnectionRejected) {
         // TODO: something else? Retries?
     }
+
+    /// Remembers how to reach the directly connected peer and, if it's a relay,
+    /// nodes behind it. Connections to newly reachable nodes are opened through
+    /// the relay.
+    fn on_direct_peer(
+        &mut self,
+        peer: &socket::Peer,
+        remote: &internode::SwitchToControl,
+        is_initiator: bool,
+    ) {
+        // The initiator knows the exact transport, otherwise rely on advertised ones.
+        let transport = if is_initiator {
+            Some(peer.transport.clone())
+        } else {
+            resolve_listen(&remote.listen, &peer.transport)
+        };
+
+        let transport = ward!(transport);
+        let route = Route {
+            transport: transport.clone(),
+            via: None,
+            hops: 0,
+        };
+        self.node_map.routes.lock().insert(peer.node_no, route);
+
+        let routes = ward!(remote.routes.as_ref());
+        let max_hops = self.ctx.config().relay.max_hops;
+        let mut new_nodes = Vec::new();
+
+        {
+            let mut known = self.node_map.routes.lock();
+
+            for advertised in routes {
+                // Loops are prevented by preferring shorter routes and limiting hops.
+                if advertised.node_no == self.node_map.this.node_no
+                    || advertised.hops > max_hops
+                    || known
+                        .get(&advertised.node_no)
+                        .is_some_and(|r| r.hops <= advertised.hops)
+                {
+                    continue;
+                }
+
+                let route = Route {
+                    transport: transport.clone(),
+                    via: Some(peer.node_no),
+                    hops: advertised.hops,
+                };
+
+                if known.insert(advertised.node_no, route).is_none() {
+                    new_nodes.push(advertised.node_no);
+                }
+            }
+        }
+
+        for node_no in new_nodes {
+            info!(
+                message = "new node is reachable through relay",
+                node_no = %node_no,
+                relay = %peer.node_no,
+            );
+
+            let target = RelayTarget {
+                node_no,
+                ttl: max_hops,
+            };
+            let msg = self.make_control_message(Some(node_no));
+            self.open_connection(&transport, Some(target), ConnectionRole::Control(msg));
+        }
+
+        // Ask the relay for new routes periodically.
+        if is_initiator {
+            let interval = self.ctx.config().discovery.gossip_interval;
+            let msg = RefreshRoutes { peer: transport };
+            self.ctx.attach(Delay::new(interval, msg));
+        }
+    }
+
+    fn on_relay_requested(&mut self, msg: RelayRequested) {
+        let request = msg.request.take().unwrap();
+        let target = request.target;
+        let config = &self.ctx.config().relay;
+
+        let route = self.node_map.routes.lock().get(&target.node_no).cloned();
+        let next = route
+            .filter(|_| config.enabled && target.ttl > 0)
+            .map(|route| (route.transport, route.via));
+
+        let Some((transport, via)) = next else {
+            warn!(
+                message = "relay request rejected",
+                peer = %request.peer,
+                target = %target.node_no,
+                ttl = target.ttl,
+                enabled = config.enabled,
+            );
+            return;
+        };
+
+        debug!(
+            message = "relaying connection",
+            peer = %request.peer,
+            target = %target.node_no,
+            via = ?via,
+        );
+
+        // If the target isn't reachable directly, pass the request further.
+        let next = via.map(|_| RelayTarget {
+            node_no: target.node_no,
+            ttl: target.ttl - 1,
+        });
+
+        increment_gauge!("elfo_network_relayed_connections", 1.);
+        self.ctx.attach(Stream::once(async move {
+            let result = socket::relay(request, &transport, next).await;
+            decrement_gauge!("elfo_network_relayed_connections", 1.);
+
+            RelayClosed {
+                target: target.node_no,
+                error: result.err().map(|err| format!("{:#}", err)),
+            }
+        }));
+    }
+
+    fn on_relay_closed(&mut self, msg: RelayClosed) {
+        match msg.error {
+            Some(error) => warn!(
+                message = "relayed connection failed",
+                target = %msg.target,
+                error = %error,
+            ),
+            None => debug!(message = "relayed connection closed", target = %msg.target),
+        }
+    }
+}
+
+/// Waits for a permit of the current semaphore and limits the attempt's time.
+async fn limit_attempt<T>(
+    attempts: &ArcSwap<Semaphore>,
+    timeout: Duration,
+    attempt: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let attempts = attempts.load_full();
+    let _permit = attempts.acquire().await.expect("never closed");
+
+    tokio::time::timeout(timeout, attempt)
+        .await
+        .unwrap_or_else(|_| {
+            Failure::Timeout.count();
+            Err(eyre!("timed out after {:?}", timeout))
+        })
 }
 
 async fn accept_connection(
     mut socket: Socket,
     role: ConnectionRole,
-    this_node: &NodeInfo,
+    my_control: internode::SwitchToControl,
 ) -> Result<ConnectionAccepted> {
-    let (is_initiator, role) = match role {
+    let (is_initiator, role) = exchange_roles(&mut socket, role, my_control)
+        .await
+        .inspect_err(|_| Failure::Exchange.count())?;
+
+    socket.timer.phase(Phase::GroupsExchanged);
+
+    Ok(ConnectionAccepted {
+        is_initiator,
+        role,
+        socket: socket.into(),
+    })
+}
+
+/// Returns whether this node is the initiator and the peer's role.
+async fn exchange_roles(
+    socket: &mut Socket,
+    role: ConnectionRole,
+    my_control: internode::SwitchToControl,
+) -> Result<(bool, ConnectionRole)> {
+    Ok(match role {
         ConnectionRole::Unknown => {
-            msg!(match recv(&mut socket).await? {
+            msg!(match recv(socket).await? {
                 msg @ internode::SwitchToControl => {
-                    let my_msg = internode::SwitchToControl {
-                        groups: this_node.groups.clone(),
-                    };
-                    send_regular(&mut socket, my_msg).await?;
+                    send_regular(socket, my_control).await?;
                     (false, ConnectionRole::Control(msg))
                 }
                 msg @ internode::SwitchToData => {
@@ -346,7 +851,7 @@ async fn accept_connection(
                         your_group_no: msg.my_group_no,
                         initial_window: INITIAL_WINDOW_SIZE,
                     };
-                    send_regular(&mut socket, my_msg).await?;
+                    send_regular(socket, my_msg).await?;
                     (false, ConnectionRole::Data(msg))
                 }
                 envelope =>
@@ -357,21 +862,40 @@ async fn accept_connection(
             })
         }
         ConnectionRole::Control(msg) => {
-            send_regular(&mut socket, msg).await?;
-            let msg = recv_regular::<internode::SwitchToControl>(&mut socket).await?;
+            send_regular(socket, msg).await?;
+            let msg = recv_regular::<internode::SwitchToControl>(socket).await?;
             (true, ConnectionRole::Control(msg))
         }
         ConnectionRole::Data(msg) => {
-            send_regular(&mut socket, msg).await?;
-            let msg = recv_regular::<internode::SwitchToData>(&mut socket).await?;
+            send_regular(socket, msg).await?;
+            let msg = recv_regular::<internode::SwitchToData>(socket).await?;
             (true, ConnectionRole::Data(msg))
         }
-    };
+    })
+}
 
-    Ok(ConnectionAccepted {
-        is_initiator,
-        role,
-        socket: socket.into(),
+/// Returns the transport the peer is reachable at, see `advertise_addr`.
+/// The first one of the same kind as the observed transport is preferred.
+/// Unspecified addresses (e.g. `0.0.0.0`) are replaced with the observed one.
+fn resolve_listen(listen: &[String], observed: &Transport) -> Option<Transport> {
+    let transports = listen
+        .iter()
+        .filter_map(|transport| config::parse_transport(transport).ok())
+        .collect::<Vec<_>>();
+
+    let is_same_kind = |t: &&Transport| mem::discriminant(*t) == mem::discriminant(observed);
+    let transport = transports
+        .iter()
+        .find(is_same_kind)
+        .or(transports.first())?
+        .clone();
+
+    Some(match (transport, observed.ip()) {
+        (Transport::Tcp(mut addr), Some(ip)) if addr.ip().is_unspecified() => {
+            addr.set_ip(ip);
+            Transport::Tcp(addr)
+        }
+        (transport, _) => transport,
     })
 }
 
@@ -392,6 +916,11 @@ async fn send_regular<M: Message>(socket: &mut Socket, msg: M) -> Result<()> {
         sender: NetworkAddr::NULL,    // doesn't matter
         recipient: NetworkAddr::NULL, // doesn't matter
         trace_id: scope::trace_id(),
+        message_id: None,
+        parent_id: None,
+        sent_time: None,
+        baggage: Baggage::default(),
+        versions: false,
         payload: NetworkEnvelopePayload::Regular {
             message: msg.upcast(),
         },
@@ -448,3 +977,83 @@ fn unexpected_message_error(envelope: Envelope, expected: &[&str]) -> eyre::Repo
         expected.join(" or "),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future;
+
+    use super::*;
+
+    async fn max_in_progress(attempts: &ArcSwap<Semaphore>, count: usize) -> usize {
+        let in_progress = AtomicUsize::new(0);
+        let max = AtomicUsize::new(0);
+
+        let attempts = (0..count).map(|_| {
+            limit_attempt(attempts, Duration::from_secs(10), async {
+                let current = in_progress.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                in_progress.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            })
+        });
+
+        for result in future::join_all(attempts).await {
+            result.unwrap();
+        }
+
+        max.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn it_limits_parallel_attempts() {
+        let attempts = ArcSwap::from_pointee(Semaphore::new(2));
+        assert_eq!(max_in_progress(&attempts, 5).await, 2);
+
+        // The limit is changed on reconfiguration.
+        attempts.store(Arc::new(Semaphore::new(3)));
+        assert_eq!(max_in_progress(&attempts, 5).await, 3);
+    }
+
+    #[test]
+    fn it_resolves_advertised_transports() {
+        let listen = ["mem://a".into(), "tcp://0.0.0.0:4242".into()];
+        let observed = Transport::Tcp("1.2.3.4:5678".parse().unwrap());
+
+        let resolved = resolve_listen(&listen, &observed);
+        assert_eq!(
+            resolved,
+            Some(Transport::Tcp("1.2.3.4:4242".parse().unwrap()))
+        );
+
+        let resolved = resolve_listen(&listen, &Transport::Mem("b".into()));
+        assert_eq!(resolved, Some(Transport::Mem("a".into())));
+
+        // Falls back to the first transport of another kind.
+        let resolved = resolve_listen(&listen[..1], &observed);
+        assert_eq!(resolved, Some(Transport::Mem("a".into())));
+
+        assert_eq!(resolve_listen(&[], &observed), None);
+    }
+
+    #[tokio::test]
+    async fn it_limits_attempt_time() {
+        let attempts = ArcSwap::from_pointee(Semaphore::new(1));
+        let timeout = Duration::from_millis(10);
+
+        let attempt = future::pending::<Result<()>>();
+        let err = limit_attempt(&attempts, timeout, attempt)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "timed out after 10ms");
+
+        // The permit is released after the timeout.
+        let attempt = future::ready(Ok(42));
+        assert_eq!(
+            limit_attempt(&attempts, timeout, attempt).await.unwrap(),
+            42
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Checks peers against `system.network.peers`.
+
+use std::net::IpAddr;
+
+use eyre::{eyre, Result};
+
+use elfo_core::_priv::NodeNo;
+
+use crate::{
+    config::{PeerRule, PeersConfig, Transport},
+    lifecycle::Failure,
+    protocol::internode,
+    socket::{Peer, RelayTarget},
+};
+
+/// What is known about the peer at the moment. Unknown properties don't
+/// reject the peer, so it's checked again once they're known.
+pub(super) struct Candidate<'a> {
+    pub(super) node_no: Option<NodeNo>,
+    pub(super) address: Option<IpAddr>,
+    pub(super) groups: Option<&'a [internode::GroupInfo]>,
+}
+
+impl<'a> Candidate<'a> {
+    /// The peer isn't connected yet.
+    pub(super) fn dialed(transport: &Transport, relay: Option<RelayTarget>) -> Self {
+        Self {
+            node_no: relay.map(|target| target.node_no),
+            // The address of the relay isn't interesting.
+            address: relay.is_none().then(|| transport.ip()).flatten(),
+            groups: None,
+        }
+    }
+
+    /// The handshake is done.
+    pub(super) fn handshaken(peer: &Peer) -> Self {
+        Self {
+            node_no: Some(peer.node_no),
+            address: peer.relay.is_none().then(|| peer.transport.ip()).flatten(),
+            groups: None,
+        }
+    }
+
+    pub(super) fn with_groups(mut self, groups: &'a [internode::GroupInfo]) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+}
+
+pub(super) fn check(config: &PeersConfig, candidate: &Candidate<'_>) -> Result<()> {
+    let denied = config
+        .deny
+        .iter()
+        .any(|rule| matches(rule, candidate) == Some(true));
+
+    let allowed = config.allow.is_empty()
+        || config
+            .allow
+            .iter()
+            .any(|rule| matches(rule, candidate) != Some(false));
+
+    if allowed && !denied {
+        Ok(())
+    } else {
+        Failure::Denied.count();
+        Err(eyre!("the peer is denied by `system.network.peers`"))
+    }
+}
+
+/// Returns `None` if the rule cannot be checked yet.
+fn matches(rule: &PeerRule, candidate: &Candidate<'_>) -> Option<bool> {
+    let mut is_known = true;
+    let mut check = |expected: bool, actual: Option<bool>| match actual {
+        Some(actual) => !expected || actual,
+        None => {
+            is_known &= !expected;
+            true
+        }
+    };
+
+    let node_no = check(
+        rule.node_no.is_some(),
+        candidate
+            .node_no
+            .map(|node_no| Some(node_no) == rule.node_no),
+    );
+    let address = check(
+        rule.address.is_some(),
+        candidate
+            .address
+            .map(|addr| rule.address.is_some_and(|cidr| cidr.contains(addr))),
+    );
+    let group = check(
+        rule.group.is_some(),
+        candidate
+            .groups
+            .map(|groups| groups.iter().any(|g| Some(&g.name) == rule.group.as_ref())),
+    );
+
+    if !(node_no && address && group) {
+        Some(false)
+    } else if is_known {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::_priv::GroupNo;
+
+    use super::*;
+    use crate::config;
+
+    fn rule(node_no: Option<u16>, address: Option<&str>, group: Option<&str>) -> PeerRule {
+        PeerRule {
+            node_no: node_no.map(|n| NodeNo::from_bits(n).unwrap()),
+            address: address.map(|a| config::parse_cidr(a).unwrap()),
+            group: group.map(Into::into),
+        }
+    }
+
+    fn candidate(node_no: Option<u16>, address: Option<&str>) -> Candidate<'static> {
+        Candidate {
+            node_no: node_no.map(|n| NodeNo::from_bits(n).unwrap()),
+            address: address.map(|a| a.parse().unwrap()),
+            groups: None,
+        }
+    }
+
+    #[test]
+    fn deny() {
+        let config = PeersConfig {
+            allow: vec![],
+            deny: vec![
+                rule(Some(2), None, None),
+                rule(None, Some("10.1.0.0/16"), None),
+            ],
+        };
+
+        assert!(check(&config, &candidate(None, None)).is_ok());
+        assert!(check(&config, &candidate(Some(1), Some("10.2.0.1"))).is_ok());
+        assert!(check(&config, &candidate(Some(2), None)).is_err());
+        assert!(check(&config, &candidate(None, Some("10.1.0.1"))).is_err());
+    }
+
+    #[test]
+    fn allow() {
+        let config = PeersConfig {
+            allow: vec![rule(Some(2), Some("10.1.0.0/16"), None)],
+            deny: vec![],
+        };
+
+        // Unknown properties don't reject the peer.
+        assert!(check(&config, &candidate(None, None)).is_ok());
+        assert!(check(&config, &candidate(None, Some("10.1.0.1"))).is_ok());
+        assert!(check(&config, &candidate(Some(2), Some("10.1.0.1"))).is_ok());
+        assert!(check(&config, &candidate(Some(1), Some("10.1.0.1"))).is_err());
+        assert!(check(&config, &candidate(None, Some("10.2.0.1"))).is_err());
+    }
+
+    #[test]
+    fn groups() {
+        let config = PeersConfig {
+            allow: vec![],
+            deny: vec![rule(None, None, Some("gateways"))],
+        };
+        let groups = |names: &[&str]| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| internode::GroupInfo {
+                    group_no: GroupNo::from_bits(i as u8 + 1).unwrap(),
+                    name: name.to_string(),
+                    interests: Vec::new(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let gateways = groups(&["gateways", "workers"]);
+        let workers = groups(&["workers"]);
+
+        assert!(check(&config, &candidate(Some(1), None)).is_ok());
+        let candidate = |groups| candidate(Some(1), None).with_groups(groups);
+        assert!(check(&config, &candidate(&gateways)).is_err());
+        assert!(check(&config, &candidate(&workers)).is_ok());
+    }
+}
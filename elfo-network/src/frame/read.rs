@@ -2,7 +2,7 @@ use eyre::{eyre, Result};
 
 use crate::{
     codec::{
-        self,
+        chunk::Reassembler,
         decode::{DecodeState, DecodeStats, EnvelopeDetails},
         format::NetworkEnvelope,
     },
@@ -25,8 +25,18 @@ impl FramedRead {
     pub(crate) fn none() -> Self {
         FramedRead::None(NoneFramedRead::new())
     }
+
+    /// See `Reassembler::set_max_envelope_size()`.
+    pub(crate) fn set_max_envelope_size(&mut self, max_envelope_size: usize) {
+        let reassembler = match self {
+            FramedRead::Lz4(lz4) => &mut lz4.reassembler,
+            FramedRead::None(none) => &mut none.reassembler,
+        };
+        reassembler.set_max_envelope_size(max_envelope_size);
+    }
 }
 
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum FramedReadState<'a> {
     /// The stategy needs more data written at the beginning of the specified
     /// `buffer`.
@@ -83,6 +93,7 @@ pub(crate) struct LZ4FramedRead {
     decompressed_buffer: LZ4Buffer,
     stats: FramedReadStats,
     position: usize,
+    reassembler: Reassembler,
 }
 
 impl LZ4FramedRead {
@@ -92,6 +103,7 @@ impl LZ4FramedRead {
             decompressed_buffer: LZ4Buffer::with_capacity(DECOMPRESSED_DATA_BUFFER_CAPACITY),
             stats: Default::default(),
             position: 0,
+            reassembler: Default::default(),
         }
     }
 }
@@ -136,8 +148,9 @@ impl FramedReadStrategy for LZ4FramedRead {
             // will be skipped and we will try to decompress the next frame.
             'decoding: loop {
                 let envelope_buffer = &self.decompressed_buffer.filled_slice()[self.position..];
-                let codec_state =
-                    codec::decode::decode(envelope_buffer, &mut self.stats.decode_stats)?;
+                let codec_state = self
+                    .reassembler
+                    .decode(envelope_buffer, &mut self.stats.decode_stats)?;
                 match codec_state {
                     DecodeState::NeedMoreData { .. } => {
                         if self.position == self.decompressed_buffer.len() {
@@ -162,6 +175,10 @@ impl FramedReadStrategy for LZ4FramedRead {
                             continue 'decoding;
                         }
                     }
+                    DecodeState::Chunk { bytes_consumed } => {
+                        self.position += bytes_consumed;
+                        continue 'decoding;
+                    }
                     DecodeState::Done {
                         bytes_consumed,
                         decoded,
@@ -186,6 +203,7 @@ impl FramedReadStrategy for LZ4FramedRead {
 pub(crate) struct NoneFramedRead {
     buffer: ReadBuffer,
     stats: FramedReadStats,
+    reassembler: Reassembler,
 }
 
 impl NoneFramedRead {
@@ -193,6 +211,7 @@ impl NoneFramedRead {
         Self {
             buffer: ReadBuffer::with_capacity(DECOMPRESSED_DATA_BUFFER_CAPACITY),
             stats: Default::default(),
+            reassembler: Default::default(),
         }
     }
 }
@@ -200,8 +219,9 @@ impl NoneFramedRead {
 impl FramedReadStrategy for NoneFramedRead {
     fn read(&mut self) -> Result<FramedReadState<'_>> {
         loop {
-            let codec_state =
-                codec::decode::decode(self.buffer.filled_slice(), &mut self.stats.decode_stats)?;
+            let codec_state = self
+                .reassembler
+                .decode(self.buffer.filled_slice(), &mut self.stats.decode_stats)?;
             match codec_state {
                 DecodeState::NeedMoreData {
                     total_length_estimate,
@@ -225,6 +245,10 @@ impl FramedReadStrategy for NoneFramedRead {
                         continue;
                     }
                 }
+                DecodeState::Chunk { bytes_consumed } => {
+                    self.stats.decompress_stats.total_uncompressed_bytes += bytes_consumed as u64;
+                    self.buffer.consume_filled(bytes_consumed);
+                }
                 DecodeState::Done {
                     bytes_consumed,
                     decoded,
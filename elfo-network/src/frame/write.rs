@@ -1,9 +1,12 @@
 use eyre::Result;
+use metrics::counter;
+use tracing::error;
 
 use super::buffers::{COMPRESSED_DATA_BUFFER_CAPACITY, DECOMPRESSED_DATA_BUFFER_CAPACITY};
 use crate::{
     codec::{
         self,
+        chunk::Chunker,
         encode::{EncodeError, EncodeStats},
         format::NetworkEnvelope,
     },
@@ -22,12 +25,24 @@ pub(crate) enum FramedWrite {
 }
 
 impl FramedWrite {
-    pub(crate) fn lz4(envelope_size_limit: Option<usize>) -> Self {
-        FramedWrite::Lz4(LZ4FramedWrite::new(envelope_size_limit))
+    /// If `chunking` is set, large envelopes are written in chunks.
+    pub(crate) fn lz4(envelope_size_limit: Option<usize>, chunking: bool) -> Self {
+        FramedWrite::Lz4(LZ4FramedWrite::new(envelope_size_limit, chunking))
     }
 
-    pub(crate) fn none(envelope_size_limit: Option<usize>) -> Self {
-        FramedWrite::None(NoneFramedWrite::new(envelope_size_limit))
+    pub(crate) fn none(envelope_size_limit: Option<usize>, chunking: bool) -> Self {
+        FramedWrite::None(NoneFramedWrite::new(envelope_size_limit, chunking))
+    }
+
+    /// See `Chunker::set_max_envelope_size()`.
+    pub(crate) fn set_max_envelope_size(&mut self, max_envelope_size: usize) {
+        let chunker = match self {
+            FramedWrite::Lz4(lz4) => &mut lz4.chunker,
+            FramedWrite::None(none) => &mut none.chunker,
+        };
+        if let Some(chunker) = chunker {
+            chunker.set_max_envelope_size(max_envelope_size);
+        }
     }
 }
 
@@ -45,6 +60,10 @@ pub(crate) trait FramedWriteStrategy {
     fn finalize(&mut self) -> Result<&[u8]>;
 
     fn take_stats(&mut self) -> FramedWriteStats;
+
+    /// Returns `true` if a large envelope is being written in chunks. Every
+    /// `finalize()` adds one chunk into the frame.
+    fn has_pending_chunks(&self) -> bool;
 }
 
 /// Hand-rolled dynamic dispatch to use branch predictor and allow
@@ -70,6 +89,13 @@ impl FramedWriteStrategy for FramedWrite {
             FramedWrite::None(none) => none.take_stats(),
         }
     }
+
+    fn has_pending_chunks(&self) -> bool {
+        match self {
+            FramedWrite::Lz4(lz4) => lz4.has_pending_chunks(),
+            FramedWrite::None(none) => none.has_pending_chunks(),
+        }
+    }
 }
 
 pub(crate) struct LZ4FramedWrite {
@@ -77,15 +103,17 @@ pub(crate) struct LZ4FramedWrite {
     compressed_buffer: LZ4Buffer,
     stats: FramedWriteStats,
     envelope_size_limit: Option<usize>,
+    chunker: Option<Chunker>,
 }
 
 impl LZ4FramedWrite {
-    pub(crate) fn new(envelope_size_limit: Option<usize>) -> Self {
+    pub(crate) fn new(envelope_size_limit: Option<usize>, chunking: bool) -> Self {
         Self {
             decompressed_buffer: Vec::with_capacity(DECOMPRESSED_DATA_BUFFER_CAPACITY),
             compressed_buffer: LZ4Buffer::with_capacity(COMPRESSED_DATA_BUFFER_CAPACITY),
             stats: Default::default(),
             envelope_size_limit,
+            chunker: chunking.then(Chunker::default),
         }
     }
 }
@@ -95,6 +123,7 @@ const OUTPUT_FLUSH_THRESHOLD: usize = 64 * 1024;
 
 impl FramedWriteStrategy for LZ4FramedWrite {
     fn write(&mut self, envelope: &NetworkEnvelope) -> Result<FrameState, EncodeError> {
+        let start = self.decompressed_buffer.len();
         codec::encode::encode(
             envelope,
             &mut self.decompressed_buffer,
@@ -102,6 +131,19 @@ impl FramedWriteStrategy for LZ4FramedWrite {
             self.envelope_size_limit,
         )?;
 
+        if let Some(chunker) = &mut self.chunker {
+            let taken = chunker.take_large(
+                &mut self.decompressed_buffer,
+                start,
+                DECOMPRESSED_DATA_BUFFER_CAPACITY,
+            );
+            match taken {
+                Ok(true) => return Ok(FrameState::FlushAdvised),
+                Ok(false) => {}
+                Err(err) => return Err(skip_oversized(envelope, err, &mut self.stats)),
+            }
+        }
+
         // We conservatively estimate that LZ4 will provide us with x2 compression rate
         // on msgpack data.
         // TODO: improve estimate on actual compression rates.
@@ -115,6 +157,10 @@ impl FramedWriteStrategy for LZ4FramedWrite {
     }
 
     fn finalize(&mut self) -> Result<&[u8]> {
+        if let Some(chunker) = &mut self.chunker {
+            chunker.write_next(&mut self.decompressed_buffer);
+        }
+
         let result = self
             .compressed_buffer
             .compress_frame(&self.decompressed_buffer, &mut self.stats.compress_stats);
@@ -126,6 +172,10 @@ impl FramedWriteStrategy for LZ4FramedWrite {
     fn take_stats(&mut self) -> FramedWriteStats {
         std::mem::take(&mut self.stats)
     }
+
+    fn has_pending_chunks(&self) -> bool {
+        self.chunker.as_ref().is_some_and(|c| !c.is_empty())
+    }
 }
 
 pub(crate) struct NoneFramedWrite {
@@ -133,15 +183,17 @@ pub(crate) struct NoneFramedWrite {
     stats: FramedWriteStats,
     after_finalize: bool,
     envelope_size_limit: Option<usize>,
+    chunker: Option<Chunker>,
 }
 
 impl NoneFramedWrite {
-    fn new(envelope_size_limit: Option<usize>) -> Self {
+    fn new(envelope_size_limit: Option<usize>, chunking: bool) -> Self {
         Self {
             buffer: Vec::with_capacity(DECOMPRESSED_DATA_BUFFER_CAPACITY),
             stats: Default::default(),
             after_finalize: false,
             envelope_size_limit,
+            chunker: chunking.then(Chunker::default),
         }
     }
 }
@@ -153,6 +205,7 @@ impl FramedWriteStrategy for NoneFramedWrite {
             self.after_finalize = false;
         }
 
+        let start = self.buffer.len();
         codec::encode::encode(
             envelope,
             &mut self.buffer,
@@ -160,6 +213,14 @@ impl FramedWriteStrategy for NoneFramedWrite {
             self.envelope_size_limit,
         )?;
 
+        if let Some(chunker) = &mut self.chunker {
+            match chunker.take_large(&mut self.buffer, start, DECOMPRESSED_DATA_BUFFER_CAPACITY) {
+                Ok(true) => return Ok(FrameState::FlushAdvised),
+                Ok(false) => {}
+                Err(err) => return Err(skip_oversized(envelope, err, &mut self.stats)),
+            }
+        }
+
         Ok(if self.buffer.len() > OUTPUT_FLUSH_THRESHOLD {
             FrameState::FlushAdvised
         } else {
@@ -168,6 +229,13 @@ impl FramedWriteStrategy for NoneFramedWrite {
     }
 
     fn finalize(&mut self) -> Result<&[u8]> {
+        if self.after_finalize {
+            self.buffer.clear();
+        }
+        if let Some(chunker) = &mut self.chunker {
+            chunker.write_next(&mut self.buffer);
+        }
+
         self.after_finalize = true;
         self.stats.compress_stats.total_uncompressed_bytes += self.buffer.len() as u64;
         Ok(&self.buffer)
@@ -176,4 +244,29 @@ impl FramedWriteStrategy for NoneFramedWrite {
     fn take_stats(&mut self) -> FramedWriteStats {
         std::mem::take(&mut self.stats)
     }
+
+    fn has_pending_chunks(&self) -> bool {
+        self.chunker.as_ref().is_some_and(|c| !c.is_empty())
+    }
+}
+
+/// The envelope rejected by the chunker has been already encoded, so it's
+/// accounted as skipped instead.
+fn skip_oversized(
+    envelope: &NetworkEnvelope,
+    error: eyre::Report,
+    stats: &mut FramedWriteStats,
+) -> EncodeError {
+    stats.encode_stats.total_messages_encoded -= 1;
+    stats.encode_stats.total_messages_encoding_skipped += 1;
+    counter!("elfo_network_oversized_envelopes_total", 1);
+
+    let (protocol, name) = envelope.payload.protocol_and_name();
+    error!(
+        message = "cannot send message, skipping",
+        error = format!("{:#}", error),
+        %protocol,
+        %name,
+    );
+    EncodeError::Skipped
 }
@@ -0,0 +1,247 @@
+//! Migration of keyed actors between nodes, e.g. to drain a node before
+//! maintenance without dropping their state.
+//!
+//! The flow is cooperative: the migrating actor decides when to move and
+//! provides its snapshot, which is restored by the actor with the same key on
+//! the target node. The migration consists of the following steps:
+//! 1. The actor stops handling messages, so they are held in its mailbox.
+//! 2. The snapshot is sent as a sequence of [`Handoff`] chunks to the target
+//!    node, see the `blob` module. The target group's router must route them by
+//!    the `key` field, so the actor is spawned on the first chunk.
+//! 3. The target actor restores the snapshot using [`Restorer`].
+//! 4. Once the snapshot is restored, the key is marked as migrated in
+//!    [`MigrationTable`], so the routing from this node to the remote group
+//!    sends it to the target node.
+//! 5. The source actor forwards held and following messages to the target node
+//!    until it's terminated, see [`forward_rest()`].
+//!
+//! Until the key is marked, messages aren't routed to the target node, so
+//! the target actor doesn't handle them before the snapshot is restored.
+//!
+//! The local group must be connected to its remote counterpart, routing
+//! [`Handoff`] chunks to [`Handoff::node_no`] and other messages using
+//! [`MigrationTable::outcome()`]:
+//! ```ignore
+//! let sessions = topology.local("sessions");
+//! let remote_sessions = topology.remote("sessions");
+//! let table = MigrationTable::<SessionId>::default();
+//! sessions.route_to(&remote_sessions, move |envelope, _| {
+//!     msg!(match envelope {
+//!         Handoff { node_no, .. } => Outcome::Unicast(*node_no),
+//!         m @ SessionMessage => table.outcome(&m.session_id),
+//!         _ => Outcome::Discard,
+//!     })
+//! });
+//! ```
+//!
+//! If the transfer fails, the key isn't marked and the actor continues working
+//! on this node.
+
+use std::{
+    fmt::Display,
+    hash::Hash,
+    io::Cursor,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+use fxhash::FxBuildHasher;
+use once_cell::sync::Lazy;
+
+use elfo_core::{message, messages::Terminate, topology::Outcome, Context, NodeNo, ResponseToken};
+
+use crate::blob::{BlobAck, BlobChunk, BlobError, BlobReceiver, BlobTransfer};
+
+/// A chunk of the snapshot of the migrating actor.
+#[message(ret = BlobAck)]
+#[non_exhaustive]
+pub struct Handoff {
+    /// The node the actor is migrated to.
+    pub node_no: NodeNo,
+    /// The key of the migrating actor, formatted by `Display`.
+    pub key: String,
+    /// The chunk of the snapshot.
+    pub chunk: BlobChunk,
+}
+
+/// Keys of actors migrated from this node and nodes they're moved to.
+/// Cheap to clone, shared between routers and actors.
+pub struct MigrationTable<K> {
+    keys: Arc<DashMap<K, NodeNo, FxBuildHasher>>,
+}
+
+impl<K> Clone for MigrationTable<K> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> Default for MigrationTable<K> {
+    fn default() -> Self {
+        Self {
+            keys: Default::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> MigrationTable<K> {
+    /// Marks the key as migrated to the node.
+    pub fn insert(&self, key: K, node_no: NodeNo) {
+        self.keys.insert(key, node_no);
+    }
+
+    /// Unmarks the key, e.g. once the actor is moved back.
+    pub fn remove(&self, key: &K) {
+        self.keys.remove(key);
+    }
+
+    /// Returns the node the key is migrated to.
+    pub fn node_no(&self, key: &K) -> Option<NodeNo> {
+        self.keys.get(key).map(|node_no| *node_no)
+    }
+
+    /// Routes to the node the key is migrated to, discards otherwise.
+    pub fn outcome(&self, key: &K) -> Outcome {
+        self.node_no(key).map_or(Outcome::Discard, Outcome::Unicast)
+    }
+
+    /// Like `outcome()`, but accepts the key formatted by `Display`, e.g.
+    /// [`Handoff::key`].
+    pub fn outcome_str(&self, key: &str) -> Outcome
+    where
+        K: FromStr,
+    {
+        key.parse()
+            .map_or(Outcome::Discard, |key| self.outcome(&key))
+    }
+}
+
+/// Migrates the current actor to the node, see the module-level
+/// documentation. Messages received by the actor meanwhile are held in its
+/// mailbox, so they should be forwarded by [`forward_rest()`] on success.
+///
+/// The key is marked as migrated only on success, so the actor can continue
+/// working on failure.
+pub async fn handoff<C, K>(
+    ctx: &Context<C, K>,
+    table: &MigrationTable<K>,
+    node_no: NodeNo,
+    snapshot: Vec<u8>,
+) -> Result<(), BlobError>
+where
+    C: 'static,
+    K: Display + Clone + Hash + Eq,
+{
+    let key = ctx.key().clone();
+    let key_str = key.to_string();
+
+    // The snapshot can change between attempts, so every attempt is a new transfer.
+    // Ids start randomly, so transfers from different nodes or launches don't
+    // clash.
+    static NEXT_TRANSFER_ID: Lazy<AtomicU64> = Lazy::new(|| AtomicU64::new(random_u64()));
+    let transfer_id = NEXT_TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+
+    BlobTransfer::new(transfer_id)
+        .send_wrapped(ctx, Cursor::new(snapshot), |chunk| Handoff {
+            node_no,
+            key: key_str.clone(),
+            chunk,
+        })
+        .await?;
+
+    table.insert(key, node_no);
+    Ok(())
+}
+
+fn random_u64() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    // `RandomState` is randomly seeded.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    hasher.finish()
+}
+
+/// Forwards all messages to the actor's new location until `Terminate` is
+/// received. Must be called after successful [`handoff()`].
+pub async fn forward_rest<C, K>(ctx: &mut Context<C, K>)
+where
+    C: 'static,
+{
+    while let Some(envelope) = ctx.recv().await {
+        if envelope.is::<Terminate>() {
+            break;
+        }
+
+        let _ = ctx.forward(envelope).await;
+    }
+}
+
+/// Restores the snapshot on the target node from [`Handoff`] chunks.
+#[derive(Default)]
+pub struct Restorer {
+    receiver: Option<(u64, BlobReceiver<Vec<u8>>)>,
+}
+
+impl Restorer {
+    /// Handles the chunk and responds to it. Returns the snapshot once it's
+    /// completely received.
+    pub async fn handle<C, K>(
+        &mut self,
+        ctx: &Context<C, K>,
+        handoff: &Handoff,
+        token: ResponseToken<Handoff>,
+    ) -> Option<Vec<u8>> {
+        let id = handoff.chunk.id;
+
+        // Chunks of a previous failed attempt are discarded.
+        if !matches!(&self.receiver, Some((current, _)) if *current == id) {
+            self.receiver = Some((id, BlobReceiver::new(Vec::new())));
+        }
+
+        let (_, receiver) = self.receiver.as_mut().unwrap();
+
+        // Writing into `Vec` cannot fail.
+        let ack = receiver.handle(&handoff.chunk).await.unwrap();
+        ctx.respond(token, ack);
+
+        if receiver.is_complete() {
+            self.receiver
+                .take()
+                .map(|(_, receiver)| receiver.into_inner())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table() {
+        let table = MigrationTable::<u32>::default();
+        let node_no = NodeNo::from_bits(2).unwrap();
+
+        assert!(matches!(table.outcome(&1), Outcome::Discard));
+
+        table.insert(1, node_no);
+        assert_eq!(table.node_no(&1), Some(node_no));
+        assert!(matches!(table.outcome(&1), Outcome::Unicast(n) if n == node_no));
+        assert!(matches!(table.outcome_str("1"), Outcome::Unicast(n) if n == node_no));
+        assert!(matches!(table.outcome_str("x"), Outcome::Discard));
+
+        table.clone().remove(&1);
+        assert!(matches!(table.outcome(&1), Outcome::Discard));
+    }
+}
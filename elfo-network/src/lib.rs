@@ -10,6 +10,7 @@ extern crate elfo_utils;
 use std::{
     fmt::{self, Display},
     hash::Hash,
+    sync::Arc,
 };
 
 use elfo_core::{
@@ -21,17 +22,29 @@ use elfo_core::{
 
 use crate::{
     config::Config,
-    protocol::{GroupInfo, HandleConnection},
+    protocol::{CloseConnection, GroupInfo, HandleConnection},
+    status::{CheckReachability, GetNetworkStatus, GetNodeMap, StatusRegistry},
 };
 
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
+pub mod blob;
+mod circuit_breaker;
 mod codec;
 mod config;
 mod discovery;
 mod frame;
+pub mod handoff;
+mod lifecycle;
+mod mem;
+pub mod messages;
 mod node_map;
 mod protocol;
+pub mod replica;
 mod rtt;
+mod skew;
 mod socket;
+pub mod status;
 mod worker;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -57,32 +70,63 @@ impl Display for ActorKey {
 
 type NetworkContext = Context<Config, ActorKey>;
 
+/// The key of the baggage entry added to envelopes received from other nodes
+/// if `system.network.annotate_received_time` is set.
+///
+/// The value is the unix time in nanoseconds when the envelope is received,
+/// measured by the sender's clock, i.e. the local time corrected by the
+/// estimated clock offset. It's comparable with timestamps made by the sender.
+/// The entry is added only once the offset is estimated, and it's propagated
+/// further like other baggage entries.
+pub const RECEIVED_TIME_KEY: &str = "elfo.received_time_ns";
+
 /// TODO
 pub fn new(topology: &Topology) -> Blueprint {
     let topology = topology.clone();
+    let status = Arc::new(StatusRegistry::default());
+    let router_status = status.clone();
 
     ActorGroup::new()
         .config::<Config>()
         // The restart policy is overrided by the discovery actor.
         .restart_policy(RestartPolicy::never())
-        .router(MapRouter::new(|envelope| {
+        .router(MapRouter::new(move |envelope| {
             msg!(match envelope {
                 // TODO: send to all connections.
                 UpdateConfig => Outcome::Unicast(ActorKey::Discovery),
+                GetNetworkStatus => Outcome::Unicast(ActorKey::Discovery),
+                GetNodeMap => Outcome::Unicast(ActorKey::Discovery),
+                // Answered by any connection to the node.
+                msg @ CheckReachability => Outcome::GentleMulticast(
+                    router_status
+                        .groups_of(msg.node_no)
+                        .into_iter()
+                        .map(|(local, remote)| ActorKey::Worker { local, remote })
+                        .collect(),
+                ),
                 msg @ HandleConnection => Outcome::Unicast(ActorKey::Worker {
                     local: msg.local.clone(),
                     remote: msg.remote.clone(),
                 }),
+                msg @ CloseConnection => Outcome::GentleUnicast(ActorKey::Worker {
+                    local: msg.local.clone(),
+                    remote: msg.remote.clone(),
+                }),
                 _ => Outcome::Default,
             })
         }))
         .exec(move |ctx: Context<Config, ActorKey>| {
             let topology = topology.clone();
+            let status = status.clone();
             async move {
                 match ctx.key().clone() {
-                    ActorKey::Discovery => discovery::Discovery::new(ctx, topology).main().await,
+                    ActorKey::Discovery => {
+                        discovery::Discovery::new(ctx, topology, status)
+                            .main()
+                            .await
+                    }
                     ActorKey::Worker { local, remote } => {
-                        worker::Worker::new(ctx, local, remote, topology)
+                        worker::Worker::new(ctx, local, remote, topology, status)
                             .main()
                             .await
                     }
@@ -0,0 +1,132 @@
+//! Telemetry of connection lifecycle: durations of handshake phases and
+//! reasons of failed handshakes.
+
+use std::time::{Duration, Instant};
+
+use metrics::{counter, histogram};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    /// The TCP connection is opened. Only for outgoing connections.
+    TcpConnected,
+    /// Handshakes are exchanged, the version and capabilities are chosen.
+    VersionNegotiated,
+    /// `SwitchToControl` or `SwitchToData` messages are exchanged.
+    GroupsExchanged,
+    /// The connection is passed to the node map or a worker.
+    Established,
+}
+
+impl Phase {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::TcpConnected => "tcp_connected",
+            Self::VersionNegotiated => "version_negotiated",
+            Self::GroupsExchanged => "groups_exchanged",
+            Self::Established => "established",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failure {
+    /// Cannot open the TCP connection.
+    Connect,
+    /// The attempt, including the handshake, took too long.
+    Timeout,
+    /// The relay rejected the request or forwarded it to another node.
+    Relay,
+    /// Invalid handshake or I/O error while exchanging handshakes.
+    Handshake,
+    /// Cannot exchange `SwitchToControl` or `SwitchToData` messages.
+    Exchange,
+    /// The data connection refers to unknown groups.
+    UnknownGroup,
+    /// The worker cannot be started.
+    NoWorker,
+    /// The peer is denied by `system.network.peers`.
+    Denied,
+    /// The peer's groups don't handle protocols of pipelines to them.
+    Pipeline,
+}
+
+impl Failure {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Timeout => "timeout",
+            Self::Relay => "relay",
+            Self::Handshake => "handshake",
+            Self::Exchange => "exchange",
+            Self::UnknownGroup => "unknown_group",
+            Self::NoWorker => "no_worker",
+            Self::Denied => "denied",
+            Self::Pipeline => "pipeline",
+        }
+    }
+
+    pub(crate) fn count(self) {
+        counter!("elfo_network_handshake_failures_total", 1, "reason" => self.as_str());
+    }
+}
+
+/// Measures phases of a single connection's handshake.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HandshakeTimer {
+    started_at: Instant,
+    phase_started_at: Instant,
+}
+
+impl HandshakeTimer {
+    pub(crate) fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started_at: now,
+            phase_started_at: now,
+        }
+    }
+
+    /// Records the duration of the completed phase, i.e. the time since the
+    /// previous one. The total duration is recorded once established.
+    pub(crate) fn phase(&mut self, phase: Phase) {
+        let now = Instant::now();
+        let elapsed = now - self.phase_started_at;
+        self.phase_started_at = now;
+
+        histogram!(
+            "elfo_network_handshake_phase_seconds",
+            elapsed.as_secs_f64(),
+            "phase" => phase.as_str()
+        );
+        debug!(message = "handshake phase completed", phase = phase.as_str(), elapsed = ?elapsed);
+
+        if phase == Phase::Established {
+            histogram!("elfo_network_handshake_seconds", self.total().as_secs_f64());
+        }
+    }
+
+    pub(crate) fn total(&self) -> Duration {
+        self.phase_started_at - self.started_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer() {
+        let mut timer = HandshakeTimer::start();
+        assert_eq!(timer.total(), Duration::ZERO);
+
+        std::thread::sleep(Duration::from_millis(5));
+        timer.phase(Phase::TcpConnected);
+        let first = timer.total();
+        assert!(first >= Duration::from_millis(5));
+
+        std::thread::sleep(Duration::from_millis(5));
+        timer.phase(Phase::Established);
+        assert!(timer.total() >= first + Duration::from_millis(5));
+    }
+}
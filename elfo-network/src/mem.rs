@@ -0,0 +1,101 @@
+//! The in-process transport (`mem://<name>`) connecting nodes running in one
+//! process, e.g. in integration tests, without opening real sockets.
+//!
+//! Names are shared by all topologies in the process like TCP ports by all
+//! processes on the host, so only one node can listen to a name at a time.
+
+use eyre::{eyre, Result};
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tokio::{
+    io::{self, DuplexStream},
+    sync::mpsc,
+};
+
+/// The size of the buffer in each direction of a connection.
+const BUFFER_SIZE: usize = 64 * 1024;
+/// How many connections can wait for being accepted.
+const BACKLOG: usize = 128;
+
+static LISTENERS: Lazy<Mutex<FxHashMap<String, mpsc::Sender<DuplexStream>>>> =
+    Lazy::new(Default::default);
+
+/// Accepts connections to the name until dropped.
+pub(crate) struct Listener {
+    name: String,
+    rx: mpsc::Receiver<DuplexStream>,
+}
+
+impl Listener {
+    pub(crate) fn bind(name: &str) -> Result<Self> {
+        let mut listeners = LISTENERS.lock();
+
+        // The name can be still occupied by a listener being dropped.
+        if listeners.get(name).is_some_and(|tx| !tx.is_closed()) {
+            return Err(eyre!("the name is already in use"));
+        }
+
+        let (tx, rx) = mpsc::channel(BACKLOG);
+        listeners.insert(name.into(), tx);
+
+        Ok(Self {
+            name: name.into(),
+            rx,
+        })
+    }
+
+    pub(crate) async fn accept(&mut self) -> Option<DuplexStream> {
+        self.rx.recv().await
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.rx.close();
+
+        let mut listeners = LISTENERS.lock();
+        if listeners.get(&self.name).is_some_and(|tx| tx.is_closed()) {
+            listeners.remove(&self.name);
+        }
+    }
+}
+
+pub(crate) async fn connect(name: &str) -> Result<DuplexStream> {
+    let tx = LISTENERS.lock().get(name).cloned();
+    let tx = tx.ok_or_else(|| eyre!("nobody listens to the name"))?;
+
+    let (local, remote) = io::duplex(BUFFER_SIZE);
+    tx.send(remote)
+        .await
+        .map_err(|_| eyre!("nobody listens to the name"))?;
+
+    Ok(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_connects_by_name() {
+        assert!(connect("mem-test").await.is_err());
+
+        let mut listener = Listener::bind("mem-test").unwrap();
+        assert!(Listener::bind("mem-test").is_err());
+
+        let mut client = connect("mem-test").await.unwrap();
+        let mut server = listener.accept().await.unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buffer = [0; 4];
+        server.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"ping");
+
+        drop(listener);
+        assert!(connect("mem-test").await.is_err());
+        let _listener = Listener::bind("mem-test").unwrap();
+    }
+}
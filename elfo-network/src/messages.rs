@@ -0,0 +1,26 @@
+//! Messages sent by the network group to other groups.
+
+use elfo_core::{message, NodeNo};
+
+/// A message received from a remote node, but unknown to this node, e.g.
+/// added in a newer version of the remote node. Such messages are skipped.
+///
+/// If `forward_unknown_messages` is enabled in the config, it's sent as the
+/// `messages::DeadLetter` with the "unknown message" reason, so it should be
+/// routed to some group collecting dead letters by the topology.
+#[message]
+#[non_exhaustive]
+pub struct UnknownMessage {
+    /// The node the message is received from.
+    pub remote_node_no: NodeNo,
+    /// A name of the remote group the message is received from.
+    pub remote_group: String,
+    /// The protocol of the message.
+    pub protocol: String,
+    /// The name of the message.
+    pub name: String,
+    /// The version of the message.
+    pub version: u8,
+    /// The message encoded as msgpack.
+    pub payload: Vec<u8>,
+}
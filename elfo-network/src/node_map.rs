@@ -1,25 +1,35 @@
+use std::time::{Duration, Instant, SystemTime};
+
 use fxhash::FxHashMap;
 use parking_lot::Mutex;
 
 use elfo_core::{
     _priv::{NodeLaunchId, NodeNo},
-    node,
     topology::Topology,
 };
 
-use crate::protocol::internode::GroupInfo;
+use crate::{
+    config::Transport,
+    protocol::internode::GroupInfo,
+    status::{NodeMapStatus, NodeStatus, RouteStatus, StatusRegistry},
+};
 
 // TODO: move to discovery?
 
 pub(crate) struct NodeMap {
     pub(crate) nodes: Mutex<FxHashMap<NodeNo, NodeInfo>>,
+    pub(crate) routes: Mutex<FxHashMap<NodeNo, Route>>,
+    /// When the last connection to the node was established.
+    pub(crate) last_seen: Mutex<FxHashMap<NodeNo, SystemTime>>,
+    /// When the node was found without connections, updated by `evict()`.
+    disconnected_since: Mutex<FxHashMap<NodeNo, Instant>>,
     pub(crate) this: NodeInfo,
 }
 
 impl NodeMap {
     pub(crate) fn new(topology: &Topology) -> Self {
         let this = NodeInfo {
-            node_no: node::node_no().expect("node no is not set"),
+            node_no: topology.node_no().expect("node no is not set"),
             launch_id: topology.launch_id(),
             groups: topology
                 .locals()
@@ -44,9 +54,95 @@ impl NodeMap {
 
         Self {
             nodes: Default::default(),
+            routes: Default::default(),
+            last_seen: Default::default(),
+            disconnected_since: Default::default(),
             this,
         }
     }
+
+    /// Forgets nodes without established connections for at least `ttl`.
+    /// Returns evicted nodes and how long they have been disconnected.
+    ///
+    /// The disconnection is detected by this method, so it must be called
+    /// periodically, more often than `ttl`.
+    pub(crate) fn evict(
+        &self,
+        status: &StatusRegistry,
+        ttl: Duration,
+        now: Instant,
+    ) -> Vec<(NodeNo, Duration)> {
+        let mut nodes = self.nodes.lock();
+        let mut routes = self.routes.lock();
+        let mut disconnected_since = self.disconnected_since.lock();
+
+        let mut known = nodes
+            .keys()
+            .chain(routes.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        known.sort_by_key(|node_no| node_no.into_bits());
+        known.dedup();
+
+        let mut evicted = Vec::new();
+
+        for node_no in known {
+            if status.connections_to(node_no) > 0 {
+                disconnected_since.remove(&node_no);
+                continue;
+            }
+
+            let since = *disconnected_since.entry(node_no).or_insert(now);
+            let elapsed = now.saturating_duration_since(since);
+
+            if elapsed >= ttl {
+                nodes.remove(&node_no);
+                routes.remove(&node_no);
+                self.last_seen.lock().remove(&node_no);
+                disconnected_since.remove(&node_no);
+                evicted.push((node_no, elapsed));
+            }
+        }
+
+        evicted
+    }
+
+    pub(crate) fn snapshot(&self, status: &StatusRegistry) -> NodeMapStatus {
+        let nodes = self.nodes.lock();
+        let routes = self.routes.lock();
+        let last_seen = self.last_seen.lock();
+
+        let mut node_nos = nodes.keys().chain(routes.keys()).collect::<Vec<_>>();
+        node_nos.sort_by_key(|node_no| node_no.into_bits());
+        node_nos.dedup();
+
+        let nodes = node_nos
+            .into_iter()
+            .map(|node_no| {
+                let info = nodes.get(node_no);
+
+                NodeStatus {
+                    node_no: *node_no,
+                    launch_id: info.map(|info| info.launch_id.into_bits()),
+                    groups: info
+                        .map(|info| info.groups.iter().map(|g| g.name.clone()).collect())
+                        .unwrap_or_default(),
+                    route: routes.get(node_no).map(|route| RouteStatus {
+                        transport: route.transport.to_string(),
+                        via: route.via,
+                        hops: route.hops,
+                    }),
+                    connections: status.connections_to(*node_no),
+                    last_seen: last_seen.get(node_no).copied(),
+                }
+            })
+            .collect();
+
+        NodeMapStatus {
+            this_node_no: self.this.node_no,
+            nodes,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -55,3 +151,154 @@ pub(crate) struct NodeInfo {
     pub(crate) launch_id: NodeLaunchId,
     pub(crate) groups: Vec<GroupInfo>,
 }
+
+/// How to reach a node.
+#[derive(Clone)]
+pub(crate) struct Route {
+    /// The node itself or the first relay on the way.
+    pub(crate) transport: Transport,
+    /// `None` if the node is reachable directly.
+    pub(crate) via: Option<NodeNo>,
+    /// The number of relays on the way.
+    pub(crate) hops: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use elfo_core::_priv::GroupNo;
+
+    use super::*;
+    use crate::{config, protocol, socket};
+
+    fn node_no(no: u16) -> NodeNo {
+        NodeNo::from_bits(no).unwrap()
+    }
+
+    fn node(no: u16, groups: &[&str]) -> NodeInfo {
+        NodeInfo {
+            node_no: node_no(no),
+            launch_id: NodeLaunchId::from_bits(no.into()),
+            groups: groups
+                .iter()
+                .enumerate()
+                .map(|(i, name)| GroupInfo {
+                    group_no: GroupNo::from_bits(i as u8 + 1).unwrap(),
+                    name: name.to_string(),
+                    interests: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn peer(no: u16) -> socket::Peer {
+        socket::Peer {
+            node_no: node_no(no),
+            launch_id: NodeLaunchId::from_bits(no.into()),
+            transport: config::parse_transport("tcp://127.0.0.1:4242").unwrap(),
+            relay: None,
+        }
+    }
+
+    fn make_map() -> NodeMap {
+        NodeMap {
+            nodes: Default::default(),
+            routes: Default::default(),
+            last_seen: Default::default(),
+            disconnected_since: Default::default(),
+            this: node(1, &["local"]),
+        }
+    }
+
+    fn group(node_no_: u16, name: &str) -> protocol::GroupInfo {
+        protocol::GroupInfo {
+            node_no: node_no(node_no_),
+            group_no: GroupNo::from_bits(1).unwrap(),
+            group_name: name.into(),
+        }
+    }
+
+    #[test]
+    fn snapshot() {
+        let map = make_map();
+
+        let direct = Route {
+            transport: config::parse_transport("tcp://127.0.0.1:4242").unwrap(),
+            via: None,
+            hops: 0,
+        };
+        let relayed = Route {
+            via: Some(node_no(3)),
+            hops: 1,
+            ..direct.clone()
+        };
+
+        map.nodes.lock().insert(node_no(3), node(3, &["a", "b"]));
+        map.routes.lock().insert(node_no(3), direct);
+        map.routes.lock().insert(node_no(2), relayed);
+        map.last_seen
+            .lock()
+            .insert(node_no(3), SystemTime::UNIX_EPOCH);
+
+        let status = Arc::new(StatusRegistry::default());
+        let _guard = status.register(&group(1, "local"), &group(3, "a"), &peer(3));
+
+        let snapshot = map.snapshot(&status);
+        assert_eq!(snapshot.this_node_no, node_no(1));
+        assert_eq!(snapshot.nodes.len(), 2);
+
+        // Only reachable through the relay.
+        let node = &snapshot.nodes[0];
+        assert_eq!(node.node_no, node_no(2));
+        assert_eq!(node.launch_id, None);
+        assert!(node.groups.is_empty());
+        assert_eq!(node.route.as_ref().unwrap().via, Some(node_no(3)));
+        assert_eq!(node.connections, 0);
+        assert_eq!(node.last_seen, None);
+
+        let node = &snapshot.nodes[1];
+        assert_eq!(node.node_no, node_no(3));
+        assert_eq!(node.launch_id, Some(3));
+        assert_eq!(node.groups, vec!["a", "b"]);
+        assert_eq!(
+            node.route.as_ref().unwrap().transport,
+            "tcp://127.0.0.1:4242"
+        );
+        assert_eq!(node.connections, 1);
+        assert_eq!(node.last_seen, Some(SystemTime::UNIX_EPOCH));
+    }
+
+    #[test]
+    fn evict() {
+        let map = make_map();
+        let status = Arc::new(StatusRegistry::default());
+        let ttl = Duration::from_secs(10);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        map.nodes.lock().insert(node_no(2), node(2, &["a"]));
+        map.nodes.lock().insert(node_no(3), node(3, &["a"]));
+        map.last_seen.lock().insert(node_no(2), SystemTime::now());
+        let guard = status.register(&group(1, "local"), &group(3, "a"), &peer(3));
+
+        // Disconnection is detected, but it's too early.
+        assert!(map.evict(&status, ttl, at(0)).is_empty());
+        assert!(map.evict(&status, ttl, at(9)).is_empty());
+
+        let evicted = map.evict(&status, ttl, at(10));
+        assert_eq!(evicted, vec![(node_no(2), ttl)]);
+        assert!(!map.nodes.lock().contains_key(&node_no(2)));
+        assert!(map.last_seen.lock().is_empty());
+
+        // The timer is reset by a connection.
+        drop(guard);
+        assert!(map.evict(&status, ttl, at(15)).is_empty());
+        let guard = status.register(&group(1, "local"), &group(3, "a"), &peer(3));
+        assert!(map.evict(&status, ttl, at(30)).is_empty());
+        drop(guard);
+        assert!(map.evict(&status, ttl, at(35)).is_empty());
+        assert_eq!(map.evict(&status, ttl, at(45)).len(), 1);
+        assert!(map.nodes.lock().is_empty());
+    }
+}
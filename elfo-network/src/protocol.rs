@@ -17,6 +17,13 @@ pub(crate) struct HandleConnection {
     // TODO: different windows for rx/tx and routed flows.
 }
 
+/// Closes the established connection denied by `system.network.peers`.
+#[message]
+pub(crate) struct CloseConnection {
+    pub(crate) local: GroupInfo,
+    pub(crate) remote: GroupInfo,
+}
+
 #[message(part)]
 #[derive(PartialEq, Eq, Hash)]
 pub(crate) struct GroupInfo {
@@ -43,9 +50,27 @@ pub(crate) mod internode {
     //      SwitchToData -->
     //                   <-- SwitchToData
     //                  ...
+    //      Ping -->                 <-- Ping
+    //      Pong -->                 <-- Pong
+    //      (only if both nodes support `Capabilities::REACHABILITY_CHECK`)
+    //                  ...
     //      UpdateFlow -->
     //                  ...
     //                     <-- UpdateFlow
+    //                  ...
+    //      CancelRequest -->
+    //      (only if both nodes support `Capabilities::CANCELLATION`)
+    //
+    //           relayed connection
+    //      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    //      (client)    (relay)    (server)
+    //      RelayPreamble -->
+    //             <-- Handshake
+    //                      ...connect...
+    //      Handshake -->          -->
+    //              <--            <-- Handshake
+    //                  ...
+    //      Frames are passed through as is.
     //
     //             any connection
     //      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -59,6 +84,33 @@ pub(crate) mod internode {
     #[message]
     pub(crate) struct SwitchToControl {
         pub(crate) groups: Vec<GroupInfo>,
+        /// Transports the node is reachable at (`advertise_addr` or `listen`),
+        /// used by peers and relays to reach it.
+        #[serde(default)]
+        pub(crate) listen: Vec<String>,
+        /// Nodes reachable through the node, `None` if it isn't a relay.
+        #[serde(default)]
+        pub(crate) routes: Option<Vec<Route>>,
+        #[serde(default)]
+        pub(crate) zone: Option<String>,
+        /// Protocols handled by local groups, used to check pipelines.
+        /// `None` if the node doesn't send them.
+        #[serde(default)]
+        pub(crate) handled_protocols: Option<Vec<HandledProtocols>>,
+    }
+
+    #[message(part)]
+    pub(crate) struct HandledProtocols {
+        pub(crate) group_no: GroupNo,
+        /// `None` if the group doesn't declare handled protocols.
+        pub(crate) protocols: Option<Vec<String>>,
+    }
+
+    #[message(part)]
+    pub(crate) struct Route {
+        pub(crate) node_no: NodeNo,
+        /// The number of relays between the receiver and the node.
+        pub(crate) hops: u8,
     }
 
     #[message(part)]
@@ -91,6 +143,13 @@ pub(crate) mod internode {
         pub(crate) addr: NetworkAddr,
     }
 
+    /// The requester doesn't wait for the response anymore.
+    #[message]
+    pub(crate) struct CancelRequest {
+        pub(crate) sender: NetworkAddr,
+        pub(crate) request_id: u64,
+    }
+
     #[message]
     pub(crate) struct Ping {
         pub(crate) payload: u64,
@@ -99,5 +158,9 @@ pub(crate) mod internode {
     #[message]
     pub(crate) struct Pong {
         pub(crate) payload: u64,
+        /// The unix time in nanoseconds when the ping is handled.
+        /// Used to estimate clock skew, missing in old versions.
+        #[serde(default)]
+        pub(crate) time_ns: Option<u64>,
     }
 }
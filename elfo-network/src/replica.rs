@@ -0,0 +1,324 @@
+//! Local read replicas of a state owned by another group, usually located on
+//! another node, e.g. reference data.
+//!
+//! The owner keeps [`ReplicaOwner`], which sends the whole state to new
+//! subscribers and pushes diffs to all of them afterwards. Subscribers keep
+//! [`ReplicaCache`], which applies diffs to the local copy and resubscribes
+//! if a diff is missed or the owner is restarted.
+//!
+//! ```ignore
+//! // The owner's side.
+//! let mut owner = ReplicaOwner::new("rates");
+//! while let Some(envelope) = ctx.recv().await {
+//!     let sender = envelope.sender();
+//!     msg!(match envelope {
+//!         (ReplicaSubscribe, token) => owner.subscribe(&ctx, sender, token, &rates),
+//!         SetRate { pair, rate } => {
+//!             let diff = RatesDiff { pair, rate };
+//!             owner.publish(&ctx, &diff);
+//!             rates.apply(diff);
+//!         }
+//!     });
+//! }
+//!
+//! // The subscriber's side.
+//! let mut cache = ReplicaCache::<Rates>::new("rates");
+//! cache.subscribe(&ctx).await?;
+//! while let Some(envelope) = ctx.recv().await {
+//!     msg!(match envelope {
+//!         update @ ReplicaUpdate => cache.handle(&ctx, &update).await?,
+//!         GetRate { pair } => cache.get().and_then(|rates| rates.get(&pair)),
+//!     });
+//! }
+//! ```
+//!
+//! Subscription requests are routed by the topology, so the subscriber's
+//! group must be connected to the owner's one.
+//!
+//! The age of the latest known state is exposed by the
+//! `elfo_replica_staleness_seconds` gauge, updated on every received update.
+//! Owners should call [`ReplicaOwner::heartbeat()`] periodically to make it
+//! meaningful for rarely changing states.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use metrics::gauge;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{info, warn};
+
+use elfo_core::{_priv::rmps, errors::RequestError, message, Addr, Context, ResponseToken};
+
+use crate::{blob::BlobData, status::unix_time_ns};
+
+/// A state, which can be replicated by diffs.
+pub trait Replicated: Serialize + DeserializeOwned {
+    /// An incremental change of the state.
+    type Diff: Serialize + DeserializeOwned;
+
+    /// Applies the diff to the state.
+    fn apply(&mut self, diff: Self::Diff);
+}
+
+/// A request to subscribe to the replica, handled by the owner.
+#[message(ret = ReplicaSnapshot)]
+#[non_exhaustive]
+pub struct ReplicaSubscribe {
+    /// The name of the replica.
+    pub name: String,
+}
+
+/// The whole state, a response to [`ReplicaSubscribe`].
+#[message(part)]
+#[non_exhaustive]
+pub struct ReplicaSnapshot {
+    /// Changed once the owner is restarted.
+    pub epoch: u64,
+    /// The version of the state.
+    pub version: u64,
+    /// The serialized state.
+    pub state: BlobData,
+}
+
+/// A diff pushed by the owner to subscribers.
+#[message]
+#[non_exhaustive]
+pub struct ReplicaUpdate {
+    /// The name of the replica.
+    pub name: String,
+    /// Changed once the owner is restarted.
+    pub epoch: u64,
+    /// The version of the state after applying the diff.
+    pub version: u64,
+    /// The unix time in nanoseconds when the update was sent.
+    pub sent_time: u64,
+    /// The serialized diff, `None` for heartbeats.
+    pub diff: Option<BlobData>,
+}
+
+/// An error returned by [`ReplicaCache`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReplicaError {
+    /// The owner hasn't responded to the subscription.
+    Request(RequestError),
+    /// The state or diff cannot be decoded.
+    Decode(rmps::decode::Error),
+}
+
+impl fmt::Display for ReplicaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "cannot subscribe: {err}"),
+            Self::Decode(err) => write!(f, "cannot decode: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplicaError {}
+
+// === ReplicaOwner ===
+
+/// Serves subscribers of the replicated state.
+pub struct ReplicaOwner {
+    name: String,
+    epoch: u64,
+    version: u64,
+    subscribers: Vec<Addr>,
+}
+
+impl ReplicaOwner {
+    /// Creates a new owner of the replica with the provided name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            epoch: unix_time_ns(SystemTime::now()),
+            version: 0,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Returns the number of subscribers.
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Adds the sender of [`ReplicaSubscribe`] to subscribers and responds
+    /// with the current state.
+    pub fn subscribe<C, K>(
+        &mut self,
+        ctx: &Context<C, K>,
+        sender: Addr,
+        token: ResponseToken<ReplicaSubscribe>,
+        state: &impl Serialize,
+    ) {
+        let state = match rmps::to_vec_named(state) {
+            Ok(state) => state,
+            Err(err) => {
+                warn!(message = "cannot encode the replica", name = %self.name, error = %err);
+                return;
+            }
+        };
+
+        if !self.subscribers.contains(&sender) {
+            self.subscribers.push(sender);
+        }
+
+        ctx.respond(
+            token,
+            ReplicaSnapshot {
+                epoch: self.epoch,
+                version: self.version,
+                state: BlobData(state),
+            },
+        );
+    }
+
+    /// Pushes the diff to all subscribers. It must be applied to the state
+    /// passed to further `subscribe()` calls.
+    pub fn publish<C, K>(&mut self, ctx: &Context<C, K>, diff: &impl Serialize) {
+        match rmps::to_vec_named(diff) {
+            Ok(diff) => {
+                self.version += 1;
+                self.push(ctx, Some(BlobData(diff)));
+            }
+            Err(err) => warn!(message = "cannot encode the diff", name = %self.name, error = %err),
+        }
+    }
+
+    /// Notifies subscribers that the state hasn't changed.
+    pub fn heartbeat<C, K>(&mut self, ctx: &Context<C, K>) {
+        self.push(ctx, None);
+    }
+
+    fn push<C, K>(&mut self, ctx: &Context<C, K>, diff: Option<BlobData>) {
+        let update = ReplicaUpdate {
+            name: self.name.clone(),
+            epoch: self.epoch,
+            version: self.version,
+            sent_time: unix_time_ns(SystemTime::now()),
+            diff,
+        };
+
+        // Unavailable subscribers resubscribe once they notice a gap.
+        self.subscribers
+            .retain(|addr| match ctx.try_send_to(*addr, update.clone()) {
+                Err(err) if err.is_closed() => {
+                    info!(message = "subscriber is closed, unsubscribing", %addr);
+                    false
+                }
+                _ => true,
+            });
+    }
+}
+
+// === ReplicaCache ===
+
+/// A local copy of the replicated state.
+pub struct ReplicaCache<S> {
+    name: String,
+    state: Option<S>,
+    epoch: u64,
+    version: u64,
+    synced_time: Option<u64>,
+}
+
+impl<S: Replicated> ReplicaCache<S> {
+    /// Creates an empty cache of the replica with the provided name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            state: None,
+            epoch: 0,
+            version: 0,
+            synced_time: None,
+        }
+    }
+
+    /// Returns the state, `None` until subscribed.
+    pub fn get(&self) -> Option<&S> {
+        self.state.as_ref()
+    }
+
+    /// Returns the version of the state.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the age of the latest known state according to the owner's
+    /// clock. `None` until the first update.
+    pub fn staleness(&self) -> Option<Duration> {
+        let synced_time = self.synced_time?;
+        let now = unix_time_ns(SystemTime::now());
+        Some(Duration::from_nanos(now.saturating_sub(synced_time)))
+    }
+
+    /// Requests the whole state from the owner.
+    pub async fn subscribe<C: 'static, K>(
+        &mut self,
+        ctx: &Context<C, K>,
+    ) -> Result<(), ReplicaError> {
+        let snapshot = ctx
+            .request(ReplicaSubscribe {
+                name: self.name.clone(),
+            })
+            .resolve()
+            .await
+            .map_err(ReplicaError::Request)?;
+
+        let state = rmps::from_slice(&snapshot.state.0).map_err(ReplicaError::Decode)?;
+        self.state = Some(state);
+        self.epoch = snapshot.epoch;
+        self.version = snapshot.version;
+        Ok(())
+    }
+
+    /// Applies the update, resubscribes if it doesn't follow the current
+    /// version. Updates of other replicas are ignored.
+    pub async fn handle<C: 'static, K>(
+        &mut self,
+        ctx: &Context<C, K>,
+        update: &ReplicaUpdate,
+    ) -> Result<(), ReplicaError> {
+        if update.name != self.name {
+            return Ok(());
+        }
+
+        let is_known = update.epoch == self.epoch && self.state.is_some();
+
+        // Outdated, e.g. sent before the snapshot.
+        if is_known && update.version <= self.version && update.diff.is_some() {
+            return Ok(());
+        }
+
+        match (&update.diff, &mut self.state) {
+            (Some(diff), Some(state)) if is_known && update.version == self.version + 1 => {
+                state.apply(rmps::from_slice(&diff.0).map_err(ReplicaError::Decode)?);
+                self.version = update.version;
+            }
+            (None, Some(_)) if is_known && update.version == self.version => {}
+            _ => {
+                info!(
+                    message = "replica is out of sync, resubscribing",
+                    name = %self.name,
+                    version = self.version,
+                    received = update.version,
+                );
+                self.subscribe(ctx).await?;
+            }
+        }
+
+        self.synced_time = Some(self.synced_time.unwrap_or(0).max(update.sent_time));
+        if let Some(staleness) = self.staleness() {
+            gauge!(
+                "elfo_replica_staleness_seconds",
+                staleness.as_secs_f64(),
+                "replica" => self.name.clone()
+            );
+        }
+
+        Ok(())
+    }
+}
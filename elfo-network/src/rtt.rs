@@ -28,6 +28,10 @@ impl Rtt {
 
         self.ema = Some(ema);
     }
+
+    pub(crate) fn get(&self) -> Option<Duration> {
+        self.ema.map(Duration::from_secs_f64)
+    }
 }
 
 impl Drop for Rtt {
@@ -0,0 +1,71 @@
+use metrics::gauge;
+
+/// Estimates the offset of the remote clock using timestamps of pings.
+pub(crate) struct ClockSkew {
+    ema: Option<f64>,
+    alpha: f64,
+}
+
+impl ClockSkew {
+    pub(crate) fn new(samples: usize) -> Self {
+        // https://en.wikipedia.org/wiki/Moving_average#Relationship_between_SMA_and_EMA
+        let alpha = 2.0 / (samples + 1) as f64;
+
+        Self { ema: None, alpha }
+    }
+
+    /// Pushes a new sample, all times are the unix time in nanoseconds:
+    /// * `sent` — the local time when the ping is sent.
+    /// * `remote` — the remote time when the ping is handled.
+    /// * `received` — the local time when the pong is received.
+    ///
+    /// Assumes that the network delay is symmetric like NTP does.
+    pub(crate) fn push(&mut self, sent: u64, remote: u64, received: u64) {
+        let midpoint = (i128::from(sent) + i128::from(received)) / 2;
+        let offset = (i128::from(remote) - midpoint) as f64 / 1e9;
+
+        let ema = if let Some(ema) = self.ema {
+            ema * (1.0 - self.alpha) + offset * self.alpha
+        } else {
+            offset
+        };
+
+        gauge!("elfo_network_clock_offset_seconds", ema);
+
+        self.ema = Some(ema);
+    }
+
+    /// Returns the estimated offset in seconds, positive if the remote clock
+    /// is ahead.
+    pub(crate) fn get(&self) -> Option<f64> {
+        self.ema
+    }
+}
+
+impl Drop for ClockSkew {
+    fn drop(&mut self) {
+        if self.ema.is_some() {
+            gauge!("elfo_network_clock_offset_seconds", f64::NAN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut skew = ClockSkew::new(5);
+        assert_eq!(skew.get(), None);
+
+        // The remote clock is 2s ahead, the delay is 10ms for both directions.
+        skew.push(1_000_000_000, 3_010_000_000, 1_020_000_000);
+        assert_eq!(skew.get(), Some(2.0));
+
+        // The remote clock is 2s behind.
+        let mut skew = ClockSkew::new(5);
+        skew.push(5_000_000_000, 3_010_000_000, 5_020_000_000);
+        assert_eq!(skew.get(), Some(-2.0));
+    }
+}
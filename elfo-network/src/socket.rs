@@ -1,14 +1,19 @@
-use std::{io::Cursor, net::SocketAddr};
+use std::{
+    io::{Cursor, IoSlice},
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use derive_more::Display;
 use eyre::{eyre, Result, WrapErr};
-use futures::Future;
+use futures::{stream::BoxStream, Future, StreamExt};
 use metrics::counter;
 use tokio::{
-    io,
+    io::{self, AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
     net::{
-        tcp::{self, OwnedReadHalf, OwnedWriteHalf},
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpListener, TcpStream,
     },
 };
@@ -22,17 +27,147 @@ use crate::{
     config::Transport,
     frame::{
         read::{FramedRead, FramedReadState, FramedReadStrategy},
-        write::{FrameState, FramedWrite, FramedWriteStrategy},
+        write::{FrameState, FramedWrite, FramedWriteStats, FramedWriteStrategy},
     },
+    lifecycle::{Failure, HandshakeTimer, Phase},
+    mem,
     node_map::NodeInfo,
 };
 
+// === Stream ===
+
+/// A connection of any transport.
+enum Stream {
+    Tcp(TcpStream),
+    Mem(DuplexStream),
+}
+
+enum ReadStream {
+    Tcp(OwnedReadHalf),
+    Mem(io::ReadHalf<DuplexStream>),
+}
+
+enum WriteStream {
+    Tcp(OwnedWriteHalf),
+    Mem(io::WriteHalf<DuplexStream>),
+}
+
+impl Stream {
+    fn into_split(self) -> (ReadStream, WriteStream) {
+        match self {
+            Self::Tcp(stream) => {
+                let (read, write) = stream.into_split();
+                (ReadStream::Tcp(read), WriteStream::Tcp(write))
+            }
+            Self::Mem(stream) => {
+                let (read, write) = io::split(stream);
+                (ReadStream::Mem(read), WriteStream::Mem(write))
+            }
+        }
+    }
+
+    fn reunite(read: ReadStream, write: WriteStream) -> Result<Self> {
+        match (read, write) {
+            (ReadStream::Tcp(read), WriteStream::Tcp(write)) => Ok(Self::Tcp(read.reunite(write)?)),
+            (ReadStream::Mem(read), WriteStream::Mem(write)) if read.is_pair_of(&write) => {
+                Ok(Self::Mem(read.unsplit(write)))
+            }
+            _ => Err(eyre!("halves of different streams")),
+        }
+    }
+}
+
+macro_rules! delegate_read {
+    ($ty:ident) => {
+        impl AsyncRead for $ty {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                match self.get_mut() {
+                    $ty::Tcp(inner) => Pin::new(inner).poll_read(cx, buf),
+                    $ty::Mem(inner) => Pin::new(inner).poll_read(cx, buf),
+                }
+            }
+        }
+    };
+}
+
+macro_rules! delegate_write {
+    ($ty:ident) => {
+        impl AsyncWrite for $ty {
+            fn poll_write(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                match self.get_mut() {
+                    $ty::Tcp(inner) => Pin::new(inner).poll_write(cx, buf),
+                    $ty::Mem(inner) => Pin::new(inner).poll_write(cx, buf),
+                }
+            }
+
+            fn poll_write_vectored(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                bufs: &[IoSlice<'_>],
+            ) -> Poll<io::Result<usize>> {
+                match self.get_mut() {
+                    $ty::Tcp(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
+                    $ty::Mem(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
+                }
+            }
+
+            fn is_write_vectored(&self) -> bool {
+                match self {
+                    $ty::Tcp(inner) => inner.is_write_vectored(),
+                    $ty::Mem(inner) => inner.is_write_vectored(),
+                }
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                match self.get_mut() {
+                    $ty::Tcp(inner) => Pin::new(inner).poll_flush(cx),
+                    $ty::Mem(inner) => Pin::new(inner).poll_flush(cx),
+                }
+            }
+
+            fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                match self.get_mut() {
+                    $ty::Tcp(inner) => Pin::new(inner).poll_shutdown(cx),
+                    $ty::Mem(inner) => Pin::new(inner).poll_shutdown(cx),
+                }
+            }
+        }
+    };
+}
+
+delegate_read!(Stream);
+delegate_read!(ReadStream);
+delegate_write!(Stream);
+delegate_write!(WriteStream);
+
 // === Socket ===
 
 bitflags::bitflags! {
     #[derive(Clone, Copy)]
     pub(crate) struct Capabilities: u32 {
         const LZ4 = 1 << 8;
+        /// Envelopes can contain the sent time, see the `codec` module.
+        const SENT_TIME = 1 << 9;
+        /// Large envelopes can be sent in chunks, see the `codec` module.
+        const CHUNKING = 1 << 10;
+        /// Data connections start with a ping round-trip, see `worker::reachability`.
+        const REACHABILITY_CHECK = 1 << 11;
+        /// Envelopes can contain the baggage, see the `codec` module.
+        const BAGGAGE = 1 << 12;
+        /// Envelopes can contain message ids, see the `codec` module.
+        const MESSAGE_IDS = 1 << 13;
+        /// Envelopes can contain message versions, see the `codec` module.
+        const VERSIONS = 1 << 14;
+        /// Requests can be canceled by requesters, see `internode::CancelRequest`.
+        const CANCELLATION = 1 << 15;
     }
 }
 
@@ -51,9 +186,7 @@ const HANDSHAKE_MAGIC: u64 = 0xE1F0E1F0E1F0E1F0;
 
 impl Handshake {
     pub(crate) fn make_containing_buf() -> Vec<u8> {
-        let mut buffer = Vec::new();
-        buffer.resize(HANDSHAKE_LENGTH, 0);
-        buffer
+        vec![0; HANDSHAKE_LENGTH]
     }
 
     pub(crate) fn new(this_node: &NodeInfo, capabilities: Capabilities) -> Self {
@@ -106,19 +239,76 @@ impl Handshake {
     }
 }
 
-pub(crate) struct TcpSocket {
-    read: OwnedReadHalf,
-    write: OwnedWriteHalf,
-    pub(crate) peer: Transport,
+/// The node to connect to through a relay.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RelayTarget {
+    pub(crate) node_no: NodeNo,
+    /// The number of relays allowed to pass through.
+    pub(crate) ttl: u8,
+}
+
+/// Sent instead of the handshake to ask the peer to forward the connection
+/// to the target node. The peer still responds with its own handshake, then
+/// bytes are passed through as is, so the real handshake is performed with
+/// the target node.
+struct RelayPreamble {
+    version: u8,
+    target: RelayTarget,
 }
 
-impl TcpSocket {
-    fn new(stream: TcpStream, peer: Transport) -> Self {
+// The same length as `Handshake` to distinguish them by magic.
+const RELAY_MAGIC: u64 = 0xE1F0E1F0E1F0E1F1;
+
+impl RelayPreamble {
+    fn as_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = Cursor::new(Handshake::make_containing_buf());
+
+        buf.write_u64::<LittleEndian>(RELAY_MAGIC)?;
+        buf.write_u8(self.version)?;
+        buf.write_u16::<LittleEndian>(self.target.node_no.into_bits())?;
+        buf.write_u8(self.target.ttl)?;
+
+        Ok(buf.into_inner())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>> {
+        let mut input = Cursor::new(bytes);
+
+        if input.read_u64::<LittleEndian>()? != RELAY_MAGIC {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            version: input.read_u8()?,
+            target: RelayTarget {
+                node_no: NodeNo::from_bits(input.read_u16::<LittleEndian>()?)
+                    .ok_or_else(|| eyre!("invalid node no"))?,
+                ttl: input.read_u8()?,
+            },
+        }))
+    }
+}
+
+/// A connection before the handshake.
+struct RawSocket {
+    read: ReadStream,
+    write: WriteStream,
+    peer: Transport,
+    timer: HandshakeTimer,
+}
+
+impl RawSocket {
+    fn new(stream: Stream, peer: Transport, timer: HandshakeTimer) -> Self {
         let (read, write) = stream.into_split();
-        Self { read, write, peer }
+        Self {
+            read,
+            write,
+            peer,
+            timer,
+        }
     }
 
-    pub(crate) async fn handshake(
+    async fn handshake(
         mut self,
         this_node: &NodeInfo,
         capabilities: Capabilities,
@@ -128,7 +318,40 @@ impl TcpSocket {
 
         let mut buffer = Handshake::make_containing_buf();
         io::AsyncReadExt::read_exact(&mut self.read, &mut buffer).await?;
-        let other_node_handshake = Handshake::from_bytes(&buffer)?;
+        self.finish_handshake(this_node_handshake, &buffer)
+    }
+
+    /// Like `handshake()`, but also accepts relay requests.
+    async fn accept(
+        mut self,
+        this_node: &NodeInfo,
+        capabilities: Capabilities,
+    ) -> Result<Option<Incoming>> {
+        let this_node_handshake = Handshake::new(this_node, capabilities);
+        io::AsyncWriteExt::write_all(&mut self.write, &this_node_handshake.as_bytes()?).await?;
+
+        let mut buffer = Handshake::make_containing_buf();
+        io::AsyncReadExt::read_exact(&mut self.read, &mut buffer).await?;
+
+        if let Some(preamble) = RelayPreamble::from_bytes(&buffer)? {
+            return Ok(Some(Incoming::Relay(RelayRequest {
+                stream: Stream::reunite(self.read, self.write)?,
+                peer: self.peer,
+                target: preamble.target,
+            })));
+        }
+
+        let socket = self.finish_handshake(this_node_handshake, &buffer)?;
+        Ok(socket.map(Incoming::Socket))
+    }
+
+    fn finish_handshake(
+        mut self,
+        this_node_handshake: Handshake,
+        buffer: &[u8],
+    ) -> Result<Option<Socket>> {
+        let other_node_handshake = Handshake::from_bytes(buffer)?;
+        self.timer.phase(Phase::VersionNegotiated);
 
         if this_node_handshake.node_no == other_node_handshake.node_no {
             return Ok(None);
@@ -138,6 +361,7 @@ impl TcpSocket {
             node_no: other_node_handshake.node_no,
             launch_id: other_node_handshake.launch_id,
             transport: self.peer,
+            relay: None,
         };
         let version = this_node_handshake
             .version
@@ -146,63 +370,123 @@ impl TcpSocket {
             .capabilities
             .intersection(other_node_handshake.capabilities);
 
-        Ok(Some(Socket::tcp(
+        Ok(Some(Socket::new(
             self.read,
             self.write,
             peer,
             version,
             capabilities,
+            self.timer,
         )))
     }
 }
 
+// TODO: once TLS/auth is supported, keep the authenticated identity (e.g. a
+// peer       certificate) here and expose it on received envelopes as
+//       `envelope.remote_identity()`. Fields below are declared by the peer in
+//       the handshake and aren't verified, so they mustn't be used for access
+//       control.
 #[derive(Display, Clone)]
 #[display(fmt = "peer(node_no={node_no}, launch_id={launch_id}, transport={transport})")]
 pub(crate) struct Peer {
     pub(crate) node_no: NodeNo,
     pub(crate) launch_id: NodeLaunchId,
     pub(crate) transport: Transport,
+    /// Set if the peer is connected through the relay at `transport`.
+    pub(crate) relay: Option<RelayTarget>,
 }
 
-// TODO: Make `Socket`, `ReadHalf` and `WriteHalf` generic over transport type.
 pub(crate) struct Socket {
     pub(crate) read: ReadHalf,
     pub(crate) write: WriteHalf,
     pub(crate) peer: Peer,
+    pub(crate) capabilities: Capabilities,
+    /// Finished once the connection is established.
+    pub(crate) timer: HandshakeTimer,
 }
 
 impl Socket {
-    fn tcp(
-        read: OwnedReadHalf,
-        write: OwnedWriteHalf,
+    fn new(
+        read: ReadStream,
+        write: WriteStream,
         peer: Peer,
         _version: u8,
         capabilities: Capabilities,
+        timer: HandshakeTimer,
     ) -> Self {
         // TODO: maybe do something with the version.
 
+        let chunking = capabilities.contains(Capabilities::CHUNKING);
         let (framed_read, framed_write) = if capabilities.contains(Capabilities::LZ4) {
-            (FramedRead::lz4(), FramedWrite::lz4(None))
+            (FramedRead::lz4(), FramedWrite::lz4(None, chunking))
         } else {
-            (FramedRead::none(), FramedWrite::none(None))
+            (FramedRead::none(), FramedWrite::none(None, chunking))
         };
 
         Self {
             read: ReadHalf::new(framed_read, read),
             write: WriteHalf::new(framed_write, write),
             peer,
+            capabilities,
+            timer,
+        }
+    }
+}
+
+impl Socket {
+    /// Sets the label used to account traffic of the socket.
+    pub(crate) fn set_zone_traffic(&mut self, zone_traffic: ZoneTraffic) {
+        self.read.zone_traffic = zone_traffic;
+        self.write.zone_traffic = zone_traffic;
+    }
+
+    /// Sets the maximum size of envelopes sent and received in chunks.
+    pub(crate) fn set_max_envelope_size(&mut self, max_envelope_size: usize) {
+        self.read.framing.set_max_envelope_size(max_envelope_size);
+        self.write.framing.set_max_envelope_size(max_envelope_size);
+    }
+}
+
+/// Whether the peer is located in the same zone as this node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ZoneTraffic {
+    Intra,
+    Cross,
+    /// Zones of one or both nodes aren't declared.
+    Unknown,
+}
+
+impl ZoneTraffic {
+    pub(crate) fn new(this: Option<&str>, peer: Option<&str>) -> Self {
+        match (this, peer) {
+            (Some(this), Some(peer)) if this == peer => Self::Intra,
+            (Some(_), Some(_)) => Self::Cross,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Intra => "intra",
+            Self::Cross => "cross",
+            Self::Unknown => "unknown",
         }
     }
 }
 
 pub(crate) struct ReadHalf {
     framing: FramedRead,
-    read: tcp::OwnedReadHalf,
+    read: ReadStream,
+    zone_traffic: ZoneTraffic,
 }
 
 impl ReadHalf {
-    pub(crate) fn new(framing: FramedRead, read: tcp::OwnedReadHalf) -> Self {
-        Self { framing, read }
+    fn new(framing: FramedRead, read: ReadStream) -> Self {
+        Self {
+            framing,
+            read,
+            zone_traffic: ZoneTraffic::Unknown,
+        }
     }
 }
 
@@ -262,7 +546,11 @@ impl ReadHalf {
                 // EOF.
                 return Ok(None);
             }
-            counter!("elfo_network_received_bytes_total", bytes_read as u64);
+            counter!(
+                "elfo_network_received_bytes_total",
+                bytes_read as u64,
+                "zone_traffic" => self.zone_traffic.as_str()
+            );
             self.report_framing_metrics();
 
             self.framing.mark_filled(bytes_read);
@@ -277,12 +565,22 @@ impl ReadHalf {
 
 pub(crate) struct WriteHalf {
     framing: FramedWrite,
-    write: tcp::OwnedWriteHalf,
+    write: WriteStream,
+    zone_traffic: ZoneTraffic,
 }
 
 impl WriteHalf {
-    pub(crate) fn new(framing: FramedWrite, write: tcp::OwnedWriteHalf) -> Self {
-        Self { framing, write }
+    fn new(framing: FramedWrite, write: WriteStream) -> Self {
+        Self {
+            framing,
+            write,
+            zone_traffic: ZoneTraffic::Unknown,
+        }
+    }
+
+    #[cfg(feature = "bench-support")]
+    pub(crate) fn from_tcp(framing: FramedWrite, write: OwnedWriteHalf) -> Self {
+        Self::new(framing, WriteStream::Tcp(write))
     }
 
     /// Encodes the message into the internal buffer.
@@ -292,48 +590,52 @@ impl WriteHalf {
     /// * `Ok(None)` if the message is skipped because of encoding errors.
     /// * `Err(err)` if an unrecoverable error happened.
     pub(crate) fn feed(&mut self, envelope: &NetworkEnvelope) -> Result<Option<FrameState>> {
-        // TODO: timeout, it should be clever
-        // TODO: we should also emit metrics here, not only in `flush()`.
-        let write_result = self.framing.write(envelope);
-        match write_result {
-            Ok(state) => Ok(Some(state)),
-            Err(EncodeError::Skipped) => Ok(None),
-            Err(EncodeError::Fatal(err)) => Err(err.into()),
-        }
+        feed(&mut self.framing, envelope)
+    }
+
+    /// Returns `true` if a large envelope is being written in chunks, one
+    /// chunk per `flush()`.
+    pub(crate) fn has_pending_chunks(&self) -> bool {
+        self.framing.has_pending_chunks()
     }
 
     /// Flushed the internal buffer unconditionally.
     pub(crate) async fn flush(&mut self) -> Result<()> {
         let finalized = self.framing.finalize()?;
         let finalized_len = finalized.len();
-        let mut result = io::AsyncWriteExt::write_all(&mut self.write, finalized)
-            .await
-            .context("failed to write frame");
-        if likely(result.is_ok()) {
-            result = io::AsyncWriteExt::flush(&mut self.write)
-                .await
-                .context("failed to flush the frame");
-        }
-
+        let result = write_frame(&mut self.write, finalized).await;
         let stats = self.framing.take_stats();
-        let mut total_messages_sent = stats.encode_stats.total_messages_encoding_skipped;
-        if likely(result.is_ok()) {
-            trace!(message = "wrote bytes to socket", count = finalized_len);
-
-            counter!("elfo_network_sent_bytes_total", finalized_len as u64);
-            counter!(
-                "elfo_network_sent_uncompressed_bytes_total",
-                stats.compress_stats.total_uncompressed_bytes
-            );
-
-            total_messages_sent += stats.encode_stats.total_messages_encoded;
-        }
-
-        counter!("elfo_network_sent_messages_total", total_messages_sent);
-
+        report_sent(self.zone_traffic, finalized_len, stats, result.is_ok());
         result
     }
 
+    /// Splits the write half into the encoding part and the writing one,
+    /// connected by a queue of up to `depth` finalized frames.
+    ///
+    /// Run parts in different tasks to encode next frames while previous ones
+    /// are being written, so a slow socket or a large envelope doesn't stall
+    /// encoding until the queue is full.
+    ///
+    /// Up to `pool_size` written buffers are returned to the encoding part to
+    /// be reused for next frames instead of allocating new ones.
+    pub(crate) fn pipeline(self, depth: usize, pool_size: usize) -> (EncodingHalf, FrameWriter) {
+        let (tx, rx) = kanal::bounded_async(depth);
+        let (pool_tx, pool_rx) = kanal::bounded(pool_size);
+        let encoding = EncodingHalf {
+            framing: self.framing,
+            tx,
+            pool: pool_rx,
+        };
+        let writer = FrameWriter {
+            write: self.write,
+            zone_traffic: self.zone_traffic,
+            rx,
+            pool: pool_tx,
+            batch: Vec::new(),
+        };
+        (encoding, writer)
+    }
+
     // Encodes the message and flushes the internal buffer.
     pub(crate) fn send<'a>(
         &'a mut self,
@@ -344,59 +646,328 @@ impl WriteHalf {
             result
                 .wrap_err("fatal serialization error")?
                 .ok_or(eyre!("non-fatal serialization error"))?;
-            self.flush().await
+            self.flush().await?;
+            while self.has_pending_chunks() {
+                self.flush().await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The encoding part of the pipelined `WriteHalf`, see `WriteHalf::pipeline()`.
+pub(crate) struct EncodingHalf {
+    framing: FramedWrite,
+    tx: kanal::AsyncSender<Frame>,
+    pool: kanal::Receiver<Vec<u8>>,
+}
+
+struct Frame {
+    bytes: Vec<u8>,
+    stats: FramedWriteStats,
+}
+
+impl EncodingHalf {
+    /// See `WriteHalf::feed()`.
+    pub(crate) fn feed(&mut self, envelope: &NetworkEnvelope) -> Result<Option<FrameState>> {
+        feed(&mut self.framing, envelope)
+    }
+
+    /// See `WriteHalf::has_pending_chunks()`.
+    pub(crate) fn has_pending_chunks(&self) -> bool {
+        self.framing.has_pending_chunks()
+    }
+
+    /// Finalizes the internal buffer and queues it for writing.
+    /// Waits only if the queue is full.
+    pub(crate) async fn flush(&mut self) -> Result<()> {
+        let finalized = self.framing.finalize()?;
+        let mut bytes = self.pool.try_recv().ok().flatten().unwrap_or_default();
+        bytes.extend_from_slice(finalized);
+        let stats = self.framing.take_stats();
+        self.tx
+            .send(Frame { bytes, stats })
+            .await
+            .map_err(|_| eyre!("the frame writer is stopped"))
+    }
+
+    /// Returns `true` if the writing part is stopped, e.g. by an IO error.
+    pub(crate) fn is_writer_stopped(&self) -> bool {
+        self.tx.is_disconnected()
+    }
+}
+
+/// The writing part of the pipelined `WriteHalf`, see `WriteHalf::pipeline()`.
+pub(crate) struct FrameWriter {
+    write: WriteStream,
+    zone_traffic: ZoneTraffic,
+    rx: kanal::AsyncReceiver<Frame>,
+    pool: kanal::Sender<Vec<u8>>,
+    batch: Vec<Frame>,
+}
+
+/// The maximum number of queued frames written by one vectored write.
+const MAX_VECTORED_FRAMES: usize = 16;
+
+impl FrameWriter {
+    /// Writes queued frames until the encoding part is dropped.
+    ///
+    /// Frames queued while the previous write is in progress are written
+    /// together by one vectored write.
+    pub(crate) async fn exec(mut self) -> Result<()> {
+        while let Ok(frame) = self.rx.recv().await {
+            self.batch.push(frame);
+            while self.batch.len() < MAX_VECTORED_FRAMES {
+                match self.rx.try_recv() {
+                    Ok(Some(frame)) => self.batch.push(frame),
+                    _ => break,
+                }
+            }
+
+            let result = write_frames(&mut self.write, &self.batch).await;
+
+            for mut frame in self.batch.drain(..) {
+                report_sent(
+                    self.zone_traffic,
+                    frame.bytes.len(),
+                    frame.stats,
+                    result.is_ok(),
+                );
+
+                // Return the buffer to the pool, drop it if the pool is full.
+                frame.bytes.clear();
+                let _ = self.pool.try_send(frame.bytes);
+            }
+
+            result?;
         }
+
+        Ok(())
+    }
+}
+
+fn feed(framing: &mut FramedWrite, envelope: &NetworkEnvelope) -> Result<Option<FrameState>> {
+    // TODO: timeout, it should be clever
+    // TODO: we should also emit metrics here, not only in `flush()`.
+    let write_result = framing.write(envelope);
+    match write_result {
+        Ok(state) => Ok(Some(state)),
+        Err(EncodeError::Skipped) => Ok(None),
+        Err(EncodeError::Fatal(err)) => Err(err.into()),
     }
 }
 
+async fn write_frame(write: &mut WriteStream, frame: &[u8]) -> Result<()> {
+    io::AsyncWriteExt::write_all(write, frame)
+        .await
+        .context("failed to write frame")?;
+    io::AsyncWriteExt::flush(write)
+        .await
+        .context("failed to flush the frame")
+}
+
+async fn write_frames(write: &mut WriteStream, frames: &[Frame]) -> Result<()> {
+    if let [frame] = frames {
+        return write_frame(write, &frame.bytes).await;
+    }
+
+    let mut slices = [IoSlice::new(&[]); MAX_VECTORED_FRAMES];
+    for (slice, frame) in slices.iter_mut().zip(frames) {
+        *slice = IoSlice::new(&frame.bytes);
+    }
+
+    let mut slices = &mut slices[..frames.len()];
+    while !slices.is_empty() {
+        let written = io::AsyncWriteExt::write_vectored(write, slices)
+            .await
+            .context("failed to write frames")?;
+        if written == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::WriteZero))
+                .context("failed to write frames");
+        }
+        IoSlice::advance_slices(&mut slices, written);
+    }
+
+    io::AsyncWriteExt::flush(write)
+        .await
+        .context("failed to flush frames")
+}
+
+fn report_sent(zone_traffic: ZoneTraffic, len: usize, stats: FramedWriteStats, is_ok: bool) {
+    let mut total_messages_sent = stats.encode_stats.total_messages_encoding_skipped;
+    if likely(is_ok) {
+        trace!(message = "wrote bytes to socket", count = len);
+
+        counter!(
+            "elfo_network_sent_bytes_total",
+            len as u64,
+            "zone_traffic" => zone_traffic.as_str()
+        );
+        counter!(
+            "elfo_network_sent_uncompressed_bytes_total",
+            stats.compress_stats.total_uncompressed_bytes
+        );
+
+        total_messages_sent += stats.encode_stats.total_messages_encoded;
+    }
+
+    counter!("elfo_network_sent_messages_total", total_messages_sent);
+}
+
 // === connect ===
 
 pub(crate) async fn connect(
     transport: &Transport,
+    relay: Option<RelayTarget>,
     this_node: &NodeInfo,
     capabilities: Capabilities,
 ) -> Result<Option<Socket>> {
+    let mut timer = HandshakeTimer::start();
+
+    // TODO: timeout
+    let mut stream = connect_stream(transport)
+        .await
+        .inspect_err(|_| Failure::Connect.count())?;
+
+    if let Some(target) = relay {
+        request_relay(&mut stream, target)
+            .await
+            .inspect_err(|_| Failure::Relay.count())?;
+    }
+
+    timer.phase(Phase::TcpConnected);
+
+    let socket = RawSocket::new(stream, transport.clone(), timer);
+    let socket = socket
+        .handshake(this_node, capabilities)
+        .await
+        .inspect_err(|_| Failure::Handshake.count())?;
+
+    if let Some((target, socket)) = relay.zip(socket.as_ref()) {
+        if socket.peer.node_no != target.node_no {
+            Failure::Relay.count();
+            return Err(eyre!(
+                "relayed to node {} instead of {}",
+                socket.peer.node_no,
+                target.node_no
+            ));
+        }
+    }
+
+    Ok(socket.map(|mut socket| {
+        socket.peer.relay = relay;
+        socket
+    }))
+}
+
+async fn connect_stream(transport: &Transport) -> Result<Stream> {
     match transport {
-        Transport::Tcp(addr) => connect_tcp(*addr, this_node, capabilities).await,
+        Transport::Tcp(addr) => {
+            // TODO: settings (keepalive, linger, etc.)
+            let stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            Ok(Stream::Tcp(stream))
+        }
+        Transport::Mem(name) => mem::connect(name).await.map(Stream::Mem),
     }
 }
 
-async fn connect_tcp(
-    peer: SocketAddr,
-    this_node: &NodeInfo,
-    capabilities: Capabilities,
-) -> Result<Option<Socket>> {
-    // TODO: timeout
-    // TODO: settings (keepalive, linger, etc.)
-    let stream = TcpStream::connect(peer).await?;
-    stream.set_nodelay(true)?;
-    let socket = TcpSocket::new(stream, Transport::Tcp(peer));
-    socket.handshake(this_node, capabilities).await
+/// Asks the relay to forward the connection to the target node.
+async fn request_relay(stream: &mut Stream, target: RelayTarget) -> Result<()> {
+    let preamble = RelayPreamble {
+        version: THIS_NODE_VERSION,
+        target,
+    };
+    io::AsyncWriteExt::write_all(stream, &preamble.as_bytes()?).await?;
+
+    // The relay's own handshake isn't interesting, but it must be valid.
+    let mut buffer = Handshake::make_containing_buf();
+    io::AsyncReadExt::read_exact(stream, &mut buffer).await?;
+    Handshake::from_bytes(&buffer).wrap_err("invalid relay")?;
+    Ok(())
+}
+
+// === relay ===
+
+/// A request to forward the connection to another node.
+pub(crate) struct RelayRequest {
+    stream: Stream,
+    pub(crate) peer: Transport,
+    pub(crate) target: RelayTarget,
+}
+
+/// Connects to the next hop and passes bytes in both directions until
+/// one of the connections is closed.
+///
+/// If `next` is provided, the next hop is another relay.
+pub(crate) async fn relay(
+    mut request: RelayRequest,
+    transport: &Transport,
+    next: Option<RelayTarget>,
+) -> Result<()> {
+    let mut stream = connect_stream(transport).await?;
+
+    if let Some(target) = next {
+        request_relay(&mut stream, target).await?;
+    }
+
+    io::copy_bidirectional(&mut request.stream, &mut stream).await?;
+    Ok(())
 }
 
 // === listen ===
 
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Incoming {
+    Socket(Socket),
+    Relay(RelayRequest),
+}
+
 pub(crate) async fn listen(
     transport: &Transport,
     this_node: &NodeInfo,
     capabilities: Capabilities,
-) -> Result<futures::stream::BoxStream<'static, Socket>> {
-    match transport {
-        Transport::Tcp(addr) => listen_tcp(*addr, this_node.clone(), capabilities).await,
-    }
+) -> Result<BoxStream<'static, Incoming>> {
+    let streams = match transport {
+        Transport::Tcp(addr) => listen_tcp(*addr).await?,
+        Transport::Mem(name) => listen_mem(name)?,
+    };
+
+    let listener = transport.clone();
+    let this_node = this_node.clone();
+
+    let accept = move |(stream, peer)| {
+        let listener = listener.clone();
+        let this_node = this_node.clone();
+
+        async move {
+            let timer = HandshakeTimer::start();
+            let socket = RawSocket::new(stream, peer, timer);
+            match socket.accept(&this_node, capabilities).await {
+                Ok(Some(connection)) => Some(connection),
+                Ok(None) => {
+                    info!(message = "connection to self ignored", %listener);
+                    None
+                }
+                Err(err) => {
+                    Failure::Handshake.count();
+                    warn!(message = "handshake failed", error = %err, %listener);
+                    None
+                }
+            }
+        }
+    };
+
+    Ok(Box::pin(streams.filter_map(accept)))
 }
 
-async fn listen_tcp(
-    addr: SocketAddr,
-    this_node: NodeInfo,
-    capabilities: Capabilities,
-) -> Result<futures::stream::BoxStream<'static, Socket>> {
+async fn listen_tcp(addr: SocketAddr) -> Result<BoxStream<'static, (Stream, Transport)>> {
     // TODO: timeout
     let listener = TcpListener::bind(addr)
         .await
         .wrap_err("cannot bind TCP listener")?;
 
-    let accept = move |(listener, this_node): (TcpListener, NodeInfo)| async move {
+    let accept = move |listener: TcpListener| async move {
         loop {
             match listener.accept().await {
                 Ok((stream, peer)) => {
@@ -408,33 +979,9 @@ async fn listen_tcp(
                         );
                         continue;
                     }
-                    let socket = TcpSocket::new(stream, Transport::Tcp(peer));
-                    match socket.handshake(&this_node, capabilities).await {
-                        Ok(connection) => {
-                            match connection {
-                                Some(connection) => {
-                                    return Some((connection, (listener, this_node)))
-                                }
-                                None => {
-                                    info!(
-                                        message = "connection to self ignored",
-                                        listener = %addr,
-                                    );
-
-                                    // Continue listening.
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            warn!(
-                                message = "handshake failed",
-                                error = %err,
-                                listener = %addr,
-                            );
-
-                            // Continue listening.
-                        }
-                    }
+
+                    let connection = (Stream::Tcp(stream), Transport::Tcp(peer));
+                    return Some((connection, listener));
                 }
                 Err(err) => {
                     warn!(
@@ -449,10 +996,24 @@ async fn listen_tcp(
         }
     };
 
-    Ok(Box::pin(futures::stream::unfold(
-        (listener, this_node),
-        accept,
-    )))
+    Ok(Box::pin(futures::stream::unfold(listener, accept)))
+}
+
+fn listen_mem(name: &str) -> Result<BoxStream<'static, (Stream, Transport)>> {
+    let listener = mem::Listener::bind(name).wrap_err("cannot bind mem listener")?;
+
+    // Unlike TCP, incoming connections have no own address.
+    let peer = Transport::Mem(format!("{name}/incoming"));
+
+    let accept = move |mut listener: mem::Listener| {
+        let peer = peer.clone();
+        async move {
+            let stream = listener.accept().await?;
+            Some(((Stream::Mem(stream), peer), listener))
+        }
+    };
+
+    Ok(Box::pin(futures::stream::unfold(listener, accept)))
 }
 
 #[cfg(test)]
@@ -465,7 +1026,11 @@ mod tests {
     use futures::{future, stream::StreamExt};
     use tracing::debug;
 
-    use elfo_core::{message, tracing::TraceId, Message};
+    use elfo_core::{
+        message,
+        tracing::{Baggage, TraceId},
+        Message,
+    };
 
     use crate::codec::format::{NetworkAddr, NetworkEnvelopePayload};
 
@@ -525,16 +1090,46 @@ mod tests {
         })
     }
 
-    async fn ensure_read_write(capabilities: Capabilities, port: u16) {
+    fn local_transport(port: u16) -> Transport {
+        Transport::Tcp(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+        ))
+    }
+
+    /// Starts a relay forwarding the first connection to the server.
+    async fn spawn_relay(port: u16, server_transport: Transport) -> Transport {
+        let relay_node = NodeInfo {
+            node_no: NodeNo::from_bits(3).unwrap(),
+            launch_id: NodeLaunchId::from_bits(3),
+            groups: vec![],
+        };
+        let relay_transport = local_transport(port);
+
+        let mut listen_stream = listen(&relay_transport, &relay_node, Capabilities::empty())
+            .await
+            .expect("failed to bind relay to a port");
+
+        tokio::spawn(async move {
+            let Some(Incoming::Relay(request)) = listen_stream.next().await else {
+                panic!("expected a relay request");
+            };
+            assert_eq!(request.target.node_no, NodeNo::from_bits(2).unwrap());
+            relay(request, &server_transport, None)
+                .await
+                .expect("relay failed");
+        });
+
+        relay_transport
+    }
+
+    async fn ensure_read_write(capabilities: Capabilities, port: u16, relay_port: Option<u16>) {
         let server_node = NodeInfo {
             node_no: NodeNo::from_bits(2).unwrap(),
             launch_id: NodeLaunchId::from_bits(1),
             groups: vec![],
         };
-        let server_transport = Transport::Tcp(SocketAddr::new(
-            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-            port,
-        ));
+        let server_transport = local_transport(port);
 
         let mut listen_stream = listen(&server_transport, &server_node, capabilities)
             .await
@@ -546,20 +1141,40 @@ mod tests {
             launch_id: NodeLaunchId::from_bits(2),
             groups: vec![],
         };
-        let client_socket_fut = connect(&server_transport, &client_node, capabilities);
+
+        let (transport, relay) = match relay_port {
+            Some(relay_port) => {
+                let target = RelayTarget {
+                    node_no: server_node.node_no,
+                    ttl: 1,
+                };
+                let relay_transport = spawn_relay(relay_port, server_transport).await;
+                (relay_transport, Some(target))
+            }
+            None => (server_transport, None),
+        };
+        let client_socket_fut = connect(&transport, relay, &client_node, capabilities);
 
         let (server_socket, client_socket) =
             future::join(server_socket_fut, client_socket_fut).await;
-        let mut server_socket = server_socket.expect("server failed");
+        let Some(Incoming::Socket(mut server_socket)) = server_socket else {
+            panic!("server failed");
+        };
         let mut client_socket = client_socket
             .expect("failed to connect to the server")
             .expect("handshake failed");
+        assert_eq!(client_socket.peer.node_no, server_node.node_no);
 
         for i in 0..10 {
             let envelope = NetworkEnvelope {
                 sender: NetworkAddr::NULL,
                 recipient: NetworkAddr::NULL,
                 trace_id: TraceId::try_from(1).unwrap(),
+                message_id: None,
+                parent_id: None,
+                sent_time: None,
+                baggage: Baggage::default(),
+                versions: true,
                 payload: NetworkEnvelopePayload::Regular {
                     message: TestSocketMessage("a".repeat(i * 10)).upcast(),
                 },
@@ -575,12 +1190,255 @@ mod tests {
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     #[tracing_test::traced_test]
     async fn read_write_no_framing() {
-        ensure_read_write(Capabilities::empty(), 9200).await;
+        ensure_read_write(Capabilities::empty(), 9200, None).await;
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     #[tracing_test::traced_test]
     async fn read_write_lz4() {
-        ensure_read_write(Capabilities::LZ4, 9201).await;
+        ensure_read_write(Capabilities::LZ4, 9201, None).await;
+    }
+
+    #[test]
+    fn zone_traffic() {
+        assert_eq!(ZoneTraffic::new(Some("a"), Some("a")), ZoneTraffic::Intra);
+        assert_eq!(ZoneTraffic::new(Some("a"), Some("b")), ZoneTraffic::Cross);
+        assert_eq!(ZoneTraffic::new(Some("a"), None), ZoneTraffic::Unknown);
+        assert_eq!(ZoneTraffic::new(None, None), ZoneTraffic::Unknown);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[tracing_test::traced_test]
+    async fn read_write_relayed() {
+        ensure_read_write(Capabilities::LZ4, 9202, Some(9203)).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[tracing_test::traced_test]
+    async fn read_write_chunked() {
+        let capabilities = Capabilities::LZ4 | Capabilities::CHUNKING;
+        let server_node = NodeInfo {
+            node_no: NodeNo::from_bits(2).unwrap(),
+            launch_id: NodeLaunchId::from_bits(1),
+            groups: vec![],
+        };
+        let client_node = NodeInfo {
+            node_no: NodeNo::from_bits(1).unwrap(),
+            launch_id: NodeLaunchId::from_bits(2),
+            groups: vec![],
+        };
+        let transport = local_transport(9204);
+
+        let mut listen_stream = listen(&transport, &server_node, capabilities)
+            .await
+            .expect("failed to bind server to a port");
+        let (server_socket, client_socket) = future::join(
+            listen_stream.next(),
+            connect(&transport, None, &client_node, capabilities),
+        )
+        .await;
+        let Some(Incoming::Socket(mut server_socket)) = server_socket else {
+            panic!("server failed");
+        };
+        let mut client_socket = client_socket.unwrap().unwrap();
+        assert!(client_socket.capabilities.contains(Capabilities::CHUNKING));
+
+        // Incompressible enough to produce many chunks even with LZ4.
+        let large = (0..3_000_000u32)
+            .map(|i| char::from(b'a' + (i.wrapping_mul(2_654_435_761) >> 27) as u8 % 26))
+            .collect::<String>();
+        let texts = vec!["first".to_string(), large, "last".to_string()];
+
+        let make_envelope = |text: &str| NetworkEnvelope {
+            sender: NetworkAddr::NULL,
+            recipient: NetworkAddr::NULL,
+            trace_id: TraceId::try_from(1).unwrap(),
+            message_id: None,
+            parent_id: None,
+            sent_time: None,
+            baggage: Baggage::default(),
+            versions: true,
+            payload: NetworkEnvelopePayload::Regular {
+                message: TestSocketMessage(text.into()).upcast(),
+            },
+        };
+
+        let envelopes = texts.iter().map(|t| make_envelope(t)).collect::<Vec<_>>();
+        let sender = tokio::spawn(async move {
+            for envelope in envelopes {
+                client_socket.write.send(&envelope).await.unwrap();
+            }
+            client_socket
+        });
+
+        for text in &texts {
+            let envelope = server_socket.read.recv().await.unwrap().unwrap();
+            let NetworkEnvelopePayload::Regular { message } = envelope.payload else {
+                panic!("unexpected kind of the received message");
+            };
+            let message = message.downcast::<TestSocketMessage>().unwrap();
+            assert_eq!(&message.0, text);
+        }
+
+        sender.await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[tracing_test::traced_test]
+    async fn read_write_oversized() {
+        let capabilities = Capabilities::LZ4 | Capabilities::CHUNKING;
+        let server_node = NodeInfo {
+            node_no: NodeNo::from_bits(2).unwrap(),
+            launch_id: NodeLaunchId::from_bits(1),
+            groups: vec![],
+        };
+        let client_node = NodeInfo {
+            node_no: NodeNo::from_bits(1).unwrap(),
+            launch_id: NodeLaunchId::from_bits(2),
+            groups: vec![],
+        };
+        let transport = local_transport(9206);
+
+        let mut listen_stream = listen(&transport, &server_node, capabilities)
+            .await
+            .expect("failed to bind server to a port");
+        let (server_socket, client_socket) = future::join(
+            listen_stream.next(),
+            connect(&transport, None, &client_node, capabilities),
+        )
+        .await;
+        let Some(Incoming::Socket(mut server_socket)) = server_socket else {
+            panic!("server failed");
+        };
+        let mut client_socket = client_socket.unwrap().unwrap();
+        server_socket.set_max_envelope_size(1024 * 1024);
+
+        let large = (0..3_000_000u32)
+            .map(|i| char::from(b'a' + (i.wrapping_mul(2_654_435_761) >> 27) as u8 % 26))
+            .collect::<String>();
+
+        let make_envelope = |text: &str| NetworkEnvelope {
+            sender: NetworkAddr::NULL,
+            recipient: NetworkAddr::NULL,
+            trace_id: TraceId::try_from(1).unwrap(),
+            message_id: None,
+            parent_id: None,
+            sent_time: None,
+            baggage: Baggage::default(),
+            versions: true,
+            payload: NetworkEnvelopePayload::Regular {
+                message: TestSocketMessage(text.into()).upcast(),
+            },
+        };
+
+        let (large, last) = (make_envelope(&large), make_envelope("last"));
+        let sender = tokio::spawn(async move {
+            // Rejected by the receiver.
+            client_socket.write.send(&large).await.unwrap();
+            client_socket.write.send(&last).await.unwrap();
+
+            // Rejected by the sender.
+            client_socket.set_max_envelope_size(1024 * 1024);
+            assert!(client_socket.write.send(&large).await.is_err());
+            client_socket.write.send(&last).await.unwrap();
+            client_socket
+        });
+
+        match server_socket.read.recv().await {
+            Err(ReadError::EnvelopeSkipped(details)) => {
+                assert_eq!(details.trace_id, TraceId::try_from(1).unwrap());
+            }
+            _ => panic!("the large envelope must be skipped"),
+        }
+
+        // The connection is kept.
+        for _ in 0..2 {
+            let envelope = server_socket.read.recv().await.unwrap().unwrap();
+            let NetworkEnvelopePayload::Regular { message } = envelope.payload else {
+                panic!("unexpected kind of the received message");
+            };
+            assert_eq!(message.downcast::<TestSocketMessage>().unwrap().0, "last");
+        }
+
+        sender.await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[tracing_test::traced_test]
+    async fn read_write_pipelined() {
+        let capabilities = Capabilities::LZ4 | Capabilities::CHUNKING;
+        let server_node = NodeInfo {
+            node_no: NodeNo::from_bits(2).unwrap(),
+            launch_id: NodeLaunchId::from_bits(1),
+            groups: vec![],
+        };
+        let client_node = NodeInfo {
+            node_no: NodeNo::from_bits(1).unwrap(),
+            launch_id: NodeLaunchId::from_bits(2),
+            groups: vec![],
+        };
+        let transport = local_transport(9205);
+
+        let mut listen_stream = listen(&transport, &server_node, capabilities)
+            .await
+            .expect("failed to bind server to a port");
+        let (server_socket, client_socket) = future::join(
+            listen_stream.next(),
+            connect(&transport, None, &client_node, capabilities),
+        )
+        .await;
+        let Some(Incoming::Socket(mut server_socket)) = server_socket else {
+            panic!("server failed");
+        };
+        let client_socket = client_socket.unwrap().unwrap();
+        let (mut encoding, frame_writer) = client_socket.write.pipeline(2, 2);
+        let frame_writer = tokio::spawn(frame_writer.exec());
+
+        // Frames of the large envelope are queued between small ones.
+        let large = (0..1_000_000u32)
+            .map(|i| char::from(b'a' + (i.wrapping_mul(2_654_435_761) >> 27) as u8 % 26))
+            .collect::<String>();
+        let texts = vec!["first".to_string(), large, "last".to_string()];
+
+        let envelopes = texts
+            .iter()
+            .map(|text| NetworkEnvelope {
+                sender: NetworkAddr::NULL,
+                recipient: NetworkAddr::NULL,
+                trace_id: TraceId::try_from(1).unwrap(),
+                message_id: None,
+                parent_id: None,
+                sent_time: None,
+                baggage: Baggage::default(),
+                versions: true,
+                payload: NetworkEnvelopePayload::Regular {
+                    message: TestSocketMessage(text.clone()).upcast(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let encoder = tokio::spawn(async move {
+            for envelope in envelopes {
+                encoding.feed(&envelope).unwrap().unwrap();
+                encoding.flush().await.unwrap();
+                while encoding.has_pending_chunks() {
+                    encoding.flush().await.unwrap();
+                }
+            }
+            assert!(!encoding.is_writer_stopped());
+        });
+
+        for text in &texts {
+            let envelope = server_socket.read.recv().await.unwrap().unwrap();
+            let NetworkEnvelopePayload::Regular { message } = envelope.payload else {
+                panic!("unexpected kind of the received message");
+            };
+            let message = message.downcast::<TestSocketMessage>().unwrap();
+            assert_eq!(&message.0, text);
+        }
+
+        // The writer is stopped once the encoding part is dropped.
+        encoder.await.unwrap();
+        frame_writer.await.unwrap().unwrap();
     }
 }
@@ -0,0 +1,281 @@
+//! Introspection of connections between nodes and known nodes.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
+use elfo_core::{_priv::GroupNo, message, NodeNo};
+
+use crate::{protocol::GroupInfo, socket::Peer};
+
+/// A request to get the status of all established connections.
+/// Handled by the network group.
+#[message(ret = NetworkStatus)]
+pub struct GetNetworkStatus;
+
+/// The status of all established connections, sorted.
+#[message(part)]
+#[non_exhaustive]
+pub struct NetworkStatus {
+    /// Connections between local and remote groups.
+    pub connections: Vec<ConnectionStatus>,
+}
+
+/// The status of a connection between a local group and a remote one.
+#[message(part)]
+#[non_exhaustive]
+pub struct ConnectionStatus {
+    /// A name of the local group.
+    pub local_group: String,
+    /// A node of the remote group.
+    pub remote_node_no: NodeNo,
+    /// A name of the remote group.
+    pub remote_group: String,
+    /// A smoothed round-trip time, `None` until the first ping is answered.
+    pub rtt: Option<Duration>,
+    /// An estimated offset of the remote clock relative to the local one in
+    /// seconds. Positive if the remote clock is ahead.
+    /// `None` until the first ping is answered by a node supporting it.
+    pub clock_offset: Option<f64>,
+}
+
+/// A request to get all nodes known by this node.
+/// Handled by the network group.
+#[message(ret = NodeMapStatus)]
+pub struct GetNodeMap;
+
+/// Known nodes, sorted by `node_no`.
+#[message(part)]
+#[non_exhaustive]
+pub struct NodeMapStatus {
+    /// The number of this node.
+    pub this_node_no: NodeNo,
+    /// Other nodes, connected or only reachable through relays.
+    pub nodes: Vec<NodeStatus>,
+}
+
+/// What is known about a remote node.
+#[message(part)]
+#[non_exhaustive]
+pub struct NodeStatus {
+    /// The number of the node.
+    pub node_no: NodeNo,
+    /// `None` until the control connection is established.
+    pub launch_id: Option<u64>,
+    /// Names of groups advertised by the node.
+    pub groups: Vec<String>,
+    /// How to reach the node, `None` if it's unknown.
+    pub route: Option<RouteStatus>,
+    /// The number of established connections between groups.
+    pub connections: usize,
+    /// When the last connection to the node was established.
+    pub last_seen: Option<SystemTime>,
+}
+
+/// How to reach a remote node.
+#[message(part)]
+#[non_exhaustive]
+pub struct RouteStatus {
+    /// The node itself or the first relay on the way, e.g.
+    /// `tcp://10.0.0.1:4242`.
+    pub transport: String,
+    /// The first relay on the way, `None` if the node is reachable directly.
+    pub via: Option<NodeNo>,
+    /// The number of relays on the way.
+    pub hops: u8,
+}
+
+/// A request to check that the node is reachable by a ping round-trip over
+/// any established connection. Returns the round-trip time.
+/// Fails if there are no connections or the pong isn't received in
+/// `system.network.reachability_timeout`.
+#[message(ret = Duration)]
+pub struct CheckReachability {
+    /// The node to check.
+    pub node_no: NodeNo,
+}
+
+// === StatusRegistry ===
+
+type Key = (GroupNo, NodeNo, GroupNo);
+
+struct Entry {
+    local: GroupInfo,
+    remote: GroupInfo,
+    peer: Peer,
+    status: ConnectionStatus,
+}
+
+/// Shared between all actors of the network group.
+#[derive(Default)]
+pub(crate) struct StatusRegistry {
+    connections: Mutex<FxHashMap<Key, Entry>>,
+}
+
+impl StatusRegistry {
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        local: &GroupInfo,
+        remote: &GroupInfo,
+        peer: &Peer,
+    ) -> StatusGuard {
+        let key = (local.group_no, remote.node_no, remote.group_no);
+        let status = ConnectionStatus {
+            local_group: local.group_name.clone(),
+            remote_node_no: remote.node_no,
+            remote_group: remote.group_name.clone(),
+            rtt: None,
+            clock_offset: None,
+        };
+
+        let entry = Entry {
+            local: local.clone(),
+            remote: remote.clone(),
+            peer: peer.clone(),
+            status,
+        };
+        self.connections.lock().insert(key, entry);
+
+        StatusGuard {
+            registry: self.clone(),
+            key,
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> NetworkStatus {
+        let mut connections = self
+            .connections
+            .lock()
+            .values()
+            .map(|entry| entry.status.clone())
+            .collect::<Vec<_>>();
+
+        connections.sort_by(|a, b| {
+            (
+                &a.local_group,
+                a.remote_node_no.into_bits(),
+                &a.remote_group,
+            )
+                .cmp(&(
+                    &b.local_group,
+                    b.remote_node_no.into_bits(),
+                    &b.remote_group,
+                ))
+        });
+
+        NetworkStatus { connections }
+    }
+
+    /// Returns the number of established connections to the node.
+    pub(crate) fn connections_to(&self, node_no: NodeNo) -> usize {
+        let connections = self.connections.lock();
+        connections.keys().filter(|key| key.1 == node_no).count()
+    }
+
+    /// Returns pairs of local and remote groups connected to the node.
+    pub(crate) fn groups_of(&self, node_no: NodeNo) -> Vec<(GroupInfo, GroupInfo)> {
+        let connections = self.connections.lock();
+        connections
+            .iter()
+            .filter(|(key, _)| key.1 == node_no)
+            .map(|(_, entry)| (entry.local.clone(), entry.remote.clone()))
+            .collect()
+    }
+
+    /// Returns all established connections with their peers.
+    pub(crate) fn peers(&self) -> Vec<(GroupInfo, GroupInfo, Peer)> {
+        let connections = self.connections.lock();
+        connections
+            .values()
+            .map(|entry| {
+                (
+                    entry.local.clone(),
+                    entry.remote.clone(),
+                    entry.peer.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Removes the connection from the registry on drop.
+pub(crate) struct StatusGuard {
+    registry: Arc<StatusRegistry>,
+    key: Key,
+}
+
+impl StatusGuard {
+    pub(crate) fn update(&self, f: impl FnOnce(&mut ConnectionStatus)) {
+        if let Some(entry) = self.registry.connections.lock().get_mut(&self.key) {
+            f(&mut entry.status);
+        }
+    }
+}
+
+impl Drop for StatusGuard {
+    fn drop(&mut self) {
+        self.registry.connections.lock().remove(&self.key);
+    }
+}
+
+// === Wall clock ===
+
+/// Returns the number of nanoseconds since the unix epoch.
+pub(crate) fn unix_time_ns(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::_priv::NodeLaunchId;
+
+    use super::*;
+    use crate::config;
+
+    fn peer(node_no: u16) -> Peer {
+        Peer {
+            node_no: NodeNo::from_bits(node_no).unwrap(),
+            launch_id: NodeLaunchId::from_bits(node_no.into()),
+            transport: config::parse_transport("tcp://127.0.0.1:4242").unwrap(),
+            relay: None,
+        }
+    }
+
+    fn group(node_no: u16, group_no: u8, name: &str) -> GroupInfo {
+        GroupInfo {
+            node_no: NodeNo::from_bits(node_no).unwrap(),
+            group_no: GroupNo::from_bits(group_no).unwrap(),
+            group_name: name.into(),
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let registry = Arc::new(StatusRegistry::default());
+        let local = group(1, 1, "local");
+
+        let guard_b = registry.register(&local, &group(3, 1, "b"), &peer(3));
+        let guard_a = registry.register(&local, &group(2, 1, "a"), &peer(2));
+        guard_a.update(|status| status.rtt = Some(Duration::from_millis(5)));
+
+        let status = registry.snapshot();
+        assert_eq!(status.connections.len(), 2);
+        assert_eq!(status.connections[0].remote_group, "a");
+        assert_eq!(status.connections[0].rtt, Some(Duration::from_millis(5)));
+        assert_eq!(status.connections[1].remote_group, "b");
+        assert_eq!(status.connections[1].rtt, None);
+
+        drop(guard_a);
+        let status = registry.snapshot();
+        assert_eq!(status.connections.len(), 1);
+        assert_eq!(status.connections[0].remote_group, "b");
+        drop(guard_b);
+        assert!(registry.snapshot().connections.is_empty());
+    }
+}
@@ -23,6 +23,13 @@
 //! See `TxFlowControl` and `RxFlowControl` for implementations of sender's and
 //! receiver's windows respectively.
 //!
+//! Window updates are aggregated (see `UNCLAIMED_NUMERATOR`) instead of being
+//! sent per message, because per-message acks would double the frame rate on
+//! the busiest links. Delivery is at-most-once now; if at-least-once delivery
+//! is added, its acks must follow the same rule: cumulative ("everything up to
+//! sequence number N is received") and piggybacked on data frames going in the
+//! opposite direction, falling back to a standalone ack only on idle links.
+//!
 //! If none of this makes sense, see https://en.wikipedia.org/wiki/Flow_control_(data) and
 //! https://en.wikipedia.org/wiki/Sliding_window_protocol for a more elaborate explanation.
 
@@ -1,47 +1,66 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
+use arc_swap::ArcSwap;
 use eyre::Result;
-use metrics::{decrement_gauge, increment_gauge};
+use futures::future::BoxFuture;
+use metrics::{decrement_gauge, increment_gauge, Key};
 use parking_lot::Mutex;
 use quanta::Instant;
 use tracing::{debug, error, info, trace, warn};
 
 use elfo_core::{
-    message, Local, Message,
-    _priv::{EnvelopeOwned, GroupVisitor, MessageKind, NodeNo, Object, ObjectArc},
+    _priv::{
+        AddressBook, EnvelopeOwned, GroupVisitor, MessageKind, NodeNo, Object, ObjectArc, RequestId,
+    },
+    dumping::Direction,
     errors::{RequestError, SendError, TrySendError},
-    messages::{ConfigUpdated, Impossible},
+    journal::{self, EventKind},
+    message,
+    messages::{ConfigUpdated, DeadLetter},
     msg, remote, scope,
     stream::Stream,
-    time::Interval,
-    Addr, Context, Envelope, ResponseToken, Topology,
+    time::{Delay, Interval},
+    tracing::Baggage,
+    Addr, Context, Envelope, Local, Message, ResponseToken, SourceHandle, Topology,
 };
 use elfo_utils::{likely, unlikely};
 
 use self::{
     flows_rx::RxFlows,
     flows_tx::{Acquire, TryAcquire, TxFlows},
-    requests::OutgoingRequests,
+    requests::{IncomingRequests, OutgoingRequests},
+    spool::{Pushed, Spool, Spooled},
+    tap::Taps,
 };
 use crate::{
+    circuit_breaker::CircuitBreaker,
     codec::{
-        decode::EnvelopeDetails,
+        decode::{EnvelopeDetails, UnknownMessage},
         format::{
             NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, KIND_REQUEST_ALL,
             KIND_REQUEST_ANY, KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED, KIND_RESPONSE_OK,
         },
     },
     frame::write::FrameState,
-    protocol::{internode, GroupInfo, HandleConnection},
+    messages,
+    protocol::{internode, CloseConnection, GroupInfo, HandleConnection},
     rtt::Rtt,
-    socket::{ReadError, ReadHalf, WriteHalf},
-    NetworkContext,
+    skew::ClockSkew,
+    socket::{Capabilities, EncodingHalf, FrameWriter, ReadError, ReadHalf, Socket},
+    status::{self, CheckReachability, StatusGuard, StatusRegistry},
+    NetworkContext, RECEIVED_TIME_KEY,
 };
 
 mod flow_control;
 mod flows_rx;
 mod flows_tx;
+mod reachability;
 mod requests;
+mod spool;
+mod tap;
 
 // TODO: send `CloseFlow` once an actor is closed, not only on incoming message.
 // TODO: don't send control messages if the peer knows nothing about the flow.
@@ -58,11 +77,19 @@ struct PingTick;
 #[message]
 struct ConnectionClosed;
 
+#[message]
+struct SpoolExpired;
+
+/// Requested checks with payloads of pings sent for them.
+type PendingChecks = Arc<Mutex<Vec<(u64, ResponseToken<CheckReachability>)>>>;
+
 pub(crate) struct Worker {
     ctx: NetworkContext,
     topology: Topology,
     local: GroupInfo,
     remote: GroupInfo,
+    status: Arc<StatusRegistry>,
+    taps: Arc<Taps>,
 }
 
 impl Worker {
@@ -71,12 +98,17 @@ impl Worker {
         local: GroupInfo,
         remote: GroupInfo,
         topology: Topology,
+        status: Arc<StatusRegistry>,
     ) -> Self {
+        let taps = Arc::new(Taps::new(remote.node_no, &ctx.config().taps));
+
         Self {
             ctx,
             topology,
             local,
             remote,
+            status,
+            taps,
         }
     }
 
@@ -87,63 +119,244 @@ impl Worker {
             _ => unreachable!("unexpected initial message"),
         });
 
-        let time_origin = Instant::now();
-        let tx_flows = Arc::new(TxFlows::new(first_message.initial_window));
-        let rx_flows = Arc::new(Mutex::new(RxFlows::new(
-            self.local.node_no,
-            first_message.initial_window,
-        )));
-        let requests = Arc::new(Mutex::new(OutgoingRequests::default()));
-        let socket = first_message.socket.take().unwrap();
+        let group_addr = self
+            .topology
+            .locals()
+            .map(|g| g.addr)
+            .find(|a| a.group_no() == Some(self.local.group_no))
+            .expect("invalid local group");
+
+        let mut connection = self.prepare_connection(first_message);
+        if !self.check_reachability(&mut connection).await {
+            return Ok(());
+        }
+
+        // Messages left by a previous run are sent first.
+        let mut spool = self.open_spool().await;
 
         // Register `RemoteHandle`. Now we can receive messages from local groups.
-        let (local_tx, local_rx) = kanal::unbounded_async();
+        // The handle is kept while messages are spooled, so the node is still
+        // available for routing.
+        let link = Arc::new(ArcSwap::from_pointee(connection.link()));
         let remote_handle = RemoteHandle {
-            tx: local_tx.clone(),
-            tx_flows: tx_flows.clone(),
+            node_no: self.remote.node_no,
+            this_node_no: self.local.node_no,
+            link: link.clone(),
+            book: self.ctx.book().clone(),
         };
-        let remote_group_guard = self.topology.register_remote(
+        let topology = self.topology.clone();
+        let remote_group_guard = topology.register_remote(
             self.local.group_no,
             (self.remote.node_no, self.remote.group_no),
             &self.remote.group_name,
             remote_handle,
         );
+        let handle_addr = remote_group_guard.handle_addr();
+
+        loop {
+            // Messages spooled after the link is switched are sent first as well.
+            let spooled = match spool.take() {
+                Some(spool) => spool.close().await,
+                None => Spooled::default(),
+            };
+            if !self
+                .serve(connection, spooled, group_addr, handle_addr)
+                .await
+            {
+                return Ok(());
+            }
+
+            let Some(new_spool) = self.open_spool().await else {
+                break;
+            };
+
+            link.store(Arc::new(Link::Spooling(new_spool.clone())));
+            spool = Some(new_spool.clone());
+
+            connection = loop {
+                let Some(message) = self.wait_for_reconnection().await else {
+                    let dropped = new_spool.discard().await;
+                    if dropped > 0 {
+                        warn!(
+                            message = "peer hasn't reconnected, spooled messages are dropped",
+                            dropped
+                        );
+                    }
+                    return Ok(());
+                };
+
+                let mut connection = self.prepare_connection(message);
+                if self.check_reachability(&mut connection).await {
+                    break connection;
+                }
+            };
+
+            link.store(Arc::new(connection.link()));
+        }
+
+        Ok(())
+    }
+
+    /// Returns `false` if the connection should be dropped.
+    async fn check_reachability(&self, connection: &mut Connection) -> bool {
+        let socket = &mut connection.socket;
+        if !socket
+            .capabilities
+            .contains(Capabilities::REACHABILITY_CHECK)
+        {
+            return true;
+        }
+
+        let timeout = self.ctx.config().reachability_timeout;
+        match reachability::check(socket, timeout).await {
+            Ok(rtt) => {
+                debug!(message = "peer is reachable", rtt = ?rtt);
+                true
+            }
+            Err(err) => {
+                warn!(message = "peer is unreachable, connection dropped", error = %err);
+                false
+            }
+        }
+    }
+
+    async fn open_spool(&self) -> Option<Arc<Spool>> {
+        let config = self.ctx.config().spool.as_ref()?;
+        if !config.is_enabled_for(&self.remote.group_name) {
+            return None;
+        }
+
+        let file_name = format!(
+            "{}.{}.{}.spool",
+            self.local.group_name, self.remote.node_no, self.remote.group_name
+        );
+        let path = config.path.join(file_name);
+
+        match Spool::open(path, config.max_size.as_u64(), config.ttl).await {
+            Ok(spool) => Some(Arc::new(spool)),
+            Err(err) => {
+                error!(message = "cannot open spool", path = ?config.path, error = %err);
+                None
+            }
+        }
+    }
+
+    fn prepare_connection(&self, message: HandleConnection) -> Connection {
+        let tx_flows = Arc::new(TxFlows::new(message.initial_window));
+        let rx_flows = Arc::new(Mutex::new(RxFlows::new(
+            self.local.node_no,
+            message.initial_window,
+        )));
+        let breaker = Arc::new(CircuitBreaker::new(
+            self.ctx.config().circuit_breaker.clone(),
+        ));
+        let requests = Arc::new(Mutex::new(OutgoingRequests::new(
+            breaker.clone(),
+            self.ctx.book().clone(),
+        )));
+        let (local_tx, local_rx) = kanal::unbounded_async();
+
+        Connection {
+            socket: message.socket.take().unwrap(),
+            tx_flows,
+            rx_flows,
+            breaker,
+            requests,
+            local_tx,
+            local_rx,
+        }
+    }
+
+    /// Handles the connection until it's closed.
+    /// Returns `false` if the peer is denied, so it shouldn't be waited for.
+    async fn serve(
+        &mut self,
+        connection: Connection,
+        spooled: Spooled,
+        group_addr: Addr,
+        handle_addr: Addr,
+    ) -> bool {
+        journal::record(EventKind::Connected {
+            node_no: self.remote.node_no,
+            group: self.remote.group_name.clone(),
+        });
+
+        let time_origin = Instant::now();
+        let wall_origin = SystemTime::now();
+        let Connection {
+            socket,
+            tx_flows,
+            rx_flows,
+            breaker,
+            requests,
+            local_tx,
+            local_rx,
+        } = connection;
+
+        let checks = PendingChecks::default();
+        let capabilities = socket.capabilities;
+
+        if spooled.len() > 0 {
+            info!(message = "sending spooled messages", count = spooled.len());
+        }
+
+        // Envelopes are encoded by `SocketWriter`, while `FrameWriter` writes
+        // already encoded frames to the socket concurrently.
+        let pool_size = self.ctx.config().frame_buffer_pool_size;
+        let (encoding, frame_writer) = socket.write.pipeline(PIPELINE_DEPTH, pool_size);
+        let frame_writer = self.ctx.attach(Stream::once(write_frames(frame_writer)));
 
         // Start handling local incoming messages.
         let sw = SocketWriter {
             node_no: self.local.node_no,
+            group_addr,
+            next_relay_id: 1,
+            capabilities,
+            spooled,
+            tx_flows: tx_flows.clone(),
+            taps: self.taps.clone(),
             rx: local_rx,
-            tx: socket.write,
+            tx: encoding,
             requests: requests.clone(),
         };
-        self.ctx.attach(Stream::once(sw.exec()));
+        let writer = self.ctx.attach(Stream::once(sw.exec()));
 
         // Start handling network incoming messages.
         let sr = SocketReader {
             ctx: self.ctx.pruned(),
-            group_addr: self
-                .topology
-                .locals()
-                .map(|g| g.addr)
-                .find(|a| a.group_no() == Some(self.local.group_no))
-                .expect("invalid local group"),
-            handle_addr: remote_group_guard.handle_addr(),
+            group_addr,
+            handle_addr,
             time_origin,
+            wall_origin,
             // TODO: the number of samples should be calculated based on telemetry scrape
             //       interval, but it's not povideded for now by the elfo core.
             rtt: Rtt::new(5),
+            remote: self.remote.clone(),
+            forward_unknown_messages: self.ctx.config().forward_unknown_messages,
+            annotate_received_time: self.ctx.config().annotate_received_time,
+            skew: ClockSkew::new(5),
+            status: self
+                .status
+                .register(&self.local, &self.remote, &socket.peer),
+            checks: checks.clone(),
+            taps: self.taps.clone(),
             rx: socket.read,
             tx: local_tx.clone(),
             tx_flows: tx_flows.clone(),
             rx_flows: rx_flows.clone(),
-            requests,
+            requests: requests.clone(),
+            incoming_requests: capabilities
+                .contains(Capabilities::CANCELLATION)
+                .then(IncomingRequests::default),
         };
-        self.ctx.attach(Stream::once(sr.exec()));
+        let reader = self.ctx.attach(Stream::once(sr.exec()));
 
         // Start ping ticks.
         let ping_interval = self.ctx.attach(Interval::new(PingTick));
         ping_interval.start_after(Duration::ZERO, self.ctx.config().ping_interval);
 
+        let mut is_denied = false;
+
         while let Some(envelope) = self.ctx.recv().await {
             // TODO: graceful termination
             // TODO: handle another `HandleConnection`
@@ -151,14 +364,36 @@ impl Worker {
             msg!(match envelope {
                 ConfigUpdated => {
                     ping_interval.set_period(self.ctx.config().ping_interval);
+                    breaker.configure(self.ctx.config().circuit_breaker.clone());
+                    self.taps.configure(&self.ctx.config().taps);
                 }
                 PingTick => {
-                    let envelope = make_system_envelope(internode::Ping {
-                        payload: time_origin.elapsed().as_nanos() as u64,
-                    });
-                    let _ = local_tx.try_send(KanalItem::simple(NetworkAddr::NULL, envelope));
+                    let payload = time_origin.elapsed().as_nanos() as u64;
+                    send_ping(&local_tx, payload);
 
                     // TODO: perform health check
+                    let mut requests = requests.lock();
+                    requests.check_timeouts();
+                    let canceled = requests.remove_canceled();
+                    drop(requests);
+
+                    if capabilities.contains(Capabilities::CANCELLATION) {
+                        for (owner, request_id) in canceled {
+                            let sender = NetworkAddr::from_local(owner, self.local.node_no);
+                            send_cancel_request(&local_tx, sender, request_id);
+                        }
+                    }
+
+                    // Expired checks fail once tokens are dropped.
+                    let timeout = self.ctx.config().reachability_timeout.as_nanos() as u64;
+                    checks
+                        .lock()
+                        .retain(|(since, _)| payload.saturating_sub(*since) < timeout);
+                }
+                (CheckReachability, token) => {
+                    let payload = time_origin.elapsed().as_nanos() as u64;
+                    checks.lock().push((payload, token));
+                    send_ping(&local_tx, payload);
                 }
                 StartPusher(addr) => {
                     let pusher = Pusher {
@@ -171,55 +406,174 @@ impl Worker {
                     self.ctx.attach(Stream::once(pusher.exec()));
                 }
                 ConnectionClosed => {
-                    info!("connection closed by peer");
+                    info!("connection closed");
+                    break;
+                }
+                CloseConnection => {
+                    warn!("connection closed, the peer is denied by `system.network.peers`");
+                    is_denied = true;
                     break;
                 }
             });
         }
 
-        Ok(())
+        writer.terminate();
+        frame_writer.terminate();
+        reader.terminate();
+        ping_interval.terminate();
+
+        journal::record(EventKind::Disconnected {
+            node_no: self.remote.node_no,
+            group: self.remote.group_name.clone(),
+        });
+
+        !is_denied
+    }
+
+    /// Waits for the peer to reconnect while messages are spooled.
+    async fn wait_for_reconnection(&mut self) -> Option<HandleConnection> {
+        let ttl = self
+            .ctx
+            .config()
+            .spool
+            .as_ref()
+            .map_or(Duration::ZERO, |c| c.ttl);
+        let expired = self.ctx.attach(Delay::new(ttl, SpoolExpired));
+        info!(message = "spooling messages until reconnection", ttl = ?ttl);
+
+        while let Some(envelope) = self.ctx.recv().await {
+            msg!(match envelope {
+                msg @ HandleConnection => {
+                    expired.terminate();
+                    return Some(msg);
+                }
+                ConfigUpdated => self.taps.configure(&self.ctx.config().taps),
+                SpoolExpired => return None,
+                CloseConnection => return None,
+            });
+        }
+
+        None
+    }
+}
+
+/// Per-connection state.
+struct Connection {
+    socket: Socket,
+    tx_flows: Arc<TxFlows>,
+    rx_flows: Arc<Mutex<RxFlows>>,
+    breaker: Arc<CircuitBreaker>,
+    requests: Arc<Mutex<OutgoingRequests>>,
+    local_tx: kanal::AsyncSender<KanalItem>,
+    local_rx: kanal::AsyncReceiver<KanalItem>,
+}
+
+impl Connection {
+    fn link(&self) -> Link {
+        Link::Connected(Connected {
+            tx: self.local_tx.clone(),
+            tx_flows: self.tx_flows.clone(),
+            breaker: self.breaker.clone(),
+        })
     }
 }
 
 // === SocketWriter ===
 
-/// A subtask that handles incoming messages from local actors and writes them
-/// to the socket.
+/// The number of encoded frames waiting to be written to the socket.
+/// Once the queue is full, `SocketWriter` waits for `FrameWriter`.
+pub(crate) const PIPELINE_DEPTH: usize = 4;
+
+/// A subtask that handles incoming messages from local actors, encodes them
+/// and passes frames to `FrameWriter`.
 struct SocketWriter {
     node_no: NodeNo,
+    /// Used as the requester of relayed requests.
+    group_addr: Addr,
+    next_relay_id: u64,
+    /// Defines optional parts of envelopes supported by the remote node.
+    capabilities: Capabilities,
+    /// Sent before other messages.
+    spooled: Spooled,
+    tx_flows: Arc<TxFlows>,
+    taps: Arc<Taps>,
     rx: kanal::AsyncReceiver<KanalItem>,
-    tx: WriteHalf,
+    tx: EncodingHalf,
     requests: Arc<Mutex<OutgoingRequests>>,
 }
 
 impl SocketWriter {
-    async fn exec(mut self) -> Impossible {
+    async fn exec(mut self) -> ConnectionClosed {
+        // IO errors are reported by `FrameWriter`.
+        if let Err(err) = self.write_all().await {
+            if !self.tx.is_writer_stopped() {
+                warn!(message = "cannot write to socket", error = %err);
+            }
+        }
+
+        ConnectionClosed
+    }
+
+    async fn write_all(&mut self) -> Result<()> {
+        self.write_spooled().await?;
+
         // We should write messages as many as possible at once to have better
         // compression rate and reduce the number of system calls.
         // On the other hand, we should minimize the time which every message is unsent.
         // Thus, we should find a balance between these two factors, some trade-off.
         // The current strategy is to send all available messages and then forcibly
-        // flush intermediate buffers to the socket. So, frames besides the last
-        // one (before the channel is empty) are complete.
+        // flush intermediate buffers to `FrameWriter`. So, frames besides the last
+        // one (before the channel is empty) are complete. Frames are written by
+        // `FrameWriter` concurrently, so encoding doesn't wait for the socket
+        // until `PIPELINE_DEPTH` frames are queued.
         //
         // TODO: tokio implements budget on sockets, so this subtask sometimes returns
         // the execution back to the runtime even in case of a full incoming queue.
         // We should use `tokio::task::unconstrained()` here and preempt the (sub)task
         // after sending each batch of messages.
+        //
+        // Large envelopes are written in chunks, one per frame, so the socket's
+        // backpressure (through the frame queue) is applied to every chunk. Meanwhile,
+        // new messages aren't taken to preserve ordering.
         loop {
+            if self.tx.has_pending_chunks() {
+                self.tx.flush().await?;
+                continue;
+            }
+
             // TODO: error handling, metrics.
             let mut item = self.rx.recv().await.unwrap();
             loop {
-                let (network_envelope, response_token) = make_network_envelope(item, self.node_no);
+                // Requests from other nodes are sent on behalf of the local group
+                // with a new id, because the original requester isn't addressable
+                // by the remote node.
+                let relay = item.relay.map(|handle| (handle, self.make_relay_id()));
+
+                let (network_envelope, response_token) = make_network_envelope(
+                    item,
+                    self.node_no,
+                    relay.map(|(_, request_id)| (self.group_addr, request_id)),
+                    self.capabilities,
+                );
                 scope::set_trace_id(network_envelope.trace_id);
+                self.taps.dump(Direction::Out, &network_envelope);
 
-                // NOTE: We use `unwrap()` for results from all `self.tx` methods because these
-                // errors are unrecoverable.
-                if let Some(frame_state) = self.tx.feed(&network_envelope).unwrap() {
+                // NOTE: errors of all `self.tx` methods are unrecoverable and close the
+                // connection.
+                if let Some(frame_state) = self.tx.feed(&network_envelope)? {
                     // Envelope was encoded successfylly, so we can store the response token.
                     // Otherwise, it will be dropped with the `Failed` reason.
                     if let Some(token) = response_token {
-                        self.requests.lock().add_token(token);
+                        let mut requests = self.requests.lock();
+                        match relay {
+                            Some((handle, request_id)) => requests.add_relayed_token(
+                                self.group_addr,
+                                request_id,
+                                token,
+                                handle,
+                            ),
+                            None => requests.add_token(token),
+                        }
                     }
 
                     if frame_state == FrameState::FlushAdvised {
@@ -233,20 +587,94 @@ impl SocketWriter {
             // We have either received a recommendation for a flush or there are no more
             // messages for the time being. Since we don't know how long we'll
             // wait for the next message, we flush in both cases.
-            self.tx.flush().await.unwrap();
+            self.tx.flush().await?;
+        }
+    }
+
+    async fn write_spooled(&mut self) -> Result<()> {
+        while let Some(mut envelope) = self.spooled.next().await {
+            // Spooled messages haven't been counted by flow control yet.
+            if !self.tx_flows.do_acquire(envelope.recipient) {
+                continue;
+            }
+
+            // Chunks of the previous envelope are written first to preserve ordering.
+            while self.tx.has_pending_chunks() {
+                self.tx.flush().await?;
+            }
+
+            if !self.capabilities.contains(Capabilities::SENT_TIME) {
+                envelope.sent_time = None;
+            }
+            if !self.capabilities.contains(Capabilities::BAGGAGE) {
+                envelope.baggage = Baggage::default();
+            }
+            if !self.capabilities.contains(Capabilities::MESSAGE_IDS) {
+                envelope.message_id = None;
+                envelope.parent_id = None;
+            }
+            if !self.capabilities.contains(Capabilities::VERSIONS) {
+                envelope.versions = false;
+            }
+
+            scope::set_trace_id(envelope.trace_id);
+            self.taps.dump(Direction::Out, &envelope);
+            if self.tx.feed(&envelope)? == Some(FrameState::FlushAdvised) {
+                self.tx.flush().await?;
+            }
+        }
+
+        self.tx.flush().await
+    }
+
+    fn make_relay_id(&mut self) -> RequestId {
+        loop {
+            let request_id = RequestId::from_ffi(self.next_relay_id);
+            self.next_relay_id = self.next_relay_id.wrapping_add(1);
+
+            if !request_id.is_null() {
+                break request_id;
+            }
         }
     }
 }
 
+/// `relay` is the local requester and the request id used instead of
+/// the original ones if the request is relayed from another node.
 fn make_network_envelope(
     item: KanalItem,
     node_no: NodeNo,
+    relay: Option<(Addr, RequestId)>,
+    capabilities: Capabilities,
 ) -> (NetworkEnvelope, Option<ResponseToken>) {
+    // Optional parts supported by the remote node.
+    // Failed/Ignored responses have no envelope, so they're sent without them.
+    let headers = item.envelope.as_ref().ok().map(|envelope| {
+        let sent_time = capabilities
+            .contains(Capabilities::SENT_TIME)
+            .then(|| status::unix_time_ns(SystemTime::now() - envelope.queued_for()));
+
+        let baggage = if capabilities.contains(Capabilities::BAGGAGE) {
+            envelope.baggage().clone()
+        } else {
+            Baggage::default()
+        };
+
+        let message_ids = if capabilities.contains(Capabilities::MESSAGE_IDS) {
+            (Some(envelope.message_id()), envelope.parent_id())
+        } else {
+            (None, None)
+        };
+
+        (sent_time, baggage, message_ids)
+    });
+
     let (sender, trace_id, payload, token) = match (item.envelope, item.token) {
         // Regular, RequestAny, RequestAll
         (Ok(envelope), None) => {
-            let sender = envelope.sender();
+            let sender = relay.map_or_else(|| envelope.sender(), |(owner, _)| owner);
             let trace_id = envelope.trace_id();
+            let request_id = |token: &ResponseToken| relay.map_or(token.request_id(), |r| r.1);
 
             let (payload, token) = match envelope.message_kind() {
                 MessageKind::Regular { .. } => (
@@ -259,7 +687,7 @@ fn make_network_envelope(
                     let (message, token) = envelope.unpack_request();
                     (
                         NetworkEnvelopePayload::RequestAny {
-                            request_id: token.request_id(),
+                            request_id: request_id(&token),
                             message,
                         },
                         Some(token),
@@ -269,7 +697,7 @@ fn make_network_envelope(
                     let (message, token) = envelope.unpack_request();
                     (
                         NetworkEnvelopePayload::RequestAll {
-                            request_id: token.request_id(),
+                            request_id: request_id(&token),
                             message,
                         },
                         Some(token),
@@ -282,7 +710,10 @@ fn make_network_envelope(
         }
         // Response
         (Ok(envelope), Some(token)) => {
-            let sender = envelope.sender();
+            // Relayed responses come from another node, so hide the responder.
+            let sender = Some(envelope.sender())
+                .filter(|sender| sender.is_local())
+                .unwrap_or(Addr::NULL);
             let trace_id = envelope.trace_id();
 
             let payload = match envelope.message_kind() {
@@ -321,16 +752,31 @@ fn make_network_envelope(
         (Err(_), None) => unreachable!(),
     };
 
+    let (sent_time, baggage, (message_id, parent_id)) = headers.unwrap_or_default();
     let envelope = NetworkEnvelope {
         sender: NetworkAddr::from_local(sender, node_no),
         recipient: item.recipient,
         trace_id,
+        message_id,
+        parent_id,
+        sent_time,
+        baggage,
+        versions: capabilities.contains(Capabilities::VERSIONS),
         payload,
     };
 
     (envelope, token)
 }
 
+/// A subtask that writes frames encoded by `SocketWriter` to the socket.
+async fn write_frames(writer: FrameWriter) -> ConnectionClosed {
+    if let Err(err) = writer.exec().await {
+        warn!(message = "cannot write to socket", error = %err);
+    }
+
+    ConnectionClosed
+}
+
 // === SocketReader ===
 
 /// A subtask that reads messages from the socket and routes them to local
@@ -340,12 +786,22 @@ struct SocketReader {
     group_addr: Addr,
     handle_addr: Addr,
     time_origin: Instant,
+    wall_origin: SystemTime,
     rtt: Rtt,
+    skew: ClockSkew,
+    remote: GroupInfo,
+    forward_unknown_messages: bool,
+    annotate_received_time: bool,
+    status: StatusGuard,
+    checks: PendingChecks,
+    taps: Arc<Taps>,
     rx: ReadHalf,
     tx: kanal::AsyncSender<KanalItem>,
     tx_flows: Arc<TxFlows>,
     rx_flows: Arc<Mutex<RxFlows>>,
     requests: Arc<Mutex<OutgoingRequests>>,
+    /// `None` if the remote node doesn't cancel requests.
+    incoming_requests: Option<IncomingRequests>,
 }
 
 impl SocketReader {
@@ -359,17 +815,29 @@ impl SocketReader {
                     self.handle_skipped_message(details);
                     continue;
                 }
-                Err(ReadError::Fatal(e)) => {
-                    // TODO: error handling.
-                    panic!("fatal error while reading from socket: {:#}", e);
+                Err(ReadError::Fatal(err)) => {
+                    warn!(
+                        message = "cannot read from socket",
+                        error = format!("{:#}", err)
+                    );
+                    break;
                 }
             };
 
             scope::set_trace_id(network_envelope.trace_id);
+            self.taps.dump(Direction::In, &network_envelope);
 
             let (sender, recipient) = (network_envelope.sender, network_envelope.recipient);
             let envelope = ward!(self.make_envelope(network_envelope), continue);
 
+            if let (
+                Some(incoming),
+                MessageKind::RequestAny(token) | MessageKind::RequestAll(token),
+            ) = (&mut self.incoming_requests, envelope.message_kind())
+            {
+                incoming.add_token(token);
+            }
+
             // System messages have a special handling.
             if unlikely(self.handle_system_message(&envelope)) {
                 continue;
@@ -378,6 +846,11 @@ impl SocketReader {
             // Recipients can respond to the sender, so we should add a flow.
             self.tx_flows.add_flow_if_needed(sender);
 
+            if unlikely(envelope.is_expired()) {
+                self.handle_expired_message(recipient, envelope);
+                continue;
+            }
+
             // `NULL` means we should route to the group.
             if recipient == NetworkAddr::NULL {
                 self.handle_routed_message(envelope);
@@ -393,21 +866,12 @@ impl SocketReader {
     /// are properly accounted for in flow control. Also notifies the remote
     /// actor if the message was a request in order to avoid indefinite
     /// waiting from the remote actor's side.
-    fn handle_skipped_message(&self, details: EnvelopeDetails) {
-        let update = {
-            let mut rx_flows = self.rx_flows.lock();
-            if details.recipient == NetworkAddr::NULL {
-                rx_flows.acquire_routed(true);
-                rx_flows.release_routed()
-            } else {
-                // TODO: it's debatable that we should create a flow here.
-                let mut rx_flow = rx_flows.get_or_create_flow(details.recipient.into_local());
-                rx_flow.acquire_direct(true);
-                rx_flow.release_direct()
-            }
-        };
+    fn handle_skipped_message(&self, mut details: EnvelopeDetails) {
+        self.release_dropped(details.recipient);
 
-        self.send_back(update);
+        if let Some(unknown) = details.unknown.take() {
+            self.handle_unknown_message(*unknown);
+        }
 
         if details.kind == KIND_REQUEST_ALL || details.kind == KIND_REQUEST_ANY {
             let sender = self
@@ -432,10 +896,12 @@ impl SocketReader {
             || details.kind == KIND_RESPONSE_FAILED
             || details.kind == KIND_RESPONSE_IGNORED
         {
-            let Some(token) = self.requests.lock().get_token(
-                details.recipient.into_remote(),
+            let owner = details.recipient.into_local();
+            let Some((token, respond_to)) = self.requests.lock().get_token(
+                owner,
                 details.request_id.expect("bug: request_id is missing"),
                 true,
+                true,
             ) else {
                 warn!(
                     message = "received response to unknown request",
@@ -447,15 +913,86 @@ impl SocketReader {
                 return;
             };
 
-            // Dropped token will notify the request sender that the request failed.
-            drop(token);
+            if respond_to == owner {
+                // Dropped token will notify the request sender that the request failed.
+                drop(token);
+            } else if let Some(object) = self.ctx.book().get(respond_to) {
+                object.respond(token, Err(RequestError::Failed));
+            }
+        }
+    }
+
+    /// Forwards a message unknown to this node as a dead letter if enabled.
+    fn handle_unknown_message(&self, unknown: UnknownMessage) {
+        if !self.forward_unknown_messages {
+            return;
+        }
+
+        let message = messages::UnknownMessage {
+            remote_node_no: self.remote.node_no,
+            remote_group: self.remote.group_name.clone(),
+            protocol: unknown.protocol,
+            name: unknown.name,
+            version: unknown.version,
+            payload: unknown.payload,
+        };
+        let dead_letter = DeadLetter::new("unknown message".into(), message.upcast());
+
+        if let Err(err) = self.ctx.try_send(dead_letter) {
+            debug!(error = %err, "dead letter is lost");
+        }
+    }
+
+    /// Drops messages whose TTL has elapsed on the way to this node, so they
+    /// don't occupy mailboxes of recipients.
+    fn handle_expired_message(&self, recipient: NetworkAddr, envelope: Envelope) {
+        self.release_dropped(recipient);
+
+        let message = envelope.message();
+        trace!("expired {:?}", message);
+
+        if let Some(recorder) = metrics::try_recorder() {
+            let key = Key::from_static_parts("elfo_expired_messages_total", message.labels());
+            recorder.increment_counter(&key, 1);
         }
+
+        // Dropped token will notify the request sender that the request failed.
+        drop(envelope);
+    }
+
+    /// Accounts a message that isn't delivered to the recipient in flows.
+    fn release_dropped(&self, recipient: NetworkAddr) {
+        let update = {
+            let mut rx_flows = self.rx_flows.lock();
+            if recipient == NetworkAddr::NULL {
+                rx_flows.acquire_routed(true);
+                rx_flows.release_routed()
+            } else {
+                // TODO: it's debatable that we should create a flow here.
+                let mut rx_flow = rx_flows.get_or_create_flow(recipient.into_local());
+                rx_flow.acquire_direct(true);
+                rx_flow.release_direct()
+            }
+        };
+
+        self.send_back(update);
     }
 
     fn make_envelope(&self, network_envelope: NetworkEnvelope) -> Option<Envelope> {
         let sender = network_envelope.sender.into_remote();
         let recipient = network_envelope.recipient.into_local();
         let trace_id = network_envelope.trace_id;
+        let sent_time = network_envelope.sent_time;
+        let baggage = self.annotate_baggage(network_envelope.baggage);
+        let (message_id, parent_id) = (network_envelope.message_id, network_envelope.parent_id);
+
+        let restore_headers = |envelope: &mut Envelope| {
+            envelope.set_baggage(baggage.clone());
+            if let Some(message_id) = message_id {
+                envelope.set_message_ids(message_id, parent_id);
+            }
+            self.correct_age(envelope, sent_time);
+        };
 
         let (message, message_kind) = match network_envelope.payload {
             NetworkEnvelopePayload::Regular { message } => {
@@ -492,11 +1029,12 @@ impl SocketReader {
                     }
                 }
 
-                let Some(token) = self
-                    .requests
-                    .lock()
-                    .get_token(recipient, request_id, is_last)
-                else {
+                let Some((token, respond_to)) = self.requests.lock().get_token(
+                    recipient,
+                    request_id,
+                    is_last,
+                    matches!(message, Err(RequestError::Failed)),
+                ) else {
                     warn!(
                         message = "received response to unknown request",
                         recipient = %recipient,
@@ -506,7 +1044,7 @@ impl SocketReader {
                     return None;
                 };
 
-                let Some(object) = self.ctx.book().get(recipient) else {
+                let Some(object) = self.ctx.book().get(respond_to) else {
                     debug!(
                         message = "received response, but requester has gone",
                         recipient = %recipient,
@@ -516,23 +1054,58 @@ impl SocketReader {
                     return None;
                 };
 
+                // Relayed requests are sent with another id.
+                let request_id = token.request_id();
                 let envelope = message.map(|message| {
-                    Envelope::with_trace_id(
+                    let mut envelope = Envelope::with_trace_id(
                         message,
                         MessageKind::Response { sender, request_id },
                         trace_id,
-                    )
+                    );
+                    restore_headers(&mut envelope);
+                    envelope
                 });
 
-                // Since this is a response to a request which originated from this node,
-                // all the neccessary flows have been already added.
+                // Since this is a response to a request which originated from or passed
+                // through this node, all the neccessary flows have been already added.
                 object.respond(token, envelope);
 
                 return None;
             }
         };
 
-        Some(Envelope::with_trace_id(message, message_kind, trace_id))
+        let mut envelope = Envelope::with_trace_id(message, message_kind, trace_id);
+        restore_headers(&mut envelope);
+        Some(envelope)
+    }
+
+    /// Takes into account the time spent by the message before receiving,
+    /// so waiting and delivery times cover the whole path from the sender.
+    ///
+    /// The sent time is measured by the remote clock, so it's corrected using
+    /// the estimated clock offset. Until it's estimated, nothing is changed.
+    fn correct_age(&self, envelope: &mut Envelope, sent_time: Option<u64>) {
+        let (Some(sent_time), Some(offset)) = (sent_time, self.skew.get()) else {
+            return;
+        };
+
+        let now = status::unix_time_ns(SystemTime::now());
+        let age = (now as f64 - sent_time as f64) / 1e9 + offset;
+
+        // The offset is an estimation, so the age can be slightly negative.
+        envelope.set_age(Duration::from_secs_f64(age.max(0.)));
+    }
+
+    /// Adds the receive time measured by the sender's clock, if enabled.
+    /// Until the clock offset is estimated, nothing is changed.
+    fn annotate_baggage(&self, baggage: Baggage) -> Baggage {
+        let (true, Some(offset)) = (self.annotate_received_time, self.skew.get()) else {
+            return baggage;
+        };
+
+        let now = status::unix_time_ns(SystemTime::now());
+        let received_time = (now as f64 + offset * 1e9).max(0.) as u64;
+        baggage.with(RECEIVED_TIME_KEY, received_time.to_string())
     }
 
     fn handle_system_message(&mut self, envelope: &Envelope) -> bool {
@@ -543,14 +1116,35 @@ impl SocketReader {
             msg @ internode::CloseFlow => {
                 self.tx_flows.close_flow(msg);
             }
+            msg @ internode::CancelRequest => {
+                if let Some(incoming) = &mut self.incoming_requests {
+                    let request_id = RequestId::from_ffi(msg.request_id);
+                    incoming.cancel(msg.sender.into_remote(), request_id);
+                }
+            }
             msg @ internode::Ping => {
                 self.send_back(Some(internode::Pong {
                     payload: msg.payload,
+                    time_ns: Some(status::unix_time_ns(SystemTime::now())),
                 }));
             }
             msg @ internode::Pong => {
-                let time_ns = self.time_origin.elapsed().as_nanos() as u64 - msg.payload;
-                self.rtt.push(Duration::from_nanos(time_ns));
+                let elapsed_ns = self.time_origin.elapsed().as_nanos() as u64;
+                let sample = Duration::from_nanos(elapsed_ns - msg.payload);
+                self.rtt.push(sample);
+                self.answer_checks(msg.payload, sample);
+
+                if let Some(remote_ns) = msg.time_ns {
+                    let origin_ns = status::unix_time_ns(self.wall_origin);
+                    self.skew
+                        .push(origin_ns + msg.payload, remote_ns, origin_ns + elapsed_ns);
+                }
+
+                let (rtt, clock_offset) = (self.rtt.get(), self.skew.get());
+                self.status.update(|status| {
+                    status.rtt = rtt;
+                    status.clock_offset = clock_offset;
+                });
             }
             _ => return false,
         });
@@ -558,6 +1152,22 @@ impl SocketReader {
         true
     }
 
+    /// Answers checks requested before the ping with `payload` is sent.
+    fn answer_checks(&self, payload: u64, rtt: Duration) {
+        let ready = {
+            let mut checks = self.checks.lock();
+            let (ready, pending) = std::mem::take(&mut *checks)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(since, _)| *since <= payload);
+            *checks = pending;
+            ready
+        };
+
+        for (_, token) in ready {
+            self.ctx.respond(token, rtt);
+        }
+    }
+
     fn handle_direct_message(&self, recipient: Addr, envelope: Envelope) {
         let book = self.ctx.book();
         let mut flows = self.rx_flows.lock();
@@ -585,6 +1195,10 @@ impl SocketReader {
                 // TODO: maybe emit some metric?
             }
 
+            fn full(&mut self, _envelope: Envelope, _room: BoxFuture<'static, ()>) {
+                // Rejected messages are counted by the group.
+            }
+
             fn visit(&mut self, object: &ObjectArc, envelope: &Envelope) {
                 let envelope = envelope.duplicate();
                 self.this
@@ -632,7 +1246,7 @@ impl SocketReader {
         // If the recipient is unstable (i.e. already has pending messages), enqueue and
         // return. The envelope will be handled by the corresponding pusher.
         // Unexisted flows (new ones or already closed) are considered stable.
-        if flow.as_ref().map_or(false, |f| !f.is_stable()) {
+        if flow.as_ref().is_some_and(|f| !f.is_stable()) {
             let mut flow = flow.unwrap();
             flow.acquire_direct(!routed);
             flow.enqueue(envelope, routed);
@@ -642,7 +1256,7 @@ impl SocketReader {
         let result = object.try_send(Addr::NULL, envelope);
 
         // If the recipient has gone, close the flow and return.
-        if matches!(result, Err(TrySendError::Closed(_))) {
+        if result.as_ref().is_err_and(|err| !err.is_full()) {
             let (close, update) = flows.close(object.addr());
             self.send_back(close);
             self.send_back(update);
@@ -665,7 +1279,7 @@ impl SocketReader {
                     self.send_back(flows.release_routed());
                 }
             }
-            Err(TrySendError::Full(envelope)) => {
+            Err(TrySendError::MailboxFull(envelope)) => {
                 flow.enqueue(envelope, routed);
 
                 // Start a pusher for this actor.
@@ -674,19 +1288,35 @@ impl SocketReader {
                     error!(error = %err, "failed to start a pusher");
                 }
             }
-            Err(TrySendError::Closed(_)) => unreachable!(),
+            Err(_) => unreachable!(),
         }
     }
 
     fn send_back(&self, message: Option<impl Message>) {
         if let Some(envelope) = message.map(make_system_envelope) {
-            self.tx
-                .try_send(KanalItem::simple(NetworkAddr::NULL, envelope))
-                .unwrap();
+            // Fails only if the connection is closed.
+            let _ = self
+                .tx
+                .try_send(KanalItem::simple(NetworkAddr::NULL, envelope));
         }
     }
 }
 
+fn send_ping(tx: &kanal::AsyncSender<KanalItem>, payload: u64) {
+    let envelope = make_system_envelope(internode::Ping { payload });
+    // Fails only if the connection is closed.
+    let _ = tx.try_send(KanalItem::simple(NetworkAddr::NULL, envelope));
+}
+
+fn send_cancel_request(tx: &kanal::AsyncSender<KanalItem>, sender: NetworkAddr, id: RequestId) {
+    let envelope = make_system_envelope(internode::CancelRequest {
+        sender,
+        request_id: id.to_ffi(),
+    });
+    // Fails only if the connection is closed.
+    let _ = tx.try_send(KanalItem::simple(NetworkAddr::NULL, envelope));
+}
+
 fn make_system_envelope(message: impl Message) -> Envelope {
     Envelope::new(
         message.upcast(),
@@ -756,9 +1386,10 @@ impl Pusher {
 
     fn send_back(&self, message: Option<impl Message>) {
         if let Some(envelope) = message.map(make_system_envelope) {
-            self.tx
-                .try_send(KanalItem::simple(NetworkAddr::NULL, envelope))
-                .unwrap();
+            // Fails only if the connection is closed.
+            let _ = self
+                .tx
+                .try_send(KanalItem::simple(NetworkAddr::NULL, envelope));
         }
     }
 }
@@ -775,6 +1406,8 @@ struct KanalItem {
     recipient: NetworkAddr,
     envelope: Result<Envelope, RequestError>,
     token: Option<ResponseToken>,
+    /// The handle to respond through if the request is relayed.
+    relay: Option<Addr>,
 }
 
 impl KanalItem {
@@ -783,49 +1416,198 @@ impl KanalItem {
             recipient,
             envelope: Ok(envelope),
             token: None,
+            relay: None,
         }
     }
 }
 
 struct RemoteHandle {
+    node_no: NodeNo,
+    this_node_no: NodeNo,
+    link: Arc<ArcSwap<Link>>,
+    book: AddressBook,
+}
+
+/// The current way to deliver messages to the remote node.
+enum Link {
+    Connected(Connected),
+    /// The connection is closed, messages are spooled until reconnection.
+    Spooling(Arc<Spool>),
+}
+
+struct Connected {
     tx: kanal::AsyncSender<KanalItem>,
     tx_flows: Arc<TxFlows>,
+    breaker: Arc<CircuitBreaker>,
 }
 
-impl remote::RemoteHandle for RemoteHandle {
-    fn send(&self, recipient: Addr, envelope: Envelope) -> remote::SendResult {
-        let recipient = NetworkAddr::from_remote(recipient);
+impl RemoteHandle {
+    fn make_item(&self, recipient: NetworkAddr, envelope: Envelope) -> KanalItem {
+        let requester = match envelope.message_kind() {
+            MessageKind::RequestAny(token) | MessageKind::RequestAll(token) => token.sender(),
+            _ => Addr::NULL,
+        };
 
-        match self.tx_flows.acquire(recipient) {
+        let mut item = KanalItem::simple(recipient, envelope);
+
+        // The request has been received from another node and now is forwarded.
+        // Remote addresses are resolved relative to the current group, so
+        // remember the requester's handle while we're in the forwarder's scope.
+        if requester.is_remote() {
+            item.relay = self.book.get(requester).map(|object| object.addr());
+        }
+
+        item
+    }
+
+    fn send_connected(
+        &self,
+        connected: &Connected,
+        recipient: NetworkAddr,
+        envelope: Envelope,
+    ) -> remote::SendResult {
+        if unlikely(is_rejected(&connected.breaker, &envelope)) {
+            return remote::SendResult::Err(SendError::RemoteUnavailable {
+                message: envelope,
+                node_no: self.node_no,
+            });
+        }
+
+        match connected.tx_flows.acquire(recipient) {
             Acquire::Done => {
-                let mut item = Some(KanalItem::simple(recipient, envelope));
-                match self.tx.try_send_option(&mut item) {
+                let mut item = Some(self.make_item(recipient, envelope));
+                match connected.tx.try_send_option(&mut item) {
                     Ok(true) => remote::SendResult::Ok,
                     Ok(false) => unreachable!(),
-                    Err(_) => {
-                        remote::SendResult::Err(SendError(item.take().unwrap().envelope.unwrap()))
-                    }
+                    Err(_) => remote::SendResult::Err(SendError::RemoteDown {
+                        message: item.take().unwrap().envelope.unwrap(),
+                        node_no: self.node_no,
+                    }),
                 }
             }
             Acquire::Full(notified) => remote::SendResult::Wait(notified, envelope),
-            Acquire::Closed => remote::SendResult::Err(SendError(envelope)),
+            Acquire::Closed => remote::SendResult::Err(SendError::MailboxClosed(envelope)),
         }
     }
 
-    fn try_send(&self, recipient: Addr, envelope: Envelope) -> Result<(), TrySendError<Envelope>> {
-        let recipient = NetworkAddr::from_remote(recipient);
+    #[allow(clippy::result_large_err)] // `Envelope` is large by design.
+    fn try_send_connected(
+        &self,
+        connected: &Connected,
+        recipient: NetworkAddr,
+        envelope: Envelope,
+    ) -> Result<(), TrySendError<Envelope>> {
+        if unlikely(is_rejected(&connected.breaker, &envelope)) {
+            return Err(TrySendError::RemoteUnavailable {
+                message: envelope,
+                node_no: self.node_no,
+            });
+        }
 
-        match self.tx_flows.try_acquire(recipient) {
+        match connected.tx_flows.try_acquire(recipient) {
             TryAcquire::Done => {
-                let mut item = Some(KanalItem::simple(recipient, envelope));
-                match self.tx.try_send_option(&mut item) {
+                let mut item = Some(self.make_item(recipient, envelope));
+                match connected.tx.try_send_option(&mut item) {
                     Ok(true) => Ok(()),
                     Ok(false) => unreachable!(),
-                    Err(_) => Err(TrySendError::Closed(item.take().unwrap().envelope.unwrap())),
+                    Err(_) => Err(TrySendError::RemoteDown {
+                        message: item.take().unwrap().envelope.unwrap(),
+                        node_no: self.node_no,
+                    }),
                 }
             }
-            TryAcquire::Full => Err(TrySendError::Full(envelope)),
-            TryAcquire::Closed => Err(TrySendError::Closed(envelope)),
+            TryAcquire::Full => Err(TrySendError::MailboxFull(envelope)),
+            TryAcquire::Closed => Err(TrySendError::MailboxClosed(envelope)),
+        }
+    }
+
+    /// Returns the envelope back if it cannot be spooled right now.
+    #[allow(clippy::result_large_err)]
+    fn spool(
+        &self,
+        spool: &Spool,
+        recipient: NetworkAddr,
+        envelope: Envelope,
+    ) -> Result<(), (Pushed, Envelope)> {
+        // Requests cannot be spooled, because their tokens cannot be persisted,
+        // so they're rejected as if the spool were full.
+        if !matches!(envelope.message_kind(), MessageKind::Regular { .. }) {
+            return Err((Pushed::Full, envelope));
+        }
+
+        // The original is kept to be returned to the sender if encoding fails.
+        match spool.push(|| {
+            let item = KanalItem::simple(recipient, envelope.duplicate());
+            make_network_envelope(item, self.this_node_no, None, Capabilities::all()).0
+        }) {
+            Pushed::Done => Ok(()),
+            pushed => Err((pushed, envelope)),
+        }
+    }
+}
+
+/// Requests are rejected locally while the circuit breaker is open.
+fn is_rejected(breaker: &CircuitBreaker, envelope: &Envelope) -> bool {
+    let is_request = matches!(
+        envelope.message_kind(),
+        MessageKind::RequestAny(_) | MessageKind::RequestAll(_)
+    );
+
+    is_request && !breaker.allow()
+}
+
+impl remote::RemoteHandle for RemoteHandle {
+    fn send(&self, recipient: Addr, mut envelope: Envelope) -> remote::SendResult {
+        let recipient = NetworkAddr::from_remote(recipient);
+
+        loop {
+            match &**self.link.load() {
+                Link::Connected(connected) => {
+                    return self.send_connected(connected, recipient, envelope)
+                }
+                Link::Spooling(spool) => match self.spool(spool, recipient, envelope) {
+                    Ok(()) => return remote::SendResult::Ok,
+                    // The link has been switched meanwhile.
+                    Err((Pushed::Closed, e)) => envelope = e,
+                    Err((Pushed::Unencodable, e)) => {
+                        return remote::SendResult::Err(SendError::Serialization(e))
+                    }
+                    Err((_, e)) => {
+                        return remote::SendResult::Err(SendError::RemoteDown {
+                            message: e,
+                            node_no: self.node_no,
+                        })
+                    }
+                },
+            }
+        }
+    }
+
+    fn try_send(
+        &self,
+        recipient: Addr,
+        mut envelope: Envelope,
+    ) -> Result<(), TrySendError<Envelope>> {
+        let recipient = NetworkAddr::from_remote(recipient);
+
+        loop {
+            match &**self.link.load() {
+                Link::Connected(connected) => {
+                    return self.try_send_connected(connected, recipient, envelope)
+                }
+                Link::Spooling(spool) => match self.spool(spool, recipient, envelope) {
+                    Ok(()) => return Ok(()),
+                    // The link has been switched meanwhile.
+                    Err((Pushed::Closed, e)) => envelope = e,
+                    Err((Pushed::Unencodable, e)) => return Err(TrySendError::Serialization(e)),
+                    Err((_, e)) => {
+                        return Err(TrySendError::RemoteDown {
+                            message: e,
+                            node_no: self.node_no,
+                        })
+                    }
+                },
+            }
         }
     }
 
@@ -835,19 +1617,29 @@ impl remote::RemoteHandle for RemoteHandle {
 
         let recipient = NetworkAddr::from_remote(token.sender());
 
-        if likely(self.tx_flows.do_acquire(recipient)) {
-            let item = KanalItem {
-                recipient,
-                envelope,
-                token: Some(token),
-            };
-            match self.tx.try_send(item) {
-                Ok(true) => return,
-                Ok(false) => unreachable!(),
-                Err(_) => {}
+        let mut token = Some(token);
+
+        if let Link::Connected(Connected { tx, tx_flows, .. }) = &**self.link.load() {
+            if likely(tx_flows.do_acquire(recipient)) {
+                let mut item = Some(KanalItem {
+                    recipient,
+                    envelope,
+                    token: token.take(),
+                    relay: None,
+                });
+                match tx.try_send_option(&mut item) {
+                    Ok(true) => return,
+                    Ok(false) => unreachable!(),
+                    Err(_) => token = item.and_then(|item| item.token),
+                }
             }
         }
 
+        // Dropping the token would respond through this handle again.
+        if let Some(token) = token {
+            token.forget();
+        }
+
         trace!(addr = %recipient, "flow is closed, response is lost");
     }
 }
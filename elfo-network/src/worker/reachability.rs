@@ -0,0 +1,185 @@
+//! Checks that the peer is reachable before the connection is used, so the
+//! remote group isn't advertised as routable over a blackholed link.
+//!
+//! Both sides send `Ping` first and answer the peer's `Ping` with `Pong`,
+//! echoing its payload. Thus, the peer's `Ping` is always received before its
+//! `Pong`, and other messages are sent only once both sides have got a `Pong`.
+
+use std::time::{Duration, SystemTime};
+
+use eyre::{bail, eyre, Result, WrapErr};
+use quanta::Instant;
+
+use elfo_core::{_priv::AnyMessage, scope, tracing::Baggage, Message};
+
+use crate::{
+    codec::format::{NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload},
+    protocol::internode,
+    socket::{ReadError, Socket},
+    status,
+};
+
+/// Returns the round-trip time.
+pub(super) async fn check(socket: &mut Socket, timeout: Duration) -> Result<Duration> {
+    tokio::time::timeout(timeout, exchange(socket))
+        .await
+        .map_err(|_| eyre!("no pong in {:?}", timeout))?
+}
+
+async fn exchange(socket: &mut Socket) -> Result<Duration> {
+    let started_at = Instant::now();
+    let payload = status::unix_time_ns(SystemTime::now());
+    send(socket, internode::Ping { payload }).await?;
+
+    loop {
+        let message = match recv(socket).await?.downcast::<internode::Ping>() {
+            Ok(ping) => {
+                let pong = internode::Pong {
+                    payload: ping.payload,
+                    time_ns: Some(status::unix_time_ns(SystemTime::now())),
+                };
+                send(socket, pong).await?;
+                continue;
+            }
+            Err(message) => message,
+        };
+
+        match message.downcast::<internode::Pong>() {
+            Ok(pong) if pong.payload == payload => return Ok(started_at.elapsed()),
+            Ok(_) => bail!("pong with unexpected payload"),
+            Err(message) => bail!("unexpected message: {}", message.name()),
+        }
+    }
+}
+
+async fn send(socket: &mut Socket, message: impl Message) -> Result<()> {
+    let name = message.name();
+    let envelope = NetworkEnvelope {
+        sender: NetworkAddr::NULL,
+        recipient: NetworkAddr::NULL,
+        trace_id: scope::trace_id(),
+        message_id: None,
+        parent_id: None,
+        sent_time: None,
+        baggage: Baggage::default(),
+        versions: false,
+        payload: NetworkEnvelopePayload::Regular {
+            message: message.upcast(),
+        },
+    };
+
+    let send_future = socket.write.send(&envelope);
+    send_future
+        .await
+        .wrap_err_with(|| eyre!("cannot send {}", name))
+}
+
+async fn recv(socket: &mut Socket) -> Result<AnyMessage> {
+    let envelope = socket
+        .read
+        .recv()
+        .await
+        .map_err(|err| match err {
+            ReadError::EnvelopeSkipped(..) => eyre!("failed to decode message"),
+            ReadError::Fatal(report) => report,
+        })?
+        .ok_or_else(|| eyre!("connection closed"))?;
+
+    match envelope.payload {
+        NetworkEnvelopePayload::Regular { message } => Ok(message),
+        _ => bail!("unexpected message kind"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::Arc,
+    };
+
+    use futures::{future, StreamExt};
+
+    use elfo_core::{
+        _priv::{NodeLaunchId, NodeNo},
+        scope::Scope,
+        ActorMeta, Addr,
+    };
+
+    use super::*;
+    use crate::{
+        config::Transport,
+        node_map::NodeInfo,
+        socket::{self, Capabilities, Incoming},
+    };
+
+    fn node(node_no: u16) -> NodeInfo {
+        NodeInfo {
+            node_no: NodeNo::from_bits(node_no).unwrap(),
+            launch_id: NodeLaunchId::from_bits(node_no.into()),
+            groups: vec![],
+        }
+    }
+
+    async fn in_scope<F: Future>(f: F) -> F::Output {
+        let meta = Arc::new(ActorMeta {
+            group: "test".into(),
+            key: String::new(),
+        });
+        Scope::test(Addr::NULL, meta).within(f).await
+    }
+
+    async fn make_pair(port: u16) -> (Socket, Socket) {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let transport = Transport::Tcp(addr);
+        let capabilities = Capabilities::REACHABILITY_CHECK;
+
+        let mut listener = socket::listen(&transport, &node(1), capabilities)
+            .await
+            .unwrap();
+        let client_node = node(2);
+        let client = socket::connect(&transport, None, &client_node, capabilities);
+        let (server, client) = future::join(listener.next(), client).await;
+
+        let Some(Incoming::Socket(server)) = server else {
+            panic!("server failed");
+        };
+        (server, client.unwrap().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reachable() {
+        in_scope(async {
+            let (mut server, mut client) = make_pair(9210).await;
+            let timeout = Duration::from_secs(5);
+
+            let (server_result, client_result) =
+                future::join(check(&mut server, timeout), check(&mut client, timeout)).await;
+            server_result.unwrap();
+            client_result.unwrap();
+
+            // The connection can be used further.
+            send(&mut client, internode::Ping { payload: 42 })
+                .await
+                .unwrap();
+            let ping = recv(&mut server).await.unwrap();
+            assert_eq!(ping.downcast::<internode::Ping>().unwrap().payload, 42);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn unreachable() {
+        in_scope(async {
+            let (_server, mut client) = make_pair(9211).await;
+
+            // The server doesn't answer.
+            let err = check(&mut client, Duration::from_millis(50))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("no pong"));
+        })
+        .await;
+    }
+}
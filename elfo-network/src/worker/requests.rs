@@ -1,23 +1,79 @@
+use std::sync::Arc;
+
 use fxhash::FxHashMap;
 use metrics::{decrement_gauge, increment_gauge};
+use quanta::Instant;
 use tracing::error;
 
-use elfo_core::{Addr, ResponseToken, _priv::RequestId};
+use elfo_core::{
+    _priv::{AddressBook, RemoteCancellation, RequestId},
+    errors::RequestError,
+    Addr, ResponseToken,
+};
+
+use crate::circuit_breaker::CircuitBreaker;
 
-#[derive(Default)]
 pub(super) struct OutgoingRequests {
-    map: FxHashMap<(Addr, RequestId), ResponseToken>,
+    map: FxHashMap<(Addr, RequestId), OutgoingRequest>,
+    breaker: Arc<CircuitBreaker>,
+    book: AddressBook,
+}
+
+struct OutgoingRequest {
+    token: ResponseToken,
+    /// The handle of the requester's node if the request is relayed.
+    relay: Option<Addr>,
+    sent_at: Instant,
+    /// Whether the request has been already counted by the breaker as failed.
+    is_timed_out: bool,
 }
 
 impl OutgoingRequests {
+    pub(super) fn new(breaker: Arc<CircuitBreaker>, book: AddressBook) -> Self {
+        Self {
+            map: FxHashMap::default(),
+            breaker,
+            book,
+        }
+    }
+
     pub(super) fn add_token(&mut self, token: ResponseToken) {
         let (owner, request_id) = (token.sender(), token.request_id());
+        self.insert(owner, request_id, token, None);
+    }
+
+    /// Stores the token of a request received from another node and relayed
+    /// further. The response is sent back through the `relay` handle.
+    pub(super) fn add_relayed_token(
+        &mut self,
+        owner: Addr,
+        request_id: RequestId,
+        token: ResponseToken,
+        relay: Addr,
+    ) {
+        debug_assert!(token.sender().is_remote());
+        self.insert(owner, request_id, token, Some(relay));
+    }
 
+    fn insert(
+        &mut self,
+        owner: Addr,
+        request_id: RequestId,
+        token: ResponseToken,
+        relay: Option<Addr>,
+    ) {
         debug_assert!(!token.is_forgotten());
         debug_assert!(owner.is_local());
         debug_assert!(!request_id.is_null());
 
-        if self.map.insert((owner, request_id), token).is_some() {
+        let request = OutgoingRequest {
+            token,
+            relay,
+            sent_at: Instant::now(),
+            is_timed_out: false,
+        };
+
+        if self.map.insert((owner, request_id), request).is_some() {
             error!(
                 message = "duplicate request found",
                 owner = %owner,
@@ -28,27 +84,69 @@ impl OutgoingRequests {
         }
     }
 
+    /// Returns the token for the response and the address of the object to
+    /// respond through, and reports the outcome of the request to the circuit
+    /// breaker once the last response is received.
     pub(super) fn get_token(
         &mut self,
         owner: Addr,
         request_id: RequestId,
         is_last_response: bool,
-    ) -> Option<ResponseToken> {
+        is_failed: bool,
+    ) -> Option<(ResponseToken, Addr)> {
         debug_assert!(owner.is_local());
         debug_assert!(!request_id.is_null());
 
         if is_last_response {
-            let token = self.map.remove(&(owner, request_id));
+            let request = self.map.remove(&(owner, request_id))?;
+            decrement_gauge!("elfo_network_outgoing_requests", 1.);
 
-            if token.is_some() {
-                decrement_gauge!("elfo_network_outgoing_requests", 1.);
+            if request.is_timed_out {
+                // Already counted.
+            } else if is_failed {
+                self.breaker.on_failure();
+            } else {
+                self.breaker.on_success();
             }
 
-            token
+            Some((request.token, request.relay.unwrap_or(owner)))
         } else {
             self.map
                 .get(&(owner, request_id))
-                .map(ResponseToken::duplicate)
+                .map(|request| (request.token.duplicate(), request.relay.unwrap_or(owner)))
+        }
+    }
+
+    /// Forgets requests canceled by requesters, e.g. terminated ones, and
+    /// returns their keys to notify the remote node.
+    /// Responses to them are discarded once received.
+    pub(super) fn remove_canceled(&mut self) -> Vec<(Addr, RequestId)> {
+        let mut removed = Vec::new();
+        self.map.retain(|key, request| {
+            let is_canceled = request.token.is_canceled();
+            if is_canceled {
+                removed.push(*key);
+            }
+            !is_canceled
+        });
+
+        if !removed.is_empty() {
+            decrement_gauge!("elfo_network_outgoing_requests", removed.len() as f64);
+        }
+
+        removed
+    }
+
+    /// Reports requests without a response for too long as failed.
+    pub(super) fn check_timeouts(&mut self) {
+        let timeout = ward!(self.breaker.request_timeout());
+        let now = Instant::now();
+
+        for request in self.map.values_mut() {
+            if !request.is_timed_out && now - request.sent_at >= timeout {
+                request.is_timed_out = true;
+                self.breaker.on_failure();
+            }
         }
     }
 }
@@ -61,5 +159,42 @@ impl Drop for OutgoingRequests {
         if count > 0 {
             decrement_gauge!("elfo_network_outgoing_requests", count as f64);
         }
+
+        // Tokens of relayed requests cannot be resolved in this scope,
+        // so notify requesters through the corresponding handles.
+        for (_, request) in self.map.drain() {
+            if let Some(object) = request.relay.and_then(|relay| self.book.get(relay)) {
+                object.respond(request.token, Err(RequestError::Failed));
+            }
+        }
+    }
+}
+
+/// Requests received from the remote node and not responded yet.
+/// Used to propagate cancellation to local responders.
+#[derive(Default)]
+pub(super) struct IncomingRequests {
+    map: FxHashMap<(Addr, RequestId), RemoteCancellation>,
+    /// Responded requests are removed once the map reaches this size.
+    cleanup_at: usize,
+}
+
+impl IncomingRequests {
+    pub(super) fn add_token(&mut self, token: &ResponseToken) {
+        let cancellation = ward!(token.remote_cancellation());
+
+        if self.map.len() >= self.cleanup_at {
+            self.map.retain(|_, cancellation| cancellation.is_pending());
+            self.cleanup_at = (self.map.len() * 2).max(64);
+        }
+
+        self.map
+            .insert((token.sender(), token.request_id()), cancellation);
+    }
+
+    pub(super) fn cancel(&mut self, sender: Addr, request_id: RequestId) {
+        if let Some(cancellation) = self.map.remove(&(sender, request_id)) {
+            cancellation.cancel();
+        }
     }
 }
@@ -0,0 +1,492 @@
+//! An on-disk spool of messages sent to a disconnected node.
+//!
+//! Messages are stored in the same format as they're sent over the network,
+//! so the spool file is a sequence of encoded envelopes. Only regular messages
+//! are spooled, because response tokens of requests cannot be persisted.
+//!
+//! Senders only encode messages, the file is written by a blocking task
+//! receiving them through a bounded queue. Every batch of queued messages is
+//! synced to disk, so only messages still in the queue or in the last unsynced
+//! batch are lost if the host crashes. Once the spool is closed, the file is
+//! read envelope by envelope, so large spools aren't loaded into memory.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use metrics::counter;
+use parking_lot::Mutex;
+use tokio::{io::AsyncReadExt, task::JoinHandle};
+use tracing::{error, warn};
+
+use elfo_core::scope;
+
+use crate::codec::{
+    decode::{self, DecodeState, DecodeStats},
+    encode::{self, EncodeError, EncodeStats},
+    format::NetworkEnvelope,
+};
+
+/// The maximum number of encoded messages waiting to be written.
+/// Messages are rejected as if the spool were full once it's reached.
+const QUEUE_CAPACITY: usize = 1024;
+
+pub(super) struct Spool {
+    path: PathBuf,
+    max_size: u64,
+    /// `None` once the spool is closed.
+    inner: Mutex<Option<Inner>>,
+}
+
+struct Inner {
+    tx: kanal::Sender<Vec<u8>>,
+    writer: JoinHandle<()>,
+    /// Including messages waiting in the queue.
+    size: u64,
+    count: usize,
+    stats: EncodeStats,
+}
+
+pub(super) enum Pushed {
+    Done,
+    /// The spool is full, the message isn't consumed.
+    Full,
+    /// The spool is closed, the message isn't consumed.
+    Closed,
+    /// The message cannot be encoded, already logged.
+    Unencodable,
+}
+
+impl Spool {
+    /// Opens the spool file. Messages left by a previous run are kept if the
+    /// file has been modified within `ttl`.
+    pub(super) async fn open(path: PathBuf, max_size: u64, ttl: Duration) -> io::Result<Self> {
+        let (file, size, count) = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || open_file(&path, ttl))
+                .await
+                .map_err(io::Error::other)??
+        };
+
+        let (tx, rx) = kanal::bounded(QUEUE_CAPACITY);
+        let writer = {
+            let path = path.clone();
+            let scope = scope::try_expose();
+            tokio::task::spawn_blocking(move || match scope {
+                Some(scope) => scope.sync_within(|| write_all(file, rx, &path)),
+                None => write_all(file, rx, &path),
+            })
+        };
+
+        Ok(Self {
+            path,
+            max_size,
+            inner: Mutex::new(Some(Inner {
+                tx,
+                writer,
+                size,
+                count,
+                stats: EncodeStats::default(),
+            })),
+        })
+    }
+
+    /// Appends the envelope produced by `make`, which is called only if the
+    /// spool is open and isn't full. The limit can be exceeded by the last
+    /// message.
+    ///
+    /// Doesn't block, the envelope is written to the file asynchronously.
+    pub(super) fn push(&self, make: impl FnOnce() -> NetworkEnvelope) -> Pushed {
+        let mut inner = self.inner.lock();
+        let Some(inner) = inner.as_mut() else {
+            return Pushed::Closed;
+        };
+
+        if inner.size >= self.max_size || inner.tx.len() >= QUEUE_CAPACITY {
+            counter!("elfo_network_spool_rejected_messages_total", 1);
+            return Pushed::Full;
+        }
+
+        let mut buffer = Vec::new();
+        match encode::encode(&make(), &mut buffer, &mut inner.stats, None) {
+            Ok(()) => {}
+            // Already logged by the encoder.
+            Err(EncodeError::Skipped) => return Pushed::Unencodable,
+            Err(EncodeError::Fatal(err)) => {
+                error!(message = "cannot encode message to spool", error = %err);
+                return Pushed::Unencodable;
+            }
+        }
+
+        let size = buffer.len() as u64;
+        match inner.tx.try_send(buffer) {
+            Ok(true) => {}
+            // The queue is full or the writer has failed, already logged.
+            Ok(false) | Err(_) => {
+                counter!("elfo_network_spool_rejected_messages_total", 1);
+                return Pushed::Full;
+            }
+        }
+
+        inner.size += size;
+        inner.count += 1;
+        counter!("elfo_network_spooled_messages_total", 1);
+        Pushed::Done
+    }
+
+    /// Stops accepting messages and returns spooled ones. The file is removed,
+    /// so a new spool can be opened at the same path meanwhile.
+    pub(super) async fn close(&self) -> Spooled {
+        let Some(count) = self.stop().await else {
+            return Spooled::default();
+        };
+
+        let path = self.path.clone();
+        let result = async {
+            let file = tokio::fs::File::open(&path).await?;
+            // The file is still readable after removing, also on Windows,
+            // because std opens files with `FILE_SHARE_DELETE`.
+            tokio::fs::remove_file(&path).await?;
+            Ok::<_, io::Error>(file)
+        }
+        .await;
+
+        match result {
+            Ok(file) => Spooled {
+                reader: Some(tokio::io::BufReader::new(file)),
+                count,
+                stats: DecodeStats::default(),
+            },
+            Err(err) => {
+                error!(message = "cannot read spool", path = ?path, error = %err);
+                Spooled::default()
+            }
+        }
+    }
+
+    /// Stops accepting messages and removes the file.
+    /// Returns the number of discarded messages.
+    pub(super) async fn discard(&self) -> usize {
+        let Some(count) = self.stop().await else {
+            return 0;
+        };
+
+        if let Err(err) = tokio::fs::remove_file(&self.path).await {
+            error!(message = "cannot remove spool", path = ?self.path, error = %err);
+        }
+
+        count
+    }
+
+    /// Waits until queued messages are written, returns `None` if the spool
+    /// is already closed.
+    async fn stop(&self) -> Option<usize> {
+        let Inner {
+            tx, writer, count, ..
+        } = self.inner.lock().take()?;
+
+        // The writer finishes once the queue is empty.
+        drop(tx);
+        if let Err(err) = writer.await {
+            error!(message = "spool writer has failed", error = %err);
+        }
+
+        Some(count)
+    }
+}
+
+/// Messages read from a closed spool, see `Spool::close()`.
+#[derive(Default)]
+pub(super) struct Spooled {
+    reader: Option<tokio::io::BufReader<tokio::fs::File>>,
+    count: usize,
+    stats: DecodeStats,
+}
+
+impl Spooled {
+    /// Returns the number of spooled messages, including not decodable ones.
+    pub(super) fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Reads the next envelope, skipping not decodable ones.
+    pub(super) async fn next(&mut self) -> Option<NetworkEnvelope> {
+        loop {
+            let reader = self.reader.as_mut()?;
+            let result = read_envelope(reader, &mut self.stats).await;
+
+            match result {
+                Ok(Some(Some(envelope))) => return Some(envelope),
+                // Already logged by the decoder.
+                Ok(Some(None)) => {}
+                Ok(None) => self.reader = None,
+                Err(err) => {
+                    error!(message = "spool is corrupted", error = %err);
+                    self.reader = None;
+                }
+            }
+        }
+    }
+}
+
+/// Returns `Ok(None)` at the end of the file, `Ok(Some(None))` if the envelope
+/// is skipped.
+async fn read_envelope(
+    reader: &mut tokio::io::BufReader<tokio::fs::File>,
+    stats: &mut DecodeStats,
+) -> io::Result<Option<Option<NetworkEnvelope>>> {
+    let mut header = [0; 4];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    // Truncated by `Spool::open()`, so it's always complete.
+    let size = u32::from_le_bytes(header) as usize;
+    let mut data = vec![0; size.max(4)];
+    data[..4].copy_from_slice(&header);
+    reader.read_exact(&mut data[4..]).await?;
+
+    match decode::decode(&data, stats) {
+        Ok(DecodeState::Done { decoded, .. }) => Ok(Some(Some(decoded))),
+        // Chunks are never written to the spool.
+        Ok(DecodeState::Skipped { .. } | DecodeState::Chunk { .. }) => Ok(Some(None)),
+        Ok(DecodeState::NeedMoreData { .. }) => {
+            warn!(message = "spool is truncated", bytes = data.len());
+            Ok(None)
+        }
+        Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err.to_string())),
+    }
+}
+
+/// Returns the file, its size and the number of envelopes in it.
+fn open_file(path: &PathBuf, ttl: Duration) -> io::Result<(File, u64, usize)> {
+    if let Ok(metadata) = fs::metadata(path) {
+        let is_stale = metadata.modified()?.elapsed().is_ok_and(|age| age >= ttl);
+        if is_stale {
+            fs::remove_file(path)?;
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+
+    // The last envelope can be partially written, e.g. because of a crash.
+    let (size, count) = complete_len(&mut file)?;
+    file.set_len(size)?;
+
+    Ok((file, size, count))
+}
+
+/// Returns the length of the prefix containing only complete envelopes and
+/// their number. Only headers are read.
+fn complete_len(file: &mut File) -> io::Result<(u64, usize)> {
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut header = [0; 4];
+    let mut pos = 0;
+    let mut count = 0;
+
+    while pos + 4 <= len {
+        reader.read_exact(&mut header)?;
+        let size = u64::from(u32::from_le_bytes(header));
+        if size < 4 || pos + size > len {
+            break;
+        }
+
+        reader.seek_relative(size as i64 - 4)?;
+        pos += size;
+        count += 1;
+    }
+
+    Ok((pos, count))
+}
+
+fn write_all(file: File, rx: kanal::Receiver<Vec<u8>>, path: &PathBuf) {
+    let mut file = BufWriter::new(file);
+
+    let result = (|| {
+        while let Ok(buffer) = rx.recv() {
+            file.write_all(&buffer)?;
+
+            // Write a batch of queued messages at once.
+            if rx.is_empty() {
+                file.flush()?;
+                // Survive a crash of the host, not only of the process.
+                file.get_ref().sync_data()?;
+            }
+        }
+
+        file.flush()?;
+        file.get_ref().sync_data()
+    })();
+
+    if let Err(err) = result {
+        // Next messages are rejected, because the queue is disconnected.
+        error!(message = "cannot write to spool", path = ?path, error = %err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use elfo_core::{
+        message,
+        tracing::{Baggage, TraceId},
+        Local, Message,
+    };
+
+    use super::*;
+    use crate::codec::format::{NetworkAddr, NetworkEnvelopePayload};
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Command(u32);
+
+    #[message]
+    struct Unencodable(Local<u32>);
+
+    fn make(no: u32) -> NetworkEnvelope {
+        make_with(Command(no))
+    }
+
+    fn make_with(message: impl Message) -> NetworkEnvelope {
+        NetworkEnvelope {
+            sender: NetworkAddr::NULL,
+            recipient: NetworkAddr::NULL,
+            trace_id: TraceId::try_from(1).unwrap(),
+            message_id: None,
+            parent_id: None,
+            sent_time: Some(42),
+            baggage: Baggage::default(),
+            versions: true,
+            payload: NetworkEnvelopePayload::Regular {
+                message: message.upcast(),
+            },
+        }
+    }
+
+    async fn numbers(mut spooled: Spooled) -> Vec<u32> {
+        let mut numbers = Vec::new();
+        while let Some(envelope) = spooled.next().await {
+            match envelope.payload {
+                NetworkEnvelopePayload::Regular { message } => {
+                    numbers.push(message.downcast::<Command>().unwrap().0)
+                }
+                _ => unreachable!(),
+            }
+        }
+        numbers
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("elfo-spool-{name}-{nanos}/spool"))
+    }
+
+    #[tokio::test]
+    async fn push_and_close() {
+        let path = temp_path("push");
+        let spool = Spool::open(path.clone(), 1024, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        for no in 0..3 {
+            assert!(matches!(spool.push(|| make(no)), Pushed::Done));
+        }
+
+        let spooled = spool.close().await;
+        assert_eq!(spooled.len(), 3);
+        assert!(!path.exists());
+        assert_eq!(numbers(spooled).await, vec![0, 1, 2]);
+
+        // Closed, the envelope isn't made.
+        assert!(matches!(
+            spool.push(|| unreachable!("closed")),
+            Pushed::Closed
+        ));
+        assert_eq!(spool.close().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn limit() {
+        let spool = Spool::open(temp_path("limit"), 1, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert!(matches!(spool.push(|| make(0)), Pushed::Done));
+        assert!(matches!(spool.push(|| unreachable!("full")), Pushed::Full));
+        assert_eq!(numbers(spool.close().await).await, vec![0]);
+    }
+
+    #[tokio::test]
+    async fn unencodable() {
+        let spool = Spool::open(temp_path("unencodable"), 1024, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let message = Unencodable(Local::from(42));
+        assert!(matches!(
+            spool.push(|| make_with(message)),
+            Pushed::Unencodable
+        ));
+        assert!(matches!(spool.push(|| make(1)), Pushed::Done));
+        assert_eq!(numbers(spool.close().await).await, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn discard() {
+        let path = temp_path("discard");
+        let spool = Spool::open(path.clone(), 1024, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert!(matches!(spool.push(|| make(0)), Pushed::Done));
+        assert_eq!(spool.discard().await, 1);
+        assert!(!path.exists());
+        assert_eq!(spool.discard().await, 0);
+    }
+
+    #[tokio::test]
+    async fn restore() {
+        let path = temp_path("restore");
+        let ttl = Duration::from_secs(60);
+
+        let spool = Spool::open(path.clone(), 1024, ttl).await.unwrap();
+        assert!(matches!(spool.push(|| make(0)), Pushed::Done));
+        spool.stop().await;
+
+        // Emulate a crash while writing.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[100, 0, 0, 0, 0]).unwrap();
+        drop(file);
+
+        let spool = Spool::open(path.clone(), 1024, ttl).await.unwrap();
+        assert!(matches!(spool.push(|| make(1)), Pushed::Done));
+        assert_eq!(numbers(spool.close().await).await, vec![0, 1]);
+
+        // Stale messages are discarded.
+        let spool = Spool::open(path.clone(), 1024, ttl).await.unwrap();
+        assert!(matches!(spool.push(|| make(2)), Pushed::Done));
+        spool.stop().await;
+        let spool = Spool::open(path.clone(), 1024, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(spool.close().await.len(), 0);
+    }
+}
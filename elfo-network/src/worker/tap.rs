@@ -0,0 +1,172 @@
+//! Dumps of messages exchanged with the remote node, see `TapConfig`.
+
+use arc_swap::ArcSwap;
+use fxhash::FxHashSet;
+
+use elfo_core::{
+    _priv::NodeNo,
+    dumping::{Direction, Dump, Dumper, MessageKind},
+    Message,
+};
+
+use crate::{
+    codec::format::{NetworkEnvelope, NetworkEnvelopePayload},
+    config::{TapConfig, TapDirection},
+};
+
+const CLASS: &str = "network";
+
+pub(super) struct Taps {
+    node_no: NodeNo,
+    dumper: Dumper,
+    filters: ArcSwap<Filters>,
+}
+
+#[derive(Default)]
+struct Filters {
+    ingress: Option<Filter>,
+    egress: Option<Filter>,
+}
+
+/// Names of messages to dump, `None` means all.
+type Filter = Option<FxHashSet<String>>;
+
+impl Taps {
+    pub(super) fn new(node_no: NodeNo, configs: &[TapConfig]) -> Self {
+        let taps = Self {
+            node_no,
+            dumper: Dumper::new(CLASS),
+            filters: Default::default(),
+        };
+        taps.configure(configs);
+        taps
+    }
+
+    pub(super) fn configure(&self, configs: &[TapConfig]) {
+        let mut filters = Filters::default();
+
+        for config in configs {
+            if config
+                .node_no
+                .is_some_and(|node_no| node_no != self.node_no)
+            {
+                continue;
+            }
+
+            for direction in [TapDirection::Ingress, TapDirection::Egress] {
+                if config.direction.is_some_and(|d| d != direction) {
+                    continue;
+                }
+
+                let filter = match direction {
+                    TapDirection::Ingress => &mut filters.ingress,
+                    TapDirection::Egress => &mut filters.egress,
+                };
+
+                merge(filter, &config.messages);
+            }
+        }
+
+        self.filters.store(filters.into());
+    }
+
+    pub(super) fn dump(&self, direction: Direction, envelope: &NetworkEnvelope) {
+        let filters = self.filters.load();
+        let filter = match direction {
+            Direction::In => &filters.ingress,
+            Direction::Out => &filters.egress,
+        };
+
+        let Some(filter) = filter else {
+            return;
+        };
+
+        let (message, kind) = match &envelope.payload {
+            NetworkEnvelopePayload::Regular { message } => (message, MessageKind::Regular),
+            NetworkEnvelopePayload::RequestAny {
+                request_id,
+                message,
+            }
+            | NetworkEnvelopePayload::RequestAll {
+                request_id,
+                message,
+            } => (message, MessageKind::Request(request_id.to_ffi())),
+            NetworkEnvelopePayload::Response {
+                request_id,
+                message: Ok(message),
+                ..
+            } => (message, MessageKind::Response(request_id.to_ffi())),
+            // Nothing to dump.
+            NetworkEnvelopePayload::Response {
+                message: Err(_), ..
+            } => return,
+        };
+
+        if filter
+            .as_ref()
+            .is_some_and(|names| !names.contains(message.name()))
+        {
+            return;
+        }
+
+        if !message.dumping_allowed() {
+            return;
+        }
+
+        if let Some(permit) = self.dumper.acquire() {
+            permit.record(
+                Dump::builder()
+                    .direction(direction)
+                    .message_kind(kind)
+                    .message_id(envelope.message_id)
+                    .parent_id(envelope.parent_id)
+                    .finish_any(message),
+            );
+        }
+    }
+}
+
+fn merge(filter: &mut Option<Filter>, messages: &[String]) {
+    let filter = filter.get_or_insert_with(|| Some(FxHashSet::default()));
+
+    if messages.is_empty() {
+        *filter = None;
+    } else if let Some(names) = filter {
+        names.extend(messages.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(direction: Option<TapDirection>, node_no: u16, messages: &[&str]) -> TapConfig {
+        TapConfig {
+            direction,
+            node_no: NodeNo::from_bits(node_no),
+            messages: messages.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let taps = Taps::new(NodeNo::from_bits(2).unwrap(), &[]);
+        assert!(taps.filters.load().ingress.is_none());
+        assert!(taps.filters.load().egress.is_none());
+
+        taps.configure(&[
+            config(Some(TapDirection::Ingress), 2, &["A"]),
+            config(Some(TapDirection::Ingress), 2, &["B"]),
+            config(None, 3, &[]),
+            config(Some(TapDirection::Egress), 0, &[]),
+            config(Some(TapDirection::Egress), 2, &["C"]),
+        ]);
+
+        let filters = taps.filters.load();
+        let ingress = filters.ingress.as_ref().unwrap().as_ref().unwrap();
+        let mut names = ingress.iter().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["A", "B"]);
+        assert!(matches!(filters.egress, Some(None)));
+    }
+}
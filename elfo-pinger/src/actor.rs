@@ -1,5 +1,6 @@
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
+use metrics::{counter, gauge};
 use tokio::{select, time};
 use tracing::{debug, info, warn};
 
@@ -9,7 +10,10 @@ use elfo_core::{
 };
 use elfo_utils::ward;
 
-use crate::config::Config;
+use crate::{
+    config::Config,
+    protocol::{GroupRecovered, GroupUnhealthy},
+};
 
 #[message]
 struct PingTick;
@@ -27,6 +31,7 @@ pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology) {
 
     let mut is_alarming = false;
     let mut timed_out = 0;
+    let mut unhealthy = HashSet::new();
     let mut pinging = None;
 
     interval.start(ctx.config().ping_interval / group_count);
@@ -61,17 +66,31 @@ pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology) {
                 let fut = scope::expose().within(ping_group(ctx.pruned(), group, warn_threshold));
                 pinging = Some(Box::pin(fut));
             },
-            responsive = async { pinging.as_mut().unwrap().await }, if pinging.is_some() => {
+            (group, responsive) = async { pinging.as_mut().unwrap().await }, if pinging.is_some() => {
                 pinging = None;
 
                 if !responsive {
                     timed_out += 1;
+                    counter!("elfo_pinger_timeouts_total", 1, "group" => group.clone());
 
                     if !is_alarming {
                         is_alarming = true;
                         ctx.set_status(ActorStatus::ALARMING);
                     }
                 }
+
+                if responsive == unhealthy.contains(&group) {
+                    if responsive {
+                        unhealthy.remove(&group);
+                        info!(group = %group, "group has recovered");
+                        let _ = ctx.send(GroupRecovered { group }).await;
+                    } else {
+                        unhealthy.insert(group.clone());
+                        let _ = ctx.send(GroupUnhealthy { group }).await;
+                    }
+
+                    gauge!("elfo_pinger_unhealthy_groups", unhealthy.len() as f64);
+                }
             },
         }
     }
@@ -84,10 +103,14 @@ fn collect_groups(topology: &Topology, exclude: &[Addr]) -> Vec<LocalActorGroup>
         .collect()
 }
 
-async fn ping_group(ctx: Context, group: LocalActorGroup, warn_threshold: Duration) -> bool {
+async fn ping_group(
+    ctx: Context,
+    group: LocalActorGroup,
+    warn_threshold: Duration,
+) -> (String, bool) {
     debug!(group = %group.name, "checking a group");
     let fut = ctx.request_to(group.addr, Ping::default()).all().resolve();
-    if time::timeout(warn_threshold, fut).await.is_err() {
+    let responsive = if time::timeout(warn_threshold, fut).await.is_err() {
         warn!(
             message = "group hasn't responded in the allowed time",
             group = %group.name,
@@ -96,5 +119,7 @@ async fn ping_group(ctx: Context, group: LocalActorGroup, warn_threshold: Durati
         false
     } else {
         true
-    }
+    };
+
+    (group.name, responsive)
 }
@@ -2,6 +2,8 @@
 
 use elfo_core::{ActorGroup, Blueprint, Topology};
 
+pub mod protocol;
+
 mod actor;
 mod config;
 
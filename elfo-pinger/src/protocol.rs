@@ -0,0 +1,21 @@
+//! Contains events emitted by the pinger.
+//!
+//! The events are sent by `ctx.send()`, so they're delivered to groups routed
+//! from the pinger's group by the topology.
+
+use elfo_core::message;
+
+/// A group hasn't responded to a ping within `warn_threshold`.
+/// Emitted once until the group recovers.
+#[message]
+#[non_exhaustive]
+pub struct GroupUnhealthy {
+    pub group: String,
+}
+
+/// A group previously reported as unhealthy has responded to a ping.
+#[message]
+#[non_exhaustive]
+pub struct GroupRecovered {
+    pub group: String,
+}
@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use metrics::increment_counter;
+use tokio_postgres::{AsyncMessage, Client, Connection, NoTls, Socket};
+use tracing::{debug, info, warn};
+
+use elfo_core::{
+    _priv::AnyMessage,
+    message,
+    messages::{ConfigUpdated, Ping},
+    msg, registry,
+    stream::Stream,
+    time::Delay,
+    ActorStatus, Context, Message,
+};
+
+use crate::{codec::Codec, config::Config};
+
+#[message]
+struct Notified {
+    channel: String,
+    payload: String,
+}
+
+#[message]
+struct Disconnected {
+    connection_no: u64,
+    error: String,
+}
+
+#[message]
+struct Reconnect;
+
+struct Adapter {
+    ctx: Context<Config>,
+    codec: Arc<dyn Codec>,
+    tx: mpsc::Sender<AnyMessage>,
+    client: Option<Client>,
+    connected: Option<Config>,
+    connection_no: u64,
+}
+
+pub(crate) async fn exec(mut ctx: Context<Config>, codec: Arc<dyn Codec>) {
+    // Notifications are forwarded by connection tasks, so the actor can await
+    // queries without blocking the connection.
+    let (tx, rx) = mpsc::channel(128);
+    ctx.attach(Stream::from_futures03(rx));
+
+    let adapter = Adapter {
+        ctx,
+        codec,
+        tx,
+        client: None,
+        connected: None,
+        connection_no: 0,
+    };
+
+    adapter.main().await
+}
+
+impl Adapter {
+    async fn main(mut self) {
+        let system_protocol = Ping::default().protocol();
+        self.connect().await;
+
+        while let Some(envelope) = self.ctx.recv().await {
+            let envelope = msg!(match envelope {
+                ConfigUpdated => {
+                    // If disconnected, the new config is used on reconnection.
+                    let config = self.ctx.config();
+                    if self
+                        .connected
+                        .as_ref()
+                        .is_some_and(|c| config.is_reconnection_needed(c))
+                    {
+                        info!("config changed, reconnect");
+                        self.connect().await;
+                    }
+                    continue;
+                }
+                Notified { channel, payload } => {
+                    self.on_notified(&channel, &payload).await;
+                    continue;
+                }
+                Disconnected {
+                    connection_no,
+                    error,
+                } => {
+                    // Ignore connections replaced after config updates.
+                    if connection_no == self.connection_no {
+                        warn!(%error, "disconnected");
+                        self.disconnect(error);
+                    }
+                    continue;
+                }
+                Reconnect => {
+                    if self.client.is_none() {
+                        self.connect().await;
+                    }
+                    continue;
+                }
+                envelope => envelope,
+            });
+
+            // System messages (e.g. `Terminate`) aren't published.
+            if envelope.message().protocol() == system_protocol {
+                continue;
+            }
+
+            let name = envelope.message().name();
+            if let Some(payload) = self.encode(envelope.message()) {
+                drop(envelope);
+                self.notify(name, payload).await;
+            }
+        }
+    }
+
+    async fn connect(&mut self) {
+        // Drops the previous connection, if any.
+        self.client = None;
+        self.connection_no += 1;
+        self.ctx
+            .set_status(ActorStatus::INITIALIZING.with_details("connecting"));
+
+        let config = self.ctx.config().clone();
+        let connecting = tokio_postgres::connect(&config.url, NoTls);
+
+        let (client, connection) =
+            match tokio::time::timeout(config.connect_timeout, connecting).await {
+                Ok(Ok(pair)) => pair,
+                Ok(Err(err)) => return self.disconnect(err.to_string()),
+                Err(_) => return self.disconnect("connection timed out".into()),
+            };
+
+        spawn_connection(self.connection_no, connection, self.tx.clone());
+
+        let query = config
+            .channels
+            .iter()
+            .map(|channel| format!("LISTEN {};", quote_ident(channel)))
+            .collect::<String>();
+
+        if let Err(err) = client.batch_execute(&query).await {
+            return self.disconnect(err.to_string());
+        }
+
+        info!(channels = ?config.channels, "connected");
+        self.ctx
+            .set_status(ActorStatus::NORMAL.with_details("connected"));
+        self.client = Some(client);
+        self.connected = Some(config);
+    }
+
+    fn disconnect(&mut self, error: String) {
+        self.client = None;
+        self.connected = None;
+
+        let delay = self.ctx.config().reconnect_delay;
+        self.ctx
+            .set_status(ActorStatus::ALARMING.with_details(error));
+        self.ctx.attach(Delay::new(delay, Reconnect));
+    }
+
+    async fn on_notified(&self, channel: &str, payload: &str) {
+        let message = match self.codec.decode(channel, payload) {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(err) => {
+                warn!(%channel, error = %err, "cannot decode the notification, skipped");
+                increment_counter!("elfo_postgres_skipped_notifications_total");
+                return;
+            }
+        };
+
+        // Anyone allowed to `NOTIFY` can produce messages, so only configured
+        // protocols are accepted. System messages (e.g. `Terminate`) never are.
+        let protocol = message.protocol();
+        if protocol == Ping::default().protocol()
+            || !self.ctx.config().protocols.iter().any(|p| p == protocol)
+        {
+            warn!(%channel, %protocol, name = %message.name(), "forbidden protocol, skipped");
+            increment_counter!("elfo_postgres_skipped_notifications_total");
+            return;
+        }
+
+        // Notifications aren't acknowledged, so there is no one to respond to.
+        let is_request =
+            registry::lookup(protocol, message.name()).is_some_and(|info| info.is_request);
+
+        if is_request {
+            warn!(%channel, name = %message.name(), "requests aren't supported, skipped");
+            increment_counter!("elfo_postgres_skipped_notifications_total");
+            return;
+        }
+
+        if let Err(err) = self.ctx.send(message).await {
+            debug!(%channel, error = %err, "cannot send the message");
+        }
+    }
+
+    fn encode(&self, message: &AnyMessage) -> Option<String> {
+        let name = message.name();
+        if self.ctx.config().notify_channel.is_none() {
+            debug!(%name, "no notify channel, discarded");
+            increment_counter!("elfo_postgres_discarded_messages_total", "reason" => "no_channel");
+            return None;
+        }
+
+        match self.codec.encode(message) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!(%name, error = %err, "cannot encode the message, discarded");
+                increment_counter!("elfo_postgres_discarded_messages_total", "reason" => "encoding");
+                None
+            }
+        }
+    }
+
+    async fn notify(&self, name: &str, payload: String) {
+        let config = self.ctx.config();
+        let channel = config.notify_channel.as_ref().expect("checked on encoding");
+
+        let Some(client) = &self.client else {
+            debug!(%name, "disconnected, the message is discarded");
+            increment_counter!("elfo_postgres_discarded_messages_total", "reason" => "disconnected");
+            return;
+        };
+
+        // Published one by one to keep the order.
+        let query = "SELECT pg_notify($1, $2)";
+        if let Err(err) = client.execute(query, &[channel, &payload]).await {
+            debug!(%name, error = %err, "cannot notify, the message is discarded");
+            increment_counter!("elfo_postgres_discarded_messages_total", "reason" => "notification");
+        }
+    }
+}
+
+fn spawn_connection<T>(
+    connection_no: u64,
+    mut connection: Connection<Socket, T>,
+    mut tx: mpsc::Sender<AnyMessage>,
+) where
+    T: tokio_postgres::tls::TlsStream + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        let error = loop {
+            match messages.next().await {
+                Some(Ok(AsyncMessage::Notification(notification))) => {
+                    let notified = Notified {
+                        channel: notification.channel().into(),
+                        payload: notification.payload().into(),
+                    };
+
+                    // The actor is terminated.
+                    if tx.send(notified.upcast()).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(AsyncMessage::Notice(notice))) => {
+                    debug!(message = %notice.message(), "notice");
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => break err.to_string(),
+                None => break "connection closed".into(),
+            }
+        };
+
+        let disconnected = Disconnected {
+            connection_no,
+            error,
+        };
+        let _ = tx.send(disconnected.upcast()).await;
+    });
+}
+
+/// Quotes the channel name, so it can be used in `LISTEN` as is.
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_quotes_idents() {
+        assert_eq!(quote_ident("orders"), r#""orders""#);
+        assert_eq!(quote_ident("Orders"), r#""Orders""#);
+        assert_eq!(quote_ident(r#"a"b"#), r#""a""b""#);
+    }
+}
@@ -0,0 +1,66 @@
+//! Conversion between notification payloads and messages.
+
+use elfo_core::_priv::AnyMessage;
+
+/// Converts payloads of notifications to messages and messages to payloads.
+pub trait Codec: Send + Sync + 'static {
+    /// Converts a payload received on the channel to a message,
+    /// `Ok(None)` skips the notification.
+    fn decode(&self, channel: &str, payload: &str) -> Result<Option<AnyMessage>, String>;
+
+    /// Converts a message to a payload, `Ok(None)` skips the message.
+    ///
+    /// Note that Postgres limits payloads to 8000 bytes by default.
+    fn encode(&self, message: &AnyMessage) -> Result<Option<String>, String>;
+}
+
+/// Messages as `[protocol, name, body]` JSON arrays in payloads, e.g.
+/// `["orders", "OrderPlaced", { "id": 42 }]`, which can be built by triggers
+/// with `json_build_array()`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn decode(&self, _channel: &str, payload: &str) -> Result<Option<AnyMessage>, String> {
+        serde_json::from_str(payload)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    fn encode(&self, message: &AnyMessage) -> Result<Option<String>, String> {
+        serde_json::to_string(message)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{message, Message};
+
+    use super::*;
+
+    #[message(protocol = "postgres-test")]
+    #[derive(PartialEq)]
+    struct OrderPlaced {
+        id: u32,
+    }
+
+    #[test]
+    fn json_codec() {
+        let message = OrderPlaced { id: 42 };
+
+        let payload = JsonCodec
+            .encode(&message.clone().upcast())
+            .unwrap()
+            .unwrap();
+        assert_eq!(payload, r#"["postgres-test","OrderPlaced",{"id":42}]"#);
+
+        // `json_build_array()` adds spaces.
+        let payload = r#"["postgres-test", "OrderPlaced", {"id" : 42}]"#;
+        let decoded = JsonCodec.decode("orders", payload).unwrap().unwrap();
+        assert_eq!(decoded.downcast::<OrderPlaced>().unwrap(), message);
+
+        assert!(JsonCodec.decode("orders", "").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
+
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use elfo_core::{
+    message, messages::ConfigUpdated, msg, scope, time::Delay, tracing::TraceId, Addr, Context,
+    SourceHandle, Topology,
+};
+use elfo_utils::ward;
+
+use crate::{
+    config::{CatchUp, Config, JobConfig},
+    Jobs, MakeMessage,
+};
+
+/// Limits the number of runs performed at once, e.g. after long suspension.
+const MAX_RUNS_AT_ONCE: u32 = 100;
+
+#[message]
+struct Run {
+    job: String,
+}
+
+struct Job {
+    config: JobConfig,
+    make: MakeMessage,
+    targets: Vec<Addr>,
+    next: SystemTime,
+    delay: Option<Delay<Run>>,
+}
+
+pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology, registry: Jobs) {
+    let mut jobs = BTreeMap::new();
+    reschedule(&mut ctx, &topology, &registry, &mut jobs);
+
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            ConfigUpdated => reschedule(&mut ctx, &topology, &registry, &mut jobs),
+            Run { job: name } => {
+                let job = ward!(jobs.get_mut(&name), continue);
+                run(&mut ctx, &name, job);
+            }
+        });
+    }
+}
+
+fn reschedule(
+    ctx: &mut Context<Config>,
+    topology: &Topology,
+    registry: &Jobs,
+    jobs: &mut BTreeMap<String, Job>,
+) {
+    for job in std::mem::take(jobs).into_values() {
+        if let Some(delay) = job.delay {
+            delay.terminate();
+        }
+    }
+
+    let now = SystemTime::now();
+
+    for (name, config) in &ctx.config().jobs {
+        let make = ward!(registry.get(name), {
+            warn!(job = %name, "the job isn't registered, ignored");
+            continue;
+        });
+
+        let targets = config
+            .targets
+            .iter()
+            .filter_map(|target| {
+                let addr = topology
+                    .locals()
+                    .find(|g| g.name == *target)
+                    .map(|g| g.addr);
+                if addr.is_none() {
+                    warn!(job = %name, target = %target, "unknown target group, ignored");
+                }
+                addr
+            })
+            .collect();
+
+        let next = ward!(config.schedule.next_after(now), {
+            warn!(job = %name, "the schedule never occurs, ignored");
+            continue;
+        });
+
+        let job = Job {
+            config: config.clone(),
+            make: make.clone(),
+            targets,
+            next,
+            delay: None,
+        };
+
+        jobs.insert(name.clone(), job);
+    }
+
+    for (name, job) in jobs.iter_mut() {
+        schedule(ctx, name, job);
+    }
+
+    info!(count = jobs.len(), "jobs are scheduled");
+}
+
+fn schedule(ctx: &mut Context<Config>, name: &str, job: &mut Job) {
+    let jitter = job.config.jitter.mul_f64(fastrand::f64());
+    let when = at(job.next + jitter);
+    let run = Run { job: name.into() };
+    job.delay = Some(ctx.attach(Delay::until(when, run)));
+}
+
+fn run(ctx: &mut Context<Config>, name: &str, job: &mut Job) {
+    let now = SystemTime::now();
+    let tolerance = job.config.misfire_threshold + job.config.jitter;
+
+    // The scheduled occurrence is due even if the wall clock is behind.
+    let mut last = job.next;
+    let mut on_time = 0;
+    let mut missed = 0;
+    let mut occurrence = Some(job.next);
+
+    while let Some(time) = occurrence.filter(|t| *t == job.next || *t <= now) {
+        let lateness = now.duration_since(time).unwrap_or_default();
+        if lateness <= tolerance {
+            on_time += 1;
+        } else {
+            missed += 1;
+        }
+
+        last = time;
+        if on_time + missed >= MAX_RUNS_AT_ONCE {
+            break;
+        }
+
+        occurrence = job.config.schedule.next_after(time);
+    }
+
+    let catch_up = job.config.catch_up;
+    let runs = on_time
+        + match catch_up {
+            CatchUp::Skip => 0,
+            CatchUp::Once => missed.min(1),
+            CatchUp::All => missed,
+        };
+
+    if missed > 0 {
+        warn!(job = %name, missed, ?catch_up, "runs are missed");
+    }
+
+    for _ in 0..runs {
+        scope::set_trace_id(TraceId::generate());
+
+        for &target in &job.targets {
+            if let Err(err) = ctx.try_send_to(target, (job.make)()) {
+                warn!(job = %name, %target, error = %err, "cannot send the message");
+            }
+        }
+    }
+
+    match job.config.schedule.next_after(last.max(now)) {
+        Some(next) => {
+            job.next = next;
+            schedule(ctx, name, job);
+        }
+        None => warn!(job = %name, "the schedule doesn't occur anymore"),
+    }
+}
+
+fn at(time: SystemTime) -> Instant {
+    let delay = time
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO);
+    Instant::now() + delay
+}
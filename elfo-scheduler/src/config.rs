@@ -0,0 +1,44 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::Deserialize;
+
+use crate::cron::Schedule;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    #[serde(default)]
+    pub(crate) jobs: BTreeMap<String, JobConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct JobConfig {
+    /// A cron expression in UTC.
+    pub(crate) schedule: Schedule,
+    /// Names of local groups to send the job's message to.
+    pub(crate) targets: Vec<String>,
+    /// What to do with runs missed by more than `misfire_threshold`.
+    #[serde(default)]
+    pub(crate) catch_up: CatchUp,
+    /// Every run is delayed by a random duration in `[0, jitter)`.
+    #[serde(with = "humantime_serde", default)]
+    pub(crate) jitter: Duration,
+    /// How late a run can be to be considered on time.
+    #[serde(with = "humantime_serde", default = "default_misfire_threshold")]
+    pub(crate) misfire_threshold: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CatchUp {
+    /// Missed runs are skipped.
+    #[default]
+    Skip,
+    /// Missed runs are coalesced into one run.
+    Once,
+    /// Every missed run is performed.
+    All,
+}
+
+fn default_misfire_threshold() -> Duration {
+    Duration::from_secs(1)
+}
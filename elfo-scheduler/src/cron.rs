@@ -0,0 +1,303 @@
+//! Cron expressions and their occurrences in UTC.
+
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, Deserialize, Deserializer};
+
+const SECS_PER_DAY: i64 = 86_400;
+
+// Enough to find rare occurrences like Feb 29 on Monday.
+const MAX_LOOKAHEAD: i64 = 30 * 366 * SECS_PER_DAY;
+
+const MONTHS: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// A parsed cron expression, either `sec min hour day month weekday` or
+/// `min hour day month weekday` (at the zero second).
+///
+/// Each field is a list of values, ranges (`a-b`) or `*`, optionally with
+/// steps (`*/5`, `10-40/10`). Months and weekdays can be specified by names
+/// (`JAN`, `MON`). Both `0` and `7` mean Sunday. If both the day and weekday
+/// are restricted, a day matching either of them is matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Schedule {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Returns the first occurrence strictly after the provided time.
+    pub(crate) fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let start = time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64 + 1;
+        let limit = start + MAX_LOOKAHEAD;
+        let mut t = start;
+
+        while t < limit {
+            let days = t.div_euclid(SECS_PER_DAY);
+            let secs = t.rem_euclid(SECS_PER_DAY);
+            let (year, month, day) = civil_from_days(days);
+
+            if !has(self.months, month) {
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                t = days_from_civil(year, month, 1) * SECS_PER_DAY;
+                continue;
+            }
+
+            let weekday = (days + 4).rem_euclid(7) as u32; // 1970-01-01 is Thursday.
+            if !self.matches_day(day, weekday) {
+                t = (days + 1) * SECS_PER_DAY;
+                continue;
+            }
+
+            let (hour, minute, second) = (secs / 3600, secs % 3600 / 60, secs % 60);
+
+            if !has(self.hours, hour as u32) {
+                t = days * SECS_PER_DAY + (hour + 1) * 3600;
+            } else if !has(self.minutes, minute as u32) {
+                t = days * SECS_PER_DAY + hour * 3600 + (minute + 1) * 60;
+            } else if !has(self.seconds, second as u32) {
+                t += 1;
+            } else {
+                return Some(UNIX_EPOCH + Duration::from_secs(t as u64));
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, day: u32, weekday: u32) -> bool {
+        let by_day = has(self.days, day);
+        let by_weekday = has(self.weekdays, weekday);
+
+        if self.any_day || self.any_weekday {
+            by_day && by_weekday
+        } else {
+            by_day || by_weekday
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        let (second, rest) = match fields.len() {
+            5 => ("0", &fields[..]),
+            6 => (fields[0], &fields[1..]),
+            n => return Err(format!("expected 5 or 6 fields, got {n}")),
+        };
+
+        let (seconds, _) = parse_field(second, 0, 59, &[]).map_err(in_field("second"))?;
+        let (minutes, _) = parse_field(rest[0], 0, 59, &[]).map_err(in_field("minute"))?;
+        let (hours, _) = parse_field(rest[1], 0, 23, &[]).map_err(in_field("hour"))?;
+        let (days, any_day) = parse_field(rest[2], 1, 31, &[]).map_err(in_field("day"))?;
+        let (months, _) = parse_field(rest[3], 1, 12, MONTHS).map_err(in_field("month"))?;
+        let (mut weekdays, any_weekday) =
+            parse_field(rest[4], 0, 7, WEEKDAYS).map_err(in_field("weekday"))?;
+
+        // Both `0` and `7` mean Sunday.
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            seconds,
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            any_day,
+            any_weekday,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|err| de::Error::custom(format!("invalid cron expression `{s}`: {err}")))
+    }
+}
+
+fn in_field(field: &'static str) -> impl Fn(String) -> String {
+    move |err| format!("{err} in the {field} field")
+}
+
+/// Returns a bitmask of matched values and whether the field is `*`.
+fn parse_field(s: &str, min: u32, max: u32, names: &[&str]) -> Result<(u64, bool), String> {
+    let mut mask = 0;
+
+    for part in s.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("invalid step `{step}`")),
+            },
+            None => (part, None),
+        };
+
+        let (from, to) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some((from, to)) = range.split_once('-') {
+            (parse_value(from, min, names)?, parse_value(to, min, names)?)
+        } else {
+            let value = parse_value(range, min, names)?;
+            (value, if step.is_some() { max } else { value })
+        };
+
+        if from < min || to > max || from > to {
+            return Err(format!("`{range}` is out of range {min}-{max}"));
+        }
+
+        for value in (from..=to).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok((mask, s == "*" || s == "?"))
+}
+
+fn parse_value(s: &str, min: u32, names: &[&str]) -> Result<u32, String> {
+    if let Ok(value) = s.parse() {
+        return Ok(value);
+    }
+
+    names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(s))
+        .map(|idx| idx as u32 + min)
+        .ok_or_else(|| format!("invalid value `{s}`"))
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+// See http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(days: i64, secs: i64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs((days * SECS_PER_DAY + secs) as u64)
+    }
+
+    fn date(year: i64, month: u32, day: u32, hour: i64, minute: i64, second: i64) -> SystemTime {
+        at(
+            days_from_civil(year, month, day),
+            hour * 3600 + minute * 60 + second,
+        )
+    }
+
+    #[test]
+    fn civil_roundtrip() {
+        for days in -1000..100_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn parse() {
+        assert!("* * * * *".parse::<Schedule>().is_ok());
+        assert!("*/5 * * * * *".parse::<Schedule>().is_ok());
+        assert!("0 9-17/2 * JAN-mar MON,fri".parse::<Schedule>().is_ok());
+
+        for invalid in [
+            "* * * *",
+            "* * * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "x * * * *",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{invalid}");
+        }
+
+        let sunday = "0 0 * * 0".parse::<Schedule>().unwrap();
+        assert_eq!(sunday, "0 0 * * 7".parse().unwrap());
+        assert_eq!(sunday, "0 0 * * sun".parse().unwrap());
+    }
+
+    #[test]
+    fn next_after() {
+        let next = |expr: &str, time| expr.parse::<Schedule>().unwrap().next_after(time);
+
+        let now = date(2024, 2, 28, 23, 59, 30);
+        assert_eq!(
+            next("* * * * * *", now),
+            Some(date(2024, 2, 28, 23, 59, 31))
+        );
+        assert_eq!(next("* * * * *", now), Some(date(2024, 2, 29, 0, 0, 0)));
+        assert_eq!(
+            next("*/15 * * * * *", now),
+            Some(date(2024, 2, 28, 23, 59, 45))
+        );
+        assert_eq!(next("30 12 1 * *", now), Some(date(2024, 3, 1, 12, 30, 0)));
+        assert_eq!(next("0 0 1 1 *", now), Some(date(2025, 1, 1, 0, 0, 0)));
+
+        // 2024-02-28 is Wednesday.
+        assert_eq!(next("0 9 * * MON", now), Some(date(2024, 3, 4, 9, 0, 0)));
+        // Either the day or the weekday.
+        assert_eq!(next("0 9 10 * MON", now), Some(date(2024, 3, 4, 9, 0, 0)));
+        assert_eq!(next("0 9 1 * MON", now), Some(date(2024, 3, 1, 9, 0, 0)));
+
+        // Leap days.
+        assert_eq!(next("0 0 29 2 *", now), Some(date(2024, 2, 29, 0, 0, 0)));
+        assert_eq!(
+            next("0 0 29 2 *", date(2024, 3, 1, 0, 0, 0)),
+            Some(date(2028, 2, 29, 0, 0, 0))
+        );
+
+        // Never happens.
+        assert_eq!(next("0 0 31 2 *", now), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! Sends messages to groups on cron schedules.
+//!
+//! Jobs are registered in code, each producing a message on every run, and
+//! configured in the group's config:
+//! ```toml
+//! [system.schedulers.jobs.cleanup]
+//! # A cron expression in UTC: `[sec] min hour day month weekday`.
+//! schedule = "0 */5 * * * *"
+//! # Local groups to send the message to.
+//! targets = ["storages"]
+//! # What to do with runs missed by more than `misfire_threshold`, e.g.
+//! # because of system suspension or clock jumps:
+//! # * "skip" (default): missed runs are skipped
+//! # * "once": missed runs are coalesced into one run
+//! # * "all": every missed run is performed, up to 100 at once
+//! catch_up = "once"
+//! # Every run is delayed by a random duration up to `jitter`.
+//! jitter = "10s"
+//! # How late a run can be to be considered on time.
+//! misfire_threshold = "1s"
+//! ```
+//!
+//! Jobs that are configured, but not registered, are ignored with a warning.
+//! Every run starts a new trace.
+#![warn(rust_2018_idioms, unreachable_pub)]
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use elfo_core::{_priv::AnyMessage, ActorGroup, Blueprint, Message, Topology};
+
+mod actor;
+mod config;
+mod cron;
+
+type MakeMessage = Arc<dyn Fn() -> AnyMessage + Send + Sync>;
+
+/// A set of jobs that can be run by the scheduler.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # #[elfo::message] struct Cleanup;
+/// # let topology = elfo::Topology::empty();
+/// let jobs = elfo_scheduler::Jobs::default().add("cleanup", || Cleanup);
+/// let blueprint = elfo_scheduler::new(&topology, jobs);
+/// ```
+#[derive(Default, Clone)]
+pub struct Jobs {
+    makers: BTreeMap<String, MakeMessage>,
+}
+
+impl Jobs {
+    /// Registers a job, producing a message on every run for every target.
+    ///
+    /// A job with the same name is replaced.
+    pub fn add<M: Message>(
+        mut self,
+        name: impl Into<String>,
+        make: impl Fn() -> M + Send + Sync + 'static,
+    ) -> Self {
+        let make = Arc::new(move || make().upcast());
+        self.makers.insert(name.into(), make);
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<&MakeMessage> {
+        self.makers.get(name)
+    }
+}
+
+/// Creates a blueprint of the scheduler running the provided jobs.
+pub fn new(topology: &Topology, jobs: Jobs) -> Blueprint {
+    let topology = topology.clone();
+    ActorGroup::new()
+        .config::<config::Config>()
+        .exec(move |ctx| actor::exec(ctx, topology.clone(), jobs.clone()))
+}
@@ -0,0 +1,127 @@
+use metrics::increment_counter;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use elfo_core::{
+    messages::{ConfigUpdated, Ping},
+    msg, Addr, Context, Envelope, Message, Topology,
+};
+
+use crate::config::{Config, RuleConfig};
+
+struct Rule {
+    config: RuleConfig,
+    addr: Addr,
+}
+
+pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology) {
+    let system_protocol = Ping::default().protocol();
+    let mut rules = compile(&ctx, &topology);
+
+    while let Some(envelope) = ctx.recv().await {
+        let envelope = msg!(match envelope {
+            ConfigUpdated => {
+                rules = compile(&ctx, &topology);
+                continue;
+            }
+            envelope => envelope,
+        });
+
+        // System messages (e.g. `Terminate`) are never routed.
+        if envelope.message().protocol() == system_protocol {
+            continue;
+        }
+
+        route(&ctx, &rules, envelope).await;
+    }
+}
+
+fn compile(ctx: &Context<Config>, topology: &Topology) -> Vec<Rule> {
+    let rules = ctx
+        .config()
+        .rules
+        .iter()
+        .filter_map(|config| {
+            let addr = topology
+                .locals()
+                .find(|g| g.name == config.to)
+                .map(|g| g.addr);
+            if addr.is_none() {
+                warn!(to = %config.to, "unknown target group, the rule is ignored");
+            }
+
+            Some(Rule {
+                config: config.clone(),
+                addr: addr?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    info!(count = rules.len(), "rules are loaded");
+    rules
+}
+
+async fn route(ctx: &Context<Config>, rules: &[Rule], envelope: Envelope) {
+    let message = envelope.message();
+    let name = message.name();
+
+    // Serialized lazily, only if some rule checks fields.
+    let mut body = None;
+    let rule = rules.iter().find(|rule| {
+        let config = &rule.config;
+        config.message.as_ref().is_none_or(|m| m == name)
+            && config
+                .protocol
+                .as_ref()
+                .is_none_or(|p| p == message.protocol())
+            && config.fields.iter().all(|(path, expected)| {
+                let body = body.get_or_insert_with(|| serialize(message));
+                lookup(body, path) == Some(expected)
+            })
+    });
+
+    let Some(rule) = rule else {
+        debug!(message = %name, "no matching rule, discarded");
+        increment_counter!("elfo_switchboard_discarded_messages_total", "reason" => "unmatched");
+        return;
+    };
+
+    if ctx.forward_to(rule.addr, envelope).await.is_err() {
+        debug!(message = %name, to = %rule.config.to, "cannot forward, discarded");
+        increment_counter!("elfo_switchboard_discarded_messages_total", "reason" => "failed");
+    }
+}
+
+fn serialize(message: &impl Message) -> Value {
+    serde_json::to_value(&*message._erase()).unwrap_or_else(|err| {
+        debug!(error = %err, "cannot serialize the message, fields aren't matched");
+        Value::Null
+    })
+}
+
+/// Looks up a value by a dot-separated path, e.g. `route.legs.0.venue`.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, part| match value {
+        Value::Object(map) => map.get(part),
+        Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_looks_up_nested_fields() {
+        let value = json!({ "venue": "nyse", "legs": [{ "qty": 1 }, { "qty": 2 }] });
+
+        assert_eq!(lookup(&value, "venue"), Some(&json!("nyse")));
+        assert_eq!(lookup(&value, "legs.1.qty"), Some(&json!(2)));
+        assert_eq!(lookup(&value, "legs.2.qty"), None);
+        assert_eq!(lookup(&value, "legs.first"), None);
+        assert_eq!(lookup(&value, "venue.name"), None);
+    }
+}
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    /// Rules checked in order, the first matched one is applied.
+    #[serde(default)]
+    pub(crate) rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RuleConfig {
+    /// The message name, any message matches if omitted.
+    pub(crate) message: Option<String>,
+    /// The message protocol, any protocol matches if omitted.
+    pub(crate) protocol: Option<String>,
+    /// Expected values of fields by dot-separated paths.
+    #[serde(default)]
+    pub(crate) fields: BTreeMap<String, Value>,
+    /// The name of a local group to forward matched messages to.
+    pub(crate) to: String,
+}
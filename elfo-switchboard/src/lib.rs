@@ -0,0 +1,39 @@
+//! Routes messages to local groups by rules in the config, so simple
+//! integration topologies can be rewired without code changes.
+//!
+//! Messages should be routed to the switchboard's group, e.g. by
+//! `Local::route_all_to()`. Every message is checked against rules in order,
+//! and the first matched rule forwards it as is to the rule's group. Thus,
+//! responses to requests go directly to requesters. Unmatched messages are
+//! discarded and counted by `elfo_switchboard_discarded_messages_total`.
+//!
+//! ```toml
+//! [[system.switchboards.rules]]
+//! # The message name, any message matches if omitted.
+//! message = "OrderPlaced"
+//! # The message protocol, any protocol matches if omitted.
+//! protocol = "orders"
+//! # Expected values of the message's fields, compared exactly.
+//! # Nested fields (and items of sequences) are separated by dots.
+//! fields = { venue = "nyse", "route.kind" = "fast" }
+//! # The local group to forward matched messages to.
+//! to = "nyse_gateways"
+//! ```
+//!
+//! Rules are reloaded on config updates. Rules referring to unknown groups are
+//! ignored with a warning. System messages aren't forwarded.
+#![warn(rust_2018_idioms, unreachable_pub)]
+
+use elfo_core::{ActorGroup, Blueprint, Topology};
+
+mod actor;
+mod config;
+
+/// Creates a blueprint of the switchboard forwarding messages to local
+/// groups of the provided topology.
+pub fn new(topology: &Topology) -> Blueprint {
+    let topology = topology.clone();
+    ActorGroup::new()
+        .config::<config::Config>()
+        .exec(move |ctx| actor::exec(ctx, topology.clone()))
+}
@@ -1,17 +1,24 @@
 use std::sync::Arc;
 
+use hyper::client::{Client, HttpConnector};
 use metrics::gauge;
 use tokio::task::JoinHandle;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
 use elfo_core::{
-    message, messages::ConfigUpdated, msg, scope, time::Interval, tracing::TraceId, ActorGroup,
-    Blueprint, Context, MoveOwnership,
+    errors::RequestError,
+    journal, message,
+    messages::{ConfigUpdated, DescribeActor},
+    msg, node, scope,
+    time::Interval,
+    tracing::TraceId,
+    ActorGroup, Blueprint, Context, MoveOwnership,
 };
 
 use crate::{
-    config::{Config, Retention, Sink},
-    protocol::{GetSnapshot, Snapshot},
+    collector::Remotes,
+    config::{Config, OtlpConfig, OtlpProtocol, Retention, Sink},
+    protocol::{GetRecentEvents, GetSnapshot, MetricsReport, Snapshot},
     render::Renderer,
     storage::Storage,
 };
@@ -19,8 +26,14 @@ use crate::{
 struct Telemeter {
     ctx: Context<Config>,
     interval: Interval<CompactionTick>,
+    push_interval: Interval<PushTick>,
+    is_pushing: bool,
+    forward_interval: Interval<ForwardTick>,
+    client: Client<HttpConnector>,
+    grpc_client: Client<HttpConnector>,
     storage: Arc<Storage>,
     snapshot: Arc<Snapshot>,
+    remotes: Remotes,
     renderer: Renderer,
 }
 
@@ -33,6 +46,15 @@ struct Rendered(#[serde(serialize_with = "elfo_core::dumping::hide")] String);
 #[message]
 struct CompactionTick;
 
+#[message]
+struct PushTick;
+
+#[message]
+struct Pushed;
+
+#[message]
+struct ForwardTick;
+
 #[message]
 struct ServerFailed(MoveOwnership<hyper::Error>);
 
@@ -49,8 +71,14 @@ impl Telemeter {
 
         Self {
             interval: ctx.attach(Interval::new(CompactionTick)),
+            push_interval: ctx.attach(Interval::new(PushTick)),
+            is_pushing: false,
+            forward_interval: ctx.attach(Interval::new(ForwardTick)),
+            client: Client::new(),
+            grpc_client: Client::builder().http2_only(true).build_http(),
             storage,
             snapshot: Default::default(),
+            remotes: Remotes::default(),
             renderer,
             ctx,
         }
@@ -61,23 +89,31 @@ impl Telemeter {
         assert_eq!(self.ctx.config().sink, Sink::Prometheus);
 
         let mut address = self.ctx.config().address;
+        let mut describe_actors = self.ctx.config().describe_actors;
         let mut server = start_server(&self.ctx);
 
         self.interval.start(self.ctx.config().compaction_interval);
+        self.configure_push();
+        self.configure_forwarding();
 
         while let Some(envelope) = self.ctx.recv().await {
+            let sender = envelope.sender();
+
             msg!(match envelope {
                 ConfigUpdated => {
                     let config = self.ctx.config();
 
-                    if config.address != address {
-                        info!("address changed, rerun the server");
+                    if config.address != address || config.describe_actors != describe_actors {
+                        info!("server settings changed, rerun the server");
                         server.abort();
                         address = config.address;
+                        describe_actors = config.describe_actors;
                         server = start_server(&self.ctx);
                     }
 
                     self.renderer.configure(config);
+                    self.configure_push();
+                    self.configure_forwarding();
                 }
                 (GetSnapshot, token) => {
                     // Rendering includes compaction, skip extra compaction tick.
@@ -86,13 +122,19 @@ impl Telemeter {
                     self.fill_snapshot(/* only_histograms = */ false);
                     self.ctx.respond(token, self.snapshot.clone().into());
                 }
+                (GetRecentEvents { limit }, token) => {
+                    self.ctx.respond(token, journal::recent(limit));
+                }
                 (Render, token) => {
                     // Rendering includes compaction, skip extra compaction tick.
                     self.interval.start(self.ctx.config().compaction_interval);
 
                     self.fill_snapshot(/* only_histograms = */ false);
+                    self.remove_stale_remotes();
                     let descriptions = self.storage.descriptions();
-                    let output = self.renderer.render(&self.snapshot, &descriptions);
+                    let output = self
+                        .renderer
+                        .render(&self.snapshot, &self.remotes, &descriptions);
                     drop(descriptions);
 
                     self.ctx.respond(token, Rendered(output));
@@ -104,6 +146,21 @@ impl Telemeter {
                 CompactionTick => {
                     self.fill_snapshot(/* only_histograms = */ true);
                 }
+                PushTick => self.push(),
+                Pushed => self.is_pushing = false,
+                ForwardTick => self.forward(),
+                MetricsReport { node_no, metrics } => {
+                    let node_no = node_no.or_else(|| sender.node_no());
+
+                    match (&self.ctx.config().collector, node_no) {
+                        (Some(_), Some(node_no)) => {
+                            let now = scope::clock().now();
+                            self.remotes.update(node_no, metrics, now);
+                        }
+                        (Some(_), None) => warn!("received metrics of an unknown node"),
+                        (None, _) => warn!("received metrics, but the collector is disabled"),
+                    }
+                }
                 ServerFailed(error) => {
                     error!(error = %&error.take().unwrap(), "server failed");
                     panic!("server failed");
@@ -124,10 +181,258 @@ impl Telemeter {
         }
     }
 
+    fn configure_push(&mut self) {
+        match &self.ctx.config().otlp {
+            Some(otlp) => self.push_interval.start(otlp.interval),
+            None => self.push_interval.stop(),
+        }
+    }
+
+    fn push(&mut self) {
+        if self.ctx.config().otlp.is_none() {
+            return;
+        }
+
+        // Pushes don't overlap, a slow collector delays the next one.
+        if self.is_pushing {
+            debug!("the previous push isn't completed, skip this one");
+            return;
+        }
+
+        // Rendering includes compaction, skip extra compaction tick.
+        self.interval.start(self.ctx.config().compaction_interval);
+
+        self.fill_snapshot(/* only_histograms = */ false);
+        let config = self.ctx.config().otlp.as_ref().expect("checked above");
+        let descriptions = self.storage.descriptions();
+        let batches = self
+            .renderer
+            .render_otlp(&self.snapshot, &descriptions, config);
+        drop(descriptions);
+
+        self.is_pushing = true;
+        let client = match config.protocol {
+            OtlpProtocol::Grpc => self.grpc_client.clone(),
+            OtlpProtocol::HttpJson => self.client.clone(),
+        };
+        let pushing = push_batches(client, config, batches);
+        self.ctx.attach_task(async move {
+            pushing.await;
+            Pushed
+        });
+    }
+
+    fn configure_forwarding(&mut self) {
+        match &self.ctx.config().forwarding {
+            Some(forwarding) => self.forward_interval.start(forwarding.interval),
+            None => self.forward_interval.stop(),
+        }
+
+        if self.ctx.config().collector.is_none() {
+            self.remotes.clear();
+        }
+    }
+
+    fn forward(&mut self) {
+        if self.ctx.config().forwarding.is_none() {
+            return;
+        }
+
+        // Rendering includes compaction, skip extra compaction tick.
+        self.interval.start(self.ctx.config().compaction_interval);
+
+        self.fill_snapshot(/* only_histograms = */ false);
+        let report = MetricsReport {
+            node_no: node::node_no(),
+            metrics: self.renderer.render_report(&self.snapshot),
+        };
+
+        // Metrics shouldn't block the telemeter, so reports are dropped
+        // if the collector is unavailable or too slow.
+        if let Err(err) = self.ctx.try_send(report) {
+            debug!(error = %err, "cannot forward metrics");
+            return;
+        }
+
+        // Forwarding is scraping by the collector.
+        if self.ctx.config().retention == Retention::ResetOnScrape {
+            self.reset_distributions();
+        }
+    }
+
+    fn remove_stale_remotes(&mut self) {
+        if let Some(collector) = &self.ctx.config().collector {
+            let now = scope::clock().now();
+            self.remotes.remove_stale(now, collector.stale_after);
+        }
+    }
+
     fn reset_distributions(&mut self) {
         // Reuse the latest snapshot if possible.
         let snapshot = Arc::make_mut(&mut self.snapshot);
         snapshot.distributions_mut().for_each(|d| d.reset());
+        self.renderer.on_distributions_reset();
+    }
+}
+
+fn push_batches(
+    client: Client<HttpConnector>,
+    config: &OtlpConfig,
+    batches: Vec<Vec<u8>>,
+) -> impl std::future::Future<Output = ()> {
+    let endpoint = config.endpoint.clone();
+    let protocol = config.protocol;
+    let timeout = config.timeout;
+
+    let pushing = async move {
+        for batch in batches {
+            let result = match protocol {
+                OtlpProtocol::Grpc => push_grpc(&client, &endpoint, batch).await,
+                OtlpProtocol::HttpJson => push_http_json(&client, &endpoint, batch).await,
+            };
+
+            if let Err(error) = result {
+                warn!(%error, "failed to push metrics");
+            }
+        }
+    };
+
+    async move {
+        if tokio::time::timeout(timeout, pushing).await.is_err() {
+            warn!(?timeout, "pushing metrics timed out");
+        }
+    }
+}
+
+async fn push_http_json(
+    client: &Client<HttpConnector>,
+    endpoint: &hyper::Uri,
+    batch: Vec<u8>,
+) -> Result<(), String> {
+    use hyper::{header::CONTENT_TYPE, Body, Request};
+
+    let request = Request::post(endpoint.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(batch))
+        .expect("invalid request");
+
+    let response = client
+        .request(request)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+
+    Ok(())
+}
+
+/// Calls `MetricsService/Export` of the OTLP/gRPC protocol.
+async fn push_grpc(
+    client: &Client<HttpConnector>,
+    endpoint: &hyper::Uri,
+    batch: Vec<u8>,
+) -> Result<(), String> {
+    use hyper::{body::HttpBody, header::CONTENT_TYPE, Body, HeaderMap, Request};
+
+    const METHOD: &str = "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export";
+
+    let uri = format!("{}{METHOD}", endpoint.to_string().trim_end_matches('/'));
+
+    // Length-prefixed message: the compression flag and the big-endian length.
+    let mut body = Vec::with_capacity(5 + batch.len());
+    body.push(0);
+    body.extend_from_slice(&(batch.len() as u32).to_be_bytes());
+    body.extend_from_slice(&batch);
+
+    let request = Request::post(uri)
+        .header(CONTENT_TYPE, "application/grpc")
+        .header("te", "trailers")
+        .body(Body::from(body))
+        .map_err(|err| err.to_string())?;
+
+    let response = client
+        .request(request)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+
+    fn check_status(headers: &HeaderMap) -> Option<Result<(), String>> {
+        let status = headers.get("grpc-status")?;
+
+        Some(if status == "0" {
+            Ok(())
+        } else {
+            let message = headers
+                .get("grpc-message")
+                .and_then(|m| m.to_str().ok())
+                .unwrap_or_default();
+            Err(format!(
+                "grpc-status {}: {message}",
+                status.to_str().unwrap_or_default()
+            ))
+        })
+    }
+
+    // Errors can be returned in headers ("Trailers-Only" responses).
+    if let Some(result) = check_status(response.headers()) {
+        return result;
+    }
+
+    let mut body = response.into_body();
+    while let Some(chunk) = body.data().await {
+        chunk.map_err(|err| err.to_string())?;
+    }
+
+    let trailers = body.trailers().await.map_err(|err| err.to_string())?;
+    trailers
+        .as_ref()
+        .and_then(check_status)
+        .unwrap_or_else(|| Err("no grpc-status".into()))
+}
+
+/// Handles `/actors/{group}/{key}/state`, see `messages::DescribeActor`.
+async fn describe_actor(ctx: &Context, path: &str) -> hyper::Response<hyper::Body> {
+    use hyper::{Body, Response, StatusCode};
+
+    let respond = |status, body: String| {
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = status;
+        response
+    };
+
+    let Some((group, key)) = path
+        .strip_suffix("/state")
+        .and_then(|path| path.split_once('/'))
+    else {
+        return respond(StatusCode::NOT_FOUND, "unknown path".into());
+    };
+
+    let Some(addr) = ctx.book().local_group(group) else {
+        return respond(StatusCode::NOT_FOUND, "no such group".into());
+    };
+
+    match ctx
+        .request_to(addr, DescribeActor::new(key.into()))
+        .resolve()
+        .await
+    {
+        Ok(state) => {
+            let output = serde_json::to_string(&state).expect("cannot serialize the state");
+            respond(StatusCode::OK, output)
+        }
+        Err(RequestError::Ignored) => respond(
+            StatusCode::NOT_IMPLEMENTED,
+            "the actor doesn't describe its state".into(),
+        ),
+        Err(err) if err.send_error().is_some_and(|err| err.is_no_route()) => {
+            respond(StatusCode::NOT_FOUND, "no such actor".into())
+        }
+        Err(err) => respond(StatusCode::SERVICE_UNAVAILABLE, err.to_string()),
     }
 }
 
@@ -135,10 +440,11 @@ fn start_server(ctx: &Context<Config>) -> JoinHandle<()> {
     use hyper::{
         server::{conn::AddrStream, Server},
         service::{make_service_fn, service_fn},
-        Body, Error as HyperError, Response,
+        Body, Error as HyperError, Request, Response,
     };
 
     let address = ctx.config().address;
+    let describe_actors = ctx.config().describe_actors;
     let ctx = Arc::new(ctx.pruned());
     let ctx1 = ctx.clone();
 
@@ -152,11 +458,41 @@ fn start_server(ctx: &Context<Config>) -> JoinHandle<()> {
             let scope = scope.clone();
 
             async move {
-                Ok::<_, HyperError>(service_fn(move |_| {
+                Ok::<_, HyperError>(service_fn(move |req: Request<Body>| {
                     let ctx = ctx.clone();
                     let scope = scope.clone();
 
                     let f = async move {
+                        // Introspection of the message registry.
+                        if req.uri().path() == "/messages" {
+                            let output = serde_json::to_string(&elfo_core::registry::messages())
+                                .expect("cannot serialize the message registry");
+
+                            return Ok::<_, HyperError>(Response::new(Body::from(output)));
+                        }
+
+                        // Introspection of the system journal.
+                        if req.uri().path() == "/events" {
+                            let events = elfo_core::journal::recent(usize::MAX);
+                            let output = serde_json::to_string(&events)
+                                .expect("cannot serialize the system journal");
+
+                            return Ok::<_, HyperError>(Response::new(Body::from(output)));
+                        }
+
+                        // Introspection of actors' states, if enabled.
+                        if let Some(path) = req.uri().path().strip_prefix("/actors/") {
+                            let response = if describe_actors {
+                                describe_actor(&ctx, path).await
+                            } else {
+                                let mut response = Response::new(Body::from("disabled"));
+                                *response.status_mut() = hyper::StatusCode::NOT_FOUND;
+                                response
+                            };
+
+                            return Ok::<_, HyperError>(response);
+                        }
+
                         let Rendered(output) = ctx
                             .request_to(ctx.addr(), Render)
                             .resolve()
@@ -185,3 +521,72 @@ fn start_server(ctx: &Context<Config>) -> JoinHandle<()> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use hyper::{
+        body::Bytes,
+        service::{make_service_fn, service_fn},
+        Body, HeaderMap, Request, Response, Server,
+    };
+
+    use super::*;
+
+    // Responds with `grpc-status` in trailers, like real gRPC servers do.
+    async fn serve(grpc_status: &'static str) -> (hyper::Uri, tokio::sync::mpsc::Receiver<Bytes>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+
+        let make_svc = make_service_fn(move |_| {
+            let tx = tx.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let tx = tx.clone();
+                    async move {
+                        assert_eq!(
+                            req.uri().path(),
+                            "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export"
+                        );
+                        assert_eq!(req.headers()["content-type"], "application/grpc");
+                        tx.send(hyper::body::to_bytes(req).await.unwrap())
+                            .await
+                            .unwrap();
+
+                        let (mut sender, body) = Body::channel();
+                        tokio::spawn(async move {
+                            let mut trailers = HeaderMap::new();
+                            trailers.insert("grpc-status", grpc_status.parse().unwrap());
+                            trailers.insert("grpc-message", "oops".parse().unwrap());
+                            sender.send_trailers(trailers).await.unwrap();
+                        });
+
+                        Ok::<_, Infallible>(Response::new(body))
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .http2_only(true)
+            .serve(make_svc);
+        let uri = format!("http://{}", server.local_addr()).parse().unwrap();
+        tokio::spawn(server);
+        (uri, rx)
+    }
+
+    #[tokio::test]
+    async fn it_pushes_via_grpc() {
+        let client = Client::builder().http2_only(true).build_http();
+
+        let (endpoint, mut rx) = serve("0").await;
+        let res = push_grpc(&client, &endpoint, vec![1, 2, 3]).await;
+        assert_eq!(res, Ok(()));
+        assert_eq!(rx.recv().await.unwrap(), [0, 0, 0, 0, 3, 1, 2, 3][..]);
+
+        let (endpoint, mut rx) = serve("14").await;
+        let res = push_grpc(&client, &endpoint, vec![]).await;
+        assert_eq!(res, Err("grpc-status 14: oops".into()));
+        assert_eq!(rx.recv().await.unwrap(), [0, 0, 0, 0, 0][..]);
+    }
+}
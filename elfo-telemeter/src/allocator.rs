@@ -1,4 +1,7 @@
-use std::alloc::{GlobalAlloc, Layout};
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    mem,
+};
 
 /// Global allocator providing metrics on allocated memory
 ///
@@ -11,15 +14,124 @@ use std::alloc::{GlobalAlloc, Layout};
 /// Setting this as the global allocator provides two counters:
 /// `elfo_allocated_bytes_total` and `elfo_deallocated_bytes_total`, tracking
 /// total allocated and deallocated memory in bytes.
+///
+/// # Resident memory
+///
+/// The counters above are attributed to the actor performing (de)allocation,
+/// so memory allocated by one group and freed by another one (e.g. messages)
+/// cannot be used to find a leaking group. [`AllocatorStats::track_resident`]
+/// attributes each allocation to the group allocated it and provides the
+/// `elfo_resident_bytes` gauge per group, updated once actors are polled.
+///
+/// ```
+/// # use elfo_telemeter::AllocatorStats;
+/// #[global_allocator]
+/// static ALLOCATOR: AllocatorStats<std::alloc::System> =
+///     AllocatorStats::new(std::alloc::System).track_resident();
+/// ```
+///
+/// It costs an additional header per allocation (at least `usize`).
+///
+/// Like the allocator itself, resident memory is tracked per process: groups
+/// are identified by their numbers, so if several topologies run in one
+/// process (e.g. in tests), groups with the same number share the gauge.
 #[stability::unstable]
 pub struct AllocatorStats<A> {
     inner: A,
+    track_resident: bool,
 }
 
 impl<A> AllocatorStats<A> {
     /// Wrap a global allocator, instrumenting it with metrics
     pub const fn new(inner: A) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            track_resident: false,
+        }
+    }
+
+    /// Enables tracking of resident memory per group.
+    pub const fn track_resident(mut self) -> Self {
+        self.track_resident = true;
+        self
+    }
+}
+
+const HEADER_SIZE: usize = mem::size_of::<usize>();
+
+/// Returns a layout with the header storing a slot of the owning group and an
+/// offset of user data.
+fn with_header(layout: Layout) -> Option<(Layout, usize)> {
+    let align = layout.align().max(HEADER_SIZE);
+    // `offset` is a multiple of the original alignment, because both are
+    // powers of two.
+    let offset = align;
+    let size = layout.size().checked_add(offset)?;
+    Layout::from_size_align(size, align)
+        .ok()
+        .map(|layout| (layout, offset))
+}
+
+unsafe fn write_header(ptr: *mut u8, slot: u8) {
+    ptr.sub(HEADER_SIZE)
+        .cast::<usize>()
+        .write(usize::from(slot));
+}
+
+unsafe fn read_header(ptr: *mut u8) -> u8 {
+    ptr.sub(HEADER_SIZE).cast::<usize>().read() as u8
+}
+
+impl<A: GlobalAlloc> AllocatorStats<A> {
+    unsafe fn alloc_resident(&self, layout: Layout, zeroed: bool) -> *mut u8 {
+        let (full, offset) = match with_header(layout) {
+            Some(pair) => pair,
+            None => return std::ptr::null_mut(),
+        };
+
+        let base = if zeroed {
+            self.inner.alloc_zeroed(full)
+        } else {
+            self.inner.alloc(full)
+        };
+
+        if base.is_null() {
+            return base;
+        }
+
+        let slot = elfo_core::scope::try_with(|scope| scope.resident_slot()).unwrap_or(0);
+        let ptr = base.add(offset);
+        write_header(ptr, slot);
+        elfo_core::scope::adjust_resident_bytes(slot, layout.size() as isize);
+        ptr
+    }
+
+    unsafe fn dealloc_resident(&self, ptr: *mut u8, layout: Layout) {
+        // Cannot fail, because the same layout has been used to allocate.
+        let (full, offset) = with_header(layout).unwrap();
+        let slot = read_header(ptr);
+        self.inner.dealloc(ptr.sub(offset), full);
+        elfo_core::scope::adjust_resident_bytes(slot, -(layout.size() as isize));
+    }
+
+    unsafe fn realloc_resident(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let (full, offset) = with_header(layout).unwrap();
+        let new_full_size = match new_size.checked_add(offset) {
+            Some(size) => size,
+            None => return std::ptr::null_mut(),
+        };
+
+        // The header is preserved, so the memory is still attributed to
+        // the group allocated it originally.
+        let slot = read_header(ptr);
+        let base = self.inner.realloc(ptr.sub(offset), full, new_full_size);
+        if base.is_null() {
+            return base;
+        }
+
+        let delta = new_size as isize - layout.size() as isize;
+        elfo_core::scope::adjust_resident_bytes(slot, delta);
+        base.add(offset)
     }
 }
 
@@ -28,7 +140,12 @@ where
     A: GlobalAlloc,
 {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let ptr = self.inner.alloc(layout);
+        let ptr = if self.track_resident {
+            self.alloc_resident(layout, false)
+        } else {
+            self.inner.alloc(layout)
+        };
+
         if !ptr.is_null() {
             elfo_core::scope::try_with(|scope| {
                 scope.increment_allocated_bytes(layout.size());
@@ -38,14 +155,24 @@ where
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        self.inner.dealloc(ptr, layout);
+        if self.track_resident {
+            self.dealloc_resident(ptr, layout);
+        } else {
+            self.inner.dealloc(ptr, layout);
+        }
+
         elfo_core::scope::try_with(|scope| {
             scope.increment_deallocated_bytes(layout.size());
         });
     }
 
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        let ptr = self.inner.alloc_zeroed(layout);
+        let ptr = if self.track_resident {
+            self.alloc_resident(layout, true)
+        } else {
+            self.inner.alloc_zeroed(layout)
+        };
+
         if !ptr.is_null() {
             elfo_core::scope::try_with(|scope| {
                 scope.increment_allocated_bytes(layout.size());
@@ -55,7 +182,12 @@ where
     }
 
     unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
-        let ptr = self.inner.realloc(ptr, layout, new_size);
+        let ptr = if self.track_resident {
+            self.realloc_resident(ptr, layout, new_size)
+        } else {
+            self.inner.realloc(ptr, layout, new_size)
+        };
+
         if !ptr.is_null() {
             elfo_core::scope::try_with(|scope| {
                 scope.increment_deallocated_bytes(layout.size());
@@ -65,3 +197,43 @@ where
         ptr
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::System;
+
+    use super::*;
+
+    #[test]
+    fn resident_roundtrip() {
+        let allocator = AllocatorStats::new(System).track_resident();
+
+        for align in [1, 2, 4, 8, 16, 64, 4096] {
+            for size in [0, 1, 7, 100, 5000] {
+                let layout = Layout::from_size_align(size, align).unwrap();
+
+                unsafe {
+                    let ptr = allocator.alloc(layout);
+                    assert!(!ptr.is_null());
+                    assert_eq!(ptr as usize % align, 0);
+                    assert_eq!(read_header(ptr), 0);
+                    ptr.write_bytes(0xAB, size);
+
+                    let new_size = size * 2 + 1;
+                    let ptr = allocator.realloc(ptr, layout, new_size);
+                    assert!(!ptr.is_null());
+                    assert_eq!(ptr as usize % align, 0);
+                    assert_eq!(read_header(ptr), 0);
+                    assert!((0..size).all(|i| *ptr.add(i) == 0xAB));
+
+                    let layout = Layout::from_size_align(new_size, align).unwrap();
+                    allocator.dealloc(ptr, layout);
+
+                    let ptr = allocator.alloc_zeroed(layout);
+                    assert!((0..new_size).all(|i| *ptr.add(i) == 0));
+                    allocator.dealloc(ptr, layout);
+                }
+            }
+        }
+    }
+}
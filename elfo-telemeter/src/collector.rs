@@ -0,0 +1,69 @@
+use std::time::{Duration, SystemTime};
+
+use fxhash::FxHashMap;
+use metrics::{Key, Label};
+
+use elfo_core::NodeNo;
+
+use crate::protocol::{ReportedMetric, ReportedValue};
+
+/// The latest metrics forwarded by other nodes, see `MetricsReport`.
+#[derive(Default)]
+pub(crate) struct Remotes {
+    nodes: FxHashMap<NodeNo, RemoteNode>,
+}
+
+struct RemoteNode {
+    received_at: SystemTime,
+    metrics: Vec<(Key, ReportedValue)>,
+}
+
+impl Remotes {
+    /// Replaces metrics of the node with the reported ones.
+    pub(crate) fn update(
+        &mut self,
+        node_no: NodeNo,
+        metrics: Vec<ReportedMetric>,
+        now: SystemTime,
+    ) {
+        let metrics = metrics
+            .into_iter()
+            .map(|metric| {
+                let labels = metric
+                    .labels
+                    .into_iter()
+                    .map(|(key, value)| Label::new(key, value))
+                    .collect::<Vec<_>>();
+
+                (Key::from_parts(metric.name, labels), metric.value)
+            })
+            .collect();
+
+        let node = RemoteNode {
+            received_at: now,
+            metrics,
+        };
+
+        self.nodes.insert(node_no, node);
+    }
+
+    /// Removes metrics of nodes that haven't reported for `stale_after`.
+    pub(crate) fn remove_stale(&mut self, now: SystemTime, stale_after: Duration) {
+        self.nodes.retain(|_, node| {
+            !now.duration_since(node.received_at)
+                .is_ok_and(|elapsed| elapsed >= stale_after)
+        });
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (NodeNo, &Key, &ReportedValue)> {
+        self.nodes.iter().flat_map(|(node_no, node)| {
+            node.metrics
+                .iter()
+                .map(move |(key, value)| (*node_no, key, value))
+        })
+    }
+}
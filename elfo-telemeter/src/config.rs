@@ -1,6 +1,7 @@
 use std::{net::SocketAddr, time::Duration};
 
-use serde::Deserialize;
+use hyper::Uri;
+use serde::{de, Deserialize, Deserializer};
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
@@ -20,6 +21,70 @@ pub(crate) struct Config {
     /// The maximum time between compaction ticks.
     #[serde(with = "humantime_serde", default = "default_compaction_interval")]
     pub(crate) compaction_interval: Duration,
+    /// Whether to expose states of actors on `/actors/{group}/{key}/state`.
+    /// Disabled by default, because states can contain sensitive data.
+    #[serde(default)]
+    pub(crate) describe_actors: bool,
+    /// Pushing metrics via OTLP in addition to the sink, disabled if omitted.
+    #[serde(default)]
+    pub(crate) otlp: Option<OtlpConfig>,
+    /// Forwarding metrics to the collector node, disabled if omitted.
+    #[serde(default)]
+    pub(crate) forwarding: Option<ForwardingConfig>,
+    /// Exposing metrics forwarded by other nodes, disabled if omitted.
+    #[serde(default)]
+    pub(crate) collector: Option<CollectorConfig>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub(crate) struct OtlpConfig {
+    /// The collector's endpoint, e.g. `http://localhost:4317` for OTLP/gRPC
+    /// or `http://localhost:4318/v1/metrics` for OTLP/HTTP.
+    /// TLS and custom headers aren't supported, so only `http://` endpoints
+    /// are accepted, e.g. of a local collector forwarding to a vendor backend.
+    #[serde(deserialize_with = "deserialize_endpoint")]
+    pub(crate) endpoint: Uri,
+    /// The transport used to push metrics.
+    #[serde(default)]
+    pub(crate) protocol: OtlpProtocol,
+    /// How often metrics are pushed.
+    #[serde(with = "humantime_serde", default = "default_otlp_interval")]
+    pub(crate) interval: Duration,
+    /// The maximum time of one push, including all batches.
+    #[serde(with = "humantime_serde", default = "default_otlp_timeout")]
+    pub(crate) timeout: Duration,
+    /// The maximum number of metrics in one request.
+    #[serde(default = "default_otlp_batch_size")]
+    pub(crate) batch_size: usize,
+    /// The `service.name` resource attribute.
+    #[serde(default = "default_otlp_service_name")]
+    pub(crate) service_name: String,
+    /// Resource attributes in addition to `service.name` and `node_no`.
+    #[serde(default)]
+    pub(crate) resource_attributes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub(crate) enum OtlpProtocol {
+    /// Protobuf over HTTP/2 (cleartext), as `MetricsService/Export`.
+    #[default]
+    Grpc,
+    /// JSON over HTTP/1.1.
+    HttpJson,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub(crate) struct ForwardingConfig {
+    /// How often metrics are forwarded.
+    #[serde(with = "humantime_serde", default = "default_forwarding_interval")]
+    pub(crate) interval: Duration,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub(crate) struct CollectorConfig {
+    /// Metrics of nodes that haven't reported for this time are removed.
+    #[serde(with = "humantime_serde", default = "default_collector_stale_after")]
+    pub(crate) stale_after: Duration,
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -27,19 +92,14 @@ pub(crate) enum Sink {
     Prometheus,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, Default, PartialEq, Deserialize)]
 pub(crate) enum Retention {
     Forever,
+    #[default]
     ResetOnScrape,
     // TODO: `SlidingWindow`
 }
 
-impl Default for Retention {
-    fn default() -> Self {
-        Self::ResetOnScrape
-    }
-}
-
 fn default_quantiles() -> Vec<f64> {
     vec![0.75, 0.9, 0.95, 0.99]
 }
@@ -49,3 +109,86 @@ fn default_compaction_interval() -> Duration {
     // 1.1s is a good value that splits the scrape interval uniformly enough.
     Duration::from_millis(1100)
 }
+
+fn default_otlp_interval() -> Duration {
+    Duration::from_secs(15)
+}
+
+fn default_otlp_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_forwarding_interval() -> Duration {
+    Duration::from_secs(15)
+}
+
+fn default_collector_stale_after() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_otlp_batch_size() -> usize {
+    1000
+}
+
+fn default_otlp_service_name() -> String {
+    "elfo".into()
+}
+
+fn deserialize_endpoint<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uri, D::Error> {
+    let uri = String::deserialize(deserializer)?;
+    let uri = uri.parse::<Uri>().map_err(de::Error::custom)?;
+
+    if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+        return Err(de::Error::custom(
+            "only `http://host:port` endpoints are supported, TLS isn't supported",
+        ));
+    }
+
+    Ok(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn describe_actors_is_opt_in() {
+        let config = json!({ "sink": "Prometheus", "address": "0.0.0.0:9042" });
+        let config = Config::deserialize(config).unwrap();
+        assert!(!config.describe_actors);
+
+        let config = json!({
+            "sink": "Prometheus",
+            "address": "0.0.0.0:9042",
+            "describe_actors": true,
+        });
+        let config = Config::deserialize(config).unwrap();
+        assert!(config.describe_actors);
+    }
+
+    #[test]
+    fn otlp_uses_grpc_by_default() {
+        let config = json!({ "endpoint": "http://localhost:4317" });
+        let config = OtlpConfig::deserialize(config).unwrap();
+        assert_eq!(config.protocol, OtlpProtocol::Grpc);
+
+        let config = json!({
+            "endpoint": "http://localhost:4318/v1/metrics",
+            "protocol": "HttpJson",
+        });
+        let config = OtlpConfig::deserialize(config).unwrap();
+        assert_eq!(config.protocol, OtlpProtocol::HttpJson);
+    }
+
+    #[test]
+    fn otlp_rejects_tls() {
+        let config = json!({ "endpoint": "https://otlp.example.com:4317" });
+        let err = OtlpConfig::deserialize(config).unwrap_err();
+        assert!(err.to_string().contains("TLS isn't supported"));
+
+        let config = json!({ "endpoint": "localhost:4317" });
+        assert!(OtlpConfig::deserialize(config).is_err());
+    }
+}
@@ -11,6 +11,38 @@
 //! label is added, but it's possible to provide `actor_key` on a group basis.
 //! It's useful, if a group has few actors inside.
 //!
+//! Also, the `/messages` path exposes all registered messages with their
+//! protocols and schema hashes as JSON, see [`elfo_core::registry`], and
+//! the `/events` path exposes the system journal as JSON, see
+//! [`elfo_core::journal`]. If `describe_actors` is enabled in the config,
+//! the `/actors/{group}/{key}/state` path exposes the state of the actor as
+//! JSON, if the actor describes it, see [`elfo_core::messages::DescribeActor`].
+//!
+//! Optionally, the same metrics are pushed via OTLP/gRPC (or OTLP/HTTP in the
+//! JSON encoding) to backends that don't scrape Prometheus endpoints, see the
+//! `otlp` section of the config. Counters and summaries are cumulative; with
+//! `Retention::ResetOnScrape`, the start time of summaries is moved on every
+//! reset. `service.name` and `node_no` are added as resource attributes.
+//! TLS and custom headers aren't supported yet, so only `http://` endpoints
+//! are accepted; use a local collector to push to backends requiring them.
+//!
+//! In multi-node setups, worker nodes can forward their metrics to a single
+//! collector node instead of being scraped one by one. Workers with the
+//! `forwarding` section periodically send [`protocol::MetricsReport`], which
+//! should be routed to the telemeter of the collector node, e.g.
+//! ```ignore
+//! telemeters.route_to(&topology.remote("system.telemeters"), |envelope, _| {
+//!     msg!(match envelope {
+//!         MetricsReport => Outcome::Broadcast,
+//!         _ => Outcome::Discard,
+//!     })
+//! });
+//! ```
+//! The collector with the `collector` section exposes reported metrics along
+//! with its own ones, adding the `node_no` label. Global labels of workers
+//! are kept, the collector's ones aren't added. Metrics of nodes that haven't
+//! reported for `collector.stale_after` are removed.
+//!
 //! I'm going to extend the original crate to reuse code.
 
 #![warn(rust_2018_idioms, unreachable_pub, missing_docs)]
@@ -26,6 +58,7 @@ use self::{recorder::Recorder, storage::Storage};
 pub mod protocol;
 
 mod actor;
+mod collector;
 mod config;
 mod recorder;
 mod render;
@@ -6,7 +6,7 @@ use fxhash::FxHashMap;
 use metrics::Key;
 use metrics_util::Summary;
 
-use elfo_core::{message, ActorMeta, Local};
+use elfo_core::{journal::Event, message, ActorMeta, Local, NodeNo};
 
 /// A command to get actual snapshot of all metrics.
 /// The response is restricted to be local only for now.
@@ -14,6 +14,63 @@ use elfo_core::{message, ActorMeta, Local};
 #[non_exhaustive]
 pub struct GetSnapshot;
 
+/// A command to get the most recent events of the system journal, the oldest
+/// first. See [`elfo_core::journal`] for details.
+#[message(ret = Vec<Event>)]
+pub struct GetRecentEvents {
+    /// The maximum number of returned events.
+    pub limit: usize,
+}
+
+/// Summarized metrics of a node, forwarded by telemeters of worker nodes to
+/// the collector node. See the crate-level documentation for details.
+#[message]
+#[non_exhaustive]
+pub struct MetricsReport {
+    /// The node that has produced metrics.
+    pub node_no: Option<NodeNo>,
+    /// All metrics of the node, labels are already rendered.
+    pub metrics: Vec<ReportedMetric>,
+}
+
+/// A metric of [`MetricsReport`].
+#[message(part)]
+pub struct ReportedMetric {
+    /// The name of the metric.
+    pub name: String,
+    /// All labels of the metric, including global ones and
+    /// `actor_group`/`actor_key`.
+    pub labels: Vec<(String, String)>,
+    /// The value of the metric.
+    pub value: ReportedValue,
+}
+
+/// A value of [`ReportedMetric`].
+#[message(part)]
+pub enum ReportedValue {
+    /// A monotonically increasing counter.
+    Counter(u64),
+    /// A numerical value that can arbitrarily go up and down.
+    Gauge(f64),
+    /// A summary of a distribution.
+    Summary(ReportedSummary),
+}
+
+/// A summary of a distribution, calculated by the reporting node.
+#[message(part)]
+pub struct ReportedSummary {
+    /// Pairs of quantiles and their values.
+    pub quantiles: Vec<(f64, f64)>,
+    /// The sum of all samples.
+    pub sum: f64,
+    /// The number of all samples.
+    pub count: u64,
+    /// The minimum value, if any.
+    pub min: Option<f64>,
+    /// The maximum value, if any.
+    pub max: Option<f64>,
+}
+
 /// Actual values of all metrics.
 #[derive(Default, Clone)]
 pub struct Snapshot {
@@ -1,18 +1,27 @@
+use std::collections::BTreeMap;
+
 use fxhash::FxHashMap;
-use metrics::Label;
-use metrics_util::{parse_quantiles, Quantile};
+use metrics::{Key, Label};
+use metrics_util::{parse_quantiles, MetricKind, Quantile};
 
-use crate::{config::Config, protocol::Snapshot};
+use crate::{
+    collector::Remotes,
+    config::{Config, OtlpConfig},
+    protocol::{Distribution, Metrics, ReportedMetric, ReportedSummary, ReportedValue, Snapshot},
+};
 
-use self::prometheus::PrometheusRenderer;
+use self::{otlp::OtlpRenderer, prometheus::PrometheusRenderer};
 
+mod otlp;
 mod prometheus;
+mod report;
 
 #[derive(Default)]
 pub(crate) struct Renderer {
     quantiles: Vec<(Quantile, Label)>,
     global_labels: Vec<Label>,
     prometheus: PrometheusRenderer,
+    otlp: OtlpRenderer,
 }
 
 struct RenderOptions<'a> {
@@ -39,9 +48,12 @@ impl Renderer {
             .collect();
     }
 
+    /// Renders the snapshot and metrics of other nodes in the Prometheus
+    /// exposition format.
     pub(crate) fn render(
         &mut self,
         snapshot: &Snapshot,
+        remotes: &Remotes,
         descriptions: &FxHashMap<String, &'static str>,
     ) -> String {
         let options = RenderOptions {
@@ -50,6 +62,144 @@ impl Renderer {
             global_labels: &self.global_labels,
         };
 
-        self.prometheus.render(snapshot, options)
+        self.prometheus.render(snapshot, remotes, options)
+    }
+
+    /// Renders the snapshot into metrics forwarded to the collector.
+    pub(crate) fn render_report(&self, snapshot: &Snapshot) -> Vec<ReportedMetric> {
+        let descriptions = FxHashMap::default();
+        let options = RenderOptions {
+            quantiles: &self.quantiles,
+            descriptions: &descriptions,
+            global_labels: &self.global_labels,
+        };
+
+        report::render(snapshot, options)
+    }
+
+    /// Must be called whenever distributions of the snapshot are reset.
+    pub(crate) fn on_distributions_reset(&mut self) {
+        self.otlp.on_distributions_reset();
     }
+
+    /// Renders the snapshot into bodies of OTLP requests, encoded according
+    /// to the configured protocol.
+    pub(crate) fn render_otlp(
+        &mut self,
+        snapshot: &Snapshot,
+        descriptions: &FxHashMap<String, &'static str>,
+        config: &OtlpConfig,
+    ) -> Vec<Vec<u8>> {
+        let options = RenderOptions {
+            quantiles: &self.quantiles,
+            descriptions,
+            global_labels: &self.global_labels,
+        };
+
+        self.otlp.render(snapshot, options, config)
+    }
+}
+
+type GroupedData<'a> = BTreeMap<(MetricKind, &'a str), BTreeMap<MetricMeta<'a>, MetricValue<'a>>>;
+
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord)]
+struct MetricMeta<'a> {
+    /// Set only for metrics forwarded by other nodes.
+    node_no: Option<u16>,
+    actor_group: Option<&'a str>,
+    actor_key: Option<&'a str>,
+    key: &'a Key,
+}
+
+enum MetricValue<'a> {
+    Counter(u64),
+    Gauge(f64),
+    Distribution(&'a Distribution),
+    Summary(&'a ReportedSummary),
+}
+
+fn group_by_name(snapshot: &Snapshot) -> GroupedData<'_> {
+    let mut data: GroupedData<'_> = BTreeMap::new();
+
+    for (key, value, kind) in iter_metrics(&snapshot.global) {
+        data.entry((kind, key.name())).or_default().insert(
+            MetricMeta {
+                node_no: None,
+                actor_group: None,
+                actor_key: None,
+                key,
+            },
+            value,
+        );
+    }
+
+    for (group, per_group) in &snapshot.per_group {
+        for (key, value, kind) in iter_metrics(per_group) {
+            data.entry((kind, key.name())).or_default().insert(
+                MetricMeta {
+                    node_no: None,
+                    actor_group: Some(group),
+                    actor_key: None,
+                    key,
+                },
+                value,
+            );
+        }
+    }
+
+    for (actor_meta, per_actor) in &snapshot.per_actor {
+        for (key, value, kind) in iter_metrics(per_actor) {
+            data.entry((kind, key.name())).or_default().insert(
+                MetricMeta {
+                    node_no: None,
+                    actor_group: Some(&actor_meta.group),
+                    actor_key: Some(&actor_meta.key),
+                    key,
+                },
+                value,
+            );
+        }
+    }
+
+    data
+}
+
+fn add_remotes<'a>(data: &mut GroupedData<'a>, remotes: &'a Remotes) {
+    for (node_no, key, value) in remotes.iter() {
+        let (kind, value) = match value {
+            ReportedValue::Counter(value) => (MetricKind::Counter, MetricValue::Counter(*value)),
+            ReportedValue::Gauge(value) => (MetricKind::Gauge, MetricValue::Gauge(*value)),
+            ReportedValue::Summary(summary) => {
+                (MetricKind::Histogram, MetricValue::Summary(summary))
+            }
+        };
+
+        let meta = MetricMeta {
+            node_no: Some(node_no.into_bits()),
+            actor_group: None,
+            actor_key: None,
+            key,
+        };
+
+        data.entry((kind, key.name()))
+            .or_default()
+            .insert(meta, value);
+    }
+}
+
+fn iter_metrics(metrics: &Metrics) -> impl Iterator<Item = (&Key, MetricValue<'_>, MetricKind)> {
+    let c = metrics
+        .counters
+        .iter()
+        .map(|(k, v)| (k, MetricValue::Counter(*v), MetricKind::Counter));
+    let g = metrics
+        .gauges
+        .iter()
+        .map(|(k, v)| (k, MetricValue::Gauge(*v), MetricKind::Gauge));
+    let d = metrics
+        .distributions
+        .iter()
+        .map(|(k, v)| (k, MetricValue::Distribution(v), MetricKind::Histogram));
+
+    c.chain(g).chain(d)
 }
@@ -0,0 +1,686 @@
+//! Renders metrics as `ExportMetricsServiceRequest`, either in the protobuf
+//! encoding for OTLP/gRPC or in the JSON encoding for OTLP/HTTP, see
+//! <https://opentelemetry.io/docs/specs/otlp/>.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use metrics_util::MetricKind;
+use serde::{Serialize, Serializer};
+
+use super::{group_by_name, MetricMeta, MetricValue, RenderOptions};
+use crate::{
+    config::{OtlpConfig, OtlpProtocol},
+    protocol::Snapshot,
+};
+
+// See `AggregationTemporality` in the OTLP protocol.
+const CUMULATIVE: u8 = 2;
+
+pub(super) struct OtlpRenderer {
+    /// The start of cumulative counters.
+    start_time: u64,
+    /// The start of summaries, updated whenever distributions are reset.
+    summary_start_time: u64,
+}
+
+impl Default for OtlpRenderer {
+    fn default() -> Self {
+        let now = unix_nanos(SystemTime::now());
+
+        Self {
+            start_time: now,
+            summary_start_time: now,
+        }
+    }
+}
+
+impl OtlpRenderer {
+    /// Must be called whenever distributions are reset, e.g. by scraping with
+    /// `Retention::ResetOnScrape`, because summaries are cumulative.
+    pub(super) fn on_distributions_reset(&mut self) {
+        self.summary_start_time = unix_nanos(SystemTime::now());
+    }
+
+    pub(super) fn render(
+        &self,
+        snapshot: &Snapshot,
+        options: RenderOptions<'_>,
+        config: &OtlpConfig,
+    ) -> Vec<Vec<u8>> {
+        let now = unix_nanos(SystemTime::now());
+
+        let node_no = elfo_core::node::node_no().map(|n| n.to_string());
+        let resource = Resource {
+            attributes: [("service.name", config.service_name.as_str())]
+                .into_iter()
+                .chain(node_no.as_deref().map(|n| ("node_no", n)))
+                .chain(config.resource_attributes.iter().map(|(k, v)| (&**k, &**v)))
+                .map(|(key, value)| KeyValue::new(key, value))
+                .collect(),
+        };
+
+        let metrics = group_by_name(snapshot)
+            .into_iter()
+            .map(|((kind, name), by_labels)| {
+                let data = match kind {
+                    MetricKind::Counter => Data::Sum(Sum {
+                        data_points: by_labels
+                            .iter()
+                            .filter_map(|(meta, value)| match value {
+                                MetricValue::Counter(value) => Some(NumberDataPoint {
+                                    attributes: attributes(&options, meta),
+                                    start_time_unix_nano: Some(self.start_time),
+                                    time_unix_nano: now,
+                                    value: NumberValue::AsInt(*value as i64),
+                                }),
+                                _ => None,
+                            })
+                            .collect(),
+                        aggregation_temporality: CUMULATIVE,
+                        is_monotonic: true,
+                    }),
+                    MetricKind::Gauge => Data::Gauge(Gauge {
+                        data_points: by_labels
+                            .iter()
+                            .filter_map(|(meta, value)| match value {
+                                MetricValue::Gauge(value) => Some(NumberDataPoint {
+                                    attributes: attributes(&options, meta),
+                                    start_time_unix_nano: None,
+                                    time_unix_nano: now,
+                                    value: NumberValue::AsDouble(*value),
+                                }),
+                                _ => None,
+                            })
+                            .collect(),
+                    }),
+                    MetricKind::Histogram => Data::Summary(Summary {
+                        data_points: by_labels
+                            .iter()
+                            .filter_map(|(meta, value)| match value {
+                                MetricValue::Distribution(distribution) => {
+                                    // By the spec, 0.0 and 1.0 quantiles are min and max.
+                                    let quantile_values = distribution
+                                        .min()
+                                        .map(|min| (0., min))
+                                        .into_iter()
+                                        .chain(options.quantiles.iter().filter_map(|(q, _)| {
+                                            let value = distribution.quantile(q.value())?;
+                                            Some((q.value(), value))
+                                        }))
+                                        .chain(distribution.max().map(|max| (1., max)))
+                                        .map(|(quantile, value)| ValueAtQuantile {
+                                            quantile,
+                                            value,
+                                        })
+                                        .collect();
+
+                                    Some(SummaryDataPoint {
+                                        attributes: attributes(&options, meta),
+                                        start_time_unix_nano: self.summary_start_time,
+                                        time_unix_nano: now,
+                                        count: distribution.count() as u64,
+                                        sum: distribution.sum(),
+                                        quantile_values,
+                                    })
+                                }
+                                _ => None,
+                            })
+                            .collect(),
+                    }),
+                };
+
+                Metric {
+                    name,
+                    description: options.descriptions.get(name).copied().unwrap_or_default(),
+                    data,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        metrics
+            .chunks(config.batch_size.max(1))
+            .map(|batch| {
+                let request = ExportRequest {
+                    resource_metrics: [ResourceMetrics {
+                        resource: &resource,
+                        scope_metrics: [ScopeMetrics {
+                            scope: Scope {
+                                name: env!("CARGO_PKG_NAME"),
+                                version: env!("CARGO_PKG_VERSION"),
+                            },
+                            metrics: batch,
+                        }],
+                    }],
+                };
+
+                match config.protocol {
+                    OtlpProtocol::Grpc => {
+                        let mut buf = Vec::new();
+                        request.encode(&mut buf);
+                        buf
+                    }
+                    OtlpProtocol::HttpJson => {
+                        serde_json::to_vec(&request).expect("cannot serialize metrics")
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+fn attributes<'a>(options: &RenderOptions<'a>, meta: &MetricMeta<'a>) -> Vec<KeyValue<'a>> {
+    let global = options
+        .global_labels
+        .iter()
+        .map(|label| KeyValue::new(label.key(), label.value()));
+    let actor_group = meta.actor_group.map(|g| KeyValue::new("actor_group", g));
+    let actor_key = meta.actor_key.map(|k| KeyValue::new("actor_key", k));
+    let labels = meta
+        .key
+        .labels()
+        .map(|label| KeyValue::new(label.key(), label.value()));
+
+    global
+        .chain(actor_group)
+        .chain(actor_key)
+        .chain(labels)
+        .collect()
+}
+
+// Mirrors messages of `opentelemetry/proto/metrics/v1/metrics.proto`.
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRequest<'a> {
+    resource_metrics: [ResourceMetrics<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceMetrics<'a> {
+    resource: &'a Resource<'a>,
+    scope_metrics: [ScopeMetrics<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Resource<'a> {
+    attributes: Vec<KeyValue<'a>>,
+}
+
+#[derive(Serialize)]
+struct ScopeMetrics<'a> {
+    scope: Scope,
+    metrics: &'a [Metric<'a>],
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct Metric<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    description: &'a str,
+    #[serde(flatten)]
+    data: Data<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Data<'a> {
+    Sum(Sum<'a>),
+    Gauge(Gauge<'a>),
+    Summary(Summary<'a>),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Sum<'a> {
+    data_points: Vec<NumberDataPoint<'a>>,
+    aggregation_temporality: u8,
+    is_monotonic: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Gauge<'a> {
+    data_points: Vec<NumberDataPoint<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary<'a> {
+    data_points: Vec<SummaryDataPoint<'a>>,
+}
+
+// 64-bit integers are encoded as strings in the JSON encoding.
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NumberDataPoint<'a> {
+    attributes: Vec<KeyValue<'a>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_opt_as_str"
+    )]
+    start_time_unix_nano: Option<u64>,
+    #[serde(serialize_with = "serialize_as_str")]
+    time_unix_nano: u64,
+    #[serde(flatten)]
+    value: NumberValue,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum NumberValue {
+    AsInt(#[serde(serialize_with = "serialize_as_str")] i64),
+    AsDouble(f64),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryDataPoint<'a> {
+    attributes: Vec<KeyValue<'a>>,
+    #[serde(serialize_with = "serialize_as_str")]
+    start_time_unix_nano: u64,
+    #[serde(serialize_with = "serialize_as_str")]
+    time_unix_nano: u64,
+    #[serde(serialize_with = "serialize_as_str")]
+    count: u64,
+    sum: f64,
+    quantile_values: Vec<ValueAtQuantile>,
+}
+
+#[derive(Serialize)]
+struct ValueAtQuantile {
+    quantile: f64,
+    value: f64,
+}
+
+#[derive(Serialize)]
+struct KeyValue<'a> {
+    key: &'a str,
+    value: AnyValue<'a>,
+}
+
+impl<'a> KeyValue<'a> {
+    fn new(key: &'a str, value: &'a str) -> Self {
+        Self {
+            key,
+            value: AnyValue {
+                string_value: value,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnyValue<'a> {
+    string_value: &'a str,
+}
+
+fn serialize_as_str<S: Serializer>(value: &impl ToString, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&value.to_string())
+}
+
+fn serialize_opt_as_str<S: Serializer>(value: &Option<u64>, s: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_as_str(value, s),
+        None => s.serialize_none(),
+    }
+}
+
+// The protobuf encoding. Field numbers are taken from `metrics.proto`,
+// `resource.proto`, `common.proto` and `metrics_service.proto`.
+
+trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_FIXED64: u64 = 1;
+const WIRE_LEN: u64 = 2;
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_tag(buf: &mut Vec<u8>, field: u64, wire_type: u64) {
+    put_varint(buf, field << 3 | wire_type);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_tag(buf, field, WIRE_LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_str(buf: &mut Vec<u8>, field: u64, value: &str) {
+    // Default values are omitted.
+    if !value.is_empty() {
+        put_bytes(buf, field, value.as_bytes());
+    }
+}
+
+fn put_uint(buf: &mut Vec<u8>, field: u64, value: u64) {
+    if value != 0 {
+        put_tag(buf, field, WIRE_VARINT);
+        put_varint(buf, value);
+    }
+}
+
+fn put_fixed64(buf: &mut Vec<u8>, field: u64, value: u64) {
+    if value != 0 {
+        put_tag(buf, field, WIRE_FIXED64);
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// Unlike other fields, it's written even if zero, because it can be a member of
+// `oneof`.
+fn put_double(buf: &mut Vec<u8>, field: u64, value: f64) {
+    put_tag(buf, field, WIRE_FIXED64);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_message(buf: &mut Vec<u8>, field: u64, message: &impl Encode) {
+    let mut nested = Vec::new();
+    message.encode(&mut nested);
+    put_bytes(buf, field, &nested);
+}
+
+impl Encode for ExportRequest<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for resource_metrics in &self.resource_metrics {
+            put_message(buf, 1, resource_metrics);
+        }
+    }
+}
+
+impl Encode for ResourceMetrics<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_message(buf, 1, self.resource);
+        for scope_metrics in &self.scope_metrics {
+            put_message(buf, 2, scope_metrics);
+        }
+    }
+}
+
+impl Encode for Resource<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for attribute in &self.attributes {
+            put_message(buf, 1, attribute);
+        }
+    }
+}
+
+impl Encode for ScopeMetrics<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_message(buf, 1, &self.scope);
+        for metric in self.metrics {
+            put_message(buf, 2, metric);
+        }
+    }
+}
+
+impl Encode for Scope {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_str(buf, 1, self.name);
+        put_str(buf, 2, self.version);
+    }
+}
+
+impl Encode for Metric<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_str(buf, 1, self.name);
+        put_str(buf, 2, self.description);
+        match &self.data {
+            Data::Gauge(gauge) => put_message(buf, 5, gauge),
+            Data::Sum(sum) => put_message(buf, 7, sum),
+            Data::Summary(summary) => put_message(buf, 11, summary),
+        }
+    }
+}
+
+impl Encode for Sum<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for point in &self.data_points {
+            put_message(buf, 1, point);
+        }
+        put_uint(buf, 2, self.aggregation_temporality.into());
+        put_uint(buf, 3, self.is_monotonic.into());
+    }
+}
+
+impl Encode for Gauge<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for point in &self.data_points {
+            put_message(buf, 1, point);
+        }
+    }
+}
+
+impl Encode for Summary<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for point in &self.data_points {
+            put_message(buf, 1, point);
+        }
+    }
+}
+
+impl Encode for NumberDataPoint<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_fixed64(buf, 2, self.start_time_unix_nano.unwrap_or_default());
+        put_fixed64(buf, 3, self.time_unix_nano);
+        match self.value {
+            NumberValue::AsDouble(value) => put_double(buf, 4, value),
+            NumberValue::AsInt(value) => {
+                // `sfixed64`, written even if zero as a member of `oneof`.
+                put_tag(buf, 6, WIRE_FIXED64);
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for attribute in &self.attributes {
+            put_message(buf, 7, attribute);
+        }
+    }
+}
+
+impl Encode for SummaryDataPoint<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_fixed64(buf, 2, self.start_time_unix_nano);
+        put_fixed64(buf, 3, self.time_unix_nano);
+        put_fixed64(buf, 4, self.count);
+        put_double(buf, 5, self.sum);
+        for quantile in &self.quantile_values {
+            put_message(buf, 6, quantile);
+        }
+        for attribute in &self.attributes {
+            put_message(buf, 7, attribute);
+        }
+    }
+}
+
+impl Encode for ValueAtQuantile {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_double(buf, 1, self.quantile);
+        put_double(buf, 2, self.value);
+    }
+}
+
+impl Encode for KeyValue<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_str(buf, 1, self.key);
+        put_message(buf, 2, &self.value);
+    }
+}
+
+impl Encode for AnyValue<'_> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        // `string_value` is a member of `oneof`, so it's written even if empty.
+        put_bytes(buf, 1, self.string_value.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use fxhash::FxHashMap;
+    use metrics::{Key, Label};
+    use metrics_util::parse_quantiles;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::protocol::{Distribution, Metrics};
+
+    #[test]
+    fn it_renders_batches() {
+        let mut snapshot = Snapshot::default();
+        snapshot.global.counters.insert(Key::from_name("c"), 5);
+
+        let mut group = Metrics::default();
+        group.gauges.insert(Key::from_name("g"), 1.5);
+        let mut distribution = Distribution::default();
+        distribution.record_samples(&[1., 2., 3.]);
+        group
+            .distributions
+            .insert(Key::from_name("d"), distribution);
+        snapshot.per_group.insert("group".into(), group);
+
+        let quantiles = parse_quantiles(&[0.5])
+            .into_iter()
+            .map(|q| {
+                let label = Label::new("quantile", q.value().to_string());
+                (q, label)
+            })
+            .collect::<Vec<_>>();
+        let mut descriptions = FxHashMap::default();
+        descriptions.insert("c".into(), "some counter");
+        let global_labels = [Label::new("env", "test")];
+
+        let options = RenderOptions {
+            quantiles: &quantiles,
+            descriptions: &descriptions,
+            global_labels: &global_labels,
+        };
+
+        let config = OtlpConfig {
+            endpoint: "http://localhost:4318/v1/metrics".parse().unwrap(),
+            protocol: OtlpProtocol::HttpJson,
+            interval: Duration::from_secs(15),
+            timeout: Duration::from_secs(10),
+            batch_size: 2,
+            service_name: "test".into(),
+            resource_attributes: vec![("a".into(), "b".into())],
+        };
+
+        let batches = OtlpRenderer::default().render(&snapshot, options, &config);
+        assert_eq!(batches.len(), 2);
+
+        let batches = batches
+            .iter()
+            .map(|batch| serde_json::from_slice::<Value>(batch).unwrap())
+            .collect::<Vec<_>>();
+
+        let resource = &batches[0]["resourceMetrics"][0]["resource"];
+        assert_eq!(
+            resource["attributes"],
+            json!([
+                { "key": "service.name", "value": { "stringValue": "test" } },
+                { "key": "a", "value": { "stringValue": "b" } },
+            ])
+        );
+
+        let metrics = |batch: &Value| {
+            batch["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+                .as_array()
+                .unwrap()
+                .clone()
+        };
+        let metrics = metrics(&batches[0])
+            .into_iter()
+            .chain(metrics(&batches[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(metrics.len(), 3);
+
+        let counter = metrics.iter().find(|m| m["name"] == "c").unwrap();
+        assert_eq!(counter["description"], "some counter");
+        assert_eq!(counter["sum"]["isMonotonic"], true);
+        assert_eq!(counter["sum"]["aggregationTemporality"], CUMULATIVE);
+        let point = &counter["sum"]["dataPoints"][0];
+        assert_eq!(point["asInt"], "5");
+        assert_eq!(
+            point["attributes"],
+            json!([{ "key": "env", "value": { "stringValue": "test" } }])
+        );
+
+        let gauge = metrics.iter().find(|m| m["name"] == "g").unwrap();
+        let point = &gauge["gauge"]["dataPoints"][0];
+        assert_eq!(point["asDouble"], 1.5);
+        assert_eq!(point["attributes"][1]["key"], "actor_group");
+        assert_eq!(point["attributes"][1]["value"]["stringValue"], "group");
+
+        let summary = metrics.iter().find(|m| m["name"] == "d").unwrap();
+        let point = &summary["summary"]["dataPoints"][0];
+        assert_eq!(point["count"], "3");
+        assert_eq!(point["sum"], 6.);
+        let quantiles = point["quantileValues"].as_array().unwrap();
+        assert_eq!(quantiles.len(), 3);
+        assert_eq!(quantiles[0], json!({ "quantile": 0., "value": 1. }));
+        assert_eq!(quantiles[2], json!({ "quantile": 1., "value": 3. }));
+    }
+
+    #[test]
+    fn it_resets_start_time_of_summaries() {
+        let mut renderer = OtlpRenderer::default();
+        let start_time = renderer.start_time;
+        assert_eq!(renderer.summary_start_time, start_time);
+
+        std::thread::sleep(Duration::from_millis(1));
+        renderer.on_distributions_reset();
+
+        assert_eq!(renderer.start_time, start_time);
+        assert!(renderer.summary_start_time > start_time);
+    }
+
+    #[test]
+    fn it_encodes_protobuf() {
+        let point = NumberDataPoint {
+            attributes: vec![KeyValue::new("k", "v")],
+            start_time_unix_nano: Some(1),
+            time_unix_nano: 2,
+            value: NumberValue::AsInt(0),
+        };
+
+        let mut buf = Vec::new();
+        point.encode(&mut buf);
+
+        #[rustfmt::skip]
+        let expected = [
+            0x11, 1, 0, 0, 0, 0, 0, 0, 0, // start_time_unix_nano = 1
+            0x19, 2, 0, 0, 0, 0, 0, 0, 0, // time_unix_nano = 2
+            0x31, 0, 0, 0, 0, 0, 0, 0, 0, // as_int = 0
+            0x3a, 8, // attributes
+                0x0a, 1, b'k', // key
+                0x12, 3, 0x0a, 1, b'v', // value.string_value
+        ];
+        assert_eq!(buf, expected);
+
+        let mut buf = Vec::new();
+        put_uint(&mut buf, 2, 300);
+        assert_eq!(buf, [0x10, 0xac, 0x02]);
+    }
+}
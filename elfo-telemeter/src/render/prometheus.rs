@@ -1,18 +1,17 @@
 //! Highly inspired by `metrics-exporter-prometheus`.
 use std::{
     borrow::Cow,
-    collections::BTreeMap,
     fmt::{Display, Write},
     iter,
 };
 
 use cow_utils::CowUtils;
 use fxhash::FxHashSet;
-use metrics::{Key, Label};
+use metrics::Label;
 use metrics_util::MetricKind;
 
-use super::RenderOptions;
-use crate::protocol::{Distribution, Metrics, Snapshot};
+use super::{add_remotes, group_by_name, MetricValue, RenderOptions};
+use crate::{collector::Remotes, protocol::Snapshot};
 
 #[derive(Default)]
 pub(super) struct PrometheusRenderer {
@@ -24,9 +23,20 @@ pub(super) struct PrometheusRenderer {
 }
 
 impl PrometheusRenderer {
-    pub(super) fn render(&mut self, snapshot: &Snapshot, options: RenderOptions<'_>) -> String {
+    pub(super) fn render(
+        &mut self,
+        snapshot: &Snapshot,
+        remotes: &Remotes,
+        options: RenderOptions<'_>,
+    ) -> String {
         let mut output = String::with_capacity(self.prev_size * 5 / 4);
-        render(&mut output, snapshot, options, &mut self.known_counters);
+        render(
+            &mut output,
+            snapshot,
+            remotes,
+            options,
+            &mut self.known_counters,
+        );
         self.prev_size = output.len();
         output
     }
@@ -35,10 +45,14 @@ impl PrometheusRenderer {
 fn render(
     buffer: &mut String,
     snapshot: &Snapshot,
+    remotes: &Remotes,
     options: RenderOptions<'_>,
     known_counters: &mut FxHashSet<u64>,
 ) {
-    for ((kind, original_name), by_labels) in group_by_name(snapshot) {
+    let mut data = group_by_name(snapshot);
+    add_remotes(&mut data, remotes);
+
+    for ((kind, original_name), by_labels) in data {
         let name = &*sanitize_name(original_name);
 
         if let Some(desc) = options.descriptions.get(original_name) {
@@ -48,6 +62,13 @@ fn render(
         write_type_line(buffer, name, kind);
 
         for (meta, value) in by_labels {
+            // Metrics of other nodes already contain their global labels.
+            let global_labels = if meta.node_no.is_none() {
+                options.global_labels
+            } else {
+                &[]
+            };
+            let node_no_label = meta.node_no.map(|n| Label::new("node_no", n.to_string()));
             let actor_group_label = meta
                 .actor_group
                 .map(|g| Label::new("actor_group", g.to_string()));
@@ -55,9 +76,9 @@ fn render(
                 .actor_key
                 .map(|k| Label::new("actor_key", k.to_string()));
 
-            let labels = options
-                .global_labels
+            let labels = global_labels
                 .iter()
+                .chain(node_no_label.as_ref())
                 .chain(actor_group_label.as_ref())
                 .chain(actor_key_label.as_ref())
                 .chain(meta.key.labels());
@@ -100,86 +121,35 @@ fn render(
                         write_metric_line(buffer, name, Some("max"), labels.clone(), max);
                     }
                 }
-            }
-        }
-
-        buffer.push('\n');
-    }
-}
-
-type GroupedData<'a> = BTreeMap<(MetricKind, &'a str), BTreeMap<MetricMeta<'a>, MetricValue<'a>>>;
+                MetricValue::Summary(summary) => {
+                    for &(quantile, value) in &summary.quantiles {
+                        let label = Label::new("quantile", quantile.to_string());
+                        let all_labels = labels.clone().chain(iter::once(&label));
+                        write_metric_line(buffer, name, None, all_labels, value);
+                    }
 
-#[derive(Hash, PartialEq, Eq, PartialOrd, Ord)]
-struct MetricMeta<'a> {
-    actor_group: Option<&'a str>,
-    actor_key: Option<&'a str>,
-    key: &'a Key,
-}
+                    let (sum, count) = if known_counters.insert(fxhash::hash64(&meta)) {
+                        (0., 0)
+                    } else {
+                        (summary.sum, summary.count)
+                    };
 
-enum MetricValue<'a> {
-    Counter(u64),
-    Gauge(f64),
-    Distribution(&'a Distribution),
-}
+                    write_metric_line(buffer, name, Some("sum"), labels.clone(), sum);
+                    write_metric_line(buffer, name, Some("count"), labels.clone(), count);
 
-fn group_by_name(snapshot: &Snapshot) -> GroupedData<'_> {
-    let mut data: GroupedData<'_> = BTreeMap::new();
-
-    for (key, value, kind) in iter_metrics(&snapshot.global) {
-        data.entry((kind, key.name())).or_default().insert(
-            MetricMeta {
-                actor_group: None,
-                actor_key: None,
-                key,
-            },
-            value,
-        );
-    }
+                    if let Some(min) = summary.min {
+                        write_metric_line(buffer, name, Some("min"), labels.clone(), min);
+                    }
 
-    for (group, per_group) in &snapshot.per_group {
-        for (key, value, kind) in iter_metrics(per_group) {
-            data.entry((kind, key.name())).or_default().insert(
-                MetricMeta {
-                    actor_group: Some(group),
-                    actor_key: None,
-                    key,
-                },
-                value,
-            );
+                    if let Some(max) = summary.max {
+                        write_metric_line(buffer, name, Some("max"), labels.clone(), max);
+                    }
+                }
+            }
         }
-    }
 
-    for (actor_meta, per_actor) in &snapshot.per_actor {
-        for (key, value, kind) in iter_metrics(per_actor) {
-            data.entry((kind, key.name())).or_default().insert(
-                MetricMeta {
-                    actor_group: Some(&actor_meta.group),
-                    actor_key: Some(&actor_meta.key),
-                    key,
-                },
-                value,
-            );
-        }
+        buffer.push('\n');
     }
-
-    data
-}
-
-fn iter_metrics(metrics: &Metrics) -> impl Iterator<Item = (&Key, MetricValue<'_>, MetricKind)> {
-    let c = metrics
-        .counters
-        .iter()
-        .map(|(k, v)| (k, MetricValue::Counter(*v), MetricKind::Counter));
-    let g = metrics
-        .gauges
-        .iter()
-        .map(|(k, v)| (k, MetricValue::Gauge(*v), MetricKind::Gauge));
-    let d = metrics
-        .distributions
-        .iter()
-        .map(|(k, v)| (k, MetricValue::Distribution(v), MetricKind::Histogram));
-
-    c.chain(g).chain(d)
 }
 
 fn write_help_line(buffer: &mut String, name: &str, desc: &str) {
@@ -252,7 +222,7 @@ fn sanitize_label_key(key: &str) -> Cow<'_, str> {
 }
 
 fn sanitize_label_value(value: &str) -> Cow<'_, str> {
-    if value.contains(|c: char| c == '\\' || c == '"' || c == '\n') {
+    if value.contains(['\\', '"', '\n']) {
         value.into()
     } else {
         value
@@ -263,3 +233,80 @@ fn sanitize_label_value(value: &str) -> Cow<'_, str> {
             .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use fxhash::FxHashMap;
+    use metrics::Key;
+    use metrics_util::parse_quantiles;
+
+    use elfo_core::NodeNo;
+
+    use super::*;
+    use crate::{
+        protocol::{Distribution, Metrics},
+        render::report,
+    };
+
+    #[test]
+    fn it_renders_remote_metrics() {
+        let mut snapshot = Snapshot::default();
+        snapshot.global.counters.insert(Key::from_name("c"), 5);
+
+        let mut group = Metrics::default();
+        let mut distribution = Distribution::default();
+        distribution.record_samples(&[1., 2., 3.]);
+        group
+            .distributions
+            .insert(Key::from_name("d"), distribution);
+        snapshot.per_group.insert("group".into(), group);
+
+        let quantiles = parse_quantiles(&[0.5])
+            .into_iter()
+            .map(|q| {
+                let label = Label::new("quantile", q.value().to_string());
+                (q, label)
+            })
+            .collect::<Vec<_>>();
+        let descriptions = FxHashMap::default();
+        let remote_labels = [Label::new("env", "remote")];
+        let local_labels = [Label::new("env", "local")];
+
+        let options = |global_labels| RenderOptions {
+            quantiles: &quantiles,
+            descriptions: &descriptions,
+            global_labels,
+        };
+
+        // The same snapshot is reported by the remote node.
+        let metrics = report::render(&snapshot, options(&remote_labels));
+        let mut remotes = Remotes::default();
+        let node_no = NodeNo::from_bits(2).unwrap();
+        remotes.update(node_no, metrics, SystemTime::now());
+
+        let mut renderer = PrometheusRenderer::default();
+        let render = |renderer: &mut PrometheusRenderer| {
+            renderer.render(&snapshot, &remotes, options(&local_labels))
+        };
+
+        // Counters start from zero.
+        let output = render(&mut renderer);
+        assert!(output.contains("c{env=\"local\"} 0\n"));
+        assert!(output.contains("c{node_no=\"2\",env=\"remote\"} 0\n"));
+
+        let output = render(&mut renderer);
+        assert_eq!(output.matches("# TYPE c counter").count(), 1);
+        assert!(output.contains("c{env=\"local\"} 5\n"));
+        assert!(output.contains("c{node_no=\"2\",env=\"remote\"} 5\n"));
+
+        let remote = "node_no=\"2\",env=\"remote\",actor_group=\"group\"";
+        assert!(output.contains(&format!("d{{{remote},quantile=\"0.5\"}} 1\n")));
+        assert!(output.contains(&format!("d_sum{{{remote}}} 6\n")));
+        assert!(output.contains(&format!("d_count{{{remote}}} 3\n")));
+        assert!(output.contains(&format!("d_min{{{remote}}} 1\n")));
+        assert!(output.contains(&format!("d_max{{{remote}}} 3\n")));
+        assert!(output.contains("d_count{env=\"local\",actor_group=\"group\"} 3\n"));
+    }
+}
@@ -0,0 +1,54 @@
+//! Renders metrics into `ReportedMetric`s forwarded to the collector node.
+use super::{group_by_name, MetricValue, RenderOptions};
+use crate::protocol::{ReportedMetric, ReportedSummary, ReportedValue, Snapshot};
+
+pub(super) fn render(snapshot: &Snapshot, options: RenderOptions<'_>) -> Vec<ReportedMetric> {
+    let mut metrics = Vec::new();
+
+    for ((_, name), by_labels) in group_by_name(snapshot) {
+        for (meta, value) in by_labels {
+            let actor_group = meta.actor_group.map(|g| ("actor_group", g));
+            let actor_key = meta.actor_key.map(|k| ("actor_key", k));
+
+            let labels = options
+                .global_labels
+                .iter()
+                .map(|label| (label.key(), label.value()))
+                .chain(actor_group)
+                .chain(actor_key)
+                .chain(meta.key.labels().map(|label| (label.key(), label.value())))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+
+            let value = match value {
+                MetricValue::Counter(value) => ReportedValue::Counter(value),
+                MetricValue::Gauge(value) => ReportedValue::Gauge(value),
+                MetricValue::Distribution(distribution) => {
+                    ReportedValue::Summary(ReportedSummary {
+                        quantiles: options
+                            .quantiles
+                            .iter()
+                            .filter_map(|(q, _)| {
+                                let value = distribution.quantile(q.value())?;
+                                Some((q.value(), value))
+                            })
+                            .collect(),
+                        sum: distribution.sum(),
+                        count: distribution.count() as u64,
+                        min: distribution.min(),
+                        max: distribution.max(),
+                    })
+                }
+                MetricValue::Summary(summary) => ReportedValue::Summary(summary.clone()),
+            };
+
+            metrics.push(ReportedMetric {
+                name: name.to_string(),
+                labels,
+                value,
+            });
+        }
+    }
+
+    metrics
+}
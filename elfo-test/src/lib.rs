@@ -1,5 +1,5 @@
 #![warn(rust_2018_idioms, unreachable_pub)]
 
-pub use proxy::{proxy, Proxy};
+pub use proxy::{proxy, proxy_with_clock, Proxy};
 
 mod proxy;
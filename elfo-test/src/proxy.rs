@@ -20,14 +20,16 @@ use serde_value::Value;
 use tokio::task;
 
 use elfo_core::{
-    ActorGroup, ActorMeta, Addr, Blueprint, Context, Envelope, Local, Message, Request,
-    ResponseToken,
     _priv::do_start,
     errors::TrySendError,
+    journal::Journal,
     message, msg,
     routers::{MapRouter, Outcome},
     scope::Scope,
+    time::Clock,
     topology::Topology,
+    ActorGroup, ActorMeta, Addr, Blueprint, Context, Envelope, Local, Message, Request,
+    ResponseToken,
 };
 
 const SYNC_YIELD_COUNT: usize = 32;
@@ -35,6 +37,8 @@ const SYNC_YIELD_COUNT: usize = 32;
 pub struct Proxy {
     context: ProxyContext,
     scope: Scope,
+    clock: Clock,
+    journal: Journal,
     subject_addr: Addr,
     recv_timeout: Duration,
 }
@@ -170,7 +174,9 @@ impl Proxy {
         });
 
         Proxy {
-            scope: Scope::test(context.addr(), meta),
+            scope: Scope::test_with_clock(context.addr(), meta, self.clock.clone()),
+            clock: self.clock.clone(),
+            journal: self.journal.clone(),
             context,
             subject_addr: self.subject_addr,
             recv_timeout: self.recv_timeout,
@@ -183,6 +189,11 @@ impl Proxy {
         self.scope.clone().within(fut).await
     }
 
+    /// Returns the journal of the topology, see [`elfo_core::journal`].
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
     /// Closes a mailbox of the proxy.
     pub fn close(&self) {
         self.scope.clone().sync_within(|| self.context.close());
@@ -225,6 +236,16 @@ fn testers(tx: shared::OneshotSender<ProxyContext>) -> Blueprint {
 }
 
 pub async fn proxy(blueprint: Blueprint, config: impl for<'de> Deserializer<'de>) -> Proxy {
+    proxy_with_clock(blueprint, config, Clock::system()).await
+}
+
+/// Like [`proxy()`], but the topology and the proxy use the provided clock,
+/// e.g. a simulated one to control timers and TTLs in tests.
+pub async fn proxy_with_clock(
+    blueprint: Blueprint,
+    config: impl for<'de> Deserializer<'de>,
+    clock: Clock,
+) -> Proxy {
     let _ = tracing_subscriber::fmt()
         .with_target(false)
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -237,11 +258,13 @@ pub async fn proxy(blueprint: Blueprint, config: impl for<'de> Deserializer<'de>
     let config = Value::Map(map);
 
     let topology = Topology::empty();
+    topology.set_clock(clock.clone());
     let subject = topology.local("subject");
     let testers = topology.local("system.testers");
     let configurers = topology.local("system.configurers").entrypoint();
 
     let subject_addr = subject.addr();
+    let journal = topology.journal();
 
     testers.route_all_to(&subject);
     subject.route_all_to(&testers);
@@ -264,7 +287,9 @@ pub async fn proxy(blueprint: Blueprint, config: impl for<'de> Deserializer<'de>
     });
 
     Proxy {
-        scope: Scope::test(context.addr(), meta),
+        scope: Scope::test_with_clock(context.addr(), meta, clock.clone()),
+        clock,
+        journal,
         context,
         subject_addr,
         recv_timeout: Duration::from_millis(150),
@@ -0,0 +1,85 @@
+//! Conversion between WebSocket frames and messages.
+
+use serde::{Deserialize, Serialize};
+
+use elfo_core::_priv::AnyMessage;
+
+/// A data frame of the WebSocket protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frame {
+    /// A text frame.
+    Text(String),
+    /// A binary frame.
+    Binary(Vec<u8>),
+}
+
+/// Converts frames received from clients to messages and messages received by
+/// sessions to frames sent to clients.
+pub trait Codec: Send + Sync + 'static {
+    /// Converts a frame to a message, `Ok(None)` skips the frame.
+    fn decode(&self, frame: Frame) -> Result<Option<AnyMessage>, String>;
+
+    /// Converts a message to a frame, `Ok(None)` skips the message.
+    fn encode(&self, message: &AnyMessage) -> Result<Option<Frame>, String>;
+}
+
+/// Messages as `[protocol, name, body]` JSON arrays in text frames, e.g.
+/// `["ui", "Subscribe", { "topic": "orders" }]`. Binary frames are decoded
+/// the same way.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn decode(&self, frame: Frame) -> Result<Option<AnyMessage>, String> {
+        let result = match &frame {
+            Frame::Text(text) => serde_json::from_str(text),
+            Frame::Binary(bytes) => serde_json::from_slice(bytes),
+        };
+
+        result.map(Some).map_err(|err| err.to_string())
+    }
+
+    fn encode(&self, message: &AnyMessage) -> Result<Option<Frame>, String> {
+        serde_json::to_string(message)
+            .map(|text| Some(Frame::Text(text)))
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{message, Message};
+
+    use super::*;
+
+    #[message(protocol = "websocket-test")]
+    #[derive(PartialEq)]
+    struct Update {
+        topic: String,
+    }
+
+    #[test]
+    fn json_codec() {
+        let message = Update {
+            topic: "orders".into(),
+        };
+
+        let frame = JsonCodec
+            .encode(&message.clone().upcast())
+            .unwrap()
+            .unwrap();
+        let text = r#"["websocket-test","Update",{"topic":"orders"}]"#;
+        assert_eq!(frame, Frame::Text(text.into()));
+
+        let decoded = JsonCodec.decode(frame).unwrap().unwrap();
+        assert_eq!(decoded.downcast::<Update>().unwrap(), message);
+
+        let decoded = JsonCodec
+            .decode(Frame::Binary(text.into()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.downcast::<Update>().unwrap(), message);
+
+        assert!(JsonCodec.decode(Frame::Text("[]".into())).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    /// The address to listen for clients.
+    pub(crate) address: SocketAddr,
+    /// Protocols of messages accepted from clients, others are discarded.
+    pub(crate) protocols: Vec<String>,
+    /// The maximum time of the WebSocket handshake.
+    #[serde(with = "humantime_serde", default = "default_handshake_timeout")]
+    pub(crate) handshake_timeout: Duration,
+}
+
+fn default_handshake_timeout() -> Duration {
+    Duration::from_secs(10)
+}
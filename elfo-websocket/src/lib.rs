@@ -0,0 +1,92 @@
+//! Accepts WebSocket connections and spawns a session actor per connection.
+//!
+//! Frames received from the client are converted to messages by the provided
+//! [`Codec`] and sent by the session by routes of the bridge's group, e.g.
+//! `websockets.route_all_to(&pushers)`. Thus, recipients see the session as
+//! the sender and can reply or push updates directly to it. Messages received
+//! by the session are converted to frames and sent to the client.
+//!
+//! Requests received from the client are handled one by one, and responses
+//! are sent to the client as well. Messages of protocols not listed in the
+//! config are discarded, so clients cannot send arbitrary messages.
+//!
+//! The session is terminated once the connection is closed.
+//!
+//! ```toml
+//! [system.websockets]
+//! address = "0.0.0.0:9044"
+//! # Protocols of messages accepted from clients.
+//! protocols = ["ui"]
+//! # The maximum time of the WebSocket handshake, 10s by default.
+//! handshake_timeout = "5s"
+//! ```
+#![warn(rust_2018_idioms, unreachable_pub, missing_docs)]
+
+use std::{
+    fmt::{self, Display},
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use elfo_core::{
+    messages::UpdateConfig,
+    msg,
+    routers::{MapRouter, Outcome},
+    ActorGroup, Blueprint, Context, RestartPolicy,
+};
+
+use crate::{config::Config, protocol::OpenSession};
+
+pub mod codec;
+
+mod config;
+mod listener;
+mod protocol;
+mod session;
+
+pub use codec::{Codec, Frame, JsonCodec};
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+enum ActorKey {
+    Listener,
+    Session(u64),
+}
+
+impl Display for ActorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActorKey::Listener => f.write_str("listener"),
+            ActorKey::Session(id) => write!(f, "session:{id}"),
+        }
+    }
+}
+
+type BridgeContext = Context<Config, ActorKey>;
+
+/// Creates a blueprint of the bridge converting frames by the provided codec.
+pub fn new(codec: impl Codec) -> Blueprint {
+    let codec = Arc::new(codec) as Arc<dyn Codec>;
+    // Shared to keep ids unique if the listener is restarted.
+    let last_id = Arc::new(AtomicU64::new(0));
+
+    ActorGroup::new()
+        .config::<Config>()
+        // The restart policy is overrided by the listener.
+        .restart_policy(RestartPolicy::never())
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                UpdateConfig => Outcome::Unicast(ActorKey::Listener),
+                msg @ OpenSession => Outcome::Unicast(ActorKey::Session(msg.id)),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(move |ctx: BridgeContext| {
+            let codec = codec.clone();
+            let last_id = last_id.clone();
+            async move {
+                match ctx.key().clone() {
+                    ActorKey::Listener => listener::exec(ctx, last_id).await,
+                    ActorKey::Session(_) => session::exec(ctx, codec).await,
+                }
+            }
+        })
+}
@@ -0,0 +1,82 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info, warn};
+
+use elfo_core::{
+    message, messages::ConfigUpdated, msg, stream::Stream, MoveOwnership, RestartPolicy,
+    SourceHandle,
+};
+
+use crate::{protocol::OpenSession, BridgeContext};
+
+#[message]
+struct Accepted {
+    peer: SocketAddr,
+    stream: MoveOwnership<TcpStream>,
+}
+
+pub(crate) async fn exec(mut ctx: BridgeContext, last_id: Arc<AtomicU64>) {
+    // The default restart policy of this group is `never`, so override it.
+    ctx.set_restart_policy(RestartPolicy::on_failures());
+
+    let mut address = ctx.config().address;
+    let mut accepting = listen(&mut ctx).await;
+
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            ConfigUpdated => {
+                if ctx.config().address != address {
+                    info!("address changed, rebind the listener");
+                    accepting.terminate();
+                    address = ctx.config().address;
+                    accepting = listen(&mut ctx).await;
+                }
+            }
+            Accepted { peer, stream } => {
+                let id = last_id.fetch_add(1, Ordering::Relaxed) + 1;
+                let open = OpenSession { id, peer, stream };
+
+                if let Err(err) = ctx.send_to(ctx.group(), open).await {
+                    warn!(%peer, error = %err, "cannot open a session");
+                }
+            }
+        });
+    }
+}
+
+async fn listen(ctx: &mut BridgeContext) -> Stream {
+    let address = ctx.config().address;
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(%address, error = %err, "cannot bind the listener");
+            panic!("cannot bind the listener");
+        }
+    };
+
+    info!(%address, "listening for connections");
+
+    ctx.attach(Stream::generate(|mut e| async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let stream = stream.into();
+                    e.emit(Accepted { peer, stream }).await;
+                }
+                Err(err) => {
+                    warn!(error = %err, "cannot accept a connection");
+                    // Errors like `EMFILE` aren't resolved immediately.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }))
+}
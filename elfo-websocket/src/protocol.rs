@@ -0,0 +1,13 @@
+use std::net::SocketAddr;
+
+use tokio::net::TcpStream;
+
+use elfo_core::{message, MoveOwnership};
+
+/// Sent by the listener to spawn a session for the accepted connection.
+#[message]
+pub(crate) struct OpenSession {
+    pub(crate) id: u64,
+    pub(crate) peer: SocketAddr,
+    pub(crate) stream: MoveOwnership<TcpStream>,
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use futures::{stream::SplitSink, SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message as WsMessage, WebSocketStream};
+use tracing::{debug, info, warn};
+
+use elfo_core::{
+    _priv::AnyMessage,
+    message,
+    messages::{Ping, Terminate},
+    msg, registry,
+    stream::Stream,
+    Envelope, Message,
+};
+
+use crate::{
+    codec::{Codec, Frame},
+    protocol::OpenSession,
+    BridgeContext,
+};
+
+type Sink = SplitSink<WebSocketStream<TcpStream>, WsMessage>;
+
+#[message]
+struct Received(Frame);
+
+#[message]
+struct Disconnected;
+
+struct Session {
+    ctx: BridgeContext,
+    codec: Arc<dyn Codec>,
+    sink: Option<Sink>,
+}
+
+pub(crate) async fn exec(ctx: BridgeContext, codec: Arc<dyn Codec>) {
+    Session {
+        ctx,
+        codec,
+        sink: None,
+    }
+    .main()
+    .await
+}
+
+impl Session {
+    async fn main(mut self) {
+        let system_protocol = Ping::default().protocol();
+
+        while let Some(envelope) = self.ctx.recv().await {
+            let envelope = msg!(match envelope {
+                OpenSession { peer, stream, .. } => {
+                    if !self.open(stream.take().unwrap()).await {
+                        return;
+                    }
+
+                    info!(%peer, "session opened");
+                    continue;
+                }
+                Received(frame) => {
+                    self.on_frame(frame).await;
+                    continue;
+                }
+                Disconnected => {
+                    info!("session closed by the client");
+                    return;
+                }
+                Terminate => break,
+                envelope => envelope,
+            });
+
+            // System messages (e.g. `Ping`) aren't sent to the client.
+            if envelope.message().protocol() == system_protocol {
+                continue;
+            }
+
+            self.on_message(envelope).await;
+        }
+
+        if let Some(sink) = &mut self.sink {
+            let _ = sink.close().await;
+        }
+    }
+
+    async fn open(&mut self, stream: TcpStream) -> bool {
+        let timeout = self.ctx.config().handshake_timeout;
+        let handshake = tokio_tungstenite::accept_async(stream);
+
+        let ws_stream = match tokio::time::timeout(timeout, handshake).await {
+            Ok(Ok(ws_stream)) => ws_stream,
+            Ok(Err(err)) => {
+                debug!(error = %err, "handshake failed");
+                return false;
+            }
+            Err(_) => {
+                debug!(?timeout, "handshake timed out");
+                return false;
+            }
+        };
+
+        let (sink, stream) = ws_stream.split();
+        self.sink = Some(sink);
+
+        // Control frames are handled by `tungstenite` itself.
+        let frames = stream
+            .take_while(|result| {
+                if let Err(err) = result {
+                    debug!(error = %err, "cannot read a frame");
+                }
+
+                let is_closed = matches!(result, Err(_) | Ok(WsMessage::Close(_)));
+                async move { !is_closed }
+            })
+            .filter_map(|result| async move {
+                match result.ok()? {
+                    WsMessage::Text(text) => Some(Received(Frame::Text(text))),
+                    WsMessage::Binary(bytes) => Some(Received(Frame::Binary(bytes))),
+                    _ => None,
+                }
+            })
+            .map(|received| received.upcast())
+            .chain(futures::stream::once(async { Disconnected.upcast() }));
+
+        self.ctx.attach(Stream::from_futures03(frames));
+        true
+    }
+
+    async fn on_frame(&mut self, frame: Frame) {
+        let message = match self.codec.decode(frame) {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(err) => {
+                debug!(error = %err, "cannot decode the frame");
+                return;
+            }
+        };
+
+        let protocol = message.protocol();
+        if !self.ctx.config().protocols.iter().any(|p| p == protocol) {
+            debug!(%protocol, name = message.name(), "forbidden protocol, discarded");
+            return;
+        }
+
+        let is_request = registry::lookup(protocol, message.name()).is_some_and(|i| i.is_request);
+
+        if !is_request {
+            if let Err(err) = self.ctx.send(message).await {
+                debug!(error = %err, "cannot send the message");
+            }
+            return;
+        }
+
+        match self.ctx.request(message).resolve().await {
+            Ok(response) => {
+                let ws_message = self.encode(&response);
+                self.write(ws_message).await;
+            }
+            Err(err) => debug!(error = %err, "request failed"),
+        }
+    }
+
+    async fn on_message(&mut self, envelope: Envelope) {
+        // Requests to the session are ignored, only their messages are sent.
+        let ws_message = self.encode(envelope.message());
+        drop(envelope);
+        self.write(ws_message).await;
+    }
+
+    fn encode(&self, message: &AnyMessage) -> Option<WsMessage> {
+        let frame = match self.codec.encode(message) {
+            Ok(frame) => frame?,
+            Err(err) => {
+                warn!(name = message.name(), error = %err, "cannot encode the message");
+                return None;
+            }
+        };
+
+        Some(match frame {
+            Frame::Text(text) => WsMessage::Text(text),
+            Frame::Binary(bytes) => WsMessage::Binary(bytes),
+        })
+    }
+
+    async fn write(&mut self, ws_message: Option<WsMessage>) {
+        let (Some(sink), Some(ws_message)) = (&mut self.sink, ws_message) else {
+            return;
+        };
+
+        // Failures are detected by the reading side.
+        if let Err(err) = sink.send(ws_message).await {
+            debug!(error = %err, "cannot write a frame");
+        }
+    }
+}
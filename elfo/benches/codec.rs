@@ -0,0 +1,54 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+
+use elfo::bench_support::{
+    codec::{decode_all, encode_regular},
+    Payload,
+};
+
+const PAYLOAD_SIZES: [usize; 4] = [0, 64, 1024, 65536];
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("codec/encode");
+
+    for size in PAYLOAD_SIZES {
+        let message = Payload::new(0, size);
+        let mut buffer = Vec::new();
+
+        group.throughput(Throughput::Bytes(
+            encode_regular(message.clone(), &mut buffer) as u64,
+        ));
+        group.bench_with_input(BenchmarkId::new("payload_size", size), &message, |b, m| {
+            b.iter_batched(
+                || m.clone(),
+                |message| {
+                    buffer.clear();
+                    black_box(encode_regular(message, &mut buffer))
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("codec/decode");
+
+    for size in PAYLOAD_SIZES {
+        let mut buffer = Vec::new();
+        encode_regular(Payload::new(0, size), &mut buffer);
+
+        group.throughput(Throughput::Bytes(buffer.len() as u64));
+        group.bench_with_input(BenchmarkId::new("payload_size", size), &buffer, |b, buf| {
+            b.iter(|| black_box(decode_all(buf)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,179 @@
+//! Compares the frame writer without buffer reusing (`pool_size = 0`, as it
+//! was before the pool) and with the default pool, by time and by the number
+//! of heap allocations per message.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    BenchmarkId, Criterion, Throughput,
+};
+use tokio::runtime::{Builder, Runtime};
+
+use elfo::bench_support::{codec::write_frames, Payload};
+
+const POOL_SIZES: [usize; 2] = [0, 8];
+const PAYLOAD_SIZES: [usize; 3] = [64, 1024, 65536];
+
+// === Allocations ===
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Measures the number of allocations (including reallocations).
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATIONS.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        ALLOCATIONS.load(Ordering::SeqCst) - i
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationsFormatter
+    }
+}
+
+struct AllocationsFormatter;
+
+impl ValueFormatter for AllocationsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (count, unit) = match *throughput {
+            Throughput::Bytes(n) | Throughput::BytesDecimal(n) => (n, "allocs/B"),
+            Throughput::Elements(n) => (n, "allocs/msg"),
+        };
+
+        for value in values {
+            *value /= count as f64;
+        }
+
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+// === Benches ===
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_all().build().unwrap()
+}
+
+fn run(rt: &Runtime, pool_size: usize, payload_size: usize, count: u64) {
+    let message = Payload::new(0, payload_size);
+    let received = rt.block_on(write_frames(message, count as usize, pool_size));
+    assert_ne!(received, 0);
+}
+
+fn time(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("frame_writer/time");
+    group.throughput(Throughput::Elements(1));
+
+    for payload_size in PAYLOAD_SIZES {
+        for pool_size in POOL_SIZES {
+            group.bench_with_input(
+                BenchmarkId::new(format!("pool_size={pool_size}"), payload_size),
+                &payload_size,
+                |b, &payload_size| {
+                    b.iter_custom(|count| {
+                        let start = Instant::now();
+                        run(&rt, pool_size, payload_size, count);
+                        start.elapsed()
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn allocations(c: &mut Criterion<Allocations>) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("frame_writer/allocations");
+    group.throughput(Throughput::Elements(1));
+
+    for payload_size in PAYLOAD_SIZES {
+        for pool_size in POOL_SIZES {
+            group.bench_with_input(
+                BenchmarkId::new(format!("pool_size={pool_size}"), payload_size),
+                &payload_size,
+                |b, &payload_size| {
+                    b.iter_custom(|count| {
+                        let start = Allocations.start();
+                        run(&rt, pool_size, payload_size, count);
+                        Allocations.end(start)
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(time_benches, time);
+criterion_group! {
+    name = allocation_benches;
+    config = Criterion::default()
+        .with_measurement(Allocations)
+        .warm_up_time(Duration::from_millis(500))
+        .without_plots();
+    targets = allocations
+}
+criterion_main!(time_benches, allocation_benches);
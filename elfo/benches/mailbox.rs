@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+use elfo::bench_support::MailboxBench;
+
+fn run(bench: &MailboxBench, message_count: u64) -> Duration {
+    let rt = Runtime::new().unwrap();
+    let elapsed = rt.block_on(bench.run(message_count));
+    rt.shutdown_timeout(Duration::from_secs(10));
+    elapsed
+}
+
+fn one_to_one(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mailbox/one_to_one");
+    group.throughput(Throughput::Elements(1));
+
+    for payload_size in [0, 64, 1024] {
+        let bench = MailboxBench::new().payload_size(payload_size);
+        group.bench_with_input(
+            BenchmarkId::new("payload_size", payload_size),
+            &bench,
+            |b, bench| b.iter_custom(|iter_count| run(bench, iter_count)),
+        );
+    }
+
+    group.finish();
+}
+
+fn many_to_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mailbox/many_to_many");
+    group.throughput(Throughput::Elements(1));
+
+    for n in [2, 4, 8] {
+        let bench = MailboxBench::new().producers(n).consumers(n);
+        group.bench_with_input(BenchmarkId::new("actors", n), &bench, |b, bench| {
+            b.iter_custom(|iter_count| run(bench, iter_count))
+        });
+    }
+
+    group.finish();
+}
+
+fn batched(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mailbox/batched");
+    group.throughput(Throughput::Elements(1));
+
+    for batch_size in [1, 16, 256] {
+        let bench = MailboxBench::new().producers(4).batch_size(batch_size);
+        group.bench_with_input(
+            BenchmarkId::new("batch_size", batch_size),
+            &bench,
+            |b, bench| b.iter_custom(|iter_count| run(bench, iter_count)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, one_to_one, many_to_many, batched);
+criterion_main!(benches);
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::future::join_all;
+use tokio::runtime::Runtime;
+
+use elfo::{config::AnyConfig, messages::Terminate, prelude::*, topology::Topology, ResponseToken};
+
+#[message(ret = u64)]
+struct Hold(u64);
+
+/// Holds tokens until `outstanding` requests are received, then responds
+/// to all of them at once, so the requester has `outstanding` pending
+/// requests at the peak.
+fn make_responders(outstanding: usize) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        let mut tokens: Vec<(u64, ResponseToken<Hold>)> = Vec::with_capacity(outstanding);
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Hold(value), token) => {
+                    tokens.push((value, token));
+
+                    if tokens.len() == outstanding {
+                        for (value, token) in tokens.drain(..) {
+                            ctx.respond(token, value);
+                        }
+                    }
+                }
+            });
+        }
+    })
+}
+
+async fn run(outstanding: usize, iter_count: u64) -> Duration {
+    let topology = Topology::empty();
+    let requesters = topology.local("requesters");
+    let responders = topology.local("responders");
+    let configurers = topology.local("system.configurers").entrypoint();
+
+    requesters.route_all_to(&responders);
+
+    let responders_addr = responders.addr();
+
+    responders.mount(make_responders(outstanding));
+    configurers.mount(elfo::batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+
+    elfo::_priv::do_start(topology, false, move |ctx, _| async move {
+        let start_at = Instant::now();
+
+        for _ in 0..iter_count {
+            let requests = (0..outstanding as u64)
+                .map(|value| ctx.request_to(responders_addr, Hold(value)).resolve());
+
+            for response in join_all(requests).await {
+                response.unwrap();
+            }
+        }
+
+        let elapsed = start_at.elapsed();
+
+        ctx.try_send_to(responders_addr, Terminate::closing())
+            .unwrap();
+        ctx.finished(responders_addr).await;
+
+        elapsed
+    })
+    .await
+    .unwrap()
+}
+
+fn outstanding_requests(c: &mut Criterion) {
+    let mut group = c.benchmark_group("requests/outstanding");
+    group.sample_size(10);
+
+    for outstanding in [1_000, 10_000, 100_000] {
+        group.throughput(Throughput::Elements(outstanding as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(outstanding),
+            &outstanding,
+            |b, &outstanding| {
+                b.iter_custom(|iter_count| {
+                    let rt = Runtime::new().unwrap();
+                    let elapsed = rt.block_on(run(outstanding, iter_count));
+                    rt.shutdown_timeout(Duration::from_secs(10));
+                    elapsed
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, outstanding_requests);
+criterion_main!(benches);
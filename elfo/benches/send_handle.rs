@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+use elfo::bench_support::{MailboxBench, SendMode};
+
+fn run(bench: &MailboxBench, message_count: u64) -> Duration {
+    let rt = Runtime::new().unwrap();
+    let elapsed = rt.block_on(bench.run(message_count));
+    rt.shutdown_timeout(Duration::from_secs(10));
+    elapsed
+}
+
+fn send_modes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send_handle/one_to_many");
+    group.throughput(Throughput::Elements(1));
+
+    let modes = [
+        ("routing", SendMode::Routing),
+        ("send_to", SendMode::SendTo),
+        ("send_handle", SendMode::SendHandle),
+    ];
+
+    for (name, mode) in modes {
+        let bench = MailboxBench::new().consumers(4).send_mode(mode);
+        group.bench_with_input(BenchmarkId::new("mode", name), &bench, |b, bench| {
+            b.iter_custom(|iter_count| run(bench, iter_count))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, send_modes);
+criterion_main!(benches);
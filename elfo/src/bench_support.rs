@@ -0,0 +1,315 @@
+//! Building blocks for benchmarks: synthetic messages and ready-to-run
+//! topologies. Used by benches of this repository, but also intended for
+//! downstream crates to measure regressions on their own hardware.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use elfo_core::{
+    _priv::do_start,
+    config::AnyConfig,
+    message,
+    messages::{Terminate, UpdateConfig},
+    msg,
+    routers::{MapRouter, Outcome},
+    ActorGroup, Addr, Blueprint, Local, ResponseToken, Topology,
+};
+
+// === Messages ===
+
+/// A synthetic message with a payload of the configurable size.
+#[message(elfo = elfo_core)]
+pub struct Payload {
+    /// A sequence number, used for routing.
+    pub seq: u64,
+    /// Some opaque data.
+    pub data: Vec<u8>,
+}
+
+impl Payload {
+    /// Creates a new message with `size` bytes of data.
+    pub fn new(seq: u64, size: usize) -> Self {
+        Self {
+            seq,
+            data: vec![0xE1; size],
+        }
+    }
+}
+
+/// Returns an infinite iterator over messages with `size` bytes of data
+/// and increasing sequence numbers.
+pub fn payloads(size: usize) -> impl Iterator<Item = Payload> {
+    (0..).map(move |seq| Payload::new(seq, size))
+}
+
+#[message(elfo = elfo_core, ret = ())]
+struct WaitConsumed;
+
+#[message(elfo = elfo_core, ret = (u64, Local<Addr>))]
+struct GetConsumerAddr;
+
+// === MailboxBench ===
+
+/// How producers of [`MailboxBench`] send messages to consumers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SendMode {
+    /// By the routing system, see `Context::send()`.
+    #[default]
+    Routing,
+    /// By addresses of consumers, see `Context::send_to()`.
+    SendTo,
+    /// By handles to consumers, see `Context::send_handle()`.
+    SendHandle,
+}
+
+/// Producers sending [`Payload`]s to consumers, distributed by `seq`.
+///
+/// Measures the time between the first sent message and the moment when all
+/// messages are handled by consumers.
+///
+/// # Example
+/// ```ignore
+/// b.iter_custom(|iter_count| {
+///     let rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(MailboxBench::new().producers(4).run(iter_count))
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct MailboxBench {
+    producers: u64,
+    consumers: u64,
+    payload_size: usize,
+    batch_size: usize,
+    send_mode: SendMode,
+}
+
+impl Default for MailboxBench {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MailboxBench {
+    /// Creates a new benchmark with one producer, one consumer and empty
+    /// payloads.
+    pub fn new() -> Self {
+        Self {
+            producers: 1,
+            consumers: 1,
+            payload_size: 0,
+            batch_size: 1,
+            send_mode: SendMode::Routing,
+        }
+    }
+
+    /// Sets the number of producers.
+    ///
+    /// # Panics
+    /// If `count` is zero.
+    pub fn producers(mut self, count: u64) -> Self {
+        assert_ne!(count, 0, "at least one producer is required");
+        self.producers = count;
+        self
+    }
+
+    /// Sets the number of consumers.
+    ///
+    /// # Panics
+    /// If `count` is zero.
+    pub fn consumers(mut self, count: u64) -> Self {
+        assert_ne!(count, 0, "at least one consumer is required");
+        self.consumers = count;
+        self
+    }
+
+    /// Sets the size of data in every sent message.
+    pub fn payload_size(mut self, size: usize) -> Self {
+        self.payload_size = size;
+        self
+    }
+
+    /// Sets the maximum number of messages received by consumers at once,
+    /// see `Context::recv_many()`. `1` means using `Context::recv()`.
+    ///
+    /// # Panics
+    /// If `size` is zero.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        assert_ne!(size, 0, "batch size must be positive");
+        self.batch_size = size;
+        self
+    }
+
+    /// Sets how producers send messages, see [`SendMode`].
+    pub fn send_mode(mut self, mode: SendMode) -> Self {
+        self.send_mode = mode;
+        self
+    }
+
+    /// Starts a new system, sends `message_count` messages in total and
+    /// returns the elapsed time. The system is terminated at the end.
+    ///
+    /// Must be called inside the tokio runtime.
+    pub async fn run(&self, message_count: u64) -> Duration {
+        let started_at = Arc::new(Mutex::new(None));
+
+        let topology = Topology::empty();
+        let producers = topology.local("producers");
+        let consumers = topology.local("consumers");
+        let configurers = topology.local("system.configurers").entrypoint();
+
+        producers.route_all_to(&consumers);
+
+        let producers_addr = producers.addr();
+        let consumers_addr = consumers.addr();
+
+        producers.mount(self.make_producers(message_count, started_at.clone()));
+        consumers.mount(self.make_consumers(message_count));
+        configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+
+        do_start(topology, false, |ctx, _| async move {
+            for result in ctx
+                .request_to(consumers_addr, WaitConsumed)
+                .all()
+                .resolve()
+                .await
+            {
+                result.expect("consumer has failed");
+            }
+
+            let started_at = started_at.lock().unwrap().expect("no messages are sent");
+            let elapsed = started_at.elapsed();
+
+            let _ = ctx.try_send_to(producers_addr, Terminate::closing());
+            let _ = ctx.try_send_to(consumers_addr, Terminate::closing());
+            ctx.finished(producers_addr).await;
+            ctx.finished(consumers_addr).await;
+
+            elapsed
+        })
+        .await
+        .expect("cannot start")
+    }
+
+    fn make_producers(&self, message_count: u64, started_at: SharedInstant) -> Blueprint {
+        let producer_count = self.producers;
+        let consumer_count = self.consumers;
+        let payload_size = self.payload_size;
+        let send_mode = self.send_mode;
+
+        ActorGroup::new()
+            .router(MapRouter::new(move |envelope| {
+                msg!(match envelope {
+                    UpdateConfig => Outcome::Multicast((0..producer_count).collect()),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(move |mut ctx| {
+                let started_at = started_at.clone();
+                async move {
+                    // Addresses are resolved before measuring, ordered by keys of consumers.
+                    let addrs = match send_mode {
+                        SendMode::Routing => Vec::new(),
+                        SendMode::SendTo | SendMode::SendHandle => {
+                            let mut addrs = ctx
+                                .request(GetConsumerAddr)
+                                .all()
+                                .resolve()
+                                .await
+                                .into_iter()
+                                .map(|res| res.expect("consumer has failed"))
+                                .collect::<Vec<_>>();
+                            addrs.sort_unstable_by_key(|(key, _)| *key);
+                            addrs.into_iter().map(|(_, addr)| *addr).collect()
+                        }
+                    };
+                    let mut handles = addrs
+                        .iter()
+                        .map(|addr| ctx.send_handle(*addr))
+                        .collect::<Vec<_>>();
+
+                    started_at.lock().unwrap().get_or_insert_with(Instant::now);
+
+                    let first = *ctx.key();
+                    for seq in (first..message_count).step_by(producer_count as usize) {
+                        let message = Payload::new(seq, payload_size);
+                        let no = (seq % consumer_count) as usize;
+                        let result = match send_mode {
+                            SendMode::Routing => ctx.send(message).await,
+                            SendMode::SendTo => ctx.send_to(addrs[no], message).await,
+                            SendMode::SendHandle => handles[no].send(&ctx, message).await,
+                        };
+                        result.expect("cannot send");
+                    }
+
+                    while ctx.recv().await.is_some() {}
+                }
+            })
+    }
+
+    fn make_consumers(&self, message_count: u64) -> Blueprint {
+        let consumer_count = self.consumers;
+        let batch_size = self.batch_size;
+
+        ActorGroup::new()
+            .router(MapRouter::new(move |envelope| {
+                msg!(match envelope {
+                    UpdateConfig | WaitConsumed | GetConsumerAddr => {
+                        Outcome::Multicast((0..consumer_count).collect())
+                    }
+                    Payload { seq, .. } => Outcome::Unicast(seq % consumer_count),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(move |mut ctx| async move {
+                let key = *ctx.key();
+                let mut remaining = message_count.saturating_sub(key).div_ceil(consumer_count);
+                let mut waiter: Option<ResponseToken<WaitConsumed>> = None;
+                let mut batch = Vec::with_capacity(batch_size);
+
+                loop {
+                    if batch_size == 1 {
+                        batch.extend(ctx.recv().await);
+                    } else {
+                        ctx.recv_many(&mut batch, batch_size).await;
+                    }
+
+                    if batch.is_empty() {
+                        break;
+                    }
+
+                    for envelope in batch.drain(..) {
+                        msg!(match envelope {
+                            Payload => {
+                                remaining -= 1;
+                                if remaining == 0 {
+                                    if let Some(token) = waiter.take() {
+                                        ctx.respond(token, ());
+                                    }
+                                }
+                            }
+                            (GetConsumerAddr, token) => {
+                                ctx.respond(token, (key, ctx.addr().into()));
+                            }
+                            (WaitConsumed, token) => {
+                                if remaining == 0 {
+                                    ctx.respond(token, ());
+                                } else {
+                                    waiter = Some(token);
+                                }
+                            }
+                        });
+                    }
+                }
+            })
+    }
+}
+
+type SharedInstant = Arc<Mutex<Option<Instant>>>;
+
+/// Helpers to measure the internode codec.
+#[cfg(feature = "network")]
+pub mod codec {
+    pub use elfo_network::bench_support::{decode_all, encode_regular, write_frames};
+}
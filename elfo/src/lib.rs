@@ -12,6 +12,10 @@ pub use elfo_macros::{message, msg};
 #[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
 pub use elfo_test as test;
 
+#[cfg(feature = "bench-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench-support")))]
+pub mod bench_support;
+
 /// A set of actors for common tasks.
 pub mod batteries {
     #[cfg(feature = "elfo-configurer")]
@@ -20,6 +24,12 @@ pub mod batteries {
     #[cfg(feature = "elfo-dumper")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     pub use elfo_dumper as dumper;
+    #[cfg(feature = "elfo-gateway")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+    pub use elfo_gateway as gateway;
+    #[cfg(feature = "elfo-kafka")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "kafka")))]
+    pub use elfo_kafka as kafka;
     #[cfg(feature = "elfo-logger")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     pub use elfo_logger as logger;
@@ -29,9 +39,21 @@ pub mod batteries {
     #[cfg(feature = "elfo-pinger")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     pub use elfo_pinger as pinger;
+    #[cfg(feature = "elfo-postgres")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+    pub use elfo_postgres as postgres;
+    #[cfg(feature = "elfo-scheduler")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+    pub use elfo_scheduler as scheduler;
+    #[cfg(feature = "elfo-switchboard")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+    pub use elfo_switchboard as switchboard;
     #[cfg(feature = "elfo-telemeter")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     pub use elfo_telemeter as telemeter;
+    #[cfg(feature = "elfo-websocket")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+    pub use elfo_websocket as websocket;
 }
 
 /// Things that are useful to have included when writing actors.
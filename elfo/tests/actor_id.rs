@@ -0,0 +1,99 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    ActorId,
+};
+
+#[message(ret = ActorId)]
+struct GetId(u32);
+
+#[message(ret = bool)]
+struct Call {
+    key: u32,
+    callback: ActorId,
+}
+
+#[message]
+struct Ping;
+
+#[message]
+struct Stop(u32);
+
+fn blueprint(pings: &'static AtomicU32) -> Blueprint {
+    ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                GetId(key) => Outcome::Unicast(*key),
+                Call { key, .. } => Outcome::Unicast(*key),
+                Stop(key) => Outcome::Unicast(*key),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(move |mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (GetId(_), token) => ctx.respond(token, ctx.actor_id()),
+                    (Call { callback, .. }, token) => {
+                        let handle = ctx.resolve_actor_id(callback);
+                        let called = match handle {
+                            Some(mut handle) => handle.send(&ctx, Ping).await.is_ok(),
+                            None => false,
+                        };
+                        ctx.respond(token, called);
+                    }
+                    Ping => {
+                        pings.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Stop(_) => break,
+                });
+            }
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_resolves_only_alive_incarnations() {
+    static PINGS: AtomicU32 = AtomicU32::new(0);
+
+    let proxy = elfo::test::proxy(blueprint(&PINGS), AnyConfig::default()).await;
+
+    let callback = proxy.request(GetId(0)).await;
+    assert_eq!(
+        callback.key_hash(),
+        proxy.request(GetId(0)).await.key_hash()
+    );
+    assert_ne!(
+        callback.key_hash(),
+        proxy.request(GetId(1)).await.key_hash()
+    );
+
+    assert!(proxy.request(Call { key: 1, callback }).await);
+    proxy.request(GetId(0)).await; // the ping is handled before
+    assert_eq!(PINGS.load(Ordering::SeqCst), 1);
+
+    // The next incarnation has another id.
+    proxy.send(Stop(0)).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let new_callback = proxy.request(GetId(0)).await;
+    assert_ne!(callback, new_callback);
+    assert_eq!(callback.key_hash(), new_callback.key_hash());
+
+    assert!(!proxy.request(Call { key: 1, callback }).await);
+    assert!(
+        proxy
+            .request(Call {
+                key: 1,
+                callback: new_callback
+            })
+            .await
+    );
+    proxy.request(GetId(0)).await;
+    assert_eq!(PINGS.load(Ordering::SeqCst), 2);
+}
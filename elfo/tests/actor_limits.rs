@@ -0,0 +1,158 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    AdmissionPolicy,
+};
+
+#[message]
+struct Started(u32);
+
+#[message]
+struct Hello(u32);
+
+#[message(ret = u32)]
+struct Ask(u32);
+
+#[message]
+struct Stop(u32);
+
+fn blueprint(max_actors: usize, policy: AdmissionPolicy) -> Blueprint {
+    ActorGroup::new()
+        .max_actors(max_actors)
+        .admission_policy(policy)
+        .router(MapRouter::new(|e| {
+            msg!(match e {
+                Hello(no) | Stop(no) => Outcome::Unicast(*no),
+                Ask(no) => Outcome::Unicast(*no),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let _ = ctx.send(Started(*ctx.key())).await;
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Ask(no), token) => ctx.respond(token, no),
+                    Stop => break,
+                    _ => {}
+                });
+            }
+        })
+}
+
+#[tokio::test]
+async fn reject() {
+    let mut proxy = elfo::test::proxy(
+        blueprint(2, AdmissionPolicy::reject()),
+        AnyConfig::default(),
+    )
+    .await;
+
+    proxy.send(Hello(1)).await;
+    proxy.send(Hello(2)).await;
+    assert_msg!(proxy.recv().await, Started(1));
+    assert_msg!(proxy.recv().await, Started(2));
+
+    // The limit is reached.
+    assert!(proxy.try_send(Hello(3)).is_err());
+    assert_eq!(proxy.request(Ask(1)).await, 1);
+
+    // The slot is released once the actor is terminated.
+    proxy.send(Stop(1)).await;
+    proxy.sync().await;
+    proxy.send(Hello(3)).await;
+    assert_msg!(proxy.recv().await, Started(3));
+}
+
+#[tokio::test]
+async fn queue() {
+    let mut proxy = elfo::test::proxy(
+        blueprint(1, AdmissionPolicy::queue(1)),
+        AnyConfig::default(),
+    )
+    .await;
+
+    proxy.send(Hello(1)).await;
+    assert_msg!(proxy.recv().await, Started(1));
+
+    // Queued until the first actor is terminated.
+    assert!(proxy.try_send(Hello(2)).is_ok());
+    // The queue is full.
+    assert!(proxy.try_send(Hello(3)).is_err());
+    proxy.sync().await;
+    assert!(proxy.try_recv().await.is_none());
+
+    proxy.send(Stop(1)).await;
+    assert_msg!(proxy.recv().await, Started(2));
+}
+
+#[tokio::test]
+async fn evict_least_recently_active() {
+    let policy = AdmissionPolicy::evict_least_recently_active();
+    let mut proxy = elfo::test::proxy(blueprint(2, policy), AnyConfig::default()).await;
+
+    proxy.send(Hello(1)).await;
+    proxy.send(Hello(2)).await;
+    assert_msg!(proxy.recv().await, Started(1));
+    assert_msg!(proxy.recv().await, Started(2));
+
+    // Now the second actor is the least recently active one.
+    assert_eq!(proxy.request(Ask(1)).await, 1);
+
+    proxy.send(Hello(3)).await;
+    assert_msg!(proxy.recv().await, Started(3));
+
+    assert_eq!(proxy.request(Ask(1)).await, 1);
+    assert_eq!(proxy.request(Ask(3)).await, 3);
+}
+
+#[tokio::test]
+async fn target() {
+    #[message]
+    #[derive(PartialEq)]
+    struct Finished(u32);
+
+    let blueprint = ActorGroup::new()
+        .target_actors(2)
+        .router(MapRouter::new(|e| {
+            msg!(match e {
+                Hello(no) => Outcome::Unicast(*no),
+                Ask(no) => Outcome::Unicast(*no),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let no = *ctx.key();
+            let _ = ctx.send(Started(no)).await;
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Ask(no), token) => ctx.respond(token, no),
+                    _ => {}
+                });
+            }
+
+            let _ = ctx.send(Finished(no)).await;
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    proxy.send(Hello(1)).await;
+    proxy.send(Hello(2)).await;
+    assert_msg!(proxy.recv().await, Started(1));
+    assert_msg!(proxy.recv().await, Started(2));
+
+    // Now the second actor is the least recently active one.
+    assert_eq!(proxy.request(Ask(1)).await, 1);
+
+    // The new actor is spawned, then the second one is evicted.
+    proxy.send(Hello(3)).await;
+    assert_msg!(proxy.recv().await, Started(3));
+    assert_msg_eq!(proxy.recv().await, Finished(2));
+
+    assert_eq!(proxy.request(Ask(1)).await, 1);
+    assert_eq!(proxy.request(Ask(3)).await, 3);
+}
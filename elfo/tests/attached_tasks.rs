@@ -0,0 +1,100 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    future,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use elfo::{config::AnyConfig, prelude::*, RestartPolicy};
+
+#[message]
+struct Fetch;
+
+#[message]
+struct Fetched(u32);
+
+#[message]
+struct Started;
+
+#[tokio::test]
+async fn output_is_sent_to_actor() {
+    let blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Fetch => ctx.attach_task(async { Fetched(42) }),
+                msg @ Fetched(_) => ctx.send(msg).await.unwrap(),
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    proxy.send(Fetch).await;
+    assert_msg!(proxy.recv().await, Fetched(42));
+}
+
+#[tokio::test(start_paused = true)]
+async fn panic_fails_actor() {
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+    let blueprint = ActorGroup::new()
+        .restart_policy(RestartPolicy::on_failures())
+        .exec(move |mut ctx| async move {
+            if STARTED.fetch_add(1, Ordering::SeqCst) == 0 {
+                ctx.attach_task(async { panic!("boom!") as () });
+            }
+
+            ctx.send(Started).await.unwrap();
+            while ctx.recv().await.is_some() {}
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    assert_msg!(proxy.recv().await, Started);
+
+    // https://github.com/tokio-rs/tokio/issues/3985
+    tokio::time::sleep(Duration::from_millis(5001)).await;
+
+    assert_msg!(proxy.recv().await, Started);
+    assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn aborted_on_termination() {
+    static ALIVE: AtomicUsize = AtomicUsize::new(0);
+
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            ALIVE.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    let blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        ALIVE.fetch_add(1, Ordering::SeqCst);
+        let guard = Guard;
+        ctx.attach_task(async move {
+            let _guard = guard;
+            future::pending::<()>().await
+        });
+
+        ctx.send(Started).await.unwrap();
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Fetch => break,
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    assert_msg!(proxy.recv().await, Started);
+    assert_eq!(ALIVE.load(Ordering::SeqCst), 1);
+
+    proxy.send(Fetch).await;
+    proxy.sync().await;
+    tokio::task::yield_now().await;
+    assert_eq!(ALIVE.load(Ordering::SeqCst), 0);
+}
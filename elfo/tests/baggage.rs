@@ -0,0 +1,86 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::Baggage};
+
+#[message]
+struct Start;
+
+#[message]
+struct Inner;
+
+#[message]
+struct Check;
+
+#[message]
+struct Seen(Option<String>);
+
+#[message(ret = ())]
+struct Ask;
+
+fn tenant() -> Option<String> {
+    scope::baggage().get("tenant").map(String::from)
+}
+
+#[tokio::test]
+async fn it_restores_baggage() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Start => {
+                    scope::set_baggage(Baggage::default().with("tenant", "acme"));
+                    ctx.send_to(ctx.addr(), Inner).await.unwrap();
+                    scope::set_baggage(Baggage::default());
+                }
+                Inner | Check => ctx.send(Seen(tenant())).await.unwrap(),
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    // The baggage is captured when sent and restored by the recipient.
+    proxy.send(Start).await;
+    let envelope = proxy.recv().await;
+    assert_eq!(envelope.baggage().get("tenant"), Some("acme"));
+    assert_msg!(envelope, Seen(Some(_)));
+
+    // The baggage isn't leaked to other messages.
+    proxy.send(Check).await;
+    let envelope = proxy.recv().await;
+    assert!(envelope.baggage().is_empty());
+    assert_msg!(envelope, Seen(None));
+}
+
+#[tokio::test]
+async fn it_propagates_baggage_with_requests() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let mut worker = None;
+
+        while let Some(envelope) = ctx.recv().await {
+            let sender = envelope.sender();
+
+            msg!(match envelope {
+                Check => worker = Some(sender),
+                Start => {
+                    scope::set_baggage(Baggage::default().with("tenant", "acme"));
+                    let worker = worker.expect("no registered worker");
+                    ctx.request_to(worker, Ask).resolve().await.unwrap();
+                    ctx.send(Seen(tenant())).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let mut worker = proxy.subproxy().await;
+    worker.send(Check).await;
+    proxy.send(Start).await;
+
+    let envelope = worker.recv().await;
+    assert_eq!(envelope.baggage().get("tenant"), Some("acme"));
+    msg!(match envelope {
+        (Ask, token) => worker.respond(token, ()),
+    });
+
+    assert_msg!(proxy.recv().await, Seen(Some(_)));
+}
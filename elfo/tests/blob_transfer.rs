@@ -0,0 +1,101 @@
+#![cfg(all(feature = "test-util", feature = "network"))]
+
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use elfo::{
+    _priv::do_start,
+    batteries::network::blob::{BlobChunk, BlobReceiver, BlobTransfer},
+    prelude::*,
+    Topology,
+};
+use elfo_core::config::AnyConfig;
+
+fn make_blob() -> Vec<u8> {
+    (0..300_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+/// Transfers the blob, the receiver drops the chunk at `lost_offset` once.
+async fn run(receiver: BlobReceiver<Vec<u8>>, lost_offset: Option<u64>) -> Vec<u8> {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let sender_blueprint = ActorGroup::new().exec(move |ctx| async move {
+        let size = BlobTransfer::new(1)
+            .chunk_size(64 * 1024)
+            .retry_interval(Duration::from_millis(10))
+            .send(&ctx, Cursor::new(make_blob()))
+            .await
+            .unwrap();
+        assert_eq!(size, 300_000);
+    });
+
+    let receiver = parking_lot::Mutex::new(Some(receiver));
+    let receiver_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+        let mut receiver = receiver.lock().take().unwrap();
+        let mut lost_offset = lost_offset;
+
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (chunk @ BlobChunk, token) => {
+                        if lost_offset == Some(chunk.offset) {
+                            // Emulate a lost connection, the request fails.
+                            lost_offset = None;
+                            drop(token);
+                            continue;
+                        }
+
+                        let ack = receiver.handle(&chunk).await.unwrap();
+                        ctx.respond(token, ack);
+
+                        if receiver.is_complete() {
+                            tx.send(receiver.into_inner()).unwrap();
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let senders = topology.local("senders");
+    let receivers = topology.local("receivers");
+
+    senders.route_all_to(&receivers);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    senders.mount(sender_blueprint);
+    receivers.mount(receiver_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("the transfer is stuck")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn transfer() {
+    let received = run(BlobReceiver::new(Vec::new()), None).await;
+    assert_eq!(received, make_blob());
+}
+
+#[tokio::test]
+async fn retry_lost_chunk() {
+    let received = run(BlobReceiver::new(Vec::new()), Some(128 * 1024)).await;
+    assert_eq!(received, make_blob());
+}
+
+#[tokio::test]
+async fn resume() {
+    // The receiver has already written a part of the blob, e.g. before restart.
+    let written = make_blob()[..100_000].to_vec();
+    let received = run(BlobReceiver::resume(written, 100_000), None).await;
+    assert_eq!(received, make_blob());
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{_priv::do_start, config::AnyConfig, prelude::*, Envelope, Topology};
+
+#[message(ret = u32)]
+struct Double(u32);
+
+#[message(ret = ())]
+struct Ignore;
+
+#[message(ret = u32)]
+struct DoubleByActor(u32);
+
+elfo::client! {
+    struct ServiceClient {
+        async fn double(Double);
+        async fn ignore(Ignore);
+    }
+}
+
+fn service() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Double(n), token) => ctx.respond(token, n * 2),
+                (Ignore, token) => drop(token),
+            });
+        }
+    })
+}
+
+fn consumer() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (DoubleByActor(n), token) => {
+                    let client = ServiceClient::new(&ctx);
+                    let doubled = client.double(Double(n)).await.unwrap();
+                    ctx.respond(token, doubled);
+                }
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_requests_inside_and_outside_actors() {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let consumers = topology.local("consumers");
+    let services = topology.local("services");
+
+    let is_by_actor = |envelope: &Envelope| {
+        msg!(match envelope {
+            DoubleByActor => true,
+            _ => false,
+        })
+    };
+    api.route_to(&consumers, is_by_actor);
+    api.route_to(&services, move |envelope: &Envelope| !is_by_actor(envelope));
+    consumers.route_all_to(&services);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    consumers.mount(consumer());
+    services.mount(service());
+    let handle = api.handle();
+
+    do_start(topology, false, |_, _| async move {
+        let client = ServiceClient::new(handle.clone());
+        assert_eq!(client.double(Double(21)).await.unwrap(), 42);
+        assert!(client.ignore(Ignore).await.unwrap_err().is_ignored());
+
+        assert_eq!(handle.request(DoubleByActor(5)).await.unwrap(), 10);
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Query(u32);
+
+#[message]
+struct Done(u32);
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static MAX: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test(start_paused = true)]
+async fn it_limits_handling_actors() {
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Query(key) => Outcome::Unicast(*key),
+                _ => Outcome::Default,
+            })
+        }))
+        .concurrency_limit(2)
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Query(key) => {
+                        let current = CURRENT.fetch_add(1, Ordering::SeqCst) + 1;
+                        MAX.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        CURRENT.fetch_sub(1, Ordering::SeqCst);
+
+                        ctx.send(Done(key)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    for key in 0..5 {
+        proxy.send(Query(key)).await;
+    }
+
+    let mut done = Vec::new();
+    for _ in 0..5 {
+        msg!(match proxy.recv().await {
+            Done(key) => done.push(key),
+        });
+    }
+    done.sort_unstable();
+    assert_eq!(done, vec![0, 1, 2, 3, 4]);
+    assert_eq!(MAX.load(Ordering::SeqCst), 2);
+
+    // Actors waiting for the next message don't hold permits.
+    proxy.send(Query(0)).await;
+    proxy.send(Query(1)).await;
+    proxy.send(Query(2)).await;
+    for _ in 0..3 {
+        msg!(match proxy.recv().await {
+            Done => {}
+        });
+    }
+    assert_eq!(MAX.load(Ordering::SeqCst), 2);
+}
@@ -34,7 +34,7 @@ async fn singleton_actor_with_default_validate_config() {
     info!("actor started");
 
     let result = proxy.try_send(ValidateConfig::new(AnyConfig::default()));
-    assert!(matches!(result, Err(TrySendError::Closed(..))));
+    assert!(matches!(result, Err(TrySendError::NoRoute(..))));
 }
 
 #[tokio::test]
@@ -105,7 +105,7 @@ async fn actor_group_with_default_validate_config() {
     info!("actors started");
 
     let result = proxy.try_send(ValidateConfig::new(AnyConfig::default()));
-    assert!(matches!(result, Err(TrySendError::Closed(..))));
+    assert!(matches!(result, Err(TrySendError::NoRoute(..))));
 }
 
 #[tokio::test]
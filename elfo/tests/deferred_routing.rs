@@ -0,0 +1,139 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{Outcome, Router},
+    Envelope,
+};
+
+/// Routed after the delay to an actor with the specified key.
+#[message]
+struct Job {
+    id: u32,
+    key: u32,
+    delay_ms: u64,
+}
+
+/// Routed synchronously.
+#[message]
+struct Direct {
+    id: u32,
+    key: u32,
+}
+
+#[message]
+#[derive(PartialEq)]
+struct Handled {
+    id: u32,
+    key: u32,
+}
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static MAX: AtomicUsize = AtomicUsize::new(0);
+
+struct JobRouter;
+
+impl Router<()> for JobRouter {
+    type Key = u32;
+
+    fn route(&self, envelope: &Envelope) -> Outcome<u32> {
+        msg!(match envelope {
+            Job => Outcome::Deferred,
+            Direct { key, .. } => Outcome::Unicast(*key),
+            _ => Outcome::Default,
+        })
+    }
+
+    fn route_deferred(&self, envelope: &Envelope) -> BoxFuture<'static, Outcome<u32>> {
+        let (key, delay_ms) = msg!(match envelope {
+            Job { key, delay_ms, .. } => (*key, *delay_ms),
+            _ => unreachable!(),
+        });
+
+        Box::pin(async move {
+            let current = CURRENT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            CURRENT.fetch_sub(1, Ordering::SeqCst);
+            Outcome::Unicast(key)
+        })
+    }
+}
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new()
+        .router(JobRouter)
+        .max_deferred_routes(2)
+        .exec(|mut ctx| async move {
+            let key = *ctx.key();
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Job { id, .. } | Direct { id, .. } => {
+                        ctx.send(Handled { id, key }).await.unwrap();
+                    }
+                });
+            }
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn order_and_concurrency() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    // Following messages of the same sender wait for deferred ones.
+    proxy
+        .send(Job {
+            id: 1,
+            key: 1,
+            delay_ms: 20,
+        })
+        .await;
+    proxy
+        .send(Job {
+            id: 2,
+            key: 1,
+            delay_ms: 0,
+        })
+        .await;
+    proxy.send(Direct { id: 3, key: 1 }).await;
+
+    for id in 1..=3 {
+        assert_msg_eq!(proxy.recv().await, Handled { id, key: 1 });
+    }
+
+    // Messages of different senders are routed concurrently, but limited.
+    let senders = [
+        proxy.subproxy().await,
+        proxy.subproxy().await,
+        proxy.subproxy().await,
+    ];
+    for (i, sender) in senders.iter().enumerate() {
+        let key = i as u32 + 10;
+        sender
+            .send(Job {
+                id: key,
+                key,
+                delay_ms: 10,
+            })
+            .await;
+    }
+
+    let mut handled = Vec::new();
+    for _ in 0..3 {
+        msg!(match proxy.recv().await {
+            Handled { id, .. } => handled.push(id),
+        });
+    }
+    handled.sort_unstable();
+    assert_eq!(handled, vec![10, 11, 12]);
+    assert_eq!(MAX.load(Ordering::SeqCst), 2);
+}
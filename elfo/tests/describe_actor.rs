@@ -0,0 +1,90 @@
+#![cfg(feature = "test-util")]
+
+use serde_json::{json, Value};
+
+use elfo::{
+    config::AnyConfig,
+    errors::RequestError,
+    messages::DescribeActor,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Spawn(u32);
+
+#[message]
+struct Increment(u32);
+
+// Describes the actor with the provided key from another actor.
+#[message(ret = String)]
+struct Probe(String);
+
+struct Counter {
+    key: u32,
+    value: u64,
+}
+
+impl Counter {
+    fn describe(&self) -> Value {
+        json!({ "key": self.key, "value": self.value })
+    }
+}
+
+#[tokio::test]
+async fn it_describes_actors_by_keys() {
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Spawn(key) | Increment(key) => Outcome::Unicast(*key),
+                Probe => Outcome::Unicast(0),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let mut counter = Counter {
+                key: *ctx.key(),
+                value: 0,
+            };
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Spawn => {}
+                    Increment => counter.value += 1,
+                    (DescribeActor, token) => {
+                        // The second actor doesn't describe its state.
+                        if counter.key != 2 {
+                            ctx.respond(token, counter.describe());
+                        }
+                    }
+                    (Probe(key), token) => {
+                        let request = DescribeActor::new(key);
+                        let result = match ctx.request_to(ctx.group(), request).resolve().await {
+                            Ok(state) => state.to_string(),
+                            Err(RequestError::Ignored) => "ignored".into(),
+                            Err(err) if err.send_error().is_some_and(|err| err.is_no_route()) => {
+                                "no route".into()
+                            }
+                            Err(_) => "failed".into(),
+                        };
+                        ctx.respond(token, result);
+                    }
+                });
+            }
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    proxy.send(Spawn(1)).await;
+    proxy.send(Spawn(2)).await;
+    proxy.send(Increment(1)).await;
+    proxy.send(Increment(1)).await;
+    proxy.sync().await;
+
+    let state = proxy.request(DescribeActor::new("1".into())).await;
+    assert_eq!(state, json!({ "key": 1, "value": 2 }));
+
+    let probe = |key: &str| proxy.request(Probe(key.into()));
+    assert_eq!(probe("1").await, r#"{"key":1,"value":2}"#);
+    assert_eq!(probe("2").await, "ignored");
+    assert_eq!(probe("3").await, "no route");
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use elfo::{
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    SystemHandle, Topology,
+};
+use elfo_core::config::AnyConfig;
+
+#[message(ret = u32)]
+struct Ask;
+
+#[message]
+struct Event(u32);
+
+fn topology(is_terminated: Arc<AtomicBool>) -> (Topology, SystemHandle) {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let service = topology.local("service");
+
+    api.route_all_to(&service);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    service.mount(ActorGroup::new().exec(move |mut ctx| {
+        let is_terminated = is_terminated.clone();
+
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Ask, token) => ctx.respond(token, 42),
+                });
+            }
+            is_terminated.store(true, Ordering::SeqCst);
+        }
+    }));
+    let handle = api.handle();
+
+    (topology, handle)
+}
+
+#[test]
+fn it_terminates_on_drop() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let is_terminated = Arc::new(AtomicBool::new(false));
+    let (topology, handle) = topology(is_terminated.clone());
+
+    let mut guard = elfo::start_with_runtime(rt.handle(), topology).unwrap();
+    rt.block_on(guard.started()).unwrap();
+    assert_eq!(rt.block_on(handle.request(Ask)).unwrap(), 42);
+    assert!(!guard.is_finished());
+
+    drop(guard);
+    assert!(is_terminated.load(Ordering::SeqCst));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_shuts_down() {
+    let is_terminated = Arc::new(AtomicBool::new(false));
+    let (topology, handle) = topology(is_terminated.clone());
+
+    let mut guard = elfo::start_with_runtime(&tokio::runtime::Handle::current(), topology)
+        .unwrap()
+        .with_shutdown_deadline(Duration::from_secs(5));
+    guard.started().await.unwrap();
+    assert_eq!(handle.request(Ask).await.unwrap(), 42);
+
+    guard.shutdown().await.unwrap();
+    assert!(is_terminated.load(Ordering::SeqCst));
+}
+
+#[test]
+fn it_sends_from_plain_threads() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let received = Arc::new(AtomicU32::new(0));
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let service = topology.local("service");
+
+    api.route_all_to(&service);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    let received_by_actor = received.clone();
+    service.mount(
+        ActorGroup::new()
+            .router(MapRouter::new(|envelope| {
+                msg!(match envelope {
+                    Event(no) => Outcome::Unicast(*no),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(move |mut ctx| {
+                let received = received_by_actor.clone();
+
+                async move {
+                    while let Some(envelope) = ctx.recv().await {
+                        msg!(match envelope {
+                            Event(no) => received.store(no, Ordering::SeqCst),
+                        });
+                    }
+                }
+            }),
+    );
+    let handle = api.handle();
+
+    let mut guard = elfo::start_with_runtime(rt.handle(), topology)
+        .unwrap()
+        .with_shutdown_deadline(Duration::from_secs(5));
+    rt.block_on(guard.started()).unwrap();
+
+    // The message spawns a new actor outside the runtime.
+    std::thread::spawn(move || handle.try_send(Event(7)))
+        .join()
+        .unwrap()
+        .unwrap();
+
+    for _ in 0..100 {
+        if received.load(Ordering::SeqCst) == 7 {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    panic!("the message isn't received");
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    errors::ExecError,
+    messages::UpdateConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    RestartPolicy,
+};
+
+#[message]
+struct Spawn;
+
+#[tokio::test(start_paused = true)]
+async fn fatal_isnt_restarted() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    let blueprint = ActorGroup::new()
+        .restart_policy(RestartPolicy::always())
+        .exec(|_ctx| async {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+
+            // Found even if wrapped.
+            Err(ExecError::fatal("oops")).context("cannot connect")
+        });
+
+    let _proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn transient_is_restarted() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        if STARTED.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Err(ExecError::transient("oops"));
+        }
+
+        while ctx.recv().await.is_some() {}
+        Ok(())
+    });
+
+    let _proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn misconfiguration_waits_for_config() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Config {
+        valid: bool,
+    }
+
+    let blueprint = ActorGroup::new()
+        .config::<Config>()
+        .restart_policy(RestartPolicy::always())
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Spawn => Outcome::Unicast(0),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+
+            if !ctx.config().valid {
+                return Err(ExecError::misconfiguration("invalid"));
+            }
+
+            while ctx.recv().await.is_some() {}
+            Ok(())
+        });
+
+    let proxy = elfo::test::proxy(blueprint, toml! { valid = false }).await;
+    proxy.send(Spawn).await;
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 1);
+
+    // Routed messages are discarded instead of spawning the actor again.
+    for _ in 0..10 {
+        assert!(proxy.try_send(Spawn).is_err());
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    assert_eq!(STARTED.load(Ordering::SeqCst), 1);
+
+    // Spawned again without routing messages to the actor.
+    let config = AnyConfig::deserialize(toml! { valid = true }).unwrap();
+    proxy.send(UpdateConfig::new(config)).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+}
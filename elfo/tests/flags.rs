@@ -0,0 +1,38 @@
+#![cfg(feature = "test-util")]
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{config::AnyConfig, flags::Flag, messages::UpdateConfig, prelude::*};
+
+struct OrderV2Enabled;
+
+impl Flag for OrderV2Enabled {
+    const DEFAULT: bool = false;
+    const NAME: &'static str = "order_v2_enabled";
+}
+
+#[message(ret = bool)]
+struct IsOrderV2;
+
+#[tokio::test]
+async fn it_updates_flags() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (IsOrderV2, token) => ctx.respond(token, ctx.flag::<OrderV2Enabled>()),
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    assert!(!proxy.request(IsOrderV2).await);
+
+    let config = toml! {
+        [system.flags]
+        order_v2_enabled = true
+    };
+    let config = AnyConfig::deserialize(config).unwrap();
+    proxy.send(UpdateConfig::new(config)).await;
+    assert!(proxy.request(IsOrderV2).await);
+}
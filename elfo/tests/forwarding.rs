@@ -0,0 +1,118 @@
+#![cfg(feature = "test-util")]
+
+use std::{sync::Arc, time::Duration};
+
+use elfo::{_priv::do_start, errors::RequestError, prelude::*, Topology};
+use elfo_core::config::AnyConfig;
+
+#[message(ret = u64)]
+struct Ask(u64);
+
+async fn run(with_backend: bool) -> Result<u64, RequestError> {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let requester_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            let result = ctx.request(Ask(42)).resolve().await;
+            tx.send(result).unwrap();
+        }
+    });
+
+    let gateway_blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            // The response goes directly to the requester.
+            let _ = ctx.forward(envelope).await;
+        }
+    });
+
+    let backend_blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Ask(n), token) => ctx.respond(token, n + 1),
+            });
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let requesters = topology.local("requesters");
+    let gateways = topology.local("gateways");
+    let backends = topology.local("backends");
+
+    requesters.route_all_to(&gateways);
+    if with_backend {
+        gateways.route_all_to(&backends);
+    }
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    requesters.mount(requester_blueprint);
+    gateways.mount(gateway_blueprint);
+    backends.mount(backend_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("the request is stuck")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn request() {
+    assert!(matches!(run(true).await, Ok(43)));
+}
+
+#[tokio::test]
+async fn no_route() {
+    // The forwarded envelope is dropped, so the request fails.
+    assert!(matches!(run(false).await, Err(RequestError::Failed)));
+}
+
+#[message]
+struct Register;
+
+#[message]
+struct Hello;
+
+#[tokio::test]
+async fn forward_to() {
+    let blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        let mut worker = None;
+
+        while let Some(envelope) = ctx.recv().await {
+            let sender = envelope.sender();
+
+            msg!(match envelope {
+                Register => worker = Some(sender),
+                envelope => {
+                    let worker = worker.expect("no registered worker");
+                    ctx.forward_to(worker, envelope).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let mut worker = proxy.subproxy().await;
+    worker.send(Register).await;
+
+    // The sender is preserved.
+    proxy.send(Hello).await;
+    let envelope = worker.recv().await;
+    assert_eq!(envelope.sender(), proxy.addr());
+    assert_msg!(envelope, Hello);
+
+    // The response goes directly to the requester.
+    let respond = async {
+        msg!(match worker.recv().await {
+            (Ask(n), token) => worker.respond(token, n + 1),
+        });
+    };
+    let (response, _) = futures::join!(proxy.request(Ask(42)), respond);
+    assert_eq!(response, 43);
+}
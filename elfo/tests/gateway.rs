@@ -0,0 +1,127 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use toml::toml;
+
+use elfo::{_priv::do_start, prelude::*, scope, Topology};
+
+#[message(ret = u32)]
+struct Sum {
+    a: u32,
+    b: u32,
+}
+
+#[message]
+struct Poke;
+
+#[message(ret = ())]
+struct Ignored;
+
+#[message(ret = ())]
+struct Hidden;
+
+// Sends a request and returns the status line, headers and the body.
+async fn post(path: &str, trace_id: Option<&str>, body: &str) -> (String, String, String) {
+    let mut stream = loop {
+        match TcpStream::connect("127.0.0.1:43917").await {
+            Ok(stream) => break stream,
+            // The server can be not started yet.
+            Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    };
+
+    let trace_id = trace_id.map_or(String::new(), |t| format!("x-trace-id: {t}\r\n"));
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nhost: localhost\r\n{trace_id}content-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let (status, headers) = head.split_once("\r\n").unwrap();
+    (status.into(), headers.to_lowercase(), body.into())
+}
+
+#[tokio::test]
+async fn it_exposes_messages() {
+    let config = toml! {
+        [system.gateways]
+        address = "127.0.0.1:43917"
+        timeout = "1s"
+        max_body_size = 32
+
+        [[system.gateways.messages]]
+        protocol = "elfo"
+        name = "Sum"
+
+        [[system.gateways.messages]]
+        protocol = "elfo"
+        name = "Poke"
+
+        [[system.gateways.messages]]
+        protocol = "elfo"
+        name = "Ignored"
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let gateways = topology.local("system.gateways");
+    let calculators = topology.local("calculators");
+
+    gateways.route_all_to(&calculators);
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    gateways.mount(elfo::batteries::gateway::new());
+    calculators.mount(ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Sum { a, b }, token) => {
+                    assert_eq!(scope::trace_id().to_string(), "4242");
+                    ctx.respond(token, a + b);
+                }
+                Poke => {}
+                (Ignored, token) => drop(token),
+            });
+        }
+    }));
+
+    do_start(topology, false, |_, _| async move {
+        let (status, headers, body) =
+            post("/messages/elfo/Sum", Some("4242"), r#"{"a":1,"b":2}"#).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(headers.contains("x-trace-id: 4242"));
+        assert_eq!(body, "3");
+
+        // Regular messages are sent without waiting.
+        let (status, headers, _) = post("/messages/elfo/Poke", None, "").await;
+        assert_eq!(status, "HTTP/1.1 202 Accepted");
+        assert!(headers.contains("x-trace-id: "));
+
+        let (status, ..) = post("/messages/elfo/Ignored", None, "").await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+
+        let (status, ..) = post("/messages/elfo/Hidden", None, "").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (status, ..) = post("/messages/elfo/Sum", None, r#"{"a":1}"#).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+        let (status, ..) = post("/messages/elfo/Sum", Some("nope"), "").await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+        let body = format!(r#"{{"a":1,"b":2,"c":"{}"}}"#, "x".repeat(32));
+        let (status, _, body) = post("/messages/elfo/Sum", None, &body).await;
+        assert_eq!(status, "HTTP/1.1 413 Payload Too Large");
+        assert_eq!(body, "the body is larger than 32 bytes");
+    })
+    .await
+    .expect("cannot start");
+}
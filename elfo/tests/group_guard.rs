@@ -0,0 +1,60 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, messages::DeadLetter, prelude::*};
+
+#[message]
+struct Relevant(u32);
+
+#[message]
+struct Irrelevant(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Handled(u32);
+
+fn blueprint(forward_dead_letters: bool) -> Blueprint {
+    ActorGroup::new()
+        .guard(|envelope| {
+            msg!(match envelope {
+                Relevant => true,
+                _ => false,
+            })
+        })
+        .forward_dead_letters(forward_dead_letters)
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Relevant(no) => ctx.send(Handled(no)).await.unwrap(),
+                    Irrelevant => panic!("must be rejected by the guard"),
+                });
+            }
+        })
+}
+
+#[tokio::test]
+async fn it_rejects_messages() {
+    let mut proxy = elfo::test::proxy(blueprint(false), AnyConfig::default()).await;
+
+    assert!(proxy.try_send(Irrelevant(1)).is_err());
+    proxy.send(Relevant(2)).await;
+    assert_msg_eq!(proxy.recv().await, Handled(2));
+
+    proxy.sync().await;
+    assert!(proxy.try_recv().await.is_none());
+}
+
+#[tokio::test]
+async fn it_forwards_dead_letters() {
+    let mut proxy = elfo::test::proxy(blueprint(true), AnyConfig::default()).await;
+
+    assert!(proxy.try_send(Irrelevant(1)).is_err());
+
+    msg!(match proxy.recv().await {
+        DeadLetter {
+            reason, message, ..
+        } => {
+            assert_eq!(reason, "guard");
+            assert_eq!(message.downcast_ref::<Irrelevant>().unwrap().0, 1);
+        }
+    });
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Hang(Duration);
+
+#[message(ret = u32)]
+struct GetStarted;
+
+fn blueprint(started: &'static AtomicU32) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        started.fetch_add(1, Ordering::SeqCst);
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Hang(duration) => tokio::time::sleep(duration).await,
+                (GetStarted, token) => ctx.respond(token, started.load(Ordering::SeqCst)),
+            });
+        }
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn restart() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    let config = toml! {
+        [system.handling]
+        timeouts.Hang = "5s"
+        on_timeout = "Restart"
+    };
+    let proxy = elfo::test::proxy(blueprint(&STARTED), config).await;
+
+    proxy.send(Hang(Duration::from_secs(3600))).await;
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 1);
+
+    // The handler is cancelled and the actor is restarted.
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(proxy.request(GetStarted).await, 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn alert() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    let config = toml! {
+        [system.handling]
+        timeout = "1s"
+    };
+    let proxy = elfo::test::proxy(blueprint(&STARTED), config).await;
+
+    proxy.send(Hang(Duration::from_secs(10))).await;
+    assert_eq!(proxy.request(GetStarted).await, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn disabled_by_default() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    let proxy = elfo::test::proxy(blueprint(&STARTED), AnyConfig::default()).await;
+
+    proxy.send(Hang(Duration::from_secs(3600))).await;
+    assert_eq!(proxy.request(GetStarted).await, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn waiting_for_permit_isnt_counted() {
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+
+    #[message]
+    struct HangKeyed(u32, Duration);
+
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                HangKeyed(key, _) => Outcome::Unicast(*key),
+                GetStarted => Outcome::Unicast(0),
+                _ => Outcome::Default,
+            })
+        }))
+        .concurrency_limit(1)
+        .exec(|mut ctx| async move {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    HangKeyed(_, duration) => tokio::time::sleep(duration).await,
+                    (GetStarted, token) => ctx.respond(token, STARTED.load(Ordering::SeqCst)),
+                });
+            }
+        });
+
+    let config = toml! {
+        [system.handling]
+        timeout = "5s"
+        on_timeout = "Restart"
+    };
+    let proxy = elfo::test::proxy(blueprint, config).await;
+
+    // The second actor waits for the permit for 4s, then handles for 4s.
+    proxy.send(HangKeyed(0, Duration::from_secs(4))).await;
+    proxy.send(HangKeyed(1, Duration::from_secs(4))).await;
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(proxy.request(GetStarted).await, 2);
+}
@@ -0,0 +1,214 @@
+#![cfg(all(feature = "test-util", feature = "network"))]
+
+use std::{sync::Arc, time::Duration};
+
+use elfo::{
+    _priv::do_start,
+    batteries::network::handoff::{self, Handoff, MigrationTable, Restorer},
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    topology, NodeNo, SystemHandle, Topology,
+};
+use elfo_core::config::AnyConfig;
+use serde_json::json;
+
+#[message]
+struct Add {
+    session: u32,
+    n: u64,
+}
+
+#[message]
+struct Migrate {
+    session: u32,
+}
+
+#[message(ret = (u64, u64))]
+struct Get {
+    session: u32,
+}
+
+// The remote node is emulated by another local group, so `Handoff` and
+// forwarded messages are routed to it unconditionally.
+fn sessions(table: MigrationTable<u32>) -> Blueprint {
+    ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Add { session, .. } => Outcome::Unicast(*session),
+                Migrate { session } => Outcome::Unicast(*session),
+                Get { session } => Outcome::Unicast(*session),
+                Handoff { key, .. } => Outcome::Unicast(key.parse().unwrap()),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(move |mut ctx| {
+            let table = table.clone();
+
+            async move {
+                let mut sum = 0u64;
+                let mut restorer = Restorer::default();
+
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        Add { n, .. } => sum += n,
+                        (Get { .. }, token) => ctx.respond(token, (sum, ctx.group().into_bits())),
+                        Migrate => {
+                            let node_no = NodeNo::from_bits(2).unwrap();
+                            let snapshot = sum.to_le_bytes().to_vec();
+                            handoff::handoff(&ctx, &table, node_no, snapshot)
+                                .await
+                                .unwrap();
+                            assert_eq!(table.node_no(ctx.key()), Some(node_no));
+                            handoff::forward_rest(&mut ctx).await;
+                            return;
+                        }
+                        (handoff @ Handoff, token) => {
+                            if let Some(snapshot) = restorer.handle(&ctx, &handoff, token).await {
+                                sum = u64::from_le_bytes(snapshot.try_into().unwrap());
+                            }
+                        }
+                    });
+                }
+            }
+        })
+}
+
+#[tokio::test]
+async fn it_works() {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let client_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            ctx.send(Add { session: 1, n: 10 }).await.unwrap();
+            ctx.send(Migrate { session: 1 }).await.unwrap();
+            // Held by the migrating actor and forwarded after the handoff.
+            ctx.send(Add { session: 1, n: 5 }).await.unwrap();
+            let result = ctx.request(Get { session: 1 }).resolve().await;
+            tx.send(result).unwrap();
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let clients = topology.local("clients");
+    let sessions_a = topology.local("sessions_a");
+    let sessions_b = topology.local("sessions_b");
+    let sessions_b_addr = sessions_b.addr();
+
+    clients.route_all_to(&sessions_a);
+    sessions_a.route_all_to(&sessions_b);
+
+    let table = MigrationTable::default();
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    clients.mount(client_blueprint);
+    sessions_a.mount(sessions(table.clone()));
+    sessions_b.mount(sessions(MigrationTable::default()));
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let (sum, group) = tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("the migration is stuck")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(sum, 15);
+    assert_eq!(group, sessions_b_addr.into_bits());
+    assert!(table.node_no(&1).is_some());
+}
+
+fn node(
+    node_no: u16,
+    network: serde_json::Value,
+    table: MigrationTable<u32>,
+) -> (Topology, SystemHandle) {
+    let topology = Topology::empty();
+    topology.set_node_no(NodeNo::from_bits(node_no).unwrap());
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let network_group = topology.local("system.network");
+    let api = topology.local("api");
+    let local_sessions = topology.local("sessions");
+    let remote_sessions = topology.remote("sessions");
+
+    api.route_all_to(&local_sessions);
+
+    // Migrated sessions are reached by table routing only.
+    let routing_table = table.clone();
+    local_sessions.route_to(&remote_sessions, move |envelope, _| {
+        msg!(match envelope {
+            Handoff { node_no, .. } => topology::Outcome::Unicast(*node_no),
+            Add { session, .. } => routing_table.outcome(session),
+            Get { session } => routing_table.outcome(session),
+            _ => topology::Outcome::Discard,
+        })
+    });
+
+    let config = json!({ "system": { "network": network } });
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    network_group.mount(elfo::batteries::network::new(&topology));
+    local_sessions.mount(sessions(table));
+    let handle = api.handle();
+
+    (topology, handle)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_migrates_between_nodes() {
+    let rt = tokio::runtime::Handle::current();
+    let table = MigrationTable::default();
+    let (source, source_handle) = node(
+        1,
+        json!({
+            "listen": ["mem://handoff-source"],
+            "discovery": { "predefined": ["mem://handoff-target"] },
+        }),
+        table.clone(),
+    );
+    let (target, target_handle) = node(
+        2,
+        json!({ "listen": ["mem://handoff-target"] }),
+        MigrationTable::default(),
+    );
+
+    let mut target_guard = elfo::start_with_runtime(&rt, target).unwrap();
+    let mut source_guard = elfo::start_with_runtime(&rt, source).unwrap();
+    target_guard.started().await.unwrap();
+    source_guard.started().await.unwrap();
+
+    source_handle.send(Add { session: 1, n: 10 }).await.unwrap();
+    source_handle.send(Migrate { session: 1 }).await.unwrap();
+
+    // Sent by the source actor to the target node once the key is migrated.
+    source_handle.send(Add { session: 1, n: 5 }).await.unwrap();
+
+    let wait_for = |expected: u64| {
+        let target_handle = target_handle.clone();
+        async move {
+            let waiting = async {
+                loop {
+                    match target_handle.request(Get { session: 1 }).await {
+                        Ok((sum, _)) if sum == expected => break,
+                        _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                    }
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(10), waiting).await
+        }
+    };
+
+    wait_for(15).await.expect("the migration is stuck");
+    assert_eq!(table.node_no(&1), NodeNo::from_bits(2));
+
+    // Forwarded by the source actor and routed to the target node by the table.
+    source_handle.send(Add { session: 1, n: 1 }).await.unwrap();
+    wait_for(16).await.expect("the message isn't routed");
+
+    source_guard.shutdown().await.unwrap();
+    target_guard.shutdown().await.unwrap();
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use toml::toml;
+
+use elfo::{_priv::do_start, prelude::*, Topology};
+
+#[message(ret = u32, idempotent)]
+struct Fetch;
+
+static IS_FIRST_SLOW: AtomicBool = AtomicBool::new(false);
+
+#[tokio::test]
+async fn it_works() {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let requester_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            // Collect the latency, both replicas respond in 50ms.
+            ctx.request(Fetch).resolve().await.unwrap();
+
+            IS_FIRST_SLOW.store(true, Ordering::Relaxed);
+
+            let start = Instant::now();
+            let replica = ctx.request(Fetch).resolve().await.unwrap();
+            tx.send((replica, start.elapsed())).unwrap();
+        }
+    });
+
+    let replica_blueprint = |no: u32| {
+        ActorGroup::new().exec(move |mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Fetch, token) => {
+                        let delay = if no == 1 && IS_FIRST_SLOW.load(Ordering::Relaxed) {
+                            Duration::from_secs(5)
+                        } else {
+                            Duration::from_millis(50)
+                        };
+
+                        tokio::time::sleep(delay).await;
+                        ctx.respond(token, no);
+                    }
+                    _ => {}
+                });
+            }
+        })
+    };
+
+    // Replicas are resolved by the topology, e.g. remote groups on different
+    // nodes. Here, local groups are used for simplicity.
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let requester = topology.local("requester");
+    let replica1 = topology.local("replica1");
+    let replica2 = topology.local("replica2");
+
+    requester.route_all_to(&replica1);
+    requester.route_all_to(&replica2);
+
+    let config = toml! {
+        [requester.system.hedging]
+        min_samples = 1
+    };
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    requester.mount(requester_blueprint);
+    replica1.mount(replica_blueprint(1));
+    replica2.mount(replica_blueprint(2));
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    // The duplicate is sent to the second replica after ~50ms.
+    let (replica, elapsed) = rx.receive().await.unwrap();
+    assert_eq!(replica, 2);
+    assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+}
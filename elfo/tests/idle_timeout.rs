@@ -0,0 +1,102 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    messages::IdleTimeout,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    RestartPolicy,
+};
+use tokio::time;
+
+#[message]
+struct Hello(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Started(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Expiring(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Finished(u32);
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new()
+        .idle_timeout(TIMEOUT)
+        .restart_policy(RestartPolicy::always())
+        .router(MapRouter::new(|e| {
+            msg!(match e {
+                Hello(no) => Outcome::Unicast(*no),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let no = *ctx.key();
+            ctx.send(Started(no)).await.unwrap();
+
+            // The second actor vetoes the first expiration.
+            let mut vetoes = u32::from(no == 2);
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    IdleTimeout => {
+                        ctx.send(Expiring(no)).await.unwrap();
+
+                        if vetoes > 0 {
+                            vetoes -= 1;
+                            ctx.keep_alive();
+                        }
+                    }
+                    _ => {}
+                });
+            }
+
+            ctx.send(Finished(no)).await.unwrap();
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn expiration() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    proxy.send(Hello(1)).await;
+    assert_msg_eq!(proxy.recv().await, Started(1));
+
+    // Messages reset the timer.
+    time::sleep(TIMEOUT / 2).await;
+    proxy.send(Hello(1)).await;
+    time::sleep(TIMEOUT * 3 / 4).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    time::sleep(TIMEOUT / 2).await;
+    assert_msg_eq!(proxy.recv().await, Expiring(1));
+    assert_msg_eq!(proxy.recv().await, Finished(1));
+
+    // Closed actors aren't restarted.
+    time::sleep(TIMEOUT * 2).await;
+    assert!(proxy.try_recv().await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn veto() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    proxy.send(Hello(2)).await;
+    assert_msg_eq!(proxy.recv().await, Started(2));
+
+    time::sleep(TIMEOUT * 3 / 2).await;
+    assert_msg_eq!(proxy.recv().await, Expiring(2));
+    assert!(proxy.try_recv().await.is_none());
+
+    time::sleep(TIMEOUT).await;
+    assert_msg_eq!(proxy.recv().await, Expiring(2));
+    assert_msg_eq!(proxy.recv().await, Finished(2));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "test-util")]
+
+use std::{sync::Arc, time::Duration};
+
+use elfo::{_priv::do_start, errors::RequestError, prelude::*, Topology};
+use elfo_core::config::AnyConfig;
+
+#[message(ret = u64)]
+struct Ask;
+
+#[tokio::test]
+async fn dropped_token_is_ignored() {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let requester_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            let result = ctx.request(Ask).resolve().await;
+            tx.send(matches!(result, Err(RequestError::Ignored)))
+                .unwrap();
+        }
+    });
+
+    let responder_blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Ask, token) => drop(token),
+            });
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let requesters = topology.local("requesters");
+    let responders = topology.local("responders");
+
+    requesters.route_all_to(&responders);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    requesters.mount(requester_blueprint);
+    responders.mount(responder_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    // No timeout is set for the request, so it must be resolved immediately.
+    let is_ignored = tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("the request is stuck")
+        .unwrap();
+
+    assert!(is_ignored);
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use elfo::{config::AnyConfig, journal::EventKind, prelude::*, ActorStatusKind, RestartPolicy};
+
+#[message]
+struct Started;
+
+#[tokio::test(start_paused = true)]
+async fn it_records_lifecycle_events() {
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+    let blueprint = ActorGroup::new()
+        .restart_policy(RestartPolicy::on_failures())
+        .exec(|mut ctx| async move {
+            if STARTED.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("boom!");
+            }
+
+            ctx.send(Started).await.unwrap();
+            while ctx.recv().await.is_some() {}
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    // https://github.com/tokio-rs/tokio/issues/3985
+    tokio::time::sleep(Duration::from_millis(5001)).await;
+    assert_msg!(proxy.recv().await, Started);
+
+    let events = proxy
+        .journal()
+        .recent(usize::MAX)
+        .into_iter()
+        .filter(|e| e.meta.group == "subject")
+        .map(|e| e.kind)
+        .collect::<Vec<_>>();
+
+    let position = |f: &dyn Fn(&EventKind) -> bool| events.iter().position(f).unwrap();
+
+    let config_updated = position(&|e| matches!(e, EventKind::ConfigUpdated));
+    let failed = position(&|e| is_status(e, ActorStatusKind::Failed));
+    let restarting = position(&|e| matches!(e, EventKind::Restarting { .. }));
+    let normal = position(&|e| is_status(e, ActorStatusKind::Normal));
+
+    assert!(config_updated < failed);
+    assert!(failed < restarting);
+    assert!(restarting < normal);
+
+    proxy.journal().set_capacity(1);
+    assert_eq!(proxy.journal().recent(usize::MAX).len(), 1);
+}
+
+#[tokio::test]
+async fn it_keeps_journals_per_topology() {
+    let blueprint = || {
+        ActorGroup::new().exec(|mut ctx| async move {
+            ctx.send(Started).await.unwrap();
+            while ctx.recv().await.is_some() {}
+        })
+    };
+
+    let mut first = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+    let mut second = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+    assert_msg!(first.recv().await, Started);
+    assert_msg!(second.recv().await, Started);
+
+    // Each journal contains only events of its own topology.
+    for proxy in [&first, &second] {
+        let config_updates = proxy
+            .journal()
+            .recent(usize::MAX)
+            .into_iter()
+            .filter(|e| e.meta.group == "subject" && e.kind == EventKind::ConfigUpdated)
+            .count();
+
+        assert_eq!(config_updates, 1);
+    }
+}
+
+fn is_status(event: &EventKind, kind: ActorStatusKind) -> bool {
+    matches!(event, EventKind::StatusChanged { status } if status.kind() == kind)
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::MessageId};
+
+#[message]
+struct Start;
+
+#[message]
+struct Inner(Option<MessageId>);
+
+#[message]
+struct Done {
+    start: Option<MessageId>,
+    inner: MessageId,
+    inner_parent: Option<MessageId>,
+}
+
+#[tokio::test]
+async fn it_links_messages_to_parents() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            let (message_id, parent_id) = (envelope.message_id(), envelope.parent_id());
+
+            msg!(match envelope {
+                Start => {
+                    assert_eq!(scope::message_id(), Some(message_id));
+                    let inner = Inner(scope::message_id());
+                    ctx.send_to(ctx.addr(), inner).await.unwrap();
+                }
+                Inner(start) => {
+                    let done = Done {
+                        start,
+                        inner: message_id,
+                        inner_parent: parent_id,
+                    };
+                    ctx.send(done).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    proxy.send(Start).await;
+
+    let envelope = proxy.recv().await;
+    let parent_id = envelope.parent_id();
+    let message_id = envelope.message_id();
+
+    msg!(match envelope {
+        Done {
+            start,
+            inner,
+            inner_parent,
+        } => {
+            assert!(start.is_some());
+            assert_eq!(inner_parent, start);
+            assert_eq!(parent_id, Some(inner));
+            assert_ne!(message_id, inner);
+        }
+    });
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "test-util")]
+
+use std::sync::{Arc, Mutex};
+
+use toml::toml;
+use tracing::{field::Visit, info, span, Event, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+use elfo::{messages::Terminate, prelude::*};
+
+#[message(ret = ())]
+struct Hello;
+
+/// Records the `message` field of the message span around each event.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Option<String>>>>);
+
+struct MessageName(String);
+
+impl Visit for MessageName {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = value.into();
+        }
+    }
+
+    fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() == "message" {
+            let mut name = MessageName(String::new());
+            attrs.record(&mut name);
+            ctx.span(id).unwrap().extensions_mut().insert(name);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != module_path!() {
+            return;
+        }
+
+        let name = ctx
+            .event_span(event)
+            .and_then(|span| span.extensions().get::<MessageName>().map(|n| n.0.clone()));
+        self.0.lock().unwrap().push(name);
+    }
+}
+
+async fn run(config: toml::Value) -> Vec<Option<String>> {
+    let recorder = Recorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Hello, token) => {
+                    info!("before yield");
+                    tokio::task::yield_now().await;
+                    info!("after yield");
+                    ctx.respond(token, ());
+                }
+            });
+        }
+
+        info!("finished");
+    });
+
+    let proxy = elfo::test::proxy(blueprint, config).await;
+    proxy.request(Hello).await;
+    proxy.send(Terminate::closing()).await;
+    proxy.finished().await;
+
+    let records = recorder.0.lock().unwrap();
+    records.clone()
+}
+
+#[tokio::test]
+async fn enabled() {
+    let config = toml! {
+        [system.tracing]
+        message_spans = true
+    };
+
+    let records = run(config.into()).await;
+    let hello = Some("Hello".to_string());
+    assert_eq!(records, vec![hello.clone(), hello, None]);
+}
+
+#[tokio::test]
+async fn disabled_by_default() {
+    let records = run(toml::Value::Table(Default::default())).await;
+    assert_eq!(records, vec![None, None, None]);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, prelude::*};
+
+#[message(ttl = "50ms")]
+struct Tick;
+
+#[message]
+struct Quote;
+
+#[message]
+struct Block;
+
+#[message(ret = ())]
+struct SendQuotes;
+
+#[message(ret = (u32, u32))]
+struct GetCounts;
+
+fn sample() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let (mut ticks, mut quotes) = (0, 0);
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Block => std::thread::sleep(Duration::from_millis(100)),
+                (SendQuotes, token) => {
+                    let ttl = Duration::from_millis(50);
+                    let addr = ctx.addr();
+                    ctx.send_to_with_ttl(addr, Quote, ttl).await.unwrap();
+                    ctx.send_to(addr, Quote).await.unwrap();
+                    std::thread::sleep(Duration::from_millis(100));
+                    ctx.respond(token, ());
+                }
+                Tick => ticks += 1,
+                Quote => quotes += 1,
+                (GetCounts, token) => ctx.respond(token, (ticks, quotes)),
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn ttl_by_attribute() {
+    let proxy = elfo::test::proxy(sample(), AnyConfig::default()).await;
+
+    // Expires while the actor is blocked.
+    proxy.send(Block).await;
+    proxy.send(Tick).await;
+    assert_eq!(proxy.request(GetCounts).await, (0, 0));
+
+    proxy.send(Tick).await;
+    assert_eq!(proxy.request(GetCounts).await, (1, 0));
+}
+
+#[tokio::test]
+async fn ttl_at_send_time() {
+    let proxy = elfo::test::proxy(sample(), AnyConfig::default()).await;
+
+    // Only the quote without TTL is received.
+    proxy.request(SendQuotes).await;
+    assert_eq!(proxy.request(GetCounts).await, (0, 1));
+}
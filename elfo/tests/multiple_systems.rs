@@ -0,0 +1,463 @@
+#![cfg(all(feature = "test-util", feature = "unstable"))]
+
+#[cfg(feature = "network")]
+use std::time::Duration;
+
+#[cfg(feature = "network")]
+use elfo::{errors::RequestError, topology::Outcome, ResponseToken};
+use elfo::{prelude::*, tracing::TraceId, NodeNo, SystemHandle, Topology};
+use elfo_core::config::AnyConfig;
+#[cfg(feature = "network")]
+use serde_json::{json, Value};
+
+#[message(ret = (Option<NodeNo>, Option<NodeNo>))]
+struct WhoAmI;
+
+#[cfg(feature = "network")]
+#[message(ret = Option<String>)]
+struct GetReceivedTime;
+
+#[cfg(feature = "network")]
+#[message(ret = u32)]
+struct Slow;
+
+/// Returns `None` if there is no pending `Slow` request.
+#[cfg(feature = "network")]
+#[message(ret = Option<bool>)]
+struct IsSlowCanceled;
+
+fn topology(node_no: u16) -> (Topology, SystemHandle) {
+    let topology = Topology::empty();
+    topology.set_node_no(NodeNo::from_bits(node_no).unwrap());
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let service = topology.local("service");
+
+    api.route_all_to(&service);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    service.mount(ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (WhoAmI, token) => {
+                    let trace_id = TraceId::generate();
+                    ctx.respond(token, (elfo::node::node_no(), trace_id.node_no()));
+                }
+            });
+        }
+    }));
+    let handle = api.handle();
+
+    (topology, handle)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_runs_several_nodes() {
+    let rt = tokio::runtime::Handle::current();
+    let (client, client_handle) = topology(1);
+    let (server, server_handle) = topology(2);
+
+    let mut client_guard = elfo::start_with_runtime(&rt, client).unwrap();
+    let mut server_guard = elfo::start_with_runtime(&rt, server).unwrap();
+    client_guard.started().await.unwrap();
+    server_guard.started().await.unwrap();
+
+    let expected = NodeNo::from_bits(1);
+    let response = client_handle.request(WhoAmI).await.unwrap();
+    assert_eq!(response, (expected, expected));
+
+    let expected = NodeNo::from_bits(2);
+    let response = server_handle.request(WhoAmI).await.unwrap();
+    assert_eq!(response, (expected, expected));
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+struct Node {
+    topology: Topology,
+    api: SystemHandle,
+    network: SystemHandle,
+}
+
+#[cfg(feature = "network")]
+fn node(node_no: u16, network: Value, handles: &[&str]) -> Node {
+    let topology = Topology::empty();
+    topology.set_node_no(NodeNo::from_bits(node_no).unwrap());
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let network_group = topology.local("system.network");
+    let api = topology.local("api");
+    let service = topology.local("service");
+    let remote_service = topology.remote("service");
+    let network_status = topology.local("network_status");
+    network_status.route_all_to(&network_group);
+
+    // Requests are handled by the service of another node.
+    let peer = NodeNo::from_bits(3 - node_no).unwrap();
+    api.route_to(&remote_service, move |_, _| Outcome::Unicast(peer));
+    api.pipeline("requests", &remote_service, ["proto"]);
+
+    let config = json!({ "system": { "network": network } });
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    network_group.mount(elfo::batteries::network::new(&topology));
+    let service_group = ActorGroup::new().handles(handles.iter().copied());
+    service.mount(service_group.exec(|mut ctx| async move {
+        let mut slow: Option<ResponseToken<Slow>> = None;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (WhoAmI, token) => {
+                    let trace_id = TraceId::generate();
+                    ctx.respond(token, (elfo::node::node_no(), trace_id.node_no()));
+                }
+                (Slow, token) => slow = Some(token),
+                (IsSlowCanceled, token) => {
+                    ctx.respond(token, slow.as_ref().map(|slow| slow.is_canceled()));
+                }
+                (GetReceivedTime, token) => {
+                    let baggage = elfo::scope::baggage();
+                    let time = baggage.get(elfo::batteries::network::RECEIVED_TIME_KEY);
+                    ctx.respond(token, time.map(String::from));
+                }
+            });
+        }
+    }));
+
+    Node {
+        api: api.handle(),
+        network: network_status.handle(),
+        topology,
+    }
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_connects_nodes_over_mem_transport() {
+    let rt = tokio::runtime::Handle::current();
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-client"],
+            "discovery": { "predefined": ["mem://multiple-systems-server"] },
+        }),
+        &["proto"],
+    );
+    let server = node(
+        2,
+        json!({ "listen": ["mem://multiple-systems-server"] }),
+        &["proto"],
+    );
+    let (client_handle, server_handle) = (client.api, server.api);
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    // Wait for the connection to be established.
+    let request = |handle: SystemHandle| async move {
+        loop {
+            match handle.request(WhoAmI).await {
+                Ok(response) => return response,
+                Err(RequestError::Failed | RequestError::NotSent(_)) => {
+                    tokio::time::sleep(Duration::from_millis(10)).await
+                }
+                Err(err) => panic!("unexpected error: {err:?}"),
+            }
+        }
+    };
+
+    let response = tokio::time::timeout(Duration::from_secs(10), request(client_handle))
+        .await
+        .expect("nodes aren't connected");
+    let expected = NodeNo::from_bits(2);
+    assert_eq!(response, (expected, expected));
+
+    let response = tokio::time::timeout(Duration::from_secs(10), request(server_handle))
+        .await
+        .expect("nodes aren't connected");
+    let expected = NodeNo::from_bits(1);
+    assert_eq!(response, (expected, expected));
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_rejects_nodes_violating_pipelines() {
+    let rt = tokio::runtime::Handle::current();
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-bad-client"],
+            "discovery": { "predefined": ["mem://multiple-systems-bad-server"] },
+        }),
+        &["proto"],
+    );
+    // The server's service doesn't handle the client's pipeline.
+    let server = node(
+        2,
+        json!({ "listen": ["mem://multiple-systems-bad-server"] }),
+        &["another"],
+    );
+    let client_handle = client.api;
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    let request = async {
+        loop {
+            if let Ok(response) = client_handle.request(WhoAmI).await {
+                return response;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    let res = tokio::time::timeout(Duration::from_secs(1), request).await;
+    assert!(res.is_err(), "nodes are connected");
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_advertises_transports() {
+    use elfo::batteries::network::status::GetNodeMap;
+
+    let rt = tokio::runtime::Handle::current();
+    // The server dials nobody, so it learns the client's transport only from
+    // the advertised ones.
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-adv-client"],
+            "advertise_addr": ["tcp://10.0.0.1:4242", "mem://multiple-systems-adv-public"],
+            "discovery": { "predefined": ["mem://multiple-systems-adv-server"] },
+        }),
+        &["proto"],
+    );
+    let server = node(
+        2,
+        json!({ "listen": ["mem://multiple-systems-adv-server"] }),
+        &["proto"],
+    );
+    let server_network = server.network;
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    let client_no = NodeNo::from_bits(1).unwrap();
+    let route = async {
+        loop {
+            let node_map = server_network.request(GetNodeMap).await.unwrap();
+            let client = node_map.nodes.into_iter().find(|n| n.node_no == client_no);
+            if let Some(route) = client.and_then(|n| n.route) {
+                return route;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    let route = tokio::time::timeout(Duration::from_secs(10), route)
+        .await
+        .expect("nodes aren't connected");
+
+    // The advertised transport of the same kind as the observed one is used.
+    assert_eq!(route.transport, "mem://multiple-systems-adv-public");
+    assert_eq!(route.via, None);
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_annotates_received_time() {
+    let rt = tokio::runtime::Handle::current();
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-time-client"],
+            "discovery": { "predefined": ["mem://multiple-systems-time-server"] },
+        }),
+        &["proto"],
+    );
+    let server = node(
+        2,
+        json!({
+            "listen": ["mem://multiple-systems-time-server"],
+            "ping_interval": "10ms",
+            "annotate_received_time": true,
+        }),
+        &["proto"],
+    );
+    let client_handle = client.api;
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    // The annotation appears once the clock offset is estimated by pings.
+    let received_time = async {
+        loop {
+            if let Ok(Some(time)) = client_handle.request(GetReceivedTime).await {
+                return time;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    let received_time = tokio::time::timeout(Duration::from_secs(10), received_time)
+        .await
+        .expect("received time isn't annotated");
+
+    // Both nodes share the clock, so the offset is negligible.
+    let received_time =
+        std::time::UNIX_EPOCH + Duration::from_nanos(received_time.parse().unwrap());
+    let diff = match std::time::SystemTime::now().duration_since(received_time) {
+        Ok(diff) => diff,
+        Err(err) => err.duration(),
+    };
+    assert!(diff < Duration::from_secs(1), "{diff:?}");
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_cancels_remote_requests() {
+    let rt = tokio::runtime::Handle::current();
+    // Canceled requests are collected on pings.
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-cancel-client"],
+            "ping_interval": "10ms",
+            "discovery": { "predefined": ["mem://multiple-systems-cancel-server"] },
+        }),
+        &["proto"],
+    );
+    let server = node(
+        2,
+        json!({ "listen": ["mem://multiple-systems-cancel-server"] }),
+        &["proto"],
+    );
+    let client_handle = client.api;
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    // Wait for the connection to be established.
+    let connecting = async {
+        while client_handle.request(WhoAmI).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), connecting)
+        .await
+        .expect("nodes aren't connected");
+
+    let wait_for = |expected: bool| {
+        let handle = client_handle.clone();
+        let waiting = async move {
+            while handle.request(IsSlowCanceled).await.ok().flatten() != Some(expected) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), waiting)
+    };
+
+    // The requester is waiting.
+    tokio::select! {
+        _ = client_handle.request(Slow) => panic!("the request is responded"),
+        result = wait_for(false) => result.expect("the request isn't received"),
+    }
+
+    // The requester has stopped waiting.
+    wait_for(true).await.expect("the request isn't canceled");
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
+
+#[cfg(feature = "network")]
+#[tokio::test(flavor = "multi_thread")]
+async fn it_closes_connections_denied_by_reconfiguration() {
+    use elfo::{batteries::network::status::GetNetworkStatus, messages::UpdateConfig};
+
+    let rt = tokio::runtime::Handle::current();
+    let client = node(
+        1,
+        json!({
+            "listen": ["mem://multiple-systems-deny-client"],
+            "discovery": { "predefined": ["mem://multiple-systems-deny-server"] },
+        }),
+        &["proto"],
+    );
+    let server = node(
+        2,
+        json!({ "listen": ["mem://multiple-systems-deny-server"] }),
+        &["proto"],
+    );
+    let (client_handle, server_network) = (client.api, server.network);
+
+    let mut server_guard = elfo::start_with_runtime(&rt, server.topology).unwrap();
+    let mut client_guard = elfo::start_with_runtime(&rt, client.topology).unwrap();
+    server_guard.started().await.unwrap();
+    client_guard.started().await.unwrap();
+
+    let request = async {
+        loop {
+            if let Ok(response) = client_handle.request(WhoAmI).await {
+                return response;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    tokio::time::timeout(Duration::from_secs(10), request)
+        .await
+        .expect("nodes aren't connected");
+
+    let config = json!({
+        "listen": ["mem://multiple-systems-deny-server"],
+        "peers": { "deny": [{ "node_no": 1 }] },
+    });
+    let config = serde::Deserialize::deserialize(config).unwrap();
+    server_network
+        .request(UpdateConfig::new(config))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let closed = async {
+        loop {
+            let status = server_network.request(GetNetworkStatus).await.unwrap();
+            if status.connections.is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    tokio::time::timeout(Duration::from_secs(10), closed)
+        .await
+        .expect("connections aren't closed");
+
+    client_guard.shutdown().await.unwrap();
+    server_guard.shutdown().await.unwrap();
+}
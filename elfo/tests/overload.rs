@@ -0,0 +1,178 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use toml::toml;
+use tracing::{field::Visit, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+use elfo::{
+    messages::{ActorStatusReport, SubscribeToActorStatuses},
+    prelude::*,
+    test::Proxy,
+    ActorStatus, ActorStatusKind,
+};
+
+#[message]
+struct Block;
+
+#[message]
+struct Ping;
+
+#[message(priority = "low")]
+struct Tick;
+
+#[message(ret = u32)]
+struct GetTicks;
+
+async fn next_status(proxy: &mut Proxy) -> ActorStatus {
+    let envelope = proxy.try_recv().await.expect("no status");
+    msg!(match envelope {
+        ActorStatusReport { status, .. } => status,
+        _ => unreachable!(),
+    })
+}
+
+#[tokio::test]
+async fn waiting_time() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Block => std::thread::sleep(Duration::from_millis(100)),
+                _ => {}
+            });
+        }
+    });
+
+    let config = toml! {
+        [system.overload]
+        max_waiting_time = "50ms"
+    };
+    let mut proxy = elfo::test::proxy(blueprint, config).await;
+
+    // Start the actor and subscribe to its statuses.
+    proxy.send(Ping).await;
+    proxy.send(SubscribeToActorStatuses::default()).await;
+    proxy.sync().await;
+
+    assert_eq!(
+        next_status(&mut proxy).await.kind(),
+        ActorStatusKind::Normal
+    );
+
+    // `Ping` waits for `Block`.
+    proxy.send(Block).await;
+    proxy.send(Ping).await;
+    proxy.sync().await;
+
+    let status = next_status(&mut proxy).await;
+    assert_eq!(status.kind(), ActorStatusKind::Overloaded);
+    assert!(status
+        .details()
+        .unwrap()
+        .starts_with("a message has waited"));
+
+    // The previous status is restored.
+    proxy.send(Ping).await;
+    proxy.sync().await;
+
+    let status = next_status(&mut proxy).await;
+    assert_eq!(status.kind(), ActorStatusKind::Normal);
+    assert_eq!(status.details(), None);
+}
+
+#[tokio::test]
+async fn shedding() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let mut ticks = 0;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Block => std::thread::sleep(Duration::from_millis(100)),
+                Tick => ticks += 1,
+                (GetTicks, token) => ctx.respond(token, ticks),
+                _ => {}
+            });
+        }
+    });
+
+    let config = toml! {
+        [system.overload]
+        max_waiting_time = "50ms"
+        shedding = true
+    };
+    let mut proxy = elfo::test::proxy(blueprint, config).await;
+
+    proxy.send(Tick).await;
+    assert_eq!(proxy.request(GetTicks).await, 1);
+
+    // Become overloaded.
+    proxy.send(Block).await;
+    proxy.send(Ping).await;
+    proxy.sync().await;
+
+    // Low-priority messages are shed.
+    proxy.send(Tick).await;
+    proxy.send(Tick).await;
+    assert_eq!(proxy.request(GetTicks).await, 1);
+
+    // But only until recovering.
+    proxy.send(Tick).await;
+    assert_eq!(proxy.request(GetTicks).await, 2);
+}
+
+/// Records the `reason` field of warnings.
+#[derive(Clone, Default)]
+struct Reasons(Arc<Mutex<Vec<String>>>);
+
+struct Reason(Option<String>);
+
+impl Visit for Reason {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "reason" {
+            self.0 = Some(value.into());
+        }
+    }
+
+    fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+}
+
+impl<S: Subscriber> Layer<S> for Reasons {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            let mut reason = Reason(None);
+            event.record(&mut reason);
+            self.0.lock().unwrap().extend(reason.0);
+        }
+    }
+}
+
+#[tokio::test]
+async fn startup_delay() {
+    let reasons = Reasons::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(reasons.clone()));
+
+    let blueprint =
+        ActorGroup::new().exec(|mut ctx| async move { while ctx.recv().await.is_some() {} });
+
+    // Any actor is started later than in a nanosecond.
+    let config = toml! {
+        [system.overload]
+        max_startup_delay = "1ns"
+    };
+    let mut proxy = elfo::test::proxy(blueprint, config).await;
+
+    proxy.send(Ping).await;
+    proxy.sync().await;
+
+    assert!(reasons
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|r| r == "runtime_saturated"));
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use toml::toml;
+use tracing::{field::Visit, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+use elfo::{_priv::do_start, prelude::*, Topology};
+
+#[message(ret = u32)]
+struct Ask(u32);
+
+/// Records the `pending` field of warnings.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<u64>>>);
+
+struct Pending(Option<u64>);
+
+impl Visit for Pending {
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        if field.name() == "pending" {
+            self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::WARN {
+            return;
+        }
+
+        let mut pending = Pending(None);
+        event.record(&mut pending);
+        self.0.lock().unwrap().extend(pending.0);
+    }
+}
+
+#[tokio::test]
+async fn warns_once_threshold_is_crossed() {
+    let recorder = Recorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let requester_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            let ask = |no| ctx.request(Ask(no)).resolve();
+            let (a, b, c, d) = futures::join!(ask(1), ask(2), ask(3), ask(4));
+            tx.send([a.ok(), b.ok(), c.ok(), d.ok()]).unwrap();
+        }
+    });
+
+    // Responds once all requests are pending.
+    let responder_blueprint = ActorGroup::new().exec(move |mut ctx| async move {
+        let mut tokens = Vec::new();
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Ask(no), token) => tokens.push((no, token)),
+            });
+
+            if tokens.len() == 4 {
+                for (no, token) in tokens.drain(..) {
+                    ctx.respond(token, no * 10);
+                }
+            }
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let requesters = topology.local("requesters");
+    let responders = topology.local("responders");
+
+    requesters.route_all_to(&responders);
+
+    let config = toml! {
+        [requesters]
+        system.requests.pending_warn_threshold = 2
+    };
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    requesters.mount(requester_blueprint);
+    responders.mount(responder_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let responses = tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("requests are stuck")
+        .unwrap();
+
+    assert_eq!(responses, [Some(10), Some(20), Some(30), Some(40)]);
+    assert_eq!(*recorder.0.lock().unwrap(), vec![3]);
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use toml::toml;
+
+use elfo::{
+    _priv::do_start,
+    batteries::pinger::protocol::{GroupRecovered, GroupUnhealthy},
+    prelude::*,
+    Topology,
+};
+
+#[tokio::test]
+async fn reports_unhealthy_groups() {
+    let (tx, rx) = futures_intrusive::channel::shared::unbuffered_channel();
+
+    let stuck_blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        // Don't respond to pings for a while.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        while ctx.recv().await.is_some() {}
+    });
+
+    let observer_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    GroupUnhealthy { group, .. } => tx.send((group, false)).await.unwrap(),
+                    GroupRecovered { group, .. } => tx.send((group, true)).await.unwrap(),
+                });
+            }
+        }
+    });
+
+    let config = toml! {
+        [system.pingers]
+        ping_interval = "30ms"
+        warn_threshold = "50ms"
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let pingers = topology.local("system.pingers");
+    let stuck = topology.local("stuck");
+    let observers = topology.local("observers");
+
+    pingers.route_all_to(&observers);
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    pingers.mount(elfo::batteries::pinger::new(&topology));
+    stuck.mount(stuck_blueprint);
+    observers.mount(observer_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let receive = || async {
+        tokio::time::timeout(Duration::from_secs(5), rx.receive())
+            .await
+            .expect("no events")
+            .unwrap()
+    };
+
+    assert_eq!(receive().await, ("stuck".into(), false));
+    assert_eq!(receive().await, ("stuck".into(), true));
+}
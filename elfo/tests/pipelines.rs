@@ -0,0 +1,88 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{_priv::do_start, prelude::*, Topology};
+
+fn topology(handles: Option<&[&str]>) -> Topology {
+    let topology = Topology::empty();
+    let producers = topology.local("producers");
+    let consumers = topology.local("consumers");
+
+    producers.route_all_to(&consumers);
+    producers.pipeline("events", &consumers, ["proto-a", "proto-b"]);
+
+    let mut consumers_group = ActorGroup::new();
+    if let Some(protocols) = handles {
+        consumers_group = consumers_group.handles(protocols.iter().copied());
+    }
+
+    producers.mount(ActorGroup::new().exec(|_| async {}));
+    consumers.mount(consumers_group.exec(|_| async {}));
+    topology
+}
+
+async fn start(topology: Topology) -> Result<(), String> {
+    do_start(topology, true, |_, _| async {})
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tokio::test]
+async fn it_starts_if_protocols_are_handled() {
+    start(topology(Some(&["proto-b", "proto-a", "proto-c"])))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn it_fails_on_unhandled_protocols() {
+    let err = start(topology(Some(&["proto-a"]))).await.unwrap_err();
+    assert_eq!(
+        err,
+        "failed to start: 1. the pipeline `events` carries the `proto-b` protocol, \
+         but the group doesn't handle it (consumers); "
+    );
+}
+
+#[tokio::test]
+async fn it_fails_on_undeclared_protocols() {
+    let err = start(topology(None)).await.unwrap_err();
+    assert_eq!(
+        err,
+        "failed to start: 1. the group is a recipient of the pipeline `events`, \
+         but doesn't declare handled protocols (consumers); "
+    );
+}
+
+#[cfg(all(feature = "network", feature = "unstable"))]
+#[test]
+fn it_checks_remote_pipelines() {
+    let topology = Topology::empty();
+    let producers = topology.local("producers");
+    let consumers = topology.remote("consumers");
+    let _others = topology.remote("others");
+
+    producers.pipeline("events", &consumers, ["proto-a", "proto-b"]);
+
+    // Remote pipelines don't prevent the system from starting.
+    producers.mount(ActorGroup::new().exec(|_| async {}));
+
+    let to_strings = |protocols: &[&str]| protocols.iter().map(|p| p.to_string()).collect();
+    let check = |group: &str, handled: Option<Vec<String>>| {
+        topology
+            .check_remote_pipelines(group, handled.as_deref())
+            .into_iter()
+            .map(|err| err.reason)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(check("consumers", Some(to_strings(&["proto-b", "proto-a"]))).is_empty());
+    assert_eq!(
+        check("consumers", Some(to_strings(&["proto-a"]))),
+        ["the pipeline `events` carries the `proto-b` protocol, but the group doesn't handle it"]
+    );
+    assert_eq!(
+        check("consumers", None),
+        ["the group is a recipient of the pipeline `events`, but doesn't declare handled protocols"]
+    );
+    assert!(check("others", None).is_empty());
+}
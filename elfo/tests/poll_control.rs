@@ -0,0 +1,52 @@
+#![cfg(feature = "test-util")]
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    messages::{Terminate, UpdateConfig},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+struct Config {
+    value: u32,
+}
+
+#[message]
+struct Produced(u32);
+
+#[message]
+struct Finished;
+
+#[tokio::test]
+async fn source_actor() {
+    let blueprint = ActorGroup::new()
+        .config::<Config>()
+        .exec(|mut ctx| async move {
+            let mut last = None;
+
+            while ctx.poll_control().await {
+                let value = ctx.config().value;
+                if last != Some(value) {
+                    ctx.send(Produced(value)).await.unwrap();
+                    last = Some(value);
+                }
+
+                tokio::task::yield_now().await;
+            }
+
+            ctx.send(Finished).await.unwrap();
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, toml! { value = 1 }).await;
+    assert_msg!(proxy.recv().await, Produced(1));
+
+    let config = AnyConfig::deserialize(toml! { value = 2 }).unwrap();
+    proxy.send(UpdateConfig::new(config)).await;
+    assert_msg!(proxy.recv().await, Produced(2));
+
+    proxy.send(Terminate::default()).await;
+    assert_msg!(proxy.recv().await, Finished);
+}
@@ -10,10 +10,11 @@ fn parse<A: Message + PartialEq, B: Message + PartialEq>(input: A, expected: B)
     let mut buf = Vec::new();
     let input = input.upcast();
     input.write_msgpack(&mut buf, 512)?;
-    let actual = AnyMessage::read_msgpack(&buf, expected.protocol(), expected.name())?
-        .ok_or_else(|| anyhow!("no such message"))?
-        .downcast::<B>()
-        .map_err(|_| anyhow!("cannot downcast"))?;
+    let actual =
+        AnyMessage::read_msgpack(&buf, expected.protocol(), expected.name(), input.version())?
+            .ok_or_else(|| anyhow!("no such message"))?
+            .downcast::<B>()
+            .map_err(|_| anyhow!("cannot downcast"))?;
 
     ensure!(actual == expected);
     Ok(())
@@ -319,3 +320,30 @@ fn enum_untagged() {
     ensure_parsable(SE0 { a: 42 }, SE1 { a: A::Num(42) });
     ensure_parsable(SE1 { a: A::Num(42) }, SE0 { a: 42 });
 }
+
+#[test]
+fn struct_versioned() {
+    #[message(part, version = 1)]
+    struct V1 {
+        a: u32,
+    }
+
+    #[message(version = 2, migrates_from = V1)]
+    #[derive(PartialEq, Eq)]
+    struct V2 {
+        a: u32,
+        b: u32,
+    }
+
+    impl From<V1> for V2 {
+        fn from(old: V1) -> Self {
+            Self { a: old.a, b: 1 }
+        }
+    }
+
+    // The required field is provided by the migration.
+    ensure_parsable(S0 { a: 42 }, V2 { a: 42, b: 1 });
+    ensure_parsable(V2 { a: 42, b: 2 }, V2 { a: 42, b: 2 });
+    // Newer versions cannot be parsed by older nodes.
+    ensure_unparsable(V2 { a: 42, b: 2 }, S0 { a: 42 });
+}
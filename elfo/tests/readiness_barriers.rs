@@ -0,0 +1,231 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use elfo::{
+    _priv::do_start,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+    Topology,
+};
+use elfo_core::config::AnyConfig;
+use futures_intrusive::channel::shared::OneshotSender;
+
+#[message]
+struct Event;
+
+async fn consume<K>(
+    mut ctx: Context<(), K>,
+    tx: Arc<OneshotSender<bool>>,
+    is_storage_ready: Arc<AtomicBool>,
+) {
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            Event => {
+                let _ = tx.send(is_storage_ready.load(Ordering::SeqCst));
+            }
+        });
+    }
+}
+
+async fn run(storage_init_time: Duration, timeout: Duration, is_keyed: bool) -> bool {
+    let is_storage_ready = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let producer_blueprint = ActorGroup::new().exec(move |ctx| async move {
+        ctx.send(Event).await.unwrap();
+    });
+
+    let is_ready = is_storage_ready.clone();
+    let storage_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let is_ready = is_ready.clone();
+
+        async move {
+            tokio::time::sleep(storage_init_time).await;
+            is_ready.store(true, Ordering::SeqCst);
+            while ctx.recv().await.is_some() {}
+        }
+    });
+
+    // Keyed actors are spawned by the first released message.
+    let consumer_blueprint = if is_keyed {
+        ActorGroup::new()
+            .router(MapRouter::new(|envelope| {
+                msg!(match envelope {
+                    Event => Outcome::Unicast(0),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(move |ctx| consume(ctx, tx.clone(), is_storage_ready.clone()))
+    } else {
+        ActorGroup::new().exec(move |ctx| consume(ctx, tx.clone(), is_storage_ready.clone()))
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let producer = topology.local("producer");
+    let storage = topology.local("storage");
+    let consumer = topology.local("consumer");
+
+    producer.route_all_to(&consumer);
+    consumer.wait_for_ready(&storage, timeout);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    producer.mount(producer_blueprint);
+    storage.mount(storage_blueprint);
+    consumer.mount(consumer_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    rx.receive().await.unwrap()
+}
+
+#[tokio::test]
+async fn held_until_ready() {
+    let is_storage_ready = run(Duration::from_millis(300), Duration::from_secs(10), false).await;
+    assert!(is_storage_ready);
+}
+
+#[tokio::test]
+async fn held_until_ready_keyed() {
+    let is_storage_ready = run(Duration::from_millis(300), Duration::from_secs(10), true).await;
+    assert!(is_storage_ready);
+}
+
+#[tokio::test]
+async fn released_by_timeout() {
+    let is_storage_ready = run(Duration::from_secs(10), Duration::from_millis(100), false).await;
+    assert!(!is_storage_ready);
+}
+
+#[tokio::test]
+async fn rejected_over_limit() {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let producer_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            // Wait for the config of the consumer.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            let results = vec![
+                ctx.try_send(Event).map_err(|err| err.to_string()),
+                ctx.try_send(Event).map_err(|err| err.to_string()),
+                ctx.try_send(Event).map_err(|err| err.to_string()),
+                ctx.send_timeout(Event, Duration::from_millis(10))
+                    .await
+                    .map_err(|err| err.to_string()),
+            ];
+            let _ = tx.send(results);
+        }
+    });
+
+    let storage_blueprint = ActorGroup::new().exec(|_| async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let producer = topology.local("producer");
+    let storage = topology.local("storage");
+    let consumer = topology.local("consumer");
+
+    producer.route_all_to(&consumer);
+    consumer.wait_for_ready(&storage, Duration::from_secs(10));
+
+    let config = toml::toml! {
+        [consumer.system.readiness]
+        max_held = 2
+    };
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    producer.mount(producer_blueprint);
+    storage.mount(storage_blueprint);
+    consumer.mount(
+        ActorGroup::new().exec(|mut ctx| async move { while ctx.recv().await.is_some() {} }),
+    );
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let results = rx.receive().await.unwrap();
+    let err = |reason: &str| Err(reason.to_string());
+    assert_eq!(
+        results,
+        [Ok(()), Ok(()), err("mailbox full"), err("mailbox full")]
+    );
+}
+
+#[tokio::test]
+async fn send_waits_for_room() {
+    let is_storage_ready = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let is_ready = is_storage_ready.clone();
+    let producer_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+        let is_ready = is_ready.clone();
+
+        async move {
+            // Wait for the config of the consumer.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            ctx.try_send(Event).unwrap();
+            let result = ctx.send(Event).await.map_err(|err| err.to_string());
+            let _ = tx.send((result, is_ready.load(Ordering::SeqCst)));
+        }
+    });
+
+    let storage_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let is_ready = is_storage_ready.clone();
+
+        async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            is_ready.store(true, Ordering::SeqCst);
+            while ctx.recv().await.is_some() {}
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let producer = topology.local("producer");
+    let storage = topology.local("storage");
+    let consumer = topology.local("consumer");
+
+    producer.route_all_to(&consumer);
+    consumer.wait_for_ready(&storage, Duration::from_secs(10));
+
+    let config = toml::toml! {
+        [consumer.system.readiness]
+        max_held = 1
+    };
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    producer.mount(producer_blueprint);
+    storage.mount(storage_blueprint);
+    consumer.mount(
+        ActorGroup::new().exec(|mut ctx| async move { while ctx.recv().await.is_some() {} }),
+    );
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    // `send()` waits until held messages are released.
+    let (result, is_storage_ready) = rx.receive().await.unwrap();
+    assert_eq!(result, Ok(()));
+    assert!(is_storage_ready);
+}
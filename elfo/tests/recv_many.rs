@@ -0,0 +1,114 @@
+#![cfg(feature = "test-util")]
+
+use std::convert::TryFrom;
+
+use elfo::{config::AnyConfig, messages::Terminate, prelude::*, scope, tracing::TraceId};
+
+#[message]
+struct Num(u32);
+
+#[message(ret = Vec<u32>)]
+struct GetBatches;
+
+#[message(ret = u32)]
+struct GetSum;
+
+#[message]
+struct Traced;
+
+#[message]
+struct Handled(Vec<TraceId>);
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut batch = Vec::new();
+        let mut batches = Vec::new();
+        let mut sum = 0;
+
+        while ctx.recv_many(&mut batch, 4).await > 0 {
+            assert!(batch.len() <= 4);
+            batches.push(batch.len() as u32);
+
+            for envelope in batch.drain(..) {
+                msg!(match envelope {
+                    Num(n) => sum += n,
+                    (GetBatches, token) => ctx.respond(token, batches.clone()),
+                    (GetSum, token) => ctx.respond(token, sum),
+                    Terminate => return,
+                });
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_receives_in_batches() {
+    let proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    for n in 1..=10 {
+        proxy.send(Num(n)).await;
+    }
+
+    assert_eq!(proxy.request(GetSum).await, 55);
+
+    let batches = proxy.request(GetBatches).await;
+    assert!(batches.iter().all(|&len| len > 0 && len <= 4));
+    assert!(batches.iter().any(|&len| len > 1));
+}
+
+#[tokio::test]
+async fn it_returns_zero_once_closed() {
+    let proxy = elfo::test::proxy(
+        ActorGroup::new().exec(|mut ctx| async move {
+            let mut batch = Vec::new();
+            while ctx.recv_many(&mut batch, 16).await > 0 {
+                batch.clear();
+            }
+        }),
+        AnyConfig::default(),
+    )
+    .await;
+
+    proxy.send(Terminate::closing()).await;
+    proxy.finished().await;
+}
+
+#[tokio::test]
+async fn it_restores_scopes_of_batched_envelopes() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        // Sent in different traces to be received in one batch.
+        for n in 1..=3 {
+            scope::set_trace_id(TraceId::try_from(n).unwrap());
+            ctx.send_to(ctx.addr(), Traced).await.unwrap();
+        }
+
+        let mut batch = Vec::new();
+        assert_eq!(ctx.recv_many(&mut batch, 4).await, 3);
+
+        // The batch isn't attributed to any of its envelopes.
+        let batch_trace_id = scope::trace_id();
+        assert!(batch.iter().all(|e| e.trace_id() != batch_trace_id));
+        assert_eq!(scope::message_id(), None);
+
+        let mut trace_ids = Vec::new();
+        for envelope in batch.drain(..) {
+            ctx.set_scope(&envelope);
+            assert_eq!(scope::message_id(), Some(envelope.message_id()));
+            trace_ids.push(scope::trace_id());
+        }
+
+        // Sent in the trace of the last handled envelope.
+        ctx.send(Handled(trace_ids)).await.unwrap();
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let envelope = proxy.recv().await;
+    let expected = (1..=3)
+        .map(|n| TraceId::try_from(n).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(envelope.trace_id(), expected[2]);
+    msg!(match envelope {
+        Handled(trace_ids) => assert_eq!(trace_ids, expected),
+    });
+}
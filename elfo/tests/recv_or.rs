@@ -0,0 +1,39 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, messages::Terminate, prelude::*, RecvOr};
+
+#[message(ret = u32)]
+struct GetTicks;
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut ticks = 0;
+
+        while let Some(input) = ctx
+            .recv_or(tokio::time::sleep(Duration::from_secs(1)))
+            .await
+        {
+            match input {
+                RecvOr::Envelope(envelope) => msg!(match envelope {
+                    (GetTicks, token) => ctx.respond(token, ticks),
+                }),
+                RecvOr::Future(()) => ticks += 1,
+            }
+        }
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_races_mailbox_and_future() {
+    let proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+    assert_eq!(proxy.request(GetTicks).await, 0);
+
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+    assert_eq!(proxy.request(GetTicks).await, 3);
+
+    // The mailbox is closed, the loop is finished.
+    proxy.send(Terminate::closing()).await;
+    proxy.finished().await;
+}
@@ -0,0 +1,142 @@
+#![cfg(all(feature = "test-util", feature = "network"))]
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use elfo::{
+    _priv::do_start,
+    batteries::network::replica::{
+        ReplicaCache, ReplicaOwner, ReplicaSubscribe, ReplicaUpdate, Replicated,
+    },
+    prelude::*,
+    Topology,
+};
+use elfo_core::config::AnyConfig;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Rates(BTreeMap<String, u64>);
+
+#[derive(Serialize, Deserialize)]
+struct RateChanged(String, u64);
+
+impl Replicated for Rates {
+    type Diff = RateChanged;
+
+    fn apply(&mut self, diff: RateChanged) {
+        self.0.insert(diff.0, diff.1);
+    }
+}
+
+#[message]
+struct SetRate(String, u64);
+
+#[message(ret = Option<u64>)]
+struct GetRate(String);
+
+fn owner() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut owner = ReplicaOwner::new("rates");
+        let mut rates = Rates::default();
+        rates.apply(RateChanged("a".into(), 1));
+
+        while let Some(envelope) = ctx.recv().await {
+            let sender = envelope.sender();
+            msg!(match envelope {
+                (ReplicaSubscribe, token) => owner.subscribe(&ctx, sender, token, &rates),
+                SetRate(pair, rate) => {
+                    let diff = RateChanged(pair, rate);
+                    owner.publish(&ctx, &diff);
+                    rates.apply(diff);
+                }
+            });
+        }
+    })
+}
+
+fn subscriber() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut cache = ReplicaCache::<Rates>::new("rates");
+        assert!(cache.staleness().is_none());
+        cache.subscribe(&ctx).await.unwrap();
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                update @ ReplicaUpdate => {
+                    cache.handle(&ctx, &update).await.unwrap();
+                    assert!(cache.staleness().is_some());
+                }
+                (GetRate(pair), token) => {
+                    let rate = cache.get().and_then(|rates| rates.0.get(&pair).copied());
+                    ctx.respond(token, rate);
+                }
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_works() {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    let client_blueprint = ActorGroup::new().exec(move |ctx| {
+        let tx = tx.clone();
+
+        async move {
+            // The initial state.
+            let initial = loop {
+                if let Ok(Some(rate)) = ctx.request(GetRate("a".into())).resolve().await {
+                    break rate;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            };
+
+            ctx.send(SetRate("b".into(), 2)).await.unwrap();
+            let updated = loop {
+                if let Ok(Some(rate)) = ctx.request(GetRate("b".into())).resolve().await {
+                    break rate;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            };
+
+            tx.send((initial, updated)).unwrap();
+        }
+    });
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let clients = topology.local("clients");
+    let owners = topology.local("owners");
+    let subscribers = topology.local("subscribers");
+
+    clients.route_to(&subscribers, |e| {
+        msg!(match e {
+            GetRate => true,
+            _ => false,
+        })
+    });
+    clients.route_to(&owners, |e| {
+        msg!(match e {
+            SetRate => true,
+            _ => false,
+        })
+    });
+    subscribers.route_all_to(&owners);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    clients.mount(client_blueprint);
+    owners.mount(owner());
+    subscribers.mount(subscriber());
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let result = tokio::time::timeout(Duration::from_secs(5), rx.receive())
+        .await
+        .expect("the replica is stuck")
+        .unwrap();
+
+    assert_eq!(result, (1, 2));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, prelude::*, ResponseToken};
+
+#[message(ret = u32)]
+struct Slow;
+
+#[message]
+struct Check;
+
+#[message]
+struct Respond;
+
+#[message]
+#[derive(PartialEq)]
+struct IsCanceled(bool);
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut pending: Option<ResponseToken<Slow>> = None;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Slow, token) => pending = Some(token),
+                Check => {
+                    let is_canceled = pending.as_ref().unwrap().is_canceled();
+                    ctx.send(IsCanceled(is_canceled)).await.unwrap();
+                }
+                Respond => ctx.respond(pending.take().unwrap(), 42),
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_cancels_requests() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+    let requester = proxy.subproxy().await;
+
+    // The requester is waiting.
+    let checking = async {
+        proxy.send(Check).await;
+        assert_msg_eq!(proxy.recv().await, IsCanceled(false));
+        proxy.send(Respond).await;
+    };
+    let (response, ()) = tokio::join!(requester.request(Slow), checking);
+    assert_eq!(response, 42);
+
+    // The requester has stopped waiting.
+    let result = tokio::time::timeout(Duration::from_millis(10), requester.request(Slow)).await;
+    assert!(result.is_err());
+    proxy.send(Check).await;
+    assert_msg_eq!(proxy.recv().await, IsCanceled(true));
+    proxy.send(Respond).await;
+}
@@ -3,10 +3,10 @@
 use std::sync::Arc;
 
 use elfo::{
+    _priv::do_start,
     prelude::*,
     routers::{MapRouter, Outcome},
     Topology,
-    _priv::do_start,
 };
 use elfo_core::config::AnyConfig;
 use tracing::info;
@@ -57,7 +57,7 @@ async fn test_stealing_request_routing() {
                 _ => Outcome::Default,
             })
         }))
-        .exec(move |_ctx| async move {
+        .exec::<_, _, ()>(move |_ctx| async move {
             panic!("thief should not be started");
         });
 
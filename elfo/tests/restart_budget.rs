@@ -0,0 +1,93 @@
+#![cfg(feature = "test-util")]
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use elfo::{
+    _priv::do_start,
+    config::AnyConfig,
+    messages::{RestartStorm, StartGroup},
+    prelude::*,
+    Envelope, RestartBudget, RestartPolicy, Topology,
+};
+
+#[tokio::test(start_paused = true)]
+async fn storm_switches_to_manual_start() {
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let tx = Arc::new(tx);
+
+    // Terminates immediately, so it's restarted again and again.
+    let crasher_blueprint = ActorGroup::new()
+        .restart_policy(RestartPolicy::always())
+        .exec(|_ctx| async move {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+        });
+
+    let watcher_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    RestartStorm {
+                        group, restarts, ..
+                    } => {
+                        tx.send((group, restarts)).unwrap();
+                    }
+                });
+            }
+        }
+    });
+
+    let topology = Topology::empty();
+    topology.set_restart_budget(RestartBudget::per_minute(3).manual_start_on_storm());
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let crashers = topology.local("crashers");
+    let watchers = topology.local("watchers");
+    let api = topology.local("api");
+
+    crashers.route_to(&watchers, |envelope: &Envelope| {
+        msg!(match envelope {
+            RestartStorm => true,
+            _ => false,
+        })
+    });
+    api.route_all_to(&crashers);
+
+    let crashers_addr = crashers.addr();
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    crashers.mount(crasher_blueprint);
+    watchers.mount(watcher_blueprint);
+    let handle = api.handle();
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    let storm = tokio::time::timeout(Duration::from_secs(300), rx.receive())
+        .await
+        .expect("no restart storm")
+        .unwrap();
+
+    assert_eq!(storm, ("crashers".into(), 4));
+
+    // The initial start and three restarts within the budget.
+    tokio::time::sleep(Duration::from_secs(300)).await;
+    assert_eq!(STARTED.load(Ordering::SeqCst), 4);
+
+    // Actors are spawned again once the group is started manually.
+    handle
+        .request_to(crashers_addr, StartGroup::default())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(STARTED.load(Ordering::SeqCst) > 4);
+}
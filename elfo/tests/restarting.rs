@@ -1,4 +1,5 @@
 #![cfg(feature = "test-util")]
+#![allow(clippy::never_loop)] // false positive
 
 use std::{
     panic::AssertUnwindSafe,
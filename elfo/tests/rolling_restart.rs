@@ -0,0 +1,63 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    messages::RollingRestart,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Spawn(u32);
+
+#[message(ret = u32)]
+struct GetIncarnation(u32);
+
+#[tokio::test(start_paused = true)]
+async fn restarts_in_waves_with_snapshots() {
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Spawn(key) => Outcome::Unicast(*key),
+                GetIncarnation(key) => Outcome::Unicast(*key),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let incarnation = ctx.take_snapshot::<u32>().unwrap_or(0) + 1;
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Spawn => {}
+                    (GetIncarnation, token) => ctx.respond(token, incarnation),
+                });
+            }
+
+            ctx.set_snapshot(incarnation);
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    for key in 0..5 {
+        proxy.send(Spawn(key)).await;
+    }
+    proxy.sync().await;
+
+    let start = tokio::time::Instant::now();
+    proxy
+        .request(RollingRestart::new(
+            "subject".into(),
+            2,
+            Duration::from_secs(1),
+        ))
+        .await;
+
+    // Three waves with delays between them.
+    assert!(start.elapsed() >= Duration::from_secs(2));
+
+    for key in 0..5 {
+        assert_eq!(proxy.request(GetIncarnation(key)).await, 2);
+    }
+}
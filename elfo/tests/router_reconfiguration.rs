@@ -0,0 +1,111 @@
+#![cfg(feature = "test-util")]
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    messages::UpdateConfig,
+    prelude::*,
+    routers::{ConfigRouter, MapRouter, Outcome},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+struct Config {
+    partitions: u32,
+}
+
+#[message]
+struct Item(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Handled {
+    item: u32,
+    partition: u32,
+}
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new()
+        .config::<Config>()
+        .router(ConfigRouter::new(|config: &Config| {
+            let partitions = config.partitions;
+            MapRouter::new(move |envelope| {
+                msg!(match envelope {
+                    Item(item) => Outcome::Unicast(item % partitions),
+                    UpdateConfig => Outcome::Multicast((0..partitions).collect()),
+                    _ => Outcome::Default,
+                })
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let partition = *ctx.key();
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Item(item) => ctx.send(Handled { item, partition }).await.unwrap(),
+                });
+            }
+        })
+}
+
+fn config(partitions: u32) -> AnyConfig {
+    AnyConfig::deserialize(toml! {
+        partitions = partitions
+    })
+    .unwrap()
+}
+
+async fn recv_handled(proxy: &mut elfo::test::Proxy, count: usize) -> Vec<(u32, u32)> {
+    let mut handled = Vec::new();
+    for _ in 0..count {
+        msg!(match proxy.recv().await {
+            Handled { item, partition } => handled.push((item, partition)),
+        });
+    }
+    handled.sort_unstable();
+    handled
+}
+
+#[tokio::test]
+async fn it_rebuilds_router() {
+    let mut proxy = elfo::test::proxy(blueprint(), config(2)).await;
+
+    for item in 0..4 {
+        proxy.send(Item(item)).await;
+    }
+    assert_eq!(
+        recv_handled(&mut proxy, 4).await,
+        vec![(0, 0), (1, 1), (2, 0), (3, 1)]
+    );
+
+    // Messages sent concurrently with the update are routed by either table.
+    let sender = proxy.subproxy().await;
+    let sending = async {
+        for item in 0..100 {
+            sender.send(Item(item)).await;
+            tokio::task::yield_now().await;
+        }
+    };
+    let updating = async {
+        tokio::task::yield_now().await;
+        proxy.send(UpdateConfig::new(config(3))).await;
+    };
+    tokio::join!(sending, updating);
+
+    let handled = recv_handled(&mut proxy, 100).await;
+    for (expected, (item, partition)) in (0..100).zip(handled) {
+        assert_eq!(item, expected);
+        assert!(partition == item % 2 || partition == item % 3);
+    }
+    proxy.sync().await;
+    assert!(proxy.try_recv().await.is_none());
+
+    for item in 0..3 {
+        proxy.send(Item(item)).await;
+    }
+    assert_eq!(
+        recv_handled(&mut proxy, 3).await,
+        vec![(0, 0), (1, 1), (2, 2)]
+    );
+}
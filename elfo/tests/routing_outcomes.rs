@@ -0,0 +1,86 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{
+    config::AnyConfig,
+    messages::DeadLetter,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Notify(Vec<u32>);
+
+#[message]
+struct Forbidden(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Handled(u32);
+
+fn blueprint(forward_dead_letters: bool) -> Blueprint {
+    ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Notify(keys) => Outcome::multicast(keys.iter().copied()),
+                Forbidden => Outcome::DiscardWith("forbidden"),
+                _ => Outcome::Default,
+            })
+        }))
+        .forward_dead_letters(forward_dead_letters)
+        .exec(|mut ctx| async move {
+            let key = *ctx.key();
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Notify => ctx.send(Handled(key)).await.unwrap(),
+                });
+            }
+        })
+}
+
+#[tokio::test]
+async fn multicast_skips_duplicates() {
+    let mut proxy = elfo::test::proxy(blueprint(false), AnyConfig::default()).await;
+
+    proxy.send(Notify(vec![3, 1, 3, 2, 1])).await;
+
+    let mut handled = Vec::new();
+    for _ in 0..3 {
+        msg!(match proxy.recv().await {
+            Handled(key) => handled.push(key),
+        });
+    }
+    handled.sort_unstable();
+    assert_eq!(handled, vec![1, 2, 3]);
+
+    proxy.sync().await;
+    assert!(proxy.try_recv().await.is_none());
+
+    // No keys, no actors.
+    assert!(proxy.try_send(Notify(vec![])).is_err());
+}
+
+#[tokio::test]
+async fn discard_with_reason() {
+    let mut proxy = elfo::test::proxy(blueprint(false), AnyConfig::default()).await;
+
+    assert!(proxy.try_send(Forbidden(1)).is_err());
+    proxy.sync().await;
+    assert!(proxy.try_recv().await.is_none());
+}
+
+#[tokio::test]
+async fn dead_letters() {
+    let mut proxy = elfo::test::proxy(blueprint(true), AnyConfig::default()).await;
+
+    assert!(proxy.try_send(Forbidden(1)).is_err());
+
+    msg!(match proxy.recv().await {
+        DeadLetter {
+            reason, message, ..
+        } => {
+            assert_eq!(reason, "forbidden");
+            assert_eq!(message.downcast_ref::<Forbidden>().unwrap().0, 1);
+        }
+    });
+}
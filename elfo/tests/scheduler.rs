@@ -0,0 +1,58 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use toml::toml;
+
+use elfo::{_priv::do_start, prelude::*, Topology};
+
+#[message]
+struct Cleanup;
+
+#[tokio::test]
+async fn runs_by_schedule() {
+    let (tx, rx) = futures_intrusive::channel::shared::unbuffered_channel();
+
+    let storage_blueprint = ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Cleanup => tx.send(()).await.unwrap(),
+                });
+            }
+        }
+    });
+
+    let config = toml! {
+        [system.schedulers.jobs.cleanup]
+        schedule = "* * * * * *"
+        targets = ["storages"]
+
+        [system.schedulers.jobs.unregistered]
+        schedule = "* * * * * *"
+        targets = ["storages"]
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let schedulers = topology.local("system.schedulers");
+    let storages = topology.local("storages");
+
+    let jobs = elfo::batteries::scheduler::Jobs::default().add("cleanup", || Cleanup);
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    schedulers.mount(elfo::batteries::scheduler::new(&topology, jobs));
+    storages.mount(storage_blueprint);
+
+    do_start(topology, false, |_, _| futures::future::ready(()))
+        .await
+        .expect("cannot start");
+
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(3), rx.receive())
+            .await
+            .expect("the job hasn't run")
+            .unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, errors::RequestError, prelude::*, Addr, Local};
+
+#[message]
+struct Payload(u32);
+
+#[message(ret = Vec<(String, u32)>)]
+struct Probe(Local<Addr>);
+
+#[message(ret = ())]
+struct Ping;
+
+#[message(ret = String)]
+struct RequestProbe(Local<Addr>);
+
+#[tokio::test]
+async fn it_preserves_message_and_reason() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Probe(addr), token) => {
+                    let addr = *addr;
+                    let mut results = Vec::new();
+
+                    let err = ctx.send_to(addr, Payload(1)).await.unwrap_err();
+                    results.push((err.to_string(), err.into_inner().0));
+                    let err = ctx.try_send_to(addr, Payload(2)).unwrap_err();
+                    results.push((err.to_string(), err.into_inner().0));
+
+                    ctx.respond(token, results);
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let results = proxy.request(Probe(Addr::NULL.into())).await;
+    assert_eq!(results, [("no route".into(), 1), ("no route".into(), 2)]);
+
+    let recipient = proxy.subproxy().await;
+    recipient.close();
+
+    let results = proxy.request(Probe(recipient.addr().into())).await;
+    assert_eq!(
+        results,
+        [("mailbox closed".into(), 1), ("mailbox closed".into(), 2),]
+    );
+}
+
+#[tokio::test]
+async fn it_reports_reason_of_unsent_requests() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (RequestProbe(addr), token) => {
+                    let err = ctx.request_to(*addr, Ping).resolve().await.unwrap_err();
+                    let reason = match &err {
+                        RequestError::NotSent(reason) => reason.to_string(),
+                        _ => panic!("unexpected error: {err:?}"),
+                    };
+                    ctx.respond(token, format!("{err}; {reason}"));
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let result = proxy.request(RequestProbe(Addr::NULL.into())).await;
+    assert_eq!(result, "request not sent: no route; no route");
+
+    let recipient = proxy.subproxy().await;
+    recipient.close();
+
+    let result = proxy.request(RequestProbe(recipient.addr().into())).await;
+    assert_eq!(result, "request not sent: mailbox closed; mailbox closed");
+}
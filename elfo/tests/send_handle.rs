@@ -0,0 +1,50 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, Addr, Local, SendHandle};
+
+#[message]
+struct Target(Local<Addr>);
+
+#[message(ret = bool)]
+struct Fire;
+
+#[message]
+#[derive(PartialEq)]
+struct Fired(u32);
+
+#[tokio::test]
+async fn it_sends_until_recipient_is_closed() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let mut handle: Option<SendHandle> = None;
+        let mut no = 0;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Target(addr) => handle = Some(ctx.send_handle(*addr)),
+                (Fire, token) => {
+                    let handle = handle.as_mut().unwrap();
+                    no += 1;
+                    let is_ok = if no % 2 == 0 {
+                        handle.send(&ctx, Fired(no)).await.is_ok()
+                    } else {
+                        handle.try_send(&ctx, Fired(no)).is_ok()
+                    };
+                    ctx.respond(token, is_ok);
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let mut recipient = proxy.subproxy().await;
+    proxy.send(Target(Local::from(recipient.addr()))).await;
+
+    for expected in 1..=4 {
+        assert!(proxy.request(Fire).await);
+        assert_msg_eq!(recipient.recv().await, Fired(expected));
+    }
+
+    recipient.close();
+    assert!(!proxy.request(Fire).await);
+    assert!(!proxy.request(Fire).await);
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, prelude::*, Addr, Local};
+
+#[message]
+struct Payload(u32);
+
+#[message(ret = (String, u32))]
+struct Probe(Local<Addr>);
+
+#[tokio::test]
+async fn it_sheds_load_on_full_mailbox() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Probe(addr), token) => {
+                    let addr = *addr;
+                    let timeout = Duration::from_millis(10);
+
+                    ctx.send_to_timeout(addr, Payload(0), timeout)
+                        .await
+                        .unwrap();
+
+                    // Fill the mailbox, the recipient doesn't receive anything.
+                    while ctx.try_send_to(addr, Payload(0)).is_ok() {}
+
+                    let err = ctx
+                        .send_to_timeout(addr, Payload(42), timeout)
+                        .await
+                        .unwrap_err();
+
+                    assert!(err.is_full());
+                    ctx.respond(token, (err.to_string(), err.into_inner().0));
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let recipient = proxy.subproxy().await;
+
+    let result = proxy.request(Probe(recipient.addr().into())).await;
+    assert_eq!(result, ("mailbox full".into(), 42));
+}
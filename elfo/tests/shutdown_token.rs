@@ -0,0 +1,66 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, messages::Terminate, prelude::*, TerminationPolicy};
+
+#[message]
+struct Work;
+
+#[message]
+struct Interrupted;
+
+#[message]
+struct Stopped;
+
+#[tokio::test(start_paused = true)]
+async fn it_interrupts_long_running_handlers() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let token = ctx.shutdown_token();
+        assert!(!token.is_triggered());
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Work => {
+                    while !token.is_triggered() {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                    ctx.send(Interrupted).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    proxy.send(Work).await;
+    proxy.sync().await;
+    proxy.send(Terminate::default()).await;
+    assert_msg!(proxy.recv().await, Interrupted);
+    proxy.finished().await;
+}
+
+#[tokio::test]
+async fn it_notifies_attached_tasks() {
+    // The mailbox isn't closed, so the task's output is received.
+    let group = ActorGroup::new().termination_policy(TerminationPolicy::manually());
+    let blueprint = group.exec(|mut ctx| async move {
+        let token = ctx.shutdown_token();
+        ctx.attach_task(async move {
+            token.triggered().await;
+            assert!(token.is_triggered());
+            Stopped
+        });
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                msg @ Stopped => ctx.send(msg).await.unwrap(),
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    proxy.send(Terminate::default()).await;
+    assert_msg!(proxy.recv().await, Stopped);
+}
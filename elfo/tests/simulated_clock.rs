@@ -0,0 +1,123 @@
+#![cfg(feature = "test-util")]
+
+use std::time::{Duration, SystemTime};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    scope,
+    time::{Clock, Delay, Interval},
+};
+
+#[message(ret = ())]
+struct Start;
+
+#[message]
+struct Tick;
+
+#[message]
+#[derive(PartialEq)]
+struct Ticked(SystemTime);
+
+#[message]
+struct Quote;
+
+#[message(ret = ())]
+struct SendQuotes;
+
+#[message]
+#[derive(PartialEq)]
+struct Quoted(Duration);
+
+fn start() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+}
+
+#[tokio::test]
+async fn delay() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Start, token) => {
+                    ctx.attach(Delay::new(Duration::from_secs(10), Tick));
+                    ctx.respond(token, ());
+                }
+                Tick => ctx.send(Ticked(scope::clock().now())).await.unwrap(),
+            });
+        }
+    });
+
+    let clock = Clock::simulated(start());
+    let proxy = elfo::test::proxy_with_clock(blueprint, AnyConfig::default(), clock.clone());
+    let mut proxy = proxy.await;
+    proxy.request(Start).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    clock.advance(Duration::from_secs(9));
+    assert!(proxy.try_recv().await.is_none());
+
+    clock.advance(Duration::from_secs(1));
+    let expected = start() + Duration::from_secs(10);
+    assert_msg_eq!(proxy.recv().await, Ticked(expected));
+}
+
+#[tokio::test]
+async fn interval() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let interval = ctx.attach(Interval::new(Tick));
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Start, token) => {
+                    interval.start(Duration::from_secs(5));
+                    ctx.respond(token, ());
+                }
+                Tick => ctx.send(Ticked(scope::clock().now())).await.unwrap(),
+            });
+        }
+    });
+
+    let clock = Clock::simulated(start());
+    let proxy = elfo::test::proxy_with_clock(blueprint, AnyConfig::default(), clock.clone());
+    let mut proxy = proxy.await;
+    proxy.request(Start).await;
+
+    for i in 1..=3 {
+        clock.advance(Duration::from_secs(4));
+        assert!(proxy.try_recv().await.is_none());
+
+        clock.advance(Duration::from_secs(1));
+        let expected = start() + Duration::from_secs(5 * i);
+        assert_msg_eq!(proxy.recv().await, Ticked(expected));
+    }
+}
+
+#[tokio::test]
+async fn ttl_and_waiting_time() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            let queued_for = envelope.queued_for();
+
+            msg!(match envelope {
+                (SendQuotes, token) => {
+                    let addr = ctx.addr();
+                    let ttl = Duration::from_millis(50);
+                    ctx.send_to_with_ttl(addr, Quote, ttl).await.unwrap();
+                    ctx.send_to(addr, Quote).await.unwrap();
+                    scope::clock().advance(Duration::from_millis(100));
+                    ctx.respond(token, ());
+                }
+                Quote => ctx.send(Quoted(queued_for)).await.unwrap(),
+            });
+        }
+    });
+
+    let clock = Clock::simulated(start());
+    let proxy = elfo::test::proxy_with_clock(blueprint, AnyConfig::default(), clock);
+    let mut proxy = proxy.await;
+    proxy.request(SendQuotes).await;
+
+    // The first quote is expired, the second one has waited exactly 100ms.
+    assert_msg_eq!(proxy.recv().await, Quoted(Duration::from_millis(100)));
+    assert!(proxy.try_recv().await.is_none());
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "test-util")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use elfo::{config::AnyConfig, messages::StartGroup, prelude::*, StartPolicy};
+
+#[message(ret = u32)]
+struct Hello;
+
+fn blueprint(policy: StartPolicy, spawned: &'static AtomicUsize) -> Blueprint {
+    ActorGroup::new()
+        .start_policy(policy)
+        .exec(move |mut ctx| async move {
+            spawned.fetch_add(1, Ordering::SeqCst);
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Hello, token) => ctx.respond(token, 42),
+                    _ => {}
+                });
+            }
+        })
+}
+
+#[tokio::test]
+async fn eager() {
+    static SPAWNED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut proxy = elfo::test::proxy(
+        blueprint(StartPolicy::Eager, &SPAWNED),
+        AnyConfig::default(),
+    )
+    .await;
+    proxy.sync().await;
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn on_first_message() {
+    static SPAWNED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut proxy = elfo::test::proxy(
+        blueprint(StartPolicy::OnFirstMessage, &SPAWNED),
+        AnyConfig::default(),
+    )
+    .await;
+    proxy.sync().await;
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 0);
+
+    assert_eq!(proxy.request(Hello).await, 42);
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn manual() {
+    static SPAWNED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut proxy = elfo::test::proxy(
+        blueprint(StartPolicy::Manual, &SPAWNED),
+        AnyConfig::default(),
+    )
+    .await;
+    proxy.sync().await;
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 0);
+
+    // Messages are discarded until the group is started.
+    assert!(proxy.try_send(Hello).is_err());
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 0);
+
+    proxy.request(StartGroup::default()).await;
+    proxy.sync().await;
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 1);
+    assert_eq!(proxy.request(Hello).await, 42);
+
+    // Repeated starts do nothing.
+    proxy.request(StartGroup::default()).await;
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 1);
+}
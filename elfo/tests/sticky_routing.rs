@@ -0,0 +1,92 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{Affinity, Outcome, StickyRouter},
+};
+
+#[message]
+struct Open(u64, u32);
+
+#[message]
+struct Action {
+    session: u64,
+}
+
+#[message]
+struct Close {
+    session: u64,
+}
+
+#[message]
+#[derive(PartialEq)]
+struct Handled(u64, u32);
+
+const TTL: Duration = Duration::from_secs(60);
+
+fn blueprint() -> Blueprint {
+    ActorGroup::new()
+        .router(StickyRouter::new(TTL, |envelope| {
+            msg!(match envelope {
+                Open(session, shard) => Affinity::Pin(*session, *shard),
+                Action { session } => Affinity::Follow(*session),
+                Close { session } => Affinity::Unpin(*session),
+                _ => Affinity::Route(Outcome::Default),
+            })
+        }))
+        .exec(|mut ctx| async move {
+            let shard = *ctx.key();
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Open(session, _) | Action { session } | Close { session } => {
+                        ctx.send(Handled(session, shard)).await.unwrap();
+                    }
+                });
+            }
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_pins_sessions() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    // Unknown sessions are discarded.
+    assert!(proxy.try_send(Action { session: 1 }).is_err());
+
+    proxy.send(Open(1, 5)).await;
+    proxy.send(Open(2, 7)).await;
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+    assert_msg_eq!(proxy.recv().await, Handled(2, 7));
+
+    proxy.send(Action { session: 2 }).await;
+    proxy.send(Action { session: 1 }).await;
+    assert_msg_eq!(proxy.recv().await, Handled(2, 7));
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+
+    proxy.send(Close { session: 1 }).await;
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+    assert!(proxy.try_send(Action { session: 1 }).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_expires_sessions() {
+    let mut proxy = elfo::test::proxy(blueprint(), AnyConfig::default()).await;
+
+    proxy.send(Open(1, 5)).await;
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+
+    // Messages prolong the session.
+    tokio::time::sleep(TTL / 2).await;
+    proxy.send(Action { session: 1 }).await;
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+    tokio::time::sleep(TTL * 3 / 4).await;
+    proxy.send(Action { session: 1 }).await;
+    assert_msg_eq!(proxy.recv().await, Handled(1, 5));
+
+    tokio::time::sleep(TTL).await;
+    assert!(proxy.try_send(Action { session: 1 }).is_err());
+}
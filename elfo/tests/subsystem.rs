@@ -0,0 +1,101 @@
+#![cfg(feature = "test-util")]
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{
+    _priv::do_start,
+    prelude::*,
+    topology::{Local, Subsystem},
+    Topology,
+};
+
+#[message(ret = u32)]
+struct GetFee;
+
+#[derive(Debug, Deserialize)]
+struct LedgerConfig {
+    fee: u32,
+}
+
+mod billing {
+    use super::*;
+
+    fn api_blueprint() -> Blueprint {
+        ActorGroup::new().exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (GetFee, token) => {
+                        let fee = ctx.request(GetFee).resolve().await.unwrap();
+                        ctx.respond(token, fee);
+                    }
+                });
+            }
+        })
+    }
+
+    fn ledger_blueprint() -> Blueprint {
+        ActorGroup::new().config::<LedgerConfig>().exec(
+            |mut ctx: Context<LedgerConfig>| async move {
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        (GetFee, token) => ctx.respond(token, ctx.config().fee),
+                    });
+                }
+            },
+        )
+    }
+
+    pub(crate) fn subsystem<'a>(gateways: &'a Local<'_>) -> Subsystem<'a> {
+        Subsystem::new(move |billing| {
+            let api = billing.local("api");
+            let ledger = billing.local("ledger");
+
+            gateways.route_all_to(&api);
+            api.route_all_to(&ledger);
+
+            api.mount(api_blueprint());
+            ledger.mount(ledger_blueprint());
+        })
+    }
+}
+
+#[tokio::test]
+async fn it_mounts_subsystems() {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let gateways = topology.local("gateways");
+
+    topology.mount("billing", billing::subsystem(&gateways));
+
+    let config = toml! {
+        [billing.ledger]
+        fee = 42
+    };
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    let handle = gateways.handle();
+
+    let names = topology.locals().map(|g| g.name).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "system.configurers",
+            "gateways",
+            "billing.api",
+            "billing.ledger"
+        ]
+    );
+
+    do_start(topology, false, |_, _| async move {
+        assert_eq!(handle.request(GetFee).await.unwrap(), 42);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[test]
+#[should_panic(expected = "invalid subsystem prefix `billing.`")]
+fn it_rejects_invalid_prefixes() {
+    let topology = Topology::empty();
+    topology.mount("billing.", Subsystem::new(|_| {}));
+}
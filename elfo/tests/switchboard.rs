@@ -0,0 +1,106 @@
+#![cfg(feature = "test-util")]
+
+use serde::{Deserialize, Serialize};
+use toml::toml;
+
+use elfo::{_priv::do_start, config::AnyConfig, messages::UpdateConfig, prelude::*, Topology};
+
+#[message(ret = String)]
+struct Place {
+    venue: String,
+    legs: Vec<Leg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Leg {
+    qty: u32,
+}
+
+#[message(ret = String)]
+struct Cancel;
+
+fn place(venue: &str, qty: u32) -> Place {
+    Place {
+        venue: venue.into(),
+        legs: vec![Leg { qty }],
+    }
+}
+
+// Responds with the name of the group.
+fn gateway(name: &'static str) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Place, token) => ctx.respond(token, name.into()),
+                (Cancel, token) => ctx.respond(token, name.into()),
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_routes_by_rules() {
+    let config = toml! {
+        [[system.switchboards.rules]]
+        message = "Place"
+        fields = { venue = "nyse" }
+        to = "nyse"
+
+        [[system.switchboards.rules]]
+        message = "Place"
+        to = "unknown"
+
+        [[system.switchboards.rules]]
+        message = "Place"
+        protocol = "elfo"
+        to = "others"
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let switchboards = topology.local("system.switchboards");
+    let nyse = topology.local("nyse");
+    let others = topology.local("others");
+    let api = topology.local("api");
+
+    api.route_all_to(&switchboards);
+
+    let switchboards_addr = switchboards.addr();
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    switchboards.mount(elfo::batteries::switchboard::new(&topology));
+    nyse.mount(gateway("nyse"));
+    others.mount(gateway("others"));
+    let handle = api.handle();
+
+    do_start(topology, false, |_, _| async move {
+        assert_eq!(handle.request(place("nyse", 1)).await.unwrap(), "nyse");
+        assert_eq!(handle.request(place("lse", 1)).await.unwrap(), "others");
+
+        // Unmatched messages are discarded.
+        assert!(handle.request(Cancel).await.is_err());
+
+        // Rules are reloaded on config updates.
+        let config = toml! {
+            [[rules]]
+            fields = { "legs.0.qty" = 1 }
+            to = "nyse"
+
+            [[rules]]
+            message = "Cancel"
+            to = "others"
+        };
+        let config = AnyConfig::deserialize(config).unwrap();
+        let update = UpdateConfig::new(config);
+        handle
+            .request_to(switchboards_addr, update)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(handle.request(place("lse", 1)).await.unwrap(), "nyse");
+        assert!(handle.request(place("nyse", 2)).await.is_err());
+        assert_eq!(handle.request(Cancel).await.unwrap(), "others");
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{_priv::do_start, prelude::*, tracing::TraceId, Topology};
+use elfo_core::config::AnyConfig;
+
+#[message(ret = (u32, TraceId))]
+struct Double(u32);
+
+#[message]
+struct Ping;
+
+#[message(ret = bool)]
+struct WasPinged;
+
+fn service() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        let mut was_pinged = false;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Double(n), token) => {
+                    ctx.respond(token, (n * 2, elfo::scope::trace_id()));
+                }
+                Ping => was_pinged = true,
+                (WasPinged, token) => ctx.respond(token, was_pinged),
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_sends_from_non_actor_code() {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let service_group = topology.local("service");
+
+    api.route_all_to(&service_group);
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    service_group.mount(service());
+    let handle = api.handle();
+
+    do_start(topology, false, |_, _| async move {
+        // Requests are routed by routes of the group.
+        let (doubled, _) = handle.request(Double(21)).await.unwrap();
+        assert_eq!(doubled, 42);
+
+        // The trace id can be provided.
+        let trace_id = TraceId::try_from(42).unwrap();
+        let traced = handle.with_trace_id(trace_id);
+        let (_, actual) = traced.request(Double(1)).await.unwrap();
+        assert_eq!(actual, trace_id);
+
+        // Sending works from sync code too.
+        let cloned = handle.clone();
+        std::thread::spawn(move || cloned.try_send(Ping).unwrap())
+            .join()
+            .unwrap();
+        assert!(handle.request(WasPinged).await.unwrap());
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{_priv::do_start, prelude::*, SystemHandle, Topology};
+use elfo_core::config::AnyConfig;
+use tokio::sync::mpsc;
+
+mod orders {
+    use elfo::prelude::*;
+
+    elfo::set_protocol!("orders");
+    elfo::allow_tapping!();
+
+    #[message]
+    pub(crate) struct PlaceOrder(pub(crate) u32);
+
+    #[message(ret = ())]
+    pub(crate) struct CancelOrder(pub(crate) u32);
+}
+
+mod secrets {
+    use elfo::prelude::*;
+
+    elfo::set_protocol!("secrets");
+
+    #[message]
+    pub(crate) struct Password;
+}
+
+use self::{
+    orders::{CancelOrder, PlaceOrder},
+    secrets::Password,
+};
+
+fn topology(tapped: &[&str], tx: mpsc::UnboundedSender<String>) -> (Topology, SystemHandle) {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let api = topology.local("api");
+    let service = topology.local("service");
+    let audit = topology.local("audit");
+
+    api.route_all_to(&service);
+    api.tap_to(&audit, tapped.iter().copied());
+
+    configurers.mount(elfo_configurer::fixture(&topology, AnyConfig::default()));
+    service.mount(ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (CancelOrder(_), token) => ctx.respond(token, ()),
+                _ => {}
+            });
+        }
+    }));
+    audit.mount(ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    PlaceOrder(no) => tx.send(format!("place {no}")).unwrap(),
+                    (CancelOrder(no), token) => {
+                        drop(token);
+                        tx.send(format!("cancel {no}")).unwrap();
+                    }
+                    Password => tx.send("password".into()).unwrap(),
+                    _ => {}
+                });
+            }
+        }
+    }));
+    let handle = api.handle();
+
+    (topology, handle)
+}
+
+#[tokio::test]
+async fn it_copies_tapped_protocols() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (topology, handle) = topology(&["orders"], tx);
+
+    do_start(topology, false, |_, _| async move {
+        handle.send(Password).await.unwrap();
+        handle.request(CancelOrder(1)).await.unwrap();
+        handle.send(PlaceOrder(2)).await.unwrap();
+
+        // Only regular messages of tapped protocols are copied.
+        assert_eq!(rx.recv().await.unwrap(), "place 2");
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn it_requires_opt_in() {
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let (topology, _handle) = topology(&["orders", "secrets"], tx);
+
+    let err = do_start(topology, false, |_, _| async {})
+        .await
+        .unwrap_err();
+    let err = err.to_string();
+    assert!(err.contains("`secrets` protocol is tapped"), "{err}");
+    assert!(!err.contains("`orders`"), "{err}");
+}
@@ -1,4 +1,5 @@
 #![cfg(feature = "test-util")]
+#![allow(clippy::never_loop)] // false positive
 
 use elfo::{messages::Terminate, prelude::*, TerminationPolicy};
 
@@ -0,0 +1,50 @@
+#![cfg(feature = "test-util")]
+
+use std::time::SystemTime;
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::TraceId};
+
+#[message]
+struct Bridge(TraceId);
+
+#[message]
+struct Event;
+
+#[message]
+struct Restored(TraceId);
+
+#[tokio::test]
+async fn with_trace_id() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Bridge(trace_id) => {
+                    ctx.with_trace_id(trace_id, async {
+                        assert_eq!(scope::trace_id(), trace_id);
+                        ctx.send(Event).await.unwrap();
+                    })
+                    .await;
+
+                    ctx.send(Restored(scope::trace_id())).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let upstream = TraceId::from_parts(SystemTime::now(), None, 1).unwrap();
+    proxy.send(Bridge(upstream)).await;
+
+    let envelope = proxy.recv().await;
+    assert_eq!(envelope.trace_id(), upstream);
+    assert_msg!(envelope, Event);
+
+    let envelope = proxy.recv().await;
+    let original = envelope.trace_id();
+    assert_ne!(original, upstream);
+    msg!(match envelope {
+        Restored(trace_id) => assert_eq!(trace_id, original),
+        _ => panic!("unexpected message"),
+    });
+}
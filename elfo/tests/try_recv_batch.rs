@@ -0,0 +1,48 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*};
+
+#[message]
+struct Num(u32);
+
+#[message(ret = (u32, Vec<u32>))]
+struct GetStats;
+
+#[tokio::test]
+async fn it_receives_available_batches() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let mut sum = 0;
+        let mut batches = Vec::new();
+
+        loop {
+            let batch = match ctx.try_recv_batch(2).await {
+                Ok(batch) => batch,
+                Err(err) if err.is_empty() => match ctx.recv().await {
+                    Some(envelope) => vec![envelope],
+                    None => break,
+                },
+                Err(_) => break,
+            };
+
+            assert!(!batch.is_empty() && batch.len() <= 2);
+            batches.push(batch.len() as u32);
+
+            for envelope in batch {
+                msg!(match envelope {
+                    Num(n) => sum += n,
+                    (GetStats, token) => ctx.respond(token, (sum, batches.clone())),
+                });
+            }
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    for n in 1..=5 {
+        proxy.send(Num(n)).await;
+    }
+
+    let (sum, batches) = proxy.request(GetStats).await;
+    assert_eq!(sum, 15);
+    assert!(batches.contains(&2));
+}
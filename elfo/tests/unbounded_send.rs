@@ -0,0 +1,105 @@
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, Addr, Local};
+
+#[message]
+#[derive(PartialEq)]
+struct Payload(u32);
+
+#[message(ret = usize)]
+struct Probe(Local<Addr>);
+
+#[tokio::test]
+async fn it_delivers_to_full_mailbox() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Probe(addr), token) => {
+                    let addr = *addr;
+
+                    let mut count = 0;
+                    while ctx.try_send_to(addr, Payload(0)).is_ok() {
+                        count += 1;
+                    }
+
+                    // Queued messages are delivered in order.
+                    ctx.unbounded_send_to(addr, Payload(42)).unwrap();
+                    ctx.unbounded_send_to(addr, Payload(43)).unwrap();
+                    ctx.unbounded_send_to(addr, Payload(44)).unwrap();
+                    ctx.respond(token, count);
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let mut recipient = proxy.subproxy().await;
+
+    let count = proxy.request(Probe(recipient.addr().into())).await;
+
+    for _ in 0..count {
+        assert_msg_eq!(recipient.recv().await, Payload(0));
+    }
+    assert_msg_eq!(recipient.recv().await, Payload(42));
+    assert_msg_eq!(recipient.recv().await, Payload(43));
+    assert_msg_eq!(recipient.recv().await, Payload(44));
+}
+
+#[tokio::test]
+async fn it_loses_messages_outside_runtime() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Probe(addr), token) => {
+                    let addr = *addr;
+
+                    let mut count = 0;
+                    while ctx.try_send_to(addr, Payload(0)).is_ok() {
+                        count += 1;
+                    }
+
+                    // Must not panic, the message is lost instead.
+                    let pruned = ctx.pruned();
+                    let scope = elfo::scope::expose();
+                    std::thread::spawn(move || {
+                        scope.sync_within(|| pruned.unbounded_send_to(addr, Payload(42)))
+                    })
+                    .join()
+                    .unwrap()
+                    .unwrap();
+
+                    ctx.unbounded_send_to(addr, Payload(43)).unwrap();
+                    ctx.respond(token, count);
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    let mut recipient = proxy.subproxy().await;
+
+    let count = proxy.request(Probe(recipient.addr().into())).await;
+
+    for _ in 0..count {
+        assert_msg_eq!(recipient.recv().await, Payload(0));
+    }
+    assert_msg_eq!(recipient.recv().await, Payload(43));
+}
+
+#[tokio::test]
+async fn it_fails_without_recipients() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Probe(addr), token) => {
+                    let err = ctx.unbounded_send_to(*addr, Payload(1)).unwrap_err();
+                    assert!(err.is_no_route());
+                    ctx.respond(token, 0);
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+    proxy.request(Probe(Addr::NULL.into())).await;
+}
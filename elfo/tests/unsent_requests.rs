@@ -0,0 +1,78 @@
+#![cfg(feature = "test-util")]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use metrics::{GaugeValue, Key, Recorder, Unit};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome, Singleton},
+};
+
+#[message(ret = ())]
+struct Ping;
+
+#[message(ret = Vec<String>)]
+struct Probe;
+
+static IGNORED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+struct IgnoredRequestsRecorder;
+
+impl Recorder for IgnoredRequestsRecorder {
+    fn register_counter(&self, _: &Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn register_gauge(&self, _: &Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn register_histogram(&self, _: &Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn increment_counter(&self, key: &Key, value: u64) {
+        if key.name() == "elfo_ignored_requests_total" {
+            IGNORED_REQUESTS.fetch_add(value, Ordering::SeqCst);
+        }
+    }
+
+    fn update_gauge(&self, _: &Key, _: GaugeValue) {}
+
+    fn record_histogram(&self, _: &Key, _: f64) {}
+}
+
+// Requests returned in send errors are cancelled by requesters, so they are
+// neither ignored nor failed by dropping their response tokens.
+#[tokio::test]
+async fn it_returns_unsent_requests_without_responding() {
+    metrics::set_boxed_recorder(Box::new(IgnoredRequestsRecorder)).unwrap();
+
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Ping => Outcome::Discard,
+                _ => Outcome::Unicast(Singleton),
+            })
+        }))
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Probe, token) => {
+                        let any = ctx.request_to(ctx.group(), Ping).resolve().await;
+                        let all = ctx.request_to(ctx.group(), Ping).all().resolve().await;
+
+                        let results = [any].into_iter().chain(all).map(|res| {
+                            let err = res.unwrap_err();
+                            assert!(err.send_error().is_some_and(|err| err.is_no_route()));
+                            err.to_string()
+                        });
+
+                        ctx.respond(token, results.collect());
+                    }
+                });
+            }
+        });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let results = proxy.request(Probe).await;
+    assert_eq!(results, ["request not sent: no route"; 2]);
+    assert_eq!(IGNORED_REQUESTS.load(Ordering::SeqCst), 0);
+}
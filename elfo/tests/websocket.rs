@@ -0,0 +1,89 @@
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use toml::toml;
+
+use elfo::{_priv::do_start, batteries::websocket::JsonCodec, prelude::*, Topology};
+
+#[message]
+struct Subscribe {
+    topic: String,
+}
+
+#[message]
+struct Update {
+    topic: String,
+}
+
+#[message(ret = String)]
+struct Echo(String);
+
+#[message(protocol = "admin")]
+struct Forbidden;
+
+#[tokio::test]
+async fn it_bridges_clients() {
+    let config = toml! {
+        [system.websockets]
+        address = "127.0.0.1:43918"
+        protocols = ["elfo"]
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let websockets = topology.local("system.websockets");
+    let pushers = topology.local("pushers");
+
+    websockets.route_all_to(&pushers);
+
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+    websockets.mount(elfo::batteries::websocket::new(JsonCodec));
+    pushers.mount(ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            let sender = envelope.sender();
+
+            msg!(match envelope {
+                // Pushes updates directly to the session.
+                Subscribe { topic } => {
+                    let _ = ctx.send_to(sender, Update { topic }).await;
+                }
+                (Echo(text), token) => ctx.respond(token, text),
+                Forbidden => panic!("forbidden message is received"),
+            });
+        }
+    }));
+
+    do_start(topology, false, |_, _| async move {
+        let mut client = loop {
+            match connect_async("ws://127.0.0.1:43918").await {
+                Ok((client, _)) => break client,
+                // The listener can be not started yet.
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        for text in [
+            r#"["admin","Forbidden",null]"#,
+            r#"["elfo","Subscribe",{"topic":"orders"}]"#,
+            r#"["elfo","Echo","hi"]"#,
+        ] {
+            client.send(WsMessage::Text(text.into())).await.unwrap();
+        }
+
+        // The update is queued while the session waits for the response.
+        for expected in [
+            r#"["elfo","Echo::Response","hi"]"#,
+            r#"["elfo","Update",{"topic":"orders"}]"#,
+        ] {
+            let frame = client.next().await.unwrap().unwrap();
+            assert_eq!(frame.into_text().unwrap(), expected);
+        }
+
+        client.close(None).await.unwrap();
+    })
+    .await
+    .expect("cannot start");
+}
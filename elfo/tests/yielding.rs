@@ -0,0 +1,64 @@
+#![cfg(feature = "test-util")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome},
+};
+
+#[message]
+struct Batch(Vec<u32>);
+
+#[message]
+struct Ping;
+
+#[message]
+#[derive(PartialEq)]
+struct Handled(usize);
+
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn it_doesnt_starve_siblings() {
+    let blueprint = ActorGroup::new()
+        .router(MapRouter::new(|envelope| {
+            msg!(match envelope {
+                Batch => Outcome::Unicast(0),
+                Ping => Outcome::Unicast(1),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Batch(items) => {
+                        ctx.for_each_chunked(items, 10, |_| {
+                            HANDLED.fetch_add(1, Ordering::SeqCst);
+                        })
+                        .await;
+                        ctx.send(Handled(HANDLED.load(Ordering::SeqCst)))
+                            .await
+                            .unwrap();
+                    }
+                    // Another actor checks the progress of the batch.
+                    Ping => {
+                        ctx.send(Handled(HANDLED.load(Ordering::SeqCst)))
+                            .await
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    proxy.send(Batch((0..1000).collect())).await;
+    proxy.send(Ping).await;
+
+    msg!(match proxy.recv().await {
+        Handled(progress) => assert!(progress < 1000),
+    });
+    assert_msg_eq!(proxy.recv().await, Handled(1000));
+}